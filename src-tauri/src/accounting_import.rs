@@ -0,0 +1,675 @@
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+
+use calamine::{Data, Range};
+use csv::{ReaderBuilder, Trim};
+use rusqlite::Connection;
+use serde::{Deserialize, Serialize};
+use tauri::State;
+
+use crate::csv_import::{self, CsvImportReport, CsvMapping, CsvRowError, ImportTarget};
+use crate::db::{self, DbPool};
+use crate::error::AppError;
+use crate::sales_import::{self, CustomerIndex, SalesColumnMapping, SalesImportReport};
+
+// Title and company lines printed above the column headers are skipped, up to this many rows
+const HEADER_SEARCH_ROWS: usize = 25;
+
+// Column headers each tool uses for a field, lowercase, in order of preference. Customer
+// fields are the CSV import's; invoice fields are the spreadsheet import's.
+type ColumnAliases = &'static [(&'static str, &'static [&'static str])];
+
+const ZOHO_CUSTOMER_COLUMNS: ColumnAliases = &[
+    (
+        "report_customer",
+        &["company name", "display name", "contact name"],
+    ),
+    ("tally_customer", &["display name", "contact name"]),
+    (
+        "gst_no",
+        &["gst identification number (gstin)", "gstin", "gst number"],
+    ),
+    (
+        "state_code",
+        &["place of contact", "place of supply", "billing state"],
+    ),
+    ("address", &["billing address", "billing street"]),
+    ("city", &["billing city"]),
+    (
+        "pincode",
+        &["billing code", "billing zip code", "billing zip"],
+    ),
+];
+const ZOHO_INVOICE_COLUMNS: ColumnAliases = &[
+    ("invoice_number", &["invoice number"]),
+    ("invoice_date", &["invoice date"]),
+    ("customer", &["customer name", "display name"]),
+    (
+        "customer_gstin",
+        &[
+            "gst identification number (gstin)",
+            "customer gstin",
+            "gstin",
+        ],
+    ),
+    ("place_of_supply", &["place of supply"]),
+    ("taxable_value", &["subtotal", "sub total"]),
+    ("cgst_amount", &["cgst", "cgst amount"]),
+    ("sgst_amount", &["sgst", "sgst amount"]),
+    ("igst_amount", &["igst", "igst amount"]),
+    ("total_amount", &["total"]),
+];
+const BUSY_CUSTOMER_COLUMNS: ColumnAliases = &[
+    ("report_customer", &["name", "account name", "party name"]),
+    ("tally_customer", &["print name", "account name", "name"]),
+    ("gst_no", &["gstin", "gstin/uin", "gstin no", "gst no"]),
+    ("state_code", &["state", "state name"]),
+    ("address", &["address", "address 1", "address1"]),
+    ("city", &["city", "station"]),
+    ("pincode", &["pin code", "pincode", "pin"]),
+];
+const BUSY_INVOICE_COLUMNS: ColumnAliases = &[
+    (
+        "invoice_number",
+        &[
+            "vch/bill no",
+            "vch/bill no.",
+            "vch no",
+            "vch no.",
+            "bill no",
+        ],
+    ),
+    ("invoice_date", &["date", "vch date", "bill date"]),
+    (
+        "customer",
+        &["party name", "particulars", "account", "party"],
+    ),
+    ("customer_gstin", &["gstin", "gstin/uin", "party gstin"]),
+    ("place_of_supply", &["place of supply", "state"]),
+    (
+        "taxable_value",
+        &["taxable amount", "taxable value", "taxable amt"],
+    ),
+    ("cgst_amount", &["cgst", "cgst amount", "central tax"]),
+    ("sgst_amount", &["sgst", "sgst amount", "state tax"]),
+    ("igst_amount", &["igst", "igst amount", "integrated tax"]),
+    (
+        "total_amount",
+        &["bill amount", "invoice value", "net amount", "total"],
+    ),
+];
+const MARG_CUSTOMER_COLUMNS: ColumnAliases = &[
+    ("report_customer", &["party name", "ledger name", "name"]),
+    ("tally_customer", &["party name", "ledger name", "name"]),
+    ("gst_no", &["gstin", "gst no", "gst no.", "gstin no"]),
+    ("state_code", &["state"]),
+    ("address", &["address", "address1", "address 1"]),
+    ("city", &["station", "city"]),
+    ("pincode", &["pin", "pin code", "pincode"]),
+];
+const MARG_INVOICE_COLUMNS: ColumnAliases = &[
+    (
+        "invoice_number",
+        &["bill no", "bill no.", "invoice no", "invoice no."],
+    ),
+    ("invoice_date", &["bill date", "date", "invoice date"]),
+    ("customer", &["party name", "party"]),
+    (
+        "customer_gstin",
+        &["gstin", "gst no", "gst no.", "party gstin"],
+    ),
+    ("place_of_supply", &["place of supply", "state"]),
+    (
+        "taxable_value",
+        &["taxable", "taxable amt", "taxable value", "taxable amount"],
+    ),
+    ("cgst_amount", &["cgst amt", "cgst", "cgst amount"]),
+    ("sgst_amount", &["sgst amt", "sgst", "sgst amount"]),
+    ("igst_amount", &["igst amt", "igst", "igst amount"]),
+    (
+        "total_amount",
+        &["bill amount", "net amount", "net amt", "total"],
+    ),
+];
+
+// Zoho writes one row per invoice line: SubTotal and Total repeat the invoice totals on every
+// line, while the tax columns hold that line's tax and are added up
+const ZOHO_LINE_AMOUNTS: &[&str] = &["cgst_amount", "sgst_amount", "igst_amount"];
+
+// Two-letter state codes used by Zoho in place of supply and contact columns
+const STATE_ABBREVIATIONS: &[(&str, &str)] = &[
+    ("JK", "01"),
+    ("HP", "02"),
+    ("PB", "03"),
+    ("CH", "04"),
+    ("UK", "05"),
+    ("UT", "05"),
+    ("HR", "06"),
+    ("DL", "07"),
+    ("RJ", "08"),
+    ("UP", "09"),
+    ("BR", "10"),
+    ("SK", "11"),
+    ("AR", "12"),
+    ("NL", "13"),
+    ("MN", "14"),
+    ("MZ", "15"),
+    ("TR", "16"),
+    ("ML", "17"),
+    ("AS", "18"),
+    ("WB", "19"),
+    ("JH", "20"),
+    ("OD", "21"),
+    ("OR", "21"),
+    ("CG", "22"),
+    ("CT", "22"),
+    ("MP", "23"),
+    ("GJ", "24"),
+    ("DD", "25"),
+    ("DN", "26"),
+    ("MH", "27"),
+    ("KA", "29"),
+    ("GA", "30"),
+    ("LD", "31"),
+    ("KL", "32"),
+    ("TN", "33"),
+    ("PY", "34"),
+    ("AN", "35"),
+    ("TS", "36"),
+    ("TG", "36"),
+    ("AP", "37"),
+    ("LA", "38"),
+];
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum SourceTool {
+    ZohoBooks,
+    Busy,
+    Marg,
+}
+
+impl SourceTool {
+    fn label(&self) -> &'static str {
+        match self {
+            SourceTool::ZohoBooks => "Zoho Books",
+            SourceTool::Busy => "Busy",
+            SourceTool::Marg => "Marg",
+        }
+    }
+
+    fn columns(&self, target: ImportTarget) -> ColumnAliases {
+        match (self, target) {
+            (SourceTool::ZohoBooks, ImportTarget::Customers) => ZOHO_CUSTOMER_COLUMNS,
+            (SourceTool::ZohoBooks, ImportTarget::Invoices) => ZOHO_INVOICE_COLUMNS,
+            (SourceTool::Busy, ImportTarget::Customers) => BUSY_CUSTOMER_COLUMNS,
+            (SourceTool::Busy, ImportTarget::Invoices) => BUSY_INVOICE_COLUMNS,
+            (SourceTool::Marg, ImportTarget::Customers) => MARG_CUSTOMER_COLUMNS,
+            (SourceTool::Marg, ImportTarget::Invoices) => MARG_INVOICE_COLUMNS,
+        }
+    }
+
+    // Invoice fields summed across the rows of one invoice; empty when each row is an invoice
+    fn line_amounts(&self) -> &'static [&'static str] {
+        match self {
+            SourceTool::ZohoBooks => ZOHO_LINE_AMOUNTS,
+            SourceTool::Busy | SourceTool::Marg => &[],
+        }
+    }
+}
+
+fn required_fields(target: ImportTarget) -> &'static [&'static str] {
+    match target {
+        ImportTarget::Customers => &["report_customer"],
+        ImportTarget::Invoices => &[
+            "invoice_number",
+            "invoice_date",
+            "customer",
+            "taxable_value",
+        ],
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct AccountingImportRequest {
+    pub source: SourceTool,
+    pub target: ImportTarget,
+    // CSV, or a workbook Excel can open; the first sheet is read
+    pub path: String,
+    // Customer exports carry no category, so imported customers are put in this one
+    pub default_category_id: Option<i64>,
+    #[serde(default)]
+    pub dry_run: bool,
+}
+
+// Exactly one of `customers` and `invoices` is set, matching the target
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AccountingImportReport {
+    pub source: SourceTool,
+    pub target: ImportTarget,
+    pub sheet: String,
+    pub header_row: usize,
+    // Column in the file → field it was read into
+    pub columns: BTreeMap<String, String>,
+    pub customers: Option<CsvImportReport>,
+    pub invoices: Option<SalesImportReport>,
+}
+
+struct SourceColumn {
+    field: &'static str,
+    index: usize,
+    header: String,
+}
+
+struct LocatedColumns {
+    // 1-based, as in the spreadsheet
+    header_row: usize,
+    columns: Vec<SourceColumn>,
+}
+
+impl LocatedColumns {
+    fn get(&self, field: &str) -> Option<&SourceColumn> {
+        self.columns.iter().find(|column| column.field == field)
+    }
+
+    fn header(&self, field: &str) -> Option<String> {
+        self.get(field).map(|column| column.header.clone())
+    }
+}
+
+fn normalize_header(header: &str) -> String {
+    header
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .to_lowercase()
+}
+
+// The separator used most on the first line, comma when there is none
+fn delimiter_of(first_line: &str) -> u8 {
+    let mut best = (0, b',');
+    for delimiter in [b',', b';', b'\t', b'|'] {
+        let count = first_line.bytes().filter(|b| *b == delimiter).count();
+        if count > best.0 {
+            best = (count, delimiter);
+        }
+    }
+    best.1
+}
+
+// Reads a CSV export into the same shape as a worksheet, so both go through one pipeline
+fn load_csv(path: &str) -> Result<Range<Data>, AppError> {
+    let bytes = std::fs::read(path).map_err(|e| format!("Failed to open file: {}", e))?;
+    // Older desktop tools do not always write UTF-8
+    let text = String::from_utf8_lossy(&bytes);
+    let text = text.trim_start_matches('\u{feff}');
+    let first_line = text
+        .lines()
+        .find(|line| !line.trim().is_empty())
+        .unwrap_or("");
+    let mut reader = ReaderBuilder::new()
+        .has_headers(false)
+        .flexible(true)
+        .trim(Trim::All)
+        .delimiter(delimiter_of(first_line))
+        .from_reader(text.as_bytes());
+    let records = reader
+        .records()
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("Failed to read CSV file: {}", e))?;
+    let width = records.iter().map(|record| record.len()).max().unwrap_or(0);
+    if records.is_empty() || width == 0 {
+        return Err(AppError::invalid("The file is empty"));
+    }
+
+    let mut range = Range::new((0, 0), (records.len() as u32 - 1, width as u32 - 1));
+    for (row, record) in records.iter().enumerate() {
+        for (column, value) in record.iter().enumerate() {
+            if !value.is_empty() {
+                range.set_value((row as u32, column as u32), Data::String(value.to_string()));
+            }
+        }
+    }
+    Ok(range)
+}
+
+fn load_file(path: &str) -> Result<(String, Range<Data>), String> {
+    let extension = Path::new(path)
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .map(str::to_lowercase);
+    match extension.as_deref() {
+        Some("csv") | Some("txt") => Ok(("CSV".to_string(), load_csv(path)?)),
+        _ => Ok(sales_import::load_sheet(path, None)?),
+    }
+}
+
+// Finds the first row holding every required column for the tool and target
+fn locate_columns(
+    range: &Range<Data>,
+    source: SourceTool,
+    target: ImportTarget,
+) -> Result<LocatedColumns, AppError> {
+    let aliases = source.columns(target);
+    let required = required_fields(target);
+    for (row_number, row) in sales_import::data_rows(range, 0).take(HEADER_SEARCH_ROWS) {
+        let headers: Vec<String> = row.iter().map(sales_import::cell_text).collect();
+        let normalized: Vec<String> = headers.iter().map(|h| normalize_header(h)).collect();
+        let mut columns: Vec<SourceColumn> = Vec::new();
+        for (field, names) in aliases {
+            let found = names.iter().find_map(|name| {
+                (0..normalized.len()).find(|&index| {
+                    normalized[index] == **name
+                        && !columns.iter().any(|column| column.index == index)
+                })
+            });
+            if let Some(index) = found {
+                columns.push(SourceColumn {
+                    field,
+                    index,
+                    header: headers[index].clone(),
+                });
+            }
+        }
+        if required
+            .iter()
+            .all(|field| columns.iter().any(|column| column.field == *field))
+        {
+            return Ok(LocatedColumns {
+                header_row: row_number,
+                columns,
+            });
+        }
+    }
+
+    let expected: Vec<&str> = required
+        .iter()
+        .filter_map(|field| {
+            aliases
+                .iter()
+                .find(|(name, _)| name == field)
+                .and_then(|(_, names)| names.first().copied())
+        })
+        .collect();
+    Err(AppError::invalid(format!(
+        "This does not look like a {} {} export: no header row with {} was found",
+        source.label(),
+        target.as_str(),
+        expected.join(", ")
+    )))
+}
+
+// State names, GST codes and two-letter abbreviations all become the GST code
+fn state_code(conn: &Connection, value: &str) -> Result<Option<String>, String> {
+    let value = value.trim();
+    if let Some((_, code)) = STATE_ABBREVIATIONS
+        .iter()
+        .find(|(abbreviation, _)| abbreviation.eq_ignore_ascii_case(value))
+    {
+        return Ok(Some(code.to_string()));
+    }
+    sales_import::place_of_supply(conn, value)
+}
+
+// Register exports end with totals rows that have no invoice number
+fn is_total_row(row: &[Data]) -> bool {
+    row.iter()
+        .map(sales_import::cell_text)
+        .find(|text| !text.is_empty())
+        .is_some_and(|text| {
+            let text = text.to_lowercase();
+            text.starts_with("total") || text.starts_with("grand total")
+        })
+}
+
+// Reshapes an invoice export for the spreadsheet import: one row per invoice, state codes in
+// place of supply and no totals rows. Rows keep their positions so errors point at the file.
+fn prepare_invoices(
+    conn: &Connection,
+    range: &Range<Data>,
+    located: &LocatedColumns,
+    source: SourceTool,
+) -> Result<Range<Data>, String> {
+    let mut prepared = range.clone();
+    let start_column = range.start().map(|(_, column)| column).unwrap_or(0);
+    let position =
+        |row_number: usize, index: usize| ((row_number - 1) as u32, start_column + index as u32);
+    let number_column = located
+        .get("invoice_number")
+        .map(|column| column.index)
+        .unwrap_or(0);
+    let summed: Vec<usize> = source
+        .line_amounts()
+        .iter()
+        .filter_map(|field| located.get(field).map(|column| column.index))
+        .collect();
+
+    let mut first_rows: HashMap<String, usize> = HashMap::new();
+    for (row_number, row) in sales_import::data_rows(range, located.header_row) {
+        let cell = |index: usize| row.get(index).unwrap_or(&Data::Empty);
+        let clear = |prepared: &mut Range<Data>| {
+            for index in 0..row.len() {
+                prepared.set_value(position(row_number, index), Data::Empty);
+            }
+        };
+        let invoice_number = sales_import::cell_text(cell(number_column));
+        if invoice_number.is_empty() {
+            if is_total_row(row) {
+                clear(&mut prepared);
+            }
+            continue;
+        }
+
+        if let Some(column) = located.get("place_of_supply") {
+            let value = sales_import::cell_text(cell(column.index));
+            if let Some(code) = state_code(conn, &value)? {
+                prepared.set_value(position(row_number, column.index), Data::String(code));
+            }
+        }
+
+        if summed.is_empty() {
+            continue;
+        }
+        let Some(&first_row) = first_rows.get(&invoice_number) else {
+            first_rows.insert(invoice_number, row_number);
+            continue;
+        };
+        // A line that cannot be added up stays where it is and is reported by the import
+        let amounts = summed
+            .iter()
+            .map(|&index| {
+                let first = prepared
+                    .get_value(position(first_row, index))
+                    .unwrap_or(&Data::Empty);
+                let total = sales_import::cell_amount(first, "Amount")?
+                    + sales_import::cell_amount(cell(index), "Amount")?;
+                Ok((index, total))
+            })
+            .collect::<Result<Vec<_>, String>>();
+        if let Ok(amounts) = amounts {
+            for (index, total) in amounts {
+                prepared.set_value(position(first_row, index), Data::Float(total));
+            }
+            clear(&mut prepared);
+        }
+    }
+    Ok(prepared)
+}
+
+fn import_invoices(
+    conn: &mut Connection,
+    company_id: i64,
+    sheet: String,
+    range: &Range<Data>,
+    located: &LocatedColumns,
+    request: &AccountingImportRequest,
+) -> Result<SalesImportReport, String> {
+    let required = |field: &str| located.header(field).unwrap_or_default();
+    let mapping = SalesColumnMapping {
+        sheet: None,
+        header_row: Some(located.header_row),
+        invoice_number: required("invoice_number"),
+        invoice_date: required("invoice_date"),
+        customer: required("customer"),
+        customer_gstin: located.header("customer_gstin"),
+        place_of_supply: located.header("place_of_supply"),
+        taxable_value: required("taxable_value"),
+        cgst_amount: located.header("cgst_amount"),
+        sgst_amount: located.header("sgst_amount"),
+        igst_amount: located.header("igst_amount"),
+        total_amount: located.header("total_amount"),
+    };
+    let prepared = prepare_invoices(conn, range, located, request.source)?;
+    sales_import::import_rows(
+        conn,
+        company_id,
+        sheet,
+        &prepared,
+        &mapping,
+        request.dry_run,
+    )
+}
+
+// Customer rows go through the CSV import's checks, one row at a time in a transaction that a
+// dry run rolls back
+fn import_customers(
+    conn: &mut Connection,
+    company_id: i64,
+    range: &Range<Data>,
+    located: &LocatedColumns,
+    request: &AccountingImportRequest,
+) -> Result<CsvImportReport, String> {
+    let mapping = CsvMapping {
+        target: ImportTarget::Customers,
+        column_map: located
+            .columns
+            .iter()
+            .map(|column| (column.header.clone(), column.field.to_string()))
+            .collect(),
+        date_format: None,
+        decimal_separator: ".".to_string(),
+        delimiter: ",".to_string(),
+    };
+    let customer_index = CustomerIndex::new(&[]);
+
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
+    let mut total_rows = 0;
+    let mut imported_rows = 0;
+    let mut errors = Vec::new();
+    for (row_number, row) in sales_import::data_rows(range, located.header_row) {
+        if row.iter().all(|cell| matches!(cell, Data::Empty)) {
+            continue;
+        }
+        let mut values: BTreeMap<String, String> = located
+            .columns
+            .iter()
+            .map(|column| {
+                let value = sales_import::cell_text(row.get(column.index).unwrap_or(&Data::Empty));
+                (column.field.to_string(), value)
+            })
+            .collect();
+        // Tools keep one name per party, so it serves as both names when only one is present
+        let name = |values: &BTreeMap<String, String>, field: &str| {
+            values.get(field).cloned().unwrap_or_default()
+        };
+        let (report_name, tally_name) = (
+            name(&values, "report_customer"),
+            name(&values, "tally_customer"),
+        );
+        if report_name.is_empty() {
+            values.insert("report_customer".to_string(), tally_name);
+        } else if tally_name.is_empty() {
+            values.insert("tally_customer".to_string(), report_name);
+        }
+        if let Some(state) = values.get_mut("state_code") {
+            if let Some(code) = state_code(&tx, state)? {
+                *state = code;
+            }
+        }
+
+        total_rows += 1;
+        let outcome = csv_import::import_row(
+            &tx,
+            company_id,
+            Ok(values),
+            &mapping,
+            request.default_category_id,
+            &customer_index,
+        );
+        match outcome {
+            Ok(()) => imported_rows += 1,
+            Err(row_errors) => errors.push(CsvRowError {
+                row_number,
+                errors: row_errors,
+            }),
+        }
+    }
+
+    if request.dry_run {
+        tx.rollback().map_err(|e| e.to_string())?;
+    } else {
+        tx.commit().map_err(|e| e.to_string())?;
+    }
+    Ok(CsvImportReport {
+        target: ImportTarget::Customers,
+        total_rows,
+        imported_rows,
+        error_rows: errors.len(),
+        dry_run: request.dry_run,
+        errors,
+    })
+}
+
+// Imports a customer or invoice export from Zoho Books, Busy or Marg. Columns are recognised by
+// the headers each tool writes, and rows are then checked and reported exactly as the CSV and
+// spreadsheet imports do.
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn import_accounting_export(
+    pool: State<'_, DbPool>,
+    company_id: i64,
+    request: AccountingImportRequest,
+) -> Result<AccountingImportReport, AppError> {
+    let path = request.path.trim();
+    if !Path::new(path).is_file() {
+        return Err(AppError::validation(
+            "path",
+            format!("{} was not found", path),
+        ));
+    }
+    let (sheet, range) = load_file(path)?;
+    let located = locate_columns(&range, request.source, request.target)?;
+
+    let mut conn = db::get_conn(&pool)?;
+    let (customers, invoices) = match request.target {
+        ImportTarget::Customers => {
+            let report = import_customers(&mut conn, company_id, &range, &located, &request)?;
+            (Some(report), None)
+        }
+        ImportTarget::Invoices => {
+            let report = import_invoices(
+                &mut conn,
+                company_id,
+                sheet.clone(),
+                &range,
+                &located,
+                &request,
+            )?;
+            (None, Some(report))
+        }
+    };
+
+    Ok(AccountingImportReport {
+        source: request.source,
+        target: request.target,
+        sheet,
+        header_row: located.header_row,
+        columns: located
+            .columns
+            .iter()
+            .map(|column| (column.header.clone(), column.field.to_string()))
+            .collect(),
+        customers,
+        invoices,
+    })
+}
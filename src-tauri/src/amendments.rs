@@ -0,0 +1,253 @@
+use chrono::NaiveDate;
+use rusqlite::{params, Connection, OptionalExtension, Row};
+use serde::{Deserialize, Serialize};
+use tauri::State;
+
+use crate::credit_notes;
+use crate::db::{self, DbPool};
+use crate::error::AppError;
+use crate::gstr1::parse_period;
+use crate::invoices::{self, InvoiceStatus, INVOICE_DATE_FORMAT};
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum AmendedDocument {
+    Invoice,
+    CreditDebitNote,
+}
+
+impl AmendedDocument {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AmendedDocument::Invoice => "invoice",
+            AmendedDocument::CreditDebitNote => "credit_debit_note",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "invoice" => Some(AmendedDocument::Invoice),
+            "credit_debit_note" => Some(AmendedDocument::CreditDebitNote),
+            _ => None,
+        }
+    }
+}
+
+// Amendment data model: a document already reported in a filed GSTR-1 and corrected since.
+// The corrected document is reported again, against its original number and date, in the
+// B2BA/B2CLA/CDNRA sections of the return for `return_period`.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Amendment {
+    pub id: Option<i64>,
+    pub company_id: i64,
+    pub document_type: AmendedDocument,
+    pub document_id: i64,
+    pub original_number: String,
+    pub original_date: String,
+    // MMYYYY return in which the document was first reported
+    pub original_period: String,
+    pub return_period: String,
+    pub reason: Option<String>,
+    pub created_at: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CreateAmendment {
+    pub document_type: AmendedDocument,
+    pub document_id: i64,
+    // Default to the document's current number and date, for corrections that kept them
+    pub original_number: Option<String>,
+    pub original_date: Option<String>,
+    pub return_period: String,
+    pub reason: Option<String>,
+}
+
+const SELECT_AMENDMENT: &str = "
+    SELECT id, company_id, document_type, document_id, original_number, original_date,
+           original_period, return_period, reason, created_at
+    FROM gstr1_amendments";
+
+fn amendment_from_row(row: &Row) -> rusqlite::Result<Amendment> {
+    let document_type: String = row.get("document_type")?;
+    Ok(Amendment {
+        id: row.get("id")?,
+        company_id: row.get("company_id")?,
+        document_type: AmendedDocument::parse(&document_type)
+            .unwrap_or(AmendedDocument::Invoice),
+        document_id: row.get("document_id")?,
+        original_number: row.get("original_number")?,
+        original_date: row.get("original_date")?,
+        original_period: row.get("original_period")?,
+        return_period: row.get("return_period")?,
+        reason: row.get("reason")?,
+        created_at: row.get("created_at")?,
+    })
+}
+
+pub fn get_amendment_by_id(
+    conn: &Connection,
+    id: i64,
+    company_id: i64,
+) -> Result<Option<Amendment>, AppError> {
+    conn.query_row(
+        &format!("{} WHERE id = ?1 AND company_id = ?2", SELECT_AMENDMENT),
+        params![id, company_id],
+        amendment_from_row,
+    )
+    .optional()
+    .map_err(AppError::from)
+}
+
+// Amendments reported in one return, or all of the company's when no period is given
+pub fn get_amendments(
+    conn: &Connection,
+    company_id: i64,
+    return_period: Option<&str>,
+) -> Result<Vec<Amendment>, AppError> {
+    let mut stmt = conn.prepare(&format!(
+        "{} WHERE company_id = ?1 AND (?2 IS NULL OR return_period = ?2)
+             ORDER BY original_date, original_number",
+        SELECT_AMENDMENT
+    ))?;
+    let amendments = stmt
+        .query_map(params![company_id, return_period], amendment_from_row)?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(amendments)
+}
+
+// The document's current number, date and status
+fn current_document(
+    conn: &Connection,
+    company_id: i64,
+    document_type: AmendedDocument,
+    document_id: i64,
+) -> Result<(String, String, InvoiceStatus), AppError> {
+    match document_type {
+        AmendedDocument::Invoice => invoices::get_invoice_by_id(conn, document_id, company_id)?
+            .map(|i| (i.invoice_number, i.invoice_date, i.status))
+            .ok_or_else(|| AppError::not_found("Invoice not found")),
+        AmendedDocument::CreditDebitNote => {
+            credit_notes::get_note_by_id(conn, document_id, company_id)?
+                .map(|n| (n.note_number, n.note_date, n.status))
+                .ok_or_else(|| AppError::not_found("Credit/debit note not found"))
+        }
+    }
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn mark_gstr1_amendment(
+    pool: State<'_, DbPool>,
+    company_id: i64,
+    amendment: CreateAmendment,
+) -> Result<Amendment, AppError> {
+    let (return_from, _) = parse_period(&amendment.return_period)?;
+    let conn = db::get_conn(&pool)?;
+    let (number, date, status) = current_document(
+        &conn,
+        company_id,
+        amendment.document_type,
+        amendment.document_id,
+    )?;
+    if status != InvoiceStatus::Issued {
+        return Err(AppError::invalid(
+            "Only issued documents can be reported as amendments",
+        ));
+    }
+
+    let text = |value: &Option<String>| {
+        value
+            .as_deref()
+            .map(str::trim)
+            .filter(|v| !v.is_empty())
+            .map(str::to_string)
+    };
+    let original_number = text(&amendment.original_number).unwrap_or(number);
+    if original_number.len() > 16 {
+        return Err(AppError::validation(
+            "original_number",
+            "Original document number must be 16 characters or less",
+        ));
+    }
+    let original_date = text(&amendment.original_date).unwrap_or(date);
+    let original_date =
+        NaiveDate::parse_from_str(&original_date, INVOICE_DATE_FORMAT).map_err(|_| {
+            AppError::validation(
+                "original_date",
+                "Original date must be in YYYY-MM-DD format",
+            )
+        })?;
+    if original_date >= return_from {
+        return Err(AppError::validation(
+            "original_date",
+            "Amendments are reported in a return after the one holding the original",
+        ));
+    }
+    let reason = text(&amendment.reason);
+    if reason.as_ref().is_some_and(|r| r.len() > 500) {
+        return Err(AppError::validation(
+            "reason",
+            "Reason must be 500 characters or less",
+        ));
+    }
+
+    let inserted = conn.execute(
+        "INSERT INTO gstr1_amendments (company_id, document_type, document_id, original_number, original_date, original_period, return_period, reason)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)
+         ON CONFLICT(company_id, document_type, document_id, return_period) DO NOTHING",
+        params![
+            company_id,
+            amendment.document_type.as_str(),
+            amendment.document_id,
+            original_number,
+            original_date.format(INVOICE_DATE_FORMAT).to_string(),
+            original_date.format("%m%Y").to_string(),
+            amendment.return_period.trim(),
+            reason
+        ],
+    )?;
+    if inserted == 0 {
+        return Err(AppError::conflict(
+            "document_id",
+            "This document is already marked as an amendment in that return",
+        ));
+    }
+    get_amendment_by_id(&conn, conn.last_insert_rowid(), company_id)?
+        .ok_or_else(|| AppError::not_found("Amendment not found after creation"))
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn list_gstr1_amendments(
+    pool: State<'_, DbPool>,
+    company_id: i64,
+    return_period: Option<String>,
+) -> Result<Vec<Amendment>, AppError> {
+    let return_period = return_period
+        .as_deref()
+        .map(str::trim)
+        .filter(|p| !p.is_empty());
+    if let Some(period) = return_period {
+        parse_period(period)?;
+    }
+    let conn = db::get_conn(&pool)?;
+    get_amendments(&conn, company_id, return_period)
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn delete_gstr1_amendment(
+    pool: State<'_, DbPool>,
+    id: i64,
+    company_id: i64,
+) -> Result<(), AppError> {
+    let conn = db::get_conn(&pool)?;
+    let deleted = conn.execute(
+        "DELETE FROM gstr1_amendments WHERE id = ?1 AND company_id = ?2",
+        params![id, company_id],
+    )?;
+    if deleted == 0 {
+        return Err(AppError::not_found("Amendment not found"));
+    }
+    Ok(())
+}
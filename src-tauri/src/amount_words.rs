@@ -0,0 +1,126 @@
+use serde::{Deserialize, Serialize};
+
+use crate::error::AppError;
+use crate::invoices::round2;
+
+// Amounts beyond this lose paise precision as f64
+const MAX_AMOUNT: f64 = 1e13;
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum Currency {
+    Inr,
+    Usd,
+    Eur,
+    Gbp,
+}
+
+impl Currency {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_uppercase().as_str() {
+            "INR" => Some(Currency::Inr),
+            "USD" => Some(Currency::Usd),
+            "EUR" => Some(Currency::Eur),
+            "GBP" => Some(Currency::Gbp),
+            _ => None,
+        }
+    }
+
+    // (major plural, minor singular, minor plural)
+    fn names(&self) -> (&'static str, &'static str, &'static str) {
+        match self {
+            Currency::Inr => ("Rupees", "Paisa", "Paise"),
+            Currency::Usd => ("Dollars", "Cent", "Cents"),
+            Currency::Eur => ("Euros", "Cent", "Cents"),
+            Currency::Gbp => ("Pounds", "Penny", "Pence"),
+        }
+    }
+}
+
+const ONES: [&str; 20] = [
+    "", "One", "Two", "Three", "Four", "Five", "Six", "Seven", "Eight", "Nine", "Ten", "Eleven",
+    "Twelve", "Thirteen", "Fourteen", "Fifteen", "Sixteen", "Seventeen", "Eighteen", "Nineteen",
+];
+const TENS: [&str; 10] = [
+    "", "", "Twenty", "Thirty", "Forty", "Fifty", "Sixty", "Seventy", "Eighty", "Ninety",
+];
+
+fn below_hundred(n: u64) -> String {
+    if n < 20 {
+        ONES[n as usize].to_string()
+    } else if n.is_multiple_of(10) {
+        TENS[(n / 10) as usize].to_string()
+    } else {
+        format!("{} {}", TENS[(n / 10) as usize], ONES[(n % 10) as usize])
+    }
+}
+
+// Spells out a whole number using crore, lakh and thousand groupings
+pub fn number_in_words(n: u64) -> String {
+    if n == 0 {
+        return "Zero".to_string();
+    }
+    let mut parts = Vec::new();
+    // Anything above 99 crore is counted in crores, e.g. "One Thousand Crore"
+    let crore = n / 10_000_000;
+    if crore > 0 {
+        parts.push(format!("{} Crore", number_in_words(crore)));
+    }
+    let rest = n % 10_000_000;
+    let lakh = rest / 100_000;
+    let thousand = (rest / 1_000) % 100;
+    let hundred = (rest / 100) % 10;
+    if lakh > 0 {
+        parts.push(format!("{} Lakh", below_hundred(lakh)));
+    }
+    if thousand > 0 {
+        parts.push(format!("{} Thousand", below_hundred(thousand)));
+    }
+    if hundred > 0 {
+        parts.push(format!("{} Hundred", ONES[hundred as usize]));
+    }
+    if !rest.is_multiple_of(100) {
+        parts.push(below_hundred(rest % 100));
+    }
+    parts.join(" ")
+}
+
+// e.g. "Rupees One Lakh Twenty Thousand and Fifty Paise Only"
+pub fn amount_in_words(amount: f64, currency: Currency) -> Result<String, AppError> {
+    if !amount.is_finite() {
+        return Err(AppError::validation("amount", "Amount must be a number"));
+    }
+    if amount.abs() >= MAX_AMOUNT {
+        return Err(AppError::validation("amount", "Amount is too large to convert to words"));
+    }
+
+    let minor_total = (round2(amount.abs()) * 100.0).round() as u64;
+    let (major, minor) = (minor_total / 100, minor_total % 100);
+    let (major_name, minor_singular, minor_plural) = currency.names();
+
+    let mut words = String::new();
+    if amount < 0.0 && minor_total > 0 {
+        words.push_str("Minus ");
+    }
+    words.push_str(major_name);
+    words.push(' ');
+    words.push_str(&number_in_words(major));
+    if minor > 0 {
+        let minor_name = if minor == 1 { minor_singular } else { minor_plural };
+        words.push_str(&format!(" and {} {}", number_in_words(minor), minor_name));
+    }
+    words.push_str(" Only");
+    Ok(words)
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn amount_to_words(amount: f64, currency: Option<String>) -> Result<String, AppError> {
+    let currency = match currency.as_deref().filter(|c| !c.trim().is_empty()) {
+        Some(code) => Currency::parse(code).ok_or_else(|| {
+            AppError::validation("currency", format!("Unsupported currency {}", code))
+        })?,
+        None => Currency::Inr,
+    };
+    amount_in_words(amount, currency)
+}
@@ -0,0 +1,459 @@
+use std::path::{Path, PathBuf};
+
+use argon2::password_hash::rand_core::{OsRng, RngCore};
+use chrono::Local;
+use hmac::{Hmac, Mac};
+use rusqlite::{params, Connection, OpenFlags};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use tauri::State;
+
+use crate::backup;
+use crate::customers::normalize_customer_name;
+use crate::db::{self, DbPool};
+use crate::encryption;
+use crate::error::AppError;
+use crate::gstin;
+use crate::migrations;
+
+// Columns holding party names; `company_name` gets its own label so the two stay apart
+const NAME_COLUMNS: &[&str] = &[
+    "report_customer",
+    "tally_customer",
+    "report_customer_name",
+    "customer_name",
+    "supplier_name",
+    "legal_name",
+    "trade_name",
+    "transporter_name",
+];
+const COMPANY_COLUMN: &str = "company_name";
+const GSTIN_COLUMNS: &[&str] = &["gst_no", "gstin", "supplier_gstin"];
+const ADDRESS_COLUMNS: &[&str] = &["address", "city", "pincode"];
+// REAL columns that hold money. `rate` is only a price on document lines, items and price
+// lists; elsewhere it is a percentage or an exchange rate.
+const AMOUNT_COLUMNS: &[&str] = &[
+    "amount",
+    "taxable_value",
+    "foreign_taxable_value",
+    "total_value",
+    "value",
+    "outstanding",
+    "round_off",
+    "amount_received",
+    "tcs_base",
+    "unit_cost",
+    "discount",
+    "invoice_discount",
+];
+const PRICE_TABLES: &[&str] = &["items", "price_list_items"];
+// Normalized copies recomputed from the scrambled name: (table, name column, normalized column)
+const NORMALIZED_COLUMNS: &[(&str, &str, &str)] = &[
+    ("customers", "report_customer", "normalized_name"),
+    (
+        "persistent_customer_mappings",
+        "report_customer_name",
+        "normalized_report_customer_name",
+    ),
+];
+// Logs, caches and change journals keep copies of business data as text or JSON, and users
+// hold password hashes, so they are emptied rather than scrambled
+const CLEARED_TABLES: &[&str] = &[
+    "audit_log",
+    "email_log",
+    "gstin_verifications",
+    "import_reports",
+    "jobs",
+    "sync_changes",
+    "sync_conflicts",
+    "webhook_deliveries",
+    "users",
+];
+// Signed e-invoice documents repeat names, GSTINs and amounts
+const CLEARED_EINVOICE_COLUMNS: &[&str] = &[
+    "signed_invoice",
+    "signed_qr_code",
+    "request_payload",
+    "response_payload",
+];
+
+// Amounts are all scaled by one factor in this range, so totals still add up
+const MIN_AMOUNT_FACTOR: f64 = 0.6;
+const AMOUNT_FACTOR_SPREAD: f64 = 0.8;
+const KEY_BYTES: usize = 32;
+
+type HmacSha256 = Hmac<Sha256>;
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AnonymizedExport {
+    pub path: String,
+    pub file_name: String,
+    pub size_bytes: u64,
+    pub names_replaced: usize,
+    pub gstins_replaced: usize,
+    pub amount_columns: usize,
+    pub cleared_tables: Vec<String>,
+}
+
+// Replacement values are keyed with a secret drawn for each export and then discarded: the same
+// name or GSTIN always becomes the same replacement within one file, so joins and duplicates
+// survive, but the originals cannot be recovered by hashing guesses
+struct Scrambler {
+    key: [u8; KEY_BYTES],
+}
+
+impl Scrambler {
+    fn new() -> Self {
+        let mut key = [0u8; KEY_BYTES];
+        OsRng.fill_bytes(&mut key);
+        Scrambler { key }
+    }
+
+    fn digest(&self, kind: &str, value: &str) -> Result<Vec<u8>, String> {
+        let mut mac = HmacSha256::new_from_slice(&self.key).map_err(|e| e.to_string())?;
+        mac.update(kind.as_bytes());
+        mac.update(b":");
+        mac.update(value.as_bytes());
+        Ok(mac.finalize().into_bytes().to_vec())
+    }
+
+    fn name(&self, label: &str, value: &str) -> Result<String, String> {
+        let digest = self.digest("name", value)?;
+        let tag: String = digest[..4]
+            .iter()
+            .map(|byte| format!("{:02X}", byte))
+            .collect();
+        Ok(format!("{} {}", label, tag))
+    }
+
+    // Keeps the state code, entity number and the `Z`, so intra/inter-state tax and GSTIN
+    // validation behave as they did; the PAN part is replaced and the check digit recomputed
+    fn gstin(&self, value: &str) -> Result<String, String> {
+        let digest = self.digest("gstin", value)?;
+        let value = value.trim().to_ascii_uppercase();
+        let letter = |i: usize| (b'A' + digest[i] % 26) as char;
+        let digit = |i: usize| (b'0' + digest[i] % 10) as char;
+        let pan: String = (0..5)
+            .map(letter)
+            .chain((5..9).map(digit))
+            .chain(std::iter::once(letter(9)))
+            .collect();
+        let state = value
+            .get(..2)
+            .filter(|code| code.chars().all(|c| c.is_ascii_digit()))
+            .unwrap_or("99");
+        let entity = value
+            .chars()
+            .nth(12)
+            .filter(|c| c.is_ascii_alphanumeric() && *c != '0')
+            .unwrap_or('1');
+        let first_fourteen = format!("{}{}{}Z", state, pan, entity);
+        let check = gstin::compute_check_digit(&first_fourteen).unwrap_or('0');
+        Ok(format!("{}{}", first_fourteen, check))
+    }
+
+    fn amount_factor(&self) -> f64 {
+        let seed = u32::from_le_bytes([self.key[0], self.key[1], self.key[2], self.key[3]]);
+        MIN_AMOUNT_FACTOR + AMOUNT_FACTOR_SPREAD * (seed as f64 / u32::MAX as f64)
+    }
+}
+
+fn anonymized_file_name(created: &chrono::DateTime<Local>) -> String {
+    // Not named like a backup, so backup pruning in the same folder leaves it alone
+    format!(
+        "anonymized_sales_report_{}.db",
+        created.format("%Y%m%d_%H%M%S")
+    )
+}
+
+fn user_tables(conn: &Connection) -> Result<Vec<String>, String> {
+    let mut stmt = conn
+        .prepare(
+            "SELECT name FROM sqlite_master
+             WHERE type = 'table' AND name NOT LIKE 'sqlite_%'
+             ORDER BY name",
+        )
+        .map_err(|e| e.to_string())?;
+    let tables = stmt
+        .query_map([], |row| row.get::<_, String>(0))
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+    Ok(tables)
+}
+
+// (name, declared type) of each column
+fn columns(conn: &Connection, table: &str) -> Result<Vec<(String, String)>, String> {
+    let mut stmt = conn
+        .prepare(&format!("PRAGMA table_info(\"{}\")", table))
+        .map_err(|e| e.to_string())?;
+    let columns = stmt
+        .query_map([], |row| {
+            Ok((row.get::<_, String>("name")?, row.get::<_, String>("type")?))
+        })
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+    Ok(columns)
+}
+
+fn is_amount_column(table: &str, column: &str, declared: &str) -> bool {
+    if !declared.eq_ignore_ascii_case("REAL") {
+        return false;
+    }
+    column.ends_with("_amount")
+        || AMOUNT_COLUMNS.contains(&column)
+        || (column == "rate" && (table.ends_with("_lines") || PRICE_TABLES.contains(&table)))
+}
+
+// Rewrites every distinct value of a text column; returns how many distinct values changed
+fn replace_values(
+    conn: &Connection,
+    table: &str,
+    column: &str,
+    replace: impl Fn(&str) -> Result<Option<String>, String>,
+) -> Result<usize, String> {
+    let mut stmt = conn
+        .prepare(&format!(
+            "SELECT DISTINCT \"{0}\" FROM \"{1}\" WHERE \"{0}\" IS NOT NULL AND \"{0}\" <> ''",
+            column, table
+        ))
+        .map_err(|e| e.to_string())?;
+    let values = stmt
+        .query_map([], |row| row.get::<_, String>(0))
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+    let update = format!(
+        "UPDATE \"{1}\" SET \"{0}\" = ?1 WHERE \"{0}\" = ?2",
+        column, table
+    );
+    let mut replaced = 0;
+    for value in values {
+        if let Some(new_value) = replace(&value)? {
+            conn.execute(&update, params![new_value, value])
+                .map_err(|e| format!("Failed to scramble {}.{}: {}", table, column, e))?;
+            replaced += 1;
+        }
+    }
+    Ok(replaced)
+}
+
+// The audit log refuses updates and deletes and the sync triggers would journal every change,
+// so all triggers are lifted while scrambling and put back afterwards
+pub(crate) fn take_triggers(conn: &Connection) -> Result<Vec<(String, String)>, String> {
+    let mut stmt = conn
+        .prepare("SELECT name, sql FROM sqlite_master WHERE type = 'trigger' AND sql IS NOT NULL")
+        .map_err(|e| e.to_string())?;
+    let triggers = stmt
+        .query_map([], |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))
+        })
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+    for (name, _) in &triggers {
+        conn.execute_batch(&format!("DROP TRIGGER IF EXISTS \"{}\";", name))
+            .map_err(|e| e.to_string())?;
+    }
+    Ok(triggers)
+}
+
+fn scramble(conn: &mut Connection, export: &mut AnonymizedExport) -> Result<(), String> {
+    let scrambler = Scrambler::new();
+    let factor = scrambler.amount_factor();
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
+    let triggers = take_triggers(&tx)?;
+
+    for table in user_tables(&tx)? {
+        for (column, declared) in columns(&tx, &table)? {
+            let column = column.as_str();
+            if NAME_COLUMNS.contains(&column) {
+                export.names_replaced += replace_values(&tx, &table, column, |value| {
+                    scrambler.name("Party", value).map(Some)
+                })?;
+            } else if column == COMPANY_COLUMN {
+                export.names_replaced += replace_values(&tx, &table, column, |value| {
+                    scrambler.name("Company", value).map(Some)
+                })?;
+            } else if GSTIN_COLUMNS.contains(&column) {
+                // Markers such as URP for unregistered parties carry no identity
+                export.gstins_replaced += replace_values(&tx, &table, column, |value| {
+                    if value.chars().any(|c| c.is_ascii_digit()) {
+                        scrambler.gstin(value).map(Some)
+                    } else {
+                        Ok(None)
+                    }
+                })?;
+            } else if ADDRESS_COLUMNS.contains(&column) {
+                tx.execute(
+                    &format!("UPDATE \"{}\" SET \"{}\" = NULL", table, column),
+                    [],
+                )
+                .map_err(|e| format!("Failed to clear {}.{}: {}", table, column, e))?;
+            } else if is_amount_column(&table, column, &declared) {
+                tx.execute(
+                    &format!(
+                        "UPDATE \"{1}\" SET \"{0}\" = ROUND(\"{0}\" * ?1, 2)
+                         WHERE \"{0}\" IS NOT NULL",
+                        column, table
+                    ),
+                    params![factor],
+                )
+                .map_err(|e| format!("Failed to scramble {}.{}: {}", table, column, e))?;
+                export.amount_columns += 1;
+            }
+        }
+    }
+
+    for (table, name_column, normalized_column) in NORMALIZED_COLUMNS {
+        if !migrations::column_exists(&tx, table, normalized_column)? {
+            continue;
+        }
+        let mut stmt = tx
+            .prepare(&format!(
+                "SELECT DISTINCT \"{}\" FROM \"{}\"",
+                name_column, table
+            ))
+            .map_err(|e| e.to_string())?;
+        let names = stmt
+            .query_map([], |row| row.get::<_, String>(0))
+            .map_err(|e| e.to_string())?
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| e.to_string())?;
+        for name in names {
+            tx.execute(
+                &format!(
+                    "UPDATE \"{}\" SET \"{}\" = ?1 WHERE \"{}\" = ?2",
+                    table, normalized_column, name_column
+                ),
+                params![normalize_customer_name(&name), name],
+            )
+            .map_err(|e| e.to_string())?;
+        }
+    }
+
+    let tables = user_tables(&tx)?;
+    for table in CLEARED_TABLES
+        .iter()
+        .filter(|table| tables.iter().any(|t| t == *table))
+    {
+        tx.execute(&format!("DELETE FROM \"{}\"", table), [])
+            .map_err(|e| format!("Failed to clear {}: {}", table, e))?;
+        export.cleared_tables.push(table.to_string());
+    }
+    if tables.iter().any(|table| table == "einvoices") {
+        for column in CLEARED_EINVOICE_COLUMNS {
+            if migrations::column_exists(&tx, "einvoices", column)? {
+                tx.execute(&format!("UPDATE einvoices SET \"{}\" = NULL", column), [])
+                    .map_err(|e| format!("Failed to clear einvoices.{}: {}", column, e))?;
+            }
+        }
+    }
+    // Credentials saved as settings
+    tx.execute(
+        "DELETE FROM app_settings
+         WHERE key LIKE '%token%' OR key LIKE '%api_key' OR key LIKE '%password%'
+            OR key LIKE '%secret%'",
+        [],
+    )
+    .map_err(|e| e.to_string())?;
+    if tables.iter().any(|table| table == "webhooks") {
+        tx.execute("UPDATE webhooks SET secret = ''", [])
+            .map_err(|e| e.to_string())?;
+    }
+
+    for (name, sql) in triggers {
+        tx.execute_batch(&sql)
+            .map_err(|e| format!("Failed to restore trigger {}: {}", name, e))?;
+    }
+    tx.commit().map_err(|e| e.to_string())
+}
+
+// Snapshots the live database through the backup API, writes it out unencrypted so it can be
+// opened on another machine, then scrambles the copy. VACUUM rebuilds the file so no page
+// still holds an original value.
+pub fn export_anonymized(conn: &Connection, dir: &Path) -> Result<AnonymizedExport, AppError> {
+    if !dir.is_dir() {
+        return Err(AppError::validation(
+            "destination",
+            format!("Folder {} does not exist", dir.display()),
+        ));
+    }
+    let file_name = anonymized_file_name(&Local::now());
+    let path = dir.join(&file_name);
+    if path.exists() {
+        return Err(AppError::invalid(format!(
+            "{} already exists",
+            path.display()
+        )));
+    }
+    let partial = PathBuf::from(format!("{}.partial", path.display()));
+    let staging = backup::temp_path("anonymize");
+    std::fs::create_dir(&staging).map_err(|e| format!("Failed to stage the copy: {}", e))?;
+
+    let mut export = AnonymizedExport {
+        path: path.display().to_string(),
+        file_name,
+        size_bytes: 0,
+        names_replaced: 0,
+        gstins_replaced: 0,
+        amount_columns: 0,
+        cleared_tables: Vec::new(),
+    };
+    let written = (|| {
+        let snapshot = backup::create_backup(conn, &staging, None)?;
+        let source = encryption::open(Path::new(&snapshot.path), OpenFlags::default())?;
+        source
+            .execute(
+                "ATTACH DATABASE ?1 AS plain KEY ''",
+                params![partial.display().to_string()],
+            )
+            .map_err(|e| e.to_string())?;
+        source
+            .query_row("SELECT sqlcipher_export('plain')", [], |_| Ok(()))
+            .map_err(|e| format!("Failed to copy the database: {}", e))?;
+        source
+            .execute("DETACH DATABASE plain", [])
+            .map_err(|e| e.to_string())?;
+        drop(source);
+
+        let mut copy =
+            Connection::open(&partial).map_err(|e| format!("Failed to open the copy: {}", e))?;
+        scramble(&mut copy, &mut export)?;
+        copy.execute_batch("VACUUM;")
+            .map_err(|e| format!("Failed to compact the copy: {}", e))?;
+        drop(copy);
+        std::fs::rename(&partial, &path).map_err(|e| format!("Failed to save the copy: {}", e))
+    })();
+    let _ = std::fs::remove_dir_all(&staging);
+    if let Err(e) = written {
+        let _ = std::fs::remove_file(&partial);
+        return Err(e.into());
+    }
+
+    export.size_bytes = std::fs::metadata(&path)
+        .map(|metadata| metadata.len())
+        .map_err(|e| format!("Failed to read the copy: {}", e))?;
+    Ok(export)
+}
+
+// Copy of the database with names, GSTINs and amounts scrambled, for attaching to bug reports
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn export_anonymized_db(
+    pool: State<'_, DbPool>,
+    destination: String,
+) -> Result<AnonymizedExport, AppError> {
+    let destination = destination.trim();
+    if destination.is_empty() {
+        return Err(AppError::validation(
+            "destination",
+            "Choose a folder for the copy",
+        ));
+    }
+    let conn = db::get_conn(&pool)?;
+    let export = export_anonymized(&conn, Path::new(destination))?;
+    tracing::info!(file = %export.file_name, "anonymized database exported");
+    Ok(export)
+}
@@ -0,0 +1,431 @@
+use std::net::{Ipv4Addr, SocketAddr};
+use std::sync::Mutex;
+
+use argon2::password_hash::rand_core::{OsRng, RngCore};
+use axum::extract::{Path, Query, Request, State as AxumState};
+use axum::http::{header, StatusCode};
+use axum::middleware::{self, Next};
+use axum::response::{IntoResponse, Response};
+use axum::routing::get;
+use axum::{Json, Router};
+use chrono::NaiveDate;
+use rusqlite::Connection;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tauri::async_runtime::JoinHandle;
+use tauri::{AppHandle, Manager, State};
+use tokio::sync::oneshot;
+
+use crate::companies;
+use crate::db::{self, get_setting, set_setting, DbPool};
+use crate::error::AppError;
+use crate::invoices::{self, Invoice, InvoiceStatus, InvoiceWithLines, INVOICE_DATE_FORMAT};
+use crate::reports::{self, SalesRegisterFilters};
+use crate::settings;
+
+const SETTING_ENABLED: &str = "api_server_enabled";
+const SETTING_PORT: &str = "api_server_port";
+// Only a hash of the token is kept; the token itself is shown once when generated
+const SETTING_TOKEN_HASH: &str = "api_server_token_hash";
+const DEFAULT_PORT: u32 = 8787;
+const MIN_PORT: u32 = 1024;
+const TOKEN_BYTES: usize = 32;
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy)]
+pub struct ApiServerSettings {
+    pub enabled: bool,
+    pub port: u32,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ApiServerStatus {
+    pub enabled: bool,
+    pub port: u32,
+    pub running: bool,
+    // Base URL while running, e.g. http://127.0.0.1:8787
+    pub address: Option<String>,
+    pub has_token: bool,
+}
+
+struct RunningServer {
+    port: u16,
+    shutdown: oneshot::Sender<()>,
+    task: JoinHandle<()>,
+}
+
+// The local HTTP server, when running
+#[derive(Default)]
+pub struct ApiServer(Mutex<Option<RunningServer>>);
+
+impl ApiServer {
+    fn running_port(&self) -> Option<u16> {
+        self.0.lock().ok().and_then(|server| server.as_ref().map(|s| s.port))
+    }
+
+    // Waits for the server task to finish, so the port is free again once this returns
+    async fn stop(&self) -> Result<(), String> {
+        let running = self.0.lock().map_err(|e| e.to_string())?.take();
+        if let Some(running) = running {
+            // The server may already have stopped on its own
+            let _ = running.shutdown.send(());
+            let _ = running.task.await;
+            tracing::info!(port = running.port, "API server stopped");
+        }
+        Ok(())
+    }
+}
+
+pub fn load_settings(conn: &Connection) -> Result<ApiServerSettings, String> {
+    Ok(ApiServerSettings {
+        enabled: settings::get(conn, SETTING_ENABLED)?.unwrap_or(false),
+        port: settings::get(conn, SETTING_PORT)?.unwrap_or(DEFAULT_PORT),
+    })
+}
+
+fn hash_token(token: &str) -> String {
+    format!("{:x}", Sha256::digest(token.as_bytes()))
+}
+
+fn status(conn: &Connection, server: &ApiServer) -> Result<ApiServerStatus, String> {
+    let settings = load_settings(conn)?;
+    let running = server.running_port();
+    Ok(ApiServerStatus {
+        enabled: settings.enabled,
+        port: settings.port,
+        running: running.is_some(),
+        address: running.map(|port| format!("http://127.0.0.1:{}", port)),
+        has_token: get_setting(conn, SETTING_TOKEN_HASH)?.is_some(),
+    })
+}
+
+// Error body matches what commands return, with an HTTP status to go with it
+struct ApiError(AppError);
+
+impl From<String> for ApiError {
+    fn from(message: String) -> Self {
+        ApiError(AppError::from(message))
+    }
+}
+
+impl From<AppError> for ApiError {
+    fn from(error: AppError) -> Self {
+        ApiError(error)
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        let status = match &self.0 {
+            AppError::Validation { .. } => StatusCode::BAD_REQUEST,
+            AppError::NotFound { .. } => StatusCode::NOT_FOUND,
+            AppError::Conflict { .. } => StatusCode::CONFLICT,
+            AppError::Unauthenticated { .. } => StatusCode::UNAUTHORIZED,
+            AppError::Forbidden { .. } | AppError::PeriodLocked { .. } => StatusCode::FORBIDDEN,
+            AppError::Database { .. }
+            | AppError::Cancelled { .. }
+            | AppError::Internal { .. } => StatusCode::INTERNAL_SERVER_ERROR,
+        };
+        (status, Json(self.0)).into_response()
+    }
+}
+
+type ApiResult<T> = Result<Json<T>, ApiError>;
+
+// The token is checked against the stored hash on every request, so a regenerated token takes
+// effect without restarting the server
+async fn require_token(
+    AxumState(pool): AxumState<DbPool>,
+    request: Request,
+    next: Next,
+) -> Result<Response, ApiError> {
+    let given = request
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .map(str::trim)
+        .unwrap_or("");
+    // Released before the request runs, which takes its own connection from the pool
+    let expected = {
+        let conn = db::get_conn(&pool)?;
+        get_setting(&conn, SETTING_TOKEN_HASH)?
+    };
+    match expected {
+        Some(expected) if !given.is_empty() && hash_token(given) == expected => {
+            Ok(next.run(request).await)
+        }
+        _ => Err(ApiError(AppError::Unauthenticated {
+            message: "A valid API token is required".to_string(),
+        })),
+    }
+}
+
+fn company_conn(pool: &DbPool, company_id: i64) -> Result<db::DbConn, ApiError> {
+    let conn = db::get_conn(pool)?;
+    if companies::get_company_by_id(&conn, company_id)?.is_none() {
+        return Err(AppError::not_found("Company not found").into());
+    }
+    Ok(conn)
+}
+
+#[derive(Debug, Deserialize)]
+struct DateRange {
+    from: String,
+    to: String,
+}
+
+impl DateRange {
+    fn validate(&self) -> Result<(), ApiError> {
+        for (field, value) in [("from", &self.from), ("to", &self.to)] {
+            if NaiveDate::parse_from_str(value, INVOICE_DATE_FORMAT).is_err() {
+                return Err(AppError::validation(field, "Enter the date as YYYY-MM-DD").into());
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct InvoiceQuery {
+    from: String,
+    to: String,
+    status: Option<InvoiceStatus>,
+}
+
+#[derive(Debug, Deserialize)]
+struct CustomerSalesQuery {
+    from: String,
+    to: String,
+    category_id: Option<i64>,
+}
+
+#[derive(Debug, Deserialize)]
+struct CategorySummaryQuery {
+    from: String,
+    to: String,
+    status: Option<InvoiceStatus>,
+}
+
+#[derive(Debug, Deserialize)]
+struct PeriodQuery {
+    // MMYYYY, as in GSTR-1
+    period: String,
+}
+
+async fn health() -> Json<serde_json::Value> {
+    Json(serde_json::json!({ "status": "ok" }))
+}
+
+async fn list_invoices(
+    AxumState(pool): AxumState<DbPool>,
+    Path(company_id): Path<i64>,
+    Query(query): Query<InvoiceQuery>,
+) -> ApiResult<Vec<Invoice>> {
+    let range = DateRange {
+        from: query.from,
+        to: query.to,
+    };
+    range.validate()?;
+    let conn = company_conn(&pool, company_id)?;
+    let invoices = invoices::get_invoices_in_range(&conn, company_id, &range.from, &range.to)?
+        .into_iter()
+        .filter(|invoice| query.status.is_none_or(|status| invoice.status == status))
+        .collect();
+    Ok(Json(invoices))
+}
+
+async fn get_invoice(
+    AxumState(pool): AxumState<DbPool>,
+    Path((company_id, invoice_id)): Path<(i64, i64)>,
+) -> ApiResult<InvoiceWithLines> {
+    let conn = company_conn(&pool, company_id)?;
+    let invoice = invoices::get_invoice_by_id(&conn, invoice_id, company_id)?
+        .ok_or_else(|| AppError::not_found("Invoice not found"))?;
+    let lines = invoices::get_invoice_lines(&conn, invoice_id)?;
+    Ok(Json(InvoiceWithLines { invoice, lines }))
+}
+
+async fn sales_register(
+    AxumState(pool): AxumState<DbPool>,
+    Path(company_id): Path<i64>,
+    Query(range): Query<DateRange>,
+    Query(filters): Query<SalesRegisterFilters>,
+) -> ApiResult<reports::SalesRegister> {
+    range.validate()?;
+    let conn = company_conn(&pool, company_id)?;
+    let register =
+        reports::load_sales_register(&conn, company_id, &range.from, &range.to, &filters)?;
+    Ok(Json(register))
+}
+
+async fn sales_by_customer(
+    AxumState(pool): AxumState<DbPool>,
+    Path(company_id): Path<i64>,
+    Query(query): Query<CustomerSalesQuery>,
+) -> ApiResult<Vec<reports::CustomerSales>> {
+    let range = DateRange {
+        from: query.from,
+        to: query.to,
+    };
+    range.validate()?;
+    let conn = company_conn(&pool, company_id)?;
+    let rows = reports::load_sales_by_customer(
+        &conn,
+        company_id,
+        &range.from,
+        &range.to,
+        query.category_id,
+    )?;
+    Ok(Json(rows))
+}
+
+async fn monthly_category_summary(
+    AxumState(pool): AxumState<DbPool>,
+    Path(company_id): Path<i64>,
+    Query(query): Query<CategorySummaryQuery>,
+) -> ApiResult<reports::MonthlyCategorySummary> {
+    let range = DateRange {
+        from: query.from,
+        to: query.to,
+    };
+    range.validate()?;
+    let conn = company_conn(&pool, company_id)?;
+    let summary = reports::load_monthly_category_summary(
+        &conn,
+        company_id,
+        &range.from,
+        &range.to,
+        query.status,
+    )?;
+    Ok(Json(summary))
+}
+
+async fn state_supply_split(
+    AxumState(pool): AxumState<DbPool>,
+    Path(company_id): Path<i64>,
+    Query(query): Query<PeriodQuery>,
+) -> ApiResult<reports::StateSupplySplit> {
+    let conn = company_conn(&pool, company_id)?;
+    Ok(Json(reports::load_state_supply_split(&conn, company_id, &query.period)?))
+}
+
+// Read-only: nothing here writes to the database
+fn router(pool: DbPool) -> Router {
+    let company = Router::new()
+        .route("/invoices", get(list_invoices))
+        .route("/invoices/:invoice_id", get(get_invoice))
+        .route("/reports/sales-register", get(sales_register))
+        .route("/reports/sales-by-customer", get(sales_by_customer))
+        .route("/reports/monthly-category-summary", get(monthly_category_summary))
+        .route("/reports/state-supply-split", get(state_supply_split))
+        .route_layer(middleware::from_fn_with_state(pool.clone(), require_token));
+    Router::new()
+        .route("/api/health", get(health))
+        .nest("/api/companies/:company_id", company)
+        .with_state(pool)
+}
+
+// Listens on the loopback interface only, so the data never leaves this machine
+async fn start(pool: DbPool, server: &ApiServer, port: u32) -> Result<(), String> {
+    let port = u16::try_from(port).map_err(|_| format!("Port {} is out of range", port))?;
+    let address = SocketAddr::from((Ipv4Addr::LOCALHOST, port));
+    let listener = tokio::net::TcpListener::bind(address)
+        .await
+        .map_err(|e| format!("Could not listen on port {}: {}", port, e))?;
+    let (shutdown, stopped) = oneshot::channel::<()>();
+    let task = tauri::async_runtime::spawn(async move {
+        let result = axum::serve(listener, router(pool))
+            .with_graceful_shutdown(async move {
+                let _ = stopped.await;
+            })
+            .await;
+        if let Err(e) = result {
+            tracing::warn!("API server failed: {}", e);
+        }
+    });
+    *server.0.lock().map_err(|e| e.to_string())? = Some(RunningServer {
+        port,
+        shutdown,
+        task,
+    });
+    tracing::info!(port, "API server listening");
+    Ok(())
+}
+
+// Called at startup; a port clash is logged rather than stopping the app from opening
+pub fn start_if_enabled(app: AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        let pool = app.state::<DbPool>().inner().clone();
+        let settings =
+            match db::get_conn(&pool).map_err(String::from).and_then(|conn| load_settings(&conn)) {
+                Ok(settings) => settings,
+                Err(e) => {
+                    tracing::warn!("Could not read the API server settings: {}", e);
+                    return;
+                }
+            };
+        if !settings.enabled {
+            return;
+        }
+        if let Err(e) = start(pool, &app.state::<ApiServer>(), settings.port).await {
+            tracing::warn!("API server did not start: {}", e);
+        }
+    });
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn get_api_server_status(
+    pool: State<'_, DbPool>,
+    server: State<'_, ApiServer>,
+) -> Result<ApiServerStatus, AppError> {
+    let conn = db::get_conn(&pool)?;
+    Ok(status(&conn, &server)?)
+}
+
+// Saves the settings and starts, restarts or stops the server to match
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn set_api_server_settings(
+    pool: State<'_, DbPool>,
+    server: State<'_, ApiServer>,
+    settings: ApiServerSettings,
+) -> Result<ApiServerStatus, AppError> {
+    if !(MIN_PORT..=u32::from(u16::MAX)).contains(&settings.port) {
+        return Err(AppError::validation(
+            "port",
+            format!("Port must be between {} and {}", MIN_PORT, u16::MAX),
+        ));
+    }
+    let conn = db::get_conn(&pool)?;
+    if settings.enabled && get_setting(&conn, SETTING_TOKEN_HASH)?.is_none() {
+        return Err(AppError::validation(
+            "enabled",
+            "Generate an API token before turning the server on",
+        ));
+    }
+    settings::put(&conn, SETTING_ENABLED, &settings.enabled)?;
+    settings::put(&conn, SETTING_PORT, &settings.port)?;
+
+    server.stop().await?;
+    if settings.enabled {
+        start(pool.inner().clone(), &server, settings.port)
+            .await
+            .map_err(|message| AppError::validation("port", message))?;
+    }
+    Ok(status(&conn, &server)?)
+}
+
+// Replaces the API token, which stops the old one working immediately. The new token is only
+// returned here, so it must be copied into the other tool now.
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn regenerate_api_token(pool: State<'_, DbPool>) -> Result<String, AppError> {
+    let mut bytes = [0u8; TOKEN_BYTES];
+    OsRng.fill_bytes(&mut bytes);
+    let token: String = bytes.iter().map(|byte| format!("{:02x}", byte)).collect();
+    let conn = db::get_conn(&pool)?;
+    set_setting(&conn, SETTING_TOKEN_HASH, &hash_token(&token))?;
+    tracing::info!("API token regenerated");
+    Ok(token)
+}
@@ -0,0 +1,311 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use rusqlite::{params, Connection, OptionalExtension, Row};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tauri::{AppHandle, Manager, State};
+use tauri_plugin_opener::OpenerExt;
+
+use crate::customers;
+use crate::db::{self, DbPool};
+use crate::error::AppError;
+use crate::invoices;
+
+const ATTACHMENTS_DIR: &str = "attachments";
+const MAX_ATTACHMENT_BYTES: u64 = 10 * 1024 * 1024;
+const MAX_FILE_NAME_LENGTH: usize = 200;
+// Extensions that can be attached, with the content type recorded for them
+const ALLOWED_TYPES: [(&str, &str); 11] = [
+    ("pdf", "application/pdf"),
+    ("png", "image/png"),
+    ("jpg", "image/jpeg"),
+    ("jpeg", "image/jpeg"),
+    ("txt", "text/plain"),
+    ("csv", "text/csv"),
+    ("doc", "application/msword"),
+    (
+        "docx",
+        "application/vnd.openxmlformats-officedocument.wordprocessingml.document",
+    ),
+    ("xls", "application/vnd.ms-excel"),
+    (
+        "xlsx",
+        "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet",
+    ),
+    ("zip", "application/zip"),
+];
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum AttachmentEntity {
+    Invoice,
+    Customer,
+}
+
+impl AttachmentEntity {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AttachmentEntity::Invoice => "invoice",
+            AttachmentEntity::Customer => "customer",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "invoice" => Some(AttachmentEntity::Invoice),
+            "customer" => Some(AttachmentEntity::Customer),
+            _ => None,
+        }
+    }
+}
+
+// Attachment data model; the file itself is stored once per content hash in the app data dir
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Attachment {
+    pub id: i64,
+    pub company_id: i64,
+    pub entity_type: AttachmentEntity,
+    pub entity_id: i64,
+    pub file_name: String,
+    pub content_type: String,
+    pub size_bytes: u64,
+    pub sha256: String,
+    pub created_at: Option<String>,
+}
+
+const SELECT_ATTACHMENT: &str = "
+    SELECT id, company_id, entity_type, entity_id, file_name, content_type, size_bytes, sha256,
+           created_at
+    FROM attachments";
+
+fn attachment_from_row(row: &Row) -> rusqlite::Result<Attachment> {
+    let entity_type: String = row.get("entity_type")?;
+    Ok(Attachment {
+        id: row.get("id")?,
+        company_id: row.get("company_id")?,
+        entity_type: AttachmentEntity::parse(&entity_type).unwrap_or(AttachmentEntity::Invoice),
+        entity_id: row.get("entity_id")?,
+        file_name: row.get("file_name")?,
+        content_type: row.get("content_type")?,
+        size_bytes: row.get("size_bytes")?,
+        sha256: row.get("sha256")?,
+        created_at: row.get("created_at")?,
+    })
+}
+
+fn attachments_dir(app: &AppHandle) -> Result<PathBuf, String> {
+    let dir = app
+        .path()
+        .app_data_dir()
+        .map_err(|e| format!("Failed to resolve app data directory: {}", e))?
+        .join(ATTACHMENTS_DIR);
+    fs::create_dir_all(&dir)
+        .map_err(|e| format!("Failed to create attachments directory: {}", e))?;
+    Ok(dir)
+}
+
+fn stored_path(dir: &Path, sha256: &str) -> PathBuf {
+    dir.join(sha256)
+}
+
+pub fn get_attachment_by_id(
+    conn: &Connection,
+    id: i64,
+    company_id: i64,
+) -> Result<Option<Attachment>, AppError> {
+    conn.query_row(
+        &format!("{} WHERE id = ?1 AND company_id = ?2", SELECT_ATTACHMENT),
+        params![id, company_id],
+        attachment_from_row,
+    )
+    .optional()
+    .map_err(AppError::from)
+}
+
+fn check_entity(
+    conn: &Connection,
+    company_id: i64,
+    entity_type: AttachmentEntity,
+    entity_id: i64,
+) -> Result<(), AppError> {
+    let exists = match entity_type {
+        AttachmentEntity::Invoice => {
+            invoices::get_invoice_by_id(conn, entity_id, company_id)?.is_some()
+        }
+        AttachmentEntity::Customer => {
+            customers::get_customer_by_id(conn, entity_id, company_id)?.is_some()
+        }
+    };
+    if !exists {
+        return Err(AppError::not_found(format!(
+            "{} not found",
+            match entity_type {
+                AttachmentEntity::Invoice => "Invoice",
+                AttachmentEntity::Customer => "Customer",
+            }
+        )));
+    }
+    Ok(())
+}
+
+// Returns the file name and its content type
+fn check_file(path: &Path) -> Result<(String, &'static str), AppError> {
+    let file_name = path
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .filter(|name| !name.trim().is_empty())
+        .ok_or_else(|| AppError::validation("path", "Choose a file to attach"))?;
+    if file_name.chars().count() > MAX_FILE_NAME_LENGTH {
+        return Err(AppError::validation(
+            "path",
+            format!("File names can be at most {} characters", MAX_FILE_NAME_LENGTH),
+        ));
+    }
+    let extension = path
+        .extension()
+        .map(|ext| ext.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+    let content_type = ALLOWED_TYPES
+        .iter()
+        .find(|(allowed, _)| *allowed == extension)
+        .map(|(_, content_type)| *content_type)
+        .ok_or_else(|| {
+            let allowed = ALLOWED_TYPES.iter().map(|(ext, _)| *ext).collect::<Vec<_>>();
+            AppError::validation(
+                "path",
+                format!("Only these file types can be attached: {}", allowed.join(", ")),
+            )
+        })?;
+    let metadata =
+        fs::metadata(path).map_err(|e| format!("Failed to read {}: {}", file_name, e))?;
+    if !metadata.is_file() {
+        return Err(AppError::validation("path", "Choose a file to attach"));
+    }
+    if metadata.len() > MAX_ATTACHMENT_BYTES {
+        return Err(AppError::validation(
+            "path",
+            format!("Attachments can be at most {} MB", MAX_ATTACHMENT_BYTES / (1024 * 1024)),
+        ));
+    }
+    Ok((file_name, content_type))
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn add_attachment(
+    pool: State<'_, DbPool>,
+    app: AppHandle,
+    company_id: i64,
+    entity_type: AttachmentEntity,
+    entity_id: i64,
+    path: String,
+) -> Result<Attachment, AppError> {
+    let source = PathBuf::from(path.trim());
+    let (file_name, content_type) = check_file(&source)?;
+    let conn = db::get_conn(&pool)?;
+    check_entity(&conn, company_id, entity_type, entity_id)?;
+
+    let bytes = fs::read(&source).map_err(|e| format!("Failed to read {}: {}", file_name, e))?;
+    let sha256 = format!("{:x}", Sha256::digest(&bytes));
+    let target = stored_path(&attachments_dir(&app)?, &sha256);
+    // The same file attached twice is stored once
+    if !target.exists() {
+        fs::write(&target, &bytes).map_err(|e| format!("Failed to store {}: {}", file_name, e))?;
+    }
+
+    conn.execute(
+        "INSERT INTO attachments (company_id, entity_type, entity_id, file_name, content_type,
+                                  size_bytes, sha256)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+        params![
+            company_id,
+            entity_type.as_str(),
+            entity_id,
+            file_name,
+            content_type,
+            bytes.len() as u64,
+            sha256,
+        ],
+    )?;
+    get_attachment_by_id(&conn, conn.last_insert_rowid(), company_id)?
+        .ok_or_else(|| AppError::not_found("Attachment not found after creation"))
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn list_attachments(
+    pool: State<'_, DbPool>,
+    company_id: i64,
+    entity_type: AttachmentEntity,
+    entity_id: i64,
+) -> Result<Vec<Attachment>, AppError> {
+    let conn = db::get_conn(&pool)?;
+    let mut stmt = conn.prepare(&format!(
+        "{} WHERE company_id = ?1 AND entity_type = ?2 AND entity_id = ?3
+             ORDER BY created_at DESC, id DESC",
+        SELECT_ATTACHMENT
+    ))?;
+    let attachments = stmt
+        .query_map(params![company_id, entity_type.as_str(), entity_id], attachment_from_row)?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(attachments)
+}
+
+// Opens the stored file in the default app for its type. The check against the recorded hash
+// catches files that were changed or replaced in the attachments folder.
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn open_attachment(
+    pool: State<'_, DbPool>,
+    app: AppHandle,
+    id: i64,
+    company_id: i64,
+) -> Result<Attachment, AppError> {
+    let attachment = {
+        let conn = db::get_conn(&pool)?;
+        get_attachment_by_id(&conn, id, company_id)?
+            .ok_or_else(|| AppError::not_found("Attachment not found"))?
+    };
+    let path = stored_path(&attachments_dir(&app)?, &attachment.sha256);
+    let bytes = fs::read(&path)
+        .map_err(|e| format!("The stored copy of {} is missing: {}", attachment.file_name, e))?;
+    if format!("{:x}", Sha256::digest(&bytes)) != attachment.sha256 {
+        return Err(AppError::invalid(format!(
+            "The stored copy of {} has been modified",
+            attachment.file_name
+        )));
+    }
+    app.opener()
+        .open_path(path.to_string_lossy(), None::<&str>)
+        .map_err(|e| format!("Failed to open {}: {}", attachment.file_name, e))?;
+    Ok(attachment)
+}
+
+// The stored file is removed once no attachment refers to it
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn delete_attachment(
+    pool: State<'_, DbPool>,
+    app: AppHandle,
+    id: i64,
+    company_id: i64,
+) -> Result<(), AppError> {
+    let conn = db::get_conn(&pool)?;
+    let attachment = get_attachment_by_id(&conn, id, company_id)?
+        .ok_or_else(|| AppError::not_found("Attachment not found"))?;
+    conn.execute("DELETE FROM attachments WHERE id = ?1", params![id])?;
+    let still_used: i64 = conn.query_row(
+        "SELECT COUNT(*) FROM attachments WHERE sha256 = ?1",
+        params![attachment.sha256],
+        |row| row.get(0),
+    )?;
+    if still_used == 0 {
+        let path = stored_path(&attachments_dir(&app)?, &attachment.sha256);
+        if path.exists() {
+            fs::remove_file(&path)
+                .map_err(|e| format!("Failed to delete {}: {}", attachment.file_name, e))?;
+        }
+    }
+    Ok(())
+}
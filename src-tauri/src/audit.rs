@@ -0,0 +1,199 @@
+use std::sync::RwLock;
+
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use tauri::State;
+
+use crate::db::{self, DbPool};
+use crate::error::AppError;
+
+// Bookkeeping columns that change on every write and would only add noise
+const IGNORED_FIELDS: &[&str] = &["created_at", "updated_at"];
+
+// Name recorded against each change; set when a user signs in
+static ACTOR: RwLock<Option<String>> = RwLock::new(None);
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum AuditAction {
+    Create,
+    Update,
+    Delete,
+    Restore,
+    Merge,
+    Denied,
+}
+
+impl AuditAction {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AuditAction::Create => "create",
+            AuditAction::Update => "update",
+            AuditAction::Delete => "delete",
+            AuditAction::Restore => "restore",
+            AuditAction::Merge => "merge",
+            AuditAction::Denied => "denied",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "create" => Some(AuditAction::Create),
+            "update" => Some(AuditAction::Update),
+            "delete" => Some(AuditAction::Delete),
+            "restore" => Some(AuditAction::Restore),
+            "merge" => Some(AuditAction::Merge),
+            "denied" => Some(AuditAction::Denied),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct FieldChange {
+    pub field: String,
+    pub old_value: Value,
+    pub new_value: Value,
+}
+
+// Audit entry data model
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AuditEntry {
+    pub id: i64,
+    pub company_id: i64,
+    pub entity: String,
+    pub entity_id: i64,
+    pub action: AuditAction,
+    pub changes: Vec<FieldChange>,
+    pub user_name: Option<String>,
+    pub created_at: String,
+}
+
+pub fn set_actor(user_name: Option<String>) {
+    if let Ok(mut actor) = ACTOR.write() {
+        *actor = user_name;
+    }
+}
+
+fn actor() -> Option<String> {
+    ACTOR.read().ok().and_then(|actor| actor.clone())
+}
+
+fn fields<T: Serialize>(record: Option<&T>) -> Result<Map<String, Value>, String> {
+    match record.map(serde_json::to_value).transpose() {
+        Ok(Some(Value::Object(map))) => Ok(map),
+        Ok(_) => Ok(Map::new()),
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+// Field-level differences between two serialized versions of a record; nested values such as
+// a customer's embedded category are compared as a whole
+pub fn diff<T: Serialize>(
+    before: Option<&T>,
+    after: Option<&T>,
+) -> Result<Vec<FieldChange>, String> {
+    let (before, after) = (fields(before)?, fields(after)?);
+    let mut names: Vec<&String> = before.keys().chain(after.keys()).collect();
+    names.sort();
+    names.dedup();
+    Ok(names
+        .into_iter()
+        .filter(|name| !IGNORED_FIELDS.contains(&name.as_str()))
+        .filter_map(|name| {
+            let old_value = before.get(name).cloned().unwrap_or(Value::Null);
+            let new_value = after.get(name).cloned().unwrap_or(Value::Null);
+            (old_value != new_value).then(|| FieldChange {
+                field: name.clone(),
+                old_value,
+                new_value,
+            })
+        })
+        .collect())
+}
+
+// Appends one entry; updates that change nothing are not recorded
+pub fn record<T: Serialize>(
+    conn: &Connection,
+    company_id: i64,
+    entity: &str,
+    entity_id: i64,
+    action: AuditAction,
+    before: Option<&T>,
+    after: Option<&T>,
+) -> Result<(), AppError> {
+    let changes = diff(before, after)?;
+    if action == AuditAction::Update && changes.is_empty() {
+        return Ok(());
+    }
+    let changes = serde_json::to_string(&changes).map_err(|e| e.to_string())?;
+    conn.execute(
+        "INSERT INTO audit_log (company_id, entity, entity_id, action, changes, user_name)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+        params![company_id, entity, entity_id, action.as_str(), changes, actor()],
+    )?;
+    Ok(())
+}
+
+// A refused command call; listed under the "command" entity with id 0
+pub fn record_denied(
+    conn: &Connection,
+    company_id: i64,
+    command: &str,
+    reason: &str,
+) -> Result<(), AppError> {
+    let changes = [
+        FieldChange {
+            field: "command".to_string(),
+            old_value: Value::Null,
+            new_value: Value::from(command),
+        },
+        FieldChange {
+            field: "reason".to_string(),
+            old_value: Value::Null,
+            new_value: Value::from(reason),
+        },
+    ];
+    let changes = serde_json::to_string(&changes).map_err(|e| e.to_string())?;
+    conn.execute(
+        "INSERT INTO audit_log (company_id, entity, entity_id, action, changes, user_name)
+         VALUES (?1, 'command', 0, ?2, ?3, ?4)",
+        params![company_id, AuditAction::Denied.as_str(), changes, actor()],
+    )?;
+    Ok(())
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn get_audit_trail(
+    pool: State<'_, DbPool>,
+    company_id: i64,
+    entity: String,
+    id: i64,
+) -> Result<Vec<AuditEntry>, AppError> {
+    let conn = db::get_conn(&pool)?;
+    let mut stmt = conn.prepare(
+        "SELECT id, company_id, entity, entity_id, action, changes, user_name, created_at
+             FROM audit_log
+             WHERE company_id = ?1 AND entity = ?2 AND entity_id = ?3
+             ORDER BY id",
+    )?;
+    let entries = stmt
+        .query_map(params![company_id, entity.trim(), id], |row| {
+            let action: String = row.get(4)?;
+            let changes: String = row.get(5)?;
+            Ok(AuditEntry {
+                id: row.get(0)?,
+                company_id: row.get(1)?,
+                entity: row.get(2)?,
+                entity_id: row.get(3)?,
+                action: AuditAction::parse(&action).unwrap_or(AuditAction::Update),
+                changes: serde_json::from_str(&changes).unwrap_or_default(),
+                user_name: row.get(6)?,
+                created_at: row.get(7)?,
+            })
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(entries)
+}
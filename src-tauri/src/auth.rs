@@ -0,0 +1,344 @@
+use std::sync::Mutex;
+
+use argon2::password_hash::rand_core::OsRng;
+use argon2::password_hash::{PasswordHash, PasswordHasher, PasswordVerifier, SaltString};
+use argon2::Argon2;
+use rusqlite::{params, Connection, OptionalExtension, Row};
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Emitter, Runtime, State};
+
+use crate::audit;
+use crate::db::{self, DbPool};
+use crate::error::AppError;
+
+const MIN_PASSWORD_LENGTH: usize = 8;
+// Sent with the signed-in user, or null, whenever someone signs in or out
+pub const EVENT_SESSION_CHANGED: &str = "session://changed";
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "lowercase")]
+pub enum Role {
+    Viewer,
+    Accountant,
+    Admin,
+}
+
+impl Role {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Role::Viewer => "viewer",
+            Role::Accountant => "accountant",
+            Role::Admin => "admin",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "viewer" => Some(Role::Viewer),
+            "accountant" => Some(Role::Accountant),
+            "admin" => Some(Role::Admin),
+            _ => None,
+        }
+    }
+}
+
+// User data model
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct User {
+    pub id: i64,
+    pub username: String,
+    pub role: Role,
+    pub is_active: bool,
+    pub last_login_at: Option<String>,
+    pub created_at: Option<String>,
+    pub updated_at: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CreateUser {
+    pub username: String,
+    pub password: String,
+    pub role: Role,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct UpdateUser {
+    pub role: Option<Role>,
+    pub is_active: Option<bool>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SessionUser {
+    pub id: i64,
+    pub username: String,
+    pub role: Role,
+}
+
+// Signed-in user for this app instance
+#[derive(Default)]
+pub struct Session(Mutex<Option<SessionUser>>);
+
+impl Session {
+    pub fn current(&self) -> Option<SessionUser> {
+        self.0.lock().ok().and_then(|user| user.clone())
+    }
+
+    fn set(&self, user: Option<SessionUser>) -> Result<(), String> {
+        audit::set_actor(user.as_ref().map(|user| user.username.clone()));
+        *self.0.lock().map_err(|e| e.to_string())? = user;
+        Ok(())
+    }
+}
+
+const SELECT_USER: &str = "SELECT id, username, role, is_active, last_login_at, created_at,
+    updated_at FROM users";
+
+fn user_from_row(row: &Row) -> rusqlite::Result<User> {
+    let role: String = row.get("role")?;
+    Ok(User {
+        id: row.get("id")?,
+        username: row.get("username")?,
+        role: Role::parse(&role).unwrap_or(Role::Viewer),
+        is_active: row.get("is_active")?,
+        last_login_at: row.get("last_login_at")?,
+        created_at: row.get("created_at")?,
+        updated_at: row.get("updated_at")?,
+    })
+}
+
+fn get_user_by_id(conn: &Connection, id: i64) -> Result<Option<User>, AppError> {
+    conn.query_row(&format!("{} WHERE id = ?1", SELECT_USER), params![id], user_from_row)
+        .optional()
+        .map_err(AppError::from)
+}
+
+// Until the first account is created the app runs without sign-in, as it always has
+pub fn users_exist(conn: &Connection) -> Result<bool, AppError> {
+    conn.query_row("SELECT EXISTS (SELECT 1 FROM users)", [], |row| row.get(0))
+        .map_err(AppError::from)
+}
+
+fn active_admin_count(conn: &Connection) -> Result<i64, AppError> {
+    conn.query_row("SELECT COUNT(*) FROM users WHERE role = 'admin' AND is_active = 1", [], |row| {
+        row.get(0)
+    })
+    .map_err(AppError::from)
+}
+
+fn validate_password(field: &str, password: &str) -> Result<(), AppError> {
+    if password.chars().count() < MIN_PASSWORD_LENGTH {
+        return Err(AppError::validation(
+            field,
+            format!("Password must be at least {} characters", MIN_PASSWORD_LENGTH),
+        ));
+    }
+    Ok(())
+}
+
+fn hash_password(password: &str) -> Result<String, String> {
+    let salt = SaltString::generate(&mut OsRng);
+    Argon2::default()
+        .hash_password(password.as_bytes(), &salt)
+        .map(|hash| hash.to_string())
+        .map_err(|e| format!("Failed to hash password: {}", e))
+}
+
+fn verify_password(password: &str, hash: &str) -> bool {
+    PasswordHash::new(hash)
+        .map(|parsed| Argon2::default().verify_password(password.as_bytes(), &parsed).is_ok())
+        .unwrap_or(false)
+}
+
+fn require_role(session: &Session, role: Role) -> Result<SessionUser, AppError> {
+    let user = session.current().ok_or_else(|| AppError::Unauthenticated {
+        message: "Please sign in to continue".to_string(),
+    })?;
+    if user.role < role {
+        return Err(AppError::Forbidden {
+            message: format!("This action requires the {} role", role.as_str()),
+        });
+    }
+    Ok(user)
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn login<R: Runtime>(
+    app: AppHandle<R>,
+    pool: State<'_, DbPool>,
+    session: State<'_, Session>,
+    username: String,
+    password: String,
+) -> Result<SessionUser, AppError> {
+    let conn = db::get_conn(&pool)?;
+    let found: Option<(i64, String, bool)> = conn
+        .query_row(
+            "SELECT id, password_hash, is_active FROM users WHERE username = ?1",
+            params![username.trim()],
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+        )
+        .optional()?;
+    let id = match found {
+        Some((id, hash, true)) if verify_password(&password, &hash) => id,
+        _ => {
+            return Err(AppError::Unauthenticated {
+                message: "Invalid username or password".to_string(),
+            })
+        }
+    };
+
+    conn.execute("UPDATE users SET last_login_at = CURRENT_TIMESTAMP WHERE id = ?1", params![id])?;
+    let user = get_user_by_id(&conn, id)?.ok_or_else(|| AppError::not_found("User not found"))?;
+    let current = SessionUser { id: user.id, username: user.username, role: user.role };
+    session.set(Some(current.clone()))?;
+    let _ = app.emit(EVENT_SESSION_CHANGED, &current);
+    Ok(current)
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn logout<R: Runtime>(
+    app: AppHandle<R>,
+    session: State<'_, Session>,
+) -> Result<(), AppError> {
+    session.set(None)?;
+    let _ = app.emit(EVENT_SESSION_CHANGED, None::<SessionUser>);
+    Ok(())
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn get_session(session: State<'_, Session>) -> Result<Option<SessionUser>, AppError> {
+    Ok(session.current())
+}
+
+// The first account can be created without signing in and must be an admin
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn create_user(
+    pool: State<'_, DbPool>,
+    session: State<'_, Session>,
+    user: CreateUser,
+) -> Result<User, AppError> {
+    let conn = db::get_conn(&pool)?;
+    if users_exist(&conn)? {
+        require_role(&session, Role::Admin)?;
+    } else if user.role != Role::Admin {
+        return Err(AppError::validation("role", "The first user must be an admin"));
+    }
+
+    let username = user.username.trim();
+    if username.is_empty() {
+        return Err(AppError::validation("username", "Username is required"));
+    }
+    if username.len() > 50 {
+        return Err(AppError::validation("username", "Username must be 50 characters or less"));
+    }
+    validate_password("password", &user.password)?;
+
+    conn.execute(
+        "INSERT INTO users (username, password_hash, role) VALUES (?1, ?2, ?3)",
+        params![username, hash_password(&user.password)?, user.role.as_str()],
+    )
+    .map_err(|e| {
+        if e.to_string().contains("UNIQUE constraint failed") {
+            return AppError::conflict("username", "A user with this name already exists");
+        }
+        AppError::from(e)
+    })?;
+    get_user_by_id(&conn, conn.last_insert_rowid())?
+        .ok_or_else(|| AppError::not_found("User not found after creation"))
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn list_users(pool: State<'_, DbPool>) -> Result<Vec<User>, AppError> {
+    let conn = db::get_conn(&pool)?;
+    let mut stmt = conn.prepare(&format!("{} ORDER BY username ASC", SELECT_USER))?;
+    let users = stmt.query_map([], user_from_row)?.collect::<Result<Vec<_>, _>>()?;
+    Ok(users)
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn update_user(
+    pool: State<'_, DbPool>,
+    session: State<'_, Session>,
+    id: i64,
+    user: UpdateUser,
+) -> Result<User, AppError> {
+    let conn = db::get_conn(&pool)?;
+    let existing =
+        get_user_by_id(&conn, id)?.ok_or_else(|| AppError::not_found("User not found"))?;
+    let loses_admin = existing.role == Role::Admin
+        && existing.is_active
+        && (user.role.is_some_and(|role| role != Role::Admin) || user.is_active == Some(false));
+    if loses_admin && active_admin_count(&conn)? <= 1 {
+        return Err(AppError::invalid("At least one active admin is required"));
+    }
+
+    conn.execute(
+        "UPDATE users SET
+            role = COALESCE(?1, role),
+            is_active = COALESCE(?2, is_active),
+            updated_at = CURRENT_TIMESTAMP
+         WHERE id = ?3",
+        params![user.role.map(|role| role.as_str()), user.is_active, id],
+    )?;
+
+    // Changes to the signed-in account take effect straight away
+    if session.current().is_some_and(|current| current.id == id) {
+        let updated = get_user_by_id(&conn, id)?;
+        session.set(updated.filter(|user| user.is_active).map(|user| SessionUser {
+            id: user.id,
+            username: user.username,
+            role: user.role,
+        }))?;
+    }
+    get_user_by_id(&conn, id)?
+        .ok_or_else(|| AppError::not_found("User not found after update"))
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn reset_user_password(
+    pool: State<'_, DbPool>,
+    id: i64,
+    new_password: String,
+) -> Result<(), AppError> {
+    validate_password("new_password", &new_password)?;
+    let conn = db::get_conn(&pool)?;
+    let changed = conn.execute(
+        "UPDATE users SET password_hash = ?1, updated_at = CURRENT_TIMESTAMP WHERE id = ?2",
+        params![hash_password(&new_password)?, id],
+    )?;
+    if changed == 0 {
+        return Err(AppError::not_found("User not found"));
+    }
+    Ok(())
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn change_password(
+    pool: State<'_, DbPool>,
+    session: State<'_, Session>,
+    current_password: String,
+    new_password: String,
+) -> Result<(), AppError> {
+    let user = require_role(&session, Role::Viewer)?;
+    validate_password("new_password", &new_password)?;
+    let conn = db::get_conn(&pool)?;
+    let hash: String =
+        conn.query_row("SELECT password_hash FROM users WHERE id = ?1", params![user.id], |row| {
+            row.get(0)
+        })?;
+    if !verify_password(&current_password, &hash) {
+        return Err(AppError::validation("current_password", "Current password is incorrect"));
+    }
+    conn.execute(
+        "UPDATE users SET password_hash = ?1, updated_at = CURRENT_TIMESTAMP WHERE id = ?2",
+        params![hash_password(&new_password)?, user.id],
+    )?;
+    Ok(())
+}
@@ -0,0 +1,481 @@
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use chrono::Local;
+use rusqlite::backup::{Backup, StepResult};
+use rusqlite::{Connection, OpenFlags};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tauri::{AppHandle, State};
+
+use crate::backup_archive::{self, ARCHIVE_EXTENSION};
+use crate::backup_schedule;
+use crate::cancellation::{CancelToken, Cancellations};
+use crate::dashboard::DashboardCache;
+use crate::db::{self, DbPool};
+use crate::encryption;
+use crate::error::AppError;
+use crate::migrations;
+
+// Pages copied per step; the source stays usable by other connections between steps
+const PAGES_PER_STEP: i32 = 256;
+const STEP_PAUSE: Duration = Duration::from_millis(25);
+
+// Pre-restore copies kept next to the database
+const ROLLBACK_FOLDER: &str = "rollback";
+const ROLLBACK_COPIES: u32 = 5;
+
+// Backup file data model
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct BackupInfo {
+    pub path: String,
+    pub file_name: String,
+    pub schema_version: i64,
+    pub size_bytes: u64,
+    pub sha256: String,
+    pub encrypted: bool,
+    pub created_at: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TableCount {
+    pub table: String,
+    pub backup_rows: i64,
+    // None when the table does not exist in the live database yet
+    pub current_rows: Option<i64>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RestorePreflight {
+    pub file: String,
+    pub encrypted: bool,
+    pub backup_schema_version: i64,
+    pub current_schema_version: i64,
+    // False when the backup comes from a newer version of the app
+    pub compatible: bool,
+    pub tables: Vec<TableCount>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RestoreReport {
+    pub preflight: RestorePreflight,
+    // Copy of the database as it was before the restore
+    pub rollback: BackupInfo,
+    pub schema_version: i64,
+}
+
+pub fn backup_file_name(created: &chrono::DateTime<Local>, schema_version: i64) -> String {
+    format!(
+        "sales_report_{}_v{}.db",
+        created.format("%Y%m%d_%H%M%S"),
+        schema_version
+    )
+}
+
+pub fn file_sha256(path: &Path) -> Result<(u64, String), String> {
+    let mut file = File::open(path).map_err(|e| format!("Failed to read backup: {}", e))?;
+    let mut hasher = Sha256::new();
+    let mut buffer = [0u8; 64 * 1024];
+    let mut size = 0u64;
+    loop {
+        let read = file
+            .read(&mut buffer)
+            .map_err(|e| format!("Failed to read backup: {}", e))?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buffer[..read]);
+        size += read as u64;
+    }
+    Ok((size, format!("{:x}", hasher.finalize())))
+}
+
+// Scratch file for staging a backup outside the destination folder
+pub(crate) fn temp_path(label: &str) -> PathBuf {
+    let stamp = Local::now().format("%Y%m%d%H%M%S%f");
+    std::env::temp_dir().join(format!("sales_report_{}_{}_{}.db", label, std::process::id(), stamp))
+}
+
+fn check_integrity(path: &Path) -> Result<(), String> {
+    let conn = encryption::open(path, OpenFlags::SQLITE_OPEN_READ_ONLY)?;
+    let result: String = conn
+        .query_row("PRAGMA integrity_check", [], |row| row.get(0))
+        .map_err(|e| e.to_string())?;
+    if result != "ok" {
+        return Err(format!("Backup failed integrity check: {}", result));
+    }
+    Ok(())
+}
+
+// Runs the online backup a step at a time, stopping between steps once `cancel` is set
+fn copy_pages(
+    source: &Connection,
+    target: &mut Connection,
+    cancel: Option<&CancelToken>,
+) -> Result<(), String> {
+    let backup = Backup::new(source, target).map_err(|e| format!("Backup failed: {}", e))?;
+    loop {
+        if let Some(cancel) = cancel {
+            cancel.check()?;
+        }
+        match backup
+            .step(PAGES_PER_STEP)
+            .map_err(|e| format!("Backup failed: {}", e))?
+        {
+            StepResult::Done => return Ok(()),
+            _ => std::thread::sleep(STEP_PAUSE),
+        }
+    }
+}
+
+// Copies the live database through SQLite's online backup API into a new file in `dir`. The
+// copy is written under a temporary name and only renamed once it passes an integrity check,
+// so a cancelled or failed backup leaves nothing behind.
+pub fn create_backup(
+    conn: &Connection,
+    dir: &Path,
+    cancel: Option<&CancelToken>,
+) -> Result<BackupInfo, AppError> {
+    if !dir.is_dir() {
+        return Err(AppError::invalid(format!(
+            "Backup folder {} does not exist",
+            dir.display()
+        )));
+    }
+    let created = Local::now();
+    let schema_version = migrations::current_version(conn)?;
+    let file_name = backup_file_name(&created, schema_version);
+    let path = dir.join(&file_name);
+    if path.exists() {
+        return Err(AppError::invalid(format!(
+            "{} already exists",
+            path.display()
+        )));
+    }
+    let partial = PathBuf::from(format!("{}.partial", path.display()));
+
+    let copied = (|| {
+        let mut target =
+            Connection::open(&partial).map_err(|e| format!("Failed to create backup: {}", e))?;
+        // SQLite can only copy between databases with the same key
+        if let Some(key) = encryption::current_key() {
+            encryption::apply_key(&target, &key).map_err(|e| e.to_string())?;
+        }
+        copy_pages(conn, &mut target, cancel)?;
+        drop(target);
+        check_integrity(&partial)?;
+        std::fs::rename(&partial, &path).map_err(|e| format!("Failed to save backup: {}", e))
+    })();
+    if let Err(e) = copied {
+        let _ = std::fs::remove_file(&partial);
+        return Err(e.into());
+    }
+
+    let (size_bytes, sha256) = file_sha256(&path)?;
+    Ok(BackupInfo {
+        path: path.display().to_string(),
+        file_name,
+        schema_version,
+        size_bytes,
+        sha256,
+        encrypted: false,
+        created_at: created.format("%Y-%m-%d %H:%M:%S").to_string(),
+    })
+}
+
+// Takes a plain backup in a scratch folder, then writes it to `dir` as an encrypted archive
+pub fn create_encrypted_backup(
+    conn: &Connection,
+    dir: &Path,
+    passphrase: &str,
+    cancel: Option<&CancelToken>,
+) -> Result<BackupInfo, AppError> {
+    if !dir.is_dir() {
+        return Err(AppError::invalid(format!(
+            "Backup folder {} does not exist",
+            dir.display()
+        )));
+    }
+    let staging = temp_path("backup");
+    std::fs::create_dir(&staging).map_err(|e| format!("Failed to stage backup: {}", e))?;
+    let sealed = create_backup(conn, &staging, cancel).and_then(|plain| {
+        let database =
+            std::fs::read(&plain.path).map_err(|e| format!("Failed to read backup: {}", e))?;
+        Ok((plain, backup_archive::seal(&database, passphrase)?))
+    });
+    let _ = std::fs::remove_dir_all(&staging);
+    let (plain, archive) = sealed?;
+    // Sealing is the slow part, so look again before writing anything to `dir`
+    if let Some(cancel) = cancel {
+        cancel.check()?;
+    }
+
+    let file_name = Path::new(&plain.file_name)
+        .with_extension(ARCHIVE_EXTENSION)
+        .display()
+        .to_string();
+    let path = dir.join(&file_name);
+    if path.exists() {
+        return Err(AppError::invalid(format!(
+            "{} already exists",
+            path.display()
+        )));
+    }
+    let partial = PathBuf::from(format!("{}.partial", path.display()));
+    std::fs::write(&partial, &archive)
+        .and_then(|_| std::fs::rename(&partial, &path))
+        .map_err(|e| {
+            let _ = std::fs::remove_file(&partial);
+            format!("Failed to save backup: {}", e)
+        })?;
+
+    let (size_bytes, sha256) = file_sha256(&path)?;
+    Ok(BackupInfo {
+        path: path.display().to_string(),
+        file_name,
+        size_bytes,
+        sha256,
+        encrypted: true,
+        ..plain
+    })
+}
+
+// Candidate database for a restore: the chosen file itself, or a decrypted scratch copy of an
+// encrypted archive that is removed once the restore is over
+struct StagedBackup {
+    path: PathBuf,
+    temporary: bool,
+}
+
+impl Drop for StagedBackup {
+    fn drop(&mut self) {
+        if self.temporary {
+            let _ = std::fs::remove_file(&self.path);
+        }
+    }
+}
+
+fn stage(path: &Path, passphrase: Option<&str>) -> Result<(StagedBackup, bool), AppError> {
+    let (staged, encrypted) = unpack(path, passphrase)?;
+    // A backup taken before encryption was enabled is encrypted to match the live database
+    let Some(key) = encryption::current_key() else {
+        return Ok((staged, encrypted));
+    };
+    if !encryption::is_plain_database(&staged.path)? {
+        return Ok((staged, encrypted));
+    }
+    let converted = StagedBackup {
+        path: temp_path("restore"),
+        temporary: true,
+    };
+    encryption::export_encrypted(&staged.path, &converted.path, &key)?;
+    Ok((converted, encrypted))
+}
+
+fn unpack(path: &Path, passphrase: Option<&str>) -> Result<(StagedBackup, bool), AppError> {
+    let bytes = std::fs::read(path).map_err(|e| format!("Failed to read backup: {}", e))?;
+    if !backup_archive::is_archive(&bytes) {
+        let staged = StagedBackup {
+            path: path.to_path_buf(),
+            temporary: false,
+        };
+        return Ok((staged, false));
+    }
+    let passphrase = passphrase
+        .filter(|passphrase| !passphrase.is_empty())
+        .ok_or_else(|| {
+            AppError::validation(
+                "passphrase",
+                "This backup is encrypted; enter its passphrase",
+            )
+        })?;
+    let database = backup_archive::open(&bytes, passphrase)?;
+    let staged = StagedBackup {
+        path: temp_path("restore"),
+        temporary: true,
+    };
+    std::fs::write(&staged.path, &database)
+        .map_err(|e| format!("Failed to stage backup: {}", e))?;
+    Ok((staged, true))
+}
+
+// Reads the version without creating the version table, so it works on read-only connections
+fn schema_version_of(conn: &Connection) -> Result<i64, String> {
+    let tracked: bool = conn
+        .query_row(
+            "SELECT EXISTS (SELECT 1 FROM sqlite_master
+             WHERE type = 'table' AND name = 'schema_version')",
+            [],
+            |row| row.get(0),
+        )
+        .map_err(|e| e.to_string())?;
+    if !tracked {
+        return Ok(0);
+    }
+    conn.query_row("SELECT COALESCE(MAX(version), 0) FROM schema_version", [], |row| {
+        row.get(0)
+    })
+    .map_err(|e| e.to_string())
+}
+
+fn table_counts(conn: &Connection) -> Result<Vec<(String, i64)>, String> {
+    let mut stmt = conn
+        .prepare(
+            "SELECT name FROM sqlite_master
+             WHERE type = 'table' AND name NOT LIKE 'sqlite_%'
+             ORDER BY name",
+        )
+        .map_err(|e| e.to_string())?;
+    let tables = stmt
+        .query_map([], |row| row.get::<_, String>(0))
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+    tables
+        .into_iter()
+        .map(|table| {
+            let count = conn
+                .query_row(&format!("SELECT COUNT(*) FROM \"{}\"", table), [], |row| row.get(0))
+                .map_err(|e| e.to_string())?;
+            Ok((table, count))
+        })
+        .collect()
+}
+
+// Opens the candidate read-only and checks it without touching the live database
+fn inspect(
+    conn: &Connection,
+    path: &Path,
+    passphrase: Option<&str>,
+) -> Result<(StagedBackup, RestorePreflight), AppError> {
+    let (staged, encrypted) = stage(path, passphrase)?;
+    check_integrity(&staged.path)?;
+    let source = encryption::open(&staged.path, OpenFlags::SQLITE_OPEN_READ_ONLY)?;
+    let backup_schema_version = schema_version_of(&source)?;
+
+    let current: HashMap<String, i64> = table_counts(conn)?.into_iter().collect();
+    let tables = table_counts(&source)?
+        .into_iter()
+        .map(|(table, backup_rows)| TableCount {
+            current_rows: current.get(&table).copied(),
+            table,
+            backup_rows,
+        })
+        .collect();
+
+    let preflight = RestorePreflight {
+        file: path.display().to_string(),
+        encrypted,
+        backup_schema_version,
+        current_schema_version: migrations::current_version(conn)?,
+        compatible: backup_schema_version <= migrations::latest_version(),
+        tables,
+    };
+    Ok((staged, preflight))
+}
+
+// Replaces the live database with a checked backup. The current data is first saved to
+// `rollback_dir`; the copy into the live database then runs as a single SQLite backup, so
+// other connections see either the old data or the new, and the result is migrated forward.
+pub fn restore_from(
+    conn: &mut Connection,
+    path: &Path,
+    passphrase: Option<&str>,
+    rollback_dir: &Path,
+) -> Result<RestoreReport, AppError> {
+    let (staged, preflight) = inspect(conn, path, passphrase)?;
+    if !preflight.compatible {
+        return Err(AppError::invalid(
+            "This backup was made by a newer version of the app",
+        ));
+    }
+
+    std::fs::create_dir_all(rollback_dir)
+        .map_err(|e| format!("Failed to create rollback folder: {}", e))?;
+    let rollback = create_backup(conn, rollback_dir, None)?;
+    backup_schedule::prune_backups(rollback_dir, ROLLBACK_COPIES)?;
+
+    let source = encryption::open(&staged.path, OpenFlags::SQLITE_OPEN_READ_ONLY)?;
+    Backup::new(&source, conn)
+        .and_then(|backup| backup.run_to_completion(PAGES_PER_STEP, STEP_PAUSE, None))
+        .map_err(|e| format!("Restore failed: {}", e))?;
+    drop(source);
+
+    migrations::run_pending(conn)?;
+    Ok(RestoreReport {
+        preflight,
+        rollback,
+        schema_version: migrations::current_version(conn)?,
+    })
+}
+
+pub(crate) fn rollback_dir(app: &AppHandle) -> Result<PathBuf, String> {
+    let database = db::database_path(app)?;
+    let dir = database.parent().unwrap_or_else(|| Path::new("."));
+    Ok(dir.join(ROLLBACK_FOLDER))
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn backup_database(
+    pool: State<'_, DbPool>,
+    cancellations: State<'_, Cancellations>,
+    destination: String,
+    passphrase: Option<String>,
+    operation_id: Option<String>,
+) -> Result<BackupInfo, AppError> {
+    let destination = destination.trim();
+    if destination.is_empty() {
+        return Err(AppError::validation(
+            "destination",
+            "Choose a folder for the backup",
+        ));
+    }
+    let operation = cancellations.start(operation_id.as_deref());
+    let cancel = Some(&operation.token);
+    let conn = db::get_conn(&pool)?;
+    let created = match passphrase.filter(|passphrase| !passphrase.is_empty()) {
+        Some(passphrase) => {
+            create_encrypted_backup(&conn, Path::new(destination), &passphrase, cancel)
+        }
+        None => create_backup(&conn, Path::new(destination), cancel),
+    };
+    created.map_err(|e| operation.token.map_err(e))
+}
+
+// Pre-flight report shown to the user before they confirm a restore
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn inspect_backup(
+    pool: State<'_, DbPool>,
+    path: String,
+    passphrase: Option<String>,
+) -> Result<RestorePreflight, AppError> {
+    let conn = db::get_conn(&pool)?;
+    let (_, preflight) = inspect(&conn, Path::new(path.trim()), passphrase.as_deref())?;
+    Ok(preflight)
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn restore_backup(
+    app: AppHandle,
+    pool: State<'_, DbPool>,
+    cache: State<'_, DashboardCache>,
+    path: String,
+    passphrase: Option<String>,
+) -> Result<RestoreReport, AppError> {
+    let rollback_dir = rollback_dir(&app)?;
+    let mut conn = db::get_conn(&pool)?;
+    let report = restore_from(
+        &mut conn,
+        Path::new(path.trim()),
+        passphrase.as_deref(),
+        &rollback_dir,
+    )?;
+    cache.clear();
+    Ok(report)
+}
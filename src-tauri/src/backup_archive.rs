@@ -0,0 +1,105 @@
+use aes_gcm::aead::{Aead, KeyInit, Payload};
+use aes_gcm::{Aes256Gcm, Nonce};
+use argon2::password_hash::rand_core::{OsRng, RngCore};
+use argon2::Argon2;
+
+use crate::error::AppError;
+
+// Encrypted backup layout: MAGIC | format version | salt | nonce | AES-256-GCM ciphertext of the
+// zstd-compressed database. The header is authenticated along with the ciphertext.
+const MAGIC: &[u8; 8] = b"SRBACKUP";
+const FORMAT_VERSION: u8 = 1;
+const SALT_LEN: usize = 16;
+const NONCE_LEN: usize = 12;
+const HEADER_LEN: usize = MAGIC.len() + 1 + SALT_LEN + NONCE_LEN;
+const COMPRESSION_LEVEL: i32 = 9;
+const MIN_PASSPHRASE_LENGTH: usize = 8;
+
+pub const ARCHIVE_EXTENSION: &str = "srbk";
+
+pub fn is_archive(bytes: &[u8]) -> bool {
+    bytes.starts_with(MAGIC)
+}
+
+fn derive_key(passphrase: &str, salt: &[u8]) -> Result<[u8; 32], String> {
+    let mut key = [0u8; 32];
+    Argon2::default()
+        .hash_password_into(passphrase.as_bytes(), salt, &mut key)
+        .map_err(|e| format!("Failed to derive backup key: {}", e))?;
+    Ok(key)
+}
+
+pub fn seal(database: &[u8], passphrase: &str) -> Result<Vec<u8>, AppError> {
+    if passphrase.chars().count() < MIN_PASSPHRASE_LENGTH {
+        return Err(AppError::validation(
+            "passphrase",
+            format!(
+                "Backup passphrase must be at least {} characters",
+                MIN_PASSPHRASE_LENGTH
+            ),
+        ));
+    }
+    let compressed = zstd::encode_all(database, COMPRESSION_LEVEL)
+        .map_err(|e| format!("Failed to compress backup: {}", e))?;
+
+    let mut header = Vec::with_capacity(HEADER_LEN);
+    header.extend_from_slice(MAGIC);
+    header.push(FORMAT_VERSION);
+    let mut salt = [0u8; SALT_LEN];
+    let mut nonce = [0u8; NONCE_LEN];
+    OsRng.fill_bytes(&mut salt);
+    OsRng.fill_bytes(&mut nonce);
+    header.extend_from_slice(&salt);
+    header.extend_from_slice(&nonce);
+
+    let key = derive_key(passphrase, &salt)?;
+    let cipher = Aes256Gcm::new_from_slice(&key).map_err(|e| e.to_string())?;
+    let ciphertext = cipher
+        .encrypt(
+            Nonce::from_slice(&nonce),
+            Payload {
+                msg: &compressed,
+                aad: &header,
+            },
+        )
+        .map_err(|_| AppError::internal("Failed to encrypt backup"))?;
+
+    header.extend_from_slice(&ciphertext);
+    Ok(header)
+}
+
+// Decryption fails on a wrong passphrase and on any change to the file, so a database is only
+// returned when the archive is exactly what was written
+pub fn open(archive: &[u8], passphrase: &str) -> Result<Vec<u8>, AppError> {
+    if !is_archive(archive) || archive.len() <= HEADER_LEN {
+        return Err(AppError::invalid("Not an encrypted backup file"));
+    }
+    let (header, ciphertext) = archive.split_at(HEADER_LEN);
+    if header[MAGIC.len()] != FORMAT_VERSION {
+        return Err(AppError::invalid(format!(
+            "Unsupported backup format version {}",
+            header[MAGIC.len()]
+        )));
+    }
+    let salt = &header[MAGIC.len() + 1..MAGIC.len() + 1 + SALT_LEN];
+    let nonce = &header[MAGIC.len() + 1 + SALT_LEN..];
+
+    let key = derive_key(passphrase, salt)?;
+    let cipher = Aes256Gcm::new_from_slice(&key).map_err(|e| e.to_string())?;
+    let compressed = cipher
+        .decrypt(
+            Nonce::from_slice(nonce),
+            Payload {
+                msg: ciphertext,
+                aad: header,
+            },
+        )
+        .map_err(|_| {
+            AppError::validation(
+                "passphrase",
+                "Wrong passphrase or the backup file is damaged",
+            )
+        })?;
+    zstd::decode_all(compressed.as_slice())
+        .map_err(|e| AppError::internal(format!("Failed to decompress backup: {}", e)))
+}
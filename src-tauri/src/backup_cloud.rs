@@ -0,0 +1,831 @@
+use std::path::Path;
+use std::time::Duration;
+
+use chrono::Utc;
+use hmac::{Hmac, Mac};
+use quick_xml::events::Event;
+use quick_xml::Reader;
+use rusqlite::Connection;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use tauri::{AppHandle, State};
+
+use crate::backup::{self, BackupInfo, RestoreReport};
+use crate::backup_archive::ARCHIVE_EXTENSION;
+use crate::dashboard::DashboardCache;
+use crate::db::{self, DbPool};
+use crate::encryption;
+use crate::error::AppError;
+use crate::settings;
+
+const SETTING_PROVIDER: &str = "cloud_backup_provider";
+const SETTING_S3_ENDPOINT: &str = "cloud_backup_s3_endpoint";
+const SETTING_S3_REGION: &str = "cloud_backup_s3_region";
+const SETTING_S3_BUCKET: &str = "cloud_backup_s3_bucket";
+const SETTING_S3_PREFIX: &str = "cloud_backup_s3_prefix";
+const SETTING_S3_ACCESS_KEY: &str = "cloud_backup_s3_access_key_id";
+const SETTING_DRIVE_FOLDER: &str = "cloud_backup_drive_folder_id";
+const SETTING_DRIVE_CLIENT_ID: &str = "cloud_backup_drive_client_id";
+// Secrets live in the OS keyring, never in app_settings
+const S3_SECRET_ENTRY: &str = "cloud-backup-s3-secret-key";
+const DRIVE_CLIENT_SECRET_ENTRY: &str = "cloud-backup-drive-client-secret";
+const DRIVE_REFRESH_TOKEN_ENTRY: &str = "cloud-backup-drive-refresh-token";
+
+const DEFAULT_S3_REGION: &str = "us-east-1";
+const DRIVE_TOKEN_URL: &str = "https://oauth2.googleapis.com/token";
+const DRIVE_FILES_URL: &str = "https://www.googleapis.com/drive/v3/files";
+const DRIVE_UPLOAD_URL: &str = "https://www.googleapis.com/upload/drive/v3/files";
+const UPLOAD_BOUNDARY: &str = "sales_report_backup_boundary";
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10 * 60);
+const MAX_ERROR_LENGTH: usize = 500;
+
+type HmacSha256 = Hmac<Sha256>;
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum CloudProvider {
+    #[default]
+    None,
+    S3,
+    GoogleDrive,
+}
+
+impl CloudProvider {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CloudProvider::None => "none",
+            CloudProvider::S3 => "s3",
+            CloudProvider::GoogleDrive => "google_drive",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "none" => Some(CloudProvider::None),
+            "s3" => Some(CloudProvider::S3),
+            "google_drive" => Some(CloudProvider::GoogleDrive),
+            _ => None,
+        }
+    }
+}
+
+impl settings::SettingValue for CloudProvider {
+    fn to_setting(&self) -> String {
+        self.as_str().to_string()
+    }
+
+    fn from_setting(value: &str) -> Option<Self> {
+        CloudProvider::parse(value)
+    }
+}
+
+// Remote backup target; secrets are only ever written, never returned
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct CloudBackupSettings {
+    pub provider: CloudProvider,
+    // Any S3-compatible service, e.g. https://s3.ap-south-1.amazonaws.com or a MinIO server
+    pub s3_endpoint: Option<String>,
+    pub s3_region: String,
+    pub s3_bucket: Option<String>,
+    pub s3_prefix: Option<String>,
+    pub s3_access_key_id: Option<String>,
+    pub has_s3_secret_key: bool,
+    pub drive_folder_id: Option<String>,
+    pub drive_client_id: Option<String>,
+    pub has_drive_client_secret: bool,
+    pub has_drive_refresh_token: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SaveCloudBackupSettings {
+    pub provider: CloudProvider,
+    pub s3_endpoint: Option<String>,
+    pub s3_region: Option<String>,
+    pub s3_bucket: Option<String>,
+    pub s3_prefix: Option<String>,
+    pub s3_access_key_id: Option<String>,
+    // For each secret, None keeps the saved value and an empty string removes it
+    pub s3_secret_key: Option<String>,
+    pub drive_folder_id: Option<String>,
+    pub drive_client_id: Option<String>,
+    pub drive_client_secret: Option<String>,
+    pub drive_refresh_token: Option<String>,
+}
+
+// Backup file stored with the remote target
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct CloudBackup {
+    // Object key for S3, file id for Google Drive
+    pub id: String,
+    pub file_name: String,
+    pub size_bytes: u64,
+    pub modified_at: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CloudUpload {
+    pub backup: BackupInfo,
+    pub remote: CloudBackup,
+}
+
+struct S3Target {
+    endpoint: String,
+    region: String,
+    bucket: String,
+    prefix: String,
+    access_key_id: String,
+    secret_key: String,
+}
+
+struct DriveTarget {
+    folder_id: String,
+    client_id: String,
+    client_secret: String,
+    refresh_token: String,
+}
+
+enum CloudTarget {
+    S3(S3Target),
+    GoogleDrive(DriveTarget),
+}
+
+fn trimmed(value: Option<&str>) -> String {
+    value.map(str::trim).unwrap_or("").to_string()
+}
+
+pub fn load_settings(conn: &Connection) -> Result<CloudBackupSettings, String> {
+    Ok(CloudBackupSettings {
+        provider: settings::get(conn, SETTING_PROVIDER)?.unwrap_or_default(),
+        s3_endpoint: settings::get(conn, SETTING_S3_ENDPOINT)?,
+        s3_region: settings::get(conn, SETTING_S3_REGION)?
+            .unwrap_or_else(|| DEFAULT_S3_REGION.to_string()),
+        s3_bucket: settings::get(conn, SETTING_S3_BUCKET)?,
+        s3_prefix: settings::get(conn, SETTING_S3_PREFIX)?,
+        s3_access_key_id: settings::get(conn, SETTING_S3_ACCESS_KEY)?,
+        has_s3_secret_key: encryption::read_secret(S3_SECRET_ENTRY)?.is_some(),
+        drive_folder_id: settings::get(conn, SETTING_DRIVE_FOLDER)?,
+        drive_client_id: settings::get(conn, SETTING_DRIVE_CLIENT_ID)?,
+        has_drive_client_secret: encryption::read_secret(DRIVE_CLIENT_SECRET_ENTRY)?.is_some(),
+        has_drive_refresh_token: encryption::read_secret(DRIVE_REFRESH_TOKEN_ENTRY)?.is_some(),
+    })
+}
+
+fn save_secret(name: &str, value: Option<&str>) -> Result<(), String> {
+    match value {
+        Some("") => encryption::delete_secret(name),
+        Some(secret) => encryption::write_secret(name, secret),
+        None => Ok(()),
+    }
+}
+
+fn require(value: Option<String>, what: &str) -> Result<String, AppError> {
+    value.ok_or_else(|| AppError::invalid(format!("Set up the {} for cloud backups", what)))
+}
+
+fn load_target(conn: &Connection) -> Result<CloudTarget, AppError> {
+    let settings = load_settings(conn)?;
+    match settings.provider {
+        CloudProvider::None => Err(AppError::invalid("Choose a cloud backup target first")),
+        CloudProvider::S3 => Ok(CloudTarget::S3(S3Target {
+            endpoint: require(settings.s3_endpoint, "S3 endpoint")?
+                .trim_end_matches('/')
+                .to_string(),
+            region: settings.s3_region,
+            bucket: require(settings.s3_bucket, "S3 bucket")?,
+            prefix: settings.s3_prefix.unwrap_or_default(),
+            access_key_id: require(settings.s3_access_key_id, "S3 access key")?,
+            secret_key: require(encryption::read_secret(S3_SECRET_ENTRY)?, "S3 secret key")?,
+        })),
+        CloudProvider::GoogleDrive => Ok(CloudTarget::GoogleDrive(DriveTarget {
+            folder_id: require(settings.drive_folder_id, "Google Drive folder")?,
+            client_id: require(settings.drive_client_id, "Google Drive client ID")?,
+            client_secret: require(
+                encryption::read_secret(DRIVE_CLIENT_SECRET_ENTRY)?,
+                "Google Drive client secret",
+            )?,
+            refresh_token: require(
+                encryption::read_secret(DRIVE_REFRESH_TOKEN_ENTRY)?,
+                "Google Drive refresh token",
+            )?,
+        })),
+    }
+}
+
+fn is_backup_name(name: &str) -> bool {
+    name.starts_with("sales_report_") && name.ends_with(&format!(".{}", ARCHIVE_EXTENSION))
+}
+
+async fn check_response(
+    response: reqwest::Response,
+    action: &str,
+) -> Result<reqwest::Response, String> {
+    let status = response.status();
+    if status.is_success() {
+        return Ok(response);
+    }
+    let body = response.text().await.unwrap_or_default();
+    let body: String = body.chars().take(MAX_ERROR_LENGTH).collect();
+    Err(format!(
+        "{} failed with HTTP {}: {}",
+        action,
+        status,
+        body.trim()
+    ))
+}
+
+fn hex_sha256(bytes: &[u8]) -> String {
+    format!("{:x}", Sha256::digest(bytes))
+}
+
+fn hmac_sha256(key: &[u8], message: &str) -> Result<Vec<u8>, String> {
+    let mut mac = HmacSha256::new_from_slice(key).map_err(|e| e.to_string())?;
+    mac.update(message.as_bytes());
+    Ok(mac.finalize().into_bytes().to_vec())
+}
+
+// Percent-encodes everything except RFC 3986 unreserved characters, as SigV4 requires;
+// `/` is kept in object paths
+fn uri_encode(value: &str, keep_slash: bool) -> String {
+    value
+        .bytes()
+        .map(|byte| match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                (byte as char).to_string()
+            }
+            b'/' if keep_slash => "/".to_string(),
+            _ => format!("%{:02X}", byte),
+        })
+        .collect()
+}
+
+impl S3Target {
+    fn object_key(&self, file_name: &str) -> String {
+        let prefix = self.prefix.trim_matches('/');
+        if prefix.is_empty() {
+            file_name.to_string()
+        } else {
+            format!("{}/{}", prefix, file_name)
+        }
+    }
+
+    // Path-style request signed with AWS Signature Version 4, which S3-compatible services accept
+    fn request(
+        &self,
+        client: &reqwest::Client,
+        method: reqwest::Method,
+        key: Option<&str>,
+        query: &[(&str, &str)],
+        body: &[u8],
+    ) -> Result<reqwest::RequestBuilder, String> {
+        let url = reqwest::Url::parse(&self.endpoint)
+            .map_err(|_| format!("S3 endpoint {} is not a valid URL", self.endpoint))?;
+        let host = match (url.host_str(), url.port()) {
+            (Some(host), Some(port)) => format!("{}:{}", host, port),
+            (Some(host), None) => host.to_string(),
+            (None, _) => return Err(format!("S3 endpoint {} has no host", self.endpoint)),
+        };
+        let mut path = format!("/{}", uri_encode(&self.bucket, false));
+        if let Some(key) = key {
+            path.push('/');
+            path.push_str(&uri_encode(key, true));
+        }
+        let mut pairs: Vec<(String, String)> = query
+            .iter()
+            .map(|(name, value)| (uri_encode(name, false), uri_encode(value, false)))
+            .collect();
+        pairs.sort();
+        let canonical_query = pairs
+            .iter()
+            .map(|(name, value)| format!("{}={}", name, value))
+            .collect::<Vec<_>>()
+            .join("&");
+
+        let now = Utc::now();
+        let amz_date = now.format("%Y%m%dT%H%M%SZ").to_string();
+        let date = now.format("%Y%m%d").to_string();
+        let payload_hash = hex_sha256(body);
+        let canonical_request = format!(
+            "{}\n{}\n{}\nhost:{}\nx-amz-content-sha256:{}\nx-amz-date:{}\n\n{}\n{}",
+            method.as_str(),
+            path,
+            canonical_query,
+            host,
+            payload_hash,
+            amz_date,
+            "host;x-amz-content-sha256;x-amz-date",
+            payload_hash
+        );
+        let scope = format!("{}/{}/s3/aws4_request", date, self.region);
+        let string_to_sign = format!(
+            "AWS4-HMAC-SHA256\n{}\n{}\n{}",
+            amz_date,
+            scope,
+            hex_sha256(canonical_request.as_bytes())
+        );
+        let mut signing_key = format!("AWS4{}", self.secret_key).into_bytes();
+        for part in [date.as_str(), self.region.as_str(), "s3", "aws4_request"] {
+            signing_key = hmac_sha256(&signing_key, part)?;
+        }
+        let signature = hmac_sha256(&signing_key, &string_to_sign)?
+            .iter()
+            .map(|byte| format!("{:02x}", byte))
+            .collect::<String>();
+        let authorization = format!(
+            "AWS4-HMAC-SHA256 Credential={}/{}, \
+             SignedHeaders=host;x-amz-content-sha256;x-amz-date, Signature={}",
+            self.access_key_id, scope, signature
+        );
+
+        let mut request_url = format!("{}://{}{}", url.scheme(), host, path);
+        if !canonical_query.is_empty() {
+            request_url.push('?');
+            request_url.push_str(&canonical_query);
+        }
+        Ok(client
+            .request(method, request_url)
+            .header("x-amz-content-sha256", payload_hash)
+            .header("x-amz-date", amz_date)
+            .header(reqwest::header::AUTHORIZATION, authorization)
+            .body(body.to_vec()))
+    }
+
+    async fn upload(
+        &self,
+        client: &reqwest::Client,
+        file_name: &str,
+        bytes: &[u8],
+    ) -> Result<CloudBackup, String> {
+        let key = self.object_key(file_name);
+        let response = self
+            .request(client, reqwest::Method::PUT, Some(&key), &[], bytes)?
+            .send()
+            .await
+            .map_err(|e| format!("Upload request failed: {}", e))?;
+        check_response(response, "Upload").await?;
+        Ok(CloudBackup {
+            id: key,
+            file_name: file_name.to_string(),
+            size_bytes: bytes.len() as u64,
+            modified_at: Some(Utc::now().format("%Y-%m-%d %H:%M:%S").to_string()),
+        })
+    }
+
+    async fn list(&self, client: &reqwest::Client) -> Result<Vec<CloudBackup>, String> {
+        let prefix = match self.prefix.trim_matches('/') {
+            "" => String::new(),
+            prefix => format!("{}/", prefix),
+        };
+        let mut backups = Vec::new();
+        let mut continuation: Option<String> = None;
+        loop {
+            let mut query = vec![("list-type", "2"), ("prefix", prefix.as_str())];
+            if let Some(token) = &continuation {
+                query.push(("continuation-token", token.as_str()));
+            }
+            let response = self
+                .request(client, reqwest::Method::GET, None, &query, &[])?
+                .send()
+                .await
+                .map_err(|e| format!("Listing request failed: {}", e))?;
+            let xml = check_response(response, "Listing backups")
+                .await?
+                .text()
+                .await
+                .map_err(|e| format!("Failed to read the backup listing: {}", e))?;
+            let (page, next) = parse_s3_listing(&xml)?;
+            backups.extend(page);
+            match next {
+                Some(token) => continuation = Some(token),
+                None => break,
+            }
+        }
+        Ok(backups)
+    }
+
+    async fn download(&self, client: &reqwest::Client, key: &str) -> Result<Vec<u8>, String> {
+        let response = self
+            .request(client, reqwest::Method::GET, Some(key), &[], &[])?
+            .send()
+            .await
+            .map_err(|e| format!("Download request failed: {}", e))?;
+        let bytes = check_response(response, "Download")
+            .await?
+            .bytes()
+            .await
+            .map_err(|e| format!("Failed to download backup: {}", e))?;
+        Ok(bytes.to_vec())
+    }
+}
+
+// Reads one ListObjectsV2 page; returns the backups on it and the token for the next page
+fn parse_s3_listing(xml: &str) -> Result<(Vec<CloudBackup>, Option<String>), String> {
+    let mut reader = Reader::from_str(xml);
+    reader.config_mut().trim_text(true);
+
+    let mut backups = Vec::new();
+    let mut current: Option<CloudBackup> = None;
+    let mut next_token = None;
+    let mut truncated = false;
+    let mut tag = String::new();
+
+    loop {
+        match reader.read_event() {
+            Ok(Event::Start(e)) => {
+                tag = String::from_utf8_lossy(e.local_name().as_ref()).to_string();
+                if tag == "Contents" {
+                    current = Some(CloudBackup {
+                        id: String::new(),
+                        file_name: String::new(),
+                        size_bytes: 0,
+                        modified_at: None,
+                    });
+                }
+            }
+            Ok(Event::End(e)) => {
+                if e.local_name().as_ref() == b"Contents" {
+                    if let Some(backup) = current.take().filter(|b| is_backup_name(&b.file_name)) {
+                        backups.push(backup);
+                    }
+                }
+                tag.clear();
+            }
+            Ok(Event::Text(e)) => {
+                let text = e
+                    .unescape()
+                    .map_err(|e| format!("Invalid S3 listing: {}", e))?
+                    .into_owned();
+                match (tag.as_str(), current.as_mut()) {
+                    ("Key", Some(backup)) => {
+                        backup.file_name = text.rsplit('/').next().unwrap_or("").to_string();
+                        backup.id = text;
+                    }
+                    ("Size", Some(backup)) => backup.size_bytes = text.parse().unwrap_or(0),
+                    ("LastModified", Some(backup)) => backup.modified_at = Some(text),
+                    ("IsTruncated", None) => truncated = text == "true",
+                    ("NextContinuationToken", None) => next_token = Some(text),
+                    _ => {}
+                }
+            }
+            Ok(Event::Eof) => break,
+            Ok(_) => {}
+            Err(e) => return Err(format!("Invalid S3 listing: {}", e)),
+        }
+    }
+    Ok((backups, next_token.filter(|_| truncated)))
+}
+
+impl DriveTarget {
+    // Exchanges the saved refresh token for a short-lived access token
+    async fn access_token(&self, client: &reqwest::Client) -> Result<String, String> {
+        let response = client
+            .post(DRIVE_TOKEN_URL)
+            .form(&[
+                ("client_id", self.client_id.as_str()),
+                ("client_secret", self.client_secret.as_str()),
+                ("refresh_token", self.refresh_token.as_str()),
+                ("grant_type", "refresh_token"),
+            ])
+            .send()
+            .await
+            .map_err(|e| format!("Google sign-in request failed: {}", e))?;
+        let body: Value = check_response(response, "Google sign-in")
+            .await?
+            .json()
+            .await
+            .map_err(|e| format!("Google sign-in returned invalid JSON: {}", e))?;
+        body.get("access_token")
+            .and_then(Value::as_str)
+            .map(str::to_string)
+            .ok_or_else(|| "Google sign-in did not return an access token".to_string())
+    }
+
+    async fn upload(
+        &self,
+        client: &reqwest::Client,
+        file_name: &str,
+        bytes: &[u8],
+    ) -> Result<CloudBackup, String> {
+        let token = self.access_token(client).await?;
+        let metadata = serde_json::json!({ "name": file_name, "parents": [self.folder_id] });
+        let mut body = format!(
+            "--{boundary}\r\nContent-Type: application/json; charset=UTF-8\r\n\r\n{metadata}\r\n\
+             --{boundary}\r\nContent-Type: application/octet-stream\r\n\r\n",
+            boundary = UPLOAD_BOUNDARY,
+            metadata = metadata
+        )
+        .into_bytes();
+        body.extend_from_slice(bytes);
+        body.extend_from_slice(format!("\r\n--{}--\r\n", UPLOAD_BOUNDARY).as_bytes());
+
+        let response = client
+            .post(DRIVE_UPLOAD_URL)
+            .query(&[
+                ("uploadType", "multipart"),
+                ("fields", "id,name,size,modifiedTime"),
+            ])
+            .bearer_auth(&token)
+            .header(
+                reqwest::header::CONTENT_TYPE,
+                format!("multipart/related; boundary={}", UPLOAD_BOUNDARY),
+            )
+            .body(body)
+            .send()
+            .await
+            .map_err(|e| format!("Upload request failed: {}", e))?;
+        let file: Value = check_response(response, "Upload")
+            .await?
+            .json()
+            .await
+            .map_err(|e| format!("Google Drive returned invalid JSON: {}", e))?;
+        drive_file(&file).ok_or_else(|| "Google Drive did not return the uploaded file".to_string())
+    }
+
+    async fn list(&self, client: &reqwest::Client) -> Result<Vec<CloudBackup>, String> {
+        let token = self.access_token(client).await?;
+        let filter = format!(
+            "'{}' in parents and trashed = false",
+            self.folder_id.replace('\\', "\\\\").replace('\'', "\\'")
+        );
+        let mut backups = Vec::new();
+        let mut page_token: Option<String> = None;
+        loop {
+            let mut query = vec![
+                ("q", filter.as_str()),
+                ("fields", "nextPageToken,files(id,name,size,modifiedTime)"),
+                ("pageSize", "1000"),
+            ];
+            if let Some(page) = &page_token {
+                query.push(("pageToken", page.as_str()));
+            }
+            let response = client
+                .get(DRIVE_FILES_URL)
+                .query(&query)
+                .bearer_auth(&token)
+                .send()
+                .await
+                .map_err(|e| format!("Listing request failed: {}", e))?;
+            let body: Value = check_response(response, "Listing backups")
+                .await?
+                .json()
+                .await
+                .map_err(|e| format!("Google Drive returned invalid JSON: {}", e))?;
+            let files = body.get("files").and_then(Value::as_array);
+            backups.extend(
+                files
+                    .into_iter()
+                    .flatten()
+                    .filter_map(drive_file)
+                    .filter(|backup| is_backup_name(&backup.file_name)),
+            );
+            match body.get("nextPageToken").and_then(Value::as_str) {
+                Some(next) => page_token = Some(next.to_string()),
+                None => break,
+            }
+        }
+        Ok(backups)
+    }
+
+    async fn download(&self, client: &reqwest::Client, file_id: &str) -> Result<Vec<u8>, String> {
+        let token = self.access_token(client).await?;
+        let response = client
+            .get(format!(
+                "{}/{}",
+                DRIVE_FILES_URL,
+                uri_encode(file_id, false)
+            ))
+            .query(&[("alt", "media")])
+            .bearer_auth(&token)
+            .send()
+            .await
+            .map_err(|e| format!("Download request failed: {}", e))?;
+        let bytes = check_response(response, "Download")
+            .await?
+            .bytes()
+            .await
+            .map_err(|e| format!("Failed to download backup: {}", e))?;
+        Ok(bytes.to_vec())
+    }
+}
+
+fn drive_file(file: &Value) -> Option<CloudBackup> {
+    Some(CloudBackup {
+        id: file.get("id")?.as_str()?.to_string(),
+        file_name: file.get("name")?.as_str()?.to_string(),
+        // Drive reports sizes as strings
+        size_bytes: file
+            .get("size")
+            .and_then(Value::as_str)
+            .and_then(|size| size.parse().ok())
+            .unwrap_or(0),
+        modified_at: file
+            .get("modifiedTime")
+            .and_then(Value::as_str)
+            .map(str::to_string),
+    })
+}
+
+impl CloudTarget {
+    async fn upload(
+        &self,
+        client: &reqwest::Client,
+        file_name: &str,
+        bytes: &[u8],
+    ) -> Result<CloudBackup, String> {
+        match self {
+            CloudTarget::S3(target) => target.upload(client, file_name, bytes).await,
+            CloudTarget::GoogleDrive(target) => target.upload(client, file_name, bytes).await,
+        }
+    }
+
+    // Newest first; backup names sort by the time they were taken
+    async fn list(&self, client: &reqwest::Client) -> Result<Vec<CloudBackup>, String> {
+        let mut backups = match self {
+            CloudTarget::S3(target) => target.list(client).await?,
+            CloudTarget::GoogleDrive(target) => target.list(client).await?,
+        };
+        backups.sort_by(|a, b| b.file_name.cmp(&a.file_name));
+        Ok(backups)
+    }
+
+    async fn download(&self, client: &reqwest::Client, id: &str) -> Result<Vec<u8>, String> {
+        match self {
+            CloudTarget::S3(target) => target.download(client, id).await,
+            CloudTarget::GoogleDrive(target) => target.download(client, id).await,
+        }
+    }
+}
+
+fn http_client() -> Result<reqwest::Client, String> {
+    reqwest::Client::builder()
+        .timeout(REQUEST_TIMEOUT)
+        .build()
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn get_cloud_backup_settings(
+    pool: State<'_, DbPool>,
+) -> Result<CloudBackupSettings, AppError> {
+    let conn = db::get_conn(&pool)?;
+    Ok(load_settings(&conn)?)
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn set_cloud_backup_settings(
+    pool: State<'_, DbPool>,
+    cloud: SaveCloudBackupSettings,
+) -> Result<CloudBackupSettings, AppError> {
+    let endpoint = trimmed(cloud.s3_endpoint.as_deref());
+    if cloud.provider == CloudProvider::S3 {
+        if !endpoint.starts_with("https://") && !endpoint.starts_with("http://") {
+            return Err(AppError::validation(
+                "s3_endpoint",
+                "S3 endpoint must start with http:// or https://",
+            ));
+        }
+        if trimmed(cloud.s3_bucket.as_deref()).is_empty() {
+            return Err(AppError::validation("s3_bucket", "S3 bucket is required"));
+        }
+    }
+    if cloud.provider == CloudProvider::GoogleDrive
+        && trimmed(cloud.drive_folder_id.as_deref()).is_empty()
+    {
+        return Err(AppError::validation(
+            "drive_folder_id",
+            "Google Drive folder is required",
+        ));
+    }
+    let region = match trimmed(cloud.s3_region.as_deref()) {
+        region if region.is_empty() => DEFAULT_S3_REGION.to_string(),
+        region => region,
+    };
+
+    let conn = db::get_conn(&pool)?;
+    settings::put(&conn, SETTING_PROVIDER, &cloud.provider)?;
+    settings::put(&conn, SETTING_S3_ENDPOINT, &endpoint)?;
+    settings::put(&conn, SETTING_S3_REGION, &region)?;
+    settings::put(
+        &conn,
+        SETTING_S3_BUCKET,
+        &trimmed(cloud.s3_bucket.as_deref()),
+    )?;
+    settings::put(
+        &conn,
+        SETTING_S3_PREFIX,
+        &trimmed(cloud.s3_prefix.as_deref()),
+    )?;
+    settings::put(
+        &conn,
+        SETTING_S3_ACCESS_KEY,
+        &trimmed(cloud.s3_access_key_id.as_deref()),
+    )?;
+    settings::put(
+        &conn,
+        SETTING_DRIVE_FOLDER,
+        &trimmed(cloud.drive_folder_id.as_deref()),
+    )?;
+    settings::put(
+        &conn,
+        SETTING_DRIVE_CLIENT_ID,
+        &trimmed(cloud.drive_client_id.as_deref()),
+    )?;
+    save_secret(S3_SECRET_ENTRY, cloud.s3_secret_key.as_deref())?;
+    save_secret(
+        DRIVE_CLIENT_SECRET_ENTRY,
+        cloud.drive_client_secret.as_deref(),
+    )?;
+    save_secret(
+        DRIVE_REFRESH_TOKEN_ENTRY,
+        cloud.drive_refresh_token.as_deref(),
+    )?;
+    Ok(load_settings(&conn)?)
+}
+
+// Takes an encrypted backup in a scratch folder and uploads it; backups always leave the
+// machine encrypted, so a passphrase is required
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn upload_cloud_backup(
+    pool: State<'_, DbPool>,
+    passphrase: String,
+) -> Result<CloudUpload, AppError> {
+    if passphrase.is_empty() {
+        return Err(AppError::validation(
+            "passphrase",
+            "Enter a passphrase for the backup",
+        ));
+    }
+    // Keep database access out of the await points below
+    let (target, info, bytes) = {
+        let conn = db::get_conn(&pool)?;
+        let target = load_target(&conn)?;
+        let staging = backup::temp_path("cloud");
+        std::fs::create_dir(&staging).map_err(|e| format!("Failed to stage backup: {}", e))?;
+        let sealed =
+            backup::create_encrypted_backup(&conn, &staging, &passphrase, None).and_then(|info| {
+                let bytes = std::fs::read(&info.path)
+                    .map_err(|e| format!("Failed to read backup: {}", e))?;
+                Ok((info, bytes))
+            });
+        let _ = std::fs::remove_dir_all(&staging);
+        let (info, bytes) = sealed?;
+        (target, info, bytes)
+    };
+
+    let client = http_client()?;
+    let remote = target.upload(&client, &info.file_name, &bytes).await?;
+    tracing::info!(file = %remote.file_name, "backup uploaded to the cloud");
+    Ok(CloudUpload {
+        backup: BackupInfo {
+            path: remote.id.clone(),
+            ..info
+        },
+        remote,
+    })
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn list_cloud_backups(pool: State<'_, DbPool>) -> Result<Vec<CloudBackup>, AppError> {
+    let target = {
+        let conn = db::get_conn(&pool)?;
+        load_target(&conn)?
+    };
+    Ok(target.list(&http_client()?).await?)
+}
+
+// Downloads a backup chosen from list_cloud_backups and restores it the same way as a local file
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn restore_cloud_backup(
+    app: AppHandle,
+    pool: State<'_, DbPool>,
+    cache: State<'_, DashboardCache>,
+    id: String,
+    passphrase: String,
+) -> Result<RestoreReport, AppError> {
+    let target = {
+        let conn = db::get_conn(&pool)?;
+        load_target(&conn)?
+    };
+    let bytes = target.download(&http_client()?, id.trim()).await?;
+
+    let rollback_dir = backup::rollback_dir(&app)?;
+    let downloaded = backup::temp_path("cloud");
+    std::fs::write(&downloaded, &bytes).map_err(|e| format!("Failed to stage backup: {}", e))?;
+    let report = db::get_conn(&pool).and_then(|mut conn| {
+        backup::restore_from(
+            &mut conn,
+            Path::new(&downloaded),
+            Some(&passphrase),
+            &rollback_dir,
+        )
+    });
+    let _ = std::fs::remove_file(&downloaded);
+    let report = report?;
+    cache.clear();
+    Ok(report)
+}
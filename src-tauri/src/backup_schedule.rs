@@ -0,0 +1,293 @@
+use std::path::Path;
+use std::time::Duration;
+
+use chrono::{Local, Months, NaiveDateTime};
+use rusqlite::Connection;
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Emitter, Manager, State};
+
+use crate::backup::{self, BackupInfo};
+use crate::db::{self, get_setting, set_setting, DbPool};
+use crate::error::AppError;
+use crate::maintenance::{self, MaintenanceReport};
+
+const SETTING_FREQUENCY: &str = "backup_frequency";
+const SETTING_FOLDER: &str = "backup_folder";
+const SETTING_KEEP_LAST: &str = "backup_keep_last";
+const SETTING_LAST_BACKUP: &str = "backup_last_backup_at";
+const SETTING_LAST_ATTEMPT: &str = "backup_last_attempt_at";
+const SETTING_MAINTENANCE_MONTHLY: &str = "maintenance_monthly";
+const SETTING_MAINTENANCE_LAST_ATTEMPT: &str = "maintenance_last_attempt_at";
+
+const DEFAULT_KEEP_LAST: u32 = 7;
+const TIMESTAMP_FORMAT: &str = "%Y-%m-%d %H:%M:%S";
+
+// How often the scheduler wakes up to see whether a backup is due
+const CHECK_INTERVAL: Duration = Duration::from_secs(10 * 60);
+// A failed backup is retried after this long rather than on every check
+const RETRY_AFTER_MINUTES: i64 = 60;
+
+pub const EVENT_BACKUP_COMPLETED: &str = "backup-completed";
+pub const EVENT_BACKUP_FAILED: &str = "backup-failed";
+pub const EVENT_MAINTENANCE_COMPLETED: &str = "maintenance-completed";
+pub const EVENT_MAINTENANCE_FAILED: &str = "maintenance-failed";
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum BackupFrequency {
+    Off,
+    Daily,
+    Weekly,
+}
+
+impl BackupFrequency {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            BackupFrequency::Off => "off",
+            BackupFrequency::Daily => "daily",
+            BackupFrequency::Weekly => "weekly",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "off" => Some(BackupFrequency::Off),
+            "daily" => Some(BackupFrequency::Daily),
+            "weekly" => Some(BackupFrequency::Weekly),
+            _ => None,
+        }
+    }
+
+    fn interval(&self) -> Option<chrono::Duration> {
+        match self {
+            BackupFrequency::Off => None,
+            BackupFrequency::Daily => Some(chrono::Duration::days(1)),
+            BackupFrequency::Weekly => Some(chrono::Duration::weeks(1)),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct BackupSchedule {
+    pub frequency: BackupFrequency,
+    pub folder: Option<String>,
+    pub keep_last: u32,
+    pub last_backup_at: Option<String>,
+    pub next_backup_at: Option<String>,
+    // Vacuum, analyze and checkpoint the database once a month
+    pub maintenance_monthly: bool,
+    pub last_maintenance_at: Option<String>,
+    pub next_maintenance_at: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct UpdateBackupSchedule {
+    pub frequency: BackupFrequency,
+    pub folder: Option<String>,
+    pub keep_last: u32,
+    #[serde(default)]
+    pub maintenance_monthly: bool,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct BackupFailure {
+    pub message: String,
+    pub attempted_at: String,
+}
+
+fn load_timestamp(conn: &Connection, key: &str) -> Result<Option<NaiveDateTime>, String> {
+    Ok(get_setting(conn, key)?
+        .and_then(|value| NaiveDateTime::parse_from_str(&value, TIMESTAMP_FORMAT).ok()))
+}
+
+pub fn load_schedule(conn: &Connection) -> Result<BackupSchedule, String> {
+    let frequency = get_setting(conn, SETTING_FREQUENCY)?
+        .and_then(|value| BackupFrequency::parse(&value))
+        .unwrap_or(BackupFrequency::Off);
+    let last_backup = load_timestamp(conn, SETTING_LAST_BACKUP)?;
+    let next_backup = frequency.interval().map(|interval| match last_backup {
+        Some(last) => last + interval,
+        None => Local::now().naive_local(),
+    });
+    let maintenance_monthly = get_setting(conn, SETTING_MAINTENANCE_MONTHLY)?
+        .and_then(|value| value.parse().ok())
+        .unwrap_or(false);
+    let last_maintenance = load_timestamp(conn, maintenance::SETTING_LAST_RUN)?;
+    let next_maintenance = maintenance_monthly.then(|| match last_maintenance {
+        Some(last) => last.checked_add_months(Months::new(1)).unwrap_or(last),
+        None => Local::now().naive_local(),
+    });
+    Ok(BackupSchedule {
+        frequency,
+        folder: get_setting(conn, SETTING_FOLDER)?.filter(|folder| !folder.is_empty()),
+        keep_last: get_setting(conn, SETTING_KEEP_LAST)?
+            .and_then(|value| value.parse().ok())
+            .unwrap_or(DEFAULT_KEEP_LAST),
+        last_backup_at: last_backup.map(|at| at.format(TIMESTAMP_FORMAT).to_string()),
+        next_backup_at: next_backup.map(|at| at.format(TIMESTAMP_FORMAT).to_string()),
+        maintenance_monthly,
+        last_maintenance_at: last_maintenance.map(|at| at.format(TIMESTAMP_FORMAT).to_string()),
+        next_maintenance_at: next_maintenance.map(|at| at.format(TIMESTAMP_FORMAT).to_string()),
+    })
+}
+
+// Deletes the oldest backups in `dir` beyond the newest `keep_last`. Only files named like
+// backups are considered, and backup names sort by the time they were taken.
+pub fn prune_backups(dir: &Path, keep_last: u32) -> Result<usize, String> {
+    let mut backups: Vec<_> = std::fs::read_dir(dir)
+        .map_err(|e| format!("Failed to read backup folder: {}", e))?
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| {
+            let name = entry.file_name().to_string_lossy().to_string();
+            name.starts_with("sales_report_") && name.ends_with(".db")
+        })
+        .map(|entry| entry.path())
+        .collect();
+    backups.sort();
+    let excess = backups.len().saturating_sub(keep_last as usize);
+    for path in &backups[..excess] {
+        std::fs::remove_file(path)
+            .map_err(|e| format!("Failed to remove old backup {}: {}", path.display(), e))?;
+    }
+    Ok(excess)
+}
+
+// Runs a backup if one is due. Returns None when nothing was due.
+fn run_if_due(conn: &Connection) -> Option<Result<BackupInfo, String>> {
+    let schedule = load_schedule(conn).ok()?;
+    let interval = schedule.frequency.interval()?;
+    let now = Local::now().naive_local();
+    let last_backup = load_timestamp(conn, SETTING_LAST_BACKUP).ok()?;
+    let last_attempt = load_timestamp(conn, SETTING_LAST_ATTEMPT).ok()?;
+    let retry_after = chrono::Duration::minutes(RETRY_AFTER_MINUTES);
+    if last_backup.is_some_and(|last| now < last + interval)
+        || last_attempt.is_some_and(|last| now < last + retry_after)
+    {
+        return None;
+    }
+
+    let stamp = now.format(TIMESTAMP_FORMAT).to_string();
+    let result = (|| {
+        set_setting(conn, SETTING_LAST_ATTEMPT, &stamp)?;
+        let folder = schedule
+            .folder
+            .ok_or_else(|| "No backup folder is configured".to_string())?;
+        let info = backup::create_backup(conn, Path::new(&folder), None)?;
+        set_setting(conn, SETTING_LAST_BACKUP, &stamp)?;
+        prune_backups(Path::new(&folder), schedule.keep_last)?;
+        Ok(info)
+    })();
+    Some(result)
+}
+
+// Runs maintenance if it is enabled and a month has passed since the last run. Returns None
+// when nothing was due.
+fn run_maintenance_if_due(conn: &Connection) -> Option<Result<MaintenanceReport, String>> {
+    let schedule = load_schedule(conn).ok()?;
+    let due = schedule
+        .next_maintenance_at
+        .and_then(|at| NaiveDateTime::parse_from_str(&at, TIMESTAMP_FORMAT).ok())?;
+    let now = Local::now().naive_local();
+    let last_attempt = load_timestamp(conn, SETTING_MAINTENANCE_LAST_ATTEMPT).ok()?;
+    let retry_after = chrono::Duration::minutes(RETRY_AFTER_MINUTES);
+    if now < due || last_attempt.is_some_and(|last| now < last + retry_after) {
+        return None;
+    }
+
+    let stamp = now.format(TIMESTAMP_FORMAT).to_string();
+    let result = set_setting(conn, SETTING_MAINTENANCE_LAST_ATTEMPT, &stamp)
+        .map_err(String::from)
+        .and_then(|_| maintenance::run(conn));
+    Some(result)
+}
+
+fn failure(message: String) -> BackupFailure {
+    BackupFailure {
+        message,
+        attempted_at: Local::now().format(TIMESTAMP_FORMAT).to_string(),
+    }
+}
+
+// Background thread started at launch; settings are re-read on every check so changes made
+// through set_backup_schedule apply without a restart
+pub fn start_scheduler(app: AppHandle) {
+    std::thread::spawn(move || loop {
+        let pool = app.state::<DbPool>();
+        if let Ok(conn) = db::get_conn(&pool) {
+            match run_if_due(&conn) {
+                Some(Ok(info)) => {
+                    let _ = app.emit(EVENT_BACKUP_COMPLETED, info);
+                }
+                Some(Err(message)) => {
+                    let _ = app.emit(EVENT_BACKUP_FAILED, failure(message));
+                }
+                None => {}
+            }
+            // After the backup, so a month's maintenance never runs on data not yet backed up
+            match run_maintenance_if_due(&conn) {
+                Some(Ok(report)) => {
+                    let _ = app.emit(EVENT_MAINTENANCE_COMPLETED, report);
+                }
+                Some(Err(message)) => {
+                    let _ = app.emit(EVENT_MAINTENANCE_FAILED, failure(message));
+                }
+                None => {}
+            }
+        }
+        std::thread::sleep(CHECK_INTERVAL);
+    });
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn get_backup_schedule(pool: State<'_, DbPool>) -> Result<BackupSchedule, AppError> {
+    let conn = db::get_conn(&pool)?;
+    Ok(load_schedule(&conn)?)
+}
+
+pub fn save_schedule(conn: &Connection, schedule: &UpdateBackupSchedule) -> Result<(), AppError> {
+    if schedule.keep_last == 0 {
+        return Err(AppError::validation(
+            "keep_last",
+            "Keep at least one backup",
+        ));
+    }
+    let folder = schedule.folder.as_deref().map(str::trim).unwrap_or("");
+    if schedule.frequency != BackupFrequency::Off {
+        if folder.is_empty() {
+            return Err(AppError::validation(
+                "folder",
+                "Choose a folder for scheduled backups",
+            ));
+        }
+        if !Path::new(folder).is_dir() {
+            return Err(AppError::validation(
+                "folder",
+                format!("Backup folder {} does not exist", folder),
+            ));
+        }
+    }
+
+    set_setting(conn, SETTING_FREQUENCY, schedule.frequency.as_str())?;
+    set_setting(conn, SETTING_FOLDER, folder)?;
+    set_setting(conn, SETTING_KEEP_LAST, &schedule.keep_last.to_string())?;
+    set_setting(
+        conn,
+        SETTING_MAINTENANCE_MONTHLY,
+        &schedule.maintenance_monthly.to_string(),
+    )?;
+    set_setting(conn, SETTING_MAINTENANCE_LAST_ATTEMPT, "")?;
+    // A new configuration gets a fresh attempt instead of waiting out an earlier failure
+    set_setting(conn, SETTING_LAST_ATTEMPT, "")
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn set_backup_schedule(
+    pool: State<'_, DbPool>,
+    schedule: UpdateBackupSchedule,
+) -> Result<BackupSchedule, AppError> {
+    let conn = db::get_conn(&pool)?;
+    save_schedule(&conn, &schedule)?;
+    Ok(load_schedule(&conn)?)
+}
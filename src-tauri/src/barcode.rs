@@ -0,0 +1,328 @@
+use std::io::Cursor;
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use image::{GrayImage, ImageFormat, Luma};
+use serde::{Deserialize, Serialize};
+
+use crate::error::AppError;
+
+const DEFAULT_MODULE_WIDTH: u32 = 2;
+const DEFAULT_HEIGHT: u32 = 80;
+const MAX_MODULE_WIDTH: u32 = 10;
+const MAX_HEIGHT: u32 = 1000;
+const MAX_CODE128_LENGTH: usize = 80;
+// Blank modules either side so scanners can find the edges
+const QUIET_ZONE: usize = 11;
+
+// Bar and space widths of each Code 128 symbol value; 106 is the stop pattern
+const CODE128_PATTERNS: [&str; 107] = [
+    "212222", "222122", "222221", "121223", "121322", "131222", "122213", "122312", "132212",
+    "221213", "221312", "231212", "112232", "122132", "122231", "113222", "123122", "123221",
+    "223211", "221132", "221231", "213212", "223112", "312131", "311222", "321122", "321221",
+    "312212", "322112", "322211", "212123", "212321", "232121", "111323", "131123", "131321",
+    "112313", "132113", "132311", "211313", "231113", "231311", "112133", "112331", "132131",
+    "113123", "113321", "133121", "313121", "211331", "231131", "213113", "213311", "213131",
+    "311123", "311321", "331121", "312113", "312311", "332111", "314111", "221411", "431111",
+    "111224", "111422", "121124", "121421", "141122", "141221", "112214", "112412", "122114",
+    "122411", "142112", "142211", "241211", "221114", "413111", "241112", "134111", "111242",
+    "121142", "121241", "114212", "124112", "124211", "411212", "421112", "421211", "212141",
+    "214121", "412121", "111143", "111341", "131141", "114113", "114311", "411113", "411311",
+    "113141", "114131", "311141", "411131", "211412", "211214", "211232", "2331112",
+];
+const CODE_C: usize = 99;
+const CODE_B: usize = 100;
+const START_B: usize = 104;
+const START_C: usize = 105;
+const STOP: usize = 106;
+
+// EAN-13 left-hand (odd parity, "L") digit patterns; "R" is their complement and "G" the
+// reverse of "R"
+const EAN_L: [&str; 10] = [
+    "0001101", "0011001", "0010011", "0111101", "0100011", "0110001", "0101111", "0111011",
+    "0110111", "0001011",
+];
+// Parity of the six left-hand digits, chosen by the leading digit
+const EAN_PARITY: [&str; 10] = [
+    "LLLLLL", "LLGLGG", "LLGGLG", "LLGGGL", "LGLLGG", "LGGLLG", "LGGGLL", "LGLGLG", "LGLGGL",
+    "LGGLGL",
+];
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Symbology {
+    Code128,
+    Ean13,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum BarcodeFormat {
+    #[default]
+    Png,
+    Svg,
+}
+
+// An encoded barcode as a row of modules, true for a bar; `text` is what it reads as, which
+// for EAN-13 includes the check digit
+#[derive(Debug, Clone)]
+pub struct Barcode {
+    pub text: String,
+    pub modules: Vec<bool>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct GeneratedBarcode {
+    pub symbology: Symbology,
+    pub text: String,
+    pub format: BarcodeFormat,
+    pub mime_type: String,
+    pub base64: String,
+}
+
+fn push_widths(modules: &mut Vec<bool>, widths: &str) {
+    for (index, width) in widths.bytes().enumerate() {
+        let bar = index % 2 == 0;
+        modules.extend(std::iter::repeat_n(bar, usize::from(width - b'0')));
+    }
+}
+
+fn push_bits(modules: &mut Vec<bool>, bits: &str) {
+    modules.extend(bits.bytes().map(|bit| bit == b'1'));
+}
+
+// Symbol values for `data`, using code set C for runs of four or more digits and code set B
+// for everything else
+fn code128_values(data: &str) -> Vec<usize> {
+    let bytes = data.as_bytes();
+    let digit_run = |from: usize| bytes[from..].iter().take_while(|b| b.is_ascii_digit()).count();
+    let mut values = Vec::new();
+    let mut in_c = digit_run(0) >= 4;
+    values.push(if in_c { START_C } else { START_B });
+    let mut index = 0;
+    while index < bytes.len() {
+        let run = digit_run(index);
+        if run >= 4 || (in_c && run >= 2) {
+            if !in_c {
+                values.push(CODE_C);
+                in_c = true;
+            }
+            let pairs = run / 2;
+            for pair in bytes[index..index + pairs * 2].chunks(2) {
+                values.push(usize::from(pair[0] - b'0') * 10 + usize::from(pair[1] - b'0'));
+            }
+            index += pairs * 2;
+            continue;
+        }
+        if in_c {
+            values.push(CODE_B);
+            in_c = false;
+        }
+        values.push(usize::from(bytes[index] - b' '));
+        index += 1;
+    }
+    values
+}
+
+pub fn code128(data: &str) -> Result<Barcode, AppError> {
+    if data.is_empty() || data.len() > MAX_CODE128_LENGTH {
+        return Err(AppError::validation(
+            "data",
+            format!(
+                "Code 128 data must be 1 to {} characters",
+                MAX_CODE128_LENGTH
+            ),
+        ));
+    }
+    if !data.bytes().all(|b| (b' '..=b'~').contains(&b)) {
+        return Err(AppError::validation(
+            "data",
+            "Code 128 data can only contain printable ASCII characters",
+        ));
+    }
+    let mut values = code128_values(data);
+    let checksum = values
+        .iter()
+        .enumerate()
+        .map(|(position, value)| position.max(1) * value)
+        .sum::<usize>()
+        % 103;
+    values.push(checksum);
+    values.push(STOP);
+
+    let mut modules = Vec::new();
+    for value in values {
+        push_widths(&mut modules, CODE128_PATTERNS[value]);
+    }
+    Ok(Barcode {
+        text: data.to_string(),
+        modules,
+    })
+}
+
+fn ean13_check_digit(digits: &[u8]) -> u8 {
+    let sum: u32 = digits
+        .iter()
+        .enumerate()
+        .map(|(index, digit)| u32::from(*digit) * if index % 2 == 0 { 1 } else { 3 })
+        .sum();
+    ((10 - sum % 10) % 10) as u8
+}
+
+// Takes the 12 data digits, or all 13 when the check digit is already known
+pub fn ean13(data: &str) -> Result<Barcode, AppError> {
+    if !matches!(data.len(), 12 | 13) || !data.bytes().all(|b| b.is_ascii_digit()) {
+        return Err(AppError::validation(
+            "data",
+            "EAN-13 data must be 12 or 13 digits",
+        ));
+    }
+    let mut digits: Vec<u8> = data.bytes().map(|b| b - b'0').collect();
+    let check = ean13_check_digit(&digits[..12]);
+    match digits.get(12) {
+        Some(given) if *given != check => {
+            return Err(AppError::validation(
+                "data",
+                format!("EAN-13 check digit should be {}", check),
+            ));
+        }
+        Some(_) => {}
+        None => digits.push(check),
+    }
+
+    let mut modules = Vec::new();
+    push_bits(&mut modules, "101");
+    let parity = EAN_PARITY[usize::from(digits[0])].as_bytes();
+    for (index, digit) in digits[1..7].iter().enumerate() {
+        let left = EAN_L[usize::from(*digit)];
+        if parity[index] == b'G' {
+            // Reverse of the complement
+            push_bits(
+                &mut modules,
+                &left.bytes().rev().map(|b| if b == b'1' { '0' } else { '1' }).collect::<String>(),
+            );
+        } else {
+            push_bits(&mut modules, left);
+        }
+    }
+    push_bits(&mut modules, "01010");
+    for digit in &digits[7..] {
+        let right: String = EAN_L[usize::from(*digit)]
+            .bytes()
+            .map(|b| if b == b'1' { '0' } else { '1' })
+            .collect();
+        push_bits(&mut modules, &right);
+    }
+    push_bits(&mut modules, "101");
+    Ok(Barcode {
+        text: digits.iter().map(|digit| char::from(b'0' + digit)).collect(),
+        modules,
+    })
+}
+
+pub fn encode(data: &str, symbology: Symbology) -> Result<Barcode, AppError> {
+    match symbology {
+        Symbology::Code128 => code128(data),
+        Symbology::Ean13 => ean13(data.trim()),
+    }
+}
+
+impl Barcode {
+    // Runs of bars as (first module, width in modules)
+    pub fn bars(&self) -> Vec<(usize, usize)> {
+        let mut bars = Vec::new();
+        let mut start = None;
+        for (index, bar) in self.modules.iter().chain(std::iter::once(&false)).enumerate() {
+            match (bar, start) {
+                (true, None) => start = Some(index),
+                (false, Some(from)) => {
+                    bars.push((from, index - from));
+                    start = None;
+                }
+                _ => {}
+            }
+        }
+        bars
+    }
+
+    // Width in modules including the quiet zones
+    pub fn total_modules(&self) -> usize {
+        self.modules.len() + QUIET_ZONE * 2
+    }
+
+    pub fn png(&self, module_width: u32, height: u32) -> Result<Vec<u8>, String> {
+        let width = self.total_modules() as u32 * module_width;
+        let mut image = GrayImage::from_pixel(width, height, Luma([255]));
+        for (from, run) in self.bars() {
+            let left = (QUIET_ZONE + from) as u32 * module_width;
+            for x in left..left + run as u32 * module_width {
+                for y in 0..height {
+                    image.put_pixel(x, y, Luma([0]));
+                }
+            }
+        }
+        let mut png = Vec::new();
+        image
+            .write_to(&mut Cursor::new(&mut png), ImageFormat::Png)
+            .map_err(|e| format!("Failed to encode barcode: {}", e))?;
+        Ok(png)
+    }
+
+    pub fn svg(&self, module_width: u32, height: u32) -> String {
+        let width = self.total_modules() as u32 * module_width;
+        let mut svg = format!(
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{0}\" height=\"{1}\" \
+             viewBox=\"0 0 {0} {1}\"><rect width=\"{0}\" height=\"{1}\" fill=\"#fff\"/>",
+            width, height
+        );
+        for (from, run) in self.bars() {
+            svg.push_str(&format!(
+                "<rect x=\"{}\" width=\"{}\" height=\"{}\"/>",
+                (QUIET_ZONE + from) as u32 * module_width,
+                run as u32 * module_width,
+                height
+            ));
+        }
+        svg.push_str("</svg>");
+        svg
+    }
+}
+
+// Encodes item codes, invoice numbers and the like for labels and printouts. PNG output is
+// `module_width` pixels per module and `height` pixels tall; SVG uses the same units.
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn generate_barcode(
+    data: String,
+    symbology: Symbology,
+    format: Option<BarcodeFormat>,
+    module_width: Option<u32>,
+    height: Option<u32>,
+) -> Result<GeneratedBarcode, AppError> {
+    let module_width = module_width.unwrap_or(DEFAULT_MODULE_WIDTH);
+    if !(1..=MAX_MODULE_WIDTH).contains(&module_width) {
+        return Err(AppError::validation(
+            "module_width",
+            format!("Module width must be 1 to {} pixels", MAX_MODULE_WIDTH),
+        ));
+    }
+    let height = height.unwrap_or(DEFAULT_HEIGHT);
+    if !(1..=MAX_HEIGHT).contains(&height) {
+        return Err(AppError::validation(
+            "height",
+            format!("Height must be 1 to {} pixels", MAX_HEIGHT),
+        ));
+    }
+    let barcode = encode(&data, symbology)?;
+    let format = format.unwrap_or_default();
+    let (mime_type, bytes) = match format {
+        BarcodeFormat::Png => ("image/png", barcode.png(module_width, height)?),
+        BarcodeFormat::Svg => ("image/svg+xml", barcode.svg(module_width, height).into_bytes()),
+    };
+    Ok(GeneratedBarcode {
+        symbology,
+        text: barcode.text,
+        format,
+        mime_type: mime_type.to_string(),
+        base64: STANDARD.encode(bytes),
+    })
+}
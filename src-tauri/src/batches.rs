@@ -0,0 +1,505 @@
+use std::collections::HashSet;
+
+use chrono::{Duration, Local, NaiveDate};
+use rusqlite::{params, Connection, OptionalExtension, Row};
+use serde::{Deserialize, Serialize};
+use tauri::State;
+
+use crate::audit::{self, AuditAction};
+use crate::db::{self, DbPool};
+use crate::error::AppError;
+use crate::invoices::{Invoice, InvoiceLineInput, InvoiceStatus, INVOICE_DATE_FORMAT};
+use crate::items::{self, ItemTracking};
+
+// Batches expiring this soon after the invoice date are flagged when sold
+const EXPIRY_WARNING_DAYS: i64 = 30;
+const MAX_BATCH_NUMBER_LENGTH: usize = 50;
+const MAX_SERIAL_NUMBER_LENGTH: usize = 100;
+
+// Batch data model; batch numbers are unique per item
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Batch {
+    pub id: i64,
+    pub company_id: i64,
+    pub item_id: i64,
+    pub item_code: String,
+    pub batch_number: String,
+    pub manufacturing_date: Option<String>,
+    pub expiry_date: Option<String>,
+    pub created_at: Option<String>,
+    pub updated_at: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SaveBatch {
+    pub item_id: i64,
+    pub batch_number: String,
+    #[serde(default)]
+    pub manufacturing_date: Option<String>,
+    #[serde(default)]
+    pub expiry_date: Option<String>,
+}
+
+// One invoice line that delivered a traced batch or serial number
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TraceEntry {
+    pub invoice_id: i64,
+    pub invoice_number: String,
+    pub invoice_date: String,
+    pub status: InvoiceStatus,
+    pub customer_id: i64,
+    pub customer_name: String,
+    pub item_id: i64,
+    pub item_code: String,
+    pub batch_number: Option<String>,
+    pub quantity: f64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ExpiringBatch {
+    #[serde(flatten)]
+    pub batch: Batch,
+    pub expired: bool,
+}
+
+const SELECT_BATCH: &str = "
+    SELECT b.id, b.company_id, b.item_id, i.code AS item_code, b.batch_number,
+           b.manufacturing_date, b.expiry_date, b.created_at, b.updated_at
+    FROM batches b
+    JOIN items i ON i.id = b.item_id";
+
+// Lines of live invoices, i.e. not cancelled and not in the recycle bin
+const SELECT_TRACE: &str = "
+    SELECT inv.id AS invoice_id, inv.invoice_number, inv.invoice_date, inv.status,
+           inv.customer_id, COALESCE(c.report_customer, '') AS customer_name, l.item_id,
+           it.code AS item_code, b.batch_number, l.quantity
+    FROM invoice_lines l
+    JOIN invoices inv ON inv.id = l.invoice_id
+    JOIN items it ON it.id = l.item_id
+    LEFT JOIN customers c ON c.id = inv.customer_id
+    LEFT JOIN batches b ON b.id = l.batch_id
+    WHERE inv.company_id = ?1 AND inv.deleted_at IS NULL AND inv.status != 'cancelled'";
+
+fn batch_from_row(row: &Row) -> rusqlite::Result<Batch> {
+    Ok(Batch {
+        id: row.get("id")?,
+        company_id: row.get("company_id")?,
+        item_id: row.get("item_id")?,
+        item_code: row.get("item_code")?,
+        batch_number: row.get("batch_number")?,
+        manufacturing_date: row.get("manufacturing_date")?,
+        expiry_date: row.get("expiry_date")?,
+        created_at: row.get("created_at")?,
+        updated_at: row.get("updated_at")?,
+    })
+}
+
+fn trace_from_row(row: &Row) -> rusqlite::Result<TraceEntry> {
+    let status: String = row.get("status")?;
+    Ok(TraceEntry {
+        invoice_id: row.get("invoice_id")?,
+        invoice_number: row.get("invoice_number")?,
+        invoice_date: row.get("invoice_date")?,
+        status: InvoiceStatus::parse(&status).unwrap_or(InvoiceStatus::Draft),
+        customer_id: row.get("customer_id")?,
+        customer_name: row.get("customer_name")?,
+        item_id: row.get("item_id")?,
+        item_code: row.get("item_code")?,
+        batch_number: row.get("batch_number")?,
+        quantity: row.get("quantity")?,
+    })
+}
+
+fn map_write_error(e: rusqlite::Error) -> AppError {
+    let message = e.to_string();
+    if message.contains("UNIQUE constraint failed: batches") {
+        return AppError::conflict(
+            "batch_number",
+            "A batch with this number already exists for the item",
+        );
+    }
+    AppError::from(e)
+}
+
+pub fn get_batch_by_id(
+    conn: &Connection,
+    id: i64,
+    company_id: i64,
+) -> Result<Option<Batch>, AppError> {
+    conn.query_row(
+        &format!("{} WHERE b.id = ?1 AND b.company_id = ?2", SELECT_BATCH),
+        params![id, company_id],
+        batch_from_row,
+    )
+    .optional()
+    .map_err(AppError::from)
+}
+
+// Invoice that already sold this serial number of the item, other than `invoice_id`
+fn serial_sold_on(
+    conn: &Connection,
+    company_id: i64,
+    item_id: i64,
+    serial_number: &str,
+    invoice_id: Option<i64>,
+) -> Result<Option<String>, AppError> {
+    conn.query_row(
+        "SELECT inv.invoice_number
+         FROM invoice_lines l
+         JOIN invoices inv ON inv.id = l.invoice_id
+         JOIN json_each(l.serial_numbers) s
+         WHERE inv.company_id = ?1 AND l.item_id = ?2 AND s.value = ?3
+           AND inv.deleted_at IS NULL AND inv.status != 'cancelled'
+           AND (?4 IS NULL OR inv.id != ?4)
+         LIMIT 1",
+        params![company_id, item_id, serial_number, invoice_id],
+        |row| row.get(0),
+    )
+    .optional()
+    .map_err(AppError::from)
+}
+
+// Checks the batch and serial numbers on each line against its item's tracking. Drafts may leave
+// them out, but an issued invoice must name a batch for batch-tracked items and one serial number
+// per unit for serial-tracked items. Returns warnings for expired or soon-to-expire batches.
+pub fn check_line_tracking(
+    conn: &Connection,
+    invoice: &Invoice,
+    lines: &[InvoiceLineInput],
+) -> Result<Vec<String>, AppError> {
+    let issued = invoice.status == InvoiceStatus::Issued;
+    let mut warnings = Vec::new();
+    let mut seen_serials: HashSet<(i64, &str)> = HashSet::new();
+    for (index, line) in lines.iter().enumerate() {
+        let line_no = index + 1;
+        let tracking = match line.item_id {
+            Some(item_id) => items::get_item_by_id(conn, item_id, invoice.company_id)?
+                .map(|item| item.tracking)
+                .unwrap_or_default(),
+            None => ItemTracking::None,
+        };
+        if line.batch_id.is_some() && tracking != ItemTracking::Batch {
+            return Err(AppError::validation(
+                "lines",
+                format!("Line {}: the item is not batch-tracked", line_no),
+            ));
+        }
+        if !line.serial_numbers.is_empty() && tracking != ItemTracking::Serial {
+            return Err(AppError::validation(
+                "lines",
+                format!("Line {}: the item is not serial-tracked", line_no),
+            ));
+        }
+        let item_id = line.item_id.unwrap_or_default();
+
+        match (tracking, line.batch_id) {
+            (ItemTracking::Batch, Some(batch_id)) => {
+                let batch = get_batch_by_id(conn, batch_id, invoice.company_id)?
+                    .filter(|batch| batch.item_id == item_id)
+                    .ok_or_else(|| {
+                        AppError::validation(
+                            "lines",
+                            format!("Line {}: batch not found for the item", line_no),
+                        )
+                    })?;
+                if let Some(expiry) = batch.expiry_date.as_deref() {
+                    let warn_from = NaiveDate::parse_from_str(expiry, INVOICE_DATE_FORMAT)
+                        .map(|date| date - Duration::days(EXPIRY_WARNING_DAYS))
+                        .map(|date| date.format(INVOICE_DATE_FORMAT).to_string())
+                        .unwrap_or_default();
+                    if expiry < invoice.invoice_date.as_str() {
+                        warnings.push(format!(
+                            "Line {}: batch {} expired on {}",
+                            line_no, batch.batch_number, expiry
+                        ));
+                    } else if warn_from.as_str() <= invoice.invoice_date.as_str() {
+                        warnings.push(format!(
+                            "Line {}: batch {} expires on {}",
+                            line_no, batch.batch_number, expiry
+                        ));
+                    }
+                }
+            }
+            (ItemTracking::Batch, None) if issued => {
+                return Err(AppError::validation(
+                    "lines",
+                    format!("Line {}: select the batch sold", line_no),
+                ));
+            }
+            _ => {}
+        }
+
+        if tracking != ItemTracking::Serial {
+            continue;
+        }
+        for serial in &line.serial_numbers {
+            let serial = serial.trim();
+            if serial.is_empty() || serial.len() > MAX_SERIAL_NUMBER_LENGTH {
+                return Err(AppError::validation(
+                    "lines",
+                    format!(
+                        "Line {}: serial numbers must be 1 to {} characters",
+                        line_no, MAX_SERIAL_NUMBER_LENGTH
+                    ),
+                ));
+            }
+            if !seen_serials.insert((item_id, serial)) {
+                return Err(AppError::validation(
+                    "lines",
+                    format!("Line {}: serial number {} is repeated", line_no, serial),
+                ));
+            }
+            if let Some(number) =
+                serial_sold_on(conn, invoice.company_id, item_id, serial, invoice.id)?
+            {
+                return Err(AppError::validation(
+                    "lines",
+                    format!(
+                        "Line {}: serial number {} was already sold on invoice {}",
+                        line_no, serial, number
+                    ),
+                ));
+            }
+        }
+        if issued && (line.serial_numbers.len() as f64 - line.quantity).abs() > f64::EPSILON {
+            return Err(AppError::validation(
+                "lines",
+                format!(
+                    "Line {}: enter one serial number for each of the {} units sold",
+                    line_no, line.quantity
+                ),
+            ));
+        }
+    }
+    Ok(warnings)
+}
+
+fn parse_optional_date(field: &str, value: &Option<String>) -> Result<Option<String>, AppError> {
+    match value.as_deref().map(str::trim).filter(|v| !v.is_empty()) {
+        Some(date) => NaiveDate::parse_from_str(date, INVOICE_DATE_FORMAT)
+            .map(|d| Some(d.format(INVOICE_DATE_FORMAT).to_string()))
+            .map_err(|_| AppError::validation(field, "Enter the date as YYYY-MM-DD")),
+        None => Ok(None),
+    }
+}
+
+fn validate(
+    conn: &Connection,
+    company_id: i64,
+    batch: SaveBatch,
+) -> Result<SaveBatch, AppError> {
+    let batch_number = batch.batch_number.trim().to_string();
+    if batch_number.is_empty() || batch_number.len() > MAX_BATCH_NUMBER_LENGTH {
+        return Err(AppError::validation(
+            "batch_number",
+            format!("Batch number must be 1 to {} characters", MAX_BATCH_NUMBER_LENGTH),
+        ));
+    }
+    let manufacturing_date = parse_optional_date("manufacturing_date", &batch.manufacturing_date)?;
+    let expiry_date = parse_optional_date("expiry_date", &batch.expiry_date)?;
+    if let (Some(made), Some(expiry)) = (&manufacturing_date, &expiry_date) {
+        if expiry < made {
+            return Err(AppError::validation(
+                "expiry_date",
+                "Expiry date must not be before the manufacturing date",
+            ));
+        }
+    }
+    let item = items::get_item_by_id(conn, batch.item_id, company_id)?
+        .ok_or_else(|| AppError::not_found("Item not found"))?;
+    if item.tracking != ItemTracking::Batch {
+        return Err(AppError::validation("item_id", "The item is not batch-tracked"));
+    }
+    Ok(SaveBatch {
+        item_id: batch.item_id,
+        batch_number,
+        manufacturing_date,
+        expiry_date,
+    })
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn list_batches(
+    pool: State<'_, DbPool>,
+    company_id: i64,
+    item_id: Option<i64>,
+) -> Result<Vec<Batch>, AppError> {
+    let conn = db::get_conn(&pool)?;
+    let mut stmt = conn.prepare(&format!(
+        "{} WHERE b.company_id = ?1 AND (?2 IS NULL OR b.item_id = ?2)
+             ORDER BY i.code, b.expiry_date IS NULL, b.expiry_date, b.batch_number",
+        SELECT_BATCH
+    ))?;
+    let batches = stmt
+        .query_map(params![company_id, item_id], batch_from_row)?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(batches)
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn create_batch(
+    pool: State<'_, DbPool>,
+    company_id: i64,
+    batch: SaveBatch,
+) -> Result<Batch, AppError> {
+    let conn = db::get_conn(&pool)?;
+    let batch = validate(&conn, company_id, batch)?;
+    conn.execute(
+        "INSERT INTO batches (company_id, item_id, batch_number, manufacturing_date, expiry_date)
+         VALUES (?1, ?2, ?3, ?4, ?5)",
+        params![
+            company_id,
+            batch.item_id,
+            batch.batch_number,
+            batch.manufacturing_date,
+            batch.expiry_date,
+        ],
+    )
+    .map_err(map_write_error)?;
+    let created = get_batch_by_id(&conn, conn.last_insert_rowid(), company_id)?
+        .ok_or_else(|| AppError::internal("Batch not found after creation"))?;
+    audit::record(
+        &conn,
+        company_id,
+        "batch",
+        created.id,
+        AuditAction::Create,
+        None,
+        Some(&created),
+    )?;
+    Ok(created)
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn update_batch(
+    pool: State<'_, DbPool>,
+    id: i64,
+    company_id: i64,
+    batch: SaveBatch,
+) -> Result<Batch, AppError> {
+    let conn = db::get_conn(&pool)?;
+    let existing = get_batch_by_id(&conn, id, company_id)?
+        .ok_or_else(|| AppError::not_found("Batch not found"))?;
+    let batch = validate(&conn, company_id, batch)?;
+    if batch.item_id != existing.item_id {
+        return Err(AppError::validation("item_id", "A batch cannot be moved to another item"));
+    }
+    conn.execute(
+        "UPDATE batches SET batch_number = ?1, manufacturing_date = ?2, expiry_date = ?3,
+                            updated_at = CURRENT_TIMESTAMP
+         WHERE id = ?4 AND company_id = ?5",
+        params![
+            batch.batch_number,
+            batch.manufacturing_date,
+            batch.expiry_date,
+            id,
+            company_id,
+        ],
+    )
+    .map_err(map_write_error)?;
+    let updated = get_batch_by_id(&conn, id, company_id)?
+        .ok_or_else(|| AppError::internal("Batch not found after update"))?;
+    audit::record(
+        &conn,
+        company_id,
+        "batch",
+        id,
+        AuditAction::Update,
+        Some(&existing),
+        Some(&updated),
+    )?;
+    Ok(updated)
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn delete_batch(
+    pool: State<'_, DbPool>,
+    id: i64,
+    company_id: i64,
+) -> Result<(), AppError> {
+    let conn = db::get_conn(&pool)?;
+    let existing = get_batch_by_id(&conn, id, company_id)?
+        .ok_or_else(|| AppError::not_found("Batch not found"))?;
+    let in_use: bool = conn.query_row(
+        "SELECT EXISTS(SELECT 1 FROM invoice_lines WHERE batch_id = ?1)",
+        params![id],
+        |row| row.get(0),
+    )?;
+    if in_use {
+        return Err(AppError::conflict("id", "This batch has been invoiced and must be kept"));
+    }
+    conn.execute("DELETE FROM batches WHERE id = ?1 AND company_id = ?2", params![id, company_id])?;
+    audit::record(&conn, company_id, "batch", id, AuditAction::Delete, Some(&existing), None)
+}
+
+// Every invoice that delivered goods from batches with this number, i.e. which customers
+// received it; the same number may exist for several items
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn trace_batch(
+    pool: State<'_, DbPool>,
+    company_id: i64,
+    batch_number: String,
+) -> Result<Vec<TraceEntry>, AppError> {
+    let conn = db::get_conn(&pool)?;
+    let mut stmt = conn.prepare(&format!(
+        "{} AND b.batch_number = ?2 ORDER BY inv.invoice_date, inv.id",
+        SELECT_TRACE
+    ))?;
+    let entries = stmt
+        .query_map(params![company_id, batch_number.trim()], trace_from_row)?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(entries)
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn trace_serial(
+    pool: State<'_, DbPool>,
+    company_id: i64,
+    serial_number: String,
+) -> Result<Vec<TraceEntry>, AppError> {
+    let conn = db::get_conn(&pool)?;
+    let mut stmt = conn.prepare(&format!(
+        "{} AND EXISTS(SELECT 1 FROM json_each(l.serial_numbers) s WHERE s.value = ?2)
+             ORDER BY inv.invoice_date, inv.id",
+        SELECT_TRACE
+    ))?;
+    let entries = stmt
+        .query_map(params![company_id, serial_number.trim()], trace_from_row)?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(entries)
+}
+
+// Batches already expired or expiring within `within_days` (30 by default) of today
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn list_expiring_batches(
+    pool: State<'_, DbPool>,
+    company_id: i64,
+    within_days: Option<u32>,
+) -> Result<Vec<ExpiringBatch>, AppError> {
+    let today = Local::now().date_naive();
+    let days = within_days.map(i64::from).unwrap_or(EXPIRY_WARNING_DAYS);
+    let cutoff = (today + Duration::days(days)).format(INVOICE_DATE_FORMAT).to_string();
+    let today = today.format(INVOICE_DATE_FORMAT).to_string();
+    let conn = db::get_conn(&pool)?;
+    let mut stmt = conn.prepare(&format!(
+        "{} WHERE b.company_id = ?1 AND b.expiry_date <= ?2 ORDER BY b.expiry_date, i.code",
+        SELECT_BATCH
+    ))?;
+    let batches = stmt
+        .query_map(params![company_id, cutoff], batch_from_row)?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(batches
+        .into_iter()
+        .map(|batch| {
+            let expired = batch.expiry_date.as_deref().is_some_and(|date| date < today.as_str());
+            ExpiringBatch { batch, expired }
+        })
+        .collect())
+}
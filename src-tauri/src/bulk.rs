@@ -0,0 +1,133 @@
+use std::collections::HashSet;
+
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+use tauri::State;
+
+use crate::audit::{self, AuditAction};
+use crate::categories;
+use crate::customers;
+use crate::db::{self, DbPool};
+use crate::error::AppError;
+use crate::invoices;
+
+const MAX_BULK_IDS: usize = 5000;
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BulkFailure {
+    pub id: i64,
+    pub error: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BulkResult {
+    pub succeeded: Vec<i64>,
+    pub failed: Vec<BulkFailure>,
+}
+
+// Applies `action` to each id in one transaction. Every id runs under its own savepoint, so a
+// failure is reported for that id and undone without affecting the rest.
+fn run_bulk<F>(conn: &mut Connection, ids: Vec<i64>, action: F) -> Result<BulkResult, AppError>
+where
+    F: Fn(&Connection, i64) -> Result<(), AppError>,
+{
+    if ids.is_empty() {
+        return Err(AppError::validation("ids", "Select at least one record"));
+    }
+    if ids.len() > MAX_BULK_IDS {
+        return Err(AppError::validation(
+            "ids",
+            format!("Select at most {} records at a time", MAX_BULK_IDS),
+        ));
+    }
+    let mut seen = HashSet::new();
+    let ids: Vec<i64> = ids.into_iter().filter(|id| seen.insert(*id)).collect();
+
+    let mut tx = conn.transaction()?;
+    let mut result = BulkResult {
+        succeeded: Vec::new(),
+        failed: Vec::new(),
+    };
+    for id in ids {
+        let savepoint = tx.savepoint()?;
+        match action(&savepoint, id) {
+            Ok(()) => {
+                savepoint.commit()?;
+                result.succeeded.push(id);
+            }
+            Err(error) => result.failed.push(BulkFailure {
+                id,
+                error: error.to_string(),
+            }),
+        }
+    }
+    tx.commit()?;
+    Ok(result)
+}
+
+fn assign_category(
+    conn: &Connection,
+    id: i64,
+    company_id: i64,
+    category_id: i64,
+) -> Result<(), AppError> {
+    let existing = customers::get_customer_by_id(conn, id, company_id)?
+        .ok_or_else(|| AppError::not_found("Customer not found"))?;
+    conn.execute(
+        "UPDATE customers SET category_id = ?1, updated_at = CURRENT_TIMESTAMP
+         WHERE id = ?2 AND company_id = ?3",
+        params![category_id, id, company_id],
+    )?;
+    let updated = customers::get_customer_by_id(conn, id, company_id)?;
+    audit::record(
+        conn,
+        company_id,
+        "customer",
+        id,
+        AuditAction::Update,
+        Some(&existing),
+        updated.as_ref(),
+    )
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn bulk_assign_category(
+    pool: State<'_, DbPool>,
+    company_id: i64,
+    customer_ids: Vec<i64>,
+    category_id: i64,
+) -> Result<BulkResult, AppError> {
+    let mut conn = db::get_conn(&pool)?;
+    categories::get_category_by_id(&conn, category_id, company_id)?
+        .ok_or_else(|| AppError::not_found("Category not found"))?;
+    run_bulk(&mut conn, customer_ids, |conn, id| {
+        assign_category(conn, id, company_id, category_id)
+    })
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn bulk_delete_customers(
+    pool: State<'_, DbPool>,
+    company_id: i64,
+    ids: Vec<i64>,
+) -> Result<BulkResult, AppError> {
+    let mut conn = db::get_conn(&pool)?;
+    run_bulk(&mut conn, ids, |conn, id| {
+        customers::soft_delete_customer(conn, id, company_id)
+    })
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn bulk_delete_invoices(
+    pool: State<'_, DbPool>,
+    company_id: i64,
+    ids: Vec<i64>,
+) -> Result<BulkResult, AppError> {
+    let mut conn = db::get_conn(&pool)?;
+    run_bulk(&mut conn, ids, |conn, id| {
+        invoices::soft_delete_invoice(conn, id, company_id)
+    })
+}
@@ -0,0 +1,148 @@
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+
+use rusqlite::{Connection, InterruptHandle};
+use tauri::State;
+
+use crate::error::AppError;
+
+// Returned by work that stopped because it was cancelled
+pub const CANCELLED_MESSAGE: &str = "Cancelled by the user";
+
+// Shared flag a long-running operation checks between steps. While a query is being watched,
+// cancelling also interrupts it so a slow report does not have to run to the end.
+#[derive(Clone, Default)]
+pub struct CancelToken {
+    cancelled: Arc<AtomicBool>,
+    interrupt: Arc<Mutex<Option<InterruptHandle>>>,
+}
+
+impl CancelToken {
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+
+    pub fn check(&self) -> Result<(), AppError> {
+        if self.is_cancelled() {
+            return Err(AppError::Cancelled {
+                message: CANCELLED_MESSAGE.to_string(),
+            });
+        }
+        Ok(())
+    }
+
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+        if let Ok(interrupt) = self.interrupt.lock() {
+            if let Some(handle) = interrupt.as_ref() {
+                handle.interrupt();
+            }
+        }
+    }
+
+    // Interrupts statements running on `conn` when cancelled, until the guard is dropped. The
+    // handle must not outlive the guard, as the pooled connection moves on to other work.
+    pub fn watch(&self, conn: &Connection) -> WatchGuard<'_> {
+        if let Ok(mut interrupt) = self.interrupt.lock() {
+            *interrupt = Some(conn.get_interrupt_handle());
+        }
+        WatchGuard { token: self }
+    }
+
+    // Reports an error caused by the interrupt, or any error after a cancellation, as cancelled
+    pub fn map_err(&self, error: impl Into<AppError>) -> AppError {
+        if self.is_cancelled() {
+            AppError::Cancelled {
+                message: CANCELLED_MESSAGE.to_string(),
+            }
+        } else {
+            error.into()
+        }
+    }
+}
+
+pub struct WatchGuard<'a> {
+    token: &'a CancelToken,
+}
+
+impl Drop for WatchGuard<'_> {
+    fn drop(&mut self) {
+        if let Ok(mut interrupt) = self.token.interrupt.lock() {
+            *interrupt = None;
+        }
+    }
+}
+
+// Tokens of the operations that can currently be cancelled, by the id the frontend chose when
+// starting them
+#[derive(Default)]
+pub struct Cancellations {
+    running: Mutex<HashMap<String, CancelToken>>,
+}
+
+impl Cancellations {
+    // Returns the token already registered under `id`, so nested steps of one operation share it
+    pub fn register(&self, id: &str) -> CancelToken {
+        match self.running.lock() {
+            Ok(mut running) => running.entry(id.to_string()).or_default().clone(),
+            Err(_) => CancelToken::default(),
+        }
+    }
+
+    pub fn unregister(&self, id: &str) {
+        if let Ok(mut running) = self.running.lock() {
+            running.remove(id);
+        }
+    }
+
+    pub fn cancel(&self, id: &str) -> bool {
+        let token = match self.running.lock() {
+            Ok(running) => running.get(id).cloned(),
+            Err(_) => None,
+        };
+        match token {
+            Some(token) => {
+                token.cancel();
+                true
+            }
+            None => false,
+        }
+    }
+
+    // Registers a command's `operation_id` for as long as the returned guard lives. Without an
+    // id the operation gets a token nobody can cancel.
+    pub fn start(&self, id: Option<&str>) -> Operation<'_> {
+        let id = id.map(str::trim).filter(|id| !id.is_empty());
+        Operation {
+            token: id.map(|id| self.register(id)).unwrap_or_default(),
+            id: id.map(str::to_string),
+            registry: self,
+        }
+    }
+}
+
+pub struct Operation<'a> {
+    pub token: CancelToken,
+    id: Option<String>,
+    registry: &'a Cancellations,
+}
+
+impl Drop for Operation<'_> {
+    fn drop(&mut self) {
+        if let Some(id) = &self.id {
+            self.registry.unregister(id);
+        }
+    }
+}
+
+// Asks a running operation to stop. It winds down at its next checkpoint and fails with a
+// `cancelled` error; returns false when nothing is running under the id.
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn cancel_operation(
+    cancellations: State<'_, Cancellations>,
+    operation_id: String,
+) -> Result<bool, AppError> {
+    Ok(cancellations.cancel(operation_id.trim()))
+}
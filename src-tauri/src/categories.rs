@@ -0,0 +1,260 @@
+use rusqlite::{params, OptionalExtension, Row};
+use serde::{Deserialize, Serialize};
+use tauri::State;
+
+use crate::audit::{self, AuditAction};
+use crate::db::{self, DbPool};
+use crate::error::AppError;
+
+// Category data model
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Category {
+    pub id: Option<i64>,
+    pub name: String,
+    pub company_id: i64,
+    pub created_at: Option<String>,
+    pub updated_at: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CreateCategory {
+    pub name: String,
+    pub company_id: i64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct UpdateCategory {
+    pub name: Option<String>,
+}
+
+const INITIAL_CATEGORIES: [&str; 3] = ["Raw material", "Scrap", "Regular"];
+
+const SELECT_CATEGORY: &str =
+    "SELECT id, name, company_id, created_at, updated_at FROM categories";
+
+fn category_from_row(row: &Row) -> rusqlite::Result<Category> {
+    Ok(Category {
+        id: row.get("id")?,
+        name: row.get("name")?,
+        company_id: row.get("company_id")?,
+        created_at: row.get("created_at")?,
+        updated_at: row.get("updated_at")?,
+    })
+}
+
+fn validate_create(category: &CreateCategory) -> Result<(), AppError> {
+    if category.name.trim().is_empty() {
+        return Err(AppError::validation("name", "Category name is required"));
+    }
+    if category.name.len() > 100 {
+        return Err(AppError::validation("name", "Category name must be 100 characters or less"));
+    }
+    Ok(())
+}
+
+fn validate_update(category: &UpdateCategory) -> Result<(), AppError> {
+    if let Some(name) = &category.name {
+        if name.trim().is_empty() {
+            return Err(AppError::validation("name", "Category name cannot be empty"));
+        }
+        if name.len() > 100 {
+            return Err(AppError::validation(
+                "name",
+                "Category name must be 100 characters or less",
+            ));
+        }
+    }
+    Ok(())
+}
+
+pub fn seed_initial_categories(conn: &rusqlite::Connection, company_id: i64) -> Result<(), String> {
+    for name in INITIAL_CATEGORIES {
+        conn.execute(
+            "INSERT OR IGNORE INTO categories (name, company_id) VALUES (?1, ?2)",
+            params![name, company_id],
+        )
+        .map_err(|e| e.to_string())?;
+    }
+    Ok(())
+}
+
+pub fn get_category_by_id(
+    conn: &rusqlite::Connection,
+    id: i64,
+    company_id: i64,
+) -> Result<Option<Category>, String> {
+    conn.query_row(
+        &format!(
+            "{} WHERE id = ?1 AND company_id = ?2 AND deleted_at IS NULL",
+            SELECT_CATEGORY
+        ),
+        params![id, company_id],
+        category_from_row,
+    )
+    .optional()
+    .map_err(|e| e.to_string())
+}
+
+pub fn get_category_by_name(
+    conn: &rusqlite::Connection,
+    name: &str,
+    company_id: i64,
+) -> Result<Option<Category>, String> {
+    conn.query_row(
+        &format!(
+            "{} WHERE name = ?1 COLLATE NOCASE AND company_id = ?2 AND deleted_at IS NULL",
+            SELECT_CATEGORY
+        ),
+        params![name.trim(), company_id],
+        category_from_row,
+    )
+    .optional()
+    .map_err(|e| e.to_string())
+}
+
+fn map_write_error(e: rusqlite::Error) -> AppError {
+    let message = e.to_string();
+    if message.contains("UNIQUE constraint failed") {
+        return AppError::conflict(
+            "name",
+            "A category with this name already exists, possibly in the recycle bin",
+        );
+    }
+    AppError::from(e)
+}
+
+// Category validation commands
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn validate_category_create(
+    category: CreateCategory,
+) -> Result<CreateCategory, AppError> {
+    validate_create(&category)?;
+    Ok(category)
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn validate_category_update(
+    category: UpdateCategory,
+) -> Result<UpdateCategory, AppError> {
+    validate_update(&category)?;
+    Ok(category)
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn create_category(
+    pool: State<'_, DbPool>,
+    category: CreateCategory,
+) -> Result<Category, AppError> {
+    validate_create(&category)?;
+
+    let conn = db::get_conn(&pool)?;
+    conn.execute(
+        "INSERT INTO categories (name, company_id) VALUES (?1, ?2)",
+        params![category.name.trim(), category.company_id],
+    )
+    .map_err(map_write_error)?;
+
+    let created = get_category_by_id(&conn, conn.last_insert_rowid(), category.company_id)?
+        .ok_or_else(|| AppError::internal("Category not found after creation"))?;
+    audit::record(
+        &conn,
+        created.company_id,
+        "category",
+        created.id.unwrap_or_default(),
+        AuditAction::Create,
+        None,
+        Some(&created),
+    )?;
+    Ok(created)
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn update_category(
+    pool: State<'_, DbPool>,
+    id: i64,
+    company_id: i64,
+    category: UpdateCategory,
+) -> Result<Category, AppError> {
+    validate_update(&category)?;
+
+    let conn = db::get_conn(&pool)?;
+    let existing = get_category_by_id(&conn, id, company_id)?;
+    let changed = conn
+        .execute(
+            "UPDATE categories SET
+                name = COALESCE(?1, name),
+                updated_at = CURRENT_TIMESTAMP
+             WHERE id = ?2 AND company_id = ?3 AND deleted_at IS NULL",
+            params![category.name.as_deref().map(str::trim), id, company_id],
+        )
+        .map_err(map_write_error)?;
+
+    if changed == 0 {
+        return Err(AppError::not_found("Category not found"));
+    }
+
+    let updated = get_category_by_id(&conn, id, company_id)?
+        .ok_or_else(|| AppError::internal("Category not found after update"))?;
+    audit::record(
+        &conn,
+        company_id,
+        "category",
+        id,
+        AuditAction::Update,
+        existing.as_ref(),
+        Some(&updated),
+    )?;
+    Ok(updated)
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn list_categories(
+    pool: State<'_, DbPool>,
+    company_id: i64,
+) -> Result<Vec<Category>, AppError> {
+    let conn = db::get_conn(&pool)?;
+    let mut stmt = conn.prepare(&format!(
+        "{} WHERE company_id = ?1 AND deleted_at IS NULL ORDER BY name ASC",
+        SELECT_CATEGORY
+    ))?;
+    let categories =
+        stmt.query_map(params![company_id], category_from_row)?.collect::<Result<Vec<_>, _>>()?;
+    Ok(categories)
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn delete_category(
+    pool: State<'_, DbPool>,
+    id: i64,
+    company_id: i64,
+) -> Result<(), AppError> {
+    let conn = db::get_conn(&pool)?;
+
+    let in_use: i64 = conn.query_row(
+        "SELECT COUNT(*) FROM customers
+             WHERE category_id = ?1 AND company_id = ?2 AND deleted_at IS NULL",
+        params![id, company_id],
+        |row| row.get(0),
+    )?;
+    if in_use > 0 {
+        return Err(AppError::invalid("Category is assigned to customers and cannot be deleted"));
+    }
+
+    // Moves the category to the recycle bin; it is purged once the retention period passes
+    let existing = get_category_by_id(&conn, id, company_id)?;
+    let changed = conn.execute(
+        "UPDATE categories SET deleted_at = CURRENT_TIMESTAMP
+             WHERE id = ?1 AND company_id = ?2 AND deleted_at IS NULL",
+        params![id, company_id],
+    )?;
+    if changed == 0 {
+        return Err(AppError::not_found("Category not found"));
+    }
+    audit::record(&conn, company_id, "category", id, AuditAction::Delete, existing.as_ref(), None)
+}
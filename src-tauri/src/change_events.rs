@@ -0,0 +1,221 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex, OnceLock};
+
+use rusqlite::hooks::{Action, AuthAction, AuthContext, Authorization, TransactionOperation};
+use rusqlite::Connection;
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Emitter};
+
+pub const EVENT_CUSTOMERS_CHANGED: &str = "customers://changed";
+pub const EVENT_INVOICES_CHANGED: &str = "invoices://changed";
+pub const EVENT_ITEMS_CHANGED: &str = "items://changed";
+pub const EVENT_CATEGORIES_CHANGED: &str = "categories://changed";
+pub const EVENT_RECEIPTS_CHANGED: &str = "receipts://changed";
+
+// Tables whose row changes are announced, with the event each one is announced on
+const WATCHED_TABLES: &[(&str, &str)] = &[
+    ("customers", EVENT_CUSTOMERS_CHANGED),
+    ("invoices", EVENT_INVOICES_CHANGED),
+    ("items", EVENT_ITEMS_CHANGED),
+    ("categories", EVENT_CATEGORIES_CHANGED),
+    ("receipts", EVENT_RECEIPTS_CHANGED),
+];
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "lowercase")]
+pub enum ChangeOperation {
+    Insert,
+    Update,
+    Delete,
+}
+
+// Payload of the `*://changed` events
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ChangeEvent {
+    pub operation: ChangeOperation,
+    pub ids: Vec<i64>,
+}
+
+type Change = (&'static str, ChangeOperation, i64);
+
+// Watched changes of a connection's open transaction
+#[derive(Default)]
+struct Pending {
+    changes: Vec<Change>,
+    // Each open savepoint with the number of changes made before it, innermost last
+    savepoints: Vec<(String, usize)>,
+}
+
+impl Pending {
+    fn savepoint(&mut self, operation: TransactionOperation, name: &str) {
+        // Savepoint names are case-insensitive and the innermost one of a name is meant
+        let open = self
+            .savepoints
+            .iter()
+            .rposition(|(open, _)| open.eq_ignore_ascii_case(name));
+        match (operation, open) {
+            (TransactionOperation::Begin, _) => {
+                self.savepoints.push((name.to_string(), self.changes.len()));
+            }
+            // ROLLBACK TO keeps the savepoint open but undoes everything since it began
+            (TransactionOperation::Rollback, Some(index)) => {
+                self.changes.truncate(self.savepoints[index].1);
+                self.savepoints.truncate(index + 1);
+            }
+            (TransactionOperation::Release, Some(index)) => self.savepoints.truncate(index),
+            _ => {}
+        }
+    }
+
+    fn clear(&mut self) -> Vec<Change> {
+        self.savepoints.clear();
+        std::mem::take(&mut self.changes)
+    }
+}
+
+// Set once the dispatcher runs; changes committed before that, e.g. by startup migrations,
+// are not announced
+static COMMITTED: OnceLock<Mutex<Sender<Vec<Change>>>> = OnceLock::new();
+
+fn watched_event(table: &str) -> Option<&'static str> {
+    WATCHED_TABLES
+        .iter()
+        .find(|(watched, _)| *watched == table)
+        .map(|(_, event)| *event)
+}
+
+// Collects the watched rows a connection changes and hands them to the dispatcher when the
+// transaction commits. Rolled back changes are dropped, including those undone by rolling back
+// to a savepoint, so listeners only hear about data they can read.
+pub fn install_hooks(conn: &Connection) {
+    let pending: Arc<Mutex<Pending>> = Arc::default();
+
+    let collected = pending.clone();
+    conn.update_hook(Some(
+        move |action: Action, _db: &str, table: &str, row_id: i64| {
+            let operation = match action {
+                Action::SQLITE_INSERT => ChangeOperation::Insert,
+                Action::SQLITE_UPDATE => ChangeOperation::Update,
+                Action::SQLITE_DELETE => ChangeOperation::Delete,
+                _ => return,
+            };
+            if let (Some(event), Ok(mut pending)) = (watched_event(table), collected.lock()) {
+                pending.changes.push((event, operation, row_id));
+            }
+        },
+    ));
+
+    // SQLite has no savepoint hook; the authorizer sees SAVEPOINT, RELEASE and ROLLBACK TO as
+    // they are prepared. Savepoints are always run straight away (`execute_batch` here and in
+    // rusqlite's `Savepoint`), never from a cached statement, so that is when they take effect.
+    let tracked = pending.clone();
+    conn.authorizer(Some(move |context: AuthContext<'_>| {
+        if let AuthAction::Savepoint {
+            operation,
+            savepoint_name,
+        } = context.action
+        {
+            if let Ok(mut pending) = tracked.lock() {
+                pending.savepoint(operation, savepoint_name);
+            }
+        }
+        Authorization::Allow
+    }));
+
+    let committed = pending.clone();
+    conn.commit_hook(Some(move || {
+        let changes = committed
+            .lock()
+            .map(|mut pending| pending.clear())
+            .unwrap_or_default();
+        if !changes.is_empty() {
+            if let Some(Ok(sender)) = COMMITTED.get().map(Mutex::lock) {
+                let _ = sender.send(changes);
+            }
+        }
+        // Returning true would turn the commit into a rollback
+        false
+    }));
+
+    conn.rollback_hook(Some(move || {
+        if let Ok(mut pending) = pending.lock() {
+            pending.clear();
+        }
+    }));
+}
+
+fn emit(app: &AppHandle, changes: Vec<Change>) {
+    let mut grouped: BTreeMap<(&str, ChangeOperation), BTreeSet<i64>> = BTreeMap::new();
+    for (event, operation, row_id) in changes {
+        grouped
+            .entry((event, operation))
+            .or_default()
+            .insert(row_id);
+    }
+    for ((event, operation), ids) in grouped {
+        let payload = ChangeEvent {
+            operation,
+            ids: ids.into_iter().collect(),
+        };
+        let _ = app.emit(event, payload);
+    }
+}
+
+// Sends each commit's changes as they arrive. Commits that queued up while the previous ones
+// were being sent go out together, one event per table and operation.
+fn dispatch(app: AppHandle, committed: Receiver<Vec<Change>>) {
+    while let Ok(mut changes) = committed.recv() {
+        while let Ok(more) = committed.try_recv() {
+            changes.extend(more);
+        }
+        emit(&app, changes);
+    }
+}
+
+// Background thread started at launch that turns committed changes into Tauri events
+pub fn start_dispatcher(app: AppHandle) {
+    let (sender, committed) = mpsc::channel();
+    if COMMITTED.set(Mutex::new(sender)).is_err() {
+        return;
+    }
+    std::thread::spawn(move || dispatch(app, committed));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn change(row_id: i64) -> Change {
+        (EVENT_CUSTOMERS_CHANGED, ChangeOperation::Insert, row_id)
+    }
+
+    #[test]
+    fn rolling_back_to_a_savepoint_drops_its_changes() {
+        let mut pending = Pending::default();
+        pending.changes.push(change(1));
+        pending.savepoint(TransactionOperation::Begin, "step");
+        pending.changes.push(change(2));
+        pending.savepoint(TransactionOperation::Rollback, "STEP");
+        pending.savepoint(TransactionOperation::Release, "step");
+        pending.changes.push(change(3));
+        assert_eq!(pending.clear(), vec![change(1), change(3)]);
+        assert!(pending.savepoints.is_empty());
+    }
+
+    #[test]
+    fn released_savepoints_keep_their_changes() {
+        let mut pending = Pending::default();
+        pending.savepoint(TransactionOperation::Begin, "outer");
+        pending.savepoint(TransactionOperation::Begin, "inner");
+        pending.changes.push(change(1));
+        pending.savepoint(TransactionOperation::Release, "inner");
+        pending.changes.push(change(2));
+        pending.savepoint(TransactionOperation::Rollback, "outer");
+        assert!(pending.changes.is_empty());
+
+        pending.changes.push(change(3));
+        pending.savepoint(TransactionOperation::Release, "outer");
+        assert_eq!(pending.clear(), vec![change(3)]);
+    }
+}
@@ -0,0 +1,234 @@
+use std::path::PathBuf;
+
+use chrono::NaiveDate;
+use serde_json::json;
+
+use crate::companies;
+use crate::db::{self, DATABASE_FILE};
+use crate::encryption;
+use crate::gstr1;
+use crate::invoices::INVOICE_DATE_FORMAT;
+use crate::report_export::{self, ReportFilters, ReportType};
+
+// The app's own config; its identifier names the config dir the database lives in
+const TAURI_CONFIG: &str = include_str!("../tauri.conf.json");
+
+const COMMAND: &str = "export";
+
+const USAGE: &str = "Usage: sales-report export <gstr1|sales-register|customer-wise> \
+--period <YYYY-MM> --out <file> [--company <id>] [--db <path>]";
+
+const EXIT_FAILED: i32 = 1;
+const EXIT_USAGE: i32 = 2;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Export {
+    Gstr1,
+    Report(ReportType),
+}
+
+impl Export {
+    fn parse(value: &str) -> Option<Self> {
+        match value {
+            "gstr1" => Some(Export::Gstr1),
+            "sales-register" => Some(Export::Report(ReportType::SalesRegister)),
+            "customer-wise" => Some(Export::Report(ReportType::CustomerWise)),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Options {
+    export: Export,
+    period: (NaiveDate, NaiveDate),
+    out: String,
+    company_id: Option<i64>,
+    database: Option<PathBuf>,
+}
+
+// Accepts the month as YYYY-MM, or MMYYYY as the GST portal writes it
+fn parse_period(value: &str) -> Result<(NaiveDate, NaiveDate), String> {
+    let value = value.trim();
+    let portal = match value.split_once('-') {
+        Some((year, month)) if year.len() == 4 && month.len() == 2 => format!("{}{}", month, year),
+        _ => value.to_string(),
+    };
+    gstr1::parse_period(&portal).map_err(|_| {
+        format!(
+            "Period must be a month as YYYY-MM, e.g. 2024-05; got {}",
+            value
+        )
+    })
+}
+
+fn parse_args(args: &[String]) -> Result<Options, String> {
+    let mut export = None;
+    let mut period = None;
+    let mut out = None;
+    let mut company_id = None;
+    let mut database = None;
+
+    let mut args = args.iter();
+    while let Some(flag) = args.next() {
+        let mut value = || {
+            args.next()
+                .cloned()
+                .ok_or_else(|| format!("{} needs a value", flag))
+        };
+        match flag.as_str() {
+            name if export.is_none() && !name.starts_with('-') => {
+                export =
+                    Some(Export::parse(name).ok_or_else(|| format!("Unknown export {}", name))?);
+            }
+            "--period" => period = Some(parse_period(&value()?)?),
+            "--out" => out = Some(value()?),
+            "--company" => {
+                let id = value()?;
+                company_id = Some(
+                    id.parse()
+                        .map_err(|_| format!("Invalid company id {}", id))?,
+                );
+            }
+            "--db" => database = Some(PathBuf::from(value()?)),
+            other => return Err(format!("Unknown option {}", other)),
+        }
+    }
+
+    Ok(Options {
+        export: export.ok_or("Name the export to run")?,
+        period: period.ok_or("--period is required")?,
+        out: out.ok_or("--out is required")?,
+        company_id,
+        database,
+    })
+}
+
+fn app_identifier() -> Result<String, String> {
+    let config: serde_json::Value = serde_json::from_str(TAURI_CONFIG)
+        .map_err(|e| format!("Failed to read tauri.conf.json: {}", e))?;
+    config["identifier"]
+        .as_str()
+        .map(str::to_string)
+        .ok_or_else(|| "tauri.conf.json has no identifier".to_string())
+}
+
+// Where the app keeps its database: the platform config dir joined with the app identifier,
+// as Tauri's app_config_dir resolves it
+fn default_database_path() -> Result<PathBuf, String> {
+    let config_dir = if cfg!(target_os = "windows") {
+        std::env::var_os("APPDATA").map(PathBuf::from)
+    } else if cfg!(target_os = "macos") {
+        std::env::var_os("HOME").map(|home| PathBuf::from(home).join("Library/Application Support"))
+    } else {
+        std::env::var_os("XDG_CONFIG_HOME")
+            .map(PathBuf::from)
+            .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))
+    };
+    let config_dir = config_dir
+        .ok_or_else(|| "Could not find the app config directory; pass --db".to_string())?;
+    Ok(config_dir.join(app_identifier()?).join(DATABASE_FILE))
+}
+
+// The company given, else the one last active in the app, else the only company there is
+fn resolve_company(conn: &rusqlite::Connection, company_id: Option<i64>) -> Result<i64, String> {
+    if let Some(id) = company_id {
+        return Ok(id);
+    }
+    if let Some(id) = companies::saved_active_company(conn)? {
+        return Ok(id);
+    }
+    match companies::get_all_companies(conn)?.as_slice() {
+        [only] => only.id.ok_or_else(|| "Company has no id".to_string()),
+        _ => Err("Several companies exist; choose one with --company".to_string()),
+    }
+}
+
+fn export(options: Options) -> Result<serde_json::Value, String> {
+    let path = match options.database {
+        Some(path) => path,
+        None => default_database_path()?,
+    };
+    if !path.exists() {
+        return Err(format!("No database at {}", path.display()));
+    }
+    encryption::prepare(&path)?;
+    let pool = db::init_pool(&path)?;
+    let conn = db::get_conn(&pool)?;
+    let company_id = resolve_company(&conn, options.company_id)?;
+    let (from, to) = options.period;
+
+    match options.export {
+        Export::Gstr1 => {
+            let period = from.format("%m%Y").to_string();
+            let result = gstr1::export_return(&conn, company_id, &period, Some(options.out))?;
+            serde_json::to_value(result).map_err(|e| e.to_string())
+        }
+        Export::Report(report_type) => {
+            let filters = ReportFilters {
+                company_id,
+                from_date: from.format(INVOICE_DATE_FORMAT).to_string(),
+                to_date: to.format(INVOICE_DATE_FORMAT).to_string(),
+                customer_id: None,
+                status: None,
+            };
+            let result = report_export::write_report(&conn, report_type, &filters, options.out)?;
+            serde_json::to_value(result).map_err(|e| e.to_string())
+        }
+    }
+}
+
+// Release builds on Windows use the GUI subsystem and start without a console, so output would
+// go nowhere; borrow the console of the shell that started us instead
+#[cfg(windows)]
+fn attach_console() {
+    const ATTACH_PARENT_PROCESS: u32 = u32::MAX;
+
+    #[link(name = "kernel32")]
+    extern "system" {
+        fn AttachConsole(process_id: u32) -> i32;
+    }
+
+    // SAFETY: AttachConsole takes a plain process id and has no other preconditions; when there
+    // is no parent console it fails and output is dropped as before
+    unsafe {
+        AttachConsole(ATTACH_PARENT_PROCESS);
+    }
+}
+
+#[cfg(not(windows))]
+fn attach_console() {}
+
+// Runs a month-end export without opening a window. Returns None unless the first argument is
+// the export subcommand, so the app starts as usual whatever else it is launched with;
+// otherwise the process exit code. The summary is printed to stdout as JSON and errors to
+// stderr, so scripts can check both.
+pub fn run(args: &[String]) -> Option<i32> {
+    let (command, args) = args.split_first()?;
+    if command != COMMAND {
+        return None;
+    }
+    attach_console();
+    if args.iter().any(|arg| arg == "--help" || arg == "-h") {
+        println!("{}", USAGE);
+        return Some(0);
+    }
+
+    let options = match parse_args(args) {
+        Ok(options) => options,
+        Err(message) => {
+            eprintln!("{}\n{}", message, USAGE);
+            return Some(EXIT_USAGE);
+        }
+    };
+    match export(options) {
+        Ok(summary) => {
+            println!("{}", summary);
+            Some(0)
+        }
+        Err(message) => {
+            eprintln!("{}", json!({ "error": message }));
+            Some(EXIT_FAILED)
+        }
+    }
+}
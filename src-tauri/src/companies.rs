@@ -0,0 +1,345 @@
+use std::sync::Mutex;
+
+use rusqlite::{params, OptionalExtension, Row};
+use serde::{Deserialize, Serialize};
+use tauri::State;
+
+use crate::audit::{self, AuditAction};
+use crate::categories;
+use crate::db::{self, delete_setting, get_setting, set_setting, DbPool};
+use crate::error::AppError;
+use crate::gstin;
+use crate::states;
+
+// Company data model
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Company {
+    pub id: Option<i64>,
+    pub company_name: String,
+    pub gst_no: String,
+    pub state_code: String,
+    pub address: Option<String>,
+    pub city: Option<String>,
+    pub pincode: Option<String>,
+    pub created_at: Option<String>,
+    pub updated_at: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CreateCompany {
+    pub company_name: String,
+    pub gst_no: String,
+    pub state_code: String,
+    #[serde(default)]
+    pub address: Option<String>,
+    #[serde(default)]
+    pub city: Option<String>,
+    #[serde(default)]
+    pub pincode: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct UpdateCompany {
+    pub company_name: Option<String>,
+    pub gst_no: Option<String>,
+    pub state_code: Option<String>,
+    pub address: Option<String>,
+    pub city: Option<String>,
+    pub pincode: Option<String>,
+}
+
+const SELECT_COMPANY: &str = "SELECT id, company_name, gst_no, state_code, address, city, pincode,
+    created_at, updated_at FROM companies";
+
+fn company_from_row(row: &Row) -> rusqlite::Result<Company> {
+    Ok(Company {
+        id: row.get("id")?,
+        company_name: row.get("company_name")?,
+        gst_no: row.get("gst_no")?,
+        state_code: row.get("state_code")?,
+        address: row.get("address")?,
+        city: row.get("city")?,
+        pincode: row.get("pincode")?,
+        created_at: row.get("created_at")?,
+        updated_at: row.get("updated_at")?,
+    })
+}
+
+fn validate_create(company: &CreateCompany) -> Result<(), AppError> {
+    if company.company_name.trim().is_empty() {
+        return Err(AppError::validation("company_name", "Company name is required"));
+    }
+    if company.company_name.len() > 255 {
+        return Err(AppError::validation(
+            "company_name",
+            "Company name must be 255 characters or less",
+        ));
+    }
+    if company.gst_no.trim().is_empty() {
+        return Err(AppError::validation("gst_no", "GST number is required"));
+    }
+    gstin::check_gstin(&company.gst_no).map_err(|e| AppError::validation("gst_no", e))?;
+    // A blank state code is filled in from the GSTIN
+    validate_address(company.address.as_deref(), company.pincode.as_deref())
+}
+
+// Shared with customers; both are optional but a pincode must be a valid Indian PIN
+pub fn validate_address(address: Option<&str>, pincode: Option<&str>) -> Result<(), AppError> {
+    if let Some(address) = address {
+        if address.len() > 500 {
+            return Err(AppError::validation("address", "Address must be 500 characters or less"));
+        }
+    }
+    if let Some(pincode) = pincode.map(str::trim).filter(|p| !p.is_empty()) {
+        if pincode.len() != 6
+            || !pincode.chars().all(|c| c.is_ascii_digit())
+            || pincode.starts_with('0')
+        {
+            return Err(AppError::validation("pincode", "Pincode must be a 6-digit number"));
+        }
+    }
+    Ok(())
+}
+
+fn validate_update(company: &UpdateCompany) -> Result<(), AppError> {
+    if let Some(name) = &company.company_name {
+        if name.trim().is_empty() {
+            return Err(AppError::validation("company_name", "Company name cannot be empty"));
+        }
+        if name.len() > 255 {
+            return Err(AppError::validation(
+                "company_name",
+                "Company name must be 255 characters or less",
+            ));
+        }
+    }
+
+    if let Some(gst_no) = &company.gst_no {
+        if gst_no.trim().is_empty() {
+            return Err(AppError::validation("gst_no", "GST number cannot be empty"));
+        }
+        gstin::check_gstin(gst_no).map_err(|e| AppError::validation("gst_no", e))?;
+    }
+
+    if let Some(state_code) = &company.state_code {
+        if state_code.trim().is_empty() {
+            return Err(AppError::validation("state_code", "State code cannot be empty"));
+        }
+    }
+
+    validate_address(company.address.as_deref(), company.pincode.as_deref())
+}
+
+pub fn get_company_by_id(
+    conn: &rusqlite::Connection,
+    id: i64,
+) -> Result<Option<Company>, AppError> {
+    conn.query_row(&format!("{} WHERE id = ?1", SELECT_COMPANY), params![id], company_from_row)
+        .optional()
+        .map_err(AppError::from)
+}
+
+// Company the user is currently working in, remembered across restarts. Commands may only
+// touch this company's records; the invoke guard refuses any other company id.
+#[derive(Default)]
+pub struct ActiveCompany(Mutex<Option<i64>>);
+
+impl ActiveCompany {
+    // Falls back to the company chosen in a previous session
+    pub fn current(&self, conn: &rusqlite::Connection) -> Result<Option<i64>, String> {
+        let mut current = self.0.lock().map_err(|e| e.to_string())?;
+        if current.is_none() {
+            *current = saved_active_company(conn)?;
+        }
+        Ok(*current)
+    }
+}
+
+const SETTING_ACTIVE_COMPANY: &str = "active_company_id";
+
+pub fn saved_active_company(conn: &rusqlite::Connection) -> Result<Option<i64>, String> {
+    Ok(get_setting(conn, SETTING_ACTIVE_COMPANY)?.and_then(|id| id.parse().ok()))
+}
+
+fn map_write_error(e: rusqlite::Error) -> AppError {
+    let message = e.to_string();
+    if message.contains("UNIQUE constraint failed: companies.gst_no") {
+        return AppError::conflict("gst_no", "A company with this GST number already exists");
+    }
+    AppError::from(e)
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn create_company(
+    pool: State<'_, DbPool>,
+    company: CreateCompany,
+) -> Result<Company, AppError> {
+    let mut conn = db::get_conn(&pool)?;
+    let tx = conn.transaction()?;
+    let created = create_company_in(&tx, company)?;
+    tx.commit()?;
+    Ok(created)
+}
+
+// Validates and inserts a company with its initial categories, on a connection the caller
+// commits
+pub fn create_company_in(
+    conn: &rusqlite::Connection,
+    mut company: CreateCompany,
+) -> Result<Company, AppError> {
+    validate_create(&company)?;
+    company.state_code = states::resolve_state_code(&company.gst_no, &company.state_code)?;
+
+    conn.execute(
+        "INSERT INTO companies (company_name, gst_no, state_code, address, city, pincode)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+        params![
+            company.company_name.trim(),
+            company.gst_no.trim(),
+            company.state_code.trim(),
+            company.address.as_deref().map(str::trim),
+            company.city.as_deref().map(str::trim),
+            company.pincode.as_deref().map(str::trim)
+        ],
+    )
+    .map_err(map_write_error)?;
+    let company_id = conn.last_insert_rowid();
+
+    // Seed initial categories for this company
+    categories::seed_initial_categories(conn, company_id)?;
+
+    let created = get_company_by_id(conn, company_id)?
+        .ok_or_else(|| AppError::internal("Company not found after creation"))?;
+    audit::record(
+        conn,
+        company_id,
+        "company",
+        company_id,
+        AuditAction::Create,
+        None,
+        Some(&created),
+    )?;
+    Ok(created)
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn validate_company_update(company: UpdateCompany) -> Result<UpdateCompany, AppError> {
+    validate_update(&company)?;
+    Ok(company)
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn update_company(
+    pool: State<'_, DbPool>,
+    id: i64,
+    mut company: UpdateCompany,
+) -> Result<Company, AppError> {
+    validate_update(&company)?;
+
+    let conn = db::get_conn(&pool)?;
+    let existing =
+        get_company_by_id(&conn, id)?.ok_or_else(|| AppError::not_found("Company not found"))?;
+
+    // Re-check the state code whenever either side of the GSTIN/state pair changes
+    if company.gst_no.is_some() || company.state_code.is_some() {
+        let gst_no = company.gst_no.as_deref().unwrap_or(&existing.gst_no);
+        // A new GSTIN without an explicit state takes its state from the GSTIN
+        let requested_state = company.state_code.as_deref().unwrap_or("");
+        company.state_code = Some(states::resolve_state_code(gst_no, requested_state)?);
+    }
+
+    let changed = conn
+        .execute(
+            "UPDATE companies SET
+                company_name = COALESCE(?1, company_name),
+                gst_no = COALESCE(?2, gst_no),
+                state_code = COALESCE(?3, state_code),
+                address = COALESCE(?4, address),
+                city = COALESCE(?5, city),
+                pincode = COALESCE(?6, pincode),
+                updated_at = CURRENT_TIMESTAMP
+             WHERE id = ?7",
+            params![
+                company.company_name.as_deref().map(str::trim),
+                company.gst_no.as_deref().map(str::trim),
+                company.state_code.as_deref().map(str::trim),
+                company.address.as_deref().map(str::trim),
+                company.city.as_deref().map(str::trim),
+                company.pincode.as_deref().map(str::trim),
+                id
+            ],
+        )
+        .map_err(map_write_error)?;
+
+    if changed == 0 {
+        return Err(AppError::not_found("Company not found"));
+    }
+
+    let updated = get_company_by_id(&conn, id)?
+        .ok_or_else(|| AppError::internal("Company not found after update"))?;
+    audit::record(&conn, id, "company", id, AuditAction::Update, Some(&existing), Some(&updated))?;
+    Ok(updated)
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn get_company(pool: State<'_, DbPool>, id: i64) -> Result<Option<Company>, AppError> {
+    let conn = db::get_conn(&pool)?;
+    get_company_by_id(&conn, id)
+}
+
+pub fn get_all_companies(conn: &rusqlite::Connection) -> Result<Vec<Company>, AppError> {
+    let mut stmt = conn.prepare(&format!("{} ORDER BY created_at DESC", SELECT_COMPANY))?;
+    let companies = stmt.query_map([], company_from_row)?.collect::<Result<Vec<_>, _>>()?;
+    Ok(companies)
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn list_companies(pool: State<'_, DbPool>) -> Result<Vec<Company>, AppError> {
+    let conn = db::get_conn(&pool)?;
+    get_all_companies(&conn)
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn switch_active_company(
+    pool: State<'_, DbPool>,
+    active: State<'_, ActiveCompany>,
+    company_id: i64,
+) -> Result<Company, AppError> {
+    let conn = db::get_conn(&pool)?;
+    let company = get_company_by_id(&conn, company_id)?
+        .ok_or_else(|| AppError::not_found("Company not found"))?;
+    set_setting(&conn, SETTING_ACTIVE_COMPANY, &company_id.to_string())?;
+    *active.0.lock().map_err(|e| e.to_string())? = Some(company_id);
+    Ok(company)
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn clear_active_company(
+    pool: State<'_, DbPool>,
+    active: State<'_, ActiveCompany>,
+) -> Result<(), AppError> {
+    let conn = db::get_conn(&pool)?;
+    delete_setting(&conn, SETTING_ACTIVE_COMPANY)?;
+    *active.0.lock().map_err(|e| e.to_string())? = None;
+    Ok(())
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn get_active_company(
+    pool: State<'_, DbPool>,
+    active: State<'_, ActiveCompany>,
+) -> Result<Option<Company>, AppError> {
+    let conn = db::get_conn(&pool)?;
+    match active.current(&conn)? {
+        Some(id) => Ok(get_company_by_id(&conn, id)?),
+        None => Ok(None),
+    }
+}
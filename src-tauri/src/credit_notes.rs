@@ -0,0 +1,768 @@
+use std::collections::HashMap;
+
+use chrono::NaiveDate;
+use rusqlite::{params, Connection, OptionalExtension, Row};
+use serde::{Deserialize, Serialize};
+use tauri::State;
+
+use crate::companies;
+use crate::db::{self, DbPool};
+use crate::error::AppError;
+use crate::financial_years::{self, fy_start_year};
+use crate::invoices::{self, round2, Invoice, InvoiceLine, InvoiceStatus, INVOICE_DATE_FORMAT};
+use crate::numbering::{self, DocumentType};
+use crate::place_of_supply;
+use crate::tax::{self, TaxRegime, AMOUNT_TOLERANCE};
+
+// Credit and debit note data model; both adjust a single original invoice
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum NoteType {
+    Credit,
+    Debit,
+}
+
+impl NoteType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            NoteType::Credit => "credit",
+            NoteType::Debit => "debit",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "credit" => Some(NoteType::Credit),
+            "debit" => Some(NoteType::Debit),
+            _ => None,
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            NoteType::Credit => "Credit note",
+            NoteType::Debit => "Debit note",
+        }
+    }
+
+    // Note type code used by the GST portal
+    pub fn portal_code(&self) -> &'static str {
+        match self {
+            NoteType::Credit => "C",
+            NoteType::Debit => "D",
+        }
+    }
+
+    fn document_type(&self) -> DocumentType {
+        match self {
+            NoteType::Credit => DocumentType::CreditNote,
+            NoteType::Debit => DocumentType::DebitNote,
+        }
+    }
+}
+
+// Reason codes from the GST portal's credit/debit note schema
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum NoteReason {
+    SalesReturn,
+    PostSaleDiscount,
+    DeficiencyInServices,
+    CorrectionInInvoice,
+    ChangeInPlaceOfSupply,
+    ProvisionalAssessment,
+    Others,
+}
+
+impl NoteReason {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            NoteReason::SalesReturn => "01",
+            NoteReason::PostSaleDiscount => "02",
+            NoteReason::DeficiencyInServices => "03",
+            NoteReason::CorrectionInInvoice => "04",
+            NoteReason::ChangeInPlaceOfSupply => "05",
+            NoteReason::ProvisionalAssessment => "06",
+            NoteReason::Others => "07",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "01" => Some(NoteReason::SalesReturn),
+            "02" => Some(NoteReason::PostSaleDiscount),
+            "03" => Some(NoteReason::DeficiencyInServices),
+            "04" => Some(NoteReason::CorrectionInInvoice),
+            "05" => Some(NoteReason::ChangeInPlaceOfSupply),
+            "06" => Some(NoteReason::ProvisionalAssessment),
+            "07" => Some(NoteReason::Others),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct CreditDebitNote {
+    pub id: Option<i64>,
+    pub company_id: i64,
+    pub invoice_id: i64,
+    pub note_type: NoteType,
+    pub note_number: String,
+    pub note_date: String,
+    pub reason: NoteReason,
+    pub taxable_value: f64,
+    pub cgst_amount: f64,
+    pub sgst_amount: f64,
+    pub igst_amount: f64,
+    pub total_amount: f64,
+    pub status: InvoiceStatus,
+    pub notes: Option<String>,
+    pub created_at: Option<String>,
+    pub updated_at: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct CreditDebitNoteLine {
+    pub id: Option<i64>,
+    pub note_id: i64,
+    pub line_no: i64,
+    pub invoice_line_id: Option<i64>,
+    pub description: String,
+    pub hsn_code: String,
+    pub quantity: f64,
+    pub taxable_value: f64,
+    pub gst_rate: f64,
+    pub cgst_amount: f64,
+    pub sgst_amount: f64,
+    pub igst_amount: f64,
+}
+
+// Either reverses part of an original invoice line or describes a free-standing adjustment;
+// tax amounts are always computed here from the value and rate
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct NoteLineInput {
+    // Description, HSN code and rate default to the original line's
+    pub invoice_line_id: Option<i64>,
+    #[serde(default)]
+    pub description: String,
+    #[serde(default)]
+    pub hsn_code: String,
+    #[serde(default)]
+    pub quantity: f64,
+    // Defaults to the original line's value for `quantity` when reversing an invoice line
+    pub taxable_value: Option<f64>,
+    pub gst_rate: Option<f64>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CreateCreditDebitNote {
+    pub company_id: i64,
+    pub invoice_id: i64,
+    pub note_type: NoteType,
+    // Left empty to take the next number from the note type's sequence
+    #[serde(default)]
+    pub note_number: String,
+    pub note_date: String,
+    pub reason: NoteReason,
+    pub status: Option<InvoiceStatus>,
+    pub notes: Option<String>,
+    pub lines: Vec<NoteLineInput>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CreditDebitNoteWithLines {
+    #[serde(flatten)]
+    pub note: CreditDebitNote,
+    pub lines: Vec<CreditDebitNoteLine>,
+}
+
+const SELECT_NOTE: &str = "
+    SELECT id, company_id, invoice_id, note_type, note_number, note_date, reason, taxable_value,
+           cgst_amount, sgst_amount, igst_amount, total_amount, status, notes, created_at,
+           updated_at
+    FROM credit_debit_notes";
+
+const SELECT_NOTE_LINE: &str = "
+    SELECT id, note_id, line_no, invoice_line_id, description, hsn_code, quantity, taxable_value,
+           gst_rate, cgst_amount, sgst_amount, igst_amount
+    FROM credit_debit_note_lines";
+
+fn note_from_row(row: &Row) -> rusqlite::Result<CreditDebitNote> {
+    let note_type: String = row.get("note_type")?;
+    let reason: String = row.get("reason")?;
+    let status: String = row.get("status")?;
+    Ok(CreditDebitNote {
+        id: row.get("id")?,
+        company_id: row.get("company_id")?,
+        invoice_id: row.get("invoice_id")?,
+        note_type: NoteType::parse(&note_type).unwrap_or(NoteType::Credit),
+        note_number: row.get("note_number")?,
+        note_date: row.get("note_date")?,
+        reason: NoteReason::parse(&reason).unwrap_or(NoteReason::Others),
+        taxable_value: row.get("taxable_value")?,
+        cgst_amount: row.get("cgst_amount")?,
+        sgst_amount: row.get("sgst_amount")?,
+        igst_amount: row.get("igst_amount")?,
+        total_amount: row.get("total_amount")?,
+        status: InvoiceStatus::parse(&status).unwrap_or(InvoiceStatus::Draft),
+        notes: row.get("notes")?,
+        created_at: row.get("created_at")?,
+        updated_at: row.get("updated_at")?,
+    })
+}
+
+fn note_line_from_row(row: &Row) -> rusqlite::Result<CreditDebitNoteLine> {
+    Ok(CreditDebitNoteLine {
+        id: row.get("id")?,
+        note_id: row.get("note_id")?,
+        line_no: row.get("line_no")?,
+        invoice_line_id: row.get("invoice_line_id")?,
+        description: row.get("description")?,
+        hsn_code: row.get("hsn_code")?,
+        quantity: row.get("quantity")?,
+        taxable_value: row.get("taxable_value")?,
+        gst_rate: row.get("gst_rate")?,
+        cgst_amount: row.get("cgst_amount")?,
+        sgst_amount: row.get("sgst_amount")?,
+        igst_amount: row.get("igst_amount")?,
+    })
+}
+
+fn map_write_error(e: rusqlite::Error) -> AppError {
+    let message = e.to_string();
+    if message.contains("UNIQUE constraint failed") {
+        return AppError::conflict(
+            "note_number",
+            "A credit or debit note with this number already exists for this company",
+        );
+    }
+    AppError::from(e)
+}
+
+pub fn get_note_by_id(
+    conn: &Connection,
+    id: i64,
+    company_id: i64,
+) -> Result<Option<CreditDebitNote>, AppError> {
+    conn.query_row(
+        &format!("{} WHERE id = ?1 AND company_id = ?2", SELECT_NOTE),
+        params![id, company_id],
+        note_from_row,
+    )
+    .optional()
+    .map_err(AppError::from)
+}
+
+pub fn get_note_lines(
+    conn: &Connection,
+    note_id: i64,
+) -> Result<Vec<CreditDebitNoteLine>, AppError> {
+    let mut stmt =
+        conn.prepare(&format!("{} WHERE note_id = ?1 ORDER BY line_no", SELECT_NOTE_LINE))?;
+    let lines =
+        stmt.query_map(params![note_id], note_line_from_row)?.collect::<Result<Vec<_>, _>>()?;
+    Ok(lines)
+}
+
+// Every note dated within the range, whatever its status
+pub fn get_notes_in_range(
+    conn: &Connection,
+    company_id: i64,
+    from: &str,
+    to: &str,
+) -> Result<Vec<CreditDebitNote>, AppError> {
+    let mut stmt = conn.prepare(&format!(
+        "{} WHERE company_id = ?1 AND note_date BETWEEN ?2 AND ?3
+             ORDER BY note_date, note_number",
+        SELECT_NOTE
+    ))?;
+    let notes = stmt
+        .query_map(params![company_id, from, to], note_from_row)?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(notes)
+}
+
+// Notes that still adjust their invoice, i.e. anything not cancelled
+pub fn has_active_notes(conn: &Connection, invoice_id: i64) -> Result<bool, AppError> {
+    conn.query_row(
+        "SELECT EXISTS(SELECT 1 FROM credit_debit_notes
+                       WHERE invoice_id = ?1 AND status != 'cancelled')",
+        params![invoice_id],
+        |row| row.get(0),
+    )
+    .map_err(AppError::from)
+}
+
+fn compute_line(
+    index: usize,
+    input: &NoteLineInput,
+    original_lines: &[InvoiceLine],
+    regime: TaxRegime,
+) -> Result<CreditDebitNoteLine, AppError> {
+    let line_no = index + 1;
+    if !input.quantity.is_finite() || input.quantity < 0.0 {
+        return Err(AppError::validation(
+            "lines",
+            format!("Line {}: quantity must be a non-negative number", line_no),
+        ));
+    }
+
+    let (description, hsn_code, taxable_value, gst_rate) = match input.invoice_line_id {
+        Some(line_id) => {
+            let original =
+                original_lines.iter().find(|line| line.id == Some(line_id)).ok_or_else(|| {
+                    AppError::validation(
+                        "lines",
+                        format!(
+                            "Line {}: the referenced line is not on the original invoice",
+                            line_no
+                        ),
+                    )
+                })?;
+            let taxable_value = match input.taxable_value {
+                Some(value) => value,
+                None if input.quantity > 0.0 && original.quantity > 0.0 => {
+                    original.taxable_value * input.quantity / original.quantity
+                }
+                None => {
+                    return Err(AppError::validation(
+                        "lines",
+                        format!("Line {}: enter a quantity or a taxable value", line_no),
+                    ))
+                }
+            };
+            let description = Some(input.description.trim())
+                .filter(|d| !d.is_empty())
+                .unwrap_or(&original.description)
+                .to_string();
+            let hsn_code = Some(input.hsn_code.trim())
+                .filter(|h| !h.is_empty())
+                .unwrap_or(&original.hsn_code)
+                .to_string();
+            (
+                description,
+                hsn_code,
+                taxable_value,
+                input.gst_rate.unwrap_or(original.gst_rate),
+            )
+        }
+        None => {
+            let description = input.description.trim().to_string();
+            if description.is_empty() {
+                return Err(AppError::validation(
+                    "lines",
+                    format!("Line {}: description is required", line_no),
+                ));
+            }
+            let taxable_value = input.taxable_value.ok_or_else(|| {
+                AppError::validation(
+                    "lines",
+                    format!("Line {}: taxable value is required", line_no),
+                )
+            })?;
+            let gst_rate = input.gst_rate.ok_or_else(|| {
+                AppError::validation("lines", format!("Line {}: GST rate is required", line_no))
+            })?;
+            (description, input.hsn_code.trim().to_string(), taxable_value, gst_rate)
+        }
+    };
+
+    if description.len() > 500 {
+        return Err(AppError::validation(
+            "lines",
+            format!("Line {}: description must be 500 characters or less", line_no),
+        ));
+    }
+    if !(4..=8).contains(&hsn_code.len()) || !hsn_code.chars().all(|c| c.is_ascii_digit()) {
+        return Err(AppError::validation(
+            "lines",
+            format!("Line {}: HSN/SAC code must be 4 to 8 digits", line_no),
+        ));
+    }
+    if !taxable_value.is_finite() || taxable_value <= 0.0 {
+        return Err(AppError::validation(
+            "lines",
+            format!("Line {}: taxable value must be greater than zero", line_no),
+        ));
+    }
+    if !gst_rate.is_finite() || !(0.0..=100.0).contains(&gst_rate) {
+        return Err(AppError::validation(
+            "lines",
+            format!("Line {}: GST rate must be between 0 and 100%", line_no),
+        ));
+    }
+
+    let taxable_value = round2(taxable_value);
+    let split = tax::tax_on(taxable_value, gst_rate, regime);
+    Ok(CreditDebitNoteLine {
+        id: None,
+        note_id: 0,
+        line_no: line_no as i64,
+        invoice_line_id: input.invoice_line_id,
+        description,
+        hsn_code,
+        quantity: input.quantity,
+        taxable_value,
+        gst_rate,
+        cgst_amount: split.cgst_amount,
+        sgst_amount: split.sgst_amount,
+        igst_amount: split.igst_amount,
+    })
+}
+
+// Credit notes may not take back more than the invoice charged, in value or per-line quantity
+fn check_credit_limits(
+    conn: &Connection,
+    invoice: &Invoice,
+    original_lines: &[InvoiceLine],
+    lines: &[CreditDebitNoteLine],
+) -> Result<(), AppError> {
+    let invoice_id = invoice.id.unwrap_or_default();
+    let credited: f64 = conn.query_row(
+        "SELECT COALESCE(SUM(taxable_value), 0) FROM credit_debit_notes
+             WHERE invoice_id = ?1 AND note_type = 'credit' AND status != 'cancelled'",
+        params![invoice_id],
+        |row| row.get(0),
+    )?;
+    let requested: f64 = lines.iter().map(|l| l.taxable_value).sum();
+    if credited + requested > invoice.taxable_value + AMOUNT_TOLERANCE {
+        return Err(AppError::validation(
+            "lines",
+            format!(
+                "Credit notes would exceed the taxable value of invoice {} ({:.2} already credited)",
+                invoice.invoice_number, credited
+            ),
+        ));
+    }
+
+    let mut stmt = conn.prepare(
+        "SELECT l.invoice_line_id, COALESCE(SUM(l.quantity), 0)
+             FROM credit_debit_note_lines l
+             JOIN credit_debit_notes n ON n.id = l.note_id
+             WHERE n.invoice_id = ?1 AND n.note_type = 'credit' AND n.status != 'cancelled'
+               AND l.invoice_line_id IS NOT NULL
+             GROUP BY l.invoice_line_id",
+    )?;
+    let mut returned: HashMap<i64, f64> = stmt
+        .query_map(params![invoice_id], |row| Ok((row.get(0)?, row.get(1)?)))?
+        .collect::<Result<_, _>>()?;
+
+    for line in lines {
+        let Some(line_id) = line.invoice_line_id else {
+            continue;
+        };
+        let total = returned.entry(line_id).or_insert(0.0);
+        *total += line.quantity;
+        let original = original_lines.iter().find(|l| l.id == Some(line_id));
+        if let Some(original) = original {
+            if *total > original.quantity + AMOUNT_TOLERANCE {
+                return Err(AppError::validation(
+                    "lines",
+                    format!(
+                        "Line {}: only {} of '{}' was invoiced",
+                        line.line_no, original.quantity, original.description
+                    ),
+                ));
+            }
+        }
+    }
+    Ok(())
+}
+
+// Section 34(2) of the CGST Act: a credit note must be issued by 30 November following the
+// end of the financial year of the original supply
+fn credit_note_deadline(invoice_date: NaiveDate) -> Option<NaiveDate> {
+    NaiveDate::from_ymd_opt(fy_start_year(invoice_date) + 1, 11, 30)
+}
+
+fn insert_note(conn: &Connection, note: &CreditDebitNote) -> Result<i64, AppError> {
+    conn.execute(
+        "INSERT INTO credit_debit_notes (company_id, invoice_id, note_type, note_number, note_date,
+                                         reason, taxable_value, cgst_amount, sgst_amount,
+                                         igst_amount, total_amount, status, notes)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13)",
+        params![
+            note.company_id,
+            note.invoice_id,
+            note.note_type.as_str(),
+            note.note_number,
+            note.note_date,
+            note.reason.as_str(),
+            note.taxable_value,
+            note.cgst_amount,
+            note.sgst_amount,
+            note.igst_amount,
+            note.total_amount,
+            note.status.as_str(),
+            note.notes
+        ],
+    )
+    .map_err(map_write_error)?;
+    Ok(conn.last_insert_rowid())
+}
+
+fn insert_note_lines(
+    conn: &Connection,
+    note_id: i64,
+    lines: &[CreditDebitNoteLine],
+) -> Result<(), AppError> {
+    let mut stmt = conn.prepare(
+        "INSERT INTO credit_debit_note_lines (note_id, line_no, invoice_line_id, description,
+                                                  hsn_code, quantity, taxable_value, gst_rate,
+                                                  cgst_amount, sgst_amount, igst_amount)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)",
+    )?;
+    for line in lines {
+        stmt.execute(params![
+            note_id,
+            line.line_no,
+            line.invoice_line_id,
+            line.description,
+            line.hsn_code,
+            line.quantity,
+            line.taxable_value,
+            line.gst_rate,
+            line.cgst_amount,
+            line.sgst_amount,
+            line.igst_amount
+        ])?;
+    }
+    Ok(())
+}
+
+fn note_with_lines(
+    conn: &Connection,
+    id: i64,
+    company_id: i64,
+) -> Result<Option<CreditDebitNoteWithLines>, String> {
+    let Some(note) = get_note_by_id(conn, id, company_id)? else {
+        return Ok(None);
+    };
+    let lines = get_note_lines(conn, id)?;
+    Ok(Some(CreditDebitNoteWithLines { note, lines }))
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn create_credit_debit_note(
+    pool: State<'_, DbPool>,
+    note: CreateCreditDebitNote,
+) -> Result<CreditDebitNoteWithLines, AppError> {
+    let note_date =
+        NaiveDate::parse_from_str(note.note_date.trim(), INVOICE_DATE_FORMAT).map_err(|_| {
+            AppError::validation("note_date", "Note date must be a valid date in YYYY-MM-DD format")
+        })?;
+    if note.lines.is_empty() {
+        return Err(AppError::validation("lines", "At least one line is required"));
+    }
+    if note.note_number.len() > 50 {
+        return Err(AppError::validation(
+            "note_number",
+            "Note number must be 50 characters or less",
+        ));
+    }
+    if let Some(notes) = &note.notes {
+        if notes.len() > 1000 {
+            return Err(AppError::validation("notes", "Notes must be 1000 characters or less"));
+        }
+    }
+    let status = note.status.unwrap_or(InvoiceStatus::Draft);
+    if status == InvoiceStatus::Cancelled {
+        return Err(AppError::validation("status", "A note cannot be created as cancelled"));
+    }
+
+    let mut conn = db::get_conn(&pool)?;
+    let tx = conn.transaction()?;
+    let company = companies::get_company_by_id(&tx, note.company_id)?
+        .ok_or_else(|| AppError::not_found("Company not found"))?;
+    let invoice = invoices::get_invoice_by_id(&tx, note.invoice_id, note.company_id)?
+        .ok_or_else(|| AppError::not_found("Original invoice not found"))?;
+    if invoice.status != InvoiceStatus::Issued {
+        return Err(AppError::validation(
+            "invoice_id",
+            "Notes can only be raised against issued invoices",
+        ));
+    }
+
+    let invoice_date = NaiveDate::parse_from_str(&invoice.invoice_date, INVOICE_DATE_FORMAT)
+        .map_err(|_| {
+            AppError::internal(format!("Invoice {} has an invalid date", invoice.invoice_number))
+        })?;
+    if note_date < invoice_date {
+        return Err(AppError::validation(
+            "note_date",
+            "Note date cannot be before the original invoice date",
+        ));
+    }
+    if note.note_type == NoteType::Credit {
+        if let Some(deadline) = credit_note_deadline(invoice_date) {
+            if note_date > deadline {
+                return Err(AppError::validation(
+                    "note_date",
+                    format!(
+                        "Credit notes against invoice {} must be issued by {}",
+                        invoice.invoice_number,
+                        deadline.format("%d %B %Y")
+                    ),
+                ));
+            }
+        }
+    }
+    financial_years::ensure_period_open(&tx, note.company_id, &note.note_date)?;
+
+    let regime = place_of_supply::decide(
+        &company.state_code,
+        &invoice.place_of_supply,
+        invoice.supply_kind,
+    );
+    let original_lines = invoices::get_invoice_lines(&tx, note.invoice_id)?;
+    let lines = note
+        .lines
+        .iter()
+        .enumerate()
+        .map(|(index, input)| compute_line(index, input, &original_lines, regime))
+        .collect::<Result<Vec<_>, _>>()?;
+    if note.note_type == NoteType::Credit {
+        check_credit_limits(&tx, &invoice, &original_lines, &lines)?;
+    }
+
+    let note_number = match note.note_number.trim() {
+        "" => numbering::allocate_number(
+            &tx,
+            note.company_id,
+            note.note_type.document_type(),
+            &note.note_date,
+        )?,
+        number => number.to_string(),
+    };
+    let sum = |f: fn(&CreditDebitNoteLine) -> f64| round2(lines.iter().map(f).sum());
+    let (taxable_value, cgst_amount, sgst_amount, igst_amount) = (
+        sum(|l| l.taxable_value),
+        sum(|l| l.cgst_amount),
+        sum(|l| l.sgst_amount),
+        sum(|l| l.igst_amount),
+    );
+    let record = CreditDebitNote {
+        id: None,
+        company_id: note.company_id,
+        invoice_id: note.invoice_id,
+        note_type: note.note_type,
+        note_number,
+        note_date: note.note_date.trim().to_string(),
+        reason: note.reason,
+        taxable_value,
+        cgst_amount,
+        sgst_amount,
+        igst_amount,
+        total_amount: round2(taxable_value + cgst_amount + sgst_amount + igst_amount),
+        status,
+        notes: note.notes,
+        created_at: None,
+        updated_at: None,
+    };
+
+    let id = insert_note(&tx, &record)?;
+    insert_note_lines(&tx, id, &lines)?;
+    let created = note_with_lines(&tx, id, note.company_id)?
+        .ok_or_else(|| AppError::internal("Note not found after creation"))?;
+    tx.commit()?;
+    Ok(created)
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn get_credit_debit_note(
+    pool: State<'_, DbPool>,
+    id: i64,
+    company_id: i64,
+) -> Result<Option<CreditDebitNoteWithLines>, AppError> {
+    let conn = db::get_conn(&pool)?;
+    Ok(note_with_lines(&conn, id, company_id)?)
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn list_credit_debit_notes(
+    pool: State<'_, DbPool>,
+    company_id: i64,
+    invoice_id: Option<i64>,
+) -> Result<Vec<CreditDebitNote>, AppError> {
+    let conn = db::get_conn(&pool)?;
+    let mut stmt = conn.prepare(&format!(
+        "{} WHERE company_id = ?1 AND (?2 IS NULL OR invoice_id = ?2)
+             ORDER BY note_date DESC, id DESC",
+        SELECT_NOTE
+    ))?;
+    let notes = stmt
+        .query_map(params![company_id, invoice_id], note_from_row)?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(notes)
+}
+
+// Drafts may be issued or cancelled; issued notes may only be cancelled
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn set_credit_debit_note_status(
+    pool: State<'_, DbPool>,
+    id: i64,
+    company_id: i64,
+    status: InvoiceStatus,
+) -> Result<CreditDebitNote, AppError> {
+    let conn = db::get_conn(&pool)?;
+    let existing = get_note_by_id(&conn, id, company_id)?
+        .ok_or_else(|| AppError::not_found("Note not found"))?;
+
+    let allowed = matches!(
+        (existing.status, status),
+        (InvoiceStatus::Draft, InvoiceStatus::Issued)
+            | (InvoiceStatus::Draft, InvoiceStatus::Cancelled)
+            | (InvoiceStatus::Issued, InvoiceStatus::Cancelled)
+    );
+    if !allowed {
+        return Err(AppError::validation(
+            "status",
+            format!(
+                "{} {} cannot change from {} to {}",
+                existing.note_type.label(),
+                existing.note_number,
+                existing.status.as_str(),
+                status.as_str()
+            ),
+        ));
+    }
+    financial_years::ensure_period_open(&conn, company_id, &existing.note_date)?;
+
+    conn.execute(
+        "UPDATE credit_debit_notes SET status = ?1, updated_at = CURRENT_TIMESTAMP
+         WHERE id = ?2 AND company_id = ?3",
+        params![status.as_str(), id, company_id],
+    )?;
+    get_note_by_id(&conn, id, company_id)?
+        .ok_or_else(|| AppError::not_found("Note not found after update"))
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn delete_credit_debit_note(
+    pool: State<'_, DbPool>,
+    id: i64,
+    company_id: i64,
+) -> Result<(), AppError> {
+    let conn = db::get_conn(&pool)?;
+    let existing = get_note_by_id(&conn, id, company_id)?
+        .ok_or_else(|| AppError::not_found("Note not found"))?;
+
+    // Issued notes are part of the tax record and must be cancelled instead
+    if existing.status != InvoiceStatus::Draft {
+        return Err(AppError::invalid(
+            "Only draft notes can be deleted; cancel issued notes instead",
+        ));
+    }
+    financial_years::ensure_period_open(&conn, company_id, &existing.note_date)?;
+
+    conn.execute(
+        "DELETE FROM credit_debit_notes WHERE id = ?1 AND company_id = ?2",
+        params![id, company_id],
+    )?;
+    Ok(())
+}
@@ -0,0 +1,826 @@
+use std::collections::{BTreeMap, HashSet};
+
+use chrono::format::{Item, StrftimeItems};
+use chrono::NaiveDate;
+use csv::{ReaderBuilder, StringRecord, Trim};
+use rusqlite::{params, Connection, OptionalExtension, Row};
+use serde::{Deserialize, Serialize};
+use tauri::State;
+
+use crate::categories;
+use crate::currencies;
+use crate::customer_duplicates::{self, DuplicateMatch};
+use crate::customers::{self, CreateCustomer};
+use crate::db::{self, DbPool};
+use crate::error::AppError;
+use crate::gstin;
+use crate::invoices::{self, Invoice, InvoiceStatus, INVOICE_DATE_FORMAT};
+use crate::place_of_supply::SupplyKind;
+use crate::sales_import::{self, CustomerIndex, DATE_FORMATS};
+use crate::states;
+
+const DEFAULT_PREVIEW_ROWS: usize = 20;
+const MAX_PREVIEW_ROWS: usize = 500;
+const DELIMITERS: &[&str] = &[",", ";", "\t", "|"];
+
+// Target fields per import type, flagged when they must be mapped
+const CUSTOMER_FIELDS: &[(&str, bool)] = &[
+    ("report_customer", true),
+    ("tally_customer", true),
+    ("gst_no", false),
+    ("state_code", false),
+    ("category", false),
+    ("address", false),
+    ("city", false),
+    ("pincode", false),
+];
+const INVOICE_FIELDS: &[(&str, bool)] = &[
+    ("invoice_number", true),
+    ("invoice_date", true),
+    ("customer", true),
+    ("taxable_value", true),
+    ("customer_gstin", false),
+    ("place_of_supply", false),
+    ("cgst_amount", false),
+    ("sgst_amount", false),
+    ("igst_amount", false),
+    ("total_amount", false),
+    ("notes", false),
+];
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ImportTarget {
+    Customers,
+    Invoices,
+}
+
+impl ImportTarget {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ImportTarget::Customers => "customers",
+            ImportTarget::Invoices => "invoices",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "customers" => Some(ImportTarget::Customers),
+            "invoices" => Some(ImportTarget::Invoices),
+            _ => None,
+        }
+    }
+
+    fn fields(&self) -> &'static [(&'static str, bool)] {
+        match self {
+            ImportTarget::Customers => CUSTOMER_FIELDS,
+            ImportTarget::Invoices => INVOICE_FIELDS,
+        }
+    }
+}
+
+// How to read a CSV file: source column → target field, plus locale settings
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct CsvMapping {
+    pub target: ImportTarget,
+    pub column_map: BTreeMap<String, String>,
+    // chrono format such as %d/%m/%Y; common layouts are tried when absent
+    pub date_format: Option<String>,
+    #[serde(default = "default_decimal_separator")]
+    pub decimal_separator: String,
+    #[serde(default = "default_delimiter")]
+    pub delimiter: String,
+}
+
+fn default_decimal_separator() -> String {
+    ".".to_string()
+}
+
+fn default_delimiter() -> String {
+    ",".to_string()
+}
+
+// Import mapping profile data model
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ImportProfile {
+    pub id: Option<i64>,
+    pub name: String,
+    #[serde(flatten)]
+    pub mapping: CsvMapping,
+    pub created_at: Option<String>,
+    pub updated_at: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SaveImportProfile {
+    pub name: String,
+    #[serde(flatten)]
+    pub mapping: CsvMapping,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CsvPreviewRow {
+    pub row_number: usize,
+    pub values: BTreeMap<String, String>,
+    pub errors: Vec<String>,
+    // Existing customers an incoming customer row may duplicate
+    pub duplicates: Vec<DuplicateMatch>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CsvPreview {
+    pub headers: Vec<String>,
+    pub rows: Vec<CsvPreviewRow>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct CsvRowError {
+    pub row_number: usize,
+    pub errors: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct CsvImportReport {
+    pub target: ImportTarget,
+    pub total_rows: usize,
+    pub imported_rows: usize,
+    pub error_rows: usize,
+    pub dry_run: bool,
+    // Only failing rows are kept so large files stay cheap to report on
+    pub errors: Vec<CsvRowError>,
+}
+
+const SELECT_PROFILE: &str = "
+    SELECT id, name, target, column_map, date_format, decimal_separator, delimiter,
+           created_at, updated_at
+    FROM import_mapping_profiles";
+
+fn profile_from_row(row: &Row) -> rusqlite::Result<ImportProfile> {
+    let target: String = row.get("target")?;
+    let column_map: String = row.get("column_map")?;
+    Ok(ImportProfile {
+        id: row.get("id")?,
+        name: row.get("name")?,
+        mapping: CsvMapping {
+            target: ImportTarget::parse(&target).unwrap_or(ImportTarget::Customers),
+            column_map: serde_json::from_str(&column_map).unwrap_or_default(),
+            date_format: row.get("date_format")?,
+            decimal_separator: row.get("decimal_separator")?,
+            delimiter: row.get("delimiter")?,
+        },
+        created_at: row.get("created_at")?,
+        updated_at: row.get("updated_at")?,
+    })
+}
+
+fn validate_mapping(mapping: &CsvMapping) -> Result<(), AppError> {
+    let fields = mapping.target.fields();
+    let mut mapped = HashSet::new();
+    for (source, field) in &mapping.column_map {
+        if source.trim().is_empty() {
+            return Err(AppError::validation("mapping", "Source column names cannot be empty"));
+        }
+        if !fields.iter().any(|(name, _)| *name == field.as_str()) {
+            return Err(AppError::validation(
+                "mapping",
+                format!("Unknown field '{}' for {} imports", field, mapping.target.as_str()),
+            ));
+        }
+        if !mapped.insert(field.as_str()) {
+            return Err(AppError::validation(
+                "mapping",
+                format!("Field '{}' is mapped more than once", field),
+            ));
+        }
+    }
+    for (field, required) in fields {
+        if *required && !mapped.contains(field) {
+            return Err(AppError::validation(
+                "mapping",
+                format!("Field '{}' must be mapped", field),
+            ));
+        }
+    }
+
+    if mapping.decimal_separator != "." && mapping.decimal_separator != "," {
+        return Err(AppError::validation("mapping", "Decimal separator must be '.' or ','"));
+    }
+    if !DELIMITERS.contains(&mapping.delimiter.as_str()) {
+        return Err(AppError::validation(
+            "mapping",
+            "Delimiter must be a comma, semicolon, tab or pipe",
+        ));
+    }
+    if mapping.decimal_separator == "," && mapping.delimiter == "," {
+        return Err(AppError::validation(
+            "mapping",
+            "A comma decimal separator needs a different delimiter",
+        ));
+    }
+
+    if let Some(format) = mapping.date_format.as_deref().filter(|f| !f.trim().is_empty()) {
+        if StrftimeItems::new(format).any(|item| matches!(item, Item::Error)) {
+            return Err(AppError::validation(
+                "mapping",
+                format!("Date format '{}' is not valid", format),
+            ));
+        }
+    }
+    Ok(())
+}
+
+fn validate_profile(profile: &SaveImportProfile) -> Result<(), AppError> {
+    if profile.name.trim().is_empty() {
+        return Err(AppError::validation("name", "Profile name is required"));
+    }
+    if profile.name.len() > 100 {
+        return Err(AppError::validation("name", "Profile name must be 100 characters or less"));
+    }
+    validate_mapping(&profile.mapping)
+}
+
+pub fn get_profile_by_id(conn: &Connection, id: i64) -> Result<Option<ImportProfile>, AppError> {
+    conn.query_row(&format!("{} WHERE id = ?1", SELECT_PROFILE), params![id], profile_from_row)
+        .optional()
+        .map_err(AppError::from)
+}
+
+pub(crate) fn resolve_mapping(
+    conn: &Connection,
+    profile_id: Option<i64>,
+    mapping: Option<CsvMapping>,
+) -> Result<CsvMapping, AppError> {
+    let mapping = match (mapping, profile_id) {
+        (Some(mapping), _) => mapping,
+        (None, Some(id)) => {
+            get_profile_by_id(conn, id)?
+                .ok_or_else(|| AppError::not_found("Import profile not found"))?
+                .mapping
+        }
+        (None, None) => {
+            return Err(AppError::validation(
+                "mapping",
+                "A mapping profile or column mapping is required",
+            ))
+        }
+    };
+    validate_mapping(&mapping)?;
+    Ok(mapping)
+}
+
+// Streams records from the file with each mapped field located by header name
+pub(crate) struct MappedReader {
+    reader: csv::Reader<std::fs::File>,
+    headers: Vec<String>,
+    // (target field, column index)
+    columns: Vec<(String, usize)>,
+    rows_read: usize,
+}
+
+impl MappedReader {
+    pub(crate) fn open(path: &str, mapping: &CsvMapping) -> Result<Self, AppError> {
+        let delimiter = mapping.delimiter.as_bytes()[0];
+        let mut reader = ReaderBuilder::new()
+            .delimiter(delimiter)
+            .flexible(true)
+            .trim(Trim::All)
+            .from_path(path)
+            .map_err(|e| format!("Failed to open CSV file: {}", e))?;
+
+        let headers: Vec<String> = reader
+            .headers()
+            .map_err(|e| format!("Failed to read CSV header: {}", e))?
+            .iter()
+            .map(|h| h.trim().to_string())
+            .collect();
+
+        let mut columns = Vec::new();
+        for (source, field) in &mapping.column_map {
+            let wanted = source.trim().to_lowercase();
+            let index =
+                headers.iter().position(|h| h.to_lowercase() == wanted).ok_or_else(|| {
+                    AppError::validation(
+                        "mapping",
+                        format!("Column '{}' was not found in the CSV header", source),
+                    )
+                })?;
+            columns.push((field.clone(), index));
+        }
+
+        Ok(MappedReader {
+            reader,
+            headers,
+            columns,
+            rows_read: 0,
+        })
+    }
+
+    // Bytes of the file consumed so far, for progress on large files
+    pub(crate) fn bytes_read(&self) -> u64 {
+        self.reader.position().byte()
+    }
+
+    // Yields (row number, field → raw value) pairs without loading the whole file. Calling it
+    // again continues where the previous iterator stopped.
+    pub(crate) fn records(
+        &mut self,
+    ) -> impl Iterator<Item = (usize, Result<BTreeMap<String, String>, String>)> + '_ {
+        let columns = &self.columns;
+        let rows_read = &mut self.rows_read;
+        self.reader
+            .records()
+            .map(move |record| {
+                let index = *rows_read;
+                *rows_read += 1;
+                let row_number = record
+                    .as_ref()
+                    .ok()
+                    .and_then(StringRecord::position)
+                    .map(|p| p.line() as usize)
+                    .unwrap_or(index + 2);
+                let values = record
+                    .map_err(|e| format!("Unreadable row: {}", e))
+                    .map(|record| {
+                        columns
+                            .iter()
+                            .map(|(field, i)| {
+                                (field.clone(), record.get(*i).unwrap_or("").to_string())
+                            })
+                            .collect()
+                    });
+                (row_number, values)
+            })
+    }
+}
+
+fn parse_amount(raw: &str, decimal_separator: &str, label: &str) -> Result<f64, String> {
+    let cleaned: String = raw
+        .chars()
+        .filter(|c| !c.is_whitespace() && *c != '₹')
+        .collect();
+    if cleaned.is_empty() {
+        return Ok(0.0);
+    }
+    let normalized = if decimal_separator == "," {
+        cleaned.replace('.', "").replace(',', ".")
+    } else {
+        cleaned.replace(',', "")
+    };
+    normalized
+        .parse::<f64>()
+        .map_err(|_| format!("{} '{}' is not a number", label, raw))
+}
+
+fn parse_date(raw: &str, date_format: Option<&str>) -> Result<NaiveDate, String> {
+    let raw = raw.trim();
+    if raw.is_empty() {
+        return Err("Invoice date is required".to_string());
+    }
+    match date_format.filter(|f| !f.trim().is_empty()) {
+        Some(format) => NaiveDate::parse_from_str(raw, format)
+            .map_err(|_| format!("Date '{}' does not match the format {}", raw, format)),
+        None => DATE_FORMATS
+            .iter()
+            .find_map(|format| NaiveDate::parse_from_str(raw, format).ok())
+            .ok_or_else(|| format!("Invalid date '{}'", raw)),
+    }
+}
+
+fn field<'a>(values: &'a BTreeMap<String, String>, name: &str) -> &'a str {
+    values.get(name).map(String::as_str).unwrap_or("").trim()
+}
+
+fn parse_customer(
+    conn: &Connection,
+    company_id: i64,
+    values: &BTreeMap<String, String>,
+    default_category_id: Option<i64>,
+) -> Result<CreateCustomer, Vec<String>> {
+    let mut errors = Vec::new();
+
+    let category_name = field(values, "category");
+    let category_id = if category_name.is_empty() {
+        default_category_id
+    } else {
+        match categories::get_category_by_name(conn, category_name, company_id) {
+            Ok(Some(category)) => category.id,
+            Ok(None) => {
+                errors.push(format!("Category '{}' does not exist", category_name));
+                None
+            }
+            Err(e) => {
+                errors.push(e);
+                None
+            }
+        }
+    };
+    if category_id.is_none() && errors.is_empty() {
+        errors.push("Category is required".to_string());
+    }
+
+    let gst_no = Some(field(values, "gst_no").to_uppercase()).filter(|g| !g.is_empty());
+    let mut customer = CreateCustomer {
+        report_customer: field(values, "report_customer").to_string(),
+        tally_customer: field(values, "tally_customer").to_string(),
+        gst_no,
+        registration_type: None,
+        state_code: Some(field(values, "state_code").to_string()),
+        category_id: category_id.unwrap_or_default(),
+        company_id,
+        address: Some(field(values, "address").to_string()).filter(|a| !a.is_empty()),
+        city: Some(field(values, "city").to_string()).filter(|c| !c.is_empty()),
+        pincode: Some(field(values, "pincode").to_string()).filter(|p| !p.is_empty()),
+    };
+    if errors.is_empty() {
+        if let Err(e) = customers::validate_create(&customer) {
+            errors.push(e.to_string());
+        }
+    }
+    if errors.is_empty() {
+        match states::resolve_state_code(
+            customer.gst_no.as_deref().unwrap_or(""),
+            customer.state_code.as_deref().unwrap_or(""),
+        ) {
+            Ok(state_code) => customer.state_code = Some(state_code),
+            Err(e) => errors.push(e.to_string()),
+        }
+    }
+
+    if errors.is_empty() {
+        Ok(customer)
+    } else {
+        Err(errors)
+    }
+}
+
+fn parse_invoice(
+    conn: &Connection,
+    company_id: i64,
+    values: &BTreeMap<String, String>,
+    mapping: &CsvMapping,
+    customer_index: &CustomerIndex,
+) -> Result<Result<Invoice, Vec<String>>, String> {
+    let mut errors = Vec::new();
+
+    let invoice_number = field(values, "invoice_number").to_string();
+    if invoice_number.is_empty() {
+        errors.push("Invoice number is required".to_string());
+    }
+    let invoice_date = parse_date(field(values, "invoice_date"), mapping.date_format.as_deref())
+        .map_err(|e| errors.push(e))
+        .ok();
+
+    let gst_no = Some(field(values, "customer_gstin").to_uppercase()).filter(|g| !g.is_empty());
+    if let Some(gst_no) = &gst_no {
+        if let Err(e) = gstin::check_gstin(gst_no) {
+            errors.push(e);
+        }
+    }
+    let customer_name = field(values, "customer");
+    let customer = customer_index.find(customer_name, gst_no.as_deref());
+    if customer.is_none() {
+        errors.push(format!(
+            "Customer '{}' is not set up for this company",
+            customer_name
+        ));
+    }
+
+    let mut amount = |name: &str, label: &str| {
+        parse_amount(field(values, name), &mapping.decimal_separator, label)
+            .map_err(|e| errors.push(e))
+            .unwrap_or(0.0)
+    };
+    let taxable_value = amount("taxable_value", "Taxable value");
+    let cgst_amount = amount("cgst_amount", "CGST amount");
+    let sgst_amount = amount("sgst_amount", "SGST amount");
+    let igst_amount = amount("igst_amount", "IGST amount");
+    let total_amount = if field(values, "total_amount").is_empty() {
+        invoices::round2(taxable_value + cgst_amount + sgst_amount + igst_amount)
+    } else {
+        amount("total_amount", "Total amount")
+    };
+
+    let place_of_supply = sales_import::place_of_supply(conn, field(values, "place_of_supply"))?
+        .or_else(|| {
+            customer
+                .map(|c| c.state_code.trim().to_string())
+                .filter(|code| !code.is_empty())
+        })
+        .or_else(|| gst_no.as_deref().and_then(|g| g.get(..2)).map(str::to_string));
+
+    if !invoice_number.is_empty()
+        && sales_import::invoice_exists(conn, company_id, &invoice_number)?
+    {
+        errors.push(format!("Invoice {} already exists", invoice_number));
+    }
+
+    let (Some(invoice_date), Some(customer)) = (invoice_date, customer) else {
+        return Ok(Err(errors));
+    };
+    let notes = Some(field(values, "notes").to_string()).filter(|n| !n.is_empty());
+
+    let mut invoice = Invoice {
+        id: None,
+        company_id,
+        invoice_number,
+        invoice_date: invoice_date.format(INVOICE_DATE_FORMAT).to_string(),
+        customer_id: customer.id.unwrap_or_default(),
+        place_of_supply: place_of_supply.unwrap_or_default(),
+        supply_kind: SupplyKind::Regular,
+        export: None,
+        sez_mode: None,
+        discount_percent: 0.0,
+        discount_amount: 0.0,
+        taxable_value,
+        cgst_amount,
+        sgst_amount,
+        igst_amount,
+        cess_amount: 0.0,
+        reverse_charge: false,
+        rcm_cgst_amount: 0.0,
+        rcm_sgst_amount: 0.0,
+        rcm_igst_amount: 0.0,
+        rcm_cess_amount: 0.0,
+        tcs_base: 0.0,
+        tcs_amount: 0.0,
+        round_off: 0.0,
+        total_amount,
+        currency: currencies::base_currency(),
+        exchange_rate: currencies::unit_rate(),
+        foreign_taxable_value: 0.0,
+        foreign_total_amount: 0.0,
+        amount_received: 0.0,
+        status: InvoiceStatus::Issued,
+        notes,
+        created_at: None,
+        updated_at: None,
+    };
+    if errors.is_empty() {
+        if let Err(e) = invoices::apply_tax_split(conn, &mut invoice, &mut [])
+            .and_then(|_| invoices::apply_rounding(conn, &mut invoice))
+            .and_then(|_| currencies::apply_currency(conn, &mut invoice))
+            .and_then(|_| invoices::validate_invoice(conn, &invoice))
+        {
+            errors.push(e.to_string());
+        }
+    }
+
+    if errors.is_empty() {
+        Ok(Ok(invoice))
+    } else {
+        Ok(Err(errors))
+    }
+}
+
+// Parses one record and writes it through `conn`, which callers keep inside a transaction
+pub(crate) fn import_row(
+    conn: &Connection,
+    company_id: i64,
+    values: Result<BTreeMap<String, String>, String>,
+    mapping: &CsvMapping,
+    default_category_id: Option<i64>,
+    customer_index: &CustomerIndex,
+) -> Result<(), Vec<String>> {
+    let values = values.map_err(|e| vec![e])?;
+    match mapping.target {
+        ImportTarget::Customers => parse_customer(conn, company_id, &values, default_category_id)
+            .and_then(|customer| {
+                customers::insert_customer(conn, &customer, None)
+                    .map(|_| ())
+                    .map_err(|e| vec![e.to_string()])
+            }),
+        ImportTarget::Invoices => {
+            match parse_invoice(conn, company_id, &values, mapping, customer_index) {
+                Ok(Ok(invoice)) => invoices::insert_invoice(conn, &invoice)
+                    .map(|_| ())
+                    .map_err(|e| vec![e.to_string()]),
+                Ok(Err(errors)) => Err(errors),
+                Err(e) => Err(vec![e]),
+            }
+        }
+    }
+}
+
+// Normalized field values shown in the preview grid
+fn customer_preview(customer: &CreateCustomer) -> BTreeMap<String, String> {
+    BTreeMap::from([
+        ("report_customer".to_string(), customer.report_customer.clone()),
+        ("tally_customer".to_string(), customer.tally_customer.clone()),
+        ("gst_no".to_string(), customer.gst_no.clone().unwrap_or_default()),
+        ("state_code".to_string(), customer.state_code.clone().unwrap_or_default()),
+        ("category_id".to_string(), customer.category_id.to_string()),
+    ])
+}
+
+fn invoice_preview(invoice: &Invoice) -> BTreeMap<String, String> {
+    BTreeMap::from([
+        ("invoice_number".to_string(), invoice.invoice_number.clone()),
+        ("invoice_date".to_string(), invoice.invoice_date.clone()),
+        ("customer_id".to_string(), invoice.customer_id.to_string()),
+        ("place_of_supply".to_string(), invoice.place_of_supply.clone()),
+        ("taxable_value".to_string(), format!("{:.2}", invoice.taxable_value)),
+        ("cgst_amount".to_string(), format!("{:.2}", invoice.cgst_amount)),
+        ("sgst_amount".to_string(), format!("{:.2}", invoice.sgst_amount)),
+        ("igst_amount".to_string(), format!("{:.2}", invoice.igst_amount)),
+        ("total_amount".to_string(), format!("{:.2}", invoice.total_amount)),
+    ])
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn save_import_profile(
+    pool: State<'_, DbPool>,
+    profile: SaveImportProfile,
+) -> Result<ImportProfile, AppError> {
+    validate_profile(&profile)?;
+    let column_map =
+        serde_json::to_string(&profile.mapping.column_map).map_err(|e| e.to_string())?;
+
+    let conn = db::get_conn(&pool)?;
+    conn.execute(
+        "INSERT INTO import_mapping_profiles (name, target, column_map, date_format, decimal_separator, delimiter)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6)
+         ON CONFLICT(name, target) DO UPDATE SET
+            column_map = ?3,
+            date_format = ?4,
+            decimal_separator = ?5,
+            delimiter = ?6,
+            updated_at = CURRENT_TIMESTAMP",
+        params![
+            profile.name.trim(),
+            profile.mapping.target.as_str(),
+            column_map,
+            profile
+                .mapping
+                .date_format
+                .as_deref()
+                .map(str::trim)
+                .filter(|f| !f.is_empty()),
+            profile.mapping.decimal_separator,
+            profile.mapping.delimiter
+        ],
+    )?;
+
+    conn.query_row(
+        &format!("{} WHERE name = ?1 AND target = ?2", SELECT_PROFILE),
+        params![profile.name.trim(), profile.mapping.target.as_str()],
+        profile_from_row,
+    )
+    .map_err(AppError::from)
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn list_import_profiles(
+    pool: State<'_, DbPool>,
+    target: Option<ImportTarget>,
+) -> Result<Vec<ImportProfile>, AppError> {
+    let conn = db::get_conn(&pool)?;
+    let mut stmt = conn
+        .prepare(&format!("{} WHERE (?1 IS NULL OR target = ?1) ORDER BY name", SELECT_PROFILE))?;
+    let profiles = stmt
+        .query_map(params![target.map(|t| t.as_str())], profile_from_row)?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(profiles)
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn delete_import_profile(pool: State<'_, DbPool>, id: i64) -> Result<(), AppError> {
+    let conn = db::get_conn(&pool)?;
+    let changed = conn.execute("DELETE FROM import_mapping_profiles WHERE id = ?1", params![id])?;
+    if changed == 0 {
+        return Err(AppError::not_found("Import profile not found"));
+    }
+    Ok(())
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn preview_csv_import(
+    pool: State<'_, DbPool>,
+    company_id: i64,
+    path: String,
+    profile_id: Option<i64>,
+    mapping: Option<CsvMapping>,
+    limit: Option<usize>,
+    default_category_id: Option<i64>,
+) -> Result<CsvPreview, AppError> {
+    let conn = db::get_conn(&pool)?;
+    let mapping = resolve_mapping(&conn, profile_id, mapping)?;
+    let limit = limit.unwrap_or(DEFAULT_PREVIEW_ROWS).clamp(1, MAX_PREVIEW_ROWS);
+
+    let company_customers = customers::get_customers_by_company(&conn, company_id)?;
+    let customer_index = CustomerIndex::new(&company_customers);
+
+    let mut reader = MappedReader::open(&path, &mapping)?;
+    let headers = reader.headers.clone();
+    let mut rows = Vec::new();
+    for (row_number, values) in reader.records().take(limit) {
+        let values = match values {
+            Ok(values) => values,
+            Err(e) => {
+                rows.push(CsvPreviewRow {
+                    row_number,
+                    values: BTreeMap::new(),
+                    errors: vec![e],
+                    duplicates: Vec::new(),
+                });
+                continue;
+            }
+        };
+        let mut duplicates = Vec::new();
+        let parsed = match mapping.target {
+            ImportTarget::Customers => {
+                parse_customer(&conn, company_id, &values, default_category_id).map(|customer| {
+                    duplicates = customer_duplicates::find_matches(
+                        &customer.report_customer,
+                        customer.gst_no.as_deref().unwrap_or(""),
+                        &company_customers,
+                        None,
+                    );
+                    customer_preview(&customer)
+                })
+            }
+            ImportTarget::Invoices => {
+                parse_invoice(&conn, company_id, &values, &mapping, &customer_index)?
+                    .map(|invoice| invoice_preview(&invoice))
+            }
+        };
+        rows.push(match parsed {
+            Ok(parsed) => CsvPreviewRow {
+                row_number,
+                values: parsed,
+                errors: Vec::new(),
+                duplicates,
+            },
+            Err(errors) => CsvPreviewRow {
+                row_number,
+                values,
+                errors,
+                duplicates,
+            },
+        });
+    }
+
+    Ok(CsvPreview { headers, rows })
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn import_csv(
+    pool: State<'_, DbPool>,
+    company_id: i64,
+    path: String,
+    profile_id: Option<i64>,
+    mapping: Option<CsvMapping>,
+    default_category_id: Option<i64>,
+    dry_run: Option<bool>,
+) -> Result<CsvImportReport, AppError> {
+    let dry_run = dry_run.unwrap_or(false);
+    let mut conn = db::get_conn(&pool)?;
+    let mapping = resolve_mapping(&conn, profile_id, mapping)?;
+    let mut reader = MappedReader::open(&path, &mapping)?;
+
+    let company_customers = customers::get_customers_by_company(&conn, company_id)?;
+    let customer_index = CustomerIndex::new(&company_customers);
+
+    // Rows are written as they are read; a dry run rolls the transaction back
+    let tx = conn.transaction()?;
+    let mut total_rows = 0;
+    let mut imported_rows = 0;
+    let mut errors = Vec::new();
+    for (row_number, values) in reader.records() {
+        total_rows += 1;
+        let outcome = import_row(
+            &tx,
+            company_id,
+            values,
+            &mapping,
+            default_category_id,
+            &customer_index,
+        );
+        match outcome {
+            Ok(()) => imported_rows += 1,
+            Err(row_errors) => errors.push(CsvRowError {
+                row_number,
+                errors: row_errors,
+            }),
+        }
+    }
+
+    if dry_run {
+        tx.rollback()?;
+    } else {
+        tx.commit()?;
+    }
+
+    Ok(CsvImportReport {
+        target: mapping.target,
+        total_rows,
+        imported_rows,
+        error_rows: errors.len(),
+        dry_run,
+        errors,
+    })
+}
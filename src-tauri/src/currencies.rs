@@ -0,0 +1,501 @@
+use std::time::Duration;
+
+use chrono::{Local, NaiveDate};
+use rusqlite::{params, Connection, OptionalExtension, Row};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tauri::State;
+
+use crate::db::{self, get_setting, set_setting, DbPool};
+use crate::error::AppError;
+use crate::invoices::{Invoice, INVOICE_DATE_FORMAT};
+
+const SETTING_SOURCE_URL: &str = "exchange_rate_source_url";
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(15);
+const MAX_DECIMAL_PLACES: u32 = 3;
+
+// Invoice amounts are always kept in rupees; other currencies are converted at the invoice's rate
+pub const BASE_CURRENCY: &str = "INR";
+
+pub fn base_currency() -> String {
+    BASE_CURRENCY.to_string()
+}
+
+pub fn unit_rate() -> f64 {
+    1.0
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Currency {
+    pub code: String,
+    pub name: String,
+    pub symbol: Option<String>,
+    pub decimal_places: u32,
+    pub active: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum RateSource {
+    // Reference rate fetched from the configured source
+    Rbi,
+    // Entered by hand; wins over the reference rate for the same day
+    Manual,
+}
+
+impl RateSource {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RateSource::Rbi => "rbi",
+            RateSource::Manual => "manual",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "rbi" => Some(RateSource::Rbi),
+            "manual" => Some(RateSource::Manual),
+            _ => None,
+        }
+    }
+}
+
+// Rupees per one unit of the currency on `rate_date`
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ExchangeRate {
+    pub currency_code: String,
+    pub rate_date: String,
+    pub rate: f64,
+    pub source: RateSource,
+    pub updated_at: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct FetchedRates {
+    pub rate_date: String,
+    pub rates: Vec<ExchangeRate>,
+    // Currencies in the response that are not in the currency master
+    pub skipped: Vec<String>,
+}
+
+const SELECT_CURRENCY: &str = "
+    SELECT code, name, symbol, decimal_places, active
+    FROM currencies";
+
+const SELECT_RATE: &str = "
+    SELECT currency_code, rate_date, rate, source, updated_at
+    FROM exchange_rates";
+
+fn currency_from_row(row: &Row) -> rusqlite::Result<Currency> {
+    Ok(Currency {
+        code: row.get("code")?,
+        name: row.get("name")?,
+        symbol: row.get("symbol")?,
+        decimal_places: row.get("decimal_places")?,
+        active: row.get("active")?,
+    })
+}
+
+fn rate_from_row(row: &Row) -> rusqlite::Result<ExchangeRate> {
+    let source: String = row.get("source")?;
+    Ok(ExchangeRate {
+        currency_code: row.get("currency_code")?,
+        rate_date: row.get("rate_date")?,
+        rate: row.get("rate")?,
+        source: RateSource::parse(&source).unwrap_or(RateSource::Manual),
+        updated_at: row.get("updated_at")?,
+    })
+}
+
+fn normalize_code(code: &str) -> String {
+    code.trim().to_ascii_uppercase()
+}
+
+fn parse_date(field: &str, value: &str) -> Result<NaiveDate, AppError> {
+    NaiveDate::parse_from_str(value.trim(), INVOICE_DATE_FORMAT)
+        .map_err(|_| AppError::validation(field, "Enter the date as YYYY-MM-DD"))
+}
+
+pub fn get_currency(conn: &Connection, code: &str) -> Result<Option<Currency>, AppError> {
+    conn.query_row(
+        &format!("{} WHERE code = ?1", SELECT_CURRENCY),
+        params![code],
+        currency_from_row,
+    )
+    .optional()
+    .map_err(AppError::from)
+}
+
+// The rate in force on `date`: the latest one on or before it, a manual rate winning over the
+// reference rate for the same day
+pub fn rate_on(
+    conn: &Connection,
+    code: &str,
+    date: &str,
+) -> Result<Option<ExchangeRate>, AppError> {
+    conn.query_row(
+        &format!(
+            "{} WHERE currency_code = ?1 AND rate_date <= ?2
+             ORDER BY rate_date DESC, CASE source WHEN 'manual' THEN 0 ELSE 1 END
+             LIMIT 1",
+            SELECT_RATE
+        ),
+        params![code, date],
+        rate_from_row,
+    )
+    .optional()
+    .map_err(AppError::from)
+}
+
+fn round_to(amount: f64, decimal_places: u32) -> f64 {
+    let factor = 10f64.powi(decimal_places as i32);
+    (amount * factor).round() / factor
+}
+
+// Settles the invoice currency and rate and works out the foreign-currency amounts. Export
+// invoices take both from their export details. A foreign currency without a rate gets the rate
+// in force on the invoice date.
+pub fn apply_currency(conn: &Connection, invoice: &mut Invoice) -> Result<(), AppError> {
+    if let Some(export) = &invoice.export {
+        invoice.currency = export.currency.clone();
+        invoice.exchange_rate = export.exchange_rate;
+    }
+    invoice.currency = normalize_code(&invoice.currency);
+    if invoice.currency.is_empty() {
+        invoice.currency = base_currency();
+    }
+    let currency = get_currency(conn, &invoice.currency)?
+        .filter(|currency| currency.active)
+        .ok_or_else(|| {
+            AppError::validation(
+                "currency",
+                format!("Currency {} is not in the currency master", invoice.currency),
+            )
+        })?;
+
+    if currency.code == BASE_CURRENCY {
+        invoice.exchange_rate = unit_rate();
+    } else if invoice.exchange_rate <= 0.0 {
+        let rate = rate_on(conn, &currency.code, &invoice.invoice_date)?.ok_or_else(|| {
+            AppError::validation(
+                "exchange_rate",
+                format!(
+                    "No {} exchange rate on or before {}; fetch the rates or enter one",
+                    currency.code, invoice.invoice_date
+                ),
+            )
+        })?;
+        invoice.exchange_rate = rate.rate;
+    }
+    if !invoice.exchange_rate.is_finite() || invoice.exchange_rate <= 0.0 {
+        return Err(AppError::validation(
+            "exchange_rate",
+            "Exchange rate must be greater than zero",
+        ));
+    }
+    invoice.foreign_taxable_value =
+        round_to(invoice.taxable_value / invoice.exchange_rate, currency.decimal_places);
+    invoice.foreign_total_amount =
+        round_to(invoice.total_amount / invoice.exchange_rate, currency.decimal_places);
+    Ok(())
+}
+
+fn store_rate(
+    conn: &Connection,
+    code: &str,
+    date: &str,
+    rate: f64,
+    source: RateSource,
+) -> Result<ExchangeRate, AppError> {
+    conn.execute(
+        "INSERT INTO exchange_rates (currency_code, rate_date, source, rate)
+         VALUES (?1, ?2, ?3, ?4)
+         ON CONFLICT(currency_code, rate_date, source)
+         DO UPDATE SET rate = ?4, updated_at = CURRENT_TIMESTAMP",
+        params![code, date, source.as_str(), rate],
+    )?;
+    conn.query_row(
+        &format!("{} WHERE currency_code = ?1 AND rate_date = ?2 AND source = ?3", SELECT_RATE),
+        params![code, date, source.as_str()],
+        rate_from_row,
+    )
+    .map_err(AppError::from)
+}
+
+// Rates come either as `{"rates": {"USD": 83.2, "JPY": {"rate": 55.4, "unit": 100}}}` or as a
+// list of `{"currency": "USD", "rate": 83.2}`, optionally wrapped in `data`. RBI quotes the yen
+// per 100 units, so a `unit` is divided out to get the rate per unit.
+fn parse_rates(body: &Value) -> Vec<(String, f64)> {
+    let data = body.get("data").filter(|d| !d.is_null()).unwrap_or(body);
+    let per_unit = |value: &Value| -> Option<f64> {
+        match value {
+            Value::Object(entry) => {
+                let rate = entry.get("rate").or_else(|| entry.get("value"))?;
+                let rate = rate.as_f64().or_else(|| rate.as_str()?.trim().parse().ok())?;
+                let unit = entry.get("unit").and_then(Value::as_f64).unwrap_or(1.0);
+                Some(rate / unit)
+            }
+            Value::String(text) => text.trim().parse().ok(),
+            other => other.as_f64(),
+        }
+    };
+    let mut rates = Vec::new();
+    if let Some(map) = data.get("rates").and_then(Value::as_object) {
+        for (code, value) in map {
+            if let Some(rate) = per_unit(value) {
+                rates.push((normalize_code(code), rate));
+            }
+        }
+    } else if let Some(list) = data.as_array() {
+        for entry in list {
+            let code = entry
+                .get("currency")
+                .or_else(|| entry.get("code"))
+                .and_then(Value::as_str);
+            if let (Some(code), Some(rate)) = (code, per_unit(entry)) {
+                rates.push((normalize_code(code), rate));
+            }
+        }
+    }
+    rates.retain(|(_, rate)| rate.is_finite() && *rate > 0.0);
+    rates
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn list_currencies(
+    pool: State<'_, DbPool>,
+    include_inactive: Option<bool>,
+) -> Result<Vec<Currency>, AppError> {
+    let conn = db::get_conn(&pool)?;
+    let mut stmt = conn.prepare(&format!(
+        "{} WHERE ?1 = 1 OR active = 1 ORDER BY code = 'INR' DESC, code",
+        SELECT_CURRENCY
+    ))?;
+    let currencies = stmt
+        .query_map(params![include_inactive.unwrap_or(false)], currency_from_row)?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(currencies)
+}
+
+// Adds a currency or updates an existing one; the rupee cannot be switched off
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn save_currency(
+    pool: State<'_, DbPool>,
+    currency: Currency,
+) -> Result<Currency, AppError> {
+    let code = normalize_code(&currency.code);
+    if code.len() != 3 || !code.chars().all(|c| c.is_ascii_uppercase()) {
+        return Err(AppError::validation("code", "Currency must be a 3-letter ISO code, e.g. USD"));
+    }
+    let name = currency.name.trim();
+    if name.is_empty() {
+        return Err(AppError::validation("name", "Name is required"));
+    }
+    if currency.decimal_places > MAX_DECIMAL_PLACES {
+        return Err(AppError::validation(
+            "decimal_places",
+            format!("Use at most {} decimal places", MAX_DECIMAL_PLACES),
+        ));
+    }
+    if code == BASE_CURRENCY && !currency.active {
+        return Err(AppError::validation("active", "The rupee cannot be deactivated"));
+    }
+    let symbol = currency
+        .symbol
+        .as_deref()
+        .map(str::trim)
+        .filter(|symbol| !symbol.is_empty());
+
+    let conn = db::get_conn(&pool)?;
+    conn.execute(
+        "INSERT INTO currencies (code, name, symbol, decimal_places, active)
+         VALUES (?1, ?2, ?3, ?4, ?5)
+         ON CONFLICT(code) DO UPDATE SET name = ?2, symbol = ?3, decimal_places = ?4, active = ?5",
+        params![code, name, symbol, currency.decimal_places, currency.active],
+    )?;
+    get_currency(&conn, &code)?.ok_or_else(|| AppError::not_found("Currency not found"))
+}
+
+// Most recent first; every source is listed so overrides can be seen next to reference rates
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn list_exchange_rates(
+    pool: State<'_, DbPool>,
+    currency: Option<String>,
+    from: String,
+    to: String,
+) -> Result<Vec<ExchangeRate>, AppError> {
+    let from = parse_date("from", &from)?;
+    let to = parse_date("to", &to)?;
+    if from > to {
+        return Err(AppError::validation("to", "The end date cannot be before the start date"));
+    }
+    let conn = db::get_conn(&pool)?;
+    let mut stmt = conn.prepare(&format!(
+        "{} WHERE (?1 IS NULL OR currency_code = ?1) AND rate_date BETWEEN ?2 AND ?3
+             ORDER BY rate_date DESC, currency_code, source",
+        SELECT_RATE
+    ))?;
+    let rates = stmt
+        .query_map(
+            params![
+                currency.as_deref().map(normalize_code),
+                from.format(INVOICE_DATE_FORMAT).to_string(),
+                to.format(INVOICE_DATE_FORMAT).to_string(),
+            ],
+            rate_from_row,
+        )?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(rates)
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn get_exchange_rate(
+    pool: State<'_, DbPool>,
+    currency: String,
+    date: String,
+) -> Result<Option<ExchangeRate>, AppError> {
+    let date = parse_date("date", &date)?;
+    let conn = db::get_conn(&pool)?;
+    rate_on(&conn, &normalize_code(&currency), &date.format(INVOICE_DATE_FORMAT).to_string())
+}
+
+// Manual override for one currency and day
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn set_exchange_rate(
+    pool: State<'_, DbPool>,
+    currency: String,
+    date: String,
+    rate: f64,
+) -> Result<ExchangeRate, AppError> {
+    let code = normalize_code(&currency);
+    let date = parse_date("date", &date)?.format(INVOICE_DATE_FORMAT).to_string();
+    if !rate.is_finite() || rate <= 0.0 {
+        return Err(AppError::validation("rate", "Exchange rate must be greater than zero"));
+    }
+    let conn = db::get_conn(&pool)?;
+    if code == BASE_CURRENCY || get_currency(&conn, &code)?.is_none() {
+        return Err(AppError::validation(
+            "currency",
+            format!("Currency {} is not in the currency master", code),
+        ));
+    }
+    store_rate(&conn, &code, &date, rate, RateSource::Manual)
+}
+
+// Removes a manual override so the reference rate applies again
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn clear_exchange_rate_override(
+    pool: State<'_, DbPool>,
+    currency: String,
+    date: String,
+) -> Result<(), AppError> {
+    let date = parse_date("date", &date)?.format(INVOICE_DATE_FORMAT).to_string();
+    let conn = db::get_conn(&pool)?;
+    let deleted = conn.execute(
+        "DELETE FROM exchange_rates
+             WHERE currency_code = ?1 AND rate_date = ?2 AND source = 'manual'",
+        params![normalize_code(&currency), date],
+    )?;
+    if deleted == 0 {
+        return Err(AppError::not_found("Exchange rate override not found"));
+    }
+    Ok(())
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn get_exchange_rate_source(pool: State<'_, DbPool>) -> Result<Option<String>, AppError> {
+    let conn = db::get_conn(&pool)?;
+    Ok(get_setting(&conn, SETTING_SOURCE_URL)?.filter(|url| !url.is_empty()))
+}
+
+// `url` must contain a `{date}` placeholder, filled in as YYYY-MM-DD
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn set_exchange_rate_source(
+    pool: State<'_, DbPool>,
+    url: Option<String>,
+) -> Result<(), AppError> {
+    let url = url.as_deref().map(str::trim).unwrap_or("");
+    if !url.is_empty() {
+        if !url.starts_with("https://") && !url.starts_with("http://") {
+            return Err(AppError::validation("url", "The source must be an http(s) URL"));
+        }
+        if !url.contains("{date}") {
+            return Err(AppError::validation("url", "The URL must contain a {date} placeholder"));
+        }
+    }
+    let conn = db::get_conn(&pool)?;
+    set_setting(&conn, SETTING_SOURCE_URL, url)
+}
+
+// Fetches the RBI reference rates for `date` (today when not given) from the configured source
+// and stores them for the currencies in the master. Manual overrides are left alone.
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn fetch_exchange_rates(
+    pool: State<'_, DbPool>,
+    date: Option<String>,
+) -> Result<FetchedRates, AppError> {
+    let date = match date {
+        Some(date) => parse_date("date", &date)?,
+        None => Local::now().date_naive(),
+    }
+    .format(INVOICE_DATE_FORMAT)
+    .to_string();
+    let url = {
+        let conn = db::get_conn(&pool)?;
+        get_setting(&conn, SETTING_SOURCE_URL)?
+            .filter(|url| !url.is_empty())
+            .ok_or_else(|| AppError::validation("url", "Set up the exchange rate source first"))?
+    };
+
+    let client = reqwest::Client::builder()
+        .timeout(REQUEST_TIMEOUT)
+        .build()
+        .map_err(|e| e.to_string())?;
+    let response = client
+        .get(url.replace("{date}", &date))
+        .send()
+        .await
+        .map_err(|e| format!("Exchange rate request failed: {}", e))?;
+    if !response.status().is_success() {
+        return Err(AppError::internal(format!(
+            "Exchange rate source returned {}",
+            response.status()
+        )));
+    }
+    let body: Value = response
+        .json()
+        .await
+        .map_err(|e| format!("Exchange rate source sent an unreadable response: {}", e))?;
+    let parsed = parse_rates(&body);
+    if parsed.is_empty() {
+        return Err(AppError::internal("The exchange rate source returned no rates"));
+    }
+
+    let mut conn = db::get_conn(&pool)?;
+    let tx = conn.transaction()?;
+    let mut rates = Vec::new();
+    let mut skipped = Vec::new();
+    for (code, rate) in parsed {
+        if code == BASE_CURRENCY || get_currency(&tx, &code)?.is_none() {
+            skipped.push(code);
+            continue;
+        }
+        rates.push(store_rate(&tx, &code, &date, rate, RateSource::Rbi)?);
+    }
+    tx.commit()?;
+    tracing::info!(count = rates.len(), "exchange rates fetched");
+    Ok(FetchedRates {
+        rate_date: date,
+        rates,
+        skipped,
+    })
+}
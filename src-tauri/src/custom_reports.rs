@@ -0,0 +1,952 @@
+use chrono::{Datelike, NaiveDate};
+use rusqlite::types::{Value, ValueRef};
+use rusqlite::{params, params_from_iter, Connection, OptionalExtension, Row};
+use rust_xlsxwriter::{ExcelDateTime, Workbook};
+use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
+use tauri::State;
+
+use crate::cancellation::Cancellations;
+use crate::db::{self, DbPool};
+use crate::error::AppError;
+use crate::invoices::INVOICE_DATE_FORMAT;
+use crate::report_export::{write_headers, xlsx_error, Formats, ReportExportResult};
+
+const MAX_COLUMNS: usize = 40;
+const MAX_FILTERS: usize = 30;
+const MAX_IN_VALUES: usize = 200;
+const DEFAULT_RUN_LIMIT: u32 = 1000;
+const MAX_RUN_LIMIT: u32 = 10_000;
+// Exports are not paged, but still bounded
+const MAX_EXPORT_ROWS: u32 = 100_000;
+const MAX_NAME_LENGTH: usize = 100;
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ReportEntity {
+    Invoices,
+    InvoiceLines,
+    Customers,
+    Receipts,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum FieldKind {
+    Text,
+    Number,
+    Amount,
+    Date,
+    Boolean,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Aggregate {
+    Count,
+    Sum,
+    Avg,
+    Min,
+    Max,
+}
+
+impl Aggregate {
+    fn sql(&self) -> &'static str {
+        match self {
+            Aggregate::Count => "COUNT",
+            Aggregate::Sum => "SUM",
+            Aggregate::Avg => "AVG",
+            Aggregate::Min => "MIN",
+            Aggregate::Max => "MAX",
+        }
+    }
+
+    fn label(&self) -> &'static str {
+        match self {
+            Aggregate::Count => "Count of",
+            Aggregate::Sum => "Total",
+            Aggregate::Avg => "Average",
+            Aggregate::Min => "Lowest",
+            Aggregate::Max => "Highest",
+        }
+    }
+
+    fn key(&self) -> &'static str {
+        match self {
+            Aggregate::Count => "count",
+            Aggregate::Sum => "sum",
+            Aggregate::Avg => "avg",
+            Aggregate::Min => "min",
+            Aggregate::Max => "max",
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum FilterOp {
+    Eq,
+    NotEq,
+    Lt,
+    Lte,
+    Gt,
+    Gte,
+    Contains,
+    StartsWith,
+    In,
+    Between,
+    IsNull,
+    IsNotNull,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportFormat {
+    Csv,
+    Xlsx,
+}
+
+impl ExportFormat {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "csv",
+            ExportFormat::Xlsx => "xlsx",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "csv" => Some(ExportFormat::Csv),
+            "xlsx" => Some(ExportFormat::Xlsx),
+            _ => None,
+        }
+    }
+}
+
+// A field a report can show, filter, group or sort on; `expr` is fixed SQL, never user input
+#[derive(Debug)]
+struct Field {
+    name: &'static str,
+    label: &'static str,
+    expr: &'static str,
+    kind: FieldKind,
+}
+
+const fn field(
+    name: &'static str,
+    label: &'static str,
+    expr: &'static str,
+    kind: FieldKind,
+) -> Field {
+    Field {
+        name,
+        label,
+        expr,
+        kind,
+    }
+}
+
+const CUSTOMER_FIELDS: [Field; 7] = [
+    field("customer_name", "Customer", "c.report_customer", FieldKind::Text),
+    field("tally_name", "Tally Name", "c.tally_customer", FieldKind::Text),
+    field("customer_gst_no", "Customer GSTIN", "c.gst_no", FieldKind::Text),
+    field("customer_state", "Customer State", "c.state_code", FieldKind::Text),
+    field("customer_city", "Customer City", "c.city", FieldKind::Text),
+    field("registration_type", "Registration Type", "c.registration_type", FieldKind::Text),
+    field("category", "Category", "cat.name", FieldKind::Text),
+];
+
+const INVOICE_FIELDS: [Field; 21] = [
+    field("invoice_number", "Invoice No", "i.invoice_number", FieldKind::Text),
+    field("invoice_date", "Invoice Date", "i.invoice_date", FieldKind::Date),
+    field("invoice_month", "Month", "substr(i.invoice_date, 1, 7)", FieldKind::Text),
+    field("status", "Status", "i.status", FieldKind::Text),
+    field("supply_kind", "Supply Type", "i.supply_kind", FieldKind::Text),
+    field("place_of_supply", "Place of Supply", "i.place_of_supply", FieldKind::Text),
+    field("reverse_charge", "Reverse Charge", "i.reverse_charge", FieldKind::Boolean),
+    field("taxable_value", "Taxable Value", "i.taxable_value", FieldKind::Amount),
+    field("cgst_amount", "CGST", "i.cgst_amount", FieldKind::Amount),
+    field("sgst_amount", "SGST", "i.sgst_amount", FieldKind::Amount),
+    field("igst_amount", "IGST", "i.igst_amount", FieldKind::Amount),
+    field("cess_amount", "Cess", "i.cess_amount", FieldKind::Amount),
+    field("tcs_amount", "TCS", "i.tcs_amount", FieldKind::Amount),
+    field("round_off", "Round Off", "i.round_off", FieldKind::Amount),
+    field("total_amount", "Total", "i.total_amount", FieldKind::Amount),
+    field("amount_received", "Received", "i.amount_received", FieldKind::Amount),
+    field("outstanding", "Outstanding", "i.total_amount - i.amount_received", FieldKind::Amount),
+    field("currency", "Currency", "i.currency_code", FieldKind::Text),
+    field("exchange_rate", "Exchange Rate", "i.exchange_rate", FieldKind::Number),
+    field(
+        "foreign_taxable_value",
+        "Taxable Value (FC)",
+        "i.foreign_taxable_value",
+        FieldKind::Number,
+    ),
+    field("foreign_total_amount", "Total (FC)", "i.foreign_total_amount", FieldKind::Number),
+];
+
+const LINE_FIELDS: [Field; 13] = [
+    field("invoice_number", "Invoice No", "i.invoice_number", FieldKind::Text),
+    field("invoice_date", "Invoice Date", "i.invoice_date", FieldKind::Date),
+    field("invoice_month", "Month", "substr(i.invoice_date, 1, 7)", FieldKind::Text),
+    field("status", "Status", "i.status", FieldKind::Text),
+    field("description", "Description", "l.description", FieldKind::Text),
+    field("hsn_code", "HSN/SAC", "l.hsn_code", FieldKind::Text),
+    field("quantity", "Quantity", "l.quantity", FieldKind::Number),
+    field("rate", "Rate", "l.rate", FieldKind::Amount),
+    field("gst_rate", "GST Rate", "l.gst_rate", FieldKind::Number),
+    field("taxable_value", "Taxable Value", "l.taxable_value", FieldKind::Amount),
+    field("cgst_amount", "CGST", "l.cgst_amount", FieldKind::Amount),
+    field("sgst_amount", "SGST", "l.sgst_amount", FieldKind::Amount),
+    field("igst_amount", "IGST", "l.igst_amount", FieldKind::Amount),
+];
+
+const RECEIPT_FIELDS: [Field; 5] = [
+    field("receipt_date", "Receipt Date", "r.receipt_date", FieldKind::Date),
+    field("receipt_month", "Month", "substr(r.receipt_date, 1, 7)", FieldKind::Text),
+    field("mode", "Mode", "r.mode", FieldKind::Text),
+    field("reference", "Reference", "r.reference", FieldKind::Text),
+    field("amount", "Amount", "r.amount", FieldKind::Amount),
+];
+
+impl ReportEntity {
+    // FROM clause and the condition limiting rows to one company; the company id is bound
+    // as the first parameter
+    fn source(&self) -> (&'static str, &'static str) {
+        match self {
+            ReportEntity::Invoices => (
+                "invoices i
+                 JOIN customers c ON c.id = i.customer_id
+                 LEFT JOIN categories cat ON cat.id = c.category_id",
+                "i.company_id = ? AND i.deleted_at IS NULL",
+            ),
+            ReportEntity::InvoiceLines => (
+                "invoice_lines l
+                 JOIN invoices i ON i.id = l.invoice_id
+                 JOIN customers c ON c.id = i.customer_id
+                 LEFT JOIN categories cat ON cat.id = c.category_id",
+                "i.company_id = ? AND i.deleted_at IS NULL",
+            ),
+            ReportEntity::Customers => (
+                "customers c LEFT JOIN categories cat ON cat.id = c.category_id",
+                "c.company_id = ? AND c.deleted_at IS NULL",
+            ),
+            ReportEntity::Receipts => (
+                "receipts r JOIN customers c ON c.id = r.customer_id
+                 LEFT JOIN categories cat ON cat.id = c.category_id",
+                "r.company_id = ?",
+            ),
+        }
+    }
+
+    fn own_fields(&self) -> &'static [Field] {
+        match self {
+            ReportEntity::Invoices => &INVOICE_FIELDS,
+            ReportEntity::InvoiceLines => &LINE_FIELDS,
+            ReportEntity::Customers => &[],
+            ReportEntity::Receipts => &RECEIPT_FIELDS,
+        }
+    }
+
+    fn fields(&self) -> impl Iterator<Item = &'static Field> {
+        self.own_fields().iter().chain(CUSTOMER_FIELDS.iter())
+    }
+
+    fn field(&self, name: &str) -> Result<&'static Field, AppError> {
+        self.fields().find(|field| field.name == name).ok_or_else(|| {
+            AppError::validation("definition", format!("Unknown field {} for this report", name))
+        })
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ReportColumn {
+    pub field: String,
+    #[serde(default)]
+    pub aggregate: Option<Aggregate>,
+    #[serde(default)]
+    pub label: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ReportFilter {
+    pub field: String,
+    pub op: FilterOp,
+    // A list for In, a pair for Between, unused for IsNull/IsNotNull
+    #[serde(default)]
+    pub value: JsonValue,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ReportSort {
+    pub field: String,
+    #[serde(default)]
+    pub aggregate: Option<Aggregate>,
+    #[serde(default)]
+    pub descending: bool,
+}
+
+// What a custom report shows; stored as JSON with the saved report
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ReportDefinition {
+    pub entity: ReportEntity,
+    pub columns: Vec<ReportColumn>,
+    #[serde(default)]
+    pub filters: Vec<ReportFilter>,
+    #[serde(default)]
+    pub group_by: Vec<String>,
+    #[serde(default)]
+    pub sort: Vec<ReportSort>,
+}
+
+// Custom report data model
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct CustomReport {
+    pub id: i64,
+    pub company_id: i64,
+    pub name: String,
+    pub description: Option<String>,
+    pub definition: ReportDefinition,
+    pub created_at: Option<String>,
+    pub updated_at: Option<String>,
+}
+
+// Creates a report when `id` is not given
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SaveCustomReport {
+    #[serde(default)]
+    pub id: Option<i64>,
+    pub company_id: i64,
+    pub name: String,
+    #[serde(default)]
+    pub description: Option<String>,
+    pub definition: ReportDefinition,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ReportFieldInfo {
+    pub name: String,
+    pub label: String,
+    pub kind: FieldKind,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ResultColumn {
+    pub key: String,
+    pub label: String,
+    pub kind: FieldKind,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ReportResult {
+    pub columns: Vec<ResultColumn>,
+    pub rows: Vec<Vec<JsonValue>>,
+    // More rows matched than the limit allowed
+    pub truncated: bool,
+}
+
+// SQL built from a definition, with every user-supplied value as a bound parameter
+struct CompiledReport {
+    sql: String,
+    values: Vec<Value>,
+    columns: Vec<ResultColumn>,
+}
+
+const SELECT_CUSTOM_REPORT: &str = "
+    SELECT id, company_id, name, description, definition, created_at, updated_at
+    FROM custom_reports";
+
+fn custom_report_from_row(row: &Row) -> rusqlite::Result<CustomReport> {
+    let definition: String = row.get("definition")?;
+    let definition = serde_json::from_str(&definition).map_err(|e| {
+        rusqlite::Error::FromSqlConversionFailure(0, rusqlite::types::Type::Text, Box::new(e))
+    })?;
+    Ok(CustomReport {
+        id: row.get("id")?,
+        company_id: row.get("company_id")?,
+        name: row.get("name")?,
+        description: row.get("description")?,
+        definition,
+        created_at: row.get("created_at")?,
+        updated_at: row.get("updated_at")?,
+    })
+}
+
+pub fn get_custom_report_by_id(
+    conn: &Connection,
+    id: i64,
+    company_id: i64,
+) -> Result<Option<CustomReport>, AppError> {
+    conn.query_row(
+        &format!("{} WHERE id = ?1 AND company_id = ?2", SELECT_CUSTOM_REPORT),
+        params![id, company_id],
+        custom_report_from_row,
+    )
+    .optional()
+    .map_err(AppError::from)
+}
+
+fn column_expr(field: &Field, aggregate: Option<Aggregate>) -> Result<String, AppError> {
+    match aggregate {
+        None => Ok(field.expr.to_string()),
+        Some(Aggregate::Sum | Aggregate::Avg)
+            if !matches!(field.kind, FieldKind::Number | FieldKind::Amount) =>
+        {
+            Err(AppError::validation(
+                "definition",
+                format!("{} is not a number and cannot be summed or averaged", field.label),
+            ))
+        }
+        Some(aggregate) => Ok(format!("{}({})", aggregate.sql(), field.expr)),
+    }
+}
+
+fn result_column(column: &ReportColumn, field: &Field) -> ResultColumn {
+    let (key, default_label, kind) = match column.aggregate {
+        None => (field.name.to_string(), field.label.to_string(), field.kind),
+        Some(aggregate) => (
+            format!("{}_{}", aggregate.key(), field.name),
+            format!("{} {}", aggregate.label(), field.label),
+            match aggregate {
+                Aggregate::Count => FieldKind::Number,
+                _ => field.kind,
+            },
+        ),
+    };
+    let label = column
+        .label
+        .as_deref()
+        .map(str::trim)
+        .filter(|label| !label.is_empty())
+        .map(str::to_string)
+        .unwrap_or(default_label);
+    ResultColumn { key, label, kind }
+}
+
+fn filter_value(field: &Field, value: &JsonValue) -> Result<Value, AppError> {
+    let invalid = || {
+        AppError::validation("definition", format!("Filter value for {} is not valid", field.label))
+    };
+    match field.kind {
+        FieldKind::Text => match value {
+            JsonValue::String(text) => Ok(Value::Text(text.clone())),
+            JsonValue::Number(number) => Ok(Value::Text(number.to_string())),
+            _ => Err(invalid()),
+        },
+        FieldKind::Number | FieldKind::Amount => {
+            value.as_f64().map(Value::Real).ok_or_else(invalid)
+        }
+        FieldKind::Date => {
+            let text = value.as_str().map(str::trim).ok_or_else(invalid)?;
+            NaiveDate::parse_from_str(text, INVOICE_DATE_FORMAT).map_err(|_| {
+                AppError::validation(
+                    "definition",
+                    format!("Filter dates for {} must be in YYYY-MM-DD format", field.label),
+                )
+            })?;
+            Ok(Value::Text(text.to_string()))
+        }
+        FieldKind::Boolean => {
+            value.as_bool().map(|flag| Value::Integer(flag as i64)).ok_or_else(invalid)
+        }
+    }
+}
+
+fn like_pattern(value: &JsonValue, prefix_only: bool) -> Option<Value> {
+    let text = value.as_str()?.replace('%', "\\%").replace('_', "\\_");
+    Some(Value::Text(if prefix_only {
+        format!("{}%", text)
+    } else {
+        format!("%{}%", text)
+    }))
+}
+
+fn compile_filter(
+    field: &Field,
+    filter: &ReportFilter,
+    values: &mut Vec<Value>,
+) -> Result<String, AppError> {
+    let expr = field.expr;
+    let comparison = |op: &str, values: &mut Vec<Value>| -> Result<String, AppError> {
+        values.push(filter_value(field, &filter.value)?);
+        Ok(format!("{} {} ?", expr, op))
+    };
+    match filter.op {
+        FilterOp::Eq => comparison("=", values),
+        FilterOp::NotEq => comparison("<>", values),
+        FilterOp::Lt => comparison("<", values),
+        FilterOp::Lte => comparison("<=", values),
+        FilterOp::Gt => comparison(">", values),
+        FilterOp::Gte => comparison(">=", values),
+        FilterOp::Contains | FilterOp::StartsWith => {
+            if field.kind != FieldKind::Text {
+                return Err(AppError::validation(
+                    "definition",
+                    format!("{} can only be compared, not searched", field.label),
+                ));
+            }
+            let pattern = like_pattern(&filter.value, filter.op == FilterOp::StartsWith)
+                .ok_or_else(|| {
+                    AppError::validation(
+                        "definition",
+                        format!("Filter value for {} must be text", field.label),
+                    )
+                })?;
+            values.push(pattern);
+            Ok(format!("{} LIKE ? ESCAPE '\\'", expr))
+        }
+        FilterOp::In => {
+            let items = filter
+                .value
+                .as_array()
+                .filter(|items| !items.is_empty() && items.len() <= MAX_IN_VALUES)
+                .ok_or_else(|| {
+                    AppError::validation(
+                        "definition",
+                        format!(
+                            "Filter on {} needs a list of 1 to {} values",
+                            field.label, MAX_IN_VALUES
+                        ),
+                    )
+                })?;
+            for item in items {
+                values.push(filter_value(field, item)?);
+            }
+            let placeholders = vec!["?"; items.len()].join(", ");
+            Ok(format!("{} IN ({})", expr, placeholders))
+        }
+        FilterOp::Between => {
+            let bounds =
+                filter.value.as_array().filter(|bounds| bounds.len() == 2).ok_or_else(|| {
+                    AppError::validation(
+                        "definition",
+                        format!("Filter on {} needs a from and a to value", field.label),
+                    )
+                })?;
+            values.push(filter_value(field, &bounds[0])?);
+            values.push(filter_value(field, &bounds[1])?);
+            Ok(format!("{} BETWEEN ? AND ?", expr))
+        }
+        FilterOp::IsNull => Ok(format!("{} IS NULL", expr)),
+        FilterOp::IsNotNull => Ok(format!("{} IS NOT NULL", expr)),
+    }
+}
+
+// Field names and operators are checked against the catalog above; only catalog SQL and `?`
+// placeholders end up in the statement
+fn compile(
+    definition: &ReportDefinition,
+    company_id: i64,
+    limit: u32,
+) -> Result<CompiledReport, AppError> {
+    let entity = definition.entity;
+    if definition.columns.is_empty() {
+        return Err(AppError::validation("definition", "Choose at least one column"));
+    }
+    if definition.columns.len() > MAX_COLUMNS {
+        return Err(AppError::validation(
+            "definition",
+            format!("A report can have at most {} columns", MAX_COLUMNS),
+        ));
+    }
+    if definition.filters.len() > MAX_FILTERS {
+        return Err(AppError::validation(
+            "definition",
+            format!("A report can have at most {} filters", MAX_FILTERS),
+        ));
+    }
+
+    let group_by = definition
+        .group_by
+        .iter()
+        .map(|name| entity.field(name))
+        .collect::<Result<Vec<_>, _>>()?;
+    let grouped =
+        !group_by.is_empty() || definition.columns.iter().any(|column| column.aggregate.is_some());
+    let check_grouped = |field: &Field, aggregate: Option<Aggregate>| {
+        if grouped && aggregate.is_none() && !group_by.iter().any(|g| g.name == field.name) {
+            return Err(AppError::validation(
+                "definition",
+                format!(
+                    "{} must be grouped on or summarised because the report is grouped",
+                    field.label
+                ),
+            ));
+        }
+        Ok(())
+    };
+
+    let mut select = Vec::new();
+    let mut columns = Vec::new();
+    for column in &definition.columns {
+        let field = entity.field(column.field.trim())?;
+        check_grouped(field, column.aggregate)?;
+        let result = result_column(column, field);
+        if columns.iter().any(|existing: &ResultColumn| existing.key == result.key) {
+            return Err(AppError::validation(
+                "definition",
+                format!("{} is in the report twice", result.label),
+            ));
+        }
+        select.push(format!("{} AS c{}", column_expr(field, column.aggregate)?, columns.len()));
+        columns.push(result);
+    }
+
+    let (from, company_clause) = entity.source();
+    let mut values = vec![Value::Integer(company_id)];
+    let mut conditions = vec![company_clause.to_string()];
+    for filter in &definition.filters {
+        let field = entity.field(filter.field.trim())?;
+        conditions.push(compile_filter(field, filter, &mut values)?);
+    }
+
+    let mut sql = format!(
+        "SELECT {} FROM {} WHERE {}",
+        select.join(", "),
+        from,
+        conditions.join(" AND ")
+    );
+    if !group_by.is_empty() {
+        let exprs = group_by.iter().map(|field| field.expr).collect::<Vec<_>>();
+        sql.push_str(&format!(" GROUP BY {}", exprs.join(", ")));
+    }
+    let mut order = Vec::new();
+    for sort in &definition.sort {
+        let field = entity.field(sort.field.trim())?;
+        check_grouped(field, sort.aggregate)?;
+        let direction = if sort.descending { "DESC" } else { "ASC" };
+        order.push(format!("{} {}", column_expr(field, sort.aggregate)?, direction));
+    }
+    if !order.is_empty() {
+        sql.push_str(&format!(" ORDER BY {}", order.join(", ")));
+    }
+    // One extra row tells whether the result was cut off
+    sql.push_str(&format!(" LIMIT {}", limit as u64 + 1));
+
+    Ok(CompiledReport {
+        sql,
+        values,
+        columns,
+    })
+}
+
+fn json_value(value: ValueRef, kind: FieldKind) -> JsonValue {
+    match value {
+        ValueRef::Null => JsonValue::Null,
+        ValueRef::Integer(number) if kind == FieldKind::Boolean => JsonValue::Bool(number != 0),
+        ValueRef::Integer(number) => JsonValue::from(number),
+        ValueRef::Real(number) => JsonValue::from(number),
+        ValueRef::Text(text) => JsonValue::from(String::from_utf8_lossy(text).to_string()),
+        ValueRef::Blob(_) => JsonValue::Null,
+    }
+}
+
+pub fn run_definition(
+    conn: &Connection,
+    definition: &ReportDefinition,
+    company_id: i64,
+    limit: u32,
+) -> Result<ReportResult, AppError> {
+    let compiled = compile(definition, company_id, limit)?;
+    let mut stmt = conn.prepare(&compiled.sql)?;
+    let kinds = compiled.columns.iter().map(|column| column.kind).collect::<Vec<_>>();
+    let mut rows = stmt
+        .query_map(params_from_iter(compiled.values.iter()), |row| {
+            kinds
+                .iter()
+                .enumerate()
+                .map(|(index, kind)| Ok(json_value(row.get_ref(index)?, *kind)))
+                .collect::<rusqlite::Result<Vec<_>>>()
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+    let truncated = rows.len() > limit as usize;
+    rows.truncate(limit as usize);
+    Ok(ReportResult {
+        columns: compiled.columns,
+        rows,
+        truncated,
+    })
+}
+
+fn cell_text(value: &JsonValue) -> String {
+    match value {
+        JsonValue::Null => String::new(),
+        JsonValue::String(text) => text.clone(),
+        JsonValue::Bool(true) => "Yes".to_string(),
+        JsonValue::Bool(false) => "No".to_string(),
+        other => other.to_string(),
+    }
+}
+
+fn write_csv(result: &ReportResult, path: &str) -> Result<(), String> {
+    let mut writer =
+        csv::Writer::from_path(path).map_err(|e| format!("Failed to create {}: {}", path, e))?;
+    let csv_error = |e: csv::Error| format!("Failed to write {}: {}", path, e);
+    writer
+        .write_record(result.columns.iter().map(|column| column.label.as_str()))
+        .map_err(csv_error)?;
+    for row in &result.rows {
+        writer.write_record(row.iter().map(cell_text)).map_err(csv_error)?;
+    }
+    writer.flush().map_err(|e| format!("Failed to write {}: {}", path, e))
+}
+
+fn write_xlsx(result: &ReportResult, name: &str, path: &str) -> Result<String, String> {
+    let formats = Formats::new();
+    let mut workbook = Workbook::new();
+    let sheet = workbook.add_worksheet();
+    // Sheet names are limited to 31 characters and cannot contain []:*?/\
+    let sheet_name = name
+        .chars()
+        .filter(|c| !"[]:*?/\\".contains(*c))
+        .take(31)
+        .collect::<String>();
+    let sheet_name = if sheet_name.trim().is_empty() {
+        "Report".to_string()
+    } else {
+        sheet_name
+    };
+    sheet.set_name(&sheet_name).map_err(xlsx_error)?;
+    let headers = result
+        .columns
+        .iter()
+        .map(|column| {
+            let width = match column.kind {
+                FieldKind::Text => 24.0,
+                FieldKind::Amount => 16.0,
+                FieldKind::Date => 12.0,
+                FieldKind::Number | FieldKind::Boolean => 10.0,
+            };
+            (column.label.as_str(), width)
+        })
+        .collect::<Vec<_>>();
+    write_headers(sheet, &formats, &headers)?;
+
+    for (index, row) in result.rows.iter().enumerate() {
+        let line = index as u32 + 1;
+        for ((col, value), column) in row.iter().enumerate().zip(&result.columns) {
+            let col = col as u16;
+            let date = value
+                .as_str()
+                .filter(|_| column.kind == FieldKind::Date)
+                .and_then(|text| NaiveDate::parse_from_str(text, INVOICE_DATE_FORMAT).ok());
+            match (value, date) {
+                (JsonValue::Null, _) => continue,
+                (_, Some(date)) => {
+                    let date = ExcelDateTime::from_ymd(
+                        date.year() as u16,
+                        date.month() as u8,
+                        date.day() as u8,
+                    )
+                    .map_err(xlsx_error)?;
+                    sheet.write_datetime_with_format(line, col, &date, &formats.date)
+                }
+                (JsonValue::Number(number), _) => {
+                    let number = number.as_f64().unwrap_or_default();
+                    match column.kind {
+                        FieldKind::Amount => {
+                            sheet.write_number_with_format(line, col, number, &formats.currency)
+                        }
+                        _ => sheet.write_number(line, col, number),
+                    }
+                }
+                (other, _) => {
+                    sheet.write_string_with_format(line, col, cell_text(other), &formats.text)
+                }
+            }
+            .map_err(xlsx_error)?;
+        }
+    }
+    workbook.save(path).map_err(xlsx_error)?;
+    Ok(sheet_name)
+}
+
+fn validate_save(report: &SaveCustomReport) -> Result<(), AppError> {
+    let name = report.name.trim();
+    if name.is_empty() {
+        return Err(AppError::validation("name", "Report name is required"));
+    }
+    if name.chars().count() > MAX_NAME_LENGTH {
+        return Err(AppError::validation(
+            "name",
+            format!("Report name must be {} characters or less", MAX_NAME_LENGTH),
+        ));
+    }
+    compile(&report.definition, report.company_id, 1)?;
+    Ok(())
+}
+
+fn map_write_error(e: rusqlite::Error) -> AppError {
+    let message = e.to_string();
+    if message.contains("UNIQUE constraint failed: custom_reports") {
+        return AppError::conflict("name", "A report with this name already exists");
+    }
+    AppError::from(e)
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn get_report_fields(entity: ReportEntity) -> Result<Vec<ReportFieldInfo>, AppError> {
+    Ok(entity
+        .fields()
+        .map(|field| ReportFieldInfo {
+            name: field.name.to_string(),
+            label: field.label.to_string(),
+            kind: field.kind,
+        })
+        .collect())
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn list_custom_reports(
+    pool: State<'_, DbPool>,
+    company_id: i64,
+) -> Result<Vec<CustomReport>, AppError> {
+    let conn = db::get_conn(&pool)?;
+    let mut stmt = conn.prepare(&format!(
+        "{} WHERE company_id = ?1 ORDER BY name COLLATE NOCASE ASC",
+        SELECT_CUSTOM_REPORT
+    ))?;
+    let reports = stmt
+        .query_map(params![company_id], custom_report_from_row)?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(reports)
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn save_custom_report(
+    pool: State<'_, DbPool>,
+    report: SaveCustomReport,
+) -> Result<CustomReport, AppError> {
+    validate_save(&report)?;
+    let conn = db::get_conn(&pool)?;
+    let definition = serde_json::to_string(&report.definition).map_err(|e| e.to_string())?;
+    let description = report
+        .description
+        .as_deref()
+        .map(str::trim)
+        .filter(|description| !description.is_empty());
+    let id = match report.id {
+        Some(id) => {
+            let updated = conn
+                .execute(
+                    "UPDATE custom_reports
+                     SET name = ?1, description = ?2, definition = ?3,
+                         updated_at = CURRENT_TIMESTAMP
+                     WHERE id = ?4 AND company_id = ?5",
+                    params![report.name.trim(), description, definition, id, report.company_id],
+                )
+                .map_err(map_write_error)?;
+            if updated == 0 {
+                return Err(AppError::not_found("Report not found"));
+            }
+            id
+        }
+        None => {
+            conn.execute(
+                "INSERT INTO custom_reports (company_id, name, description, definition)
+                 VALUES (?1, ?2, ?3, ?4)",
+                params![report.company_id, report.name.trim(), description, definition],
+            )
+            .map_err(map_write_error)?;
+            conn.last_insert_rowid()
+        }
+    };
+    get_custom_report_by_id(&conn, id, report.company_id)?
+        .ok_or_else(|| AppError::not_found("Report not found after saving"))
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn delete_custom_report(
+    pool: State<'_, DbPool>,
+    id: i64,
+    company_id: i64,
+) -> Result<(), AppError> {
+    let conn = db::get_conn(&pool)?;
+    let deleted = conn.execute(
+        "DELETE FROM custom_reports WHERE id = ?1 AND company_id = ?2",
+        params![id, company_id],
+    )?;
+    if deleted == 0 {
+        return Err(AppError::not_found("Report not found"));
+    }
+    Ok(())
+}
+
+// Runs a saved report, or an unsaved `definition` while it is being designed
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn run_custom_report(
+    pool: State<'_, DbPool>,
+    cancellations: State<'_, Cancellations>,
+    company_id: i64,
+    id: Option<i64>,
+    definition: Option<ReportDefinition>,
+    limit: Option<u32>,
+    operation_id: Option<String>,
+) -> Result<ReportResult, AppError> {
+    let limit = limit.unwrap_or(DEFAULT_RUN_LIMIT).clamp(1, MAX_RUN_LIMIT);
+    let operation = cancellations.start(operation_id.as_deref());
+    let conn = db::get_conn(&pool)?;
+    let definition = match (id, definition) {
+        (_, Some(definition)) => definition,
+        (Some(id), None) => {
+            get_custom_report_by_id(&conn, id, company_id)?
+                .ok_or_else(|| AppError::not_found("Report not found"))?
+                .definition
+        }
+        (None, None) => return Err(AppError::invalid("Choose a report to run")),
+    };
+    let _watch = operation.token.watch(&conn);
+    run_definition(&conn, &definition, company_id, limit).map_err(|e| operation.token.map_err(e))
+}
+
+pub fn export_saved_report(
+    conn: &Connection,
+    id: i64,
+    company_id: i64,
+    format: ExportFormat,
+    path: String,
+) -> Result<ReportExportResult, AppError> {
+    let report = get_custom_report_by_id(conn, id, company_id)?
+        .ok_or_else(|| AppError::not_found("Report not found"))?;
+    let result = run_definition(conn, &report.definition, company_id, MAX_EXPORT_ROWS)?;
+    if result.truncated {
+        return Err(AppError::invalid(format!(
+            "The report has more than {} rows; add filters to narrow it down",
+            MAX_EXPORT_ROWS
+        )));
+    }
+    let sheets = match format {
+        ExportFormat::Csv => {
+            write_csv(&result, &path)?;
+            Vec::new()
+        }
+        ExportFormat::Xlsx => vec![write_xlsx(&result, &report.name, &path)?],
+    };
+    Ok(ReportExportResult {
+        path,
+        sheets,
+        row_count: result.rows.len(),
+    })
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn export_custom_report(
+    pool: State<'_, DbPool>,
+    cancellations: State<'_, Cancellations>,
+    id: i64,
+    company_id: i64,
+    format: ExportFormat,
+    path: String,
+    operation_id: Option<String>,
+) -> Result<ReportExportResult, AppError> {
+    let operation = cancellations.start(operation_id.as_deref());
+    let conn = db::get_conn(&pool)?;
+    let _watch = operation.token.watch(&conn);
+    export_saved_report(&conn, id, company_id, format, path).map_err(|e| operation.token.map_err(e))
+}
@@ -0,0 +1,276 @@
+use rusqlite::params;
+use serde::{Deserialize, Serialize};
+use tauri::State;
+
+use crate::audit::{self, AuditAction};
+use crate::customers::{self, normalize_customer_name, Customer};
+use crate::db::{self, DbPool};
+use crate::error::AppError;
+use crate::financial_years;
+
+// Normalized names at least this similar (Jaro-Winkler) are flagged as likely duplicates
+const NAME_THRESHOLD: f64 = 0.92;
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum DuplicateReason {
+    SameGstin,
+    SameName,
+    SimilarName,
+}
+
+// Existing customer that looks like the one being checked
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct DuplicateMatch {
+    pub customer_id: i64,
+    pub report_customer: String,
+    pub gst_no: String,
+    pub reasons: Vec<DuplicateReason>,
+    pub score: f64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DuplicatePair {
+    pub customer_id: i64,
+    pub report_customer: String,
+    pub gst_no: String,
+    pub duplicate: DuplicateMatch,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct MergeReport {
+    pub survivor: Customer,
+    pub merged_customer_ids: Vec<i64>,
+    pub invoices_moved: usize,
+    pub receipts_moved: usize,
+    pub mappings_moved: usize,
+}
+
+// Logged against the surviving customer
+#[derive(Debug, Serialize)]
+struct MergeSummary<'a> {
+    merged_customer_ids: &'a [i64],
+    invoices_moved: usize,
+    receipts_moved: usize,
+}
+
+fn normalized(customer: &Customer) -> String {
+    customer
+        .normalized_name
+        .clone()
+        .filter(|name| !name.is_empty())
+        .unwrap_or_else(|| normalize_customer_name(&customer.report_customer))
+}
+
+// Compares one name/GSTIN against a customer. A shared GSTIN is always a match; names only
+// match above the threshold. Returns None when the two look unrelated.
+fn compare(name: &str, gst_no: &str, other: &Customer) -> Option<DuplicateMatch> {
+    let mut reasons = Vec::new();
+    let gst_no = gst_no.trim();
+    if !gst_no.is_empty() && gst_no.eq_ignore_ascii_case(other.gst_no.trim()) {
+        reasons.push(DuplicateReason::SameGstin);
+    }
+    let other_name = normalized(other);
+    let score = if name.is_empty() || other_name.is_empty() {
+        0.0
+    } else {
+        strsim::jaro_winkler(name, &other_name)
+    };
+    if name == other_name && !name.is_empty() {
+        reasons.push(DuplicateReason::SameName);
+    } else if score >= NAME_THRESHOLD {
+        reasons.push(DuplicateReason::SimilarName);
+    }
+    if reasons.is_empty() {
+        return None;
+    }
+    Some(DuplicateMatch {
+        customer_id: other.id?,
+        report_customer: other.report_customer.clone(),
+        gst_no: other.gst_no.clone(),
+        reasons,
+        score: (score * 1000.0).round() / 1000.0,
+    })
+}
+
+// Likely duplicates of a new or edited customer among `existing`, best match first
+pub fn find_matches(
+    report_customer: &str,
+    gst_no: &str,
+    existing: &[Customer],
+    exclude_id: Option<i64>,
+) -> Vec<DuplicateMatch> {
+    let name = normalize_customer_name(report_customer);
+    let mut matches: Vec<DuplicateMatch> = existing
+        .iter()
+        .filter(|customer| customer.id.is_some() && customer.id != exclude_id)
+        .filter_map(|customer| compare(&name, gst_no, customer))
+        .collect();
+    matches.sort_by(|a, b| {
+        let a_gstin = a.reasons.contains(&DuplicateReason::SameGstin);
+        let b_gstin = b.reasons.contains(&DuplicateReason::SameGstin);
+        b_gstin.cmp(&a_gstin).then(b.score.total_cmp(&a.score))
+    });
+    matches
+}
+
+// Every pair of existing customers that look like duplicates of each other
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn find_duplicate_customers(
+    pool: State<'_, DbPool>,
+    company_id: i64,
+) -> Result<Vec<DuplicatePair>, AppError> {
+    let conn = db::get_conn(&pool)?;
+    let company_customers = customers::get_customers_by_company(&conn, company_id)?;
+    let mut pairs = Vec::new();
+    for (i, customer) in company_customers.iter().enumerate() {
+        let Some(customer_id) = customer.id else {
+            continue;
+        };
+        let name = normalized(customer);
+        for other in &company_customers[i + 1..] {
+            if let Some(duplicate) = compare(&name, &customer.gst_no, other) {
+                pairs.push(DuplicatePair {
+                    customer_id,
+                    report_customer: customer.report_customer.clone(),
+                    gst_no: customer.gst_no.clone(),
+                    duplicate,
+                });
+            }
+        }
+    }
+    pairs.sort_by(|a, b| b.duplicate.score.total_cmp(&a.duplicate.score));
+    Ok(pairs)
+}
+
+// Checks a customer being entered or edited before it is saved
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn check_customer_duplicates(
+    pool: State<'_, DbPool>,
+    company_id: i64,
+    report_customer: String,
+    gst_no: Option<String>,
+    exclude_id: Option<i64>,
+) -> Result<Vec<DuplicateMatch>, AppError> {
+    let conn = db::get_conn(&pool)?;
+    let company_customers = customers::get_customers_by_company(&conn, company_id)?;
+    Ok(find_matches(
+        &report_customer,
+        gst_no.as_deref().unwrap_or(""),
+        &company_customers,
+        exclude_id,
+    ))
+}
+
+// Moves every invoice, receipt and import mapping of the duplicates to the survivor, then
+// sends the duplicates to the recycle bin. Credit and debit notes follow their invoices.
+// Nothing is changed unless the whole merge succeeds.
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn merge_customers(
+    pool: State<'_, DbPool>,
+    company_id: i64,
+    survivor_id: i64,
+    duplicate_ids: Vec<i64>,
+) -> Result<MergeReport, AppError> {
+    let mut duplicate_ids = duplicate_ids;
+    duplicate_ids.sort_unstable();
+    duplicate_ids.dedup();
+    if duplicate_ids.is_empty() {
+        return Err(AppError::validation(
+            "duplicate_ids",
+            "Choose at least one customer to merge",
+        ));
+    }
+    if duplicate_ids.contains(&survivor_id) {
+        return Err(AppError::validation(
+            "duplicate_ids",
+            "A customer cannot be merged into itself",
+        ));
+    }
+
+    let mut conn = db::get_conn(&pool)?;
+    let tx = conn.transaction()?;
+    customers::get_customer_by_id(&tx, survivor_id, company_id)?
+        .ok_or_else(|| AppError::not_found("Customer to keep was not found"))?;
+
+    let (mut invoices_moved, mut receipts_moved, mut mappings_moved) = (0, 0, 0);
+    for &duplicate_id in &duplicate_ids {
+        let duplicate =
+            customers::get_customer_by_id(&tx, duplicate_id, company_id)?.ok_or_else(|| {
+                AppError::not_found(format!("Customer {} was not found", duplicate_id))
+            })?;
+
+        let mut stmt = tx.prepare(
+            "SELECT invoice_date FROM invoices WHERE customer_id = ?1 AND company_id = ?2",
+        )?;
+        let dates = stmt
+            .query_map(params![duplicate_id, company_id], |row| {
+                row.get::<_, String>(0)
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+        drop(stmt);
+        for date in &dates {
+            financial_years::ensure_period_open(&tx, company_id, date)?;
+        }
+
+        invoices_moved += tx.execute(
+            "UPDATE invoices SET customer_id = ?1, updated_at = CURRENT_TIMESTAMP
+                 WHERE customer_id = ?2 AND company_id = ?3",
+            params![survivor_id, duplicate_id, company_id],
+        )?;
+        receipts_moved += tx.execute(
+            "UPDATE receipts SET customer_id = ?1, updated_at = CURRENT_TIMESTAMP
+                 WHERE customer_id = ?2 AND company_id = ?3",
+            params![survivor_id, duplicate_id, company_id],
+        )?;
+        mappings_moved += tx.execute(
+            "UPDATE persistent_customer_mappings
+                 SET mapped_customer_id = ?1, updated_at = CURRENT_TIMESTAMP
+                 WHERE mapped_customer_id = ?2 AND company_id = ?3",
+            params![survivor_id, duplicate_id, company_id],
+        )?;
+
+        tx.execute(
+            "UPDATE customers SET deleted_at = CURRENT_TIMESTAMP WHERE id = ?1 AND company_id = ?2",
+            params![duplicate_id, company_id],
+        )?;
+        audit::record(
+            &tx,
+            company_id,
+            "customer",
+            duplicate_id,
+            AuditAction::Delete,
+            Some(&duplicate),
+            None,
+        )?;
+    }
+
+    let summary = MergeSummary {
+        merged_customer_ids: &duplicate_ids,
+        invoices_moved,
+        receipts_moved,
+    };
+    audit::record(
+        &tx,
+        company_id,
+        "customer",
+        survivor_id,
+        AuditAction::Merge,
+        None,
+        Some(&summary),
+    )?;
+    let survivor = customers::get_customer_by_id(&tx, survivor_id, company_id)?
+        .ok_or_else(|| AppError::internal("Customer not found after merge"))?;
+    tx.commit()?;
+
+    Ok(MergeReport {
+        survivor,
+        merged_customer_ids: duplicate_ids,
+        invoices_moved,
+        receipts_moved,
+        mappings_moved,
+    })
+}
@@ -0,0 +1,456 @@
+use chrono::{Datelike, NaiveDate};
+use rusqlite::{params, Connection};
+use rust_xlsxwriter::{ExcelDateTime, Workbook};
+use serde::{Deserialize, Serialize};
+use tauri::State;
+
+use crate::companies;
+use crate::customers;
+use crate::db::{self, DbPool};
+use crate::error::AppError;
+use crate::invoice_pdf::{
+    column, display_date, format_amount, party_lines, state_label, wrap, write_output, Align,
+    Column, PdfWriter, RenderedPdf,
+};
+use crate::invoices::{round2, INVOICE_DATE_FORMAT};
+use crate::receipts::PaymentMode;
+use crate::report_export::{write_headers, xlsx_error, Formats, ReportExportResult};
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum StatementEntryType {
+    Invoice,
+    DebitNote,
+    CreditNote,
+    Receipt,
+}
+
+impl StatementEntryType {
+    fn label(&self) -> &'static str {
+        match self {
+            StatementEntryType::Invoice => "Invoice",
+            StatementEntryType::DebitNote => "Debit Note",
+            StatementEntryType::CreditNote => "Credit Note",
+            StatementEntryType::Receipt => "Receipt",
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct StatementEntry {
+    pub date: String,
+    pub entry_type: StatementEntryType,
+    pub document_number: String,
+    pub particulars: String,
+    pub debit: f64,
+    pub credit: f64,
+    // Running balance after this entry; positive means the customer owes us
+    pub balance: f64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CustomerStatement {
+    pub company_id: i64,
+    pub customer_id: i64,
+    pub customer_name: String,
+    pub gst_no: String,
+    pub from: String,
+    pub to: String,
+    pub opening_balance: f64,
+    pub total_debit: f64,
+    pub total_credit: f64,
+    pub closing_balance: f64,
+    pub entries: Vec<StatementEntry>,
+}
+
+// Issued invoices and debit notes are debits; issued credit notes and receipts are credits.
+// `kind` orders same-day entries so charges come before what settles them.
+const LEDGER_SQL: &str = "
+    SELECT entry_date, kind, number, particulars, debit, credit FROM (
+        SELECT invoice_date AS entry_date, 1 AS kind, invoice_number AS number,
+               '' AS particulars, total_amount AS debit, 0 AS credit
+        FROM invoices
+        WHERE company_id = ?1 AND customer_id = ?2 AND status = 'issued'
+        UNION ALL
+        SELECT n.note_date, CASE n.note_type WHEN 'debit' THEN 2 ELSE 3 END, n.note_number,
+               'Against ' || i.invoice_number,
+               CASE n.note_type WHEN 'debit' THEN n.total_amount ELSE 0 END,
+               CASE n.note_type WHEN 'credit' THEN n.total_amount ELSE 0 END
+        FROM credit_debit_notes n
+        JOIN invoices i ON i.id = n.invoice_id
+        WHERE n.company_id = ?1 AND i.customer_id = ?2 AND n.status = 'issued'
+        UNION ALL
+        SELECT receipt_date, 4, COALESCE(reference, ''), mode, 0, amount
+        FROM receipts
+        WHERE company_id = ?1 AND customer_id = ?2
+    )
+    WHERE entry_date <= ?3
+    ORDER BY entry_date, kind, number";
+
+// Share of the content width given to the wrapped particulars column
+const PARTICULARS_WIDTH: f32 = 0.18;
+
+const STATEMENT_COLUMNS: &[Column] = &[
+    column("Date", 0.11, Align::Left),
+    column("Type", 0.11, Align::Left),
+    column("Number", 0.15, Align::Left),
+    column("Particulars", PARTICULARS_WIDTH, Align::Left),
+    column("Debit", 0.15, Align::Right),
+    column("Credit", 0.15, Align::Right),
+    column("Balance", 0.15, Align::Right),
+];
+
+fn parse_date(value: &str, field: &str, label: &str) -> Result<NaiveDate, AppError> {
+    NaiveDate::parse_from_str(value.trim(), INVOICE_DATE_FORMAT).map_err(|_| {
+        AppError::validation(
+            field,
+            format!("{} date must be in YYYY-MM-DD format", label),
+        )
+    })
+}
+
+pub fn build_statement(
+    conn: &Connection,
+    company_id: i64,
+    customer_id: i64,
+    from: &str,
+    to: &str,
+) -> Result<CustomerStatement, AppError> {
+    let from_date = parse_date(from, "from", "From")?;
+    let to_date = parse_date(to, "to", "To")?;
+    if from_date > to_date {
+        return Err(AppError::validation(
+            "to",
+            "From date must be on or before the to date",
+        ));
+    }
+    let customer = customers::get_customer_by_id(conn, customer_id, company_id)?
+        .ok_or_else(|| AppError::not_found("Customer does not exist for this company"))?;
+    let (from, to) = (
+        from_date.format(INVOICE_DATE_FORMAT).to_string(),
+        to_date.format(INVOICE_DATE_FORMAT).to_string(),
+    );
+
+    let mut stmt = conn.prepare(LEDGER_SQL)?;
+    let rows = stmt
+        .query_map(params![company_id, customer_id, to], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, i64>(1)?,
+                row.get::<_, String>(2)?,
+                row.get::<_, String>(3)?,
+                row.get::<_, f64>(4)?,
+                row.get::<_, f64>(5)?,
+            ))
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let mut opening_balance = 0.0;
+    let mut balance = 0.0;
+    let mut entries = Vec::new();
+    for (date, kind, number, particulars, debit, credit) in rows {
+        balance = round2(balance + debit - credit);
+        // Everything before the period is carried in as the opening balance
+        if date < from {
+            opening_balance = balance;
+            continue;
+        }
+        let entry_type = match kind {
+            1 => StatementEntryType::Invoice,
+            2 => StatementEntryType::DebitNote,
+            3 => StatementEntryType::CreditNote,
+            _ => StatementEntryType::Receipt,
+        };
+        let particulars = match entry_type {
+            StatementEntryType::Receipt => PaymentMode::parse(&particulars)
+                .map(|mode| mode.label().to_string())
+                .unwrap_or(particulars),
+            _ => particulars,
+        };
+        entries.push(StatementEntry {
+            date,
+            entry_type,
+            document_number: number,
+            particulars,
+            debit: round2(debit),
+            credit: round2(credit),
+            balance,
+        });
+    }
+
+    let total_debit = round2(entries.iter().map(|e| e.debit).sum());
+    let total_credit = round2(entries.iter().map(|e| e.credit).sum());
+    Ok(CustomerStatement {
+        company_id,
+        customer_id,
+        customer_name: customer.report_customer,
+        gst_no: customer.gst_no,
+        from,
+        to,
+        opening_balance,
+        total_debit,
+        total_credit,
+        closing_balance: balance,
+        entries,
+    })
+}
+
+// e.g. "1,250.00 Dr" for an amount the customer owes
+fn balance_label(balance: f64) -> String {
+    let side = if balance < 0.0 { "Cr" } else { "Dr" };
+    format!("{} {}", format_amount(balance.abs()), side)
+}
+
+pub(crate) fn render_statement_pdf(
+    conn: &Connection,
+    statement: &CustomerStatement,
+) -> Result<(Vec<u8>, usize), AppError> {
+    let company = companies::get_company_by_id(conn, statement.company_id)?
+        .ok_or_else(|| AppError::not_found("Company not found"))?;
+    let customer =
+        customers::get_customer_by_id(conn, statement.customer_id, statement.company_id)?
+            .ok_or_else(|| AppError::not_found("Customer does not exist for this company"))?;
+
+    let title = format!("Statement of Account - {}", statement.customer_name);
+    let mut pdf = PdfWriter::new(&title, 210.0, 297.0, 1.0, 12.0)?;
+    let left = pdf.margin;
+    let right = pdf.width - pdf.margin;
+    let particulars_chars = pdf.chars_fitting(PARTICULARS_WIDTH * pdf.content_width() - 2.0, 8.0);
+
+    pdf.text_centered("STATEMENT OF ACCOUNT", 14.0, true);
+    pdf.advance(pdf.line_height(14.0) * 1.2);
+
+    let seller = party_lines(
+        &company.company_name,
+        company.address.as_deref(),
+        company.city.as_deref(),
+        company.pincode.as_deref(),
+        &company.gst_no,
+        &state_label(conn, &company.state_code)?,
+    );
+    let buyer = party_lines(
+        &customer.report_customer,
+        customer.address.as_deref(),
+        customer.city.as_deref(),
+        customer.pincode.as_deref(),
+        &customer.gst_no,
+        &state_label(conn, &customer.state_code)?,
+    );
+    let top = pdf.y;
+    for (line, bold) in &seller {
+        pdf.text(line, if *bold { 11.0 } else { 8.5 }, left, *bold);
+        pdf.advance(pdf.line_height(if *bold { 11.0 } else { 8.5 }));
+    }
+    let seller_bottom = pdf.y;
+    pdf.y = top;
+    let buyer_x = left + pdf.content_width() * 0.55;
+    for (line, bold) in &buyer {
+        pdf.text(line, if *bold { 11.0 } else { 8.5 }, buyer_x, *bold);
+        pdf.advance(pdf.line_height(if *bold { 11.0 } else { 8.5 }));
+    }
+    pdf.y = pdf.y.min(seller_bottom);
+
+    pdf.advance(pdf.line_height(8.5) * 0.5);
+    pdf.text(
+        &format!(
+            "Period: {} to {}",
+            display_date(&statement.from),
+            display_date(&statement.to)
+        ),
+        9.0,
+        left,
+        true,
+    );
+    pdf.advance(pdf.line_height(9.0) * 1.5);
+
+    pdf.table_header(STATEMENT_COLUMNS, 8.0);
+    pdf.table_row(
+        STATEMENT_COLUMNS,
+        &[
+            vec![display_date(&statement.from)],
+            vec![String::new()],
+            vec![String::new()],
+            vec!["Opening Balance".to_string()],
+            vec![String::new()],
+            vec![String::new()],
+            vec![balance_label(statement.opening_balance)],
+        ],
+        8.0,
+        true,
+    );
+    let amount = |value: f64| {
+        if value == 0.0 {
+            String::new()
+        } else {
+            format_amount(value)
+        }
+    };
+    for entry in &statement.entries {
+        let particulars = wrap(&entry.particulars, particulars_chars);
+        let needed = particulars.len().max(1) as f32 * pdf.line_height(8.0);
+        if pdf.ensure_space(needed) {
+            pdf.table_header(STATEMENT_COLUMNS, 8.0);
+        }
+        pdf.table_row(
+            STATEMENT_COLUMNS,
+            &[
+                vec![display_date(&entry.date)],
+                vec![entry.entry_type.label().to_string()],
+                vec![entry.document_number.clone()],
+                particulars,
+                vec![amount(entry.debit)],
+                vec![amount(entry.credit)],
+                vec![balance_label(entry.balance)],
+            ],
+            8.0,
+            false,
+        );
+    }
+
+    pdf.ensure_space(pdf.line_height(8.0) * 3.0);
+    pdf.rule(pdf.y + pdf.line_height(8.0) * 0.75);
+    pdf.table_row(
+        STATEMENT_COLUMNS,
+        &[
+            vec![String::new()],
+            vec![String::new()],
+            vec![String::new()],
+            vec!["Total".to_string()],
+            vec![format_amount(statement.total_debit)],
+            vec![format_amount(statement.total_credit)],
+            vec![String::new()],
+        ],
+        8.0,
+        true,
+    );
+    pdf.advance(pdf.line_height(8.0) * 0.5);
+    pdf.text_right(
+        &format!("Closing Balance: {}", balance_label(statement.closing_balance)),
+        10.0,
+        right,
+        true,
+    );
+    Ok(pdf.finish()?)
+}
+
+fn write_statement_xlsx(statement: &CustomerStatement, path: &str) -> Result<usize, String> {
+    let formats = Formats::new();
+    let mut workbook = Workbook::new();
+    let sheet = workbook.add_worksheet();
+    sheet.set_name("Statement").map_err(xlsx_error)?;
+    write_headers(
+        sheet,
+        &formats,
+        &[
+            ("Date", 12.0),
+            ("Type", 12.0),
+            ("Number", 18.0),
+            ("Particulars", 30.0),
+            ("Debit", 16.0),
+            ("Credit", 16.0),
+            ("Balance", 16.0),
+        ],
+    )?;
+
+    sheet
+        .write_string_with_format(1, 3, "Opening Balance", &formats.total_label)
+        .map_err(xlsx_error)?;
+    sheet
+        .write_number_with_format(1, 6, statement.opening_balance, &formats.currency)
+        .map_err(xlsx_error)?;
+
+    let mut line = 2u32;
+    for entry in &statement.entries {
+        let date = parse_date(&entry.date, "date", "Entry")?;
+        let date =
+            ExcelDateTime::from_ymd(date.year() as u16, date.month() as u8, date.day() as u8)
+                .map_err(xlsx_error)?;
+        sheet
+            .write_datetime_with_format(line, 0, &date, &formats.date)
+            .map_err(xlsx_error)?;
+        sheet
+            .write_string_with_format(line, 1, entry.entry_type.label(), &formats.text)
+            .map_err(xlsx_error)?;
+        sheet
+            .write_string_with_format(line, 2, &entry.document_number, &formats.text)
+            .map_err(xlsx_error)?;
+        sheet
+            .write_string_with_format(line, 3, &entry.particulars, &formats.text)
+            .map_err(xlsx_error)?;
+        for (col, value) in [(4u16, entry.debit), (5, entry.credit), (6, entry.balance)] {
+            sheet
+                .write_number_with_format(line, col, value, &formats.currency)
+                .map_err(xlsx_error)?;
+        }
+        line += 1;
+    }
+
+    sheet
+        .write_string_with_format(line, 3, "Closing Balance", &formats.total_label)
+        .map_err(xlsx_error)?;
+    for (col, value) in [
+        (4u16, statement.total_debit),
+        (5, statement.total_credit),
+        (6, statement.closing_balance),
+    ] {
+        sheet
+            .write_number_with_format(line, col, value, &formats.total_currency)
+            .map_err(xlsx_error)?;
+    }
+
+    workbook.save(path).map_err(xlsx_error)?;
+    Ok(statement.entries.len())
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn customer_statement(
+    pool: State<'_, DbPool>,
+    company_id: i64,
+    customer_id: i64,
+    from: String,
+    to: String,
+) -> Result<CustomerStatement, AppError> {
+    let conn = db::get_conn(&pool)?;
+    build_statement(&conn, company_id, customer_id, &from, &to)
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn export_customer_statement_pdf(
+    pool: State<'_, DbPool>,
+    company_id: i64,
+    customer_id: i64,
+    from: String,
+    to: String,
+    path: Option<String>,
+) -> Result<RenderedPdf, AppError> {
+    let (bytes, page_count) = {
+        let conn = db::get_conn(&pool)?;
+        let statement = build_statement(&conn, company_id, customer_id, &from, &to)?;
+        render_statement_pdf(&conn, &statement)?
+    };
+    Ok(write_output(bytes, page_count, path)?)
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn export_customer_statement_xlsx(
+    pool: State<'_, DbPool>,
+    company_id: i64,
+    customer_id: i64,
+    from: String,
+    to: String,
+    path: String,
+) -> Result<ReportExportResult, AppError> {
+    let statement = {
+        let conn = db::get_conn(&pool)?;
+        build_statement(&conn, company_id, customer_id, &from, &to)?
+    };
+    let row_count = write_statement_xlsx(&statement, &path)?;
+    Ok(ReportExportResult {
+        path,
+        sheets: vec!["Statement".to_string()],
+        row_count,
+    })
+}
@@ -0,0 +1,743 @@
+use rusqlite::types::Value;
+use rusqlite::{params, OptionalExtension, Row};
+use serde::{Deserialize, Serialize};
+use tauri::State;
+
+use crate::audit::{self, AuditAction};
+use crate::categories::Category;
+use crate::companies;
+use crate::db::{self, DbPool};
+use crate::error::AppError;
+use crate::gstin;
+use crate::listing::{self, ListPage, ListQuery, SqlFilter};
+use crate::states;
+use crate::validation_rules::{self, RuleEntity};
+
+// GST registration of the customer, which decides how supplies to it are taxed and reported
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum RegistrationType {
+    #[default]
+    Regular,
+    Composition,
+    // Units and developers in a special economic zone; supplies to them are zero-rated
+    Sez,
+    // UN bodies and embassies, identified by a UIN in place of a GSTIN
+    Uin,
+    Unregistered,
+}
+
+impl RegistrationType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RegistrationType::Regular => "regular",
+            RegistrationType::Composition => "composition",
+            RegistrationType::Sez => "sez",
+            RegistrationType::Uin => "uin",
+            RegistrationType::Unregistered => "unregistered",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "regular" => Some(RegistrationType::Regular),
+            "composition" => Some(RegistrationType::Composition),
+            "sez" => Some(RegistrationType::Sez),
+            "uin" => Some(RegistrationType::Uin),
+            "unregistered" => Some(RegistrationType::Unregistered),
+            _ => None,
+        }
+    }
+
+    // Used when none is given: regular with a GSTIN, unregistered without one
+    pub fn infer(gst_no: &str) -> Self {
+        if gst_no.trim().is_empty() {
+            RegistrationType::Unregistered
+        } else {
+            RegistrationType::Regular
+        }
+    }
+}
+
+// Unregistered customers have no GSTIN, UIN holders a UIN and everyone else a GSTIN
+pub fn check_registration(
+    registration_type: RegistrationType,
+    gst_no: &str,
+) -> Result<(), AppError> {
+    let gst_no = gst_no.trim();
+    let invalid = |message: String| AppError::validation("gst_no", message);
+    match registration_type {
+        RegistrationType::Unregistered if !gst_no.is_empty() => {
+            Err(AppError::validation("gst_no", "Unregistered customers cannot have a GSTIN"))
+        }
+        RegistrationType::Unregistered => Ok(()),
+        _ if gst_no.is_empty() => Err(AppError::validation(
+            "gst_no",
+            format!(
+                "A GSTIN{} is required for {} customers",
+                if registration_type == RegistrationType::Uin { " or UIN" } else { "" },
+                registration_type.as_str()
+            ),
+        )),
+        RegistrationType::Uin => gstin::check_uin(gst_no).map_err(invalid),
+        _ => gstin::check_gstin(gst_no).map_err(invalid),
+    }
+}
+
+// Customer data model
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Customer {
+    pub id: Option<i64>,
+    pub report_customer: String,
+    pub tally_customer: String,
+    pub gst_no: String,
+    #[serde(default)]
+    pub registration_type: RegistrationType,
+    pub state_code: String,
+    pub category_id: i64,
+    pub company_id: i64,
+    pub normalized_name: Option<String>,
+    pub created_from_import_id: Option<String>,
+    pub address: Option<String>,
+    pub city: Option<String>,
+    pub pincode: Option<String>,
+    pub category: Option<Category>,
+    pub created_at: Option<String>,
+    pub updated_at: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CreateCustomer {
+    pub report_customer: String,
+    pub tally_customer: String,
+    pub gst_no: Option<String>,
+    // Inferred from the GSTIN when not given
+    #[serde(default)]
+    pub registration_type: Option<RegistrationType>,
+    pub state_code: Option<String>,
+    pub category_id: i64,
+    pub company_id: i64,
+    #[serde(default)]
+    pub address: Option<String>,
+    #[serde(default)]
+    pub city: Option<String>,
+    #[serde(default)]
+    pub pincode: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct UpdateCustomer {
+    pub report_customer: Option<String>,
+    pub tally_customer: Option<String>,
+    pub gst_no: Option<String>,
+    pub registration_type: Option<RegistrationType>,
+    pub state_code: Option<String>,
+    pub category_id: Option<i64>,
+    pub address: Option<String>,
+    pub city: Option<String>,
+    pub pincode: Option<String>,
+}
+
+const SELECT_CUSTOMER: &str = "
+    SELECT c.id, c.report_customer, c.tally_customer, c.gst_no, c.state_code, c.category_id, c.company_id,
+           c.normalized_name, c.created_from_import_id, c.address, c.city, c.pincode,
+           c.registration_type, c.created_at, c.updated_at,
+           cat.id AS cat_id, cat.name AS cat_name, cat.company_id AS cat_company_id,
+           cat.created_at AS cat_created_at, cat.updated_at AS cat_updated_at
+    FROM customers c
+    LEFT JOIN categories cat ON c.category_id = cat.id AND cat.company_id = c.company_id";
+
+const BUSINESS_SUFFIXES: &[&str] = &[
+    "pvt ltd",
+    "private limited",
+    "ltd",
+    "limited",
+    "llp",
+    "llc",
+    "inc",
+    "incorporated",
+    "corp",
+    "corporation",
+    "co",
+    "company",
+    "pvt",
+    "private",
+    "ltd co",
+    "limited company",
+];
+
+fn customer_from_row(row: &Row) -> rusqlite::Result<Customer> {
+    let cat_id: Option<i64> = row.get("cat_id")?;
+    let category = match cat_id {
+        Some(id) => Some(Category {
+            id: Some(id),
+            name: row.get("cat_name")?,
+            company_id: row.get("cat_company_id")?,
+            created_at: row.get("cat_created_at")?,
+            updated_at: row.get("cat_updated_at")?,
+        }),
+        None => None,
+    };
+
+    let registration_type: String = row.get("registration_type")?;
+    Ok(Customer {
+        id: row.get("id")?,
+        report_customer: row.get("report_customer")?,
+        tally_customer: row.get("tally_customer")?,
+        gst_no: row.get("gst_no")?,
+        registration_type: RegistrationType::parse(&registration_type).unwrap_or_default(),
+        state_code: row.get("state_code")?,
+        category_id: row.get("category_id")?,
+        company_id: row.get("company_id")?,
+        normalized_name: row.get("normalized_name")?,
+        created_from_import_id: row.get("created_from_import_id")?,
+        address: row.get("address")?,
+        city: row.get("city")?,
+        pincode: row.get("pincode")?,
+        category,
+        created_at: row.get("created_at")?,
+        updated_at: row.get("updated_at")?,
+    })
+}
+
+// Normalize customer name for matching, kept in line with the frontend's normalizeCustomerName
+pub fn normalize_customer_name(name: &str) -> String {
+    let lowered = name.trim().to_lowercase();
+
+    // Remove punctuation
+    let mut normalized: String = lowered
+        .chars()
+        .filter(|c| c.is_ascii_alphanumeric() || *c == '_' || c.is_whitespace())
+        .collect();
+
+    // Remove common business suffixes
+    for suffix in BUSINESS_SUFFIXES {
+        let trimmed = normalized.trim_end();
+        if let Some(rest) = trimmed.strip_suffix(*suffix) {
+            if rest.ends_with(char::is_whitespace) {
+                normalized = rest.trim_end().to_string();
+            }
+        }
+    }
+
+    // Collapse multiple spaces
+    normalized.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+pub fn validate_create(customer: &CreateCustomer) -> Result<(), AppError> {
+    if customer.report_customer.trim().is_empty() {
+        return Err(AppError::validation("report_customer", "Report customer name is required"));
+    }
+    if customer.report_customer.len() > 255 {
+        return Err(AppError::validation(
+            "report_customer",
+            "Report customer name must be 255 characters or less",
+        ));
+    }
+
+    if customer.tally_customer.trim().is_empty() {
+        return Err(AppError::validation("tally_customer", "Tally customer name is required"));
+    }
+    if customer.tally_customer.len() > 255 {
+        return Err(AppError::validation(
+            "tally_customer",
+            "Tally customer name must be 255 characters or less",
+        ));
+    }
+
+    let gst_no = customer.gst_no.as_deref().unwrap_or("");
+    check_registration(
+        customer.registration_type.unwrap_or_else(|| RegistrationType::infer(gst_no)),
+        gst_no,
+    )?;
+
+    // State code is optional - no validation needed
+
+    if customer.category_id <= 0 {
+        return Err(AppError::validation("category_id", "Category is required"));
+    }
+
+    companies::validate_address(customer.address.as_deref(), customer.pincode.as_deref())
+}
+
+fn validate_update(customer: &UpdateCustomer) -> Result<(), AppError> {
+    if let Some(report_customer) = &customer.report_customer {
+        if report_customer.trim().is_empty() {
+            return Err(AppError::validation(
+                "report_customer",
+                "Report customer name cannot be empty",
+            ));
+        }
+        if report_customer.len() > 255 {
+            return Err(AppError::validation(
+                "report_customer",
+                "Report customer name must be 255 characters or less",
+            ));
+        }
+    }
+
+    if let Some(tally_customer) = &customer.tally_customer {
+        if tally_customer.trim().is_empty() {
+            return Err(AppError::validation(
+                "tally_customer",
+                "Tally customer name cannot be empty",
+            ));
+        }
+        if tally_customer.len() > 255 {
+            return Err(AppError::validation(
+                "tally_customer",
+                "Tally customer name must be 255 characters or less",
+            ));
+        }
+    }
+
+    // Checked against the stored registration type by update_customer; a UIN is only accepted
+    // there when the customer is, or is becoming, a UIN holder
+    if let Some(gst_no) = &customer.gst_no {
+        if !gst_no.trim().is_empty() && customer.registration_type != Some(RegistrationType::Uin) {
+            gstin::check_gstin(gst_no)
+                .or_else(|e| gstin::check_uin(gst_no).map_err(|_| e))
+                .map_err(|e| AppError::validation("gst_no", e))?;
+        }
+    }
+
+    // State code is optional - no validation needed
+
+    if let Some(category_id) = customer.category_id {
+        if category_id <= 0 {
+            return Err(AppError::validation("category_id", "Category is required"));
+        }
+    }
+
+    companies::validate_address(customer.address.as_deref(), customer.pincode.as_deref())
+}
+
+fn map_write_error(e: rusqlite::Error) -> AppError {
+    let message = e.to_string();
+    if message.contains("UNIQUE constraint failed") {
+        if message.contains("customers.normalized_name") {
+            return AppError::conflict(
+                "report_customer",
+                "Customer with this name and GST number already exists for this company",
+            );
+        }
+        if message.contains("customers.tally_customer") {
+            return AppError::conflict(
+                "tally_customer",
+                "Tally customer name must be unique, including customers in the recycle bin",
+            );
+        }
+    }
+    if message.contains("FOREIGN KEY constraint failed") {
+        return AppError::validation("category_id", "Category or company does not exist");
+    }
+    AppError::from(e)
+}
+
+pub fn get_customer_by_id(
+    conn: &rusqlite::Connection,
+    id: i64,
+    company_id: i64,
+) -> Result<Option<Customer>, AppError> {
+    // Looked up once per imported row, so the statement is cached
+    conn.prepare_cached(&format!(
+        "{} WHERE c.id = ?1 AND c.company_id = ?2 AND c.deleted_at IS NULL",
+        SELECT_CUSTOMER
+    ))
+    .and_then(|mut stmt| stmt.query_row(params![id, company_id], customer_from_row))
+    .optional()
+    .map_err(AppError::from)
+}
+
+pub fn get_customers_by_company(
+    conn: &rusqlite::Connection,
+    company_id: i64,
+) -> Result<Vec<Customer>, AppError> {
+    let mut stmt = conn.prepare(&format!(
+        "{} WHERE c.company_id = ?1 AND c.deleted_at IS NULL ORDER BY c.created_at DESC",
+        SELECT_CUSTOMER
+    ))?;
+    let customers =
+        stmt.query_map(params![company_id], customer_from_row)?.collect::<Result<Vec<_>, _>>()?;
+    Ok(customers)
+}
+
+// The customer as validation rules see it, with the category by name
+pub fn rule_record(customer: &Customer) -> Result<serde_json::Value, String> {
+    let mut record = serde_json::to_value(customer).map_err(|e| e.to_string())?;
+    record["category"] = customer
+        .category
+        .as_ref()
+        .map(|category| serde_json::Value::from(category.name.as_str()))
+        .unwrap_or_default();
+    Ok(record)
+}
+
+// Customer saves have nowhere to report warnings, so only error rules apply
+fn enforce_rules(conn: &rusqlite::Connection, customer: &Customer) -> Result<(), AppError> {
+    let record = rule_record(customer)?;
+    validation_rules::enforce(conn, customer.company_id, RuleEntity::Customer, &record)?;
+    Ok(())
+}
+
+pub fn insert_customer(
+    conn: &rusqlite::Connection,
+    customer: &CreateCustomer,
+    import_id: Option<&str>,
+) -> Result<i64, AppError> {
+    // Cached so bulk imports reuse one prepared statement
+    let mut stmt = conn.prepare_cached(
+        "INSERT INTO customers (report_customer, tally_customer, gst_no, state_code, category_id, company_id, normalized_name, created_from_import_id,
+                                address, city, pincode, registration_type)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12)",
+    )?;
+    stmt.execute(params![
+        customer.report_customer.trim(),
+        customer.tally_customer.trim(),
+        customer.gst_no.as_deref().unwrap_or("").trim(),
+        customer.state_code.as_deref().unwrap_or("").trim(),
+        customer.category_id,
+        customer.company_id,
+        normalize_customer_name(&customer.report_customer),
+        import_id,
+        customer.address.as_deref().map(str::trim),
+        customer.city.as_deref().map(str::trim),
+        customer.pincode.as_deref().map(str::trim),
+        customer
+            .registration_type
+            .unwrap_or_else(|| RegistrationType::infer(customer.gst_no.as_deref().unwrap_or("")))
+            .as_str()
+    ])
+    .map_err(map_write_error)?;
+    let id = conn.last_insert_rowid();
+    let created = get_customer_by_id(conn, id, customer.company_id)?;
+    if let Some(created) = &created {
+        enforce_rules(conn, created)?;
+    }
+    audit::record(
+        conn,
+        customer.company_id,
+        "customer",
+        id,
+        AuditAction::Create,
+        None,
+        created.as_ref(),
+    )?;
+    Ok(id)
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BulkImportRow {
+    pub index: usize,
+    pub customer_id: Option<i64>,
+    pub errors: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BulkImportReport {
+    pub total_rows: usize,
+    pub created_rows: usize,
+    pub error_rows: usize,
+    pub rows: Vec<BulkImportRow>,
+}
+
+// Customer validation commands
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn validate_customer_create(
+    customer: CreateCustomer,
+) -> Result<CreateCustomer, AppError> {
+    validate_create(&customer)?;
+    Ok(customer)
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn validate_customer_update(
+    customer: UpdateCustomer,
+) -> Result<UpdateCustomer, AppError> {
+    validate_update(&customer)?;
+    Ok(customer)
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn create_customer(
+    pool: State<'_, DbPool>,
+    customer: CreateCustomer,
+    import_id: Option<String>,
+) -> Result<Customer, AppError> {
+    let mut conn = db::get_conn(&pool)?;
+    // Rules are checked against the inserted row, which is rolled back when they fail
+    let tx = conn.transaction()?;
+    let created = create_customer_in(&tx, customer, import_id.as_deref())?;
+    tx.commit()?;
+    Ok(created)
+}
+
+// Validates and inserts a customer on a connection the caller commits, as `create_customer`
+// does in its own transaction
+pub fn create_customer_in(
+    conn: &rusqlite::Connection,
+    mut customer: CreateCustomer,
+    import_id: Option<&str>,
+) -> Result<Customer, AppError> {
+    validate_create(&customer)?;
+    customer.state_code = Some(states::resolve_state_code(
+        customer.gst_no.as_deref().unwrap_or(""),
+        customer.state_code.as_deref().unwrap_or(""),
+    )?);
+    let id = insert_customer(conn, &customer, import_id)?;
+    get_customer_by_id(conn, id, customer.company_id)?
+        .ok_or_else(|| AppError::internal("Customer not found after creation"))
+}
+
+// Each row is validated like a single create and inserted under its own savepoint, so a
+// failing row is reported without undoing the others; all created rows commit together
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn bulk_import_customers(
+    pool: State<'_, DbPool>,
+    rows: Vec<CreateCustomer>,
+    import_id: Option<String>,
+) -> Result<BulkImportReport, AppError> {
+    let mut conn = db::get_conn(&pool)?;
+    let mut tx = conn.transaction()?;
+    let mut results = Vec::with_capacity(rows.len());
+    for (index, mut customer) in rows.into_iter().enumerate() {
+        let created = validate_create(&customer)
+            .and_then(|_| {
+                states::resolve_state_code(
+                    customer.gst_no.as_deref().unwrap_or(""),
+                    customer.state_code.as_deref().unwrap_or(""),
+                )
+            })
+            .and_then(|state_code| {
+                customer.state_code = Some(state_code);
+                let savepoint = tx.savepoint()?;
+                let id = insert_customer(&savepoint, &customer, import_id.as_deref())?;
+                savepoint.commit()?;
+                Ok(id)
+            });
+        results.push(match created {
+            Ok(id) => BulkImportRow { index, customer_id: Some(id), errors: Vec::new() },
+            Err(e) => BulkImportRow { index, customer_id: None, errors: vec![e.to_string()] },
+        });
+    }
+    tx.commit()?;
+
+    let created_rows = results.iter().filter(|row| row.customer_id.is_some()).count();
+    Ok(BulkImportReport {
+        total_rows: results.len(),
+        created_rows,
+        error_rows: results.len() - created_rows,
+        rows: results,
+    })
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn update_customer(
+    pool: State<'_, DbPool>,
+    id: i64,
+    company_id: i64,
+    mut customer: UpdateCustomer,
+) -> Result<Customer, AppError> {
+    validate_update(&customer)?;
+
+    let mut conn = db::get_conn(&pool)?;
+    let tx = conn.transaction()?;
+    let existing = get_customer_by_id(&tx, id, company_id)?
+        .ok_or_else(|| AppError::not_found("Customer not found"))?;
+
+    // Re-check the state code whenever either side of the GSTIN/state pair changes
+    if customer.gst_no.is_some() || customer.state_code.is_some() {
+        let gst_no = customer.gst_no.as_deref().unwrap_or(&existing.gst_no);
+        // A new GSTIN without an explicit state takes its state from the GSTIN
+        let requested_state = customer.state_code.as_deref().unwrap_or("");
+        customer.state_code = Some(states::resolve_state_code(gst_no, requested_state)?);
+    }
+    if customer.gst_no.is_some() || customer.registration_type.is_some() {
+        let gst_no = customer.gst_no.as_deref().unwrap_or(&existing.gst_no);
+        // Without an explicit type, adding or clearing the GSTIN registers or unregisters the
+        // customer; other GSTIN changes keep the stored type
+        let registration_type = customer.registration_type.unwrap_or_else(|| {
+            let inferred = RegistrationType::infer(gst_no);
+            if inferred == RegistrationType::Unregistered
+                || existing.registration_type == RegistrationType::Unregistered
+            {
+                inferred
+            } else {
+                existing.registration_type
+            }
+        });
+        check_registration(registration_type, gst_no)?;
+        customer.registration_type = Some(registration_type);
+    }
+    let normalized_name = customer
+        .report_customer
+        .as_deref()
+        .map(normalize_customer_name);
+
+    let changed = tx
+        .execute(
+            "UPDATE customers SET
+                report_customer = COALESCE(?1, report_customer),
+                normalized_name = COALESCE(?2, normalized_name),
+                tally_customer = COALESCE(?3, tally_customer),
+                gst_no = COALESCE(?4, gst_no),
+                state_code = COALESCE(?5, state_code),
+                category_id = COALESCE(?6, category_id),
+                address = COALESCE(?7, address),
+                city = COALESCE(?8, city),
+                pincode = COALESCE(?9, pincode),
+                registration_type = COALESCE(?10, registration_type),
+                updated_at = CURRENT_TIMESTAMP
+             WHERE id = ?11 AND company_id = ?12",
+            params![
+                customer.report_customer.as_deref().map(str::trim),
+                normalized_name,
+                customer.tally_customer.as_deref().map(str::trim),
+                customer.gst_no.as_deref().map(str::trim),
+                customer.state_code.as_deref().map(str::trim),
+                customer.category_id,
+                customer.address.as_deref().map(str::trim),
+                customer.city.as_deref().map(str::trim),
+                customer.pincode.as_deref().map(str::trim),
+                customer.registration_type.map(|registration_type| registration_type.as_str()),
+                id,
+                company_id
+            ],
+        )
+        .map_err(map_write_error)?;
+
+    if changed == 0 {
+        return Err(AppError::not_found("Customer not found"));
+    }
+
+    let updated = get_customer_by_id(&tx, id, company_id)?
+        .ok_or_else(|| AppError::internal("Customer not found after update"))?;
+    enforce_rules(&tx, &updated)?;
+    audit::record(
+        &tx,
+        company_id,
+        "customer",
+        id,
+        AuditAction::Update,
+        Some(&existing),
+        Some(&updated),
+    )?;
+    tx.commit()?;
+    Ok(updated)
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn get_customer(
+    pool: State<'_, DbPool>,
+    id: i64,
+    company_id: i64,
+) -> Result<Option<Customer>, AppError> {
+    let conn = db::get_conn(&pool)?;
+    get_customer_by_id(&conn, id, company_id)
+}
+
+#[derive(Debug, Serialize, Deserialize, Default)]
+pub struct CustomerFilter {
+    // Matches report name, Tally name or GSTIN
+    pub search: Option<String>,
+    pub category_id: Option<i64>,
+    pub state_code: Option<String>,
+}
+
+const CUSTOMER_SORT_COLUMNS: &[(&str, &str)] = &[
+    ("report_customer", "c.report_customer COLLATE NOCASE"),
+    ("tally_customer", "c.tally_customer COLLATE NOCASE"),
+    ("gst_no", "c.gst_no"),
+    ("state_code", "c.state_code"),
+    ("category", "cat.name COLLATE NOCASE"),
+    ("created_at", "c.created_at"),
+];
+
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn list_customers(
+    pool: State<'_, DbPool>,
+    company_id: i64,
+    query: Option<ListQuery<CustomerFilter>>,
+) -> Result<ListPage<Customer>, AppError> {
+    let conn = db::get_conn(&pool)?;
+    let query = query.unwrap_or_default();
+    let order_by = listing::sort_column(query.sort_by.as_deref(), CUSTOMER_SORT_COLUMNS)?;
+
+    let mut filter = SqlFilter::default();
+    filter.push("c.company_id = ? AND c.deleted_at IS NULL", Value::Integer(company_id));
+    filter.search(
+        &["c.report_customer", "c.tally_customer", "c.gst_no"],
+        query.filter.search.as_deref(),
+    );
+    if let Some(category_id) = query.filter.category_id {
+        filter.push("c.category_id = ?", Value::Integer(category_id));
+    }
+    if let Some(state_code) = query.filter.state_code.as_deref().map(str::trim) {
+        if !state_code.is_empty() {
+            filter.push("c.state_code = ?", Value::Text(state_code.to_string()));
+        }
+    }
+    listing::fetch_page(
+        &conn,
+        SELECT_CUSTOMER,
+        &filter,
+        order_by,
+        "c.id",
+        &query,
+        customer_from_row,
+    )
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn delete_customer(
+    pool: State<'_, DbPool>,
+    id: i64,
+    company_id: i64,
+) -> Result<(), AppError> {
+    let conn = db::get_conn(&pool)?;
+    soft_delete_customer(&conn, id, company_id)
+}
+
+pub fn soft_delete_customer(
+    conn: &rusqlite::Connection,
+    id: i64,
+    company_id: i64,
+) -> Result<(), AppError> {
+    let has_documents: bool = conn.query_row(
+        "SELECT EXISTS(SELECT 1 FROM invoices WHERE customer_id = ?1 AND deleted_at IS NULL)
+                 OR EXISTS(SELECT 1 FROM receipts WHERE customer_id = ?1)",
+        params![id],
+        |row| row.get(0),
+    )?;
+    if has_documents {
+        return Err(AppError::invalid("Customer has invoices or receipts and cannot be deleted"));
+    }
+
+    // Moves the customer to the recycle bin; it is purged once the retention period passes
+    let existing = get_customer_by_id(conn, id, company_id)?;
+    let changed = conn.execute(
+        "UPDATE customers SET deleted_at = CURRENT_TIMESTAMP
+             WHERE id = ?1 AND company_id = ?2 AND deleted_at IS NULL",
+        params![id, company_id],
+    )?;
+    if changed == 0 {
+        return Err(AppError::not_found("Customer not found"));
+    }
+    audit::record(
+        conn,
+        company_id,
+        "customer",
+        id,
+        AuditAction::Delete,
+        existing.as_ref(),
+        None,
+    )
+}
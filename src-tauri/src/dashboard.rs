@@ -0,0 +1,313 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use chrono::{Datelike, Local, Months, NaiveDate};
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+use tauri::State;
+
+use crate::db::{self, get_setting, set_setting, DbPool};
+use crate::error::AppError;
+use crate::financial_years;
+use crate::invoices::{round2, INVOICE_DATE_FORMAT};
+
+const SETTING_CACHE_SECONDS: &str = "dashboard_cache_seconds";
+const DEFAULT_CACHE_SECONDS: u64 = 300;
+const TOP_CUSTOMERS: i64 = 5;
+const TREND_MONTHS: u32 = 12;
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct TopCustomer {
+    pub customer_id: i64,
+    pub customer_name: String,
+    pub invoice_count: i64,
+    pub total_amount: f64,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct MonthlyTrend {
+    // "YYYY-MM"
+    pub month: String,
+    pub invoice_count: i64,
+    pub total_amount: f64,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct DashboardData {
+    pub company_id: i64,
+    pub period: String,
+    // Last day covered: today for the current month, otherwise the month end
+    pub as_of: String,
+    pub financial_year: String,
+    pub mtd_sales: f64,
+    pub ytd_sales: f64,
+    pub outstanding_receivables: f64,
+    pub top_customers: Vec<TopCustomer>,
+    // Output tax on the month's invoices and notes, before input tax credit
+    pub tax_liability_estimate: f64,
+    pub invoice_count_trend: Vec<MonthlyTrend>,
+    pub generated_at: String,
+}
+
+// Recently computed dashboards, keyed by company and period
+#[derive(Default)]
+pub struct DashboardCache {
+    entries: Mutex<HashMap<(i64, String), (Instant, DashboardData)>>,
+}
+
+impl DashboardCache {
+    fn get(&self, key: &(i64, String), ttl: Duration) -> Option<DashboardData> {
+        let entries = self.entries.lock().ok()?;
+        entries
+            .get(key)
+            .filter(|(stored, _)| stored.elapsed() < ttl)
+            .map(|(_, data)| data.clone())
+    }
+
+    fn put(&self, key: (i64, String), data: DashboardData) {
+        if let Ok(mut entries) = self.entries.lock() {
+            entries.insert(key, (Instant::now(), data));
+        }
+    }
+
+    pub fn clear(&self) {
+        if let Ok(mut entries) = self.entries.lock() {
+            entries.clear();
+        }
+    }
+}
+
+pub fn load_cache_seconds(conn: &Connection) -> Result<u64, String> {
+    Ok(get_setting(conn, SETTING_CACHE_SECONDS)?
+        .and_then(|value| value.parse().ok())
+        .unwrap_or(DEFAULT_CACHE_SECONDS))
+}
+
+// Accepts "YYYY-MM" and returns the month's first day and the as-of date within it
+fn parse_period(period: &str, today: NaiveDate) -> Result<(NaiveDate, NaiveDate), AppError> {
+    let invalid = || AppError::validation("period", "Period must be in YYYY-MM format");
+    let first = NaiveDate::parse_from_str(&format!("{}-01", period.trim()), INVOICE_DATE_FORMAT)
+        .map_err(|_| invalid())?;
+    let last = first
+        .checked_add_months(Months::new(1))
+        .and_then(|next| next.pred_opt())
+        .ok_or_else(invalid)?;
+    if first > today {
+        return Err(AppError::validation(
+            "period",
+            "Period cannot be in the future",
+        ));
+    }
+    Ok((first, last.min(today)))
+}
+
+fn sales_between(
+    conn: &Connection,
+    company_id: i64,
+    from: &str,
+    to: &str,
+) -> Result<f64, AppError> {
+    conn.query_row(
+        "SELECT COALESCE(SUM(total_amount), 0) FROM invoices
+         WHERE company_id = ?1 AND status = 'issued' AND invoice_date BETWEEN ?2 AND ?3",
+        params![company_id, from, to],
+        |row| row.get::<_, f64>(0),
+    )
+    .map(round2)
+    .map_err(AppError::from)
+}
+
+// Invoice totals adjusted by issued notes, less what has been received
+fn outstanding_receivables(
+    conn: &Connection,
+    company_id: i64,
+    as_of: &str,
+) -> Result<f64, AppError> {
+    conn.query_row(
+        "SELECT COALESCE(SUM(i.total_amount - i.amount_received +
+                    (SELECT COALESCE(SUM(CASE WHEN n.note_type = 'credit' THEN -n.total_amount
+                                              ELSE n.total_amount END), 0)
+                     FROM credit_debit_notes n
+                     WHERE n.invoice_id = i.id AND n.status = 'issued')), 0)
+         FROM invoices i
+         WHERE i.company_id = ?1 AND i.status = 'issued' AND i.invoice_date <= ?2",
+        params![company_id, as_of],
+        |row| row.get::<_, f64>(0),
+    )
+    .map(|value| round2(value.max(0.0)))
+    .map_err(AppError::from)
+}
+
+fn top_customers(
+    conn: &Connection,
+    company_id: i64,
+    from: &str,
+    to: &str,
+) -> Result<Vec<TopCustomer>, AppError> {
+    let mut stmt = conn.prepare(
+        "SELECT c.id, c.report_customer, COUNT(*), SUM(i.total_amount) AS total
+             FROM invoices i
+             JOIN customers c ON c.id = i.customer_id
+             WHERE i.company_id = ?1 AND i.status = 'issued' AND i.invoice_date BETWEEN ?2 AND ?3
+             GROUP BY c.id
+             ORDER BY total DESC
+             LIMIT ?4",
+    )?;
+    let customers = stmt
+        .query_map(params![company_id, from, to, TOP_CUSTOMERS], |row| {
+            Ok(TopCustomer {
+                customer_id: row.get(0)?,
+                customer_name: row.get(1)?,
+                invoice_count: row.get(2)?,
+                total_amount: round2(row.get(3)?),
+            })
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(customers)
+}
+
+fn tax_liability(
+    conn: &Connection,
+    company_id: i64,
+    from: &str,
+    to: &str,
+) -> Result<f64, AppError> {
+    conn.query_row(
+        "SELECT
+             (SELECT COALESCE(SUM(cgst_amount + sgst_amount + igst_amount + cess_amount), 0)
+              FROM invoices
+              WHERE company_id = ?1 AND status = 'issued' AND invoice_date BETWEEN ?2 AND ?3)
+           + (SELECT COALESCE(SUM(CASE WHEN note_type = 'credit'
+                                       THEN -(cgst_amount + sgst_amount + igst_amount)
+                                       ELSE cgst_amount + sgst_amount + igst_amount END), 0)
+              FROM credit_debit_notes
+              WHERE company_id = ?1 AND status = 'issued' AND note_date BETWEEN ?2 AND ?3)",
+        params![company_id, from, to],
+        |row| row.get::<_, f64>(0),
+    )
+    .map(round2)
+    .map_err(AppError::from)
+}
+
+// The trailing months up to and including the period, oldest first
+fn invoice_trend(
+    conn: &Connection,
+    company_id: i64,
+    month_start: NaiveDate,
+    as_of: &str,
+) -> Result<Vec<MonthlyTrend>, AppError> {
+    let first = month_start
+        .checked_sub_months(Months::new(TREND_MONTHS - 1))
+        .ok_or_else(|| AppError::validation("period", "Period is out of range"))?;
+    let mut stmt = conn.prepare(
+        "SELECT substr(invoice_date, 1, 7) AS month, COUNT(*), SUM(total_amount)
+             FROM invoices
+             WHERE company_id = ?1 AND status = 'issued' AND invoice_date BETWEEN ?2 AND ?3
+             GROUP BY month",
+    )?;
+    let counts = stmt
+        .query_map(
+            params![company_id, first.format(INVOICE_DATE_FORMAT).to_string(), as_of],
+            |row| {
+                Ok((
+                    row.get::<_, String>(0)?,
+                    (row.get::<_, i64>(1)?, row.get::<_, f64>(2)?),
+                ))
+            },
+        )?
+        .collect::<Result<HashMap<_, _>, _>>()?;
+
+    Ok((0..TREND_MONTHS)
+        .filter_map(|offset| first.checked_add_months(Months::new(offset)))
+        .map(|date| {
+            let month = format!("{:04}-{:02}", date.year(), date.month());
+            let (invoice_count, total_amount) = counts
+                .get(&month)
+                .map(|(count, total)| (*count, round2(*total)))
+                .unwrap_or((0, 0.0));
+            MonthlyTrend {
+                month,
+                invoice_count,
+                total_amount,
+            }
+        })
+        .collect())
+}
+
+pub fn build_dashboard(
+    conn: &Connection,
+    company_id: i64,
+    period: &str,
+) -> Result<DashboardData, String> {
+    let (month_start, as_of) = parse_period(period, Local::now().date_naive())?;
+    let start_year = financial_years::fy_start_year(as_of);
+    let (fy_start, _) = financial_years::fy_bounds(start_year)?;
+    let (month_start_str, fy_start_str, as_of_str) = (
+        month_start.format(INVOICE_DATE_FORMAT).to_string(),
+        fy_start.format(INVOICE_DATE_FORMAT).to_string(),
+        as_of.format(INVOICE_DATE_FORMAT).to_string(),
+    );
+
+    Ok(DashboardData {
+        company_id,
+        period: period.trim().to_string(),
+        as_of: as_of_str.clone(),
+        financial_year: financial_years::fy_label(start_year),
+        mtd_sales: sales_between(conn, company_id, &month_start_str, &as_of_str)?,
+        ytd_sales: sales_between(conn, company_id, &fy_start_str, &as_of_str)?,
+        outstanding_receivables: outstanding_receivables(conn, company_id, &as_of_str)?,
+        top_customers: top_customers(conn, company_id, &fy_start_str, &as_of_str)?,
+        tax_liability_estimate: tax_liability(conn, company_id, &month_start_str, &as_of_str)?,
+        invoice_count_trend: invoice_trend(conn, company_id, month_start, &as_of_str)?,
+        generated_at: Local::now().format("%Y-%m-%d %H:%M:%S").to_string(),
+    })
+}
+
+// Defaults to the current month; `refresh` bypasses the cache
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn get_dashboard_data(
+    pool: State<'_, DbPool>,
+    cache: State<'_, DashboardCache>,
+    company_id: i64,
+    period: Option<String>,
+    refresh: Option<bool>,
+) -> Result<DashboardData, AppError> {
+    let period = period
+        .filter(|p| !p.trim().is_empty())
+        .unwrap_or_else(|| Local::now().format("%Y-%m").to_string());
+    let key = (company_id, period.trim().to_string());
+    let conn = db::get_conn(&pool)?;
+    let ttl = Duration::from_secs(load_cache_seconds(&conn)?);
+
+    if !refresh.unwrap_or(false) {
+        if let Some(data) = cache.get(&key, ttl) {
+            return Ok(data);
+        }
+    }
+    let data = build_dashboard(&conn, company_id, &period)?;
+    cache.put(key, data.clone());
+    Ok(data)
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn get_dashboard_cache_seconds(pool: State<'_, DbPool>) -> Result<u64, AppError> {
+    let conn = db::get_conn(&pool)?;
+    Ok(load_cache_seconds(&conn)?)
+}
+
+// Zero turns caching off
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn set_dashboard_cache_seconds(
+    pool: State<'_, DbPool>,
+    cache: State<'_, DashboardCache>,
+    seconds: u64,
+) -> Result<u64, AppError> {
+    let conn = db::get_conn(&pool)?;
+    set_setting(&conn, SETTING_CACHE_SECONDS, &seconds.to_string())?;
+    cache.clear();
+    Ok(load_cache_seconds(&conn)?)
+}
@@ -0,0 +1,403 @@
+use std::collections::BTreeMap;
+use std::fs;
+
+use chrono::Local;
+use rusqlite::types::ValueRef;
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use tauri::State;
+
+use crate::audit::{self, AuditAction};
+use crate::customers;
+use crate::db::{self, DbPool};
+use crate::error::AppError;
+use crate::invoices;
+use crate::transactions;
+
+const TIMESTAMP_FORMAT: &str = "%Y-%m-%d %H:%M:%S";
+// integrity_check stops after this many problems; a file this damaged needs a backup anyway
+const MAX_INTEGRITY_ERRORS: u32 = 100;
+const MAX_FOREIGN_KEY_VIOLATIONS: usize = 1000;
+
+// Records whose parent is missing, in the recycle bin, or belongs to another company
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum OrphanKind {
+    InvoiceCustomer,
+    ReceiptCustomer,
+    CustomerCategory,
+}
+
+const ORPHAN_KINDS: &[OrphanKind] = &[
+    OrphanKind::InvoiceCustomer,
+    OrphanKind::ReceiptCustomer,
+    OrphanKind::CustomerCategory,
+];
+
+impl OrphanKind {
+    fn table(&self) -> &'static str {
+        match self {
+            OrphanKind::InvoiceCustomer => "invoices",
+            OrphanKind::ReceiptCustomer => "receipts",
+            OrphanKind::CustomerCategory => "customers",
+        }
+    }
+
+    // Entity name used in the audit log
+    fn entity(&self) -> &'static str {
+        match self {
+            OrphanKind::InvoiceCustomer => "invoice",
+            OrphanKind::ReceiptCustomer => "receipt",
+            OrphanKind::CustomerCategory => "customer",
+        }
+    }
+
+    fn parent_table(&self) -> &'static str {
+        match self {
+            OrphanKind::InvoiceCustomer | OrphanKind::ReceiptCustomer => "customers",
+            OrphanKind::CustomerCategory => "categories",
+        }
+    }
+
+    fn parent_column(&self) -> &'static str {
+        match self {
+            OrphanKind::InvoiceCustomer | OrphanKind::ReceiptCustomer => "customer_id",
+            OrphanKind::CustomerCategory => "category_id",
+        }
+    }
+
+    // Column shown to the user to recognise the record
+    fn reference_column(&self) -> &'static str {
+        match self {
+            OrphanKind::InvoiceCustomer => "invoice_number",
+            OrphanKind::ReceiptCustomer => "receipt_date",
+            OrphanKind::CustomerCategory => "report_customer",
+        }
+    }
+
+    // Receipts have no recycle bin; relink them or export them for the accountant instead
+    fn can_soft_delete(&self) -> bool {
+        !matches!(self, OrphanKind::ReceiptCustomer)
+    }
+
+    fn child_active(&self) -> &'static str {
+        match self {
+            OrphanKind::ReceiptCustomer => "1",
+            _ => "t.deleted_at IS NULL",
+        }
+    }
+
+    fn find_sql(&self) -> String {
+        format!(
+            "SELECT t.id, t.company_id, t.{reference}, t.{column},
+                    p.id IS NOT NULL AND p.deleted_at IS NOT NULL
+             FROM {table} t
+             LEFT JOIN {parent} p ON p.id = t.{column}
+             WHERE {active}
+               AND (p.id IS NULL OR p.deleted_at IS NOT NULL OR p.company_id != t.company_id)
+             ORDER BY t.company_id, t.id",
+            reference = self.reference_column(),
+            column = self.parent_column(),
+            table = self.table(),
+            parent = self.parent_table(),
+            active = self.child_active(),
+        )
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct OrphanedRow {
+    pub kind: OrphanKind,
+    pub id: i64,
+    pub company_id: i64,
+    pub reference: String,
+    pub parent_id: Option<i64>,
+    // The parent exists but is in the recycle bin, so restoring it is another way out
+    pub parent_deleted: bool,
+    pub can_soft_delete: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ForeignKeyViolation {
+    pub table: String,
+    pub row_id: Option<i64>,
+    pub parent: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct DatabaseHealth {
+    pub healthy: bool,
+    // Messages from PRAGMA integrity_check; empty when the file is sound
+    pub integrity_errors: Vec<String>,
+    pub foreign_key_violations: Vec<ForeignKeyViolation>,
+    pub orphans: Vec<OrphanedRow>,
+    pub checked_at: String,
+}
+
+// A guided fix for one orphaned record
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "action", rename_all = "snake_case")]
+pub enum RepairAction {
+    // Points the record at another active parent of the same company
+    Relink {
+        kind: OrphanKind,
+        id: i64,
+        parent_id: i64,
+    },
+    // Moves the record to the recycle bin, under the usual rules for deleting it
+    SoftDelete {
+        kind: OrphanKind,
+        id: i64,
+    },
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ProblemRowsExport {
+    pub path: String,
+    pub row_count: usize,
+}
+
+fn integrity_errors(conn: &Connection) -> Result<Vec<String>, AppError> {
+    let mut stmt = conn.prepare(&format!("PRAGMA integrity_check({})", MAX_INTEGRITY_ERRORS))?;
+    let messages = stmt
+        .query_map([], |row| row.get::<_, String>(0))?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(messages
+        .into_iter()
+        .filter(|message| message != "ok")
+        .collect())
+}
+
+fn foreign_key_violations(conn: &Connection) -> Result<Vec<ForeignKeyViolation>, AppError> {
+    let mut stmt = conn.prepare("PRAGMA foreign_key_check")?;
+    let violations = stmt
+        .query_map([], |row| {
+            Ok(ForeignKeyViolation {
+                table: row.get(0)?,
+                row_id: row.get(1)?,
+                parent: row.get(2)?,
+            })
+        })?
+        .take(MAX_FOREIGN_KEY_VIOLATIONS)
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(violations)
+}
+
+fn find_orphans(conn: &Connection, kind: OrphanKind) -> Result<Vec<OrphanedRow>, AppError> {
+    let mut stmt = conn.prepare(&kind.find_sql())?;
+    let orphans = stmt
+        .query_map([], |row| {
+            Ok(OrphanedRow {
+                kind,
+                id: row.get(0)?,
+                company_id: row.get(1)?,
+                reference: row.get(2)?,
+                parent_id: row.get(3)?,
+                parent_deleted: row.get(4)?,
+                can_soft_delete: kind.can_soft_delete(),
+            })
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(orphans)
+}
+
+pub fn check(conn: &Connection) -> Result<DatabaseHealth, String> {
+    let integrity_errors = integrity_errors(conn)?;
+    let foreign_key_violations = foreign_key_violations(conn)?;
+    let mut orphans = Vec::new();
+    for kind in ORPHAN_KINDS {
+        orphans.extend(find_orphans(conn, *kind)?);
+    }
+    Ok(DatabaseHealth {
+        healthy: integrity_errors.is_empty()
+            && foreign_key_violations.is_empty()
+            && orphans.is_empty(),
+        integrity_errors,
+        foreign_key_violations,
+        orphans,
+        checked_at: Local::now().format(TIMESTAMP_FORMAT).to_string(),
+    })
+}
+
+fn relink(
+    conn: &Connection,
+    company_id: i64,
+    kind: OrphanKind,
+    id: i64,
+    parent_id: i64,
+) -> Result<(), AppError> {
+    let parent_active: Option<bool> = conn
+        .query_row(
+            &format!(
+                "SELECT deleted_at IS NULL FROM {} WHERE id = ?1 AND company_id = ?2",
+                kind.parent_table()
+            ),
+            params![parent_id, company_id],
+            |row| row.get(0),
+        )
+        .optional()?;
+    if parent_active != Some(true) {
+        return Err(AppError::validation(
+            "parent_id",
+            format!(
+                "Choose an active record from {} of this company",
+                kind.parent_table()
+            ),
+        ));
+    }
+
+    let column = kind.parent_column();
+    let before: i64 = conn
+        .query_row(
+            &format!(
+                "SELECT {} FROM {} WHERE id = ?1 AND company_id = ?2",
+                column,
+                kind.table()
+            ),
+            params![id, company_id],
+            |row| row.get(0),
+        )
+        .optional()?
+        .ok_or_else(|| AppError::not_found("Record not found"))?;
+    conn.execute(
+        &format!(
+            "UPDATE {} SET {} = ?1, updated_at = CURRENT_TIMESTAMP
+             WHERE id = ?2 AND company_id = ?3",
+            kind.table(),
+            column
+        ),
+        params![parent_id, id, company_id],
+    )?;
+    audit::record(
+        conn,
+        company_id,
+        kind.entity(),
+        id,
+        AuditAction::Update,
+        Some(&json!({ column: before })),
+        Some(&json!({ column: parent_id })),
+    )
+}
+
+fn soft_delete(
+    conn: &Connection,
+    company_id: i64,
+    kind: OrphanKind,
+    id: i64,
+) -> Result<(), AppError> {
+    match kind {
+        OrphanKind::InvoiceCustomer => invoices::soft_delete_invoice(conn, id, company_id),
+        OrphanKind::CustomerCategory => customers::soft_delete_customer(conn, id, company_id),
+        OrphanKind::ReceiptCustomer => Err(AppError::invalid(
+            "Receipts cannot be deleted; relink the receipt instead",
+        )),
+    }
+}
+
+fn row_json(row: &rusqlite::Row, columns: &[String]) -> rusqlite::Result<Value> {
+    let mut object = serde_json::Map::new();
+    for (index, column) in columns.iter().enumerate() {
+        let value = match row.get_ref(index)? {
+            ValueRef::Null => Value::Null,
+            ValueRef::Integer(number) => Value::from(number),
+            ValueRef::Real(number) => Value::from(number),
+            ValueRef::Text(text) => Value::from(String::from_utf8_lossy(text).to_string()),
+            // Binary columns only hold generated files, which are of no use for repairs
+            ValueRef::Blob(_) => Value::Null,
+        };
+        object.insert(column.clone(), value);
+    }
+    Ok(Value::Object(object))
+}
+
+// Reads whole rows by rowid; rows on pages too damaged to read are left out
+fn read_rows(conn: &Connection, table: &str, row_ids: &[i64]) -> Result<Vec<Value>, AppError> {
+    let mut stmt = conn.prepare(&format!("SELECT * FROM \"{}\" WHERE rowid = ?1", table))?;
+    let columns: Vec<String> = stmt.column_names().iter().map(|c| c.to_string()).collect();
+    let mut rows = Vec::with_capacity(row_ids.len());
+    for row_id in row_ids {
+        if let Ok(Some(row)) = stmt
+            .query_row(params![row_id], |row| row_json(row, &columns))
+            .optional()
+        {
+            rows.push(row);
+        }
+    }
+    Ok(rows)
+}
+
+// Runs the integrity and foreign key checks and looks for records whose parent is gone or in
+// the recycle bin. Read only; fixes go through `repair_database`.
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn check_database_health(pool: State<'_, DbPool>) -> Result<DatabaseHealth, AppError> {
+    let conn = db::get_conn(&pool)?;
+    Ok(check(&conn)?)
+}
+
+// Applies one repair and returns the health as it stands afterwards
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn repair_database(
+    pool: State<'_, DbPool>,
+    company_id: i64,
+    repair: RepairAction,
+) -> Result<DatabaseHealth, AppError> {
+    let mut conn = db::get_conn(&pool)?;
+    transactions::atomically(&mut conn, |tx| match repair {
+        RepairAction::Relink {
+            kind,
+            id,
+            parent_id,
+        } => relink(tx, company_id, kind, id, parent_id),
+        RepairAction::SoftDelete { kind, id } => soft_delete(tx, company_id, kind, id),
+    })?;
+    Ok(check(&conn)?)
+}
+
+// Writes every orphaned record and foreign key violation, as full rows grouped by table, to a
+// JSON file so they can be reviewed or re-entered before being repaired
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn export_problem_rows(
+    pool: State<'_, DbPool>,
+    path: String,
+) -> Result<ProblemRowsExport, AppError> {
+    let conn = db::get_conn(&pool)?;
+    let health = check(&conn)?;
+
+    let mut row_ids: BTreeMap<String, Vec<i64>> = BTreeMap::new();
+    for orphan in &health.orphans {
+        row_ids
+            .entry(orphan.kind.table().to_string())
+            .or_default()
+            .push(orphan.id);
+    }
+    for violation in &health.foreign_key_violations {
+        if let Some(row_id) = violation.row_id {
+            row_ids
+                .entry(violation.table.clone())
+                .or_default()
+                .push(row_id);
+        }
+    }
+
+    let mut tables = serde_json::Map::new();
+    let mut row_count = 0;
+    for (table, mut ids) in row_ids {
+        ids.sort_unstable();
+        ids.dedup();
+        let rows = read_rows(&conn, &table, &ids)?;
+        row_count += rows.len();
+        tables.insert(table, Value::Array(rows));
+    }
+    let export = json!({
+        "exported_at": Local::now().format(TIMESTAMP_FORMAT).to_string(),
+        "integrity_errors": health.integrity_errors,
+        "tables": tables,
+    });
+    let text = serde_json::to_string_pretty(&export).map_err(|e| e.to_string())?;
+    fs::write(&path, text).map_err(|e| format!("Failed to write {}: {}", path, e))?;
+    Ok(ProblemRowsExport { path, row_count })
+}
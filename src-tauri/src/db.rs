@@ -0,0 +1,184 @@
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+use r2d2::event::{AcquireEvent, CheckoutEvent, HandleEvent, ReleaseEvent, TimeoutEvent};
+use r2d2_sqlite::SqliteConnectionManager;
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Manager};
+
+use crate::error::AppError;
+use crate::{change_events, encryption, migrations};
+
+pub type DbPool = r2d2::Pool<SqliteConnectionManager>;
+pub type DbConn = r2d2::PooledConnection<SqliteConnectionManager>;
+
+// Same file name the frontend SQL plugin uses, so both sides see one database
+pub const DATABASE_FILE: &str = "sales_report.db";
+
+const POOL_SIZE: u32 = 8;
+// Kept open between commands so bulk imports never pay for opening and keying a connection
+const MIN_IDLE: u32 = 2;
+const CHECKOUT_TIMEOUT: Duration = Duration::from_secs(30);
+// Prepared statements cached per connection for `prepare_cached`
+const STATEMENT_CACHE_CAPACITY: usize = 64;
+
+// Counters fed by the pool's event handler since the app started
+static CONNECTIONS_OPENED: AtomicU64 = AtomicU64::new(0);
+static CONNECTIONS_CLOSED: AtomicU64 = AtomicU64::new(0);
+static CHECKOUTS: AtomicU64 = AtomicU64::new(0);
+static CHECKOUT_WAIT_MICROS: AtomicU64 = AtomicU64::new(0);
+static MAX_CHECKOUT_WAIT_MICROS: AtomicU64 = AtomicU64::new(0);
+static CHECKOUT_TIMEOUTS: AtomicU64 = AtomicU64::new(0);
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PoolMetrics {
+    pub max_size: u32,
+    pub min_idle: u32,
+    pub connections: u32,
+    pub idle_connections: u32,
+    pub in_use: u32,
+    pub connections_opened: u64,
+    pub connections_closed: u64,
+    pub checkouts: u64,
+    pub average_wait_ms: f64,
+    pub max_wait_ms: f64,
+    pub timeouts: u64,
+    pub statement_cache_capacity: usize,
+}
+
+#[derive(Debug)]
+struct PoolEvents;
+
+impl HandleEvent for PoolEvents {
+    fn handle_acquire(&self, _event: AcquireEvent) {
+        CONNECTIONS_OPENED.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn handle_release(&self, _event: ReleaseEvent) {
+        CONNECTIONS_CLOSED.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn handle_checkout(&self, event: CheckoutEvent) {
+        let waited = event.duration().as_micros() as u64;
+        CHECKOUTS.fetch_add(1, Ordering::Relaxed);
+        CHECKOUT_WAIT_MICROS.fetch_add(waited, Ordering::Relaxed);
+        MAX_CHECKOUT_WAIT_MICROS.fetch_max(waited, Ordering::Relaxed);
+    }
+
+    fn handle_timeout(&self, _event: TimeoutEvent) {
+        CHECKOUT_TIMEOUTS.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+// The SQL plugin resolves relative database paths against the app config dir
+pub fn database_path(app: &AppHandle) -> Result<PathBuf, String> {
+    let dir = app
+        .path()
+        .app_config_dir()
+        .map_err(|e| format!("Failed to resolve app config directory: {}", e))?;
+    std::fs::create_dir_all(&dir)
+        .map_err(|e| format!("Failed to create app config directory: {}", e))?;
+    Ok(dir.join(DATABASE_FILE))
+}
+
+// Run on every connection the pool opens
+fn prepare_connection(conn: &mut Connection) -> rusqlite::Result<()> {
+    if let Some(key) = encryption::current_key() {
+        encryption::apply_key(conn, &key)?;
+    }
+    conn.set_prepared_statement_cache_capacity(STATEMENT_CACHE_CAPACITY);
+    change_events::install_hooks(conn);
+    conn.execute_batch(
+        "PRAGMA foreign_keys = ON;
+         PRAGMA busy_timeout = 5000;",
+    )
+}
+
+pub fn init_pool(path: &Path) -> Result<DbPool, String> {
+    build_pool(SqliteConnectionManager::file(path))
+}
+
+// A private in-memory database shared by the pool's connections, for the test harness. It
+// lives as long as one connection stays open, which the pool's idle minimum guarantees.
+#[cfg(feature = "test-harness")]
+pub fn init_memory_pool(name: &str) -> Result<DbPool, String> {
+    use rusqlite::OpenFlags;
+
+    let manager =
+        SqliteConnectionManager::file(format!("file:{}?mode=memory&cache=shared", name))
+            .with_flags(
+                OpenFlags::SQLITE_OPEN_READ_WRITE
+                    | OpenFlags::SQLITE_OPEN_CREATE
+                    | OpenFlags::SQLITE_OPEN_URI,
+            );
+    build_pool(manager)
+}
+
+fn build_pool(manager: SqliteConnectionManager) -> Result<DbPool, String> {
+    let manager = manager.with_init(prepare_connection);
+    let pool = r2d2::Pool::builder()
+        .max_size(POOL_SIZE)
+        .min_idle(Some(MIN_IDLE))
+        .connection_timeout(CHECKOUT_TIMEOUT)
+        .event_handler(Box::new(PoolEvents))
+        .build(manager)
+        .map_err(|e| format!("Failed to open database: {}", e))?;
+
+    let mut conn = get_conn(&pool)?;
+    migrations::run_pending(&mut conn)?;
+
+    Ok(pool)
+}
+
+pub fn get_conn(pool: &DbPool) -> Result<DbConn, AppError> {
+    pool.get().map_err(|e| AppError::Database {
+        message: format!("Failed to get database connection: {}", e),
+    })
+}
+
+pub fn pool_metrics(pool: &DbPool) -> PoolMetrics {
+    let state = pool.state();
+    let checkouts = CHECKOUTS.load(Ordering::Relaxed);
+    let waited_ms = CHECKOUT_WAIT_MICROS.load(Ordering::Relaxed) as f64 / 1000.0;
+    PoolMetrics {
+        max_size: pool.max_size(),
+        min_idle: pool.min_idle().unwrap_or(0),
+        connections: state.connections,
+        idle_connections: state.idle_connections,
+        in_use: state.connections - state.idle_connections,
+        connections_opened: CONNECTIONS_OPENED.load(Ordering::Relaxed),
+        connections_closed: CONNECTIONS_CLOSED.load(Ordering::Relaxed),
+        checkouts,
+        average_wait_ms: if checkouts == 0 { 0.0 } else { waited_ms / checkouts as f64 },
+        max_wait_ms: MAX_CHECKOUT_WAIT_MICROS.load(Ordering::Relaxed) as f64 / 1000.0,
+        timeouts: CHECKOUT_TIMEOUTS.load(Ordering::Relaxed),
+        statement_cache_capacity: STATEMENT_CACHE_CAPACITY,
+    }
+}
+
+// Key-value access to the app_settings table
+pub fn get_setting(conn: &Connection, key: &str) -> Result<Option<String>, AppError> {
+    Ok(conn
+        .query_row(
+            "SELECT value FROM app_settings WHERE key = ?1",
+            params![key],
+            |row| row.get(0),
+        )
+        .optional()?)
+}
+
+pub fn set_setting(conn: &Connection, key: &str, value: &str) -> Result<(), AppError> {
+    conn.execute(
+        "INSERT INTO app_settings (key, value) VALUES (?1, ?2)
+         ON CONFLICT(key) DO UPDATE SET value = ?2, updated_at = CURRENT_TIMESTAMP",
+        params![key, value],
+    )?;
+    Ok(())
+}
+
+pub fn delete_setting(conn: &Connection, key: &str) -> Result<(), AppError> {
+    conn.execute("DELETE FROM app_settings WHERE key = ?1", params![key])?;
+    Ok(())
+}
@@ -0,0 +1,797 @@
+use chrono::{Local, NaiveDate};
+use rusqlite::{params, Connection, OptionalExtension, Row};
+use serde::{Deserialize, Serialize};
+use tauri::State;
+
+use crate::audit::{self, AuditAction};
+use crate::customers;
+use crate::db::{self, DbPool};
+use crate::error::AppError;
+use crate::invoices::{
+    self, round2, CreateInvoice, InvoiceLineInput, InvoiceStatus, SavedInvoice,
+    INVOICE_DATE_FORMAT,
+};
+use crate::items;
+use crate::numbering::{self, DocumentType};
+use crate::place_of_supply::{self, SupplyKind};
+use crate::units;
+
+// Why goods leave without an invoice; each is its own row in GSTR-1 table 13
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ChallanPurpose {
+    JobWork,
+    SupplyOnApproval,
+    LiquidGas,
+    Other,
+}
+
+impl ChallanPurpose {
+    pub const ALL: [ChallanPurpose; 4] = [
+        ChallanPurpose::JobWork,
+        ChallanPurpose::SupplyOnApproval,
+        ChallanPurpose::LiquidGas,
+        ChallanPurpose::Other,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ChallanPurpose::JobWork => "job_work",
+            ChallanPurpose::SupplyOnApproval => "supply_on_approval",
+            ChallanPurpose::LiquidGas => "liquid_gas",
+            ChallanPurpose::Other => "other",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "job_work" => Some(ChallanPurpose::JobWork),
+            "supply_on_approval" => Some(ChallanPurpose::SupplyOnApproval),
+            "liquid_gas" => Some(ChallanPurpose::LiquidGas),
+            "other" => Some(ChallanPurpose::Other),
+            _ => None,
+        }
+    }
+
+    // Document type number and label in GSTR-1 table 13
+    pub fn doc_type(&self) -> (usize, &'static str) {
+        match self {
+            ChallanPurpose::JobWork => (9, "Delivery Challan for job work"),
+            ChallanPurpose::SupplyOnApproval => (10, "Delivery Challan for supply on approval"),
+            ChallanPurpose::LiquidGas => (11, "Delivery Challan in case of liquid gas"),
+            ChallanPurpose::Other => (
+                12,
+                "Delivery Challan in cases other than by way of supply \
+                 (excluding at S no. 9 to 11)",
+            ),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ChallanStatus {
+    Draft,
+    Issued,
+    // Billed by a later invoice; locked from then on
+    Invoiced,
+    Cancelled,
+}
+
+impl ChallanStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ChallanStatus::Draft => "draft",
+            ChallanStatus::Issued => "issued",
+            ChallanStatus::Invoiced => "invoiced",
+            ChallanStatus::Cancelled => "cancelled",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "draft" => Some(ChallanStatus::Draft),
+            "issued" => Some(ChallanStatus::Issued),
+            "invoiced" => Some(ChallanStatus::Invoiced),
+            "cancelled" => Some(ChallanStatus::Cancelled),
+            _ => None,
+        }
+    }
+
+    // How the document issued summary counts the challan
+    pub fn document_status(&self) -> InvoiceStatus {
+        match self {
+            ChallanStatus::Draft => InvoiceStatus::Draft,
+            ChallanStatus::Issued | ChallanStatus::Invoiced => InvoiceStatus::Issued,
+            ChallanStatus::Cancelled => InvoiceStatus::Cancelled,
+        }
+    }
+}
+
+// Delivery challan data model; `total_value` is the value of the goods, which carry no tax
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct DeliveryChallan {
+    pub id: i64,
+    pub company_id: i64,
+    pub challan_number: String,
+    pub challan_date: String,
+    pub customer_id: i64,
+    pub customer_name: String,
+    pub purpose: ChallanPurpose,
+    pub place_of_supply: String,
+    pub vehicle_number: Option<String>,
+    pub total_value: f64,
+    pub status: ChallanStatus,
+    pub notes: Option<String>,
+    pub invoice_id: Option<i64>,
+    pub invoice_number: Option<String>,
+    pub created_at: Option<String>,
+    pub updated_at: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ChallanLine {
+    pub id: i64,
+    pub challan_id: i64,
+    pub line_no: i64,
+    pub item_id: Option<i64>,
+    pub unit_id: Option<i64>,
+    pub description: String,
+    pub hsn_code: String,
+    pub quantity: f64,
+    pub rate: f64,
+    pub value: f64,
+    pub gst_rate: f64,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ChallanLineInput {
+    #[serde(default)]
+    pub item_id: Option<i64>,
+    #[serde(default)]
+    pub unit_id: Option<i64>,
+    pub description: String,
+    pub hsn_code: String,
+    pub quantity: f64,
+    pub rate: f64,
+    // Used when the challan is converted into an invoice
+    pub gst_rate: f64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SaveDeliveryChallan {
+    // Left empty to take the next number from the challan sequence
+    #[serde(default)]
+    pub challan_number: String,
+    pub challan_date: String,
+    pub customer_id: i64,
+    pub purpose: ChallanPurpose,
+    // Left empty to use the customer's state
+    #[serde(default)]
+    pub place_of_supply: String,
+    #[serde(default)]
+    pub vehicle_number: Option<String>,
+    pub notes: Option<String>,
+    pub lines: Vec<ChallanLineInput>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ChallanWithLines {
+    #[serde(flatten)]
+    pub challan: DeliveryChallan,
+    pub lines: Vec<ChallanLine>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ConvertedChallan {
+    pub challan: DeliveryChallan,
+    pub invoice: SavedInvoice,
+}
+
+const SELECT_CHALLAN: &str = "
+    SELECT d.id, d.company_id, d.challan_number, d.challan_date, d.customer_id,
+           COALESCE(c.report_customer, '') AS customer_name, d.purpose, d.place_of_supply,
+           d.vehicle_number, d.total_value, d.status, d.notes, d.invoice_id,
+           i.invoice_number, d.created_at, d.updated_at
+    FROM delivery_challans d
+    LEFT JOIN customers c ON c.id = d.customer_id
+    LEFT JOIN invoices i ON i.id = d.invoice_id";
+
+const SELECT_CHALLAN_LINE: &str = "
+    SELECT id, challan_id, line_no, item_id, unit_id, description, hsn_code, quantity, rate,
+           value, gst_rate
+    FROM delivery_challan_lines";
+
+fn challan_from_row(row: &Row) -> rusqlite::Result<DeliveryChallan> {
+    let purpose: String = row.get("purpose")?;
+    let status: String = row.get("status")?;
+    Ok(DeliveryChallan {
+        id: row.get("id")?,
+        company_id: row.get("company_id")?,
+        challan_number: row.get("challan_number")?,
+        challan_date: row.get("challan_date")?,
+        customer_id: row.get("customer_id")?,
+        customer_name: row.get("customer_name")?,
+        purpose: ChallanPurpose::parse(&purpose).unwrap_or(ChallanPurpose::Other),
+        place_of_supply: row.get("place_of_supply")?,
+        vehicle_number: row.get("vehicle_number")?,
+        total_value: row.get("total_value")?,
+        status: ChallanStatus::parse(&status).unwrap_or(ChallanStatus::Draft),
+        notes: row.get("notes")?,
+        invoice_id: row.get("invoice_id")?,
+        invoice_number: row.get("invoice_number")?,
+        created_at: row.get("created_at")?,
+        updated_at: row.get("updated_at")?,
+    })
+}
+
+fn challan_line_from_row(row: &Row) -> rusqlite::Result<ChallanLine> {
+    Ok(ChallanLine {
+        id: row.get("id")?,
+        challan_id: row.get("challan_id")?,
+        line_no: row.get("line_no")?,
+        item_id: row.get("item_id")?,
+        unit_id: row.get("unit_id")?,
+        description: row.get("description")?,
+        hsn_code: row.get("hsn_code")?,
+        quantity: row.get("quantity")?,
+        rate: row.get("rate")?,
+        value: row.get("value")?,
+        gst_rate: row.get("gst_rate")?,
+    })
+}
+
+fn map_write_error(e: rusqlite::Error) -> AppError {
+    let message = e.to_string();
+    if message.contains("UNIQUE constraint failed: delivery_challans") {
+        return AppError::conflict(
+            "challan_number",
+            "A delivery challan with this number already exists",
+        );
+    }
+    AppError::from(e)
+}
+
+fn parse_date(field: &str, value: &str) -> Result<NaiveDate, AppError> {
+    NaiveDate::parse_from_str(value.trim(), INVOICE_DATE_FORMAT)
+        .map_err(|_| AppError::validation(field, "Enter the date as YYYY-MM-DD"))
+}
+
+pub fn get_challan_by_id(
+    conn: &Connection,
+    id: i64,
+    company_id: i64,
+) -> Result<Option<DeliveryChallan>, AppError> {
+    conn.query_row(
+        &format!("{} WHERE d.id = ?1 AND d.company_id = ?2", SELECT_CHALLAN),
+        params![id, company_id],
+        challan_from_row,
+    )
+    .optional()
+    .map_err(AppError::from)
+}
+
+pub fn get_challan_lines(conn: &Connection, challan_id: i64) -> Result<Vec<ChallanLine>, AppError> {
+    let mut stmt =
+        conn.prepare(&format!("{} WHERE challan_id = ?1 ORDER BY line_no", SELECT_CHALLAN_LINE))?;
+    let lines = stmt
+        .query_map(params![challan_id], challan_line_from_row)?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(lines)
+}
+
+// Challans dated in the range, for the document issued summary
+pub fn get_challans_in_range(
+    conn: &Connection,
+    company_id: i64,
+    from: &str,
+    to: &str,
+) -> Result<Vec<DeliveryChallan>, AppError> {
+    let mut stmt = conn.prepare(&format!(
+        "{} WHERE d.company_id = ?1 AND d.challan_date BETWEEN ?2 AND ?3
+             ORDER BY d.challan_date, d.id",
+        SELECT_CHALLAN
+    ))?;
+    let challans = stmt
+        .query_map(params![company_id, from, to], challan_from_row)?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(challans)
+}
+
+fn with_lines(conn: &Connection, id: i64, company_id: i64) -> Result<ChallanWithLines, AppError> {
+    let challan = get_challan_by_id(conn, id, company_id)?
+        .ok_or_else(|| AppError::not_found("Delivery challan not found"))?;
+    let lines = get_challan_lines(conn, id)?;
+    Ok(ChallanWithLines { challan, lines })
+}
+
+fn invoice_lines(lines: &[ChallanLineInput]) -> Vec<InvoiceLineInput> {
+    lines
+        .iter()
+        .map(|line| InvoiceLineInput {
+            description: line.description.trim().to_string(),
+            hsn_code: line.hsn_code.trim().to_string(),
+            quantity: line.quantity,
+            rate: line.rate,
+            discount_percent: 0.0,
+            discount: 0.0,
+            invoice_discount: 0.0,
+            taxable_value: 0.0,
+            gst_rate: line.gst_rate,
+            cgst_amount: 0.0,
+            sgst_amount: 0.0,
+            igst_amount: 0.0,
+            cess_rate: 0.0,
+            cess_amount: 0.0,
+            item_id: line.item_id,
+            unit_id: line.unit_id,
+            sales_order_line_id: None,
+            batch_id: None,
+            serial_numbers: Vec::new(),
+        })
+        .collect()
+}
+
+fn validate(
+    conn: &Connection,
+    company_id: i64,
+    challan: &SaveDeliveryChallan,
+) -> Result<(String, String), AppError> {
+    let date = parse_date("challan_date", &challan.challan_date)?;
+    if challan.lines.is_empty() {
+        return Err(AppError::validation("lines", "Add at least one line to the challan"));
+    }
+    for (index, line) in challan.lines.iter().enumerate() {
+        let line_no = index + 1;
+        if line.description.trim().is_empty() || line.description.len() > 500 {
+            return Err(AppError::validation(
+                "lines",
+                format!("Line {}: description must be 1 to 500 characters", line_no),
+            ));
+        }
+        let hsn_code = line.hsn_code.trim();
+        if !(4..=8).contains(&hsn_code.len()) || !hsn_code.chars().all(|c| c.is_ascii_digit()) {
+            return Err(AppError::validation(
+                "lines",
+                format!("Line {}: HSN/SAC code must be 4 to 8 digits", line_no),
+            ));
+        }
+        let amounts =
+            [("quantity", line.quantity), ("rate", line.rate), ("GST rate", line.gst_rate)];
+        for (label, amount) in amounts {
+            if !amount.is_finite() || amount < 0.0 {
+                return Err(AppError::validation(
+                    "lines",
+                    format!("Line {}: {} must be a non-negative number", line_no, label),
+                ));
+            }
+        }
+        if line.quantity == 0.0 {
+            return Err(AppError::validation(
+                "lines",
+                format!("Line {}: quantity must be greater than zero", line_no),
+            ));
+        }
+    }
+    if challan.vehicle_number.as_deref().is_some_and(|v| v.trim().len() > 20) {
+        return Err(AppError::validation(
+            "vehicle_number",
+            "Vehicle number must be 20 characters or less",
+        ));
+    }
+    if challan.notes.as_deref().is_some_and(|n| n.len() > 1000) {
+        return Err(AppError::validation("notes", "Notes must be 1000 characters or less"));
+    }
+    let lines = invoice_lines(&challan.lines);
+    units::validate_line_units(conn, &lines)?;
+    items::validate_line_items(conn, company_id, &lines)?;
+
+    let customer = customers::get_customer_by_id(conn, challan.customer_id, company_id)?
+        .ok_or_else(|| AppError::not_found("Customer not found"))?;
+    let place = match challan.place_of_supply.trim() {
+        "" => place_of_supply::default_place_of_supply(&customer, SupplyKind::Regular),
+        place => place.to_string(),
+    };
+    Ok((date.format(INVOICE_DATE_FORMAT).to_string(), place))
+}
+
+fn replace_lines(
+    conn: &Connection,
+    challan_id: i64,
+    lines: &[ChallanLineInput],
+) -> Result<f64, AppError> {
+    conn.execute("DELETE FROM delivery_challan_lines WHERE challan_id = ?1", params![challan_id])?;
+    let mut stmt = conn.prepare(
+        "INSERT INTO delivery_challan_lines (challan_id, line_no, item_id, unit_id,
+                                                 description, hsn_code, quantity, rate, value,
+                                                 gst_rate)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+    )?;
+    let mut total = 0.0;
+    for (index, line) in lines.iter().enumerate() {
+        let value = round2(line.quantity * line.rate);
+        total += value;
+        stmt.execute(params![
+            challan_id,
+            index as i64 + 1,
+            line.item_id,
+            line.unit_id,
+            line.description.trim(),
+            line.hsn_code.trim(),
+            line.quantity,
+            line.rate,
+            value,
+            line.gst_rate
+        ])?;
+    }
+    Ok(round2(total))
+}
+
+fn require_status(challan: &DeliveryChallan, allowed: &[ChallanStatus]) -> Result<(), AppError> {
+    if allowed.contains(&challan.status) {
+        return Ok(());
+    }
+    Err(AppError::validation(
+        "status",
+        format!("Delivery challan {} is {}", challan.challan_number, challan.status.as_str()),
+    ))
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn list_delivery_challans(
+    pool: State<'_, DbPool>,
+    company_id: i64,
+    status: Option<ChallanStatus>,
+    customer_id: Option<i64>,
+) -> Result<Vec<DeliveryChallan>, AppError> {
+    let conn = db::get_conn(&pool)?;
+    let mut stmt = conn.prepare(&format!(
+        "{} WHERE d.company_id = ?1 AND (?2 IS NULL OR d.status = ?2)
+               AND (?3 IS NULL OR d.customer_id = ?3)
+             ORDER BY d.challan_date DESC, d.id DESC",
+        SELECT_CHALLAN
+    ))?;
+    let challans = stmt
+        .query_map(params![company_id, status.map(|s| s.as_str()), customer_id], challan_from_row)?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(challans)
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn get_delivery_challan(
+    pool: State<'_, DbPool>,
+    id: i64,
+    company_id: i64,
+) -> Result<ChallanWithLines, AppError> {
+    let conn = db::get_conn(&pool)?;
+    with_lines(&conn, id, company_id)
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn create_delivery_challan(
+    pool: State<'_, DbPool>,
+    company_id: i64,
+    challan: SaveDeliveryChallan,
+) -> Result<ChallanWithLines, AppError> {
+    let mut conn = db::get_conn(&pool)?;
+    let tx = conn.transaction()?;
+    let (challan_date, place) = validate(&tx, company_id, &challan)?;
+    let challan_number = match challan.challan_number.trim() {
+        "" => numbering::allocate_number(
+            &tx,
+            company_id,
+            DocumentType::DeliveryChallan,
+            &challan_date,
+        )?,
+        number => number.to_string(),
+    };
+    tx.execute(
+        "INSERT INTO delivery_challans (company_id, challan_number, challan_date, customer_id,
+                                        purpose, place_of_supply, vehicle_number, status, notes)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, 'draft', ?8)",
+        params![
+            company_id,
+            challan_number,
+            challan_date,
+            challan.customer_id,
+            challan.purpose.as_str(),
+            place,
+            challan.vehicle_number.as_deref().map(|v| v.trim().to_uppercase()),
+            challan.notes,
+        ],
+    )
+    .map_err(map_write_error)?;
+    let id = tx.last_insert_rowid();
+    let total_value = replace_lines(&tx, id, &challan.lines)?;
+    tx.execute(
+        "UPDATE delivery_challans SET total_value = ?1 WHERE id = ?2",
+        params![total_value, id],
+    )?;
+    let created = with_lines(&tx, id, company_id)?;
+    audit::record(
+        &tx,
+        company_id,
+        "delivery_challan",
+        id,
+        AuditAction::Create,
+        None,
+        Some(&created),
+    )?;
+    tx.commit()?;
+    Ok(created)
+}
+
+// Only drafts can be edited; an issued challan has gone out with the goods
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn update_delivery_challan(
+    pool: State<'_, DbPool>,
+    id: i64,
+    company_id: i64,
+    challan: SaveDeliveryChallan,
+) -> Result<ChallanWithLines, AppError> {
+    let mut conn = db::get_conn(&pool)?;
+    let tx = conn.transaction()?;
+    let existing = with_lines(&tx, id, company_id)?;
+    require_status(&existing.challan, &[ChallanStatus::Draft])?;
+    let (challan_date, place) = validate(&tx, company_id, &challan)?;
+    let challan_number = match challan.challan_number.trim() {
+        "" => existing.challan.challan_number.clone(),
+        number => number.to_string(),
+    };
+    let total_value = replace_lines(&tx, id, &challan.lines)?;
+    tx.execute(
+        "UPDATE delivery_challans SET challan_number = ?1, challan_date = ?2, customer_id = ?3,
+                purpose = ?4, place_of_supply = ?5, vehicle_number = ?6, notes = ?7,
+                total_value = ?8, updated_at = CURRENT_TIMESTAMP
+         WHERE id = ?9 AND company_id = ?10",
+        params![
+            challan_number,
+            challan_date,
+            challan.customer_id,
+            challan.purpose.as_str(),
+            place,
+            challan.vehicle_number.as_deref().map(|v| v.trim().to_uppercase()),
+            challan.notes,
+            total_value,
+            id,
+            company_id,
+        ],
+    )
+    .map_err(map_write_error)?;
+    let updated = with_lines(&tx, id, company_id)?;
+    audit::record(
+        &tx,
+        company_id,
+        "delivery_challan",
+        id,
+        AuditAction::Update,
+        Some(&existing),
+        Some(&updated),
+    )?;
+    tx.commit()?;
+    Ok(updated)
+}
+
+// Drafts are issued or cancelled, issued challans cancelled; invoiced challans stay as they are
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn set_delivery_challan_status(
+    pool: State<'_, DbPool>,
+    id: i64,
+    company_id: i64,
+    status: ChallanStatus,
+) -> Result<DeliveryChallan, AppError> {
+    let conn = db::get_conn(&pool)?;
+    let existing = get_challan_by_id(&conn, id, company_id)?
+        .ok_or_else(|| AppError::not_found("Delivery challan not found"))?;
+    match status {
+        ChallanStatus::Issued => require_status(&existing, &[ChallanStatus::Draft])?,
+        ChallanStatus::Cancelled => {
+            require_status(&existing, &[ChallanStatus::Draft, ChallanStatus::Issued])?
+        }
+        ChallanStatus::Draft | ChallanStatus::Invoiced => {
+            return Err(AppError::validation(
+                "status",
+                "A challan can only be issued or cancelled",
+            ));
+        }
+    }
+    conn.execute(
+        "UPDATE delivery_challans SET status = ?1, updated_at = CURRENT_TIMESTAMP
+         WHERE id = ?2 AND company_id = ?3",
+        params![status.as_str(), id, company_id],
+    )?;
+    let updated = get_challan_by_id(&conn, id, company_id)?
+        .ok_or_else(|| AppError::internal("Delivery challan not found after update"))?;
+    audit::record(
+        &conn,
+        company_id,
+        "delivery_challan",
+        id,
+        AuditAction::Update,
+        Some(&existing),
+        Some(&updated),
+    )?;
+    Ok(updated)
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn delete_delivery_challan(
+    pool: State<'_, DbPool>,
+    id: i64,
+    company_id: i64,
+) -> Result<(), AppError> {
+    let conn = db::get_conn(&pool)?;
+    let existing = with_lines(&conn, id, company_id)?;
+    // Issued numbers must stay accounted for in the document summary; cancel those instead
+    require_status(&existing.challan, &[ChallanStatus::Draft])?;
+    conn.execute(
+        "DELETE FROM delivery_challans WHERE id = ?1 AND company_id = ?2",
+        params![id, company_id],
+    )?;
+    audit::record(
+        &conn,
+        company_id,
+        "delivery_challan",
+        id,
+        AuditAction::Delete,
+        Some(&existing),
+        None,
+    )
+}
+
+fn mark_invoiced(
+    conn: &Connection,
+    existing: &DeliveryChallan,
+    invoice_id: i64,
+) -> Result<DeliveryChallan, AppError> {
+    conn.execute(
+        "UPDATE delivery_challans SET status = 'invoiced', invoice_id = ?1,
+                                      updated_at = CURRENT_TIMESTAMP
+         WHERE id = ?2",
+        params![invoice_id, existing.id],
+    )?;
+    let updated = get_challan_by_id(conn, existing.id, existing.company_id)?
+        .ok_or_else(|| AppError::internal("Delivery challan not found after update"))?;
+    audit::record(
+        conn,
+        existing.company_id,
+        "delivery_challan",
+        existing.id,
+        AuditAction::Update,
+        Some(existing),
+        Some(&updated),
+    )?;
+    Ok(updated)
+}
+
+// Bills an issued challan with a draft invoice carrying its lines, dated `invoice_date` (today
+// when not given)
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn convert_challan_to_invoice(
+    pool: State<'_, DbPool>,
+    id: i64,
+    company_id: i64,
+    invoice_date: Option<String>,
+) -> Result<ConvertedChallan, AppError> {
+    let invoice_date = match invoice_date.as_deref().filter(|d| !d.trim().is_empty()) {
+        Some(date) => parse_date("invoice_date", date)?,
+        None => Local::now().date_naive(),
+    }
+    .format(INVOICE_DATE_FORMAT)
+    .to_string();
+    let mut conn = db::get_conn(&pool)?;
+    let tx = conn.transaction()?;
+    let existing = with_lines(&tx, id, company_id)?;
+    let challan = &existing.challan;
+    require_status(challan, &[ChallanStatus::Issued])?;
+    if invoice_date < challan.challan_date {
+        return Err(AppError::validation(
+            "invoice_date",
+            "The invoice cannot be dated before the challan",
+        ));
+    }
+
+    let lines = existing
+        .lines
+        .iter()
+        .map(|line| ChallanLineInput {
+            item_id: line.item_id,
+            unit_id: line.unit_id,
+            description: line.description.clone(),
+            hsn_code: line.hsn_code.clone(),
+            quantity: line.quantity,
+            rate: line.rate,
+            gst_rate: line.gst_rate,
+        })
+        .collect::<Vec<_>>();
+    let invoice = invoices::create_invoice_from_lines(
+        &tx,
+        CreateInvoice {
+            company_id,
+            invoice_number: String::new(),
+            invoice_date,
+            customer_id: challan.customer_id,
+            place_of_supply: challan.place_of_supply.clone(),
+            supply_kind: SupplyKind::Regular,
+            export: None,
+            sez_mode: None,
+            discount_percent: 0.0,
+            discount_amount: 0.0,
+            reverse_charge: false,
+            taxable_value: 0.0,
+            cgst_amount: 0.0,
+            sgst_amount: 0.0,
+            igst_amount: 0.0,
+            cess_amount: 0.0,
+            total_amount: 0.0,
+            currency: None,
+            exchange_rate: None,
+            status: Some(InvoiceStatus::Draft),
+            notes: Some(format!("Against delivery challan {}", challan.challan_number)),
+            lines: invoice_lines(&lines),
+        },
+    )?;
+    let invoice_id = invoice.invoice.id.unwrap_or_default();
+    let challan = mark_invoiced(&tx, challan, invoice_id)?;
+    tx.commit()?;
+    Ok(ConvertedChallan { challan, invoice })
+}
+
+// Records that an invoice raised separately bills this challan
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn link_challan_to_invoice(
+    pool: State<'_, DbPool>,
+    id: i64,
+    company_id: i64,
+    invoice_id: i64,
+) -> Result<DeliveryChallan, AppError> {
+    let conn = db::get_conn(&pool)?;
+    let existing = get_challan_by_id(&conn, id, company_id)?
+        .ok_or_else(|| AppError::not_found("Delivery challan not found"))?;
+    require_status(&existing, &[ChallanStatus::Issued])?;
+    let invoice = invoices::get_invoice_by_id(&conn, invoice_id, company_id)?
+        .ok_or_else(|| AppError::not_found("Invoice not found"))?;
+    if invoice.customer_id != existing.customer_id {
+        return Err(AppError::validation(
+            "invoice_id",
+            "The invoice is for a different customer",
+        ));
+    }
+    if invoice.status == InvoiceStatus::Cancelled {
+        return Err(AppError::validation("invoice_id", "The invoice has been cancelled"));
+    }
+    if invoice.invoice_date < existing.challan_date {
+        return Err(AppError::validation(
+            "invoice_id",
+            "The invoice is dated before the challan",
+        ));
+    }
+    mark_invoiced(&conn, &existing, invoice_id)
+}
+
+// Challans billed by an invoice, for showing on the invoice
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn list_invoice_challans(
+    pool: State<'_, DbPool>,
+    invoice_id: i64,
+    company_id: i64,
+) -> Result<Vec<DeliveryChallan>, AppError> {
+    let conn = db::get_conn(&pool)?;
+    let mut stmt = conn.prepare(&format!(
+        "{} WHERE d.company_id = ?1 AND d.invoice_id = ?2 ORDER BY d.challan_date",
+        SELECT_CHALLAN
+    ))?;
+    let challans = stmt
+        .query_map(params![company_id, invoice_id], challan_from_row)?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(challans)
+}
@@ -0,0 +1,452 @@
+use std::collections::HashSet;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use chrono::{Duration, Local, NaiveDate};
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+use tauri::State;
+
+use crate::categories;
+use crate::companies::{self, Company, CreateCompany};
+use crate::customers::{self, CreateCustomer};
+use crate::db::{self, DbPool};
+use crate::error::AppError;
+use crate::gstin;
+use crate::invoices::{self, CreateInvoice, InvoiceLineInput, InvoiceStatus, INVOICE_DATE_FORMAT};
+use crate::place_of_supply::SupplyKind;
+use crate::receipts::{self, CreateReceipt, PaymentMode};
+use crate::transactions;
+
+// (state code, city, pincode) of the places demo companies and customers are based in
+const PLACES: &[(&str, &str, &str)] = &[
+    ("27", "Mumbai", "400001"),
+    ("27", "Pune", "411001"),
+    ("29", "Bengaluru", "560001"),
+    ("33", "Chennai", "600001"),
+    ("33", "Coimbatore", "641001"),
+    ("24", "Ahmedabad", "380001"),
+    ("24", "Surat", "395003"),
+    ("07", "New Delhi", "110001"),
+    ("09", "Lucknow", "226001"),
+    ("19", "Kolkata", "700001"),
+    ("36", "Hyderabad", "500001"),
+    ("08", "Jaipur", "302001"),
+    ("32", "Kochi", "682011"),
+    ("03", "Ludhiana", "141001"),
+];
+
+const COMPANY_NAMES: &[&str] = &[
+    "Shree Ganesh Distributors",
+    "Kaveri Industrial Supplies",
+    "Sagar Electronics",
+];
+
+const CUSTOMER_NAMES: &[&str] = &[
+    "Agarwal",
+    "Balaji",
+    "Bharat",
+    "Deccan",
+    "Ganga",
+    "Gupta",
+    "Jain",
+    "Kamdhenu",
+    "Krishna",
+    "Lakshmi",
+    "Mahalaxmi",
+    "Mehta",
+    "Nandi",
+    "Om Sai",
+    "Patel",
+    "Rathi",
+    "Reddy",
+    "Saraswati",
+    "Shah",
+    "Shakti",
+    "Sharma",
+    "Shiv",
+    "Sri Venkateswara",
+    "Surya",
+    "Tirupati",
+    "Trimurti",
+    "Vinayak",
+    "Yamuna",
+];
+
+const CUSTOMER_SUFFIXES: &[&str] = &[
+    "Traders",
+    "Enterprises",
+    "Agencies",
+    "Stores",
+    "Industries",
+    "Marketing",
+    "Pvt Ltd",
+    "& Sons",
+];
+
+const DEMO_CATEGORIES: &[&str] = &["Wholesale", "Retail", "Distributor"];
+
+// (description, HSN, lowest rate, highest rate, GST rate)
+const PRODUCTS: &[(&str, &str, f64, f64, f64)] = &[
+    ("Basmati rice, 25 kg bag", "1006", 1800.0, 2600.0, 5.0),
+    ("Cotton t-shirt", "6109", 180.0, 450.0, 5.0),
+    ("Paracetamol tablets, box of 100", "3004", 95.0, 160.0, 12.0),
+    ("Stainless steel tiffin box", "7323", 350.0, 780.0, 12.0),
+    ("A4 copier paper, ream", "4802", 240.0, 320.0, 12.0),
+    ("Office chair", "9401", 2800.0, 6500.0, 18.0),
+    ("LED bulb, 9 W", "8539", 70.0, 140.0, 18.0),
+    ("Laptop, 14 inch", "8471", 32000.0, 58000.0, 18.0),
+    ("Smartphone", "8517", 9000.0, 24000.0, 18.0),
+    ("Split AC, 1.5 ton", "8415", 28000.0, 42000.0, 28.0),
+];
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum DemoSize {
+    Small,
+    Medium,
+    Large,
+}
+
+impl DemoSize {
+    // (companies, customers per company, invoices per company per month)
+    fn volumes(&self) -> (usize, usize, usize) {
+        match self {
+            DemoSize::Small => (1, 12, 8),
+            DemoSize::Medium => (2, 40, 30),
+            DemoSize::Large => (3, 150, 120),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DemoDataSummary {
+    pub companies: Vec<Company>,
+    pub customers: usize,
+    pub invoices: usize,
+    pub receipts: usize,
+}
+
+// xorshift64*; demo data only needs to look varied, not be unpredictable
+struct Rng(u64);
+
+impl Rng {
+    fn seeded() -> Self {
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|elapsed| elapsed.as_nanos() as u64)
+            .unwrap_or_default();
+        Rng(nanos | 1)
+    }
+
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        self.0.wrapping_mul(0x2545_F491_4F6C_DD1D)
+    }
+
+    fn below(&mut self, bound: usize) -> usize {
+        (self.next() % bound.max(1) as u64) as usize
+    }
+
+    fn pick<'a, T>(&mut self, values: &'a [T]) -> &'a T {
+        &values[self.below(values.len())]
+    }
+
+    fn chance(&mut self, percent: usize) -> bool {
+        self.below(100) < percent
+    }
+
+    fn letter(&mut self) -> char {
+        (b'A' + self.below(26) as u8) as char
+    }
+
+    fn between(&mut self, low: f64, high: f64) -> f64 {
+        low + (high - low) * (self.next() % 10_000) as f64 / 10_000.0
+    }
+}
+
+// A GSTIN that passes the checksum. The PAN's fourth letter is the holder type (C company,
+// F firm, P individual) and its fifth the initial of the name, as on real PANs.
+fn fake_gstin(rng: &mut Rng, state_code: &str, name: &str, holder: char) -> Result<String, String> {
+    let initial = name
+        .chars()
+        .find(|c| c.is_ascii_alphabetic())
+        .map(|c| c.to_ascii_uppercase())
+        .unwrap_or('X');
+    let first_fourteen = format!(
+        "{}{}{}{}{}{}{:04}{}1Z",
+        state_code,
+        rng.letter(),
+        rng.letter(),
+        rng.letter(),
+        holder,
+        initial,
+        1000 + rng.below(9000),
+        rng.letter()
+    );
+    let check = gstin::compute_check_digit(&first_fourteen)
+        .ok_or_else(|| "Failed to compute a GSTIN check digit".to_string())?;
+    Ok(format!("{}{}", first_fourteen, check))
+}
+
+fn demo_categories(conn: &Connection, company_id: i64) -> Result<Vec<i64>, String> {
+    let mut ids = Vec::with_capacity(DEMO_CATEGORIES.len());
+    for name in DEMO_CATEGORIES {
+        conn.execute(
+            "INSERT OR IGNORE INTO categories (name, company_id) VALUES (?1, ?2)",
+            params![name, company_id],
+        )
+        .map_err(|e| e.to_string())?;
+        if let Some(id) = categories::get_category_by_name(conn, name, company_id)?
+            .and_then(|category| category.id)
+        {
+            ids.push(id);
+        }
+    }
+    Ok(ids)
+}
+
+fn seed_company(conn: &Connection, rng: &mut Rng, index: usize) -> Result<Company, AppError> {
+    let (state_code, city, pincode) = PLACES[(index * 5) % PLACES.len()];
+    let name = format!("{} (Demo)", COMPANY_NAMES[index % COMPANY_NAMES.len()]);
+    companies::create_company_in(
+        conn,
+        CreateCompany {
+            gst_no: fake_gstin(rng, state_code, &name, 'C')?,
+            company_name: name,
+            state_code: state_code.to_string(),
+            address: Some(format!("{}, Main Road", 10 + rng.below(190))),
+            city: Some(city.to_string()),
+            pincode: Some(pincode.to_string()),
+        },
+    )
+}
+
+// Customers spread over the states, a few of them unregistered and about half in the company's
+// own state so both intra- and inter-state supplies show up in the reports
+fn seed_customers(
+    conn: &Connection,
+    rng: &mut Rng,
+    company: &Company,
+    count: usize,
+) -> Result<Vec<i64>, AppError> {
+    let company_id = company.id.unwrap_or_default();
+    let category_ids = demo_categories(conn, company_id)?;
+    let mut names = HashSet::new();
+    let mut ids = Vec::with_capacity(count);
+    while ids.len() < count {
+        let (state_code, city, pincode) = if rng.chance(50) {
+            *PLACES
+                .iter()
+                .find(|(code, _, _)| *code == company.state_code)
+                .unwrap_or(rng.pick(PLACES))
+        } else {
+            *rng.pick(PLACES)
+        };
+        let name = format!(
+            "{} {}",
+            rng.pick(CUSTOMER_NAMES),
+            rng.pick(CUSTOMER_SUFFIXES)
+        );
+        // Tally ledger names must be unique; repeats of a name and city get a number
+        let mut tally_name = format!("{} - {}", name, city);
+        if names.contains(&tally_name) {
+            tally_name = format!("{} {}", tally_name, ids.len() + 1);
+        }
+        names.insert(tally_name.clone());
+        let holder = if name.ends_with("Pvt Ltd") { 'C' } else { 'F' };
+        let gst_no = if rng.chance(85) {
+            Some(fake_gstin(rng, state_code, &name, holder)?)
+        } else {
+            None
+        };
+        let created = customers::create_customer_in(
+            conn,
+            CreateCustomer {
+                report_customer: name,
+                tally_customer: tally_name,
+                gst_no,
+                registration_type: None,
+                state_code: Some(state_code.to_string()),
+                category_id: *rng.pick(&category_ids),
+                company_id,
+                address: None,
+                city: Some(city.to_string()),
+                pincode: Some(pincode.to_string()),
+            },
+            None,
+        )?;
+        ids.push(created.id.unwrap_or_default());
+    }
+    Ok(ids)
+}
+
+fn invoice_lines(rng: &mut Rng) -> Vec<InvoiceLineInput> {
+    (0..1 + rng.below(4))
+        .map(|_| {
+            let (description, hsn_code, low, high, gst_rate) = *rng.pick(PRODUCTS);
+            InvoiceLineInput {
+                description: description.to_string(),
+                hsn_code: hsn_code.to_string(),
+                quantity: (1 + rng.below(if high > 10_000.0 { 3 } else { 40 })) as f64,
+                rate: invoices::round2(rng.between(low, high)),
+                discount_percent: 0.0,
+                discount: 0.0,
+                invoice_discount: 0.0,
+                taxable_value: 0.0,
+                gst_rate,
+                cgst_amount: 0.0,
+                sgst_amount: 0.0,
+                igst_amount: 0.0,
+                cess_rate: 0.0,
+                cess_amount: 0.0,
+                item_id: None,
+                unit_id: None,
+                sales_order_line_id: None,
+                batch_id: None,
+                serial_numbers: Vec::new(),
+            }
+        })
+        .collect()
+}
+
+fn record_receipt(
+    conn: &Connection,
+    rng: &mut Rng,
+    company_id: i64,
+    (date, customer_id, amount): (NaiveDate, i64, f64),
+) -> Result<(), String> {
+    let mode = *rng.pick(&[
+        PaymentMode::BankTransfer,
+        PaymentMode::Upi,
+        PaymentMode::Cheque,
+    ]);
+    receipts::record_receipt_in(
+        conn,
+        &CreateReceipt {
+            company_id,
+            customer_id,
+            receipt_date: date.format(INVOICE_DATE_FORMAT).to_string(),
+            mode,
+            reference: Some(format!("{:06}", rng.below(1_000_000))),
+            amount,
+            notes: None,
+            auto_allocate: true,
+        },
+    )?;
+    Ok(())
+}
+
+// A year of invoices up to today, oldest first so numbering runs in date order. About two in
+// three are paid a few weeks later; receipts are recorded as their date comes up, so FIFO
+// allocation only sees invoices raised before them.
+fn seed_invoices(
+    conn: &Connection,
+    rng: &mut Rng,
+    company_id: i64,
+    customer_ids: &[i64],
+    per_month: usize,
+) -> Result<(usize, usize), AppError> {
+    let today = Local::now().date_naive();
+    let start = today - Duration::days(365);
+    let mut dates: Vec<NaiveDate> = (0..per_month * 12)
+        .map(|_| start + Duration::days(rng.below(366) as i64))
+        .collect();
+    dates.sort();
+
+    let mut pending = Vec::new();
+    let (mut invoices, mut receipts) = (0, 0);
+    for date in dates {
+        pending.sort_by_key(|(paid_on, _, _): &(NaiveDate, i64, f64)| *paid_on);
+        while pending
+            .first()
+            .is_some_and(|(paid_on, _, _)| *paid_on <= date)
+        {
+            record_receipt(conn, rng, company_id, pending.remove(0))?;
+            receipts += 1;
+        }
+
+        let customer_id = *rng.pick(customer_ids);
+        let status = if rng.chance(95) {
+            InvoiceStatus::Issued
+        } else {
+            InvoiceStatus::Draft
+        };
+        let saved = invoices::create_invoice_from_lines(
+            conn,
+            CreateInvoice {
+                company_id,
+                invoice_number: String::new(),
+                invoice_date: date.format(INVOICE_DATE_FORMAT).to_string(),
+                customer_id,
+                place_of_supply: String::new(),
+                supply_kind: SupplyKind::Regular,
+                export: None,
+                sez_mode: None,
+                discount_percent: 0.0,
+                discount_amount: 0.0,
+                reverse_charge: false,
+                taxable_value: 0.0,
+                cgst_amount: 0.0,
+                sgst_amount: 0.0,
+                igst_amount: 0.0,
+                cess_amount: 0.0,
+                total_amount: 0.0,
+                currency: None,
+                exchange_rate: None,
+                status: Some(status),
+                notes: None,
+                lines: invoice_lines(rng),
+            },
+        )?;
+        invoices += 1;
+        if status == InvoiceStatus::Issued && rng.chance(65) {
+            let paid_on = date + Duration::days(10 + rng.below(50) as i64);
+            pending.push((paid_on, customer_id, saved.invoice.total_amount));
+        }
+    }
+    for receipt in pending
+        .into_iter()
+        .filter(|(paid_on, _, _)| *paid_on <= today)
+    {
+        record_receipt(conn, rng, company_id, receipt)?;
+        receipts += 1;
+    }
+    Ok((invoices, receipts))
+}
+
+// Creates demo companies, each with categories, customers carrying valid GSTINs for their
+// state, a year of invoices and the receipts against them. Everything is added in one
+// transaction, next to any real data; the companies are marked "(Demo)" so they are easy to
+// find and delete.
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn seed_demo_data(
+    pool: State<'_, DbPool>,
+    size: DemoSize,
+) -> Result<DemoDataSummary, AppError> {
+    let (company_count, customers_per_company, invoices_per_month) = size.volumes();
+    let mut rng = Rng::seeded();
+    let mut conn = db::get_conn(&pool)?;
+    transactions::atomically(&mut conn, |tx| {
+        let mut summary = DemoDataSummary {
+            companies: Vec::with_capacity(company_count),
+            customers: 0,
+            invoices: 0,
+            receipts: 0,
+        };
+        for index in 0..company_count {
+            let company = seed_company(tx, &mut rng, index)?;
+            let company_id = company.id.unwrap_or_default();
+            let customer_ids = seed_customers(tx, &mut rng, &company, customers_per_company)?;
+            let (invoices, receipts) =
+                seed_invoices(tx, &mut rng, company_id, &customer_ids, invoices_per_month)?;
+            summary.customers += customer_ids.len();
+            summary.invoices += invoices;
+            summary.receipts += receipts;
+            summary.companies.push(company);
+        }
+        Ok(summary)
+    })
+}
@@ -0,0 +1,310 @@
+use std::backtrace::Backtrace;
+use std::collections::VecDeque;
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use chrono::Local;
+use rusqlite::Connection;
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, State};
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipWriter};
+
+use crate::db::{self, DbPool, PoolMetrics};
+use crate::error::AppError;
+use crate::logging::Logger;
+use crate::migrations;
+use crate::settings;
+
+const SETTING_CRASH_REPORTS: &str = "crash_reports_enabled";
+const CRASH_DIR: &str = "crashes";
+const RECENT_COMMAND_LIMIT: usize = 20;
+// Newest log files bundled with a support archive
+const ARCHIVE_LOG_FILES: usize = 3;
+
+// Names only; arguments can hold customer data and never go into a crash dump
+static RECENT_COMMANDS: Mutex<VecDeque<RecentCommand>> = Mutex::new(VecDeque::new());
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct RecentCommand {
+    pub command: String,
+    pub invoked_at: String,
+}
+
+// Written to the crash folder of the app log dir when the backend panics. Crash dumps stay on
+// this machine; they only leave it in a support archive the user exports after opting in.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct CrashDump {
+    pub occurred_at: String,
+    pub app_version: String,
+    pub os: String,
+    pub arch: String,
+    pub thread: Option<String>,
+    pub message: String,
+    pub location: Option<String>,
+    pub backtrace: String,
+    pub recent_commands: Vec<RecentCommand>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct CrashSummary {
+    pub file_name: String,
+    pub occurred_at: String,
+    pub message: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct CrashReportingStatus {
+    pub enabled: bool,
+    pub crashes: Vec<CrashSummary>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct DiagnosticsExport {
+    pub path: String,
+    pub crash_count: usize,
+    pub log_file_count: usize,
+}
+
+#[derive(Debug, Serialize)]
+struct SystemInfo<'a> {
+    exported_at: String,
+    app_version: &'a str,
+    os: &'a str,
+    arch: &'a str,
+    schema_version: i64,
+}
+
+pub fn note_command(command: &str) {
+    if let Ok(mut recent) = RECENT_COMMANDS.lock() {
+        if recent.len() == RECENT_COMMAND_LIMIT {
+            recent.pop_front();
+        }
+        recent.push_back(RecentCommand {
+            command: command.to_string(),
+            invoked_at: Local::now().to_rfc3339(),
+        });
+    }
+}
+
+fn crash_dir(log_dir: &Path) -> PathBuf {
+    log_dir.join(CRASH_DIR)
+}
+
+fn write_dump(dir: &Path, dump: &CrashDump) -> Result<PathBuf, String> {
+    fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+    let path = dir.join(format!("crash-{}.json", Local::now().format("%Y%m%d-%H%M%S-%3f")));
+    let json = serde_json::to_string_pretty(dump).map_err(|e| e.to_string())?;
+    fs::write(&path, json).map_err(|e| e.to_string())?;
+    Ok(path)
+}
+
+// Records a crash dump for every panic, then hands over to the previous hook so the panic is
+// still reported as before
+pub fn install_panic_hook(log_dir: &Path, app_version: String) {
+    let dir = crash_dir(log_dir);
+    let previous = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        let payload = info.payload();
+        let message = payload
+            .downcast_ref::<&str>()
+            .map(|message| message.to_string())
+            .or_else(|| payload.downcast_ref::<String>().cloned())
+            .unwrap_or_else(|| "Unknown panic".to_string());
+        // try_lock: the panic may have happened while the list was being updated
+        let recent_commands = RECENT_COMMANDS
+            .try_lock()
+            .map(|recent| recent.iter().cloned().collect())
+            .unwrap_or_default();
+        let dump = CrashDump {
+            occurred_at: Local::now().to_rfc3339(),
+            app_version: app_version.clone(),
+            os: std::env::consts::OS.to_string(),
+            arch: std::env::consts::ARCH.to_string(),
+            thread: std::thread::current().name().map(str::to_string),
+            message,
+            location: info
+                .location()
+                .map(|location| format!("{}:{}", location.file(), location.line())),
+            backtrace: Backtrace::force_capture().to_string(),
+            recent_commands,
+        };
+        match write_dump(&dir, &dump) {
+            Ok(path) => tracing::error!(path = %path.display(), "panic: {}", dump.message),
+            Err(e) => tracing::error!("panic: {} (crash dump not written: {})", dump.message, e),
+        }
+        previous(info);
+    }));
+}
+
+fn load_enabled(conn: &Connection) -> Result<bool, String> {
+    Ok(settings::get(conn, SETTING_CRASH_REPORTS)?.unwrap_or(false))
+}
+
+// Newest first
+fn crash_files(log_dir: &Path) -> Result<Vec<PathBuf>, String> {
+    let dir = crash_dir(log_dir);
+    if !dir.exists() {
+        return Ok(Vec::new());
+    }
+    let mut files = fs::read_dir(&dir)
+        .map_err(|e| format!("Failed to read crash folder: {}", e))?
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "json"))
+        .collect::<Vec<_>>();
+    files.sort();
+    files.reverse();
+    Ok(files)
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default()
+}
+
+fn crash_summaries(log_dir: &Path) -> Result<Vec<CrashSummary>, String> {
+    let mut summaries = Vec::new();
+    for path in crash_files(log_dir)? {
+        // Dumps that cannot be read are still bundled, just not listed
+        let Ok(dump) = fs::read_to_string(&path)
+            .map_err(|e| e.to_string())
+            .and_then(|json| serde_json::from_str::<CrashDump>(&json).map_err(|e| e.to_string()))
+        else {
+            continue;
+        };
+        summaries.push(CrashSummary {
+            file_name: file_name(&path),
+            occurred_at: dump.occurred_at,
+            message: dump.message,
+        });
+    }
+    Ok(summaries)
+}
+
+pub(crate) fn add_file(
+    archive: &mut ZipWriter<File>,
+    name: &str,
+    bytes: &[u8],
+    options: SimpleFileOptions,
+) -> Result<(), String> {
+    archive
+        .start_file(name, options)
+        .map_err(|e| format!("Failed to write {} to the archive: {}", name, e))?;
+    archive
+        .write_all(bytes)
+        .map_err(|e| format!("Failed to write {} to the archive: {}", name, e))
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn get_crash_reporting(
+    pool: State<'_, DbPool>,
+    logger: State<'_, Logger>,
+) -> Result<CrashReportingStatus, AppError> {
+    let conn = db::get_conn(&pool)?;
+    Ok(CrashReportingStatus {
+        enabled: load_enabled(&conn)?,
+        crashes: crash_summaries(logger.dir())?,
+    })
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn set_crash_reporting(
+    pool: State<'_, DbPool>,
+    logger: State<'_, Logger>,
+    enabled: bool,
+) -> Result<CrashReportingStatus, AppError> {
+    let conn = db::get_conn(&pool)?;
+    settings::put(&conn, SETTING_CRASH_REPORTS, &enabled)?;
+    Ok(CrashReportingStatus {
+        enabled,
+        crashes: crash_summaries(logger.dir())?,
+    })
+}
+
+// Bundles the crash dumps, the newest log files and a short system summary into a zip the user
+// can send to support. Nothing is sent anywhere by the app itself.
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn export_diagnostics(
+    pool: State<'_, DbPool>,
+    logger: State<'_, Logger>,
+    app: AppHandle,
+    path: String,
+    include_logs: Option<bool>,
+) -> Result<DiagnosticsExport, AppError> {
+    let path = path.trim().to_string();
+    if path.is_empty() {
+        return Err(AppError::validation(
+            "path",
+            "Choose where to save the support archive",
+        ));
+    }
+    let conn = db::get_conn(&pool)?;
+    if !load_enabled(&conn)? {
+        return Err(AppError::invalid(
+            "Turn on crash reporting before exporting diagnostics",
+        ));
+    }
+    let version = app.package_info().version.to_string();
+    let system = SystemInfo {
+        exported_at: Local::now().to_rfc3339(),
+        app_version: &version,
+        os: std::env::consts::OS,
+        arch: std::env::consts::ARCH,
+        schema_version: migrations::current_version(&conn)?,
+    };
+
+    let file = File::create(&path).map_err(|e| format!("Failed to create {}: {}", path, e))?;
+    let mut archive = ZipWriter::new(file);
+    let options = SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+    let system = serde_json::to_vec_pretty(&system).map_err(|e| e.to_string())?;
+    add_file(&mut archive, "system.json", &system, options)?;
+
+    let crashes = crash_files(logger.dir())?;
+    for crash in &crashes {
+        let bytes = fs::read(crash).map_err(|e| e.to_string())?;
+        add_file(&mut archive, &format!("crashes/{}", file_name(crash)), &bytes, options)?;
+    }
+    let logs = if include_logs.unwrap_or(true) {
+        logger.log_files()?.into_iter().take(ARCHIVE_LOG_FILES).collect()
+    } else {
+        Vec::new()
+    };
+    for log in &logs {
+        let bytes = fs::read(log).map_err(|e| e.to_string())?;
+        add_file(&mut archive, &format!("logs/{}", file_name(log)), &bytes, options)?;
+    }
+    archive
+        .finish()
+        .map_err(|e| format!("Failed to finish the support archive: {}", e))?;
+
+    Ok(DiagnosticsExport {
+        path,
+        crash_count: crashes.len(),
+        log_file_count: logs.len(),
+    })
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn clear_crash_reports(logger: State<'_, Logger>) -> Result<usize, AppError> {
+    let crashes = crash_files(logger.dir())?;
+    for crash in &crashes {
+        fs::remove_file(crash)
+            .map_err(|e| format!("Failed to delete {}: {}", file_name(crash), e))?;
+    }
+    Ok(crashes.len())
+}
+
+// Connection pool usage since the app started, for diagnosing slow imports
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn get_pool_metrics(pool: State<'_, DbPool>) -> Result<PoolMetrics, AppError> {
+    Ok(db::pool_metrics(&pool))
+}
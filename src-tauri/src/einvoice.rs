@@ -0,0 +1,1025 @@
+use std::io::Cursor;
+use std::time::Duration;
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use chrono::{NaiveDate, NaiveDateTime};
+use rusqlite::{params, Connection, OptionalExtension, Row};
+use serde::{Deserialize, Serialize};
+use image::{ImageFormat, Luma};
+use qrcode::{EcLevel, QrCode};
+use serde_json::{json, Value};
+use tauri::State;
+
+use crate::audit::{self, AuditAction};
+use crate::cancellation::Cancellations;
+use crate::companies::{self, Company};
+use crate::credit_notes;
+use crate::customers::{self, Customer};
+use crate::db::{self, delete_setting, get_setting, set_setting, DbPool};
+use crate::encryption;
+use crate::error::AppError;
+use crate::exports::{self, ExportMode};
+use crate::eway_bills::{self, EwayBillStatus};
+use crate::financial_years;
+use crate::invoices::{self, round2, Invoice, InvoiceLine, InvoiceStatus, INVOICE_DATE_FORMAT};
+use crate::place_of_supply::SupplyKind;
+use crate::webhooks::{self, WebhookEvent};
+
+const SETTING_ENVIRONMENT: &str = "einvoice_environment";
+const SETTING_SANDBOX_URL: &str = "einvoice_sandbox_url";
+const SETTING_PRODUCTION_URL: &str = "einvoice_production_url";
+const SETTING_CLIENT_ID: &str = "einvoice_client_id";
+const SETTING_USERNAME: &str = "einvoice_username";
+const SETTING_TOKEN_EXPIRY: &str = "einvoice_token_expiry";
+// Secrets live in the OS keyring, never in app_settings
+const CLIENT_SECRET_ENTRY: &str = "einvoice-client-secret";
+const PASSWORD_ENTRY: &str = "einvoice-password";
+const AUTH_TOKEN_ENTRY: &str = "einvoice-auth-token";
+// Settings that held them in plain text before; moved to the keyring on the next read
+const LEGACY_SECRET_SETTINGS: &[(&str, &str)] = &[
+    ("einvoice_client_secret", CLIENT_SECRET_ENTRY),
+    ("einvoice_password", PASSWORD_ENTRY),
+    ("einvoice_auth_token", AUTH_TOKEN_ENTRY),
+];
+
+const DEFAULT_SANDBOX_URL: &str = "https://einv-apisandbox.nic.in";
+const AUTH_PATH: &str = "/eivital/v1.04/auth";
+const GENERATE_IRN_PATH: &str = "/eicore/v1.03/Invoice";
+const CANCEL_IRN_PATH: &str = "/eicore/v1.03/Invoice/Cancel";
+// The IRP only accepts cancellations this soon after the IRN was generated
+const CANCEL_WINDOW_HOURS: i64 = 24;
+const CANCEL_REMARKS_LIMIT: usize = 100;
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+
+const SCHEMA_VERSION: &str = "1.1";
+const IRP_DATE_FORMAT: &str = "%d/%m/%Y";
+pub const IRP_TIMESTAMP_FORMAT: &str = "%Y-%m-%d %H:%M:%S";
+// Buyer details the schema expects for exports
+const EXPORT_STATE_CODE: &str = "96";
+const EXPORT_PINCODE: &str = "999999";
+const DEFAULT_QR_SIZE: u32 = 300;
+const MAX_QR_SIZE: u32 = 2000;
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum EInvoiceEnvironment {
+    Sandbox,
+    Production,
+}
+
+impl EInvoiceEnvironment {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            EInvoiceEnvironment::Sandbox => "sandbox",
+            EInvoiceEnvironment::Production => "production",
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum EInvoiceStatus {
+    Active,
+    Cancelled,
+}
+
+impl EInvoiceStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            EInvoiceStatus::Active => "active",
+            EInvoiceStatus::Cancelled => "cancelled",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "active" => Some(EInvoiceStatus::Active),
+            "cancelled" => Some(EInvoiceStatus::Cancelled),
+            _ => None,
+        }
+    }
+}
+
+// Reasons the IRP accepts for cancelling an IRN
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum CancelReason {
+    Duplicate,
+    DataEntryMistake,
+    OrderCancelled,
+    Other,
+}
+
+impl CancelReason {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CancelReason::Duplicate => "duplicate",
+            CancelReason::DataEntryMistake => "data_entry_mistake",
+            CancelReason::OrderCancelled => "order_cancelled",
+            CancelReason::Other => "other",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "duplicate" => Some(CancelReason::Duplicate),
+            "data_entry_mistake" => Some(CancelReason::DataEntryMistake),
+            "order_cancelled" => Some(CancelReason::OrderCancelled),
+            "other" => Some(CancelReason::Other),
+            _ => None,
+        }
+    }
+
+    fn code(&self) -> &'static str {
+        match self {
+            CancelReason::Duplicate => "1",
+            CancelReason::DataEntryMistake => "2",
+            CancelReason::OrderCancelled => "3",
+            CancelReason::Other => "4",
+        }
+    }
+
+    fn label(&self) -> &'static str {
+        match self {
+            CancelReason::Duplicate => "Duplicate",
+            CancelReason::DataEntryMistake => "Data entry mistake",
+            CancelReason::OrderCancelled => "Order cancelled",
+            CancelReason::Other => "Other",
+        }
+    }
+}
+
+// IRP/GSP connection settings. Payload encryption, where required, is handled by the GSP. The
+// client secret and password are only ever written, never returned.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct EInvoiceConfig {
+    pub environment: EInvoiceEnvironment,
+    pub sandbox_url: String,
+    pub production_url: String,
+    pub client_id: String,
+    pub has_client_secret: bool,
+    pub username: String,
+    pub has_password: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SaveEInvoiceConfig {
+    pub environment: EInvoiceEnvironment,
+    pub sandbox_url: String,
+    pub production_url: String,
+    pub client_id: String,
+    // For each secret, None keeps the saved value and an empty string removes it
+    pub client_secret: Option<String>,
+    pub username: String,
+    pub password: Option<String>,
+}
+
+// E-invoice data model
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct EInvoice {
+    pub invoice_id: i64,
+    pub environment: String,
+    pub irn: String,
+    pub ack_no: String,
+    pub ack_date: String,
+    pub signed_invoice: Option<String>,
+    pub signed_qr_code: String,
+    pub status: EInvoiceStatus,
+    pub cancel_reason: Option<CancelReason>,
+    pub cancel_remarks: Option<String>,
+    pub cancelled_at: Option<String>,
+    pub created_at: Option<String>,
+    pub updated_at: Option<String>,
+}
+
+const SELECT_EINVOICE: &str = "
+    SELECT invoice_id, environment, irn, ack_no, ack_date, signed_invoice, signed_qr_code,
+           status, cancel_reason, cancel_remarks, cancelled_at, created_at, updated_at
+    FROM einvoices";
+
+fn einvoice_from_row(row: &Row) -> rusqlite::Result<EInvoice> {
+    let status: String = row.get("status")?;
+    let cancel_reason: Option<String> = row.get("cancel_reason")?;
+    Ok(EInvoice {
+        invoice_id: row.get("invoice_id")?,
+        environment: row.get("environment")?,
+        irn: row.get("irn")?,
+        ack_no: row.get("ack_no")?,
+        ack_date: row.get("ack_date")?,
+        signed_invoice: row.get("signed_invoice")?,
+        signed_qr_code: row.get("signed_qr_code")?,
+        status: EInvoiceStatus::parse(&status).unwrap_or(EInvoiceStatus::Active),
+        cancel_reason: cancel_reason.as_deref().and_then(CancelReason::parse),
+        cancel_remarks: row.get("cancel_remarks")?,
+        cancelled_at: row.get("cancelled_at")?,
+        created_at: row.get("created_at")?,
+        updated_at: row.get("updated_at")?,
+    })
+}
+
+pub fn get_einvoice_by_invoice_id(
+    conn: &Connection,
+    invoice_id: i64,
+) -> Result<Option<EInvoice>, AppError> {
+    conn.query_row(
+        &format!("{} WHERE invoice_id = ?1", SELECT_EINVOICE),
+        params![invoice_id],
+        einvoice_from_row,
+    )
+    .optional()
+    .map_err(AppError::from)
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct EInvoiceQr {
+    pub invoice_id: i64,
+    pub irn: String,
+    pub mime_type: String,
+    pub png_base64: String,
+}
+
+pub fn qr_code(data: &str) -> Result<QrCode, String> {
+    // Signed QR payloads are long JWTs; fall back to a lower level if they do not fit
+    QrCode::with_error_correction_level(data, EcLevel::M)
+        .or_else(|_| QrCode::with_error_correction_level(data, EcLevel::L))
+        .map_err(|e| format!("Failed to build QR code: {}", e))
+}
+
+// Renders QR data as a PNG at least `size` pixels square
+pub fn qr_png(data: &str, size: u32) -> Result<Vec<u8>, String> {
+    let image = qr_code(data)?
+        .render::<Luma<u8>>()
+        .min_dimensions(size, size)
+        .quiet_zone(true)
+        .build();
+
+    let mut png = Vec::new();
+    image
+        .write_to(&mut Cursor::new(&mut png), ImageFormat::Png)
+        .map_err(|e| format!("Failed to encode QR code: {}", e))?;
+    Ok(png)
+}
+
+fn move_legacy_secrets(conn: &Connection) -> Result<(), String> {
+    for (setting, entry) in LEGACY_SECRET_SETTINGS {
+        if let Some(secret) = get_setting(conn, setting)? {
+            if !secret.is_empty() {
+                encryption::write_secret(entry, &secret)?;
+            }
+            delete_setting(conn, setting)?;
+        }
+    }
+    Ok(())
+}
+
+fn load_config(conn: &Connection) -> Result<EInvoiceConfig, String> {
+    move_legacy_secrets(conn)?;
+    let environment = match get_setting(conn, SETTING_ENVIRONMENT)?.as_deref() {
+        Some("production") => EInvoiceEnvironment::Production,
+        _ => EInvoiceEnvironment::Sandbox,
+    };
+    Ok(EInvoiceConfig {
+        environment,
+        sandbox_url: get_setting(conn, SETTING_SANDBOX_URL)?
+            .filter(|url| !url.is_empty())
+            .unwrap_or_else(|| DEFAULT_SANDBOX_URL.to_string()),
+        production_url: get_setting(conn, SETTING_PRODUCTION_URL)?.unwrap_or_default(),
+        client_id: get_setting(conn, SETTING_CLIENT_ID)?.unwrap_or_default(),
+        has_client_secret: encryption::read_secret(CLIENT_SECRET_ENTRY)?.is_some(),
+        username: get_setting(conn, SETTING_USERNAME)?.unwrap_or_default(),
+        has_password: encryption::read_secret(PASSWORD_ENTRY)?.is_some(),
+    })
+}
+
+impl EInvoiceConfig {
+    fn base_url(&self) -> &str {
+        let url = match self.environment {
+            EInvoiceEnvironment::Sandbox => &self.sandbox_url,
+            EInvoiceEnvironment::Production => &self.production_url,
+        };
+        url.trim_end_matches('/')
+    }
+
+    fn ensure_complete(&self) -> Result<(), AppError> {
+        if self.base_url().is_empty() {
+            return Err(AppError::invalid(format!(
+                "E-invoice API URL for the {} environment is not configured",
+                self.environment.as_str()
+            )));
+        }
+        if self.client_id.is_empty() || !self.has_client_secret {
+            return Err(AppError::invalid(
+                "E-invoice API client credentials are not configured",
+            ));
+        }
+        if self.username.is_empty() || !self.has_password {
+            return Err(AppError::invalid(
+                "E-invoice API user credentials are not configured",
+            ));
+        }
+        Ok(())
+    }
+}
+
+pub fn irp_date(date: &str) -> Result<String, String> {
+    NaiveDate::parse_from_str(date, INVOICE_DATE_FORMAT)
+        .map(|d| d.format(IRP_DATE_FORMAT).to_string())
+        .map_err(|_| format!("Invalid invoice date {}", date))
+}
+
+fn require<'a>(value: Option<&'a str>, message: &str) -> Result<&'a str, AppError> {
+    value
+        .map(str::trim)
+        .filter(|v| !v.is_empty())
+        .ok_or_else(|| AppError::invalid(message))
+}
+
+fn item_json(index: usize, line: &InvoiceLine) -> Value {
+    let is_service = line.hsn_code.trim().starts_with("99");
+    let gross = round2(line.quantity * line.rate);
+    let tax = line.igst_amount + line.cgst_amount + line.sgst_amount;
+    json!({
+        "SlNo": (index + 1).to_string(),
+        "PrdDesc": line.description,
+        "IsServc": if is_service { "Y" } else { "N" },
+        "HsnCd": line.hsn_code.trim(),
+        "Qty": line.quantity,
+        "Unit": line.uqc.as_deref().unwrap_or(if is_service { "OTH" } else { "NOS" }),
+        "UnitPrice": line.rate,
+        "TotAmt": gross,
+        "Discount": line.total_discount(),
+        "AssAmt": round2(line.taxable_value),
+        "GstRt": line.gst_rate,
+        "IgstAmt": round2(line.igst_amount),
+        "CgstAmt": round2(line.cgst_amount),
+        "SgstAmt": round2(line.sgst_amount),
+        "TotItemVal": round2(line.taxable_value + tax),
+    })
+}
+
+// Builds the INV-01 schema payload the IRP expects for a tax invoice
+pub fn build_payload(
+    company: &Company,
+    customer: &Customer,
+    invoice: &Invoice,
+    lines: &[InvoiceLine],
+) -> Result<Value, AppError> {
+    if invoice.status != InvoiceStatus::Issued {
+        return Err(AppError::invalid(
+            "Only issued invoices can be registered for an IRN",
+        ));
+    }
+    if lines.is_empty() {
+        return Err(AppError::invalid(
+            "Invoice needs line items before it can be registered",
+        ));
+    }
+
+    let is_export = invoice.place_of_supply.trim() == EXPORT_STATE_CODE;
+    let buyer_gstin = customer.gst_no.trim();
+    if !is_export && buyer_gstin.is_empty() {
+        return Err(AppError::invalid(
+            "E-invoices are only issued to registered buyers or for exports",
+        ));
+    }
+    let paid_igst = match &invoice.export {
+        Some(details) => details.mode == ExportMode::WithPayment,
+        None => !exports::under_lut(invoice) && invoice.igst_amount > 0.0,
+    };
+    let supply_type = match (is_export, invoice.supply_kind, paid_igst) {
+        (true, _, true) => "EXPWP",
+        (true, _, false) => "EXPWOP",
+        (false, SupplyKind::Sez, true) => "SEZWP",
+        (false, SupplyKind::Sez, false) => "SEZWOP",
+        (false, SupplyKind::DeemedExport, _) => "DEXP",
+        (false, _, _) => "B2B",
+    };
+
+    let seller_address = require(company.address.as_deref(), "Company address is required")?;
+    let seller_city = require(company.city.as_deref(), "Company city is required")?;
+    let seller_pincode = require(company.pincode.as_deref(), "Company pincode is required")?;
+    let buyer_address = require(customer.address.as_deref(), "Customer address is required")?;
+    let buyer_city = require(customer.city.as_deref(), "Customer city is required")?;
+    let buyer_pincode = if is_export {
+        EXPORT_PINCODE
+    } else {
+        require(customer.pincode.as_deref(), "Customer pincode is required")?
+    };
+
+    let item_list: Vec<Value> = lines
+        .iter()
+        .enumerate()
+        .map(|(i, line)| item_json(i, line))
+        .collect();
+
+    let mut payload = json!({
+        "Version": SCHEMA_VERSION,
+        "TranDtls": {
+            "TaxSch": "GST",
+            "SupTyp": supply_type,
+            "RegRev": if invoice.reverse_charge { "Y" } else { "N" },
+            "IgstOnIntra": "N",
+        },
+        "DocDtls": {
+            "Typ": "INV",
+            "No": invoice.invoice_number,
+            "Dt": irp_date(&invoice.invoice_date)?,
+        },
+        "SellerDtls": {
+            "Gstin": company.gst_no.trim(),
+            "LglNm": company.company_name,
+            "Addr1": seller_address,
+            "Loc": seller_city,
+            "Pin": seller_pincode
+                .parse::<u32>()
+                .map_err(|_| AppError::invalid("Company pincode is not valid"))?,
+            "Stcd": company.state_code.trim(),
+        },
+        "BuyerDtls": {
+            "Gstin": if is_export { "URP" } else { buyer_gstin },
+            "LglNm": customer.report_customer,
+            "Pos": invoice.place_of_supply.trim(),
+            "Addr1": buyer_address,
+            "Loc": buyer_city,
+            "Pin": buyer_pincode
+                .parse::<u32>()
+                .map_err(|_| AppError::invalid("Customer pincode is not valid"))?,
+            "Stcd": if is_export { EXPORT_STATE_CODE } else { customer.state_code.trim() },
+        },
+        "ItemList": item_list,
+        "ValDtls": {
+            "AssVal": round2(invoice.taxable_value),
+            "CgstVal": round2(invoice.cgst_amount),
+            "SgstVal": round2(invoice.sgst_amount),
+            "IgstVal": round2(invoice.igst_amount),
+            "OthChrg": round2(invoice.tcs_amount),
+            "RndOffAmt": round2(invoice.round_off),
+            "TotInvVal": round2(invoice.total_amount),
+        },
+    });
+    if let Some(details) = invoice.export.as_ref().filter(|_| is_export) {
+        let mut export = json!({ "ForCur": details.currency });
+        if let Some(number) = &details.shipping_bill_number {
+            export["ShipBNo"] = json!(number);
+        }
+        if let Some(date) = &details.shipping_bill_date {
+            export["ShipBDt"] = json!(irp_date(date)?);
+        }
+        if let Some(port_code) = &details.port_code {
+            export["Port"] = json!(port_code);
+        }
+        payload["ExpDtls"] = export;
+    }
+    Ok(payload)
+}
+
+fn load_payload(conn: &Connection, invoice_id: i64, company_id: i64) -> Result<Value, AppError> {
+    let invoice = invoices::get_invoice_by_id(conn, invoice_id, company_id)?
+        .ok_or_else(|| AppError::not_found("Invoice not found"))?;
+    let company = companies::get_company_by_id(conn, company_id)?
+        .ok_or_else(|| AppError::not_found("Company not found"))?;
+    let customer = customers::get_customer_by_id(conn, invoice.customer_id, company_id)?
+        .ok_or_else(|| AppError::not_found("Customer not found"))?;
+    let lines = invoices::get_invoice_lines(conn, invoice_id)?;
+    build_payload(&company, &customer, &invoice, &lines)
+}
+
+// IRP responses carry `Data` as an object, or as a JSON string from some GSPs
+fn response_data(body: &Value) -> Result<Value, String> {
+    let success = body.get("Status").and_then(|s| {
+        s.as_i64()
+            .or_else(|| s.as_str().and_then(|s| s.parse().ok()))
+    }) == Some(1);
+    if !success {
+        let details = body
+            .get("ErrorDetails")
+            .and_then(Value::as_array)
+            .map(|errors| {
+                errors
+                    .iter()
+                    .map(|e| {
+                        format!(
+                            "{} {}",
+                            e.get("ErrorCode").and_then(Value::as_str).unwrap_or(""),
+                            e.get("ErrorMessage").and_then(Value::as_str).unwrap_or("")
+                        )
+                        .trim()
+                        .to_string()
+                    })
+                    .collect::<Vec<_>>()
+                    .join("; ")
+            })
+            .filter(|d| !d.is_empty())
+            .unwrap_or_else(|| "no error details returned".to_string());
+        return Err(format!("IRP rejected the request: {}", details));
+    }
+
+    match body.get("Data") {
+        Some(Value::String(text)) => serde_json::from_str(text)
+            .map_err(|e| format!("IRP returned unreadable data: {}", e)),
+        Some(data @ Value::Object(_)) => Ok(data.clone()),
+        _ => Err("IRP response did not include data".to_string()),
+    }
+}
+
+pub fn text(data: &Value, key: &str) -> Option<String> {
+    match data.get(key)? {
+        Value::String(s) => Some(s.clone()),
+        Value::Number(n) => Some(n.to_string()),
+        _ => None,
+    }
+}
+
+fn client() -> Result<reqwest::Client, String> {
+    reqwest::Client::builder()
+        .timeout(REQUEST_TIMEOUT)
+        .build()
+        .map_err(|e| e.to_string())
+}
+
+async fn send(request: reqwest::RequestBuilder) -> Result<Value, String> {
+    let response = request
+        .send()
+        .await
+        .map_err(|e| format!("E-invoice request failed: {}", e))?;
+    let status = response.status();
+    let raw = response
+        .text()
+        .await
+        .map_err(|e| format!("Failed to read e-invoice response: {}", e))?;
+    serde_json::from_str(&raw).map_err(|_| {
+        format!(
+            "E-invoice API returned HTTP {} with a non-JSON body",
+            status
+        )
+    })
+}
+
+async fn authenticate(
+    config: &EInvoiceConfig,
+    client_secret: &str,
+    password: &str,
+    gstin: &str,
+) -> Result<(String, String), String> {
+    let request = client()?
+        .post(format!("{}{}", config.base_url(), AUTH_PATH))
+        .header("client_id", &config.client_id)
+        .header("client_secret", client_secret)
+        .header("Gstin", gstin)
+        .json(&json!({
+            "UserName": config.username,
+            "Password": password,
+            "ForceRefreshAccessToken": false,
+        }));
+    let data = response_data(&send(request).await?)?;
+    let token = text(&data, "AuthToken").ok_or("IRP did not return an auth token")?;
+    let expiry = text(&data, "TokenExpiry").unwrap_or_default();
+    Ok((token, expiry))
+}
+
+// Reuses the stored token until a few minutes before it expires
+fn cached_token(conn: &Connection) -> Result<Option<String>, String> {
+    let token = encryption::read_secret(AUTH_TOKEN_ENTRY)?;
+    let expiry = get_setting(conn, SETTING_TOKEN_EXPIRY)?
+        .and_then(|e| NaiveDateTime::parse_from_str(&e, IRP_TIMESTAMP_FORMAT).ok());
+    let now = chrono::Local::now().naive_local();
+    match (token, expiry) {
+        (Some(token), Some(expiry)) if expiry - chrono::Duration::minutes(5) > now => {
+            Ok(Some(token))
+        }
+        _ => Ok(None),
+    }
+}
+
+// An authenticated connection to the IRP, shared by the e-invoice and e-way bill APIs
+pub struct IrpSession {
+    pub config: EInvoiceConfig,
+    client_secret: String,
+    gstin: String,
+    token: String,
+}
+
+impl IrpSession {
+    pub async fn open(pool: &DbPool, gstin: &str) -> Result<IrpSession, String> {
+        // Keep database access out of the await points below
+        let (config, token) = {
+            let conn = db::get_conn(pool)?;
+            let config = load_config(&conn)?;
+            config.ensure_complete()?;
+            (config, cached_token(&conn)?)
+        };
+        let client_secret = encryption::read_secret(CLIENT_SECRET_ENTRY)?.unwrap_or_default();
+
+        let token = match token {
+            Some(token) => token,
+            None => {
+                let password = encryption::read_secret(PASSWORD_ENTRY)?.unwrap_or_default();
+                let (token, expiry) =
+                    authenticate(&config, &client_secret, &password, gstin).await?;
+                encryption::write_secret(AUTH_TOKEN_ENTRY, &token)?;
+                let conn = db::get_conn(pool)?;
+                set_setting(&conn, SETTING_TOKEN_EXPIRY, &expiry)?;
+                token
+            }
+        };
+
+        Ok(IrpSession {
+            config,
+            client_secret,
+            gstin: gstin.to_string(),
+            token,
+        })
+    }
+
+    // Returns the raw response body alongside its decoded `Data`
+    pub async fn post(&self, path: &str, payload: &Value) -> Result<(Value, Value), String> {
+        let request = client()?
+            .post(format!("{}{}", self.config.base_url(), path))
+            .header("client_id", &self.config.client_id)
+            .header("client_secret", &self.client_secret)
+            .header("Gstin", &self.gstin)
+            .header("user_name", &self.config.username)
+            .header("AuthToken", &self.token)
+            .json(payload);
+        let body = send(request).await?;
+        let data = response_data(&body)?;
+        Ok((body, data))
+    }
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn get_einvoice_config(pool: State<'_, DbPool>) -> Result<EInvoiceConfig, AppError> {
+    let conn = db::get_conn(&pool)?;
+    Ok(load_config(&conn)?)
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn save_einvoice_config(
+    pool: State<'_, DbPool>,
+    config: SaveEInvoiceConfig,
+) -> Result<EInvoiceConfig, AppError> {
+    for url in [&config.sandbox_url, &config.production_url] {
+        let url = url.trim();
+        if !url.is_empty() && !url.starts_with("https://") {
+            return Err(AppError::invalid("E-invoice API URLs must use https://"));
+        }
+    }
+
+    let conn = db::get_conn(&pool)?;
+    let previous = load_config(&conn)?;
+    set_setting(&conn, SETTING_ENVIRONMENT, config.environment.as_str())?;
+    set_setting(&conn, SETTING_SANDBOX_URL, config.sandbox_url.trim())?;
+    set_setting(&conn, SETTING_PRODUCTION_URL, config.production_url.trim())?;
+    set_setting(&conn, SETTING_CLIENT_ID, config.client_id.trim())?;
+    set_setting(&conn, SETTING_USERNAME, config.username.trim())?;
+    for (entry, secret) in [
+        (CLIENT_SECRET_ENTRY, config.client_secret.as_deref()),
+        (PASSWORD_ENTRY, config.password.as_deref()),
+    ] {
+        match secret {
+            Some("") => encryption::delete_secret(entry)?,
+            Some(secret) => encryption::write_secret(entry, secret)?,
+            None => {}
+        }
+    }
+
+    // A token is only valid for the environment and user it was issued to
+    if previous.environment != config.environment || previous.username != config.username.trim() {
+        encryption::delete_secret(AUTH_TOKEN_ENTRY)?;
+        set_setting(&conn, SETTING_TOKEN_EXPIRY, "")?;
+    }
+    Ok(load_config(&conn)?)
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn build_einvoice_payload(
+    pool: State<'_, DbPool>,
+    invoice_id: i64,
+    company_id: i64,
+) -> Result<Value, AppError> {
+    let conn = db::get_conn(&pool)?;
+    load_payload(&conn, invoice_id, company_id)
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn get_einvoice(
+    pool: State<'_, DbPool>,
+    invoice_id: i64,
+    company_id: i64,
+) -> Result<Option<EInvoice>, AppError> {
+    let conn = db::get_conn(&pool)?;
+    if invoices::get_invoice_by_id(&conn, invoice_id, company_id)?.is_none() {
+        return Ok(None);
+    }
+    get_einvoice_by_invoice_id(&conn, invoice_id)
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn get_invoice_qr(
+    pool: State<'_, DbPool>,
+    invoice_id: i64,
+    company_id: i64,
+    size: Option<u32>,
+) -> Result<EInvoiceQr, AppError> {
+    let conn = db::get_conn(&pool)?;
+    invoices::get_invoice_by_id(&conn, invoice_id, company_id)?
+        .ok_or_else(|| AppError::not_found("Invoice not found"))?;
+    let einvoice = get_einvoice_by_invoice_id(&conn, invoice_id)?
+        .ok_or_else(|| AppError::invalid("No IRN has been generated for this invoice"))?;
+    if einvoice.status == EInvoiceStatus::Cancelled {
+        return Err(AppError::invalid(
+            "The IRN for this invoice has been cancelled",
+        ));
+    }
+    let size = size.unwrap_or(DEFAULT_QR_SIZE).clamp(100, MAX_QR_SIZE);
+    let png = qr_png(&einvoice.signed_qr_code, size)?;
+
+    Ok(EInvoiceQr {
+        invoice_id,
+        irn: einvoice.irn,
+        mime_type: "image/png".to_string(),
+        png_base64: STANDARD.encode(png),
+    })
+}
+
+// Builds the payload for an invoice that has no IRN yet, with the seller GSTIN to sign in as
+fn prepare_irn(
+    conn: &Connection,
+    invoice_id: i64,
+    company_id: i64,
+) -> Result<(Value, String), AppError> {
+    if get_einvoice_by_invoice_id(conn, invoice_id)?.is_some() {
+        return Err(AppError::invalid(
+            "An IRN has already been generated for this invoice",
+        ));
+    }
+    let payload = load_payload(conn, invoice_id, company_id)?;
+    let gstin = payload["SellerDtls"]["Gstin"]
+        .as_str()
+        .unwrap_or_default()
+        .to_string();
+    Ok((payload, gstin))
+}
+
+// Sends the payload to the IRP and stores the IRN it returns
+async fn register_irn(
+    pool: &DbPool,
+    session: &IrpSession,
+    invoice_id: i64,
+    payload: &Value,
+) -> Result<EInvoice, AppError> {
+    let (body, data) = session.post(GENERATE_IRN_PATH, payload).await?;
+
+    let irn = text(&data, "Irn").ok_or("IRP response did not include an IRN")?;
+    let ack_no =
+        text(&data, "AckNo").ok_or("IRP response did not include an acknowledgement number")?;
+    let ack_date = text(&data, "AckDt").unwrap_or_default();
+    let signed_qr_code =
+        text(&data, "SignedQRCode").ok_or("IRP response did not include the signed QR code")?;
+
+    let conn = db::get_conn(pool)?;
+    conn.execute(
+        "INSERT INTO einvoices (invoice_id, environment, irn, ack_no, ack_date, signed_invoice,
+                                signed_qr_code, request_payload, response_payload)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+        params![
+            invoice_id,
+            session.config.environment.as_str(),
+            irn,
+            ack_no,
+            ack_date,
+            text(&data, "SignedInvoice"),
+            signed_qr_code,
+            payload.to_string(),
+            body.to_string()
+        ],
+    )?;
+
+    get_einvoice_by_invoice_id(&conn, invoice_id)?
+        .ok_or_else(|| AppError::internal("E-invoice not found after generation"))
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn generate_irn(
+    pool: State<'_, DbPool>,
+    invoice_id: i64,
+    company_id: i64,
+) -> Result<EInvoice, AppError> {
+    let (payload, gstin) = {
+        let conn = db::get_conn(&pool)?;
+        prepare_irn(&conn, invoice_id, company_id)?
+    };
+
+    let session = IrpSession::open(&pool, &gstin).await?;
+    register_irn(&pool, &session, invoice_id, &payload).await
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BulkIrnFailure {
+    pub invoice_id: i64,
+    pub error: String,
+}
+
+// Each IRN is stored as soon as the IRP returns it, so a cancelled run keeps the ones it got
+// and lists the invoices it never reached
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BulkIrnReport {
+    pub generated: Vec<EInvoice>,
+    pub failed: Vec<BulkIrnFailure>,
+    pub skipped: Vec<i64>,
+    pub cancelled: bool,
+}
+
+// Generates IRNs for several invoices in one IRP session. A failing invoice is reported and
+// the run moves on to the next one.
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn generate_irn_bulk(
+    pool: State<'_, DbPool>,
+    cancellations: State<'_, Cancellations>,
+    company_id: i64,
+    invoice_ids: Vec<i64>,
+    operation_id: Option<String>,
+) -> Result<BulkIrnReport, AppError> {
+    if invoice_ids.is_empty() {
+        return Err(AppError::validation("invoice_ids", "Select at least one invoice"));
+    }
+    let operation = cancellations.start(operation_id.as_deref());
+    let gstin = {
+        let conn = db::get_conn(&pool)?;
+        companies::get_company_by_id(&conn, company_id)?
+            .ok_or_else(|| AppError::not_found("Company not found"))?
+            .gst_no
+    };
+    let session = IrpSession::open(&pool, gstin.trim()).await?;
+
+    let mut report = BulkIrnReport {
+        generated: Vec::new(),
+        failed: Vec::new(),
+        skipped: Vec::new(),
+        cancelled: false,
+    };
+    for (index, &invoice_id) in invoice_ids.iter().enumerate() {
+        if operation.token.is_cancelled() {
+            report.skipped = invoice_ids[index..].to_vec();
+            report.cancelled = true;
+            break;
+        }
+        let prepared =
+            db::get_conn(&pool).and_then(|conn| prepare_irn(&conn, invoice_id, company_id));
+        let registered = match prepared {
+            Ok((payload, _)) => register_irn(&pool, &session, invoice_id, &payload).await,
+            Err(e) => Err(e),
+        };
+        match registered {
+            Ok(einvoice) => report.generated.push(einvoice),
+            Err(error) => {
+                tracing::warn!(invoice = invoice_id, "IRN generation failed: {}", error);
+                report.failed.push(BulkIrnFailure {
+                    invoice_id,
+                    error: error.to_string(),
+                });
+            }
+        }
+    }
+    Ok(report)
+}
+
+// Checks the invoice can still be cancelled before anything is sent to the IRP
+fn cancellable_irn(
+    conn: &Connection,
+    invoice_id: i64,
+    company_id: i64,
+) -> Result<EInvoice, AppError> {
+    let invoice = invoices::get_invoice_by_id(conn, invoice_id, company_id)?
+        .ok_or_else(|| AppError::not_found("Invoice not found"))?;
+    let einvoice = get_einvoice_by_invoice_id(conn, invoice_id)?
+        .ok_or_else(|| AppError::invalid("No IRN has been generated for this invoice"))?;
+    if einvoice.status == EInvoiceStatus::Cancelled {
+        return Err(AppError::invalid(
+            "The IRN for this invoice is already cancelled",
+        ));
+    }
+    let generated = NaiveDateTime::parse_from_str(&einvoice.ack_date, IRP_TIMESTAMP_FORMAT)
+        .map_err(|_| {
+            AppError::internal(format!(
+                "Unreadable acknowledgement date '{}'",
+                einvoice.ack_date
+            ))
+        })?;
+    let now = chrono::Local::now().naive_local();
+    if now - generated > chrono::Duration::hours(CANCEL_WINDOW_HOURS) {
+        return Err(AppError::invalid(
+            "IRNs can only be cancelled within 24 hours; issue a credit note instead".to_string(),
+        ));
+    }
+    let eway_bill = eway_bills::get_eway_bill_by_invoice_id(conn, invoice_id, company_id)?;
+    if eway_bill.is_some_and(|bill| bill.status == EwayBillStatus::Generated) {
+        return Err(AppError::invalid(
+            "Cancel the e-way bill before cancelling the IRN",
+        ));
+    }
+    if credit_notes::has_active_notes(conn, invoice_id)? {
+        return Err(AppError::invalid(
+            "Cancel the credit and debit notes against this invoice first",
+        ));
+    }
+    if invoice.amount_received > 0.0 {
+        return Err(AppError::invalid(
+            "Remove the receipt allocations before cancelling this invoice",
+        ));
+    }
+    financial_years::ensure_period_open(conn, company_id, &invoice.invoice_date)?;
+    Ok(einvoice)
+}
+
+// Cancels the IRN on the IRP and the invoice with it, so it drops out of GSTR-1 and is counted
+// as cancelled in the documents issued summary
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn cancel_einvoice(
+    pool: State<'_, DbPool>,
+    invoice_id: i64,
+    company_id: i64,
+    reason: CancelReason,
+    remarks: Option<String>,
+) -> Result<EInvoice, AppError> {
+    let remarks = remarks
+        .as_deref()
+        .map(str::trim)
+        .filter(|r| !r.is_empty())
+        .unwrap_or(reason.label())
+        .to_string();
+    if remarks.len() > CANCEL_REMARKS_LIMIT {
+        return Err(AppError::validation(
+            "remarks",
+            format!("Cancellation remarks must be {} characters or less", CANCEL_REMARKS_LIMIT),
+        ));
+    }
+    let (einvoice, gstin) = {
+        let conn = db::get_conn(&pool)?;
+        let einvoice = cancellable_irn(&conn, invoice_id, company_id)?;
+        let company = companies::get_company_by_id(&conn, company_id)?
+            .ok_or_else(|| AppError::not_found("Company not found"))?;
+        (einvoice, company.gst_no)
+    };
+
+    let session = IrpSession::open(&pool, &gstin).await?;
+    let payload = json!({
+        "Irn": einvoice.irn,
+        "CnlRsn": reason.code(),
+        "CnlRem": remarks,
+    });
+    let (_, data) = session.post(CANCEL_IRN_PATH, &payload).await?;
+    let cancelled_at = text(&data, "CancelDate").unwrap_or_else(|| {
+        chrono::Local::now()
+            .naive_local()
+            .format(IRP_TIMESTAMP_FORMAT)
+            .to_string()
+    });
+
+    let mut conn = db::get_conn(&pool)?;
+    let tx = conn.transaction()?;
+    let before = invoices::get_invoice_by_id(&tx, invoice_id, company_id)?
+        .ok_or_else(|| AppError::not_found("Invoice not found"))?;
+    tx.execute(
+        "UPDATE einvoices
+         SET status = ?1, cancel_reason = ?2, cancel_remarks = ?3, cancelled_at = ?4,
+             updated_at = CURRENT_TIMESTAMP
+         WHERE invoice_id = ?5",
+        params![
+            EInvoiceStatus::Cancelled.as_str(),
+            reason.as_str(),
+            remarks,
+            cancelled_at,
+            invoice_id
+        ],
+    )?;
+    tx.execute(
+        "UPDATE invoices SET status = ?1, updated_at = CURRENT_TIMESTAMP
+         WHERE id = ?2 AND company_id = ?3",
+        params![InvoiceStatus::Cancelled.as_str(), invoice_id, company_id],
+    )?;
+    let after = invoices::get_invoice_by_id(&tx, invoice_id, company_id)?
+        .ok_or_else(|| AppError::internal("Invoice not found after cancellation"))?;
+    audit::record(
+        &tx,
+        company_id,
+        "invoice",
+        invoice_id,
+        AuditAction::Update,
+        Some(&before),
+        Some(&after),
+    )?;
+    webhooks::enqueue(&tx, company_id, WebhookEvent::Cancelled, &after)?;
+    let cancelled = get_einvoice_by_invoice_id(&tx, invoice_id)?
+        .ok_or_else(|| AppError::internal("E-invoice not found after cancellation"))?;
+    tx.commit()?;
+    Ok(cancelled)
+}
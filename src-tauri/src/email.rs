@@ -0,0 +1,601 @@
+use std::time::Duration;
+
+use lettre::message::header::ContentType;
+use lettre::message::{Attachment, Mailbox, MultiPart, SinglePart};
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::{AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor};
+use rusqlite::{params, Connection, Row};
+use serde::{Deserialize, Serialize};
+use tauri::State;
+use tera::{Context, Tera};
+
+use crate::companies;
+use crate::customer_statements::{self, CustomerStatement};
+use crate::customers;
+use crate::db::{self, DbPool};
+use crate::encryption;
+use crate::error::AppError;
+use crate::invoice_pdf;
+use crate::invoice_templates::{self, describe_tera_error};
+use crate::settings;
+
+const SETTING_HOST: &str = "smtp_host";
+const SETTING_PORT: &str = "smtp_port";
+const SETTING_SECURITY: &str = "smtp_security";
+const SETTING_USERNAME: &str = "smtp_username";
+const SETTING_FROM_ADDRESS: &str = "smtp_from_address";
+const SETTING_FROM_NAME: &str = "smtp_from_name";
+// The password lives in the OS keyring next to the database key, never in app_settings
+const PASSWORD_ENTRY: &str = "smtp-password";
+
+const DEFAULT_PORT: u32 = 587;
+const PDF_CONTENT_TYPE: &str = "application/pdf";
+const SEND_TIMEOUT: Duration = Duration::from_secs(30);
+const DEFAULT_LOG_LIMIT: u32 = 100;
+const MAX_LOG_LIMIT: u32 = 1000;
+
+const INVOICE_SUBJECT: &str =
+    "Invoice {{ invoice.invoice_number }} from {{ company.company_name }}";
+const INVOICE_BODY: &str = "Dear {{ customer.report_customer }},
+
+Please find attached invoice {{ invoice.invoice_number }} dated {{ invoice.invoice_date }} \
+for Rs. {{ invoice.total_amount }}.
+
+Regards,
+{{ company.company_name }}";
+const STATEMENT_SUBJECT: &str = "Statement of account from {{ company.company_name }} \
+    ({{ statement.from }} to {{ statement.to }})";
+const STATEMENT_BODY: &str = "Dear {{ statement.customer_name }},
+
+Please find attached your statement of account for {{ statement.from }} to {{ statement.to }}. \
+The closing balance is Rs. {{ statement.closing_balance }}.
+
+Regards,
+{{ company.company_name }}";
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum SmtpSecurity {
+    #[default]
+    StartTls,
+    Tls,
+    // Plain connection, for a relay on the local network only
+    None,
+}
+
+impl SmtpSecurity {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SmtpSecurity::StartTls => "start_tls",
+            SmtpSecurity::Tls => "tls",
+            SmtpSecurity::None => "none",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "start_tls" => Some(SmtpSecurity::StartTls),
+            "tls" => Some(SmtpSecurity::Tls),
+            "none" => Some(SmtpSecurity::None),
+            _ => None,
+        }
+    }
+}
+
+impl settings::SettingValue for SmtpSecurity {
+    fn to_setting(&self) -> String {
+        self.as_str().to_string()
+    }
+
+    fn from_setting(value: &str) -> Option<Self> {
+        SmtpSecurity::parse(value)
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum EmailKind {
+    Invoice,
+    Statement,
+    Report,
+}
+
+impl EmailKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            EmailKind::Invoice => "invoice",
+            EmailKind::Statement => "statement",
+            EmailKind::Report => "report",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "invoice" => Some(EmailKind::Invoice),
+            "statement" => Some(EmailKind::Statement),
+            "report" => Some(EmailKind::Report),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum DeliveryStatus {
+    Sent,
+    Failed,
+}
+
+impl DeliveryStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DeliveryStatus::Sent => "sent",
+            DeliveryStatus::Failed => "failed",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "sent" => Some(DeliveryStatus::Sent),
+            "failed" => Some(DeliveryStatus::Failed),
+            _ => None,
+        }
+    }
+}
+
+// SMTP account used for outgoing mail; the password is only ever written, never returned
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SmtpSettings {
+    pub host: Option<String>,
+    pub port: u32,
+    pub security: SmtpSecurity,
+    pub username: Option<String>,
+    pub from_address: Option<String>,
+    pub from_name: Option<String>,
+    pub has_password: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SaveSmtpSettings {
+    pub host: String,
+    pub port: u32,
+    pub security: SmtpSecurity,
+    pub username: Option<String>,
+    // None keeps the saved password; an empty string removes it
+    pub password: Option<String>,
+    pub from_address: String,
+    pub from_name: Option<String>,
+}
+
+// Tera templates with the same variables as invoice templates; statements get `statement`
+// instead of `invoice` and `lines`. Parts left out use the built-in wording.
+#[derive(Debug, Serialize, Deserialize, Default)]
+pub struct EmailTemplate {
+    #[serde(default)]
+    pub subject: Option<String>,
+    #[serde(default)]
+    pub body: Option<String>,
+}
+
+// Sent mail log data model
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct EmailLogEntry {
+    pub id: i64,
+    pub company_id: i64,
+    pub kind: EmailKind,
+    pub invoice_id: Option<i64>,
+    pub customer_id: Option<i64>,
+    pub recipients: Vec<String>,
+    pub subject: String,
+    pub attachment_name: Option<String>,
+    pub status: DeliveryStatus,
+    // Reply of the SMTP server that accepted the message
+    pub server_response: Option<String>,
+    pub error: Option<String>,
+    pub sent_at: Option<String>,
+}
+
+struct OutgoingEmail {
+    company_id: i64,
+    kind: EmailKind,
+    invoice_id: Option<i64>,
+    customer_id: Option<i64>,
+    recipients: Vec<Mailbox>,
+    subject: String,
+    body: String,
+    attachment_name: String,
+    attachment_type: &'static str,
+    attachment: Vec<u8>,
+}
+
+const SELECT_EMAIL_LOG: &str = "
+    SELECT id, company_id, kind, invoice_id, customer_id, recipients, subject, attachment_name,
+           status, server_response, error, sent_at
+    FROM email_log";
+
+fn email_log_from_row(row: &Row) -> rusqlite::Result<EmailLogEntry> {
+    let kind: String = row.get("kind")?;
+    let status: String = row.get("status")?;
+    let recipients: String = row.get("recipients")?;
+    Ok(EmailLogEntry {
+        id: row.get("id")?,
+        company_id: row.get("company_id")?,
+        kind: EmailKind::parse(&kind).unwrap_or(EmailKind::Invoice),
+        invoice_id: row.get("invoice_id")?,
+        customer_id: row.get("customer_id")?,
+        recipients: recipients.split(", ").map(str::to_string).collect(),
+        subject: row.get("subject")?,
+        attachment_name: row.get("attachment_name")?,
+        status: DeliveryStatus::parse(&status).unwrap_or(DeliveryStatus::Failed),
+        server_response: row.get("server_response")?,
+        error: row.get("error")?,
+        sent_at: row.get("sent_at")?,
+    })
+}
+
+pub fn load_smtp_settings(conn: &Connection) -> Result<SmtpSettings, String> {
+    Ok(SmtpSettings {
+        host: settings::get(conn, SETTING_HOST)?,
+        port: settings::get(conn, SETTING_PORT)?.unwrap_or(DEFAULT_PORT),
+        security: settings::get(conn, SETTING_SECURITY)?.unwrap_or_default(),
+        username: settings::get(conn, SETTING_USERNAME)?,
+        from_address: settings::get(conn, SETTING_FROM_ADDRESS)?,
+        from_name: settings::get(conn, SETTING_FROM_NAME)?,
+        has_password: encryption::read_secret(PASSWORD_ENTRY)?.is_some(),
+    })
+}
+
+fn trimmed(value: Option<&str>) -> String {
+    value.map(str::trim).unwrap_or("").to_string()
+}
+
+fn parse_mailbox(field: &str, address: &str) -> Result<Mailbox, AppError> {
+    address.trim().parse().map_err(|_| {
+        AppError::validation(field, format!("{} is not a valid email address", address))
+    })
+}
+
+// Accepts addresses separated by commas or semicolons
+pub(crate) fn parse_recipients(to: &str) -> Result<Vec<Mailbox>, AppError> {
+    let recipients = to
+        .split([',', ';'])
+        .map(str::trim)
+        .filter(|address| !address.is_empty())
+        .map(|address| parse_mailbox("to", address))
+        .collect::<Result<Vec<_>, _>>()?;
+    if recipients.is_empty() {
+        return Err(AppError::validation("to", "Enter at least one email address"));
+    }
+    Ok(recipients)
+}
+
+fn render(
+    label: &str,
+    template: Option<&str>,
+    default: &str,
+    context: &Context,
+) -> Result<String, String> {
+    let template = template.filter(|template| !template.trim().is_empty()).unwrap_or(default);
+    Tera::one_off(template, context, false)
+        .map_err(|e| format!("Email {} template error: {}", label, describe_tera_error(e)))
+}
+
+fn sender(settings: &SmtpSettings) -> Result<Mailbox, AppError> {
+    let address = settings
+        .from_address
+        .as_deref()
+        .ok_or_else(|| AppError::invalid("Set up the SMTP account before sending email"))?;
+    let mut mailbox: Mailbox = address
+        .parse()
+        .map_err(|_| AppError::invalid(format!("Sender address {} is not valid", address)))?;
+    mailbox.name = settings.from_name.clone();
+    Ok(mailbox)
+}
+
+fn build_transport(
+    settings: &SmtpSettings,
+) -> Result<AsyncSmtpTransport<Tokio1Executor>, AppError> {
+    let host = settings
+        .host
+        .as_deref()
+        .ok_or_else(|| AppError::invalid("Set up the SMTP account before sending email"))?;
+    let builder = match settings.security {
+        SmtpSecurity::StartTls => AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(host),
+        SmtpSecurity::Tls => AsyncSmtpTransport::<Tokio1Executor>::relay(host),
+        SmtpSecurity::None => Ok(AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(host)),
+    }
+    .map_err(|e| format!("Failed to set up the SMTP connection: {}", e))?;
+    let port =
+        u16::try_from(settings.port).map_err(|_| AppError::invalid("SMTP port is not valid"))?;
+    let mut builder = builder.port(port).timeout(Some(SEND_TIMEOUT));
+    if let Some(username) = &settings.username {
+        let password = encryption::read_secret(PASSWORD_ENTRY)?.unwrap_or_default();
+        builder = builder.credentials(Credentials::new(username.clone(), password));
+    }
+    Ok(builder.build())
+}
+
+fn build_message(from: Mailbox, email: &OutgoingEmail) -> Result<Message, String> {
+    let content_type = ContentType::parse(email.attachment_type).map_err(|e| e.to_string())?;
+    let mut builder = Message::builder().from(from).subject(email.subject.clone());
+    for recipient in &email.recipients {
+        builder = builder.to(recipient.clone());
+    }
+    let attachment = Attachment::new(email.attachment_name.clone())
+        .body(email.attachment.clone(), content_type);
+    builder
+        .multipart(
+            MultiPart::mixed()
+                .singlepart(SinglePart::plain(email.body.clone()))
+                .singlepart(attachment),
+        )
+        .map_err(|e| format!("Failed to build the email: {}", e))
+}
+
+// Returns the server's reply on success
+async fn deliver(settings: &SmtpSettings, email: &OutgoingEmail) -> Result<String, String> {
+    let message = build_message(sender(settings)?, email)?;
+    let transport = build_transport(settings)?;
+    let response = transport
+        .send(message)
+        .await
+        .map_err(|e| format!("Failed to send the email: {}", e))?;
+    let reply = response.message().collect::<Vec<_>>().join(" ");
+    Ok(format!("{} {}", response.code(), reply).trim().to_string())
+}
+
+fn record(
+    conn: &Connection,
+    email: &OutgoingEmail,
+    outcome: &Result<String, String>,
+) -> Result<EmailLogEntry, AppError> {
+    let recipients = email
+        .recipients
+        .iter()
+        .map(|recipient| recipient.email.to_string())
+        .collect::<Vec<_>>()
+        .join(", ");
+    let (status, server_response, error) = match outcome {
+        Ok(response) => (DeliveryStatus::Sent, Some(response.as_str()), None),
+        Err(e) => (DeliveryStatus::Failed, None, Some(e.as_str())),
+    };
+    conn.execute(
+        "INSERT INTO email_log (company_id, kind, invoice_id, customer_id, recipients, subject,
+                                attachment_name, status, server_response, error)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+        params![
+            email.company_id,
+            email.kind.as_str(),
+            email.invoice_id,
+            email.customer_id,
+            recipients,
+            email.subject,
+            email.attachment_name,
+            status.as_str(),
+            server_response,
+            error,
+        ],
+    )?;
+    conn.query_row(
+        &format!("{} WHERE id = ?1", SELECT_EMAIL_LOG),
+        params![conn.last_insert_rowid()],
+        email_log_from_row,
+    )
+    .map_err(AppError::from)
+}
+
+// Failed attempts are logged too, then reported to the caller
+async fn send_and_record(
+    pool: &DbPool,
+    settings: &SmtpSettings,
+    email: OutgoingEmail,
+) -> Result<EmailLogEntry, AppError> {
+    let outcome = deliver(settings, &email).await;
+    let conn = db::get_conn(pool)?;
+    let entry = record(&conn, &email, &outcome)?;
+    match outcome {
+        Ok(_) => {
+            tracing::info!(kind = email.kind.as_str(), log_id = entry.id, "email sent");
+            Ok(entry)
+        }
+        Err(e) => Err(e.into()),
+    }
+}
+
+// A generated report file to mail
+pub(crate) struct ReportAttachment {
+    pub name: String,
+    pub content_type: &'static str,
+    pub bytes: Vec<u8>,
+}
+
+// Mails a generated report file; the scheduler uses this for scheduled reports
+pub(crate) async fn send_report_email(
+    pool: &DbPool,
+    company_id: i64,
+    to: &str,
+    subject: String,
+    body: String,
+    attachment: ReportAttachment,
+) -> Result<EmailLogEntry, AppError> {
+    let recipients = parse_recipients(to)?;
+    let smtp = {
+        let conn = db::get_conn(pool)?;
+        load_smtp_settings(&conn)?
+    };
+    let email = OutgoingEmail {
+        company_id,
+        kind: EmailKind::Report,
+        invoice_id: None,
+        customer_id: None,
+        recipients,
+        subject,
+        body,
+        attachment_name: attachment.name,
+        attachment_type: attachment.content_type,
+        attachment: attachment.bytes,
+    };
+    send_and_record(pool, &smtp, email).await
+}
+
+fn statement_context(
+    conn: &Connection,
+    statement: &CustomerStatement,
+) -> Result<Context, AppError> {
+    let company = companies::get_company_by_id(conn, statement.company_id)?
+        .ok_or_else(|| AppError::not_found("Company not found"))?;
+    let customer =
+        customers::get_customer_by_id(conn, statement.customer_id, statement.company_id)?
+            .ok_or_else(|| AppError::not_found("Customer does not exist for this company"))?;
+    let mut context = Context::new();
+    context.insert("company", &company);
+    context.insert("customer", &customer);
+    context.insert("statement", statement);
+    Ok(context)
+}
+
+fn file_safe(value: &str) -> String {
+    value
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '-' { c } else { '_' })
+        .collect()
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn get_smtp_settings(pool: State<'_, DbPool>) -> Result<SmtpSettings, AppError> {
+    let conn = db::get_conn(&pool)?;
+    Ok(load_smtp_settings(&conn)?)
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn set_smtp_settings(
+    pool: State<'_, DbPool>,
+    smtp: SaveSmtpSettings,
+) -> Result<SmtpSettings, AppError> {
+    let host = smtp.host.trim();
+    if host.is_empty() {
+        return Err(AppError::validation("host", "SMTP server is required"));
+    }
+    if smtp.port == 0 || smtp.port > u32::from(u16::MAX) {
+        return Err(AppError::validation("port", "SMTP port must be between 1 and 65535"));
+    }
+    parse_mailbox("from_address", &smtp.from_address)?;
+
+    let conn = db::get_conn(&pool)?;
+    settings::put(&conn, SETTING_HOST, &host.to_string())?;
+    settings::put(&conn, SETTING_PORT, &smtp.port)?;
+    settings::put(&conn, SETTING_SECURITY, &smtp.security)?;
+    settings::put(&conn, SETTING_USERNAME, &trimmed(smtp.username.as_deref()))?;
+    settings::put(&conn, SETTING_FROM_ADDRESS, &smtp.from_address.trim().to_string())?;
+    settings::put(&conn, SETTING_FROM_NAME, &trimmed(smtp.from_name.as_deref()))?;
+    match smtp.password.as_deref() {
+        Some("") => encryption::delete_secret(PASSWORD_ENTRY)?,
+        Some(password) => encryption::write_secret(PASSWORD_ENTRY, password)?,
+        None => {}
+    }
+    Ok(load_smtp_settings(&conn)?)
+}
+
+// Emails the issued invoice as a PDF rendered with its default invoice template. `to` may hold
+// several addresses separated by commas.
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn send_invoice_email(
+    pool: State<'_, DbPool>,
+    invoice_id: i64,
+    company_id: i64,
+    to: String,
+    template: Option<EmailTemplate>,
+) -> Result<EmailLogEntry, AppError> {
+    let recipients = parse_recipients(&to)?;
+    let template = template.unwrap_or_default();
+    let (smtp, email) = {
+        let conn = db::get_conn(&pool)?;
+        let smtp = load_smtp_settings(&conn)?;
+        let document = invoice_pdf::load_document(&conn, invoice_id, company_id)?;
+        let pdf_template = invoice_templates::resolve_template(&conn, None)?;
+        let (attachment, _) = invoice_pdf::render_bytes(&document, &pdf_template, None)?;
+        let context = invoice_pdf::template_context(&document)?;
+        let invoice = document.invoice();
+        let email = OutgoingEmail {
+            company_id,
+            kind: EmailKind::Invoice,
+            invoice_id: Some(invoice_id),
+            customer_id: Some(invoice.customer_id),
+            recipients,
+            subject: render("subject", template.subject.as_deref(), INVOICE_SUBJECT, &context)?,
+            body: render("body", template.body.as_deref(), INVOICE_BODY, &context)?,
+            attachment_name: format!("Invoice-{}.pdf", file_safe(&invoice.invoice_number)),
+            attachment_type: PDF_CONTENT_TYPE,
+            attachment,
+        };
+        (smtp, email)
+    };
+    send_and_record(&pool, &smtp, email).await
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn send_statement_email(
+    pool: State<'_, DbPool>,
+    company_id: i64,
+    customer_id: i64,
+    from: String,
+    to: String,
+    recipient: String,
+    template: Option<EmailTemplate>,
+) -> Result<EmailLogEntry, AppError> {
+    let recipients = parse_recipients(&recipient)?;
+    let template = template.unwrap_or_default();
+    let (smtp, email) = {
+        let conn = db::get_conn(&pool)?;
+        let smtp = load_smtp_settings(&conn)?;
+        let statement =
+            customer_statements::build_statement(&conn, company_id, customer_id, &from, &to)?;
+        let (attachment, _) = customer_statements::render_statement_pdf(&conn, &statement)?;
+        let context = statement_context(&conn, &statement)?;
+        let email = OutgoingEmail {
+            company_id,
+            kind: EmailKind::Statement,
+            invoice_id: None,
+            customer_id: Some(customer_id),
+            recipients,
+            subject: render("subject", template.subject.as_deref(), STATEMENT_SUBJECT, &context)?,
+            body: render("body", template.body.as_deref(), STATEMENT_BODY, &context)?,
+            attachment_name: format!(
+                "Statement-{}-{}-{}.pdf",
+                file_safe(&statement.customer_name),
+                file_safe(&from),
+                file_safe(&to)
+            ),
+            attachment_type: PDF_CONTENT_TYPE,
+            attachment,
+        };
+        (smtp, email)
+    };
+    send_and_record(&pool, &smtp, email).await
+}
+
+// Newest first; limited to one invoice when `invoice_id` is given
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn list_email_log(
+    pool: State<'_, DbPool>,
+    company_id: i64,
+    invoice_id: Option<i64>,
+    limit: Option<u32>,
+) -> Result<Vec<EmailLogEntry>, AppError> {
+    let limit = limit.unwrap_or(DEFAULT_LOG_LIMIT).clamp(1, MAX_LOG_LIMIT);
+    let conn = db::get_conn(&pool)?;
+    let mut stmt = conn.prepare(&format!(
+        "{} WHERE company_id = ?1 AND (?2 IS NULL OR invoice_id = ?2)
+             ORDER BY sent_at DESC, id DESC LIMIT ?3",
+        SELECT_EMAIL_LOG
+    ))?;
+    let entries = stmt
+        .query_map(params![company_id, invoice_id, limit], email_log_from_row)?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(entries)
+}
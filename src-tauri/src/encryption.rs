@@ -0,0 +1,250 @@
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::sync::RwLock;
+
+use argon2::password_hash::rand_core::{OsRng, RngCore};
+use rusqlite::{params, Connection, OpenFlags};
+use serde::{Deserialize, Serialize};
+
+use crate::error::AppError;
+
+// Database keys live in the OS keyring, never in the database or app settings
+const KEYRING_SERVICE: &str = "sales-report";
+const KEY_ENTRY: &str = "database-key";
+// Key waiting to be applied on the next start, when no connections are open
+const PENDING_KEY_ENTRY: &str = "database-key-pending";
+
+const PLAIN_HEADER: &[u8; 16] = b"SQLite format 3\0";
+const KEY_BYTES: usize = 32;
+
+// Key of the open database, applied to every connection; None while it is unencrypted
+static DATABASE_KEY: RwLock<Option<String>> = RwLock::new(None);
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct EncryptionStatus {
+    pub encrypted: bool,
+    // A new key has been set up and is applied when the app next starts
+    pub pending_restart: bool,
+}
+
+// Returned once when a key is created; the user keeps it in case the keyring entry is lost
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RecoveryKey {
+    pub recovery_key: String,
+    pub pending_restart: bool,
+}
+
+pub fn current_key() -> Option<String> {
+    DATABASE_KEY.read().ok().and_then(|key| key.clone())
+}
+
+fn set_current_key(key: Option<String>) {
+    if let Ok(mut current) = DATABASE_KEY.write() {
+        *current = key;
+    }
+}
+
+fn keyring_entry(name: &str) -> Result<keyring::Entry, String> {
+    keyring::Entry::new(KEYRING_SERVICE, name)
+        .map_err(|e| format!("Failed to open the OS keyring: {}", e))
+}
+
+pub(crate) fn read_secret(name: &str) -> Result<Option<String>, String> {
+    match keyring_entry(name)?.get_password() {
+        Ok(secret) => Ok(Some(secret)),
+        Err(keyring::Error::NoEntry) => Ok(None),
+        Err(e) => Err(format!("Failed to read the OS keyring: {}", e)),
+    }
+}
+
+pub(crate) fn write_secret(name: &str, secret: &str) -> Result<(), String> {
+    keyring_entry(name)?
+        .set_password(secret)
+        .map_err(|e| format!("Failed to write to the OS keyring: {}", e))
+}
+
+pub(crate) fn delete_secret(name: &str) -> Result<(), String> {
+    match keyring_entry(name)?.delete_credential() {
+        Ok(()) | Err(keyring::Error::NoEntry) => Ok(()),
+        Err(e) => Err(format!("Failed to update the OS keyring: {}", e)),
+    }
+}
+
+fn generate_key() -> String {
+    let mut bytes = [0u8; KEY_BYTES];
+    OsRng.fill_bytes(&mut bytes);
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+// Unencrypted SQLite files start with a fixed header; missing and empty files count as plain
+pub fn is_plain_database(path: &Path) -> Result<bool, String> {
+    let mut file = match std::fs::File::open(path) {
+        Ok(file) => file,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(true),
+        Err(e) => return Err(format!("Failed to read {}: {}", path.display(), e)),
+    };
+    let mut header = Vec::with_capacity(PLAIN_HEADER.len());
+    file.by_ref()
+        .take(PLAIN_HEADER.len() as u64)
+        .read_to_end(&mut header)
+        .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    Ok(header.is_empty() || header == PLAIN_HEADER)
+}
+
+pub fn apply_key(conn: &Connection, key: &str) -> rusqlite::Result<()> {
+    conn.pragma_update(None, "key", key)
+}
+
+// Opens a database file, unlocking it with the app key when it is encrypted
+pub fn open(path: &Path, flags: OpenFlags) -> Result<Connection, String> {
+    let conn = Connection::open_with_flags(path, flags)
+        .map_err(|e| format!("Failed to open {}: {}", path.display(), e))?;
+    if !is_plain_database(path)? {
+        let key = current_key().ok_or_else(|| {
+            format!("{} is encrypted and database encryption is not enabled", path.display())
+        })?;
+        apply_key(&conn, &key).map_err(|e| e.to_string())?;
+    }
+    Ok(conn)
+}
+
+fn unlocks(path: &Path, key: &str) -> bool {
+    Connection::open_with_flags(path, OpenFlags::SQLITE_OPEN_READ_ONLY)
+        .and_then(|conn| {
+            apply_key(&conn, key)?;
+            conn.query_row("SELECT COUNT(*) FROM sqlite_master", [], |row| row.get::<_, i64>(0))
+        })
+        .is_ok()
+}
+
+// Copies a plain database into a new file encrypted with `key`
+pub fn export_encrypted(source: &Path, target: &Path, key: &str) -> Result<(), String> {
+    let conn = Connection::open_with_flags(source, OpenFlags::SQLITE_OPEN_READ_ONLY)
+        .map_err(|e| format!("Failed to open {}: {}", source.display(), e))?;
+    conn.execute(
+        "ATTACH DATABASE ?1 AS encrypted KEY ?2",
+        params![target.display().to_string(), key],
+    )
+    .map_err(|e| e.to_string())?;
+    conn.query_row("SELECT sqlcipher_export('encrypted')", [], |_| Ok(()))
+        .map_err(|e| format!("Failed to encrypt database: {}", e))?;
+    conn.execute("DETACH DATABASE encrypted", [])
+        .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+// `-wal` or `-shm` file kept next to a database in WAL mode
+fn sidecar(db_path: &Path, suffix: &str) -> PathBuf {
+    let mut name = db_path.as_os_str().to_owned();
+    name.push(suffix);
+    PathBuf::from(name)
+}
+
+// Moves every page in the write-ahead log into the database file and empties the log, so the
+// file alone holds all the data
+fn checkpoint(db_path: &Path) -> Result<(), String> {
+    let conn = Connection::open(db_path)
+        .map_err(|e| format!("Failed to open {}: {}", db_path.display(), e))?;
+    let busy: i64 = conn
+        .query_row("PRAGMA wal_checkpoint(TRUNCATE)", [], |row| row.get(0))
+        .map_err(|e| format!("Failed to checkpoint the database: {}", e))?;
+    if busy != 0 {
+        return Err("The database is in use and could not be checkpointed".to_string());
+    }
+    Ok(())
+}
+
+// Applies a pending key to the database file. Each step can be repeated, so an interrupted
+// run finishes on the next start. The key entry changes only once the file is on the new key;
+// until then the old key, or none, still opens it.
+fn apply_pending(db_path: &Path, key: Option<&str>, pending: &str) -> Result<(), String> {
+    if is_plain_database(db_path)? {
+        if db_path.exists() {
+            let encrypting = db_path.with_extension("db.encrypting");
+            let _ = std::fs::remove_file(&encrypting);
+            // The log is folded into the file first so it can be deleted: a log left next to
+            // the encrypted file would be replayed onto it
+            checkpoint(db_path)?;
+            export_encrypted(db_path, &encrypting, pending)?;
+            for suffix in ["-wal", "-shm"] {
+                let path = sidecar(db_path, suffix);
+                if let Err(e) = std::fs::remove_file(&path) {
+                    if e.kind() != std::io::ErrorKind::NotFound {
+                        return Err(format!("Failed to remove {}: {}", path.display(), e));
+                    }
+                }
+            }
+            std::fs::rename(&encrypting, db_path)
+                .map_err(|e| format!("Failed to replace database: {}", e))?;
+        }
+    } else if !unlocks(db_path, pending) {
+        let key = key.ok_or_else(|| "The current database key was not found".to_string())?;
+        let conn = Connection::open(db_path).map_err(|e| e.to_string())?;
+        apply_key(&conn, key).map_err(|e| e.to_string())?;
+        conn.pragma_update(None, "rekey", pending)
+            .map_err(|e| format!("Failed to change database key: {}", e))?;
+    }
+    write_secret(KEY_ENTRY, pending)?;
+    delete_secret(PENDING_KEY_ENTRY)
+}
+
+// Runs at start-up before the pool opens: finishes any pending key setup and loads the key
+// the connections will use. A new database is created encrypted when a key is already set.
+pub fn prepare(db_path: &Path) -> Result<(), String> {
+    let mut key = read_secret(KEY_ENTRY)?;
+    if let Some(pending) = read_secret(PENDING_KEY_ENTRY)? {
+        apply_pending(db_path, key.as_deref(), &pending)?;
+        key = Some(pending);
+    }
+
+    let plain = is_plain_database(db_path)?;
+    let has_data = std::fs::metadata(db_path).map(|meta| meta.len() > 0).unwrap_or(false);
+    if !plain && key.is_none() {
+        return Err(format!(
+            "The database is encrypted but its key is missing from the OS keyring. Add the \
+             recovery key under service \"{}\", account \"{}\" and restart.",
+            KEYRING_SERVICE, KEY_ENTRY
+        ));
+    }
+    set_current_key(if plain && has_data { None } else { key });
+    Ok(())
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn get_encryption_status() -> Result<EncryptionStatus, AppError> {
+    Ok(EncryptionStatus {
+        encrypted: current_key().is_some(),
+        pending_restart: read_secret(PENDING_KEY_ENTRY)?.is_some(),
+    })
+}
+
+// Sets up a key for an unencrypted database; it is encrypted when the app next starts
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn enable_database_encryption() -> Result<RecoveryKey, AppError> {
+    if current_key().is_some() {
+        return Err(AppError::invalid("The database is already encrypted"));
+    }
+    let key = generate_key();
+    write_secret(PENDING_KEY_ENTRY, &key)?;
+    Ok(RecoveryKey {
+        recovery_key: key,
+        pending_restart: true,
+    })
+}
+
+// Re-encrypts the database with a new key when the app next starts
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn change_database_key() -> Result<RecoveryKey, AppError> {
+    if current_key().is_none() {
+        return Err(AppError::invalid("The database is not encrypted"));
+    }
+    let key = generate_key();
+    write_secret(PENDING_KEY_ENTRY, &key)?;
+    Ok(RecoveryKey {
+        recovery_key: key,
+        pending_restart: true,
+    })
+}
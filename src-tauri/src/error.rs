@@ -0,0 +1,150 @@
+use std::fmt;
+
+use serde::Serialize;
+
+// Error returned by commands. Serialized as `{ "code": "validation", "field": "gst_no",
+// "message": "..." }`: the code and field let the frontend pick a translation and highlight the
+// form field, the message is the English text to fall back on. `field` uses the request's
+// snake_case field name and is only present where one field is at fault.
+#[derive(Debug, Serialize, Clone, PartialEq, Eq)]
+#[serde(tag = "code", rename_all = "snake_case")]
+pub enum AppError {
+    Validation {
+        #[serde(skip_serializing_if = "Option::is_none")]
+        field: Option<String>,
+        message: String,
+    },
+    NotFound {
+        message: String,
+    },
+    // Clashes with an existing record, e.g. a duplicate name or number
+    Conflict {
+        #[serde(skip_serializing_if = "Option::is_none")]
+        field: Option<String>,
+        message: String,
+    },
+    Unauthenticated {
+        message: String,
+    },
+    Forbidden {
+        message: String,
+    },
+    PeriodLocked {
+        message: String,
+    },
+    Database {
+        message: String,
+    },
+    // The user stopped the operation before it finished
+    Cancelled {
+        message: String,
+    },
+    // Any other failure, including the IRP, GST portal and file system
+    Internal {
+        message: String,
+    },
+}
+
+impl AppError {
+    pub fn validation(field: &str, message: impl Into<String>) -> Self {
+        AppError::Validation {
+            field: Some(field.to_string()),
+            message: message.into(),
+        }
+    }
+
+    // A request that breaks a rule not tied to one field, e.g. one across several fields
+    pub fn invalid(message: impl Into<String>) -> Self {
+        AppError::Validation {
+            field: None,
+            message: message.into(),
+        }
+    }
+
+    pub fn conflict(field: &str, message: impl Into<String>) -> Self {
+        AppError::Conflict {
+            field: Some(field.to_string()),
+            message: message.into(),
+        }
+    }
+
+    pub fn not_found(message: impl Into<String>) -> Self {
+        AppError::NotFound {
+            message: message.into(),
+        }
+    }
+
+    pub fn internal(message: impl Into<String>) -> Self {
+        AppError::Internal {
+            message: message.into(),
+        }
+    }
+
+    pub fn message(&self) -> &str {
+        match self {
+            AppError::Validation { message, .. }
+            | AppError::NotFound { message }
+            | AppError::Conflict { message, .. }
+            | AppError::Unauthenticated { message }
+            | AppError::Forbidden { message }
+            | AppError::PeriodLocked { message }
+            | AppError::Database { message }
+            | AppError::Cancelled { message }
+            | AppError::Internal { message } => message,
+        }
+    }
+
+    // Puts `prefix` in front of the message, e.g. to say which row or step failed, keeping the
+    // code and field
+    pub fn prefixed(mut self, prefix: &str) -> Self {
+        match &mut self {
+            AppError::Validation { message, .. }
+            | AppError::NotFound { message }
+            | AppError::Conflict { message, .. }
+            | AppError::Unauthenticated { message }
+            | AppError::Forbidden { message }
+            | AppError::PeriodLocked { message }
+            | AppError::Database { message }
+            | AppError::Cancelled { message }
+            | AppError::Internal { message } => message.insert_str(0, prefix),
+        }
+        self
+    }
+}
+
+impl fmt::Display for AppError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.message())
+    }
+}
+
+impl std::error::Error for AppError {}
+
+// Helpers that report plain messages give no kind to go by, so these surface as internal
+// failures; anything the user can act on is returned as a typed error where it is detected
+impl From<String> for AppError {
+    fn from(message: String) -> Self {
+        AppError::Internal { message }
+    }
+}
+
+impl From<&str> for AppError {
+    fn from(message: &str) -> Self {
+        AppError::from(message.to_string())
+    }
+}
+
+impl From<rusqlite::Error> for AppError {
+    fn from(error: rusqlite::Error) -> Self {
+        AppError::Database {
+            message: error.to_string(),
+        }
+    }
+}
+
+// Lets String-returning helpers call code that already reports an AppError
+impl From<AppError> for String {
+    fn from(error: AppError) -> Self {
+        error.to_string()
+    }
+}
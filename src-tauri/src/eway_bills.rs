@@ -0,0 +1,704 @@
+use chrono::{Duration, NaiveDate, NaiveDateTime};
+use rusqlite::{params, Connection, OptionalExtension, Row};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use tauri::State;
+
+use crate::companies;
+use crate::customers;
+use crate::db::{self, DbPool};
+use crate::einvoice::{self, irp_date, text, IrpSession, IRP_TIMESTAMP_FORMAT};
+use crate::error::AppError;
+use crate::gstin;
+use crate::invoices::{self, round2, InvoiceStatus};
+
+const GENERATE_EWB_PATH: &str = "/eiewb/v1.03/ewaybill";
+// Schema version accepted by the EWB offline bulk generation tool
+const OFFLINE_TOOL_VERSION: &str = "1.0.0621";
+// The portal rejects distances above this for a single movement
+const MAX_DISTANCE_KM: i64 = 4000;
+const DEFAULT_WARNING_HOURS: i64 = 24;
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum TransportMode {
+    Road,
+    Rail,
+    Air,
+    Ship,
+}
+
+impl TransportMode {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TransportMode::Road => "road",
+            TransportMode::Rail => "rail",
+            TransportMode::Air => "air",
+            TransportMode::Ship => "ship",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "road" => Some(TransportMode::Road),
+            "rail" => Some(TransportMode::Rail),
+            "air" => Some(TransportMode::Air),
+            "ship" => Some(TransportMode::Ship),
+            _ => None,
+        }
+    }
+
+    // Numeric code used by both the API and the offline tool
+    fn code(&self) -> &'static str {
+        match self {
+            TransportMode::Road => "1",
+            TransportMode::Rail => "2",
+            TransportMode::Air => "3",
+            TransportMode::Ship => "4",
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum VehicleType {
+    Regular,
+    OverDimensional,
+}
+
+impl VehicleType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            VehicleType::Regular => "regular",
+            VehicleType::OverDimensional => "over_dimensional",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "regular" => Some(VehicleType::Regular),
+            "over_dimensional" => Some(VehicleType::OverDimensional),
+            _ => None,
+        }
+    }
+
+    fn code(&self) -> &'static str {
+        match self {
+            VehicleType::Regular => "R",
+            VehicleType::OverDimensional => "O",
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum EwayBillStatus {
+    Pending,
+    Generated,
+}
+
+impl EwayBillStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            EwayBillStatus::Pending => "pending",
+            EwayBillStatus::Generated => "generated",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "pending" => Some(EwayBillStatus::Pending),
+            "generated" => Some(EwayBillStatus::Generated),
+            _ => None,
+        }
+    }
+}
+
+// E-way bill data model
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct EwayBill {
+    pub invoice_id: i64,
+    pub transport_mode: TransportMode,
+    pub transporter_id: Option<String>,
+    pub transporter_name: Option<String>,
+    pub vehicle_number: Option<String>,
+    pub vehicle_type: VehicleType,
+    pub transport_doc_no: Option<String>,
+    pub transport_doc_date: Option<String>,
+    pub distance_km: i64,
+    pub ewb_number: Option<String>,
+    pub ewb_date: Option<String>,
+    pub valid_until: Option<String>,
+    pub status: EwayBillStatus,
+    // Filled in on read when the bill has expired or is about to
+    pub expiry_warning: Option<String>,
+    pub created_at: Option<String>,
+    pub updated_at: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SaveEwayBill {
+    pub transport_mode: TransportMode,
+    pub transporter_id: Option<String>,
+    pub transporter_name: Option<String>,
+    pub vehicle_number: Option<String>,
+    pub vehicle_type: Option<VehicleType>,
+    pub transport_doc_no: Option<String>,
+    pub transport_doc_date: Option<String>,
+    pub distance_km: i64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RecordEwayBill {
+    pub ewb_number: String,
+    pub ewb_date: String,
+    pub valid_until: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct EwayBillExport {
+    pub path: String,
+    pub bill_count: usize,
+}
+
+const SELECT_EWAY_BILL: &str = "
+    SELECT e.invoice_id, e.transport_mode, e.transporter_id, e.transporter_name, e.vehicle_number,
+           e.vehicle_type, e.transport_doc_no, e.transport_doc_date, e.distance_km, e.ewb_number,
+           e.ewb_date, e.valid_until, e.status, e.created_at, e.updated_at
+    FROM eway_bills e
+    JOIN invoices i ON i.id = e.invoice_id";
+
+fn eway_bill_from_row(row: &Row) -> rusqlite::Result<EwayBill> {
+    let transport_mode: String = row.get("transport_mode")?;
+    let vehicle_type: String = row.get("vehicle_type")?;
+    let status: String = row.get("status")?;
+    let valid_until: Option<String> = row.get("valid_until")?;
+    Ok(EwayBill {
+        invoice_id: row.get("invoice_id")?,
+        transport_mode: TransportMode::parse(&transport_mode).unwrap_or(TransportMode::Road),
+        transporter_id: row.get("transporter_id")?,
+        transporter_name: row.get("transporter_name")?,
+        vehicle_number: row.get("vehicle_number")?,
+        vehicle_type: VehicleType::parse(&vehicle_type).unwrap_or(VehicleType::Regular),
+        transport_doc_no: row.get("transport_doc_no")?,
+        transport_doc_date: row.get("transport_doc_date")?,
+        distance_km: row.get("distance_km")?,
+        ewb_number: row.get("ewb_number")?,
+        ewb_date: row.get("ewb_date")?,
+        expiry_warning: valid_until
+            .as_deref()
+            .and_then(|v| expiry_warning(v, DEFAULT_WARNING_HOURS)),
+        valid_until,
+        status: EwayBillStatus::parse(&status).unwrap_or(EwayBillStatus::Pending),
+        created_at: row.get("created_at")?,
+        updated_at: row.get("updated_at")?,
+    })
+}
+
+pub fn get_eway_bill_by_invoice_id(
+    conn: &Connection,
+    invoice_id: i64,
+    company_id: i64,
+) -> Result<Option<EwayBill>, AppError> {
+    conn.query_row(
+        &format!(
+            "{} WHERE e.invoice_id = ?1 AND i.company_id = ?2",
+            SELECT_EWAY_BILL
+        ),
+        params![invoice_id, company_id],
+        eway_bill_from_row,
+    )
+    .optional()
+    .map_err(AppError::from)
+}
+
+fn parse_validity(value: &str) -> Option<NaiveDateTime> {
+    NaiveDateTime::parse_from_str(value, IRP_TIMESTAMP_FORMAT)
+        .ok()
+        // A bare date is valid until the end of that day
+        .or_else(|| {
+            NaiveDate::parse_from_str(value, invoices::INVOICE_DATE_FORMAT)
+                .ok()
+                .and_then(|d| d.and_hms_opt(23, 59, 59))
+        })
+}
+
+fn expiry_warning(valid_until: &str, within_hours: i64) -> Option<String> {
+    let valid_until = parse_validity(valid_until)?;
+    let now = chrono::Local::now().naive_local();
+    if valid_until < now {
+        Some(format!(
+            "E-way bill expired on {}",
+            valid_until.format(IRP_TIMESTAMP_FORMAT)
+        ))
+    } else if valid_until - Duration::hours(within_hours) < now {
+        Some(format!(
+            "E-way bill expires on {}",
+            valid_until.format(IRP_TIMESTAMP_FORMAT)
+        ))
+    } else {
+        None
+    }
+}
+
+fn clean(value: Option<&str>) -> Option<String> {
+    value
+        .map(|v| v.trim().to_string())
+        .filter(|v| !v.is_empty())
+}
+
+fn normalize_vehicle_number(value: &str) -> String {
+    value
+        .chars()
+        .filter(|c| !c.is_whitespace() && *c != '-')
+        .collect::<String>()
+        .to_uppercase()
+}
+
+fn validate_details(details: &SaveEwayBill) -> Result<(), AppError> {
+    if details.distance_km < 0 || details.distance_km > MAX_DISTANCE_KM {
+        return Err(AppError::validation(
+            "distance_km",
+            format!("Distance must be between 0 and {} km", MAX_DISTANCE_KM),
+        ));
+    }
+
+    if let Some(transporter_id) = clean(details.transporter_id.as_deref()) {
+        gstin::check_gstin(&transporter_id).map_err(|e| {
+            AppError::validation(
+                "transporter_id",
+                format!("Transporter ID is not valid: {}", e),
+            )
+        })?;
+    }
+    if let Some(name) = &details.transporter_name {
+        if name.len() > 100 {
+            return Err(AppError::validation(
+                "transporter_name",
+                "Transporter name must be 100 characters or less",
+            ));
+        }
+    }
+
+    if let Some(vehicle) = clean(details.vehicle_number.as_deref()) {
+        let vehicle = normalize_vehicle_number(&vehicle);
+        if vehicle.len() < 4
+            || vehicle.len() > 15
+            || !vehicle.chars().all(|c| c.is_ascii_alphanumeric())
+        {
+            return Err(AppError::validation(
+                "vehicle_number",
+                "Vehicle number must be 4 to 15 letters or digits",
+            ));
+        }
+    }
+
+    if let Some(date) = clean(details.transport_doc_date.as_deref()) {
+        if NaiveDate::parse_from_str(&date, invoices::INVOICE_DATE_FORMAT).is_err() {
+            return Err(AppError::validation(
+                "transport_doc_date",
+                "Transport document date must be in YYYY-MM-DD format",
+            ));
+        }
+    }
+
+    match details.transport_mode {
+        TransportMode::Road => {
+            if clean(details.vehicle_number.as_deref()).is_none()
+                && clean(details.transporter_id.as_deref()).is_none()
+            {
+                return Err(AppError::validation(
+                    "vehicle_number",
+                    "Road transport needs a vehicle number or transporter ID",
+                ));
+            }
+        }
+        TransportMode::Rail | TransportMode::Air | TransportMode::Ship => {
+            if clean(details.transport_doc_no.as_deref()).is_none()
+                || clean(details.transport_doc_date.as_deref()).is_none()
+            {
+                return Err(AppError::validation(
+                    "transport_doc_no",
+                    format!(
+                        "{} transport needs a transport document number and date",
+                        details.transport_mode.as_str()
+                    ),
+                ));
+            }
+        }
+    }
+    Ok(())
+}
+
+fn ensure_not_generated(bill: &EwayBill) -> Result<(), AppError> {
+    if bill.status == EwayBillStatus::Generated {
+        return Err(AppError::invalid(
+            "An e-way bill has already been generated for this invoice",
+        ));
+    }
+    Ok(())
+}
+
+fn optional_date(value: Option<&str>) -> Result<Value, String> {
+    match value {
+        Some(date) => Ok(Value::String(irp_date(date)?)),
+        None => Ok(Value::Null),
+    }
+}
+
+// Builds one bill in the offline tool's bulk upload format
+fn offline_bill(conn: &Connection, bill: &EwayBill, company_id: i64) -> Result<Value, AppError> {
+    let invoice = invoices::get_invoice_by_id(conn, bill.invoice_id, company_id)?
+        .ok_or_else(|| AppError::not_found("Invoice not found"))?;
+    if invoice.status != InvoiceStatus::Issued {
+        return Err(AppError::invalid(format!(
+            "Invoice {} must be issued before an e-way bill is exported",
+            invoice.invoice_number
+        )));
+    }
+    let company = companies::get_company_by_id(conn, company_id)?
+        .ok_or_else(|| AppError::not_found("Company not found"))?;
+    let customer = customers::get_customer_by_id(conn, invoice.customer_id, company_id)?
+        .ok_or_else(|| AppError::not_found("Customer not found"))?;
+    let lines = invoices::get_invoice_lines(conn, bill.invoice_id)?;
+    if lines.is_empty() {
+        return Err(AppError::invalid(format!(
+            "Invoice {} has no line items",
+            invoice.invoice_number
+        )));
+    }
+
+    let pincode = |value: Option<&str>| value.and_then(|p| p.trim().parse::<u32>().ok());
+    let state_code = |value: &str| value.trim().parse::<u32>().ok();
+    let is_interstate = invoice.igst_amount > 0.0;
+
+    let item_list: Vec<Value> = lines
+        .iter()
+        .enumerate()
+        .map(|(i, line)| {
+            let (igst_rate, half_rate) = if is_interstate {
+                (line.gst_rate, 0.0)
+            } else {
+                (0.0, line.gst_rate / 2.0)
+            };
+            json!({
+                "itemNo": i + 1,
+                "productName": line.description,
+                "productDesc": line.description,
+                "hsnCode": line.hsn_code.trim().parse::<u64>().ok(),
+                "quantity": line.quantity,
+                "qtyUnit": line.uqc.as_deref().unwrap_or("NOS"),
+                "taxableAmount": round2(line.taxable_value),
+                "igstRate": igst_rate,
+                "cgstRate": half_rate,
+                "sgstRate": half_rate,
+                "cessRate": 0,
+            })
+        })
+        .collect();
+
+    Ok(json!({
+        "userGstin": company.gst_no.trim(),
+        "supplyType": "O",
+        "subSupplyType": 1,
+        "docType": "INV",
+        "docNo": invoice.invoice_number,
+        "docDate": irp_date(&invoice.invoice_date)?,
+        "transType": 1,
+        "fromGstin": company.gst_no.trim(),
+        "fromTrdName": company.company_name,
+        "fromAddr1": company.address,
+        "fromPlace": company.city,
+        "fromPincode": pincode(company.pincode.as_deref()),
+        "fromStateCode": state_code(&company.state_code),
+        "actualFromStateCode": state_code(&company.state_code),
+        "toGstin": if customer.gst_no.trim().is_empty() { "URP" } else { customer.gst_no.trim() },
+        "toTrdName": customer.report_customer,
+        "toAddr1": customer.address,
+        "toPlace": customer.city,
+        "toPincode": pincode(customer.pincode.as_deref()),
+        "toStateCode": state_code(&customer.state_code),
+        "actualToStateCode": state_code(&invoice.place_of_supply),
+        "totalValue": round2(invoice.taxable_value),
+        "cgstValue": round2(invoice.cgst_amount),
+        "sgstValue": round2(invoice.sgst_amount),
+        "igstValue": round2(invoice.igst_amount),
+        "cessValue": 0,
+        "totInvValue": round2(invoice.total_amount),
+        "transMode": bill.transport_mode.code(),
+        "transDistance": bill.distance_km.to_string(),
+        "transporterId": bill.transporter_id,
+        "transporterName": bill.transporter_name,
+        "transDocNo": bill.transport_doc_no,
+        "transDocDate": optional_date(bill.transport_doc_date.as_deref())?,
+        "vehicleNo": bill.vehicle_number,
+        "vehicleType": bill.vehicle_type.code(),
+        "itemList": item_list,
+    }))
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn save_eway_bill_details(
+    pool: State<'_, DbPool>,
+    invoice_id: i64,
+    company_id: i64,
+    details: SaveEwayBill,
+) -> Result<EwayBill, AppError> {
+    validate_details(&details)?;
+
+    let conn = db::get_conn(&pool)?;
+    let invoice = invoices::get_invoice_by_id(&conn, invoice_id, company_id)?
+        .ok_or_else(|| AppError::not_found("Invoice not found"))?;
+    if invoice.status == InvoiceStatus::Cancelled {
+        return Err(AppError::invalid(
+            "Cancelled invoices cannot carry an e-way bill",
+        ));
+    }
+    if let Some(existing) = get_eway_bill_by_invoice_id(&conn, invoice_id, company_id)? {
+        ensure_not_generated(&existing)?;
+    }
+
+    conn.execute(
+        "INSERT INTO eway_bills (invoice_id, transport_mode, transporter_id, transporter_name,
+                                 vehicle_number, vehicle_type, transport_doc_no,
+                                 transport_doc_date, distance_km)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)
+         ON CONFLICT(invoice_id) DO UPDATE SET
+             transport_mode = excluded.transport_mode,
+             transporter_id = excluded.transporter_id,
+             transporter_name = excluded.transporter_name,
+             vehicle_number = excluded.vehicle_number,
+             vehicle_type = excluded.vehicle_type,
+             transport_doc_no = excluded.transport_doc_no,
+             transport_doc_date = excluded.transport_doc_date,
+             distance_km = excluded.distance_km,
+             updated_at = CURRENT_TIMESTAMP",
+        params![
+            invoice_id,
+            details.transport_mode.as_str(),
+            clean(details.transporter_id.as_deref()).map(|t| t.to_uppercase()),
+            clean(details.transporter_name.as_deref()),
+            clean(details.vehicle_number.as_deref()).map(|v| normalize_vehicle_number(&v)),
+            details.vehicle_type.unwrap_or(VehicleType::Regular).as_str(),
+            clean(details.transport_doc_no.as_deref()),
+            clean(details.transport_doc_date.as_deref()),
+            details.distance_km
+        ],
+    )?;
+
+    get_eway_bill_by_invoice_id(&conn, invoice_id, company_id)?
+        .ok_or_else(|| AppError::not_found("E-way bill not found after save"))
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn get_eway_bill(
+    pool: State<'_, DbPool>,
+    invoice_id: i64,
+    company_id: i64,
+) -> Result<Option<EwayBill>, AppError> {
+    let conn = db::get_conn(&pool)?;
+    get_eway_bill_by_invoice_id(&conn, invoice_id, company_id)
+}
+
+// Generates the bill on the IRP against the invoice's IRN
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn generate_eway_bill(
+    pool: State<'_, DbPool>,
+    invoice_id: i64,
+    company_id: i64,
+) -> Result<EwayBill, AppError> {
+    let (bill, irn, gstin) = {
+        let conn = db::get_conn(&pool)?;
+        let bill = get_eway_bill_by_invoice_id(&conn, invoice_id, company_id)?
+            .ok_or_else(|| AppError::invalid("Save the transport details before generating"))?;
+        ensure_not_generated(&bill)?;
+        let einvoice =
+            einvoice::get_einvoice_by_invoice_id(&conn, invoice_id)?.ok_or_else(|| {
+                AppError::invalid(
+                    "Generate the IRN first, or export the e-way bill for the offline tool",
+                )
+            })?;
+        let company = companies::get_company_by_id(&conn, company_id)?
+            .ok_or_else(|| AppError::not_found("Company not found"))?;
+        (bill, einvoice.irn, company.gst_no.trim().to_string())
+    };
+
+    let payload = json!({
+        "Irn": irn,
+        "Distance": bill.distance_km,
+        "TransMode": bill.transport_mode.code(),
+        "TransId": bill.transporter_id,
+        "TransName": bill.transporter_name,
+        "TransDocNo": bill.transport_doc_no,
+        "TransDocDt": optional_date(bill.transport_doc_date.as_deref())?,
+        "VehNo": bill.vehicle_number,
+        "VehType": bill.vehicle_type.code(),
+    });
+    let session = IrpSession::open(&pool, &gstin).await?;
+    let (_, data) = session.post(GENERATE_EWB_PATH, &payload).await?;
+
+    let ewb_number = text(&data, "EwbNo")
+        .ok_or_else(|| AppError::internal("IRP response did not include an EWB number"))?;
+    let ewb_date = text(&data, "EwbDt");
+    let valid_until = text(&data, "EwbValidTill");
+
+    let conn = db::get_conn(&pool)?;
+    conn.execute(
+        "UPDATE eway_bills
+         SET ewb_number = ?1, ewb_date = ?2, valid_until = ?3, status = ?4,
+             updated_at = CURRENT_TIMESTAMP
+         WHERE invoice_id = ?5",
+        params![
+            ewb_number,
+            ewb_date,
+            valid_until,
+            EwayBillStatus::Generated.as_str(),
+            invoice_id
+        ],
+    )?;
+
+    get_eway_bill_by_invoice_id(&conn, invoice_id, company_id)?
+        .ok_or_else(|| AppError::not_found("E-way bill not found after generation"))
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn export_eway_bill_json(
+    pool: State<'_, DbPool>,
+    company_id: i64,
+    invoice_ids: Vec<i64>,
+    path: String,
+) -> Result<EwayBillExport, AppError> {
+    if invoice_ids.is_empty() {
+        return Err(AppError::validation(
+            "invoice_ids",
+            "Select at least one invoice to export",
+        ));
+    }
+
+    let conn = db::get_conn(&pool)?;
+    let mut bills = Vec::with_capacity(invoice_ids.len());
+    for invoice_id in &invoice_ids {
+        let bill =
+            get_eway_bill_by_invoice_id(&conn, *invoice_id, company_id)?.ok_or_else(|| {
+                AppError::invalid(format!("Invoice {} has no transport details", invoice_id))
+            })?;
+        ensure_not_generated(&bill)?;
+        bills.push(offline_bill(&conn, &bill, company_id)?);
+    }
+
+    let export = json!({
+        "version": OFFLINE_TOOL_VERSION,
+        "billLists": bills,
+    });
+    let json = serde_json::to_string_pretty(&export).map_err(|e| e.to_string())?;
+    std::fs::write(&path, json).map_err(|e| format!("Failed to write e-way bill file: {}", e))?;
+
+    Ok(EwayBillExport {
+        path,
+        bill_count: bills.len(),
+    })
+}
+
+// Stores a bill generated outside the app, e.g. from the offline tool upload
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn record_eway_bill(
+    pool: State<'_, DbPool>,
+    invoice_id: i64,
+    company_id: i64,
+    record: RecordEwayBill,
+) -> Result<EwayBill, AppError> {
+    let ewb_number = record.ewb_number.trim();
+    if ewb_number.len() != 12 || !ewb_number.chars().all(|c| c.is_ascii_digit()) {
+        return Err(AppError::validation(
+            "ewb_number",
+            "E-way bill number must be 12 digits",
+        ));
+    }
+    let ewb_date = parse_validity(record.ewb_date.trim()).ok_or_else(|| {
+        AppError::validation(
+            "ewb_date",
+            "E-way bill date must be YYYY-MM-DD or YYYY-MM-DD HH:MM:SS",
+        )
+    })?;
+    let valid_until = parse_validity(record.valid_until.trim()).ok_or_else(|| {
+        AppError::validation(
+            "valid_until",
+            "Valid until must be YYYY-MM-DD or YYYY-MM-DD HH:MM:SS",
+        )
+    })?;
+    if valid_until < ewb_date {
+        return Err(AppError::validation(
+            "valid_until",
+            "Valid until must be after the e-way bill date",
+        ));
+    }
+
+    let conn = db::get_conn(&pool)?;
+    let bill = get_eway_bill_by_invoice_id(&conn, invoice_id, company_id)?
+        .ok_or_else(|| AppError::invalid("Save the transport details before recording"))?;
+    ensure_not_generated(&bill)?;
+
+    conn.execute(
+        "UPDATE eway_bills
+         SET ewb_number = ?1, ewb_date = ?2, valid_until = ?3, status = ?4,
+             updated_at = CURRENT_TIMESTAMP
+         WHERE invoice_id = ?5",
+        params![
+            ewb_number,
+            ewb_date.format(IRP_TIMESTAMP_FORMAT).to_string(),
+            valid_until.format(IRP_TIMESTAMP_FORMAT).to_string(),
+            EwayBillStatus::Generated.as_str(),
+            invoice_id
+        ],
+    )
+    .map_err(|e| {
+        if e.to_string().contains("UNIQUE constraint failed") {
+            AppError::conflict(
+                "ewb_number",
+                "This e-way bill number is already recorded against another invoice",
+            )
+        } else {
+            AppError::from(e)
+        }
+    })?;
+
+    get_eway_bill_by_invoice_id(&conn, invoice_id, company_id)?
+        .ok_or_else(|| AppError::not_found("E-way bill not found after update"))
+}
+
+// Generated bills that have expired or expire within the window
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn list_expiring_eway_bills(
+    pool: State<'_, DbPool>,
+    company_id: i64,
+    within_hours: Option<i64>,
+) -> Result<Vec<EwayBill>, AppError> {
+    let within_hours = within_hours.unwrap_or(DEFAULT_WARNING_HOURS).max(0);
+    let conn = db::get_conn(&pool)?;
+    let mut stmt = conn.prepare(&format!(
+        "{} WHERE i.company_id = ?1 AND e.status = ?2 AND e.valid_until IS NOT NULL
+             ORDER BY e.valid_until",
+        SELECT_EWAY_BILL
+    ))?;
+    let bills = stmt
+        .query_map(
+            params![company_id, EwayBillStatus::Generated.as_str()],
+            eway_bill_from_row,
+        )?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    Ok(bills
+        .into_iter()
+        .filter_map(|mut bill| {
+            let warning = expiry_warning(bill.valid_until.as_deref()?, within_hours)?;
+            bill.expiry_warning = Some(warning);
+            Some(bill)
+        })
+        .collect())
+}
@@ -0,0 +1,306 @@
+use chrono::NaiveDate;
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+use tauri::State;
+
+use crate::db::{self, DbPool};
+use crate::error::AppError;
+use crate::invoices::{round2, Invoice, INVOICE_DATE_FORMAT};
+use crate::place_of_supply::SupplyKind;
+
+// Exports and SEZ supplies are zero-rated: either under a letter of undertaking without paying
+// IGST, or with IGST paid and claimed back as a refund
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum ExportMode {
+    #[default]
+    Lut,
+    WithPayment,
+}
+
+impl ExportMode {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ExportMode::Lut => "lut",
+            ExportMode::WithPayment => "with_payment",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "lut" => Some(ExportMode::Lut),
+            "with_payment" => Some(ExportMode::WithPayment),
+            _ => None,
+        }
+    }
+
+    // Export type used in GSTR-1 table 6A
+    pub fn portal_code(&self) -> &'static str {
+        match self {
+            ExportMode::Lut => "WOPAY",
+            ExportMode::WithPayment => "WPAY",
+        }
+    }
+}
+
+// Amounts on the invoice stay in rupees; the currency and rate record what the buyer is billed
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct ExportDetails {
+    #[serde(default)]
+    pub mode: ExportMode,
+    pub currency: String,
+    // Rupees per unit of the foreign currency
+    pub exchange_rate: f64,
+    // The shipping bill is often filed after the invoice, so these may be added later
+    pub shipping_bill_number: Option<String>,
+    pub shipping_bill_date: Option<String>,
+    pub port_code: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ExportRegisterRow {
+    pub invoice_id: i64,
+    pub invoice_number: String,
+    pub invoice_date: String,
+    pub customer_name: String,
+    pub mode: ExportMode,
+    pub currency: String,
+    pub exchange_rate: f64,
+    pub foreign_value: f64,
+    pub shipping_bill_number: Option<String>,
+    pub shipping_bill_date: Option<String>,
+    pub port_code: Option<String>,
+    pub taxable_value: f64,
+    pub igst_amount: f64,
+    pub total_amount: f64,
+}
+
+#[derive(Debug, Serialize, Deserialize, Default)]
+pub struct ExportRegisterTotals {
+    pub invoice_count: i64,
+    pub taxable_value: f64,
+    pub igst_amount: f64,
+    pub total_amount: f64,
+    // Issued invoices still waiting for a shipping bill
+    pub pending_shipping_bills: i64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ExportRegister {
+    pub rows: Vec<ExportRegisterRow>,
+    pub lut: ExportRegisterTotals,
+    pub with_payment: ExportRegisterTotals,
+}
+
+fn optional_text(value: &Option<String>) -> Option<String> {
+    value
+        .as_deref()
+        .map(str::trim)
+        .filter(|v| !v.is_empty())
+        .map(str::to_string)
+}
+
+// Trims the details and upper-cases the codes before they are validated and saved
+pub fn normalize(details: &mut ExportDetails) {
+    details.currency = details.currency.trim().to_ascii_uppercase();
+    details.shipping_bill_number = optional_text(&details.shipping_bill_number);
+    details.shipping_bill_date = optional_text(&details.shipping_bill_date);
+    details.port_code = optional_text(&details.port_code).map(|code| code.to_ascii_uppercase());
+}
+
+// Export invoices need their export details and nothing else may carry them
+pub fn validate_export(invoice: &Invoice) -> Result<(), AppError> {
+    if invoice.sez_mode.is_some() && invoice.supply_kind != SupplyKind::Sez {
+        return Err(AppError::validation(
+            "sez_mode",
+            "The SEZ mode can only be given for SEZ supplies",
+        ));
+    }
+    if invoice.sez_mode == Some(ExportMode::Lut) && invoice.igst_amount > 0.0 {
+        return Err(AppError::validation(
+            "igst_amount",
+            "SEZ supplies under LUT cannot charge IGST",
+        ));
+    }
+    let details = match (&invoice.export, invoice.supply_kind) {
+        (None, SupplyKind::Export) => {
+            return Err(AppError::validation(
+                "export",
+                "Export invoices need the export mode and currency",
+            ))
+        }
+        (None, _) => return Ok(()),
+        (Some(_), kind) if kind != SupplyKind::Export => {
+            return Err(AppError::validation(
+                "export",
+                "Export details can only be given for export invoices",
+            ))
+        }
+        (Some(details), _) => details,
+    };
+
+    // Problems with the details are reported against the whole export block, as the request
+    // nests them there
+    if details.currency.len() != 3 || !details.currency.chars().all(|c| c.is_ascii_uppercase()) {
+        return Err(AppError::validation(
+            "export",
+            "Currency must be a 3-letter ISO code, e.g. USD",
+        ));
+    }
+    if !details.exchange_rate.is_finite() || details.exchange_rate <= 0.0 {
+        return Err(AppError::validation(
+            "export",
+            "Exchange rate must be greater than zero",
+        ));
+    }
+    if let Some(number) = &details.shipping_bill_number {
+        if number.len() > 20 || !number.chars().all(|c| c.is_ascii_alphanumeric()) {
+            return Err(AppError::validation(
+                "export",
+                "Shipping bill number must be up to 20 letters or digits",
+            ));
+        }
+    }
+    if let Some(date) = &details.shipping_bill_date {
+        let date = NaiveDate::parse_from_str(date, INVOICE_DATE_FORMAT).map_err(|_| {
+            AppError::validation("export", "Shipping bill date must be in YYYY-MM-DD format")
+        })?;
+        let invoice_date = NaiveDate::parse_from_str(&invoice.invoice_date, INVOICE_DATE_FORMAT)
+            .map_err(|_| {
+                AppError::validation("invoice_date", "Invoice date must be in YYYY-MM-DD format")
+            })?;
+        if date < invoice_date {
+            return Err(AppError::validation(
+                "export",
+                "Shipping bill date cannot be before the invoice date",
+            ));
+        }
+    }
+    if details.shipping_bill_number.is_some() != details.shipping_bill_date.is_some() {
+        return Err(AppError::validation(
+            "export",
+            "Enter both the shipping bill number and its date",
+        ));
+    }
+    if let Some(port_code) = &details.port_code {
+        if port_code.len() != 6 || !port_code.chars().all(|c| c.is_ascii_alphanumeric()) {
+            return Err(AppError::validation(
+                "export",
+                "Port code must be 6 letters or digits",
+            ));
+        }
+    }
+    if details.mode == ExportMode::Lut && invoice.igst_amount > 0.0 {
+        return Err(AppError::validation(
+            "igst_amount",
+            "Exports under LUT cannot charge IGST",
+        ));
+    }
+    Ok(())
+}
+
+// Zero-rated without payment of IGST: exports or SEZ supplies under LUT
+pub fn under_lut(invoice: &Invoice) -> bool {
+    let mode = match invoice.supply_kind {
+        SupplyKind::Export => invoice.export.as_ref().map(|details| details.mode),
+        SupplyKind::Sez => invoice.sez_mode,
+        _ => None,
+    };
+    mode == Some(ExportMode::Lut)
+}
+
+fn load_register_rows(
+    conn: &Connection,
+    company_id: i64,
+    from: &str,
+    to: &str,
+) -> Result<Vec<ExportRegisterRow>, AppError> {
+    let mut stmt = conn.prepare(
+        "SELECT i.id, i.invoice_number, i.invoice_date, c.report_customer, i.export_mode,
+                    i.export_currency, i.export_exchange_rate, i.shipping_bill_number,
+                    i.shipping_bill_date, i.port_code, i.taxable_value, i.igst_amount,
+                    i.total_amount
+             FROM invoices i
+             JOIN customers c ON c.id = i.customer_id
+             WHERE i.company_id = ?1 AND i.deleted_at IS NULL AND i.status = 'issued'
+               AND i.supply_kind = 'export' AND i.invoice_date BETWEEN ?2 AND ?3
+             ORDER BY i.invoice_date, i.invoice_number",
+    )?;
+    let rows = stmt
+        .query_map(params![company_id, from, to], |row| {
+            let mode: Option<String> = row.get(4)?;
+            let exchange_rate: Option<f64> = row.get(6)?;
+            let total_amount: f64 = row.get(12)?;
+            let exchange_rate = exchange_rate.filter(|rate| *rate > 0.0).unwrap_or(1.0);
+            Ok(ExportRegisterRow {
+                invoice_id: row.get(0)?,
+                invoice_number: row.get(1)?,
+                invoice_date: row.get(2)?,
+                customer_name: row.get(3)?,
+                mode: mode.as_deref().and_then(ExportMode::parse).unwrap_or_default(),
+                currency: row.get::<_, Option<String>>(5)?.unwrap_or_default(),
+                exchange_rate,
+                foreign_value: round2(total_amount / exchange_rate),
+                shipping_bill_number: row.get(7)?,
+                shipping_bill_date: row.get(8)?,
+                port_code: row.get(9)?,
+                taxable_value: row.get(10)?,
+                igst_amount: row.get(11)?,
+                total_amount,
+            })
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(rows)
+}
+
+fn totals_for(rows: &[ExportRegisterRow], mode: ExportMode) -> ExportRegisterTotals {
+    let mut totals = ExportRegisterTotals::default();
+    for row in rows.iter().filter(|row| row.mode == mode) {
+        totals.invoice_count += 1;
+        totals.taxable_value += row.taxable_value;
+        totals.igst_amount += row.igst_amount;
+        totals.total_amount += row.total_amount;
+        if row.shipping_bill_number.is_none() {
+            totals.pending_shipping_bills += 1;
+        }
+    }
+    totals.taxable_value = round2(totals.taxable_value);
+    totals.igst_amount = round2(totals.igst_amount);
+    totals.total_amount = round2(totals.total_amount);
+    totals
+}
+
+// Issued export invoices in the period with their shipping bill details, totalled by mode
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn get_export_register(
+    pool: State<'_, DbPool>,
+    company_id: i64,
+    from: String,
+    to: String,
+) -> Result<ExportRegister, AppError> {
+    let parse = |date: &str, field: &str, label: &str| {
+        NaiveDate::parse_from_str(date.trim(), INVOICE_DATE_FORMAT)
+            .map(|d| d.format(INVOICE_DATE_FORMAT).to_string())
+            .map_err(|_| {
+                AppError::validation(
+                    field,
+                    format!("{} date must be in YYYY-MM-DD format", label),
+                )
+            })
+    };
+    let (from, to) = (parse(&from, "from", "From")?, parse(&to, "to", "To")?);
+    if from > to {
+        return Err(AppError::validation(
+            "to",
+            "From date must be on or before the to date",
+        ));
+    }
+    let conn = db::get_conn(&pool)?;
+    let rows = load_register_rows(&conn, company_id, &from, &to)?;
+    Ok(ExportRegister {
+        lut: totals_for(&rows, ExportMode::Lut),
+        with_payment: totals_for(&rows, ExportMode::WithPayment),
+        rows,
+    })
+}
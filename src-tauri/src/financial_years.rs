@@ -0,0 +1,395 @@
+use chrono::{Datelike, Local, NaiveDate};
+use rusqlite::{params, Connection, OptionalExtension, Row};
+use serde::{Deserialize, Serialize};
+use tauri::State;
+
+use crate::companies;
+use crate::db::{self, DbPool};
+use crate::error::AppError;
+use crate::invoices::INVOICE_DATE_FORMAT;
+
+// Indian financial years run from 1 April to 31 March
+const FY_START_MONTH: u32 = 4;
+
+// Financial year data model
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct FinancialYear {
+    pub id: Option<i64>,
+    pub company_id: i64,
+    pub label: String,
+    pub start_date: String,
+    pub end_date: String,
+    pub is_closed: bool,
+    pub closed_at: Option<String>,
+    pub created_at: Option<String>,
+    pub updated_at: Option<String>,
+}
+
+// Period lock data model; a period is a calendar month written as YYYY-MM
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PeriodLock {
+    pub company_id: i64,
+    pub period: String,
+    pub reason: Option<String>,
+    pub locked_at: Option<String>,
+}
+
+const SELECT_FINANCIAL_YEAR: &str = "
+    SELECT id, company_id, label, start_date, end_date, is_closed, closed_at, created_at,
+           updated_at
+    FROM financial_years";
+
+fn financial_year_from_row(row: &Row) -> rusqlite::Result<FinancialYear> {
+    Ok(FinancialYear {
+        id: row.get("id")?,
+        company_id: row.get("company_id")?,
+        label: row.get("label")?,
+        start_date: row.get("start_date")?,
+        end_date: row.get("end_date")?,
+        is_closed: row.get("is_closed")?,
+        closed_at: row.get("closed_at")?,
+        created_at: row.get("created_at")?,
+        updated_at: row.get("updated_at")?,
+    })
+}
+
+fn period_lock_from_row(row: &Row) -> rusqlite::Result<PeriodLock> {
+    Ok(PeriodLock {
+        company_id: row.get("company_id")?,
+        period: row.get("period")?,
+        reason: row.get("reason")?,
+        locked_at: row.get("locked_at")?,
+    })
+}
+
+// The calendar year in which the financial year containing `date` starts
+pub fn fy_start_year(date: NaiveDate) -> i32 {
+    if date.month() >= FY_START_MONTH {
+        date.year()
+    } else {
+        date.year() - 1
+    }
+}
+
+pub fn fy_bounds(start_year: i32) -> Result<(NaiveDate, NaiveDate), AppError> {
+    let start = NaiveDate::from_ymd_opt(start_year, FY_START_MONTH, 1);
+    let end = NaiveDate::from_ymd_opt(start_year + 1, FY_START_MONTH - 1, 31);
+    start.zip(end).ok_or_else(|| {
+        AppError::validation(
+            "start_year",
+            format!("{} is not a valid financial year", start_year),
+        )
+    })
+}
+
+// e.g. "2024-25"
+pub fn fy_label(start_year: i32) -> String {
+    format!("{}-{:02}", start_year, (start_year + 1) % 100)
+}
+
+fn parse_period(period: &str) -> Result<NaiveDate, AppError> {
+    NaiveDate::parse_from_str(&format!("{}-01", period.trim()), INVOICE_DATE_FORMAT)
+        .map_err(|_| AppError::validation("period", "Period must be a month in YYYY-MM format"))
+}
+
+fn period_label(month: NaiveDate) -> String {
+    month.format("%B %Y").to_string()
+}
+
+fn months_of_year(start_year: i32) -> Result<Vec<String>, String> {
+    let (start, _) = fy_bounds(start_year)?;
+    Ok((0..12)
+        .map(|offset| {
+            let month0 = start.month0() + offset;
+            format!("{}-{:02}", start.year() + (month0 / 12) as i32, month0 % 12 + 1)
+        })
+        .collect())
+}
+
+pub fn get_financial_year_by_id(
+    conn: &Connection,
+    id: i64,
+    company_id: i64,
+) -> Result<Option<FinancialYear>, AppError> {
+    conn.query_row(
+        &format!("{} WHERE id = ?1 AND company_id = ?2", SELECT_FINANCIAL_YEAR),
+        params![id, company_id],
+        financial_year_from_row,
+    )
+    .optional()
+    .map_err(AppError::from)
+}
+
+// Returns the financial year containing `date`, creating it on first use
+pub fn ensure_financial_year(
+    conn: &Connection,
+    company_id: i64,
+    date: NaiveDate,
+) -> Result<FinancialYear, AppError> {
+    let start_year = fy_start_year(date);
+    let (start, end) = fy_bounds(start_year)?;
+    conn.execute(
+        "INSERT OR IGNORE INTO financial_years (company_id, label, start_date, end_date)
+         VALUES (?1, ?2, ?3, ?4)",
+        params![
+            company_id,
+            fy_label(start_year),
+            start.format(INVOICE_DATE_FORMAT).to_string(),
+            end.format(INVOICE_DATE_FORMAT).to_string()
+        ],
+    )?;
+
+    conn.query_row(
+        &format!(
+            "{} WHERE company_id = ?1 AND start_date = ?2",
+            SELECT_FINANCIAL_YEAR
+        ),
+        params![company_id, start.format(INVOICE_DATE_FORMAT).to_string()],
+        financial_year_from_row,
+    )
+    .map_err(AppError::from)
+}
+
+// Rejects changes to documents dated in a locked month
+pub fn ensure_period_open(conn: &Connection, company_id: i64, date: &str) -> Result<(), AppError> {
+    let Ok(date) = NaiveDate::parse_from_str(date.trim(), INVOICE_DATE_FORMAT) else {
+        // Malformed dates are reported by the caller's own validation
+        return Ok(());
+    };
+    let period = date.format("%Y-%m").to_string();
+    let locked: bool = conn.query_row(
+        "SELECT EXISTS(SELECT 1 FROM period_locks WHERE company_id = ?1 AND period = ?2)",
+        params![company_id, period],
+        |row| row.get(0),
+    )?;
+    if locked {
+        return Err(AppError::PeriodLocked {
+            message: format!(
+                "{} is locked; unlock the period before changing documents dated in it",
+                period_label(date)
+            ),
+        });
+    }
+    Ok(())
+}
+
+fn list_locks(conn: &Connection, company_id: i64) -> Result<Vec<PeriodLock>, AppError> {
+    let mut stmt = conn.prepare(
+        "SELECT company_id, period, reason, locked_at FROM period_locks
+             WHERE company_id = ?1 ORDER BY period",
+    )?;
+    let locks = stmt
+        .query_map(params![company_id], period_lock_from_row)?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(locks)
+}
+
+fn require_company(conn: &Connection, company_id: i64) -> Result<(), AppError> {
+    companies::get_company_by_id(conn, company_id)?
+        .map(|_| ())
+        .ok_or_else(|| AppError::not_found("Company not found"))
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn list_financial_years(
+    pool: State<'_, DbPool>,
+    company_id: i64,
+) -> Result<Vec<FinancialYear>, AppError> {
+    let conn = db::get_conn(&pool)?;
+    require_company(&conn, company_id)?;
+    // The current year always exists so the UI has something to select
+    ensure_financial_year(&conn, company_id, Local::now().date_naive())?;
+
+    let mut stmt = conn.prepare(&format!(
+        "{} WHERE company_id = ?1 ORDER BY start_date DESC",
+        SELECT_FINANCIAL_YEAR
+    ))?;
+    let years = stmt
+        .query_map(params![company_id], financial_year_from_row)?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(years)
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn create_financial_year(
+    pool: State<'_, DbPool>,
+    company_id: i64,
+    start_year: i32,
+) -> Result<FinancialYear, AppError> {
+    if !(2000..=2100).contains(&start_year) {
+        return Err(AppError::validation(
+            "start_year",
+            "Financial year must start between 2000 and 2100",
+        ));
+    }
+    let (start, _) = fy_bounds(start_year)?;
+
+    let conn = db::get_conn(&pool)?;
+    require_company(&conn, company_id)?;
+    ensure_financial_year(&conn, company_id, start)
+}
+
+// Locks every month of the year; draft invoices must be resolved first
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn close_financial_year(
+    pool: State<'_, DbPool>,
+    id: i64,
+    company_id: i64,
+) -> Result<FinancialYear, AppError> {
+    let mut conn = db::get_conn(&pool)?;
+    let tx = conn.transaction()?;
+    let year = get_financial_year_by_id(&tx, id, company_id)?
+        .ok_or_else(|| AppError::not_found("Financial year not found"))?;
+    if year.is_closed {
+        return Err(AppError::invalid(format!(
+            "Financial year {} is already closed",
+            year.label
+        )));
+    }
+
+    let drafts: i64 = tx.query_row(
+        "SELECT COUNT(*) FROM invoices
+             WHERE company_id = ?1 AND status = 'draft' AND deleted_at IS NULL
+               AND invoice_date BETWEEN ?2 AND ?3",
+        params![company_id, year.start_date, year.end_date],
+        |row| row.get(0),
+    )?;
+    if drafts > 0 {
+        return Err(AppError::invalid(format!(
+            "{} draft invoice(s) in {} must be issued or deleted before closing",
+            drafts, year.label
+        )));
+    }
+
+    let start = NaiveDate::parse_from_str(&year.start_date, INVOICE_DATE_FORMAT)
+        .map_err(|e| e.to_string())?;
+    let reason = format!("Financial year {} closed", year.label);
+    for period in months_of_year(start.year())? {
+        tx.execute(
+            "INSERT OR IGNORE INTO period_locks (company_id, period, reason) VALUES (?1, ?2, ?3)",
+            params![company_id, period, reason],
+        )?;
+    }
+    tx.execute(
+        "UPDATE financial_years
+         SET is_closed = 1, closed_at = CURRENT_TIMESTAMP, updated_at = CURRENT_TIMESTAMP
+         WHERE id = ?1",
+        params![id],
+    )?;
+
+    let closed = get_financial_year_by_id(&tx, id, company_id)?
+        .ok_or_else(|| AppError::internal("Financial year not found after closing"))?;
+    tx.commit()?;
+    Ok(closed)
+}
+
+// Reopening leaves the month locks in place; each month must still be unlocked explicitly
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn reopen_financial_year(
+    pool: State<'_, DbPool>,
+    id: i64,
+    company_id: i64,
+) -> Result<FinancialYear, AppError> {
+    let conn = db::get_conn(&pool)?;
+    let changed = conn.execute(
+        "UPDATE financial_years
+             SET is_closed = 0, closed_at = NULL, updated_at = CURRENT_TIMESTAMP
+             WHERE id = ?1 AND company_id = ?2",
+        params![id, company_id],
+    )?;
+    if changed == 0 {
+        return Err(AppError::not_found("Financial year not found"));
+    }
+    get_financial_year_by_id(&conn, id, company_id)?
+        .ok_or_else(|| AppError::not_found("Financial year not found after update"))
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn list_period_locks(
+    pool: State<'_, DbPool>,
+    company_id: i64,
+) -> Result<Vec<PeriodLock>, AppError> {
+    let conn = db::get_conn(&pool)?;
+    list_locks(&conn, company_id)
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn lock_period(
+    pool: State<'_, DbPool>,
+    company_id: i64,
+    period: String,
+    reason: Option<String>,
+) -> Result<PeriodLock, AppError> {
+    let month = parse_period(&period)?;
+    let period = month.format("%Y-%m").to_string();
+    if let Some(reason) = &reason {
+        if reason.len() > 200 {
+            return Err(AppError::validation(
+                "reason",
+                "Reason must be 200 characters or less",
+            ));
+        }
+    }
+
+    let conn = db::get_conn(&pool)?;
+    require_company(&conn, company_id)?;
+    conn.execute(
+        "INSERT INTO period_locks (company_id, period, reason) VALUES (?1, ?2, ?3)
+         ON CONFLICT(company_id, period) DO UPDATE SET reason = excluded.reason",
+        params![
+            company_id,
+            period,
+            reason.as_deref().map(str::trim).filter(|r| !r.is_empty())
+        ],
+    )?;
+
+    conn.query_row(
+        "SELECT company_id, period, reason, locked_at FROM period_locks
+         WHERE company_id = ?1 AND period = ?2",
+        params![company_id, period],
+        period_lock_from_row,
+    )
+    .map_err(AppError::from)
+}
+
+// The explicit permission step for editing documents in a locked month
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn unlock_period(
+    pool: State<'_, DbPool>,
+    company_id: i64,
+    period: String,
+) -> Result<(), AppError> {
+    let month = parse_period(&period)?;
+    let period = month.format("%Y-%m").to_string();
+
+    let conn = db::get_conn(&pool)?;
+    let year = ensure_financial_year(&conn, company_id, month)?;
+    if year.is_closed {
+        return Err(AppError::PeriodLocked {
+            message: format!(
+                "Financial year {} is closed; reopen it before unlocking {}",
+                year.label,
+                period_label(month)
+            ),
+        });
+    }
+
+    let changed = conn.execute(
+        "DELETE FROM period_locks WHERE company_id = ?1 AND period = ?2",
+        params![company_id, period],
+    )?;
+    if changed == 0 {
+        return Err(AppError::invalid(format!(
+            "{} is not locked",
+            period_label(month)
+        )));
+    }
+    Ok(())
+}
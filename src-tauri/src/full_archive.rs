@@ -0,0 +1,489 @@
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use chrono::Local;
+use rusqlite::types::Value;
+use rusqlite::Connection;
+use serde::{Deserialize, Serialize};
+use serde_json::Value as Json;
+use sha2::{Digest, Sha256};
+use tauri::{AppHandle, State};
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipArchive, ZipWriter};
+
+use crate::anonymize;
+use crate::backup::{self, RestoreReport};
+use crate::dashboard::DashboardCache;
+use crate::db::{self, DbPool};
+use crate::diagnostics::add_file;
+use crate::error::AppError;
+use crate::migrations;
+
+// A portable copy of the whole database: one JSON file per table, the schema as SQL and a
+// manifest with the schema version and a checksum for every file. Unlike a backup it does
+// not depend on the SQLite file format, so it can be read by other tools or kept for years.
+// It is not encrypted, so credentials are left out (see below). On import the schema is
+// built by the app's own migrations and only row data is taken from the archive.
+const FORMAT: &str = "sales-report-archive";
+const FORMAT_VERSION: u32 = 1;
+
+const MANIFEST_FILE: &str = "manifest.json";
+// For readers of the archive only; import never executes them
+const TABLES_SQL_FILE: &str = "schema/tables.sql";
+const OBJECTS_SQL_FILE: &str = "schema/objects.sql";
+// AUTOINCREMENT counters; SQLite creates the table itself, so only its rows are archived
+const SEQUENCE_TABLE: &str = "sqlite_sequence";
+// BLOB values are written as {"$blob": "<base64>"}
+const BLOB_KEY: &str = "$blob";
+// Sign-in accounts hold password hashes and are not archived; after an import the app starts
+// without users, as on a fresh install
+const OMITTED_TABLES: &[&str] = &["users"];
+// Credentials saved as settings, the same keys the anonymized export drops
+const SECRET_SETTINGS: &str = "key LIKE '%token%' OR key LIKE '%api_key' OR key LIKE '%password%'
+    OR key LIKE '%secret%'";
+// Columns written as empty text: (table, column)
+const CLEARED_COLUMNS: &[(&str, &str)] = &[("webhooks", "secret")];
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ArchiveTable {
+    pub table: String,
+    pub rows: usize,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ArchiveManifest {
+    pub format: String,
+    pub format_version: u32,
+    pub schema_version: i64,
+    pub created_at: String,
+    pub tables: Vec<ArchiveTable>,
+    // SHA-256 of every other file in the archive, by file name
+    pub checksums: BTreeMap<String, String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct FullArchiveInfo {
+    pub path: String,
+    pub schema_version: i64,
+    pub tables: Vec<ArchiveTable>,
+    pub size_bytes: u64,
+    pub sha256: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ArchiveImportReport {
+    pub archive_schema_version: i64,
+    pub archive_created_at: String,
+    pub tables: Vec<ArchiveTable>,
+    pub restore: RestoreReport,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct TableData {
+    columns: Vec<String>,
+    rows: Vec<Vec<Json>>,
+}
+
+fn table_file(table: &str) -> String {
+    format!("tables/{}.json", table)
+}
+
+fn sha256(bytes: &[u8]) -> String {
+    format!("{:x}", Sha256::digest(bytes))
+}
+
+fn quote(name: &str) -> String {
+    format!("\"{}\"", name.replace('"', "\"\""))
+}
+
+fn to_json(value: Value) -> Json {
+    match value {
+        Value::Null => Json::Null,
+        Value::Integer(n) => Json::from(n),
+        Value::Real(n) => Json::from(n),
+        Value::Text(text) => Json::String(text),
+        Value::Blob(bytes) => {
+            let mut object = serde_json::Map::new();
+            object.insert(BLOB_KEY.to_string(), Json::String(STANDARD.encode(bytes)));
+            Json::Object(object)
+        }
+    }
+}
+
+fn from_json(value: &Json) -> Result<Value, String> {
+    match value {
+        Json::Null => Ok(Value::Null),
+        Json::Bool(flag) => Ok(Value::Integer(*flag as i64)),
+        Json::Number(n) => match n.as_i64() {
+            Some(n) => Ok(Value::Integer(n)),
+            None => n
+                .as_f64()
+                .map(Value::Real)
+                .ok_or_else(|| format!("Unsupported number {}", n)),
+        },
+        Json::String(text) => Ok(Value::Text(text.clone())),
+        Json::Object(object) => object
+            .get(BLOB_KEY)
+            .and_then(Json::as_str)
+            .ok_or_else(|| "Unsupported value in archive".to_string())
+            .and_then(|encoded| STANDARD.decode(encoded).map_err(|e| e.to_string()))
+            .map(Value::Blob),
+        Json::Array(_) => Err("Unsupported value in archive".to_string()),
+    }
+}
+
+// CREATE statements for the tables (`tables` true) or for the indexes, views and triggers,
+// in the order SQLite created them. Automatic indexes have no SQL and are rebuilt with their
+// tables.
+fn schema_sql(conn: &Connection, tables: bool) -> Result<String, String> {
+    let filter = if tables {
+        "type = 'table' AND name NOT LIKE 'sqlite_%'"
+    } else {
+        "type IN ('index', 'view', 'trigger') AND sql IS NOT NULL"
+    };
+    let mut stmt = conn
+        .prepare(&format!(
+            "SELECT sql FROM sqlite_master WHERE {} ORDER BY rowid",
+            filter
+        ))
+        .map_err(|e| e.to_string())?;
+    let statements = stmt
+        .query_map([], |row| row.get::<_, String>(0))
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+    Ok(statements
+        .iter()
+        .map(|sql| format!("{};\n", sql))
+        .collect::<Vec<_>>()
+        .join("\n"))
+}
+
+fn table_names(conn: &Connection) -> Result<Vec<String>, String> {
+    let mut stmt = conn
+        .prepare(
+            "SELECT name FROM sqlite_master
+             WHERE type = 'table' AND (name NOT LIKE 'sqlite_%' OR name = ?1)
+             ORDER BY name",
+        )
+        .map_err(|e| e.to_string())?;
+    let names = stmt
+        .query_map([SEQUENCE_TABLE], |row| row.get(0))
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+    Ok(names)
+}
+
+fn read_table(conn: &Connection, table: &str) -> Result<TableData, String> {
+    let filter = if table == "app_settings" {
+        format!(" WHERE NOT ({})", SECRET_SETTINGS)
+    } else {
+        String::new()
+    };
+    let mut stmt = conn
+        .prepare(&format!("SELECT * FROM {}{}", quote(table), filter))
+        .map_err(|e| e.to_string())?;
+    let columns: Vec<String> = stmt.column_names().iter().map(|c| c.to_string()).collect();
+    let cleared: Vec<bool> = columns
+        .iter()
+        .map(|column| CLEARED_COLUMNS.contains(&(table, column.as_str())))
+        .collect();
+    let rows = stmt
+        .query_map([], |row| {
+            cleared
+                .iter()
+                .enumerate()
+                .map(|(i, &clear)| {
+                    if clear {
+                        Ok(Json::String(String::new()))
+                    } else {
+                        row.get::<_, Value>(i).map(to_json)
+                    }
+                })
+                .collect::<Result<Vec<_>, _>>()
+        })
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("Failed to read {}: {}", table, e))?;
+    Ok(TableData { columns, rows })
+}
+
+// Writes the archive under a temporary name and renames it once complete. `conn` should be
+// inside a transaction so every table is read from the same snapshot.
+pub fn export_archive(conn: &Connection, path: &Path) -> Result<FullArchiveInfo, String> {
+    let schema_version = migrations::current_version(conn)?;
+    let partial = PathBuf::from(format!("{}.partial", path.display()));
+
+    let written = (|| {
+        let file = File::create(&partial)
+            .map_err(|e| format!("Failed to create {}: {}", partial.display(), e))?;
+        let mut archive = ZipWriter::new(file);
+        let options = SimpleFileOptions::default()
+            .compression_method(CompressionMethod::Deflated)
+            .large_file(true);
+        let mut checksums = BTreeMap::new();
+        let mut add = |archive: &mut ZipWriter<File>, name: String, bytes: Vec<u8>| {
+            add_file(archive, &name, &bytes, options)?;
+            checksums.insert(name, sha256(&bytes));
+            Ok::<_, String>(())
+        };
+
+        add(
+            &mut archive,
+            TABLES_SQL_FILE.to_string(),
+            schema_sql(conn, true)?.into_bytes(),
+        )?;
+        add(
+            &mut archive,
+            OBJECTS_SQL_FILE.to_string(),
+            schema_sql(conn, false)?.into_bytes(),
+        )?;
+        let mut tables = Vec::new();
+        for table in table_names(conn)? {
+            if OMITTED_TABLES.contains(&table.as_str()) {
+                continue;
+            }
+            let data = read_table(conn, &table)?;
+            let bytes = serde_json::to_vec(&data).map_err(|e| e.to_string())?;
+            add(&mut archive, table_file(&table), bytes)?;
+            tables.push(ArchiveTable {
+                rows: data.rows.len(),
+                table,
+            });
+        }
+
+        let manifest = ArchiveManifest {
+            format: FORMAT.to_string(),
+            format_version: FORMAT_VERSION,
+            schema_version,
+            created_at: Local::now().to_rfc3339(),
+            tables: tables.clone(),
+            checksums,
+        };
+        let manifest = serde_json::to_vec_pretty(&manifest).map_err(|e| e.to_string())?;
+        add_file(&mut archive, MANIFEST_FILE, &manifest, options)?;
+        archive
+            .finish()
+            .map_err(|e| format!("Failed to finish the archive: {}", e))?;
+        std::fs::rename(&partial, path).map_err(|e| format!("Failed to save archive: {}", e))?;
+        Ok(tables)
+    })();
+    let tables = match written {
+        Ok(tables) => tables,
+        Err(e) => {
+            let _ = std::fs::remove_file(&partial);
+            return Err(e);
+        }
+    };
+
+    let (size_bytes, sha256) = backup::file_sha256(path)?;
+    Ok(FullArchiveInfo {
+        path: path.display().to_string(),
+        schema_version,
+        tables,
+        size_bytes,
+        sha256,
+    })
+}
+
+struct ArchiveReader {
+    zip: ZipArchive<File>,
+    manifest: ArchiveManifest,
+}
+
+impl ArchiveReader {
+    fn open(path: &Path) -> Result<Self, AppError> {
+        let file = File::open(path).map_err(|e| format!("Failed to read archive: {}", e))?;
+        let mut zip = ZipArchive::new(file)
+            .map_err(|_| AppError::validation("path", "This file is not a database archive"))?;
+        let manifest = read_entry(&mut zip, MANIFEST_FILE)?;
+        let manifest: ArchiveManifest = serde_json::from_slice(&manifest)
+            .map_err(|_| AppError::validation("path", "This file is not a database archive"))?;
+        if manifest.format != FORMAT {
+            return Err(AppError::validation(
+                "path",
+                "This file is not a database archive",
+            ));
+        }
+        if manifest.format_version > FORMAT_VERSION {
+            return Err(AppError::invalid(
+                "This archive was made by a newer version of the app",
+            ));
+        }
+        Ok(ArchiveReader { zip, manifest })
+    }
+
+    // Contents of a file listed in the manifest, after checking its checksum
+    fn read(&mut self, name: &str) -> Result<Vec<u8>, String> {
+        let expected = self
+            .manifest
+            .checksums
+            .get(name)
+            .ok_or_else(|| format!("Archive manifest does not list {}", name))?;
+        let bytes = read_entry(&mut self.zip, name)?;
+        if &sha256(&bytes) != expected {
+            return Err(format!(
+                "{} in the archive is damaged (checksum mismatch)",
+                name
+            ));
+        }
+        Ok(bytes)
+    }
+}
+
+fn read_entry(zip: &mut ZipArchive<File>, name: &str) -> Result<Vec<u8>, String> {
+    let mut entry = zip
+        .by_name(name)
+        .map_err(|_| format!("Archive is missing {}", name))?;
+    let mut bytes = Vec::new();
+    entry
+        .read_to_end(&mut bytes)
+        .map_err(|e| format!("Failed to read {} from the archive: {}", name, e))?;
+    Ok(bytes)
+}
+
+fn table_exists(conn: &Connection, table: &str) -> Result<bool, String> {
+    conn.query_row(
+        "SELECT EXISTS (SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = ?1)",
+        [table],
+        |row| row.get(0),
+    )
+    .map_err(|e| e.to_string())
+}
+
+fn load_table(conn: &Connection, table: &str, data: &TableData) -> Result<(), String> {
+    if data.columns.is_empty() {
+        return Ok(());
+    }
+    let columns: Vec<String> = data.columns.iter().map(|c| quote(c)).collect();
+    let placeholders: Vec<String> = (1..=columns.len()).map(|i| format!("?{}", i)).collect();
+    let mut stmt = conn
+        .prepare(&format!(
+            "INSERT INTO {} ({}) VALUES ({})",
+            quote(table),
+            columns.join(", "),
+            placeholders.join(", ")
+        ))
+        .map_err(|e| format!("Failed to load {}: {}", table, e))?;
+    for (index, row) in data.rows.iter().enumerate() {
+        if row.len() != columns.len() {
+            return Err(format!(
+                "Row {} of {} has the wrong number of values",
+                index + 1,
+                table
+            ));
+        }
+        let values = row.iter().map(from_json).collect::<Result<Vec<_>, _>>()?;
+        stmt.execute(rusqlite::params_from_iter(values))
+            .map_err(|e| format!("Failed to load row {} of {}: {}", index + 1, table, e))?;
+    }
+    Ok(())
+}
+
+// Rebuilds the archived database as a plain SQLite file at `target`. The schema comes from the
+// app's migrations at the archive's schema version, so nothing in the archive is executed; the
+// archive only supplies rows, each file checked against its checksum and each table against
+// its row count.
+fn rebuild(reader: &mut ArchiveReader, target: &Path) -> Result<(), AppError> {
+    let mut conn =
+        Connection::open(target).map_err(|e| format!("Failed to stage archive: {}", e))?;
+    migrations::migrate_to(&mut conn, reader.manifest.schema_version, false)?;
+    let tx = conn.transaction()?;
+    // Read-only tables and the sync journal are guarded by triggers, which are lifted while
+    // the migrations' seed rows are replaced with the archived ones
+    let triggers = anonymize::take_triggers(&tx)?;
+    for table in reader.manifest.tables.clone() {
+        let bytes = reader.read(&table_file(&table.table))?;
+        let data: TableData = serde_json::from_slice(&bytes)
+            .map_err(|e| format!("{} in the archive is not valid: {}", table.table, e))?;
+        if data.rows.len() != table.rows {
+            return Err(AppError::invalid(format!(
+                "{} has {} rows in the archive but the manifest lists {}",
+                table.table,
+                data.rows.len(),
+                table.rows
+            )));
+        }
+        if !table_exists(&tx, &table.table)? {
+            if table.table == SEQUENCE_TABLE {
+                continue;
+            }
+            return Err(AppError::invalid(format!(
+                "{} is not part of schema version {}",
+                table.table, reader.manifest.schema_version
+            )));
+        }
+        tx.execute(&format!("DELETE FROM {}", quote(&table.table)), [])?;
+        load_table(&tx, &table.table, &data)?;
+    }
+    for (name, sql) in triggers {
+        tx.execute_batch(&sql)
+            .map_err(|e| format!("Failed to restore trigger {}: {}", name, e))?;
+    }
+    tx.commit()?;
+    Ok(())
+}
+
+// Replaces the live database with the archive's contents. The archive is rebuilt into a
+// scratch database first, then restored like a backup: the current data is saved to
+// `rollback_dir` and an archive from an older schema is migrated forward.
+pub fn import_archive(
+    conn: &mut Connection,
+    path: &Path,
+    rollback_dir: &Path,
+) -> Result<ArchiveImportReport, AppError> {
+    let mut reader = ArchiveReader::open(path)?;
+    if reader.manifest.schema_version > migrations::latest_version() {
+        return Err(AppError::invalid(
+            "This archive was made by a newer version of the app",
+        ));
+    }
+
+    let staged = backup::temp_path("archive");
+    let restored = rebuild(&mut reader, &staged).and_then(|_| backup::restore_from(conn, &staged, None, rollback_dir));
+    let _ = std::fs::remove_file(&staged);
+    let restore = restored?;
+    Ok(ArchiveImportReport {
+        archive_schema_version: reader.manifest.schema_version,
+        archive_created_at: reader.manifest.created_at,
+        tables: reader.manifest.tables,
+        restore,
+    })
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn export_full_archive(
+    pool: State<'_, DbPool>,
+    path: String,
+) -> Result<FullArchiveInfo, AppError> {
+    let path = path.trim();
+    if path.is_empty() {
+        return Err(AppError::validation(
+            "path",
+            "Choose where to save the archive",
+        ));
+    }
+    let mut conn = db::get_conn(&pool)?;
+    let tx = conn.transaction()?;
+    Ok(export_archive(&tx, Path::new(path))?)
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn import_full_archive(
+    app: AppHandle,
+    pool: State<'_, DbPool>,
+    cache: State<'_, DashboardCache>,
+    path: String,
+) -> Result<ArchiveImportReport, AppError> {
+    let rollback_dir = backup::rollback_dir(&app)?;
+    let mut conn = db::get_conn(&pool)?;
+    let report = import_archive(&mut conn, Path::new(path.trim()), &rollback_dir)?;
+    cache.clear();
+    Ok(report)
+}
@@ -0,0 +1,187 @@
+use serde::{Deserialize, Serialize};
+
+use crate::error::AppError;
+
+// GSTIN layout: 2-digit state code, 10-character PAN, entity number, 'Z', check digit
+const GSTIN_LENGTH: usize = 15;
+const CHECKSUM_CHARS: &[u8; 36] = b"0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZ";
+
+// State codes issued for GST registrations, plus 97 (other territory) and 99 (centre jurisdiction)
+fn is_valid_state_code(code: u32) -> bool {
+    (1..=38).contains(&code) || code == 97 || code == 99
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum GstinComponent {
+    Length,
+    Characters,
+    StateCode,
+    Pan,
+    EntityNumber,
+    DefaultCharacter,
+    CheckDigit,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct GstinError {
+    pub component: GstinComponent,
+    pub message: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Gstin {
+    pub gstin: String,
+    pub state_code: String,
+    pub pan: String,
+    pub entity_number: char,
+    pub check_digit: char,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct GstinValidation {
+    pub valid: bool,
+    pub gstin: String,
+    pub details: Option<Gstin>,
+    pub failed_component: Option<GstinComponent>,
+    pub message: Option<String>,
+}
+
+fn fail(component: GstinComponent, message: &str) -> GstinError {
+    GstinError {
+        component,
+        message: message.to_string(),
+    }
+}
+
+pub fn is_valid_pan(pan: &str) -> bool {
+    let bytes = pan.as_bytes();
+    bytes.len() == 10
+        && bytes[..5].iter().all(u8::is_ascii_uppercase)
+        && bytes[5..9].iter().all(u8::is_ascii_digit)
+        && bytes[9].is_ascii_uppercase()
+}
+
+// Mod-36 check digit over the first 14 characters, alternating weights 1 and 2
+pub fn compute_check_digit(first_fourteen: &str) -> Option<char> {
+    let mut sum = 0u32;
+    for (i, c) in first_fourteen.chars().enumerate() {
+        let value = CHECKSUM_CHARS.iter().position(|&b| b as char == c)? as u32;
+        let factor = if i % 2 == 0 { 1 } else { 2 };
+        let product = value * factor;
+        sum += product / 36 + product % 36;
+    }
+    let check = (36 - sum % 36) % 36;
+    Some(CHECKSUM_CHARS[check as usize] as char)
+}
+
+pub fn parse_gstin(value: &str) -> Result<Gstin, GstinError> {
+    let gstin = value.trim().to_ascii_uppercase();
+
+    if gstin.len() != GSTIN_LENGTH {
+        return Err(fail(
+            GstinComponent::Length,
+            "GSTIN must be exactly 15 characters",
+        ));
+    }
+    if !gstin.chars().all(|c| c.is_ascii_alphanumeric()) {
+        return Err(fail(
+            GstinComponent::Characters,
+            "GSTIN may only contain letters and digits",
+        ));
+    }
+
+    let state_code = &gstin[0..2];
+    let state_valid = state_code
+        .parse::<u32>()
+        .map(is_valid_state_code)
+        .unwrap_or(false);
+    if !state_code.chars().all(|c| c.is_ascii_digit()) || !state_valid {
+        return Err(fail(
+            GstinComponent::StateCode,
+            "GSTIN must start with a valid 2-digit state code",
+        ));
+    }
+
+    let pan = &gstin[2..12];
+    if !is_valid_pan(pan) {
+        return Err(fail(
+            GstinComponent::Pan,
+            "Characters 3 to 12 of the GSTIN must be a valid PAN (5 letters, 4 digits, 1 letter)",
+        ));
+    }
+
+    let entity_number = gstin.as_bytes()[12] as char;
+    if entity_number == '0' {
+        return Err(fail(
+            GstinComponent::EntityNumber,
+            "Character 13 of the GSTIN must be an entity number from 1-9 or A-Z",
+        ));
+    }
+
+    if gstin.as_bytes()[13] != b'Z' {
+        return Err(fail(
+            GstinComponent::DefaultCharacter,
+            "Character 14 of the GSTIN must be 'Z'",
+        ));
+    }
+
+    let check_digit = gstin.as_bytes()[14] as char;
+    if compute_check_digit(&gstin[0..14]) != Some(check_digit) {
+        return Err(fail(
+            GstinComponent::CheckDigit,
+            "GSTIN check digit is invalid; please re-check the number",
+        ));
+    }
+
+    Ok(Gstin {
+        state_code: state_code.to_string(),
+        pan: pan.to_string(),
+        entity_number,
+        check_digit,
+        gstin,
+    })
+}
+
+// Convenience wrapper for form validation that only needs the message
+pub fn check_gstin(value: &str) -> Result<(), String> {
+    parse_gstin(value).map(|_| ()).map_err(|e| e.message)
+}
+
+// UINs issued to UN bodies and embassies share the GSTIN length, state prefix and check digit
+// but not the PAN or the 'Z' in position 14
+pub fn check_uin(value: &str) -> Result<(), String> {
+    let uin = value.trim().to_ascii_uppercase();
+    if uin.len() != GSTIN_LENGTH || !uin.chars().all(|c| c.is_ascii_alphanumeric()) {
+        return Err("UIN must be exactly 15 letters or digits".to_string());
+    }
+    if !uin[0..2].chars().all(|c| c.is_ascii_digit()) {
+        return Err("UIN must start with a 2-digit state code".to_string());
+    }
+    if compute_check_digit(&uin[0..14]) != uin.chars().last() {
+        return Err("UIN check digit is invalid; please re-check the number".to_string());
+    }
+    Ok(())
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn validate_gstin(gstin: String) -> Result<GstinValidation, AppError> {
+    let normalized = gstin.trim().to_ascii_uppercase();
+    Ok(match parse_gstin(&normalized) {
+        Ok(details) => GstinValidation {
+            valid: true,
+            gstin: normalized,
+            details: Some(details),
+            failed_component: None,
+            message: None,
+        },
+        Err(error) => GstinValidation {
+            valid: false,
+            gstin: normalized,
+            details: None,
+            failed_component: Some(error.component),
+            message: Some(error.message),
+        },
+    })
+}
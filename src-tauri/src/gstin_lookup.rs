@@ -0,0 +1,283 @@
+use std::time::Duration;
+
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tauri::State;
+
+use crate::db::{self, delete_setting, get_setting, set_setting, DbPool};
+use crate::encryption;
+use crate::error::AppError;
+use crate::gstin;
+
+const SETTING_API_URL: &str = "gstin_api_url";
+const SETTING_API_KEY_HEADER: &str = "gstin_api_key_header";
+const SETTING_CACHE_TTL_HOURS: &str = "gstin_cache_ttl_hours";
+// The API key lives in the OS keyring; earlier versions kept it in this setting
+const API_KEY_ENTRY: &str = "gstin-api-key";
+const LEGACY_SETTING_API_KEY: &str = "gstin_api_key";
+
+const DEFAULT_API_KEY_HEADER: &str = "x-api-key";
+const DEFAULT_CACHE_TTL_HOURS: i64 = 24 * 7;
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(15);
+
+// Lookup API configuration. `api_url` must contain a `{gstin}` placeholder. The API key is only
+// ever written, never returned.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct GstinApiConfig {
+    pub api_url: String,
+    pub has_api_key: bool,
+    pub api_key_header: String,
+    pub cache_ttl_hours: i64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SaveGstinApiConfig {
+    pub api_url: String,
+    // None keeps the saved key; an empty string removes it
+    pub api_key: Option<String>,
+    pub api_key_header: String,
+    pub cache_ttl_hours: i64,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct GstinVerification {
+    pub gstin: String,
+    pub legal_name: Option<String>,
+    pub trade_name: Option<String>,
+    pub registration_status: Option<String>,
+    pub address: Option<String>,
+    pub fetched_at: Option<String>,
+    pub from_cache: bool,
+}
+
+fn load_config(conn: &Connection) -> Result<GstinApiConfig, String> {
+    if let Some(key) = get_setting(conn, LEGACY_SETTING_API_KEY)? {
+        if !key.is_empty() {
+            encryption::write_secret(API_KEY_ENTRY, &key)?;
+        }
+        delete_setting(conn, LEGACY_SETTING_API_KEY)?;
+    }
+    Ok(GstinApiConfig {
+        api_url: get_setting(conn, SETTING_API_URL)?.unwrap_or_default(),
+        has_api_key: encryption::read_secret(API_KEY_ENTRY)?.is_some(),
+        api_key_header: get_setting(conn, SETTING_API_KEY_HEADER)?
+            .unwrap_or_else(|| DEFAULT_API_KEY_HEADER.to_string()),
+        cache_ttl_hours: get_setting(conn, SETTING_CACHE_TTL_HOURS)?
+            .and_then(|value| value.parse().ok())
+            .unwrap_or(DEFAULT_CACHE_TTL_HOURS),
+    })
+}
+
+fn cached_verification(
+    conn: &Connection,
+    gstin: &str,
+    ttl_hours: i64,
+) -> Result<Option<GstinVerification>, AppError> {
+    conn.query_row(
+        "SELECT gstin, legal_name, trade_name, registration_status, address, fetched_at
+         FROM gstin_verifications
+         WHERE gstin = ?1 AND fetched_at >= datetime('now', ?2)",
+        params![gstin, format!("-{} hours", ttl_hours)],
+        |row| {
+            Ok(GstinVerification {
+                gstin: row.get(0)?,
+                legal_name: row.get(1)?,
+                trade_name: row.get(2)?,
+                registration_status: row.get(3)?,
+                address: row.get(4)?,
+                fetched_at: row.get(5)?,
+                from_cache: true,
+            })
+        },
+    )
+    .optional()
+    .map_err(AppError::from)
+}
+
+fn store_verification(
+    conn: &Connection,
+    verification: &GstinVerification,
+    raw_response: &str,
+) -> Result<(), AppError> {
+    conn.execute(
+        "INSERT INTO gstin_verifications (gstin, legal_name, trade_name, registration_status, address, raw_response, fetched_at)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, CURRENT_TIMESTAMP)
+         ON CONFLICT(gstin) DO UPDATE SET
+            legal_name = ?2,
+            trade_name = ?3,
+            registration_status = ?4,
+            address = ?5,
+            raw_response = ?6,
+            fetched_at = CURRENT_TIMESTAMP",
+        params![
+            verification.gstin,
+            verification.legal_name,
+            verification.trade_name,
+            verification.registration_status,
+            verification.address,
+            raw_response
+        ],
+    )?;
+    Ok(())
+}
+
+fn text_field(value: &Value, keys: &[&str]) -> Option<String> {
+    keys.iter()
+        .filter_map(|key| value.get(*key))
+        .filter_map(Value::as_str)
+        .map(str::trim)
+        .find(|text| !text.is_empty())
+        .map(str::to_string)
+}
+
+// Most GSPs relay the portal's taxpayer search response (lgnm, tradeNam, sts, pradr),
+// either at the top level or wrapped in a `data` object
+fn parse_response(gstin: &str, body: &Value) -> GstinVerification {
+    let data = body.get("data").filter(|d| d.is_object()).unwrap_or(body);
+
+    let address = data
+        .get("pradr")
+        .and_then(|pradr| pradr.get("addr"))
+        .map(|addr| {
+            ["bno", "bnm", "flno", "st", "loc", "dst", "stcd", "pncd"]
+                .iter()
+                .filter_map(|key| addr.get(*key).and_then(Value::as_str))
+                .map(str::trim)
+                .filter(|part| !part.is_empty())
+                .collect::<Vec<_>>()
+                .join(", ")
+        })
+        .filter(|address| !address.is_empty())
+        .or_else(|| text_field(data, &["address"]));
+
+    GstinVerification {
+        gstin: gstin.to_string(),
+        legal_name: text_field(data, &["lgnm", "legal_name", "legalName"]),
+        trade_name: text_field(data, &["tradeNam", "trade_name", "tradeName"]),
+        registration_status: text_field(data, &["sts", "status"]),
+        address,
+        fetched_at: Some(chrono::Utc::now().format("%Y-%m-%d %H:%M:%S").to_string()),
+        from_cache: false,
+    }
+}
+
+async fn fetch_verification(
+    config: &GstinApiConfig,
+    api_key: Option<&str>,
+    gstin: &str,
+) -> Result<(GstinVerification, String), String> {
+    let url = config.api_url.replace("{gstin}", gstin);
+    let client = reqwest::Client::builder()
+        .timeout(REQUEST_TIMEOUT)
+        .build()
+        .map_err(|e| e.to_string())?;
+
+    let mut request = client.get(&url);
+    if let Some(api_key) = api_key {
+        request = request.header(config.api_key_header.as_str(), api_key);
+    }
+
+    let response = request
+        .send()
+        .await
+        .map_err(|e| format!("GSTIN lookup request failed: {}", e))?;
+    let status = response.status();
+    let raw = response
+        .text()
+        .await
+        .map_err(|e| format!("Failed to read GSTIN lookup response: {}", e))?;
+    if !status.is_success() {
+        return Err(format!("GSTIN lookup failed with HTTP status {}", status));
+    }
+
+    let body: Value = serde_json::from_str(&raw)
+        .map_err(|e| format!("GSTIN lookup returned invalid JSON: {}", e))?;
+    Ok((parse_response(gstin, &body), raw))
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn get_gstin_api_config(pool: State<'_, DbPool>) -> Result<GstinApiConfig, AppError> {
+    let conn = db::get_conn(&pool)?;
+    Ok(load_config(&conn)?)
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn save_gstin_api_config(
+    pool: State<'_, DbPool>,
+    config: SaveGstinApiConfig,
+) -> Result<GstinApiConfig, AppError> {
+    let api_url = config.api_url.trim();
+    if !api_url.is_empty() {
+        if !api_url.starts_with("https://") && !api_url.starts_with("http://") {
+            return Err(AppError::validation(
+                "api_url",
+                "GSTIN API URL must start with http:// or https://",
+            ));
+        }
+        if !api_url.contains("{gstin}") {
+            return Err(AppError::validation(
+                "api_url",
+                "GSTIN API URL must contain the {gstin} placeholder",
+            ));
+        }
+    }
+    if config.cache_ttl_hours < 0 {
+        return Err(AppError::validation(
+            "cache_ttl_hours",
+            "Cache duration cannot be negative",
+        ));
+    }
+
+    let conn = db::get_conn(&pool)?;
+    set_setting(&conn, SETTING_API_URL, api_url)?;
+    match config.api_key.as_deref() {
+        Some("") => encryption::delete_secret(API_KEY_ENTRY)?,
+        Some(key) => encryption::write_secret(API_KEY_ENTRY, key)?,
+        None => {}
+    }
+    set_setting(&conn, SETTING_API_KEY_HEADER, config.api_key_header.trim())?;
+    set_setting(
+        &conn,
+        SETTING_CACHE_TTL_HOURS,
+        &config.cache_ttl_hours.to_string(),
+    )?;
+    Ok(load_config(&conn)?)
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn verify_gstin_online(
+    pool: State<'_, DbPool>,
+    gstin: String,
+    force_refresh: Option<bool>,
+) -> Result<GstinVerification, AppError> {
+    let parsed = gstin::parse_gstin(&gstin).map_err(|e| AppError::validation("gstin", e.message))?;
+
+    // Keep database access out of the await points below
+    let config = {
+        let conn = db::get_conn(&pool)?;
+        let config = load_config(&conn)?;
+        if !force_refresh.unwrap_or(false) {
+            if let Some(cached) = cached_verification(&conn, &parsed.gstin, config.cache_ttl_hours)? {
+                return Ok(cached);
+            }
+        }
+        config
+    };
+
+    if config.api_url.is_empty() {
+        return Err(AppError::invalid("GSTIN lookup API is not configured"));
+    }
+
+    let api_key = encryption::read_secret(API_KEY_ENTRY)?;
+    let (verification, raw) =
+        fetch_verification(&config, api_key.as_deref(), &parsed.gstin).await?;
+
+    let conn = db::get_conn(&pool)?;
+    store_verification(&conn, &verification, &raw)?;
+
+    Ok(verification)
+}
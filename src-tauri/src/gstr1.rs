@@ -0,0 +1,1502 @@
+use std::collections::{BTreeMap, HashSet};
+
+use chrono::NaiveDate;
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use tauri::State;
+
+use crate::amendments::{self, AmendedDocument};
+use crate::companies::{self, Company};
+use crate::credit_notes::{self, CreditDebitNote, CreditDebitNoteLine, NoteType};
+use crate::customers::RegistrationType;
+use crate::db::{self, DbPool};
+use crate::delivery_challans::{self, ChallanPurpose, DeliveryChallan};
+use crate::error::AppError;
+use crate::exports::{self, ExportMode};
+use crate::financial_years;
+use crate::gstin;
+use crate::invoices::{self, round2, Invoice, InvoiceLine, InvoiceStatus, INVOICE_DATE_FORMAT};
+use crate::place_of_supply::{self, SupplyKind};
+use crate::tax::{self, TaxRegime};
+use crate::units;
+
+const GSTR1_VERSION: &str = "GST3.2";
+// Unregistered inter-state invoices above this value are reported invoice-wise (B2CL)
+pub(crate) const B2CL_THRESHOLD: f64 = 100_000.0;
+// Place of supply code the portal uses for exports
+pub(crate) const EXPORT_STATE_CODE: &str = "96";
+const VALID_RATES: &[f64] = &[
+    0.0, 0.1, 0.25, 1.0, 1.5, 3.0, 5.0, 6.0, 7.5, 12.0, 18.0, 28.0, 40.0,
+];
+const HSN_DESCRIPTION_LIMIT: usize = 30;
+// Above this aggregate turnover in the previous year, HSN codes need 6 digits instead of 4
+const SIX_DIGIT_HSN_TURNOVER: f64 = 50_000_000.0;
+const PORTAL_DATE_FORMAT: &str = "%d-%m-%Y";
+// Missing numbers listed per series; the count still covers the whole gap
+const MISSING_NUMBERS_LIMIT: usize = 100;
+
+// GSTR-1 offline JSON, field names as defined by the portal schema
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Gstr1Return {
+    pub gstin: String,
+    pub fp: String,
+    pub version: String,
+    pub hash: String,
+    #[serde(skip_serializing_if = "Vec::is_empty", default)]
+    pub b2b: Vec<B2bParty>,
+    #[serde(skip_serializing_if = "Vec::is_empty", default)]
+    pub b2cl: Vec<B2clPlace>,
+    #[serde(skip_serializing_if = "Vec::is_empty", default)]
+    pub b2cs: Vec<B2csEntry>,
+    #[serde(skip_serializing_if = "Vec::is_empty", default)]
+    pub cdnr: Vec<CdnrParty>,
+    #[serde(skip_serializing_if = "Vec::is_empty", default)]
+    pub exp: Vec<ExportGroup>,
+    #[serde(skip_serializing_if = "Vec::is_empty", default)]
+    pub b2ba: Vec<B2baParty>,
+    #[serde(skip_serializing_if = "Vec::is_empty", default)]
+    pub b2cla: Vec<B2claPlace>,
+    #[serde(skip_serializing_if = "Vec::is_empty", default)]
+    pub cdnra: Vec<CdnraParty>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub hsn: Option<HsnSection>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub doc_issue: Option<DocIssue>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ItemDetail {
+    pub txval: f64,
+    pub rt: f64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub iamt: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub camt: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub samt: Option<f64>,
+    pub csamt: f64,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Item {
+    pub num: usize,
+    pub itm_det: ItemDetail,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct B2bInvoice {
+    pub inum: String,
+    pub idt: String,
+    pub val: f64,
+    pub pos: String,
+    pub rchrg: String,
+    pub inv_typ: String,
+    pub itms: Vec<Item>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct B2bParty {
+    pub ctin: String,
+    pub inv: Vec<B2bInvoice>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct B2clInvoice {
+    pub inum: String,
+    pub idt: String,
+    pub val: f64,
+    pub itms: Vec<Item>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct B2clPlace {
+    pub pos: String,
+    pub inv: Vec<B2clInvoice>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct B2csEntry {
+    pub sply_ty: String,
+    pub pos: String,
+    pub typ: String,
+    pub rt: f64,
+    pub txval: f64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub iamt: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub camt: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub samt: Option<f64>,
+    pub csamt: f64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CdnrNote {
+    pub ntty: String,
+    pub nt_num: String,
+    pub nt_dt: String,
+    pub val: f64,
+    pub pos: String,
+    pub rchrg: String,
+    pub inv_typ: String,
+    pub itms: Vec<Item>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CdnrParty {
+    pub ctin: String,
+    pub nt: Vec<CdnrNote>,
+}
+
+// Amendments carry the corrected document alongside the number and date it was first filed under
+#[derive(Debug, Serialize, Deserialize)]
+pub struct B2baInvoice {
+    pub oinum: String,
+    pub oidt: String,
+    #[serde(flatten)]
+    pub inv: B2bInvoice,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct B2baParty {
+    pub ctin: String,
+    pub inv: Vec<B2baInvoice>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct B2claInvoice {
+    pub oinum: String,
+    pub oidt: String,
+    #[serde(flatten)]
+    pub inv: B2clInvoice,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct B2claPlace {
+    pub pos: String,
+    pub inv: Vec<B2claInvoice>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CdnraNote {
+    pub ont_num: String,
+    pub ont_dt: String,
+    #[serde(flatten)]
+    pub nt: CdnrNote,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CdnraParty {
+    pub ctin: String,
+    pub nt: Vec<CdnraNote>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ExportInvoice {
+    pub inum: String,
+    pub idt: String,
+    pub val: f64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sbpcode: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sbnum: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sbdt: Option<String>,
+    pub itms: Vec<ItemDetail>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ExportGroup {
+    pub exp_typ: String,
+    pub inv: Vec<ExportInvoice>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct HsnEntry {
+    pub num: usize,
+    pub hsn_sc: String,
+    pub desc: String,
+    pub uqc: String,
+    pub qty: f64,
+    pub rt: f64,
+    pub txval: f64,
+    pub iamt: f64,
+    pub camt: f64,
+    pub samt: f64,
+    pub csamt: f64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct HsnSection {
+    #[serde(skip_serializing_if = "Vec::is_empty", default)]
+    pub hsn_b2b: Vec<HsnEntry>,
+    #[serde(skip_serializing_if = "Vec::is_empty", default)]
+    pub hsn_b2c: Vec<HsnEntry>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DocSeries {
+    pub num: usize,
+    pub from: String,
+    pub to: String,
+    pub totnum: usize,
+    pub cancel: usize,
+    pub net_issue: usize,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DocType {
+    pub doc_num: usize,
+    pub doc_typ: String,
+    pub docs: Vec<DocSeries>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DocIssue {
+    pub doc_det: Vec<DocType>,
+}
+
+// One numbering series in the period with the running numbers absent between its first and last
+// document. Deleted documents and drafts were never issued, so their numbers count as missing.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SeriesSummary {
+    pub prefix: String,
+    #[serde(flatten)]
+    pub series: DocSeries,
+    pub missing_count: u64,
+    pub missing_numbers: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DocumentTypeSummary {
+    pub doc_num: usize,
+    pub doc_typ: String,
+    pub series: Vec<SeriesSummary>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DocumentSummaryReport {
+    pub period: String,
+    pub documents: Vec<DocumentTypeSummary>,
+}
+
+// An invoice line whose HSN code cannot be reported as it stands
+#[derive(Debug, Serialize, Deserialize)]
+pub struct HsnIssue {
+    pub invoice_id: i64,
+    pub invoice_number: String,
+    pub line_no: Option<i64>,
+    pub hsn_code: Option<String>,
+    pub message: String,
+}
+
+// GSTR-1 table 12, split into B2B and B2C as the portal expects
+#[derive(Debug, Serialize, Deserialize)]
+pub struct HsnSummaryReport {
+    pub period: String,
+    pub annual_turnover: f64,
+    pub required_digits: usize,
+    pub hsn_b2b: Vec<HsnEntry>,
+    pub hsn_b2c: Vec<HsnEntry>,
+    pub issues: Vec<HsnIssue>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Gstr1Result {
+    pub period: String,
+    pub path: Option<String>,
+    pub b2b_invoices: usize,
+    pub b2cl_invoices: usize,
+    pub b2cs_entries: usize,
+    pub cdnr_notes: usize,
+    pub export_invoices: usize,
+    pub amendments: usize,
+    pub hsn_entries: usize,
+    pub warnings: Vec<String>,
+    pub data: Gstr1Return,
+}
+
+struct ReturnInvoice {
+    invoice: Invoice,
+    ctin: String,
+    lines: Vec<InvoiceLine>,
+}
+
+struct ReturnNote {
+    note: CreditDebitNote,
+    ctin: String,
+    place_of_supply: String,
+    // Taken from the original invoice
+    inv_typ: &'static str,
+    inter_state: bool,
+    lines: Vec<CreditDebitNoteLine>,
+}
+
+// Accepts the portal's MMYYYY return period and returns its first and last day
+pub fn parse_period(period: &str) -> Result<(NaiveDate, NaiveDate), AppError> {
+    let period = period.trim();
+    let invalid =
+        || AppError::validation("period", "Return period must be in MMYYYY format, e.g. 042025");
+    if period.len() != 6 || !period.chars().all(|c| c.is_ascii_digit()) {
+        return Err(invalid());
+    }
+    let month: u32 = period[..2].parse().map_err(|_| invalid())?;
+    let year: i32 = period[2..].parse().map_err(|_| invalid())?;
+    let first = NaiveDate::from_ymd_opt(year, month, 1).ok_or_else(invalid)?;
+    let next = if month == 12 {
+        NaiveDate::from_ymd_opt(year + 1, 1, 1)
+    } else {
+        NaiveDate::from_ymd_opt(year, month + 1, 1)
+    }
+    .ok_or_else(invalid)?;
+    Ok((first, next.pred_opt().ok_or_else(invalid)?))
+}
+
+// Portal invoice type for B2B and CDNR: SEZ supplies with or without payment of IGST (the
+// offline tool's SEZWP/SEZWOP), deemed exports, and regular supplies
+fn invoice_type(kind: SupplyKind, under_lut: bool) -> &'static str {
+    match kind {
+        SupplyKind::Sez if under_lut => "SEWOP",
+        SupplyKind::Sez => "SEWP",
+        SupplyKind::DeemedExport => "DE",
+        SupplyKind::Regular | SupplyKind::Export => "R",
+    }
+}
+
+fn is_inter_state(company: &Company, place_of_supply: &str, kind: SupplyKind) -> bool {
+    place_of_supply::decide(&company.state_code, place_of_supply, kind) == TaxRegime::InterState
+}
+
+fn portal_date(date: &str) -> String {
+    NaiveDate::parse_from_str(date, INVOICE_DATE_FORMAT)
+        .map(|d| d.format(PORTAL_DATE_FORMAT).to_string())
+        .unwrap_or_else(|_| date.to_string())
+}
+
+// Snaps a computed rate to the nearest notified rate when it is within rounding distance
+fn normalize_rate(rate: f64) -> f64 {
+    VALID_RATES
+        .iter()
+        .copied()
+        .find(|valid| (valid - rate).abs() < 0.1)
+        .unwrap_or(round2(rate))
+}
+
+// Sums (rate, taxable, IGST, CGST, SGST) amounts into one item per rate
+fn rate_wise(
+    by_rate: BTreeMap<String, (f64, f64, f64, f64, f64)>,
+    inter_state: bool,
+) -> Vec<ItemDetail> {
+    by_rate
+        .into_values()
+        .map(|(rt, txval, iamt, camt, samt)| ItemDetail {
+            txval: round2(txval),
+            rt,
+            iamt: inter_state.then(|| round2(iamt)),
+            camt: (!inter_state).then(|| round2(camt)),
+            samt: (!inter_state).then(|| round2(samt)),
+            csamt: 0.0,
+        })
+        .collect()
+}
+
+// Rate-wise items of an invoice; invoices saved without lines become a single item
+fn invoice_items(entry: &ReturnInvoice, inter_state: bool) -> Vec<ItemDetail> {
+    let mut by_rate: BTreeMap<String, (f64, f64, f64, f64, f64)> = BTreeMap::new();
+    if entry.lines.is_empty() {
+        let invoice = &entry.invoice;
+        let tax = invoice.cgst_amount + invoice.sgst_amount + invoice.igst_amount;
+        let rate = if invoice.taxable_value > 0.0 {
+            normalize_rate(tax / invoice.taxable_value * 100.0)
+        } else {
+            0.0
+        };
+        by_rate.insert(
+            format!("{:.2}", rate),
+            (
+                rate,
+                invoice.taxable_value,
+                invoice.igst_amount,
+                invoice.cgst_amount,
+                invoice.sgst_amount,
+            ),
+        );
+    }
+    for line in &entry.lines {
+        let slot = by_rate
+            .entry(format!("{:.2}", line.gst_rate))
+            .or_insert((line.gst_rate, 0.0, 0.0, 0.0, 0.0));
+        slot.1 += line.taxable_value;
+        slot.2 += line.igst_amount;
+        slot.3 += line.cgst_amount;
+        slot.4 += line.sgst_amount;
+    }
+    rate_wise(by_rate, inter_state)
+}
+
+fn note_items(lines: &[CreditDebitNoteLine], inter_state: bool) -> Vec<ItemDetail> {
+    let mut by_rate: BTreeMap<String, (f64, f64, f64, f64, f64)> = BTreeMap::new();
+    for line in lines {
+        let slot = by_rate
+            .entry(format!("{:.2}", line.gst_rate))
+            .or_insert((line.gst_rate, 0.0, 0.0, 0.0, 0.0));
+        slot.1 += line.taxable_value;
+        slot.2 += line.igst_amount;
+        slot.3 += line.cgst_amount;
+        slot.4 += line.sgst_amount;
+    }
+    rate_wise(by_rate, inter_state)
+}
+
+fn numbered(items: Vec<ItemDetail>) -> Vec<Item> {
+    items
+        .into_iter()
+        .enumerate()
+        .map(|(i, itm_det)| Item {
+            num: i + 1,
+            itm_det,
+        })
+        .collect()
+}
+
+fn hsn_description(conn: &Connection, code: &str, fallback: &str) -> Result<String, AppError> {
+    let description: Option<String> = conn
+        .query_row("SELECT description FROM hsn_codes WHERE code = ?1", params![code], |row| {
+            row.get(0)
+        })
+        .optional()?;
+    Ok(description
+        .unwrap_or_else(|| fallback.to_string())
+        .chars()
+        .take(HSN_DESCRIPTION_LIMIT)
+        .collect())
+}
+
+fn summarize_hsn(conn: &Connection, entries: &[&ReturnInvoice]) -> Result<Vec<HsnEntry>, String> {
+    let units = units::reporting_units(conn)?;
+    let mut grouped: BTreeMap<(String, String, String), HsnEntry> = BTreeMap::new();
+    for entry in entries {
+        for line in &entry.lines {
+            let code = line.hsn_code.trim().to_string();
+            // SAC codes (chapter 99) are services and carry no quantity; goods are reported in
+            // their unit's base UQC, or NOS when the line has no unit
+            let unit = line.unit_id.and_then(|id| units.get(&id));
+            let (uqc, factor) = if code.starts_with("99") {
+                ("NA".to_string(), 0.0)
+            } else {
+                unit.map_or(("NOS".to_string(), 1.0), |unit| (unit.uqc.clone(), unit.factor))
+            };
+            let key = (code.clone(), format!("{:.2}", line.gst_rate), uqc.clone());
+            if !grouped.contains_key(&key) {
+                grouped.insert(
+                    key.clone(),
+                    HsnEntry {
+                        num: 0,
+                        desc: hsn_description(conn, &code, &line.description)?,
+                        hsn_sc: code.clone(),
+                        uqc,
+                        qty: 0.0,
+                        rt: line.gst_rate,
+                        txval: 0.0,
+                        iamt: 0.0,
+                        camt: 0.0,
+                        samt: 0.0,
+                        csamt: 0.0,
+                    },
+                );
+            }
+            if let Some(hsn) = grouped.get_mut(&key) {
+                hsn.qty += line.quantity * factor;
+                hsn.txval += line.taxable_value;
+                hsn.iamt += line.igst_amount;
+                hsn.camt += line.cgst_amount;
+                hsn.samt += line.sgst_amount;
+            }
+        }
+    }
+
+    Ok(grouped
+        .into_values()
+        .enumerate()
+        .map(|(i, mut hsn)| {
+            hsn.num = i + 1;
+            hsn.qty = round2(hsn.qty);
+            hsn.txval = round2(hsn.txval);
+            hsn.iamt = round2(hsn.iamt);
+            hsn.camt = round2(hsn.camt);
+            hsn.samt = round2(hsn.samt);
+            hsn
+        })
+        .collect())
+}
+
+// Taxable value of the company's issued invoices in the financial year before `date`
+fn previous_year_turnover(
+    conn: &Connection,
+    company_id: i64,
+    date: NaiveDate,
+) -> Result<f64, AppError> {
+    let (from, to) = financial_years::fy_bounds(financial_years::fy_start_year(date) - 1)?;
+    conn.query_row(
+        "SELECT COALESCE(SUM(taxable_value), 0) FROM invoices
+         WHERE company_id = ?1 AND deleted_at IS NULL AND status = 'issued'
+           AND invoice_date BETWEEN ?2 AND ?3",
+        params![
+            company_id,
+            from.format(INVOICE_DATE_FORMAT).to_string(),
+            to.format(INVOICE_DATE_FORMAT).to_string()
+        ],
+        |row| row.get(0),
+    )
+    .map_err(AppError::from)
+}
+
+pub fn required_hsn_digits(annual_turnover: f64) -> usize {
+    if annual_turnover > SIX_DIGIT_HSN_TURNOVER {
+        6
+    } else {
+        4
+    }
+}
+
+fn hsn_issues(entries: &[ReturnInvoice], required_digits: usize) -> Vec<HsnIssue> {
+    let mut issues = Vec::new();
+    for entry in entries {
+        let invoice = &entry.invoice;
+        if entry.lines.is_empty() {
+            issues.push(HsnIssue {
+                invoice_id: invoice.id.unwrap_or_default(),
+                invoice_number: invoice.invoice_number.clone(),
+                line_no: None,
+                hsn_code: None,
+                message: "Invoice has no line items, so it has no HSN code".to_string(),
+            });
+        }
+        for line in &entry.lines {
+            let code = line.hsn_code.trim();
+            let message = if !code.chars().all(|c| c.is_ascii_digit()) || code.len() > 8 {
+                "HSN/SAC code must be up to 8 digits".to_string()
+            } else if code.len() < required_digits {
+                format!("HSN/SAC code must have at least {} digits", required_digits)
+            } else if line.uqc.is_none() && !code.starts_with("99") {
+                "Goods line has no unit of measure, so its quantity is reported as NOS".to_string()
+            } else {
+                continue;
+            };
+            issues.push(HsnIssue {
+                invoice_id: invoice.id.unwrap_or_default(),
+                invoice_number: invoice.invoice_number.clone(),
+                line_no: Some(line.line_no),
+                hsn_code: Some(code.to_string()),
+                message,
+            });
+        }
+    }
+    issues
+}
+
+// Splits an invoice number into its series prefix and running number, e.g. INV/25-26/0042
+fn split_series(invoice_number: &str) -> (String, Option<u64>) {
+    let digits_start = invoice_number
+        .char_indices()
+        .rev()
+        .take_while(|(_, c)| c.is_ascii_digit())
+        .last()
+        .map(|(i, _)| i);
+    match digits_start {
+        Some(i) => (
+            invoice_number[..i].to_string(),
+            invoice_number[i..].parse().ok(),
+        ),
+        None => (invoice_number.to_string(), None),
+    }
+}
+
+// Running numbers skipped between consecutive documents of a series, sorted by running number
+fn missing_numbers(
+    prefix: &str,
+    numbers: &[(Option<u64>, &str, InvoiceStatus)],
+) -> (u64, Vec<String>) {
+    let running: Vec<u64> = numbers.iter().filter_map(|(running, _, _)| *running).collect();
+    // Missing numbers are padded like the last one in the series
+    let width = numbers.last().map_or(0, |(_, number, _)| number.len() - prefix.len());
+    let mut count = 0;
+    let mut missing = Vec::new();
+    for pair in running.windows(2) {
+        let (after, before) = (pair[0] + 1, pair[1]);
+        if before <= after {
+            continue;
+        }
+        count += before - after;
+        for number in after..before {
+            if missing.len() == MISSING_NUMBERS_LIMIT {
+                break;
+            }
+            missing.push(format!("{}{:0width$}", prefix, number, width = width));
+        }
+    }
+    (count, missing)
+}
+
+// Running number (when the document number ends in one), the number itself and its status
+type SeriesEntry<'a> = (Option<u64>, &'a str, InvoiceStatus);
+
+// Numbered series of one document type; drafts were never issued and are left out
+fn series_of<'a>(
+    documents: impl Iterator<Item = (&'a str, InvoiceStatus)>,
+) -> Vec<SeriesSummary> {
+    let mut series: BTreeMap<String, Vec<SeriesEntry>> = BTreeMap::new();
+    for (number, status) in documents {
+        if status == InvoiceStatus::Draft {
+            continue;
+        }
+        let (prefix, running) = split_series(number);
+        series.entry(prefix).or_default().push((running, number, status));
+    }
+
+    series
+        .into_iter()
+        .enumerate()
+        .map(|(i, (prefix, mut numbers))| {
+            numbers.sort_by(|a, b| a.0.cmp(&b.0).then_with(|| a.1.cmp(b.1)));
+            let totnum = numbers.len();
+            let cancel = numbers
+                .iter()
+                .filter(|(_, _, status)| *status == InvoiceStatus::Cancelled)
+                .count();
+            let (missing_count, missing_numbers) = missing_numbers(&prefix, &numbers);
+            SeriesSummary {
+                series: DocSeries {
+                    num: i + 1,
+                    from: numbers[0].1.to_string(),
+                    to: numbers[totnum - 1].1.to_string(),
+                    totnum,
+                    cancel,
+                    net_issue: totnum - cancel,
+                },
+                prefix,
+                missing_count,
+                missing_numbers,
+            }
+        })
+        .collect()
+}
+
+fn document_summaries(
+    invoices: &[Invoice],
+    notes: &[CreditDebitNote],
+    challans: &[DeliveryChallan],
+) -> Vec<DocumentTypeSummary> {
+    let notes_of = |note_type: NoteType| {
+        notes
+            .iter()
+            .filter(move |n| n.note_type == note_type)
+            .map(|n| (n.note_number.as_str(), n.status))
+    };
+    let challans_of = |purpose: ChallanPurpose| {
+        challans
+            .iter()
+            .filter(move |c| c.purpose == purpose)
+            .map(|c| (c.challan_number.as_str(), c.status.document_status()))
+    };
+    let kinds = [
+        (
+            1,
+            "Invoices for outward supply",
+            series_of(invoices.iter().map(|i| (i.invoice_number.as_str(), i.status))),
+        ),
+        (4, "Debit Note", series_of(notes_of(NoteType::Debit))),
+        (5, "Credit Note", series_of(notes_of(NoteType::Credit))),
+    ];
+    let challan_kinds = ChallanPurpose::ALL.into_iter().map(|purpose| {
+        let (doc_num, doc_typ) = purpose.doc_type();
+        (doc_num, doc_typ, series_of(challans_of(purpose)))
+    });
+
+    kinds
+        .into_iter()
+        .chain(challan_kinds)
+        .filter(|(_, _, series)| !series.is_empty())
+        .map(|(doc_num, doc_typ, series)| DocumentTypeSummary {
+            doc_num,
+            doc_typ: doc_typ.to_string(),
+            series,
+        })
+        .collect()
+}
+
+fn document_series(summaries: Vec<DocumentTypeSummary>) -> Option<DocIssue> {
+    let doc_det: Vec<DocType> = summaries
+        .into_iter()
+        .map(|summary| DocType {
+            doc_num: summary.doc_num,
+            doc_typ: summary.doc_typ,
+            docs: summary.series.into_iter().map(|series| series.series).collect(),
+        })
+        .collect();
+    (!doc_det.is_empty()).then_some(DocIssue { doc_det })
+}
+
+fn gap_warnings(summaries: &[DocumentTypeSummary]) -> Vec<String> {
+    summaries
+        .iter()
+        .flat_map(|summary| summary.series.iter().map(move |series| (summary, series)))
+        .filter(|(_, series)| series.missing_count > 0)
+        .map(|(summary, series)| {
+            format!(
+                "{} series {} is missing {} number(s) between {} and {}",
+                summary.doc_typ,
+                series.prefix,
+                series.missing_count,
+                series.series.from,
+                series.series.to
+            )
+        })
+        .collect()
+}
+
+// Reverse charge invoices are reported with the tax the recipient pays, which the invoice and
+// its lines record as zero supplier tax
+fn with_recipient_tax(company: &Company, invoice: &mut Invoice, lines: &mut [InvoiceLine]) {
+    if !invoice.reverse_charge {
+        return;
+    }
+    invoice.cgst_amount = invoice.rcm_cgst_amount;
+    invoice.sgst_amount = invoice.rcm_sgst_amount;
+    invoice.igst_amount = invoice.rcm_igst_amount;
+    let regime = place_of_supply::decide(
+        &company.state_code,
+        &invoice.place_of_supply,
+        invoice.supply_kind,
+    );
+    for line in lines {
+        let split = tax::tax_on(line.taxable_value, line.gst_rate, regime);
+        line.cgst_amount = split.cgst_amount;
+        line.sgst_amount = split.sgst_amount;
+        line.igst_amount = split.igst_amount;
+    }
+}
+
+fn return_invoice(
+    conn: &Connection,
+    company: &Company,
+    invoice: &Invoice,
+) -> Result<ReturnInvoice, AppError> {
+    let (ctin, registration_type): (String, String) = conn.query_row(
+        "SELECT gst_no, registration_type FROM customers WHERE id = ?1",
+        params![invoice.customer_id],
+        |row| Ok((row.get(0)?, row.get(1)?)),
+    )?;
+    // Unregistered customers are reported as B2C even if a number was left on record
+    let unregistered =
+        RegistrationType::parse(&registration_type) == Some(RegistrationType::Unregistered);
+    let ctin = if unregistered { String::new() } else { ctin };
+    let mut invoice = invoice.clone();
+    let mut lines = invoices::get_invoice_lines(conn, invoice.id.unwrap_or_default())?;
+    with_recipient_tax(company, &mut invoice, &mut lines);
+    Ok(ReturnInvoice {
+        invoice,
+        ctin: ctin.trim().to_uppercase(),
+        lines,
+    })
+}
+
+fn load_return_invoices(
+    conn: &Connection,
+    company: &Company,
+    from: NaiveDate,
+    to: NaiveDate,
+) -> Result<(Vec<Invoice>, Vec<ReturnInvoice>), String> {
+    let all = invoices::get_invoices_in_range(
+        conn,
+        company.id.unwrap_or_default(),
+        &from.format(INVOICE_DATE_FORMAT).to_string(),
+        &to.format(INVOICE_DATE_FORMAT).to_string(),
+    )?;
+
+    let issued = all
+        .iter()
+        .filter(|i| i.status == InvoiceStatus::Issued)
+        .map(|invoice| return_invoice(conn, company, invoice))
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok((all, issued))
+}
+
+fn return_note(
+    conn: &Connection,
+    company: &Company,
+    note: &CreditDebitNote,
+) -> Result<ReturnNote, AppError> {
+    // Notes take their recipient and place of supply from the original invoice
+    let source: (String, String, String, Option<String>) = conn.query_row(
+        "SELECT c.gst_no, i.place_of_supply, i.supply_kind, i.sez_mode
+             FROM invoices i
+             JOIN customers c ON c.id = i.customer_id
+             WHERE i.id = ?1",
+        params![note.invoice_id],
+        |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?)),
+    )?;
+    let (ctin, place_of_supply, supply_kind, sez_mode) = source;
+    let supply_kind = SupplyKind::parse(&supply_kind).unwrap_or_default();
+    let under_lut = sez_mode.as_deref().and_then(ExportMode::parse) == Some(ExportMode::Lut);
+    let place_of_supply = place_of_supply.trim().to_string();
+    let lines = credit_notes::get_note_lines(conn, note.id.unwrap_or_default())?;
+    Ok(ReturnNote {
+        note: note.clone(),
+        ctin: ctin.trim().to_uppercase(),
+        inv_typ: invoice_type(supply_kind, under_lut),
+        inter_state: is_inter_state(company, &place_of_supply, supply_kind),
+        place_of_supply,
+        lines,
+    })
+}
+
+fn load_return_notes(
+    conn: &Connection,
+    company: &Company,
+    from: NaiveDate,
+    to: NaiveDate,
+) -> Result<(Vec<CreditDebitNote>, Vec<ReturnNote>), String> {
+    let all = credit_notes::get_notes_in_range(
+        conn,
+        company.id.unwrap_or_default(),
+        &from.format(INVOICE_DATE_FORMAT).to_string(),
+        &to.format(INVOICE_DATE_FORMAT).to_string(),
+    )?;
+
+    let issued = all
+        .iter()
+        .filter(|n| n.status == InvoiceStatus::Issued)
+        .map(|note| return_note(conn, company, note))
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok((all, issued))
+}
+
+// Challans only feed the document issued summary; no tax is reported on them
+fn load_return_challans(
+    conn: &Connection,
+    company_id: i64,
+    from: NaiveDate,
+    to: NaiveDate,
+) -> Result<Vec<DeliveryChallan>, AppError> {
+    delivery_challans::get_challans_in_range(
+        conn,
+        company_id,
+        &from.format(INVOICE_DATE_FORMAT).to_string(),
+        &to.format(INVOICE_DATE_FORMAT).to_string(),
+    )
+}
+
+fn b2b_invoice(invoice: &Invoice, pos: String, items: Vec<ItemDetail>) -> B2bInvoice {
+    B2bInvoice {
+        inum: invoice.invoice_number.clone(),
+        idt: portal_date(&invoice.invoice_date),
+        val: round2(invoice.total_amount),
+        pos,
+        rchrg: if invoice.reverse_charge { "Y" } else { "N" }.to_string(),
+        inv_typ: invoice_type(invoice.supply_kind, exports::under_lut(invoice)).to_string(),
+        itms: numbered(items),
+    }
+}
+
+fn b2cl_invoice(invoice: &Invoice, items: Vec<ItemDetail>) -> B2clInvoice {
+    B2clInvoice {
+        inum: invoice.invoice_number.clone(),
+        idt: portal_date(&invoice.invoice_date),
+        val: round2(invoice.total_amount),
+        itms: numbered(items),
+    }
+}
+
+fn cdnr_note(entry: &ReturnNote) -> CdnrNote {
+    let note = &entry.note;
+    CdnrNote {
+        ntty: note.note_type.portal_code().to_string(),
+        nt_num: note.note_number.clone(),
+        nt_dt: portal_date(&note.note_date),
+        val: round2(note.total_amount),
+        pos: entry.place_of_supply.clone(),
+        rchrg: "N".to_string(),
+        inv_typ: entry.inv_typ.to_string(),
+        itms: numbered(note_items(&entry.lines, entry.inter_state)),
+    }
+}
+
+type AmendmentSections = (Vec<B2baParty>, Vec<B2claPlace>, Vec<CdnraParty>);
+
+// Corrections to documents from earlier, filed returns that are marked for this period. B2C
+// small and export amendments are not generated and are flagged for entry on the portal.
+fn amendment_sections(
+    conn: &Connection,
+    company: &Company,
+    period: &str,
+    warnings: &mut Vec<String>,
+) -> Result<AmendmentSections, String> {
+    let company_id = company.id.unwrap_or_default();
+    let mut b2ba: BTreeMap<String, Vec<B2baInvoice>> = BTreeMap::new();
+    let mut b2cla: BTreeMap<String, Vec<B2claInvoice>> = BTreeMap::new();
+    let mut cdnra: BTreeMap<String, Vec<CdnraNote>> = BTreeMap::new();
+
+    for amendment in amendments::get_amendments(conn, company_id, Some(period.trim()))? {
+        let oinum = amendment.original_number.clone();
+        let oidt = portal_date(&amendment.original_date);
+        match amendment.document_type {
+            AmendedDocument::Invoice => {
+                let invoice = invoices::get_invoice_by_id(conn, amendment.document_id, company_id)?
+                    .filter(|invoice| invoice.status == InvoiceStatus::Issued);
+                let Some(invoice) = invoice else {
+                    warnings.push(format!(
+                        "Amended invoice {} is no longer issued and is left out",
+                        oinum
+                    ));
+                    continue;
+                };
+                let entry = return_invoice(conn, company, &invoice)?;
+                let pos = invoice.place_of_supply.trim().to_string();
+                let inter_state = is_inter_state(company, &pos, invoice.supply_kind);
+                let items = invoice_items(&entry, inter_state);
+                if pos == EXPORT_STATE_CODE {
+                    warnings.push(format!(
+                        "Amendment to export invoice {} must be entered on the portal (EXPA)",
+                        oinum
+                    ));
+                } else if !entry.ctin.is_empty() {
+                    b2ba.entry(entry.ctin.clone()).or_default().push(B2baInvoice {
+                        oinum,
+                        oidt,
+                        inv: b2b_invoice(&invoice, pos, items),
+                    });
+                } else if inter_state && invoice.total_amount > B2CL_THRESHOLD {
+                    b2cla.entry(pos).or_default().push(B2claInvoice {
+                        oinum,
+                        oidt,
+                        inv: b2cl_invoice(&invoice, items),
+                    });
+                } else {
+                    warnings.push(format!(
+                        "Amendment to B2C invoice {} must be entered on the portal (B2CSA)",
+                        oinum
+                    ));
+                }
+            }
+            AmendedDocument::CreditDebitNote => {
+                let note = credit_notes::get_note_by_id(conn, amendment.document_id, company_id)?
+                    .filter(|note| note.status == InvoiceStatus::Issued);
+                let Some(note) = note else {
+                    warnings.push(format!(
+                        "Amended note {} is no longer issued and is left out",
+                        oinum
+                    ));
+                    continue;
+                };
+                let entry = return_note(conn, company, &note)?;
+                if entry.ctin.is_empty() {
+                    warnings.push(format!(
+                        "Amended note {} is for an unregistered customer and is not in CDNRA",
+                        oinum
+                    ));
+                    continue;
+                }
+                cdnra.entry(entry.ctin.clone()).or_default().push(CdnraNote {
+                    ont_num: oinum,
+                    ont_dt: oidt,
+                    nt: cdnr_note(&entry),
+                });
+            }
+        }
+    }
+
+    Ok((
+        b2ba.into_iter()
+            .map(|(ctin, inv)| B2baParty { ctin, inv })
+            .collect(),
+        b2cla
+            .into_iter()
+            .map(|(pos, inv)| B2claPlace { pos, inv })
+            .collect(),
+        cdnra
+            .into_iter()
+            .map(|(ctin, nt)| CdnraParty { ctin, nt })
+            .collect(),
+    ))
+}
+
+pub fn build_return(
+    conn: &Connection,
+    company_id: i64,
+    period: &str,
+) -> Result<(Gstr1Return, Vec<String>), AppError> {
+    let (from, to) = parse_period(period)?;
+    let company = companies::get_company_by_id(conn, company_id)?
+        .ok_or_else(|| AppError::not_found("Company not found"))?;
+    let (all, issued) = load_return_invoices(conn, &company, from, to)?;
+    let (all_notes, issued_notes) = load_return_notes(conn, &company, from, to)?;
+    let required_digits =
+        required_hsn_digits(previous_year_turnover(conn, company_id, from)?);
+    let mut warnings: Vec<String> = hsn_issues(&issued, required_digits)
+        .into_iter()
+        .map(|issue| match issue.line_no {
+            Some(line_no) => {
+                format!("Invoice {} line {}: {}", issue.invoice_number, line_no, issue.message)
+            }
+            None => format!("Invoice {}: {}", issue.invoice_number, issue.message),
+        })
+        .collect();
+
+    let challans = load_return_challans(conn, company_id, from, to)?;
+    let documents = document_summaries(&all, &all_notes, &challans);
+    warnings.extend(gap_warnings(&documents));
+
+    let mut b2b: BTreeMap<String, Vec<B2bInvoice>> = BTreeMap::new();
+    let mut b2cl: BTreeMap<String, Vec<B2clInvoice>> = BTreeMap::new();
+    let mut b2cs: BTreeMap<(String, String, String), B2csEntry> = BTreeMap::new();
+    let mut exports: BTreeMap<String, Vec<ExportInvoice>> = BTreeMap::new();
+    let mut b2b_entries = Vec::new();
+    let mut b2c_entries = Vec::new();
+
+    for entry in &issued {
+        let invoice = &entry.invoice;
+        let pos = invoice.place_of_supply.trim().to_string();
+        let inter_state = is_inter_state(&company, &pos, invoice.supply_kind);
+        let items = invoice_items(entry, inter_state);
+
+        if pos == EXPORT_STATE_CODE {
+            let details = invoice.export.as_ref();
+            let exp_typ = match details {
+                Some(details) => details.mode.portal_code(),
+                None if invoice.igst_amount > 0.0 => "WPAY",
+                None => "WOPAY",
+            };
+            if details.is_some_and(|details| details.shipping_bill_number.is_none()) {
+                warnings.push(format!(
+                    "Export invoice {} has no shipping bill yet; add it before filing",
+                    invoice.invoice_number
+                ));
+            }
+            exports
+                .entry(exp_typ.to_string())
+                .or_default()
+                .push(ExportInvoice {
+                    inum: invoice.invoice_number.clone(),
+                    idt: portal_date(&invoice.invoice_date),
+                    val: round2(invoice.total_amount),
+                    sbpcode: details.and_then(|details| details.port_code.clone()),
+                    sbnum: details.and_then(|details| details.shipping_bill_number.clone()),
+                    sbdt: details
+                        .and_then(|details| details.shipping_bill_date.as_deref())
+                        .map(portal_date),
+                    itms: items,
+                });
+            b2c_entries.push(entry);
+        } else if !entry.ctin.is_empty() {
+            b2b.entry(entry.ctin.clone()).or_default().push(b2b_invoice(invoice, pos, items));
+            b2b_entries.push(entry);
+        } else if inter_state && invoice.total_amount > B2CL_THRESHOLD {
+            b2cl.entry(pos).or_default().push(b2cl_invoice(invoice, items));
+            b2c_entries.push(entry);
+        } else {
+            let sply_ty = if inter_state { "INTER" } else { "INTRA" };
+            for item in items {
+                let key = (pos.clone(), format!("{:.2}", item.rt), sply_ty.to_string());
+                let summary = b2cs.entry(key).or_insert_with(|| B2csEntry {
+                    sply_ty: sply_ty.to_string(),
+                    pos: pos.clone(),
+                    typ: "OE".to_string(),
+                    rt: item.rt,
+                    txval: 0.0,
+                    iamt: inter_state.then_some(0.0),
+                    camt: (!inter_state).then_some(0.0),
+                    samt: (!inter_state).then_some(0.0),
+                    csamt: 0.0,
+                });
+                summary.txval = round2(summary.txval + item.txval);
+                let add = |total: &mut Option<f64>, value: Option<f64>| {
+                    if let (Some(total), Some(value)) = (total.as_mut(), value) {
+                        *total = round2(*total + value);
+                    }
+                };
+                add(&mut summary.iamt, item.iamt);
+                add(&mut summary.camt, item.camt);
+                add(&mut summary.samt, item.samt);
+            }
+            b2c_entries.push(entry);
+        }
+    }
+
+    let mut cdnr: BTreeMap<String, Vec<CdnrNote>> = BTreeMap::new();
+    for entry in &issued_notes {
+        let note = &entry.note;
+        if entry.ctin.is_empty() {
+            warnings.push(format!(
+                "{} {} is for an unregistered customer and is not included in CDNR",
+                note.note_type.label(),
+                note.note_number
+            ));
+            continue;
+        }
+        cdnr.entry(entry.ctin.clone()).or_default().push(cdnr_note(entry));
+    }
+    let (b2ba, b2cla, cdnra) = amendment_sections(conn, &company, period, &mut warnings)?;
+
+    let hsn_b2b = summarize_hsn(conn, &b2b_entries)?;
+    let hsn_b2c = summarize_hsn(conn, &b2c_entries)?;
+    let hsn = if hsn_b2b.is_empty() && hsn_b2c.is_empty() {
+        None
+    } else {
+        Some(HsnSection { hsn_b2b, hsn_b2c })
+    };
+
+    let data = Gstr1Return {
+        gstin: company.gst_no.trim().to_uppercase(),
+        fp: period.trim().to_string(),
+        version: GSTR1_VERSION.to_string(),
+        hash: "hash".to_string(),
+        b2b: b2b
+            .into_iter()
+            .map(|(ctin, inv)| B2bParty { ctin, inv })
+            .collect(),
+        b2cl: b2cl
+            .into_iter()
+            .map(|(pos, inv)| B2clPlace { pos, inv })
+            .collect(),
+        b2cs: b2cs.into_values().collect(),
+        cdnr: cdnr
+            .into_iter()
+            .map(|(ctin, nt)| CdnrParty { ctin, nt })
+            .collect(),
+        exp: exports
+            .into_iter()
+            .map(|(exp_typ, inv)| ExportGroup { exp_typ, inv })
+            .collect(),
+        b2ba,
+        b2cla,
+        cdnra,
+        hsn,
+        doc_issue: document_series(documents),
+    };
+    Ok((data, warnings))
+}
+
+// UIN holders are reported in B2B and CDNR under their UIN
+fn is_valid_recipient(ctin: &str) -> bool {
+    gstin::check_gstin(ctin).is_ok() || gstin::check_uin(ctin).is_ok()
+}
+
+fn is_valid_rate(rate: f64) -> bool {
+    VALID_RATES.iter().any(|valid| (valid - rate).abs() < 0.001)
+}
+
+fn is_valid_pos(pos: &str) -> bool {
+    pos.len() == 2
+        && pos.chars().all(|c| c.is_ascii_digit())
+        && matches!(pos.parse::<u32>(), Ok(1..=38 | 96 | 97 | 99))
+}
+
+fn is_two_decimal(value: f64) -> bool {
+    value.is_finite() && value >= 0.0 && (value * 100.0 - (value * 100.0).round()).abs() < 1e-6
+}
+
+fn check_item(errors: &mut Vec<String>, context: &str, item: &ItemDetail) {
+    if !is_valid_rate(item.rt) {
+        errors.push(format!("{}: rate {} is not a notified GST rate", context, item.rt));
+    }
+    let amounts = [Some(item.txval), item.iamt, item.camt, item.samt, Some(item.csamt)];
+    if amounts.into_iter().flatten().any(|amount| !is_two_decimal(amount)) {
+        errors.push(format!(
+            "{}: amounts must be non-negative with at most two decimals",
+            context
+        ));
+    }
+}
+
+// Checks the generated return against the portal schema's constraints before it is written
+pub fn validate_return(data: &Gstr1Return, from: NaiveDate, to: NaiveDate) -> Vec<String> {
+    let mut errors = Vec::new();
+
+    if gstin::check_gstin(&data.gstin).is_err() {
+        errors.push(format!("Company GSTIN {} is not valid", data.gstin));
+    }
+    if parse_period(&data.fp).is_err() {
+        errors.push(format!("Return period {} is not valid", data.fp));
+    }
+
+    let amended: HashSet<&str> = data
+        .b2ba
+        .iter()
+        .flat_map(|party| party.inv.iter().map(|amended| amended.inv.inum.as_str()))
+        .chain(data.b2cla.iter().flat_map(|place| {
+            place.inv.iter().map(|amended| amended.inv.inum.as_str())
+        }))
+        .chain(data.cdnra.iter().flat_map(|party| {
+            party.nt.iter().map(|amended| amended.nt.nt_num.as_str())
+        }))
+        .collect();
+    let mut numbers = HashSet::new();
+    let mut check_invoice = |errors: &mut Vec<String>, inum: &str, idt: &str, val: f64| {
+        if inum.is_empty() || inum.len() > 16 {
+            errors.push(format!("Document number '{}' must be 1-16 characters", inum));
+        }
+        if !numbers.insert(inum.to_string()) {
+            errors.push(format!("Document {} is reported more than once", inum));
+        }
+        match NaiveDate::parse_from_str(idt, PORTAL_DATE_FORMAT) {
+            // Amended documents keep the date from the return they were first filed in
+            Ok(date) if date > to => {
+                errors.push(format!("Document {} is dated after the return period", inum))
+            }
+            Ok(date) if date < from && !amended.contains(inum) => {
+                errors.push(format!("Document {} is dated outside the return period", inum))
+            }
+            Ok(_) => {}
+            Err(_) => errors.push(format!("Document {} has an invalid date {}", inum, idt)),
+        }
+        if !is_two_decimal(val) {
+            errors.push(format!("Document {} has an invalid value {}", inum, val));
+        }
+    };
+
+    for party in &data.b2b {
+        if !is_valid_recipient(&party.ctin) {
+            errors.push(format!("Recipient GSTIN {} is not valid", party.ctin));
+        }
+        for inv in &party.inv {
+            check_invoice(&mut errors, &inv.inum, &inv.idt, inv.val);
+            if !is_valid_pos(&inv.pos) {
+                errors.push(format!(
+                    "Invoice {}: invalid place of supply {}",
+                    inv.inum, inv.pos
+                ));
+            }
+            for item in &inv.itms {
+                check_item(&mut errors, &format!("Invoice {}", inv.inum), &item.itm_det);
+            }
+        }
+    }
+    for place in &data.b2cl {
+        if !is_valid_pos(&place.pos) {
+            errors.push(format!("B2CL: invalid place of supply {}", place.pos));
+        }
+        for inv in &place.inv {
+            check_invoice(&mut errors, &inv.inum, &inv.idt, inv.val);
+            if inv.val <= B2CL_THRESHOLD {
+                errors.push(format!("Invoice {} is below the B2CL threshold", inv.inum));
+            }
+            for item in &inv.itms {
+                check_item(&mut errors, &format!("Invoice {}", inv.inum), &item.itm_det);
+            }
+        }
+    }
+    for entry in &data.b2cs {
+        if !is_valid_pos(&entry.pos) {
+            errors.push(format!("B2CS: invalid place of supply {}", entry.pos));
+        }
+        let item = ItemDetail {
+            txval: entry.txval,
+            rt: entry.rt,
+            iamt: entry.iamt,
+            camt: entry.camt,
+            samt: entry.samt,
+            csamt: entry.csamt,
+        };
+        check_item(&mut errors, &format!("B2CS {} {}%", entry.pos, entry.rt), &item);
+    }
+    for party in &data.cdnr {
+        if !is_valid_recipient(&party.ctin) {
+            errors.push(format!("Recipient GSTIN {} is not valid", party.ctin));
+        }
+        for note in &party.nt {
+            check_invoice(&mut errors, &note.nt_num, &note.nt_dt, note.val);
+            if !is_valid_pos(&note.pos) {
+                errors.push(format!(
+                    "Note {}: invalid place of supply {}",
+                    note.nt_num, note.pos
+                ));
+            }
+            for item in &note.itms {
+                check_item(&mut errors, &format!("Note {}", note.nt_num), &item.itm_det);
+            }
+        }
+    }
+    for group in &data.exp {
+        for inv in &group.inv {
+            check_invoice(&mut errors, &inv.inum, &inv.idt, inv.val);
+            for item in &inv.itms {
+                check_item(&mut errors, &format!("Export invoice {}", inv.inum), item);
+            }
+        }
+    }
+    for party in &data.b2ba {
+        if !is_valid_recipient(&party.ctin) {
+            errors.push(format!("Recipient GSTIN {} is not valid", party.ctin));
+        }
+        for entry in &party.inv {
+            let inv = &entry.inv;
+            check_invoice(&mut errors, &inv.inum, &inv.idt, inv.val);
+            if !is_valid_pos(&inv.pos) {
+                errors.push(format!(
+                    "Amended invoice {}: invalid place of supply {}",
+                    inv.inum, inv.pos
+                ));
+            }
+            for item in &inv.itms {
+                check_item(&mut errors, &format!("Amended invoice {}", inv.inum), &item.itm_det);
+            }
+        }
+    }
+    for place in &data.b2cla {
+        if !is_valid_pos(&place.pos) {
+            errors.push(format!("B2CLA: invalid place of supply {}", place.pos));
+        }
+        for entry in &place.inv {
+            let inv = &entry.inv;
+            check_invoice(&mut errors, &inv.inum, &inv.idt, inv.val);
+            for item in &inv.itms {
+                check_item(&mut errors, &format!("Amended invoice {}", inv.inum), &item.itm_det);
+            }
+        }
+    }
+    for party in &data.cdnra {
+        if !is_valid_recipient(&party.ctin) {
+            errors.push(format!("Recipient GSTIN {} is not valid", party.ctin));
+        }
+        for entry in &party.nt {
+            let note = &entry.nt;
+            check_invoice(&mut errors, &note.nt_num, &note.nt_dt, note.val);
+            for item in &note.itms {
+                check_item(&mut errors, &format!("Amended note {}", note.nt_num), &item.itm_det);
+            }
+        }
+    }
+    if let Some(hsn) = &data.hsn {
+        for entry in hsn.hsn_b2b.iter().chain(&hsn.hsn_b2c) {
+            let code = entry.hsn_sc.as_str();
+            if code.len() < 4 || code.len() > 8 || !code.chars().all(|c| c.is_ascii_digit()) {
+                errors.push(format!("HSN summary: code '{}' must be 4-8 digits", code));
+            }
+            if !is_valid_rate(entry.rt) {
+                errors.push(format!("HSN summary: rate {} for {} is not valid", entry.rt, code));
+            }
+        }
+    }
+
+    errors
+}
+
+// Builds and validates the return, writing the portal JSON to `path` when given
+pub fn export_return(
+    conn: &Connection,
+    company_id: i64,
+    period: &str,
+    path: Option<String>,
+) -> Result<Gstr1Result, AppError> {
+    let (from, to) = parse_period(period)?;
+    let (data, warnings) = build_return(conn, company_id, period)?;
+
+    let errors = validate_return(&data, from, to);
+    if !errors.is_empty() {
+        return Err(AppError::invalid(format!(
+            "GSTR-1 data failed validation:\n{}",
+            errors.join("\n")
+        )));
+    }
+
+    if let Some(path) = &path {
+        let json = serde_json::to_string(&data).map_err(|e| e.to_string())?;
+        std::fs::write(path, json).map_err(|e| format!("Failed to write GSTR-1 file: {}", e))?;
+    }
+
+    Ok(Gstr1Result {
+        period: data.fp.clone(),
+        path,
+        b2b_invoices: data.b2b.iter().map(|p| p.inv.len()).sum(),
+        b2cl_invoices: data.b2cl.iter().map(|p| p.inv.len()).sum(),
+        b2cs_entries: data.b2cs.len(),
+        cdnr_notes: data.cdnr.iter().map(|p| p.nt.len()).sum(),
+        export_invoices: data.exp.iter().map(|g| g.inv.len()).sum(),
+        amendments: data.b2ba.iter().map(|p| p.inv.len()).sum::<usize>()
+            + data.b2cla.iter().map(|p| p.inv.len()).sum::<usize>()
+            + data.cdnra.iter().map(|p| p.nt.len()).sum::<usize>(),
+        hsn_entries: data
+            .hsn
+            .as_ref()
+            .map(|h| h.hsn_b2b.len() + h.hsn_b2c.len())
+            .unwrap_or(0),
+        warnings,
+        data,
+    })
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn generate_gstr1_json(
+    pool: State<'_, DbPool>,
+    company_id: i64,
+    period: String,
+    path: Option<String>,
+) -> Result<Gstr1Result, AppError> {
+    let conn = db::get_conn(&pool)?;
+    export_return(&conn, company_id, &period, path)
+}
+
+// GSTR-1 table 12 for the period, with every line whose HSN code would be rejected. The
+// required digits follow the previous year's turnover unless `annual_turnover` is given.
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn hsn_summary(
+    pool: State<'_, DbPool>,
+    company_id: i64,
+    period: String,
+    annual_turnover: Option<f64>,
+) -> Result<HsnSummaryReport, AppError> {
+    let (from, to) = parse_period(&period)?;
+    let conn = db::get_conn(&pool)?;
+    let company = companies::get_company_by_id(&conn, company_id)?
+        .ok_or_else(|| AppError::not_found("Company not found"))?;
+    let annual_turnover = match annual_turnover {
+        Some(turnover) if turnover.is_finite() && turnover >= 0.0 => turnover,
+        Some(_) => {
+            return Err(AppError::validation(
+                "annual_turnover",
+                "Annual turnover must be a non-negative amount",
+            ))
+        }
+        None => previous_year_turnover(&conn, company_id, from)?,
+    };
+    let required_digits = required_hsn_digits(annual_turnover);
+    let (_, issued) = load_return_invoices(&conn, &company, from, to)?;
+
+    // Same split as the return: registered recipients other than exports are B2B
+    let (b2b, b2c): (Vec<&ReturnInvoice>, Vec<&ReturnInvoice>) =
+        issued.iter().partition(|entry| {
+            !entry.ctin.is_empty() && entry.invoice.place_of_supply.trim() != EXPORT_STATE_CODE
+        });
+    Ok(HsnSummaryReport {
+        period: period.trim().to_string(),
+        annual_turnover: round2(annual_turnover),
+        required_digits,
+        hsn_b2b: summarize_hsn(&conn, &b2b)?,
+        hsn_b2c: summarize_hsn(&conn, &b2c)?,
+        issues: hsn_issues(&issued, required_digits),
+    })
+}
+
+// Documents issued in the period (GSTR-1 table 13) per numbering series, with the gaps in each
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn document_summary(
+    pool: State<'_, DbPool>,
+    company_id: i64,
+    period: String,
+) -> Result<DocumentSummaryReport, AppError> {
+    let (from, to) = parse_period(&period)?;
+    let conn = db::get_conn(&pool)?;
+    let company = companies::get_company_by_id(&conn, company_id)?
+        .ok_or_else(|| AppError::not_found("Company not found"))?;
+    let (invoices, _) = load_return_invoices(&conn, &company, from, to)?;
+    let (notes, _) = load_return_notes(&conn, &company, from, to)?;
+    let challans = load_return_challans(&conn, company_id, from, to)?;
+    Ok(DocumentSummaryReport {
+        period: period.trim().to_string(),
+        documents: document_summaries(&invoices, &notes, &challans),
+    })
+}
@@ -0,0 +1,634 @@
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::Path;
+
+use calamine::Data;
+use chrono::NaiveDate;
+use rusqlite::{params, Connection, Row};
+use serde::{Deserialize, Serialize};
+use tauri::State;
+
+use crate::companies;
+use crate::db::{self, DbPool};
+use crate::error::AppError;
+use crate::gstin;
+use crate::gstr1::parse_period;
+use crate::invoices::{round2, INVOICE_DATE_FORMAT};
+use crate::purchases::{self, Purchase};
+use crate::sales_import::{cell_amount, cell_date, cell_text, load_sheet};
+
+// Books and returns routinely differ by rounding; larger gaps are reported as mismatches
+const DEFAULT_TOLERANCE: f64 = 1.0;
+const PORTAL_DATE_FORMAT: &str = "%d-%m-%Y";
+// Sheet holding the B2B invoices in the portal's GSTR-2B Excel download
+const B2B_SHEET: &str = "B2B";
+
+// A supplier invoice as reported to the company in GSTR-2B
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Gstr2bEntry {
+    pub id: Option<i64>,
+    pub company_id: i64,
+    pub period: String,
+    pub supplier_gstin: String,
+    pub supplier_name: String,
+    pub invoice_number: String,
+    pub invoice_date: String,
+    pub invoice_type: String,
+    pub place_of_supply: String,
+    pub reverse_charge: bool,
+    pub taxable_value: f64,
+    pub igst_amount: f64,
+    pub cgst_amount: f64,
+    pub sgst_amount: f64,
+    pub cess_amount: f64,
+    pub total_amount: f64,
+    pub itc_available: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Gstr2bImportReport {
+    pub period: String,
+    pub imported: usize,
+    // Entries from an earlier import of the same period, which the new file replaces
+    pub replaced: usize,
+    pub skipped: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum MatchStatus {
+    Matched,
+    Mismatched,
+    // In GSTR-2B but not recorded as a purchase
+    MissingInBooks,
+    // Recorded as a purchase but the supplier has not reported it
+    MissingIn2b,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ReconciliationRow {
+    pub status: MatchStatus,
+    pub supplier_gstin: String,
+    pub supplier_name: String,
+    pub invoice_number: String,
+    pub purchase: Option<Purchase>,
+    pub portal: Option<Gstr2bEntry>,
+    pub differences: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Default)]
+pub struct ReconciliationSummary {
+    pub matched: usize,
+    pub mismatched: usize,
+    pub missing_in_books: usize,
+    pub missing_in_2b: usize,
+    pub tax_in_books: f64,
+    pub tax_in_2b: f64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Gstr2bReconciliation {
+    pub period: String,
+    pub tolerance: f64,
+    pub summary: ReconciliationSummary,
+    pub rows: Vec<ReconciliationRow>,
+}
+
+// Layout of the portal's GSTR-2B JSON; only the B2B section is read
+#[derive(Deserialize)]
+struct PortalReturn {
+    gstin: Option<String>,
+    rtnprd: Option<String>,
+    #[serde(default)]
+    docdata: PortalDocuments,
+}
+
+#[derive(Deserialize, Default)]
+struct PortalDocuments {
+    #[serde(default)]
+    b2b: Vec<PortalSupplier>,
+}
+
+#[derive(Deserialize)]
+struct PortalSupplier {
+    ctin: String,
+    #[serde(default)]
+    trdnm: String,
+    #[serde(default)]
+    inv: Vec<PortalInvoice>,
+}
+
+#[derive(Deserialize)]
+struct PortalInvoice {
+    inum: String,
+    dt: String,
+    #[serde(default)]
+    val: f64,
+    #[serde(default)]
+    typ: String,
+    #[serde(default)]
+    pos: String,
+    #[serde(default)]
+    rev: String,
+    #[serde(default)]
+    itcavl: String,
+    // Invoice-level totals; older files only carry them per item
+    txval: Option<f64>,
+    igst: Option<f64>,
+    cgst: Option<f64>,
+    sgst: Option<f64>,
+    cess: Option<f64>,
+    #[serde(default)]
+    items: Vec<PortalItem>,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default)]
+struct PortalItem {
+    txval: f64,
+    igst: f64,
+    cgst: f64,
+    sgst: f64,
+    cess: f64,
+}
+
+struct ParsedFile {
+    entries: Vec<Gstr2bEntry>,
+    skipped: Vec<String>,
+}
+
+const SELECT_ENTRY: &str = "
+    SELECT id, company_id, period, supplier_gstin, supplier_name, invoice_number, invoice_date,
+           invoice_type, place_of_supply, reverse_charge, taxable_value, igst_amount, cgst_amount,
+           sgst_amount, cess_amount, total_amount, itc_available
+    FROM gstr2b_entries";
+
+fn entry_from_row(row: &Row) -> rusqlite::Result<Gstr2bEntry> {
+    Ok(Gstr2bEntry {
+        id: row.get("id")?,
+        company_id: row.get("company_id")?,
+        period: row.get("period")?,
+        supplier_gstin: row.get("supplier_gstin")?,
+        supplier_name: row.get("supplier_name")?,
+        invoice_number: row.get("invoice_number")?,
+        invoice_date: row.get("invoice_date")?,
+        invoice_type: row.get("invoice_type")?,
+        place_of_supply: row.get("place_of_supply")?,
+        reverse_charge: row.get("reverse_charge")?,
+        taxable_value: row.get("taxable_value")?,
+        igst_amount: row.get("igst_amount")?,
+        cgst_amount: row.get("cgst_amount")?,
+        sgst_amount: row.get("sgst_amount")?,
+        cess_amount: row.get("cess_amount")?,
+        total_amount: row.get("total_amount")?,
+        itc_available: row.get("itc_available")?,
+    })
+}
+
+// Entries for one period, or for every imported period when none is given
+fn get_entries(
+    conn: &Connection,
+    company_id: i64,
+    period: Option<&str>,
+) -> Result<Vec<Gstr2bEntry>, AppError> {
+    let mut stmt = conn.prepare(&format!(
+        "{} WHERE company_id = ?1 AND (?2 IS NULL OR period = ?2)
+             ORDER BY supplier_gstin, invoice_date, invoice_number",
+        SELECT_ENTRY
+    ))?;
+    let entries = stmt
+        .query_map(params![company_id, period], entry_from_row)?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(entries)
+}
+
+fn yes(value: &str) -> bool {
+    matches!(value.trim().to_ascii_uppercase().as_str(), "Y" | "YES")
+}
+
+fn parse_json(
+    path: &str,
+    company_id: i64,
+    period: &str,
+    company_gstin: &str,
+) -> Result<ParsedFile, AppError> {
+    let text = fs::read_to_string(path).map_err(|e| format!("Failed to read file: {}", e))?;
+    let mut value: serde_json::Value = serde_json::from_str(&text).map_err(|e| {
+        AppError::validation("path", format!("Not a valid GSTR-2B JSON file: {}", e))
+    })?;
+    // The portal wraps the return in "data"; offline tool exports do not
+    if let Some(data) = value.get_mut("data") {
+        value = data.take();
+    }
+    let file: PortalReturn = serde_json::from_value(value).map_err(|e| {
+        AppError::validation("path", format!("Not a valid GSTR-2B JSON file: {}", e))
+    })?;
+
+    if let Some(rtnprd) = file.rtnprd.as_deref().map(str::trim) {
+        if rtnprd != period {
+            return Err(AppError::validation(
+                "period",
+                format!("This file is for period {}, not {}", rtnprd, period),
+            ));
+        }
+    }
+    let company_gstin = company_gstin.trim().to_uppercase();
+    if let Some(file_gstin) = file.gstin.as_deref().map(|g| g.trim().to_uppercase()) {
+        if !company_gstin.is_empty() && file_gstin != company_gstin {
+            return Err(AppError::validation(
+                "path",
+                format!("This file belongs to GSTIN {}", file_gstin),
+            ));
+        }
+    }
+
+    let mut parsed = ParsedFile {
+        entries: Vec::new(),
+        skipped: Vec::new(),
+    };
+    for supplier in file.docdata.b2b {
+        let supplier_gstin = supplier.ctin.trim().to_uppercase();
+        for invoice in supplier.inv {
+            let Ok(date) = NaiveDate::parse_from_str(invoice.dt.trim(), PORTAL_DATE_FORMAT) else {
+                parsed.skipped.push(format!(
+                    "Invoice {} from {}: invalid date '{}'",
+                    invoice.inum, supplier_gstin, invoice.dt
+                ));
+                continue;
+            };
+            let item_total = |amount: fn(&PortalItem) -> f64| {
+                invoice.items.iter().map(amount).sum::<f64>()
+            };
+            parsed.entries.push(Gstr2bEntry {
+                id: None,
+                company_id,
+                period: period.to_string(),
+                supplier_gstin: supplier_gstin.clone(),
+                supplier_name: supplier.trdnm.trim().to_string(),
+                invoice_number: invoice.inum.trim().to_string(),
+                invoice_date: date.format(INVOICE_DATE_FORMAT).to_string(),
+                invoice_type: invoice.typ.trim().to_string(),
+                place_of_supply: invoice.pos.trim().to_string(),
+                reverse_charge: yes(&invoice.rev),
+                taxable_value: round2(invoice.txval.unwrap_or_else(|| item_total(|i| i.txval))),
+                igst_amount: round2(invoice.igst.unwrap_or_else(|| item_total(|i| i.igst))),
+                cgst_amount: round2(invoice.cgst.unwrap_or_else(|| item_total(|i| i.cgst))),
+                sgst_amount: round2(invoice.sgst.unwrap_or_else(|| item_total(|i| i.sgst))),
+                cess_amount: round2(invoice.cess.unwrap_or_else(|| item_total(|i| i.cess))),
+                total_amount: round2(invoice.val),
+                // Missing means the portal did not restrict it
+                itc_available: invoice.itcavl.trim().is_empty() || yes(&invoice.itcavl),
+            });
+        }
+    }
+    Ok(parsed)
+}
+
+fn parse_excel(path: &str, company_id: i64, period: &str) -> Result<ParsedFile, AppError> {
+    let (_, range) = load_sheet(path, Some(B2B_SHEET))?;
+    let rows: Vec<&[Data]> = range.rows().collect();
+    let header_index = rows
+        .iter()
+        .position(|row| {
+            row.iter().any(|cell| {
+                cell_text(cell)
+                    .to_lowercase()
+                    .starts_with("gstin of supplier")
+            })
+        })
+        .ok_or_else(|| {
+            AppError::validation("path", "The B2B sheet has no 'GSTIN of supplier' header")
+        })?;
+
+    // Tax columns sit under a merged "Tax Amount" heading, so headers span two rows
+    let header_text = |row: usize, column: usize| {
+        rows.get(row)
+            .and_then(|cells| cells.get(column))
+            .map(cell_text)
+            .unwrap_or_default()
+    };
+    let width = rows[header_index].len();
+    let headers: Vec<String> = (0..width)
+        .map(|column| {
+            format!(
+                "{} {}",
+                header_text(header_index, column),
+                header_text(header_index + 1, column)
+            )
+            .to_lowercase()
+        })
+        .collect();
+    let find = |label: &str| headers.iter().position(|header| header.contains(label));
+    let required = |label: &str| {
+        find(label).ok_or_else(|| {
+            AppError::validation("path", format!("The B2B sheet has no '{}' column", label))
+        })
+    };
+    let gstin_column = required("gstin of supplier")?;
+    let number_column = required("invoice number")?;
+    let date_column = required("invoice date")?;
+    let taxable_column = required("taxable value")?;
+    let name_column = find("trade/legal name");
+    let type_column = find("invoice type");
+    let value_column = find("invoice value");
+    let pos_column = find("place of supply");
+    let reverse_charge_column = find("reverse charge");
+    let igst_column = find("integrated tax");
+    let cgst_column = find("central tax");
+    let sgst_column = find("state/ut tax");
+    let cess_column = find("cess");
+    let itc_column = find("itc availability");
+
+    let mut parsed = ParsedFile {
+        entries: Vec::new(),
+        skipped: Vec::new(),
+    };
+    let first_row = range.start().map(|(row, _)| row as usize + 1).unwrap_or(1);
+    for (index, row) in rows.iter().enumerate().skip(header_index + 1) {
+        let cell = |column: Option<usize>| column.and_then(|c| row.get(c)).unwrap_or(&Data::Empty);
+        let text = |column: Option<usize>| cell_text(cell(column));
+        let supplier_gstin = text(Some(gstin_column)).to_uppercase();
+        // Blank rows and the second header row
+        if supplier_gstin.is_empty() || supplier_gstin.starts_with("GSTIN") {
+            continue;
+        }
+        let row_number = first_row + index;
+        let amount = |column: Option<usize>, label: &str| cell_amount(cell(column), label);
+        let entry = gstin::check_gstin(&supplier_gstin)
+            .map_err(|e| format!("supplier GSTIN {}", e))
+            .and_then(|_| {
+                Ok(Gstr2bEntry {
+                    id: None,
+                    company_id,
+                    period: period.to_string(),
+                    supplier_gstin: supplier_gstin.clone(),
+                    supplier_name: text(name_column),
+                    invoice_number: text(Some(number_column)),
+                    invoice_date: cell_date(cell(Some(date_column)))?
+                        .format(INVOICE_DATE_FORMAT)
+                        .to_string(),
+                    invoice_type: text(type_column),
+                    place_of_supply: text(pos_column),
+                    reverse_charge: yes(&text(reverse_charge_column)),
+                    taxable_value: round2(amount(Some(taxable_column), "Taxable value")?),
+                    igst_amount: round2(amount(igst_column, "Integrated tax")?),
+                    cgst_amount: round2(amount(cgst_column, "Central tax")?),
+                    sgst_amount: round2(amount(sgst_column, "State/UT tax")?),
+                    cess_amount: round2(amount(cess_column, "Cess")?),
+                    total_amount: round2(amount(value_column, "Invoice value")?),
+                    itc_available: itc_column.is_none() || yes(&text(itc_column)),
+                })
+            });
+        match entry {
+            Ok(entry) if entry.invoice_number.is_empty() => {
+                parsed.skipped.push(format!("Row {}: invoice number is missing", row_number))
+            }
+            Ok(entry) => parsed.entries.push(entry),
+            Err(e) => parsed.skipped.push(format!("Row {}: {}", row_number, e)),
+        }
+    }
+    Ok(parsed)
+}
+
+// Suppliers and books write the same number with different separators and case
+fn match_key(gstin: &str, invoice_number: &str) -> (String, String) {
+    let number = invoice_number
+        .chars()
+        .filter(char::is_ascii_alphanumeric)
+        .collect::<String>()
+        .to_uppercase();
+    (gstin.trim().to_uppercase(), number)
+}
+
+fn tax_of(igst: f64, cgst: f64, sgst: f64, cess: f64) -> f64 {
+    igst + cgst + sgst + cess
+}
+
+fn compare(purchase: &Purchase, entry: &Gstr2bEntry, tolerance: f64) -> Vec<String> {
+    let mut differences = Vec::new();
+    if purchase.invoice_date != entry.invoice_date {
+        differences.push(format!(
+            "Invoice date: books {}, GSTR-2B {}",
+            purchase.invoice_date, entry.invoice_date
+        ));
+    }
+    let amounts = [
+        ("Taxable value", purchase.taxable_value, entry.taxable_value),
+        ("IGST", purchase.igst_amount, entry.igst_amount),
+        ("CGST", purchase.cgst_amount, entry.cgst_amount),
+        ("SGST", purchase.sgst_amount, entry.sgst_amount),
+        ("Cess", purchase.cess_amount, entry.cess_amount),
+        ("Invoice value", purchase.total_amount, entry.total_amount),
+    ];
+    for (label, books, portal) in amounts {
+        if (books - portal).abs() > tolerance {
+            differences.push(format!(
+                "{}: books {:.2}, GSTR-2B {:.2}",
+                label, books, portal
+            ));
+        }
+    }
+    if purchase.reverse_charge != entry.reverse_charge {
+        differences.push("Reverse charge differs".to_string());
+    }
+    differences
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn import_gstr2b(
+    pool: State<'_, DbPool>,
+    company_id: i64,
+    period: String,
+    path: String,
+) -> Result<Gstr2bImportReport, AppError> {
+    parse_period(&period)?;
+    let period = period.trim().to_string();
+    let mut conn = db::get_conn(&pool)?;
+    let company = companies::get_company_by_id(&conn, company_id)?
+        .ok_or_else(|| AppError::not_found("Company not found"))?;
+
+    let is_json = Path::new(&path)
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .is_some_and(|extension| extension.eq_ignore_ascii_case("json"));
+    let parsed = if is_json {
+        parse_json(&path, company_id, &period, &company.gst_no)?
+    } else {
+        parse_excel(&path, company_id, &period)?
+    };
+    if parsed.entries.is_empty() {
+        return Err(AppError::invalid("The file has no B2B invoices to import"));
+    }
+
+    let tx = conn.transaction()?;
+    let replaced = tx.execute(
+        "DELETE FROM gstr2b_entries WHERE company_id = ?1 AND period = ?2",
+        params![company_id, period],
+    )?;
+    for entry in &parsed.entries {
+        tx.execute(
+            "INSERT INTO gstr2b_entries (company_id, period, supplier_gstin, supplier_name, invoice_number, invoice_date, invoice_type, place_of_supply, reverse_charge, taxable_value, igst_amount, cgst_amount, sgst_amount, cess_amount, total_amount, itc_available)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16)",
+            params![
+                entry.company_id,
+                entry.period,
+                entry.supplier_gstin,
+                entry.supplier_name,
+                entry.invoice_number,
+                entry.invoice_date,
+                entry.invoice_type,
+                entry.place_of_supply,
+                entry.reverse_charge,
+                entry.taxable_value,
+                entry.igst_amount,
+                entry.cgst_amount,
+                entry.sgst_amount,
+                entry.cess_amount,
+                entry.total_amount,
+                entry.itc_available
+            ],
+        )?;
+    }
+    tx.commit()?;
+
+    Ok(Gstr2bImportReport {
+        period,
+        imported: parsed.entries.len(),
+        replaced,
+        skipped: parsed.skipped,
+    })
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn list_gstr2b_entries(
+    pool: State<'_, DbPool>,
+    company_id: i64,
+    period: String,
+) -> Result<Vec<Gstr2bEntry>, AppError> {
+    parse_period(&period)?;
+    let conn = db::get_conn(&pool)?;
+    get_entries(&conn, company_id, Some(period.trim()))
+}
+
+// Matches the period's GSTR-2B against the purchase books by supplier GSTIN and invoice number.
+// Purchases dated in the period are missing from 2B only if no imported period reports them,
+// since suppliers often file a late invoice in a later month's return.
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn reconcile_gstr2b(
+    pool: State<'_, DbPool>,
+    company_id: i64,
+    period: String,
+    tolerance: Option<f64>,
+) -> Result<Gstr2bReconciliation, AppError> {
+    let (from, to) = parse_period(&period)?;
+    let period = period.trim().to_string();
+    let tolerance = match tolerance {
+        Some(tolerance) if tolerance.is_finite() && tolerance >= 0.0 => tolerance,
+        Some(_) => {
+            return Err(AppError::validation(
+                "tolerance",
+                "Tolerance must be a non-negative amount",
+            ))
+        }
+        None => DEFAULT_TOLERANCE,
+    };
+    let conn = db::get_conn(&pool)?;
+    let entries = get_entries(&conn, company_id, Some(&period))?;
+    if entries.is_empty() {
+        return Err(AppError::not_found(format!(
+            "No GSTR-2B has been imported for {}",
+            period
+        )));
+    }
+    let reported: HashSet<(String, String)> = get_entries(&conn, company_id, None)?
+        .iter()
+        .map(|entry| match_key(&entry.supplier_gstin, &entry.invoice_number))
+        .collect();
+    let purchases = purchases::get_purchases(&conn, company_id, None, None)?;
+    let mut books: HashMap<(String, String), &Purchase> = purchases
+        .iter()
+        .map(|purchase| {
+            (
+                match_key(&purchase.supplier_gstin, &purchase.invoice_number),
+                purchase,
+            )
+        })
+        .collect();
+
+    let mut summary = ReconciliationSummary::default();
+    let mut rows = Vec::new();
+    for entry in entries {
+        summary.tax_in_2b += tax_of(
+            entry.igst_amount,
+            entry.cgst_amount,
+            entry.sgst_amount,
+            entry.cess_amount,
+        );
+        let purchase = books.remove(&match_key(&entry.supplier_gstin, &entry.invoice_number));
+        let differences = purchase
+            .map(|purchase| compare(purchase, &entry, tolerance))
+            .unwrap_or_default();
+        let status = match purchase {
+            None => MatchStatus::MissingInBooks,
+            Some(_) if differences.is_empty() => MatchStatus::Matched,
+            Some(_) => MatchStatus::Mismatched,
+        };
+        rows.push(ReconciliationRow {
+            status,
+            supplier_gstin: entry.supplier_gstin.clone(),
+            supplier_name: entry.supplier_name.clone(),
+            invoice_number: entry.invoice_number.clone(),
+            purchase: purchase.cloned(),
+            portal: Some(entry),
+            differences,
+        });
+    }
+
+    let from = from.format(INVOICE_DATE_FORMAT).to_string();
+    let to = to.format(INVOICE_DATE_FORMAT).to_string();
+    for purchase in &purchases {
+        let key = match_key(&purchase.supplier_gstin, &purchase.invoice_number);
+        let in_period = purchase.invoice_date >= from && purchase.invoice_date <= to;
+        if !in_period || reported.contains(&key) {
+            continue;
+        }
+        rows.push(ReconciliationRow {
+            status: MatchStatus::MissingIn2b,
+            supplier_gstin: purchase.supplier_gstin.clone(),
+            supplier_name: purchase.supplier_name.clone(),
+            invoice_number: purchase.invoice_number.clone(),
+            purchase: Some(purchase.clone()),
+            portal: None,
+            differences: Vec::new(),
+        });
+    }
+
+    for row in &rows {
+        match row.status {
+            MatchStatus::Matched => summary.matched += 1,
+            MatchStatus::Mismatched => summary.mismatched += 1,
+            MatchStatus::MissingInBooks => summary.missing_in_books += 1,
+            MatchStatus::MissingIn2b => summary.missing_in_2b += 1,
+        }
+        if let Some(purchase) = &row.purchase {
+            summary.tax_in_books += tax_of(
+                purchase.igst_amount,
+                purchase.cgst_amount,
+                purchase.sgst_amount,
+                purchase.cess_amount,
+            );
+        }
+    }
+    summary.tax_in_books = round2(summary.tax_in_books);
+    summary.tax_in_2b = round2(summary.tax_in_2b);
+
+    Ok(Gstr2bReconciliation {
+        period,
+        tolerance,
+        summary,
+        rows,
+    })
+}
@@ -0,0 +1,169 @@
+use rusqlite::{params, Connection, OptionalExtension, Row};
+use serde::{Deserialize, Serialize};
+use tauri::State;
+
+use crate::db::{self, DbPool};
+use crate::error::AppError;
+use crate::invoices::InvoiceLineInput;
+
+// HSN/SAC master data model
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct HsnCode {
+    pub code: String,
+    pub description: String,
+    pub chapter: String,
+    pub chapter_description: Option<String>,
+    pub gst_rate: Option<f64>,
+    pub is_service: bool,
+}
+
+// Two-digit entries are chapter headings and carry no rate of their own.
+// Rates are the standard GST rates at the time of seeding; users should verify them against current notifications.
+const HSN_SEED: &[(&str, &str, Option<f64>)] = &[
+    ("04", "Dairy produce; birds' eggs; natural honey", None),
+    ("0401", "Milk and cream, not concentrated nor sweetened", Some(0.0)),
+    ("0402", "Milk and cream, concentrated or sweetened", Some(5.0)),
+    ("09", "Coffee, tea, mate and spices", None),
+    ("0901", "Coffee, whether or not roasted", Some(5.0)),
+    ("0902", "Tea, whether or not flavoured", Some(5.0)),
+    ("17", "Sugars and sugar confectionery", None),
+    ("1701", "Cane or beet sugar and chemically pure sucrose", Some(5.0)),
+    ("25", "Salt; sulphur; earths and stone; plastering materials, lime and cement", None),
+    ("2523", "Portland cement, aluminous cement and similar hydraulic cements", Some(18.0)),
+    ("33", "Essential oils and resinoids; perfumery, cosmetic or toilet preparations", None),
+    ("3304", "Beauty or make-up preparations", Some(18.0)),
+    ("34", "Soap, organic surface-active agents, washing and lubricating preparations", None),
+    ("3401", "Soap; organic surface-active products for use as soap", Some(5.0)),
+    ("39", "Plastics and articles thereof", None),
+    ("3923", "Articles for the conveyance or packing of goods, of plastics", Some(18.0)),
+    ("40", "Rubber and articles thereof", None),
+    ("4011", "New pneumatic tyres, of rubber", Some(18.0)),
+    ("72", "Iron and steel", None),
+    ("7208", "Flat-rolled products of iron or non-alloy steel, hot-rolled", Some(18.0)),
+    ("73", "Articles of iron or steel", None),
+    ("7308", "Structures and parts of structures, of iron or steel", Some(18.0)),
+    ("7318", "Screws, bolts, nuts, washers and similar articles, of iron or steel", Some(18.0)),
+    ("84", "Nuclear reactors, boilers, machinery and mechanical appliances; parts thereof", None),
+    ("8415", "Air conditioning machines", Some(18.0)),
+    ("8471", "Automatic data processing machines and units thereof", Some(18.0)),
+    ("85", "Electrical machinery and equipment and parts thereof", None),
+    ("8517", "Telephone sets and other apparatus for transmission of voice or data", Some(18.0)),
+    ("87", "Vehicles other than railway or tramway rolling stock, and parts thereof", None),
+    ("8708", "Parts and accessories of motor vehicles", Some(18.0)),
+    ("94", "Furniture; bedding, mattresses; lamps and lighting fittings", None),
+    ("9403", "Other furniture and parts thereof", Some(18.0)),
+    ("99", "Services", None),
+    ("9954", "Construction services", Some(18.0)),
+    ("9963", "Accommodation, food and beverage services", Some(5.0)),
+    ("9971", "Financial and related services", Some(18.0)),
+    ("9983", "Other professional, technical and business services", Some(18.0)),
+    ("998314", "Information technology design and development services", Some(18.0)),
+    ("9985", "Support services", Some(18.0)),
+    ("9987", "Maintenance, repair and installation services", Some(18.0)),
+    ("9997", "Other services", Some(18.0)),
+];
+
+const SELECT_HSN: &str = "
+    SELECT h.code, h.description, h.gst_rate, h.is_service, ch.description AS chapter_description
+    FROM hsn_codes h
+    LEFT JOIN hsn_codes ch ON ch.code = substr(h.code, 1, 2)";
+
+fn hsn_from_row(row: &Row) -> rusqlite::Result<HsnCode> {
+    let code: String = row.get("code")?;
+    Ok(HsnCode {
+        chapter: code.chars().take(2).collect(),
+        code,
+        description: row.get("description")?,
+        chapter_description: row.get("chapter_description")?,
+        gst_rate: row.get("gst_rate")?,
+        is_service: row.get("is_service")?,
+    })
+}
+
+// Used by the migration that creates the master table
+pub fn seed_hsn_codes(conn: &Connection) -> Result<(), String> {
+    let mut stmt = conn
+        .prepare(
+            "INSERT OR IGNORE INTO hsn_codes (code, description, gst_rate, is_service)
+             VALUES (?1, ?2, ?3, ?4)",
+        )
+        .map_err(|e| e.to_string())?;
+    for (code, description, gst_rate) in HSN_SEED {
+        // SAC codes all live under chapter 99
+        let is_service = code.starts_with("99");
+        stmt.execute(params![code, description, gst_rate, is_service])
+            .map_err(|e| e.to_string())?;
+    }
+    Ok(())
+}
+
+// Finds the most specific master entry with a rate for a line's HSN code
+pub fn rate_for_code(conn: &Connection, code: &str) -> Result<Option<f64>, String> {
+    conn.query_row(
+        "SELECT gst_rate FROM hsn_codes
+         WHERE gst_rate IS NOT NULL AND ?1 LIKE code || '%'
+         ORDER BY length(code) DESC LIMIT 1",
+        params![code.trim()],
+        |row| row.get(0),
+    )
+    .optional()
+    .map_err(|e| e.to_string())
+}
+
+// Rate mismatches are warnings rather than errors: the master may lag behind notifications
+pub fn rate_warnings(conn: &Connection, lines: &[InvoiceLineInput]) -> Result<Vec<String>, String> {
+    let mut warnings = Vec::new();
+    for (index, line) in lines.iter().enumerate() {
+        match rate_for_code(conn, &line.hsn_code)? {
+            Some(rate) if (rate - line.gst_rate).abs() > f64::EPSILON => warnings.push(format!(
+                "Line {}: GST rate {}% differs from the HSN master rate of {}% for {}",
+                index + 1,
+                line.gst_rate,
+                rate,
+                line.hsn_code.trim()
+            )),
+            Some(_) => {}
+            None => warnings.push(format!(
+                "Line {}: HSN/SAC code {} is not in the HSN master",
+                index + 1,
+                line.hsn_code.trim()
+            )),
+        }
+    }
+    Ok(warnings)
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn lookup_hsn(
+    pool: State<'_, DbPool>,
+    code_or_description: String,
+) -> Result<Vec<HsnCode>, AppError> {
+    let query = code_or_description.trim();
+    if query.is_empty() {
+        return Err(AppError::validation(
+            "code_or_description",
+            "Enter an HSN/SAC code or description to search",
+        ));
+    }
+
+    let conn = db::get_conn(&pool)?;
+    let sql = if query.chars().all(|c| c.is_ascii_digit()) {
+        // Match both broader headings of the entered code and more specific sub-codes
+        format!(
+            "{} WHERE length(h.code) > 2 AND (h.code LIKE ?1 || '%' OR ?1 LIKE h.code || '%')
+             ORDER BY length(h.code) DESC, h.code LIMIT 50",
+            SELECT_HSN
+        )
+    } else {
+        format!(
+            "{} WHERE length(h.code) > 2 AND h.description LIKE '%' || ?1 || '%'
+             ORDER BY h.code LIMIT 50",
+            SELECT_HSN
+        )
+    };
+
+    let mut stmt = conn.prepare(&sql)?;
+    let codes = stmt.query_map(params![query], hsn_from_row)?.collect::<Result<Vec<_>, _>>()?;
+    Ok(codes)
+}
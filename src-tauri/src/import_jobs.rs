@@ -0,0 +1,512 @@
+use std::collections::HashSet;
+use std::time::Instant;
+
+use argon2::password_hash::rand_core::{OsRng, RngCore};
+use rusqlite::Connection;
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Emitter, Manager, State};
+
+use crate::cancellation::{CancelToken, Cancellations};
+use crate::csv_import::{self, CsvImportReport, CsvMapping, CsvRowError, MappedReader};
+use crate::customers;
+use crate::db::{self, DbPool};
+use crate::error::AppError;
+use crate::invoices;
+use crate::sales_import::{
+    self, CustomerIndex, SalesColumnMapping, SalesImportReport, SalesRowStatus,
+};
+
+pub const EVENT_IMPORT_PROGRESS: &str = "import://progress";
+pub const EVENT_IMPORT_FINISHED: &str = "import://finished";
+
+// Rows written per transaction; progress is reported and cancellation checked between chunks
+const CHUNK_ROWS: usize = 500;
+
+fn new_job_id() -> String {
+    let mut bytes = [0u8; 8];
+    OsRng.fill_bytes(&mut bytes);
+    format!(
+        "import-{}",
+        bytes
+            .iter()
+            .map(|byte| format!("{:02x}", byte))
+            .collect::<String>()
+    )
+}
+
+// Same options as import_csv
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct CsvImportRequest {
+    pub path: String,
+    pub profile_id: Option<i64>,
+    pub mapping: Option<CsvMapping>,
+    pub default_category_id: Option<i64>,
+    #[serde(default)]
+    pub dry_run: bool,
+}
+
+// Same options as import_sales_excel
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SalesImportRequest {
+    pub path: String,
+    pub mapping: SalesColumnMapping,
+    #[serde(default)]
+    pub dry_run: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ImportStatus {
+    Running,
+    Completed,
+    Cancelled,
+    Failed,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ImportProgress {
+    pub job_id: String,
+    pub status: ImportStatus,
+    pub rows_processed: usize,
+    pub imported_rows: usize,
+    pub error_rows: usize,
+    // Known up front for spreadsheets; CSV files report progress by bytes read instead
+    pub total_rows: Option<usize>,
+    pub percent: f64,
+    pub eta_seconds: Option<u64>,
+}
+
+// Sent once when a job ends. Rows committed before a cancellation or failure stay imported and
+// are counted in the report.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ImportFinished {
+    pub job_id: String,
+    pub status: ImportStatus,
+    pub error: Option<String>,
+    pub csv: Option<CsvImportReport>,
+    pub sales: Option<SalesImportReport>,
+}
+
+// Called with every progress update besides the event, e.g. to persist it. It gets the
+// import's connection, as the open chunk transaction blocks writes from any other.
+pub(crate) type ProgressHook = Box<dyn Fn(&Connection, &ImportProgress) + Send>;
+
+struct Tracker {
+    app: AppHandle,
+    job_id: String,
+    cancel: CancelToken,
+    started: Instant,
+    on_progress: Option<ProgressHook>,
+}
+
+impl Tracker {
+    fn start(app: &AppHandle, job_id: String, on_progress: Option<ProgressHook>) -> Self {
+        let cancel = app.state::<Cancellations>().register(&job_id);
+        Tracker {
+            app: app.clone(),
+            job_id,
+            cancel,
+            started: Instant::now(),
+            on_progress,
+        }
+    }
+
+    fn cancelled(&self) -> bool {
+        self.cancel.is_cancelled()
+    }
+
+    // `done` is the finished fraction of the file, between 0 and 1
+    fn report(
+        &self,
+        conn: &Connection,
+        rows_processed: usize,
+        imported_rows: usize,
+        error_rows: usize,
+        total_rows: Option<usize>,
+        done: f64,
+    ) {
+        let done = done.clamp(0.0, 1.0);
+        let elapsed = self.started.elapsed().as_secs_f64();
+        let eta_seconds = (done > 0.0).then(|| (elapsed * (1.0 - done) / done).round() as u64);
+        let progress = ImportProgress {
+            job_id: self.job_id.clone(),
+            status: ImportStatus::Running,
+            rows_processed,
+            imported_rows,
+            error_rows,
+            total_rows,
+            percent: (done * 1000.0).round() / 10.0,
+            eta_seconds,
+        };
+        if let Some(on_progress) = &self.on_progress {
+            on_progress(conn, &progress);
+        }
+        let _ = self.app.emit(EVENT_IMPORT_PROGRESS, progress);
+    }
+
+    fn finish(
+        &self,
+        status: ImportStatus,
+        error: Option<String>,
+        csv: Option<CsvImportReport>,
+        sales: Option<SalesImportReport>,
+    ) -> ImportFinished {
+        let finished = ImportFinished {
+            job_id: self.job_id.clone(),
+            status,
+            error,
+            csv,
+            sales,
+        };
+        let _ = self.app.emit(EVENT_IMPORT_FINISHED, finished.clone());
+        self.app.state::<Cancellations>().unregister(&self.job_id);
+        finished
+    }
+}
+
+// Runs `chunk` until it reports there is nothing left or the job is cancelled. A real run
+// commits each chunk so other windows can keep writing; a dry run keeps one transaction, so
+// rows checked later still see the earlier ones, and rolls it back at the end.
+fn run_chunks(
+    conn: &mut Connection,
+    tracker: &Tracker,
+    dry_run: bool,
+    mut chunk: impl FnMut(&Connection) -> Result<bool, String>,
+) -> Result<bool, String> {
+    if dry_run {
+        let tx = conn.transaction().map_err(|e| e.to_string())?;
+        while !tracker.cancelled() {
+            if !chunk(&tx)? {
+                return Ok(true);
+            }
+        }
+        return Ok(false);
+    }
+    while !tracker.cancelled() {
+        let tx = conn.transaction().map_err(|e| e.to_string())?;
+        let more = chunk(&tx)?;
+        tx.commit().map_err(|e| e.to_string())?;
+        if !more {
+            return Ok(true);
+        }
+    }
+    Ok(false)
+}
+
+fn run_csv_import(
+    conn: &mut Connection,
+    tracker: &Tracker,
+    company_id: i64,
+    mut reader: MappedReader,
+    mapping: &CsvMapping,
+    request: &CsvImportRequest,
+    file_size: u64,
+) -> (Result<bool, String>, CsvImportReport) {
+    let dry_run = request.dry_run;
+    let company_customers = match customers::get_customers_by_company(conn, company_id) {
+        Ok(customers) => customers,
+        Err(e) => {
+            return (
+                Err(e.to_string()),
+                csv_report(mapping, 0, 0, Vec::new(), dry_run),
+            )
+        }
+    };
+    let customer_index = CustomerIndex::new(&company_customers);
+
+    let mut total_rows = 0;
+    let mut imported_rows = 0;
+    let mut errors = Vec::new();
+    let completed = run_chunks(conn, tracker, dry_run, |tx| {
+        let mut read = 0;
+        for (row_number, values) in reader.records().take(CHUNK_ROWS) {
+            read += 1;
+            match csv_import::import_row(
+                tx,
+                company_id,
+                values,
+                mapping,
+                request.default_category_id,
+                &customer_index,
+            ) {
+                Ok(()) => imported_rows += 1,
+                Err(row_errors) => errors.push(CsvRowError {
+                    row_number,
+                    errors: row_errors,
+                }),
+            }
+        }
+        total_rows += read;
+        let done = if file_size == 0 {
+            1.0
+        } else {
+            reader.bytes_read() as f64 / file_size as f64
+        };
+        tracker.report(tx, total_rows, imported_rows, errors.len(), None, done);
+        Ok(read == CHUNK_ROWS)
+    });
+    let report = csv_report(mapping, total_rows, imported_rows, errors, dry_run);
+    (completed, report)
+}
+
+fn csv_report(
+    mapping: &CsvMapping,
+    total_rows: usize,
+    imported_rows: usize,
+    errors: Vec<CsvRowError>,
+    dry_run: bool,
+) -> CsvImportReport {
+    CsvImportReport {
+        target: mapping.target,
+        total_rows,
+        imported_rows,
+        error_rows: errors.len(),
+        dry_run,
+        errors,
+    }
+}
+
+// The workbook is read whole, as spreadsheet files cannot be streamed, but rows are checked
+// and written in chunks
+fn run_sales_import(
+    conn: &mut Connection,
+    tracker: &Tracker,
+    company_id: i64,
+    request: &SalesImportRequest,
+) -> (Result<bool, String>, Option<SalesImportReport>) {
+    let dry_run = request.dry_run;
+    let mapping = &request.mapping;
+    let loaded = sales_import::load_sheet(&request.path, mapping.sheet.as_deref()).and_then(
+        |(sheet, range)| {
+            let (header_row, columns) = sales_import::sheet_columns(&range, mapping)?;
+            Ok((sheet, range, header_row, columns))
+        },
+    );
+    let (sheet, range, header_row, columns) = match loaded {
+        Ok(loaded) => loaded,
+        Err(e) => return (Err(e.to_string()), None),
+    };
+    let company_customers = match customers::get_customers_by_company(conn, company_id) {
+        Ok(customers) => customers,
+        Err(e) => return (Err(e.to_string()), None),
+    };
+    let customer_index = CustomerIndex::new(&company_customers);
+
+    let total_rows = sales_import::data_rows(&range, header_row).count();
+    let mut rows = sales_import::data_rows(&range, header_row);
+    let mut processed = 0;
+    let mut results = Vec::new();
+    let mut seen_numbers = HashSet::new();
+    let completed = run_chunks(conn, tracker, dry_run, |tx| {
+        let mut read = 0;
+        // Rows join the report only once their chunk is written, so a failed chunk leaves
+        // nothing marked as imported that was rolled back
+        let mut chunk_results = Vec::new();
+        for (row_number, row) in rows.by_ref().take(CHUNK_ROWS) {
+            read += 1;
+            let checked = sales_import::check_row(
+                tx,
+                company_id,
+                row_number,
+                row,
+                &columns,
+                &customer_index,
+                &mut seen_numbers,
+            )?;
+            let Some((mut result, invoice)) = checked else {
+                continue;
+            };
+            if let Some(invoice) = invoice {
+                if !dry_run {
+                    let id = invoices::insert_invoice(tx, &invoice)
+                        .map_err(|e| format!("Row {}: {}", row_number, e))?;
+                    result.status = SalesRowStatus::Imported;
+                    result.invoice_id = Some(id);
+                }
+            }
+            chunk_results.push(result);
+        }
+        results.append(&mut chunk_results);
+        processed += read;
+        let valid = results
+            .iter()
+            .filter(|r| r.status != SalesRowStatus::Error)
+            .count();
+        let imported = if dry_run { 0 } else { valid };
+        let done = if total_rows == 0 {
+            1.0
+        } else {
+            processed as f64 / total_rows as f64
+        };
+        tracker.report(
+            tx,
+            processed,
+            imported,
+            results.len() - valid,
+            Some(total_rows),
+            done,
+        );
+        Ok(read == CHUNK_ROWS)
+    });
+
+    let valid_rows = results
+        .iter()
+        .filter(|r| r.status != SalesRowStatus::Error)
+        .count();
+    let report = SalesImportReport {
+        sheet,
+        total_rows: results.len(),
+        valid_rows,
+        error_rows: results.len() - valid_rows,
+        imported_rows: if dry_run { 0 } else { valid_rows },
+        dry_run,
+        rows: results,
+    };
+    (completed, Some(report))
+}
+
+fn outcome_status(completed: &Result<bool, String>) -> (ImportStatus, Option<String>) {
+    match completed {
+        Ok(true) => (ImportStatus::Completed, None),
+        Ok(false) => (ImportStatus::Cancelled, None),
+        Err(e) => (ImportStatus::Failed, Some(e.clone())),
+    }
+}
+
+fn prepare_csv(
+    pool: &DbPool,
+    request: &CsvImportRequest,
+) -> Result<(CsvMapping, MappedReader), String> {
+    let conn = db::get_conn(pool)?;
+    let mapping = csv_import::resolve_mapping(&conn, request.profile_id, request.mapping.clone())?;
+    let reader = MappedReader::open(&request.path, &mapping)?;
+    Ok((mapping, reader))
+}
+
+fn finish_csv(
+    pool: &DbPool,
+    tracker: &Tracker,
+    company_id: i64,
+    request: &CsvImportRequest,
+    mapping: CsvMapping,
+    reader: MappedReader,
+) -> ImportFinished {
+    let file_size = std::fs::metadata(&request.path)
+        .map(|metadata| metadata.len())
+        .unwrap_or(0);
+    let (completed, report) = match db::get_conn(pool) {
+        Ok(mut conn) => run_csv_import(
+            &mut conn, tracker, company_id, reader, &mapping, request, file_size,
+        ),
+        Err(e) => (
+            Err(e.to_string()),
+            csv_report(&mapping, 0, 0, Vec::new(), request.dry_run),
+        ),
+    };
+    let (status, error) = outcome_status(&completed);
+    tracing::info!(job = %tracker.job_id, ?status, rows = report.total_rows, "csv import finished");
+    tracker.finish(status, error, Some(report), None)
+}
+
+fn finish_sales(
+    pool: &DbPool,
+    tracker: &Tracker,
+    company_id: i64,
+    request: &SalesImportRequest,
+) -> ImportFinished {
+    let (completed, report) = match db::get_conn(pool) {
+        Ok(mut conn) => run_sales_import(&mut conn, tracker, company_id, request),
+        Err(e) => (Err(e.to_string()), None),
+    };
+    let (status, error) = outcome_status(&completed);
+    tracing::info!(job = %tracker.job_id, ?status, "sales import finished");
+    tracker.finish(status, error, None, report)
+}
+
+// Runs a CSV import on the calling thread for the job queue. Events and cancel_import work as
+// for start_csv_import, with `job_id` chosen by the caller.
+pub(crate) fn run_csv_job(
+    app: &AppHandle,
+    pool: &DbPool,
+    job_id: String,
+    company_id: i64,
+    request: &CsvImportRequest,
+    on_progress: ProgressHook,
+) -> ImportFinished {
+    let tracker = Tracker::start(app, job_id, Some(on_progress));
+    match prepare_csv(pool, request) {
+        Ok((mapping, reader)) => finish_csv(pool, &tracker, company_id, request, mapping, reader),
+        Err(e) => tracker.finish(ImportStatus::Failed, Some(e), None, None),
+    }
+}
+
+// Spreadsheet counterpart of run_csv_job
+pub(crate) fn run_sales_job(
+    app: &AppHandle,
+    pool: &DbPool,
+    job_id: String,
+    company_id: i64,
+    request: &SalesImportRequest,
+    on_progress: ProgressHook,
+) -> ImportFinished {
+    let tracker = Tracker::start(app, job_id, Some(on_progress));
+    finish_sales(pool, &tracker, company_id, request)
+}
+
+// Same as import_csv, but runs on a background thread and returns a job id right away.
+// Progress arrives as import://progress events and the report with import://finished.
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn start_csv_import(
+    app: AppHandle,
+    pool: State<'_, DbPool>,
+    company_id: i64,
+    request: CsvImportRequest,
+) -> Result<String, AppError> {
+    // Mapping and header problems are reported straight away rather than as a failed job
+    let (mapping, reader) = prepare_csv(&pool, &request)?;
+
+    let tracker = Tracker::start(&app, new_job_id(), None);
+    let job_id = tracker.job_id.clone();
+    let pool = pool.inner().clone();
+    std::thread::spawn(move || {
+        finish_csv(&pool, &tracker, company_id, &request, mapping, reader);
+    });
+    Ok(job_id)
+}
+
+// Background version of import_sales_excel; see start_csv_import
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn start_sales_import(
+    app: AppHandle,
+    pool: State<'_, DbPool>,
+    company_id: i64,
+    request: SalesImportRequest,
+) -> Result<String, AppError> {
+    if !std::path::Path::new(&request.path).is_file() {
+        return Err(AppError::validation(
+            "path",
+            format!("{} was not found", request.path),
+        ));
+    }
+
+    let tracker = Tracker::start(&app, new_job_id(), None);
+    let job_id = tracker.job_id.clone();
+    let pool = pool.inner().clone();
+    std::thread::spawn(move || {
+        finish_sales(&pool, &tracker, company_id, &request);
+    });
+    Ok(job_id)
+}
+
+// Stops a running import after the chunk in progress. Returns false when the job has already
+// finished.
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn cancel_import(
+    cancellations: State<'_, Cancellations>,
+    job_id: String,
+) -> Result<bool, AppError> {
+    Ok(cancellations.cancel(job_id.trim()))
+}
@@ -0,0 +1,522 @@
+use rusqlite::types::{Value, ValueRef};
+use rusqlite::{params, params_from_iter, Connection, OptionalExtension, Row};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value as Json};
+use tauri::State;
+
+use crate::customers::{self, normalize_customer_name};
+use crate::db::{self, DbPool};
+use crate::error::AppError;
+
+const MAPPING_TYPES: &[&str] = &["user_mapped", "auto_created"];
+const SESSION_STATUSES: &[&str] = &["pending", "verified", "importing", "completed", "failed"];
+const CUSTOMER_STATUSES: &[&str] = &["verified", "error"];
+
+// import_reports columns and the report header each is read from
+const REPORT_COLUMNS: &[(&str, &str)] = &[
+    ("invoice_no", "invoice_no"),
+    ("cust_cde", "cust_cde"),
+    ("cust_name", "cust_name"),
+    ("IO_DATE", "IO_DATE"),
+    ("Invno", "Invno"),
+    ("prod_cde", "prod_cde"),
+    ("prod_cust_no", "prod_cust_no"),
+    ("prod_name_ko", "prod_name_ko"),
+    ("tariff_code", "tariff_code"),
+    ("io_qty", "io_qty"),
+    ("rate_pre_unit", "rate_pre_unit"),
+    ("Amortisation_cost", "Amortisation_cost"),
+    ("supp_mat_cost", "supp_mat_cost"),
+    ("ASSESSABLE_VALUE", "ASSESSABLE_VALUE"),
+    ("supplier_mat_value", "Supplier MAt Value"),
+    ("Amort_Value", "Amort_Value"),
+    ("ED_Value", "ED_Value"),
+    ("ADDL_DUTY", "ADDL_DUTY"),
+    ("EDU_CESS", "EDU_CESS"),
+    ("SH_EDT_CESS", "SH_EDT_CESS"),
+    ("Total", "Total"),
+    ("VAT_CST", "VAT_CST"),
+    ("invoice_Total", "invoice_Total"),
+    ("Grand_total", "Grand_total"),
+    ("total_basic_value", "Total Basic Value"),
+    ("total_ed_value", "Total ED Value"),
+    ("Total_VAT", "Total_VAT"),
+    ("Total_Inv_Value", "Total_Inv_Value"),
+    ("ST_VAT", "ST_VAT"),
+    ("CGST_RATE", "CGST_RATE"),
+    ("CGST_AMT", "CGST_AMT"),
+    ("SGST_RATE", "SGST_RATE"),
+    ("SGST_AMT", "SGST_AMT"),
+    ("IGST_RATE", "IGST_RATE"),
+    ("IGST_AMT", "IGST_AMT"),
+    ("TCS_amt", "TCS_amt"),
+    ("CGST_TOTAL", "CGST_TOTAL"),
+    ("SGST_TOTAL", "SGST_TOTAL"),
+    ("IGST_TOTAL", "IGST_TOTAL"),
+    ("Total_Amorization", "Total_Amorization"),
+    ("Total_TCS", "Total_TCS"),
+];
+
+// Report customer name remembered against a customer, so later imports map it automatically
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CustomerMapping {
+    pub id: i64,
+    pub company_id: i64,
+    pub report_customer_name: String,
+    pub normalized_report_customer_name: String,
+    pub mapped_customer_id: i64,
+    pub mapping_type: String,
+    pub created_at: Option<String>,
+    pub updated_at: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ReportCustomer {
+    pub report_customer_id: String,
+    pub report_customer_name: String,
+}
+
+// How one customer of an import session was resolved
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SessionCustomer {
+    pub id: i64,
+    pub import_session_id: String,
+    pub report_customer_id: String,
+    pub report_customer_name: String,
+    pub mapped_customer_id: Option<i64>,
+    pub created_customer_id: Option<i64>,
+    pub status: String,
+    pub created_at: Option<String>,
+    pub updated_at: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ReportRowMapping {
+    pub report_customer_name: String,
+    pub tally_customer_id: i64,
+    pub category_id: i64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ReportImportResult {
+    pub imported_rows: usize,
+    pub errors: Vec<String>,
+}
+
+fn mapping_from_row(row: &Row) -> rusqlite::Result<CustomerMapping> {
+    Ok(CustomerMapping {
+        id: row.get("id")?,
+        company_id: row.get("company_id")?,
+        report_customer_name: row.get("report_customer_name")?,
+        normalized_report_customer_name: row.get("normalized_report_customer_name")?,
+        mapped_customer_id: row.get("mapped_customer_id")?,
+        mapping_type: row.get("mapping_type")?,
+        created_at: row.get("created_at")?,
+        updated_at: row.get("updated_at")?,
+    })
+}
+
+fn session_customer_from_row(row: &Row) -> rusqlite::Result<SessionCustomer> {
+    Ok(SessionCustomer {
+        id: row.get("id")?,
+        import_session_id: row.get("import_session_id")?,
+        report_customer_id: row.get("report_customer_id")?,
+        report_customer_name: row.get("report_customer_name")?,
+        mapped_customer_id: row.get("mapped_customer_id")?,
+        created_customer_id: row.get("created_customer_id")?,
+        status: row.get("status")?,
+        created_at: row.get("created_at")?,
+        updated_at: row.get("updated_at")?,
+    })
+}
+
+fn check_customer(conn: &Connection, company_id: i64, customer_id: i64) -> Result<(), AppError> {
+    match customers::get_customer_by_id(conn, customer_id, company_id)? {
+        Some(_) => Ok(()),
+        None => Err(AppError::not_found("Customer not found")),
+    }
+}
+
+fn check_session(conn: &Connection, company_id: i64, session_id: &str) -> Result<(), AppError> {
+    let exists: Option<i64> = conn
+        .query_row(
+            "SELECT 1 FROM import_sessions WHERE id = ?1 AND company_id = ?2",
+            params![session_id, company_id],
+            |row| row.get(0),
+        )
+        .optional()?;
+    match exists {
+        Some(_) => Ok(()),
+        None => Err(AppError::not_found("Import session not found")),
+    }
+}
+
+fn json_value(value: ValueRef) -> Json {
+    match value {
+        ValueRef::Null | ValueRef::Blob(_) => Json::Null,
+        ValueRef::Integer(n) => Json::from(n),
+        ValueRef::Real(n) => Json::from(n),
+        ValueRef::Text(text) => Json::String(String::from_utf8_lossy(text).into_owned()),
+    }
+}
+
+fn sql_value(value: Option<&Json>) -> Value {
+    match value {
+        None | Some(Json::Null) => Value::Null,
+        Some(Json::Bool(flag)) => Value::Integer(i64::from(*flag)),
+        Some(Json::Number(number)) => match number.as_i64() {
+            Some(integer) => Value::Integer(integer),
+            None => Value::Real(number.as_f64().unwrap_or_default()),
+        },
+        Some(Json::String(text)) => Value::Text(text.clone()),
+        Some(other) => Value::Text(other.to_string()),
+    }
+}
+
+fn insert_report_row(
+    conn: &Connection,
+    company_id: i64,
+    row: &Map<String, Json>,
+    mapping: &ReportRowMapping,
+) -> Result<(), AppError> {
+    let columns = REPORT_COLUMNS
+        .iter()
+        .map(|(column, _)| *column)
+        .collect::<Vec<_>>();
+    let placeholders = vec!["?"; columns.len() + 3].join(", ");
+    let mut values = vec![Value::Integer(company_id)];
+    values.extend(
+        REPORT_COLUMNS
+            .iter()
+            .map(|(_, key)| sql_value(row.get(*key))),
+    );
+    values.push(Value::Integer(mapping.tally_customer_id));
+    values.push(Value::Integer(mapping.category_id));
+    conn.prepare_cached(&format!(
+        "INSERT INTO import_reports (company_id, {}, tally_customer_id, category_id)
+         VALUES ({})",
+        columns.join(", "),
+        placeholders
+    ))
+    .and_then(|mut stmt| stmt.execute(params_from_iter(values)))?;
+    Ok(())
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn save_customer_mapping(
+    pool: State<'_, DbPool>,
+    company_id: i64,
+    report_customer_name: String,
+    mapped_customer_id: i64,
+    mapping_type: Option<String>,
+) -> Result<(), AppError> {
+    let mapping_type = mapping_type.unwrap_or_else(|| "user_mapped".to_string());
+    if !MAPPING_TYPES.contains(&mapping_type.as_str()) {
+        return Err(AppError::validation("mapping_type", "Unknown mapping type"));
+    }
+    let conn = db::get_conn(&pool)?;
+    check_customer(&conn, company_id, mapped_customer_id)?;
+    conn.execute(
+        "INSERT INTO persistent_customer_mappings
+            (company_id, report_customer_name, normalized_report_customer_name, mapped_customer_id, mapping_type)
+         VALUES (?1, ?2, ?3, ?4, ?5)
+         ON CONFLICT(company_id, normalized_report_customer_name) DO UPDATE SET
+            report_customer_name = ?2,
+            mapped_customer_id = ?4,
+            mapping_type = ?5,
+            updated_at = CURRENT_TIMESTAMP",
+        params![
+            company_id,
+            report_customer_name.trim(),
+            normalize_customer_name(&report_customer_name),
+            mapped_customer_id,
+            mapping_type
+        ],
+    )?;
+    Ok(())
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn get_customer_mapping(
+    pool: State<'_, DbPool>,
+    company_id: i64,
+    report_customer_name: String,
+) -> Result<Option<i64>, AppError> {
+    let conn = db::get_conn(&pool)?;
+    let customer_id = conn
+        .query_row(
+            "SELECT mapped_customer_id FROM persistent_customer_mappings
+             WHERE company_id = ?1 AND normalized_report_customer_name = ?2",
+            params![company_id, normalize_customer_name(&report_customer_name)],
+            |row| row.get(0),
+        )
+        .optional()?;
+    Ok(customer_id)
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn list_customer_mappings(
+    pool: State<'_, DbPool>,
+    company_id: i64,
+) -> Result<Vec<CustomerMapping>, AppError> {
+    let conn = db::get_conn(&pool)?;
+    let mut stmt = conn.prepare(
+        "SELECT id, company_id, report_customer_name, normalized_report_customer_name,
+                    mapped_customer_id, mapping_type, created_at, updated_at
+             FROM persistent_customer_mappings
+             WHERE company_id = ?1
+             ORDER BY updated_at DESC",
+    )?;
+    let mappings = stmt
+        .query_map(params![company_id], mapping_from_row)?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(mappings)
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn delete_customer_mapping(
+    pool: State<'_, DbPool>,
+    company_id: i64,
+    report_customer_name: String,
+) -> Result<(), AppError> {
+    let conn = db::get_conn(&pool)?;
+    conn.execute(
+        "DELETE FROM persistent_customer_mappings
+         WHERE company_id = ?1 AND normalized_report_customer_name = ?2",
+        params![company_id, normalize_customer_name(&report_customer_name)],
+    )?;
+    Ok(())
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn create_import_session(
+    pool: State<'_, DbPool>,
+    company_id: i64,
+    session_id: String,
+    file_name: String,
+) -> Result<(), AppError> {
+    let conn = db::get_conn(&pool)?;
+    conn.execute(
+        "INSERT INTO import_sessions (id, company_id, file_name, status) VALUES (?1, ?2, ?3, 'pending')",
+        params![session_id, company_id, file_name],
+    )?;
+    Ok(())
+}
+
+// Lists the report's customers on the session, each waiting to be verified
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn add_import_customers(
+    pool: State<'_, DbPool>,
+    company_id: i64,
+    session_id: String,
+    customers: Vec<ReportCustomer>,
+) -> Result<(), AppError> {
+    let mut conn = db::get_conn(&pool)?;
+    let tx = conn.transaction()?;
+    check_session(&tx, company_id, &session_id)?;
+    for customer in &customers {
+        tx.execute(
+            "INSERT INTO import_customer_mappings
+                (import_session_id, report_customer_id, report_customer_name, status)
+             VALUES (?1, ?2, ?3, 'unverified')",
+            params![
+                session_id,
+                customer.report_customer_id,
+                customer.report_customer_name
+            ],
+        )?;
+    }
+    tx.commit()?;
+    Ok(())
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn set_import_session_status(
+    pool: State<'_, DbPool>,
+    company_id: i64,
+    session_id: String,
+    status: String,
+) -> Result<(), AppError> {
+    if !SESSION_STATUSES.contains(&status.as_str()) {
+        return Err(AppError::validation(
+            "status",
+            "Unknown import session status",
+        ));
+    }
+    let conn = db::get_conn(&pool)?;
+    let changed = conn.execute(
+        "UPDATE import_sessions SET status = ?1, updated_at = CURRENT_TIMESTAMP
+             WHERE id = ?2 AND company_id = ?3",
+        params![status, session_id, company_id],
+    )?;
+    if changed == 0 {
+        return Err(AppError::not_found("Import session not found"));
+    }
+    Ok(())
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ResolveSessionCustomer {
+    pub report_customer_id: String,
+    pub mapped_customer_id: Option<i64>,
+    pub created_customer_id: Option<i64>,
+    pub status: String,
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn resolve_import_customer(
+    pool: State<'_, DbPool>,
+    company_id: i64,
+    session_id: String,
+    resolution: ResolveSessionCustomer,
+) -> Result<(), AppError> {
+    if !CUSTOMER_STATUSES.contains(&resolution.status.as_str()) {
+        return Err(AppError::validation(
+            "status",
+            "Unknown customer mapping status",
+        ));
+    }
+    let conn = db::get_conn(&pool)?;
+    check_session(&conn, company_id, &session_id)?;
+    for customer_id in [
+        resolution.mapped_customer_id,
+        resolution.created_customer_id,
+    ]
+    .into_iter()
+    .flatten()
+    {
+        check_customer(&conn, company_id, customer_id)?;
+    }
+    let changed = conn.execute(
+        "UPDATE import_customer_mappings
+             SET mapped_customer_id = ?1, created_customer_id = ?2, status = ?3,
+                 updated_at = CURRENT_TIMESTAMP
+             WHERE import_session_id = ?4 AND report_customer_id = ?5",
+        params![
+            resolution.mapped_customer_id,
+            resolution.created_customer_id,
+            resolution.status,
+            session_id,
+            resolution.report_customer_id
+        ],
+    )?;
+    if changed == 0 {
+        return Err(AppError::not_found("Customer is not part of this import"));
+    }
+    Ok(())
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn list_import_customers(
+    pool: State<'_, DbPool>,
+    company_id: i64,
+    session_id: String,
+) -> Result<Vec<SessionCustomer>, AppError> {
+    let conn = db::get_conn(&pool)?;
+    check_session(&conn, company_id, &session_id)?;
+    let mut stmt = conn.prepare(
+        "SELECT id, import_session_id, report_customer_id, report_customer_name,
+                    mapped_customer_id, created_customer_id, status, created_at, updated_at
+             FROM import_customer_mappings
+             WHERE import_session_id = ?1
+             ORDER BY created_at ASC, id ASC",
+    )?;
+    let customers = stmt
+        .query_map(params![session_id], session_customer_from_row)?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(customers)
+}
+
+// Stores report rows against their mapped customer. Each row goes in under its own savepoint,
+// so a failing row is reported without dropping the rest.
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn import_report_rows(
+    pool: State<'_, DbPool>,
+    company_id: i64,
+    rows: Vec<Map<String, Json>>,
+    mappings: Vec<ReportRowMapping>,
+) -> Result<ReportImportResult, AppError> {
+    let mut conn = db::get_conn(&pool)?;
+    let mut tx = conn.transaction()?;
+    let mut imported_rows = 0;
+    let mut errors = Vec::new();
+    for row in &rows {
+        let name = row
+            .get("cust_name")
+            .and_then(Json::as_str)
+            .unwrap_or("")
+            .trim();
+        let Some(mapping) = mappings
+            .iter()
+            .find(|m| m.report_customer_name.trim().eq_ignore_ascii_case(name))
+        else {
+            errors.push(format!("No mapping found for customer: {}", name));
+            continue;
+        };
+        if mapping.tally_customer_id <= 0 {
+            errors.push(format!("Invalid customer ID for customer: {}", name));
+            continue;
+        }
+        if mapping.category_id <= 0 {
+            errors.push(format!("Invalid category ID for customer: {}", name));
+            continue;
+        }
+        let inserted = check_customer(&tx, company_id, mapping.tally_customer_id).and_then(|_| {
+            let savepoint = tx.savepoint()?;
+            insert_report_row(&savepoint, company_id, row, mapping)?;
+            savepoint.commit().map_err(AppError::from)
+        });
+        match inserted {
+            Ok(()) => imported_rows += 1,
+            Err(e) => errors.push(format!("Failed to import row for customer {}: {}", name, e)),
+        }
+    }
+    tx.commit()?;
+    Ok(ReportImportResult {
+        imported_rows,
+        errors,
+    })
+}
+
+// Imported rows with the Tally customer and category names, newest first
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn list_import_reports(
+    pool: State<'_, DbPool>,
+    company_id: i64,
+) -> Result<Vec<Map<String, Json>>, AppError> {
+    let conn = db::get_conn(&pool)?;
+    let mut stmt = conn.prepare(
+        "SELECT ir.*, c.tally_customer, cat.name AS category_name
+             FROM import_reports ir
+             LEFT JOIN customers c ON ir.tally_customer_id = c.id
+             LEFT JOIN categories cat ON ir.category_id = cat.id
+             WHERE ir.company_id = ?1
+             ORDER BY ir.created_at DESC, ir.id DESC",
+    )?;
+    let names = stmt
+        .column_names()
+        .into_iter()
+        .map(String::from)
+        .collect::<Vec<_>>();
+    let rows = stmt
+        .query_map(params![company_id], |row| {
+            let mut record = Map::new();
+            for (index, name) in names.iter().enumerate() {
+                record.insert(name.clone(), json_value(row.get_ref(index)?));
+            }
+            Ok(record)
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(rows)
+}
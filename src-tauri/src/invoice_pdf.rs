@@ -0,0 +1,996 @@
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::BufReader;
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use chrono::NaiveDate;
+use printpdf::image_crate::codecs::{jpeg::JpegDecoder, png::PngDecoder};
+use printpdf::{
+    BuiltinFont, Image, ImageTransform, IndirectFontRef, Line, Mm, PdfDocument,
+    PdfDocumentReference, PdfLayerReference, Point, Rect,
+};
+use qrcode::Color;
+use rusqlite::Connection;
+use serde::{Deserialize, Serialize};
+use tauri::State;
+use tera::Context;
+
+use crate::amount_words::{amount_in_words, Currency};
+use crate::barcode::{self, Barcode};
+use crate::companies::{self, Company};
+use crate::customers::{self, Customer};
+use crate::db::{self, DbPool};
+use crate::einvoice::{self, EInvoice, EInvoiceStatus};
+use crate::error::AppError;
+use crate::invoice_templates::{self, render_text, InvoiceTemplate, PageLayout};
+use crate::invoices::{self, round2, Invoice, InvoiceLine, InvoiceStatus, INVOICE_DATE_FORMAT};
+use crate::states;
+
+// Built-in PDF fonts have no glyph for the rupee sign
+const CURRENCY_PREFIX: &str = "Rs.";
+const PT_TO_MM: f32 = 0.3528;
+// Average Helvetica glyph width as a fraction of the font size, used to right-align text
+const GLYPH_WIDTH: f32 = 0.52;
+
+const THERMAL_WIDTH: f32 = 80.0;
+const THERMAL_MARGIN: f32 = 4.0;
+const THERMAL_SCALE: f32 = 0.8;
+
+// Width and height in millimetres, plus the factor font sizes and spacing are scaled by
+fn page_setup(layout: PageLayout) -> (f32, f32, f32) {
+    match layout {
+        PageLayout::A4 => (210.0, 297.0, 1.0),
+        PageLayout::A5 => (148.0, 210.0, 0.72),
+        // Receipts are one continuous page sized to their content
+        PageLayout::Thermal80mm => (THERMAL_WIDTH, 0.0, THERMAL_SCALE),
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RenderedPdf {
+    pub path: Option<String>,
+    // Set when no path was given so the frontend can preview the bytes directly
+    pub pdf_base64: Option<String>,
+    pub page_count: usize,
+}
+
+pub struct InvoiceDocument {
+    company: Company,
+    customer: Customer,
+    invoice: Invoice,
+    lines: Vec<InvoiceLine>,
+    einvoice: Option<EInvoice>,
+    company_state: String,
+    customer_state: String,
+    place_of_supply: String,
+}
+
+impl InvoiceDocument {
+    pub fn invoice(&self) -> &Invoice {
+        &self.invoice
+    }
+}
+
+#[derive(Clone, Copy)]
+pub(crate) enum Align {
+    Left,
+    Center,
+    Right,
+}
+
+pub(crate) struct Column {
+    header: &'static str,
+    width: f32,
+    align: Align,
+}
+
+pub(crate) const fn column(header: &'static str, width: f32, align: Align) -> Column {
+    Column {
+        header,
+        width,
+        align,
+    }
+}
+
+const ITEM_COLUMNS: &[Column] = &[
+    column("#", 0.04, Align::Left),
+    column("Description", 0.26, Align::Left),
+    column("HSN/SAC", 0.09, Align::Left),
+    column("Qty", 0.07, Align::Right),
+    column("Rate", 0.10, Align::Right),
+    column("Disc.", 0.07, Align::Right),
+    column("Taxable", 0.11, Align::Right),
+    column("GST %", 0.06, Align::Right),
+    column("Tax", 0.09, Align::Right),
+    column("Total", 0.11, Align::Right),
+];
+
+const HSN_COLUMNS: &[Column] = &[
+    column("HSN/SAC", 0.18, Align::Left),
+    column("GST %", 0.10, Align::Right),
+    column("Taxable", 0.18, Align::Right),
+    column("CGST", 0.18, Align::Right),
+    column("SGST", 0.18, Align::Right),
+    column("IGST", 0.18, Align::Right),
+];
+
+// Formats with Indian digit grouping, e.g. 12,34,567.89
+pub fn format_amount(value: f64) -> String {
+    let value = round2(value);
+    let sign = if value < 0.0 { "-" } else { "" };
+    let fixed = format!("{:.2}", value.abs());
+    let (whole, fraction) = fixed.split_once('.').unwrap_or((fixed.as_str(), "00"));
+
+    let grouped = if whole.len() <= 3 {
+        whole.to_string()
+    } else {
+        let (head, last_three) = whole.split_at(whole.len() - 3);
+        let mut groups: Vec<&str> = Vec::new();
+        let mut end = head.len();
+        while end > 0 {
+            let start = end.saturating_sub(2);
+            groups.push(&head[start..end]);
+            end = start;
+        }
+        groups.reverse();
+        format!("{},{}", groups.join(","), last_three)
+    };
+    format!("{}{}.{}", sign, grouped, fraction)
+}
+
+pub(crate) fn display_date(date: &str) -> String {
+    NaiveDate::parse_from_str(date, INVOICE_DATE_FORMAT)
+        .map(|d| d.format("%d-%m-%Y").to_string())
+        .unwrap_or_else(|_| date.to_string())
+}
+
+// Word-wraps to at most `max_chars` per line, hard-splitting words that are longer
+pub(crate) fn wrap(text: &str, max_chars: usize) -> Vec<String> {
+    let max_chars = max_chars.max(1);
+    let mut lines = Vec::new();
+    let mut current = String::new();
+    for word in text.split_whitespace() {
+        let mut word = word.to_string();
+        while word.chars().count() > max_chars {
+            if !current.is_empty() {
+                lines.push(std::mem::take(&mut current));
+            }
+            let split: String = word.chars().take(max_chars).collect();
+            word = word.chars().skip(max_chars).collect();
+            lines.push(split);
+        }
+        if current.is_empty() {
+            current = word;
+        } else if current.chars().count() + 1 + word.chars().count() <= max_chars {
+            current.push(' ');
+            current.push_str(&word);
+        } else {
+            lines.push(std::mem::replace(&mut current, word));
+        }
+    }
+    if !current.is_empty() || lines.is_empty() {
+        lines.push(current);
+    }
+    lines
+}
+
+fn line_height(size: f32, scale: f32) -> f32 {
+    size * scale * PT_TO_MM * 1.35
+}
+
+fn chars_fitting(width: f32, size: f32, scale: f32) -> usize {
+    (width / (size * scale * GLYPH_WIDTH * PT_TO_MM)) as usize
+}
+
+struct Logo {
+    image: Image,
+    width: f32,
+    height: f32,
+}
+
+// Loads a PNG or JPEG logo scaled to the given height in millimetres
+fn load_logo(path: &str, height: f32) -> Result<Logo, AppError> {
+    let file = File::open(path).map_err(|e| format!("Failed to open logo {}: {}", path, e))?;
+    let mut reader = BufReader::new(file);
+    let lower = path.to_lowercase();
+    let image = if lower.ends_with(".png") {
+        let decoder = PngDecoder::new(&mut reader).map_err(|e| e.to_string())?;
+        Image::try_from(decoder)
+    } else if lower.ends_with(".jpg") || lower.ends_with(".jpeg") {
+        let decoder = JpegDecoder::new(&mut reader).map_err(|e| e.to_string())?;
+        Image::try_from(decoder)
+    } else {
+        return Err(AppError::validation(
+            "logo_path",
+            "Logo must be a PNG or JPEG file",
+        ));
+    }
+    .map_err(|e| format!("Failed to read logo {}: {}", path, e))?;
+
+    let (pixels_wide, pixels_high) = (image.image.width.0 as f32, image.image.height.0 as f32);
+    if pixels_high == 0.0 {
+        return Err(AppError::validation(
+            "logo_path",
+            format!("Logo {} is empty", path),
+        ));
+    }
+    Ok(Logo {
+        width: height * pixels_wide / pixels_high,
+        height,
+        image,
+    })
+}
+
+pub(crate) struct PdfWriter {
+    doc: PdfDocumentReference,
+    layer: PdfLayerReference,
+    regular: IndirectFontRef,
+    bold: IndirectFontRef,
+    pub(crate) width: f32,
+    height: f32,
+    pub(crate) margin: f32,
+    pub(crate) scale: f32,
+    page_count: usize,
+    // Current baseline, in millimetres from the bottom of the page
+    pub(crate) y: f32,
+}
+
+impl PdfWriter {
+    pub(crate) fn new(
+        title: &str,
+        width: f32,
+        height: f32,
+        scale: f32,
+        margin: f32,
+    ) -> Result<PdfWriter, String> {
+        let (doc, page, layer) = PdfDocument::new(title, Mm(width), Mm(height), "Layer 1");
+        let regular = doc
+            .add_builtin_font(BuiltinFont::Helvetica)
+            .map_err(|e| e.to_string())?;
+        let bold = doc
+            .add_builtin_font(BuiltinFont::HelveticaBold)
+            .map_err(|e| e.to_string())?;
+        let layer = doc.get_page(page).get_layer(layer);
+        Ok(PdfWriter {
+            doc,
+            layer,
+            regular,
+            bold,
+            width,
+            height,
+            margin,
+            scale,
+            page_count: 1,
+            y: height - margin,
+        })
+    }
+
+    pub(crate) fn content_width(&self) -> f32 {
+        self.width - 2.0 * self.margin
+    }
+
+    fn font_size(&self, size: f32) -> f32 {
+        size * self.scale
+    }
+
+    pub(crate) fn line_height(&self, size: f32) -> f32 {
+        line_height(size, self.scale)
+    }
+
+    fn text_width(&self, text: &str, size: f32) -> f32 {
+        text.chars().count() as f32 * self.font_size(size) * GLYPH_WIDTH * PT_TO_MM
+    }
+
+    pub(crate) fn chars_fitting(&self, width: f32, size: f32) -> usize {
+        chars_fitting(width, size, self.scale)
+    }
+
+    fn text_at(&self, text: &str, size: f32, x: f32, y: f32, bold: bool) {
+        let font = if bold { &self.bold } else { &self.regular };
+        self.layer
+            .use_text(text, self.font_size(size), Mm(x), Mm(y), font);
+    }
+
+    pub(crate) fn text(&self, text: &str, size: f32, x: f32, bold: bool) {
+        self.text_at(text, size, x, self.y, bold);
+    }
+
+    pub(crate) fn text_right(&self, text: &str, size: f32, right: f32, bold: bool) {
+        self.text(text, size, right - self.text_width(text, size), bold);
+    }
+
+    pub(crate) fn text_centered(&self, text: &str, size: f32, bold: bool) {
+        let x = (self.width - self.text_width(text, size)) / 2.0;
+        self.text(text, size, x, bold);
+    }
+
+    pub(crate) fn advance(&mut self, mm: f32) {
+        self.y -= mm;
+    }
+
+    pub(crate) fn rule(&self, y: f32) {
+        self.layer.set_outline_thickness(0.5 * self.scale);
+        self.layer.add_line(Line {
+            points: vec![
+                (Point::new(Mm(self.margin), Mm(y)), false),
+                (Point::new(Mm(self.width - self.margin), Mm(y)), false),
+            ],
+            is_closed: false,
+        });
+    }
+
+    // Starts a new page when fewer than `needed` millimetres remain
+    pub(crate) fn ensure_space(&mut self, needed: f32) -> bool {
+        if self.y - needed >= self.margin {
+            return false;
+        }
+        let (page, layer) = self
+            .doc
+            .add_page(Mm(self.width), Mm(self.height), "Layer 1");
+        self.layer = self.doc.get_page(page).get_layer(layer);
+        self.page_count += 1;
+        self.y = self.height - self.margin;
+        true
+    }
+
+    pub(crate) fn table_row(
+        &mut self,
+        columns: &[Column],
+        cells: &[Vec<String>],
+        size: f32,
+        bold: bool,
+    ) {
+        let line_height = self.line_height(size);
+        let rows = cells.iter().map(Vec::len).max().unwrap_or(1);
+        let mut x = self.margin;
+        for (column, lines) in columns.iter().zip(cells) {
+            let width = column.width * self.content_width();
+            for (i, line) in lines.iter().enumerate() {
+                let y = self.y - i as f32 * line_height;
+                match column.align {
+                    Align::Left => self.text_at(line, size, x + 1.0, y, bold),
+                    Align::Center => {
+                        let offset = (width - self.text_width(line, size)) / 2.0;
+                        self.text_at(line, size, x + offset, y, bold)
+                    }
+                    Align::Right => {
+                        let right = x + width - 1.0;
+                        self.text_at(line, size, right - self.text_width(line, size), y, bold)
+                    }
+                }
+            }
+            x += width;
+        }
+        self.advance(rows as f32 * line_height);
+    }
+
+    pub(crate) fn table_header(&mut self, columns: &[Column], size: f32) {
+        let cells: Vec<Vec<String>> = columns.iter().map(|c| vec![c.header.to_string()]).collect();
+        self.rule(self.y + self.line_height(size) * 0.75);
+        self.table_row(columns, &cells, size, true);
+        self.rule(self.y + self.line_height(size) * 0.75);
+    }
+
+    fn qr_code(&self, data: &str, x: f32, top: f32, size: f32) -> Result<(), String> {
+        let code = einvoice::qr_code(data)?;
+        let modules = code.width();
+        let module = size / modules as f32;
+        for (i, color) in code.to_colors().iter().enumerate() {
+            if *color != Color::Dark {
+                continue;
+            }
+            let (column, row) = ((i % modules) as f32, (i / modules) as f32);
+            let left = x + column * module;
+            let upper = top - row * module;
+            self.layer
+                .add_rect(Rect::new(Mm(left), Mm(upper - module), Mm(left + module), Mm(upper)));
+        }
+        Ok(())
+    }
+
+    // Bars only; the caller leaves room for the quiet zones and any human-readable text
+    pub(crate) fn barcode(&self, barcode: &Barcode, x: f32, top: f32, width: f32, height: f32) {
+        let module = width / barcode.modules.len() as f32;
+        for (from, run) in barcode.bars() {
+            let left = x + from as f32 * module;
+            let right = left + run as f32 * module;
+            self.layer.add_rect(Rect::new(Mm(left), Mm(top - height), Mm(right), Mm(top)));
+        }
+    }
+
+    fn logo(&self, logo: Logo, x: f32, top: f32) {
+        // The image is placed at its natural size for this dpi
+        let dpi = logo.image.image.height.0 as f32 * 25.4 / logo.height;
+        logo.image.add_to_layer(
+            self.layer.clone(),
+            ImageTransform {
+                translate_x: Some(Mm(x)),
+                translate_y: Some(Mm(top - logo.height)),
+                dpi: Some(dpi),
+                ..Default::default()
+            },
+        );
+    }
+
+    pub(crate) fn finish(self) -> Result<(Vec<u8>, usize), String> {
+        let page_count = self.page_count;
+        let bytes = self.doc.save_to_bytes().map_err(|e| e.to_string())?;
+        Ok((bytes, page_count))
+    }
+}
+
+pub(crate) fn state_label(conn: &Connection, code: &str) -> Result<String, String> {
+    Ok(match states::get_state_by_code(conn, code.trim())? {
+        Some(state) => format!("{} ({})", state.name, state.code),
+        None => code.trim().to_string(),
+    })
+}
+
+pub fn load_document(
+    conn: &Connection,
+    invoice_id: i64,
+    company_id: i64,
+) -> Result<InvoiceDocument, AppError> {
+    let invoice = invoices::get_invoice_by_id(conn, invoice_id, company_id)?
+        .ok_or_else(|| AppError::not_found("Invoice not found"))?;
+    if invoice.status == InvoiceStatus::Draft {
+        return Err(AppError::invalid("Issue the invoice before printing it"));
+    }
+    let company = companies::get_company_by_id(conn, company_id)?
+        .ok_or_else(|| AppError::not_found("Company not found"))?;
+    let customer = customers::get_customer_by_id(conn, invoice.customer_id, company_id)?
+        .ok_or_else(|| AppError::not_found("Customer not found"))?;
+    let lines = invoices::get_invoice_lines(conn, invoice_id)?;
+    // A cancelled IRN and its QR code no longer validate the document
+    let einvoice = einvoice::get_einvoice_by_invoice_id(conn, invoice_id)?
+        .filter(|einvoice| einvoice.status == EInvoiceStatus::Active);
+
+    Ok(InvoiceDocument {
+        company_state: state_label(conn, &company.state_code)?,
+        customer_state: state_label(conn, &customer.state_code)?,
+        place_of_supply: state_label(conn, &invoice.place_of_supply)?,
+        company,
+        customer,
+        invoice,
+        lines,
+        einvoice,
+    })
+}
+
+pub(crate) fn party_lines(
+    name: &str,
+    address: Option<&str>,
+    city: Option<&str>,
+    pincode: Option<&str>,
+    gstin: &str,
+    state: &str,
+) -> Vec<(String, bool)> {
+    let mut lines = vec![(name.to_string(), true)];
+    if let Some(address) = address.filter(|a| !a.trim().is_empty()) {
+        lines.extend(wrap(address, 45).into_iter().map(|l| (l, false)));
+    }
+    let locality = [city, pincode]
+        .iter()
+        .flatten()
+        .map(|s| s.trim())
+        .filter(|s| !s.is_empty())
+        .collect::<Vec<_>>()
+        .join(" - ");
+    if !locality.is_empty() {
+        lines.push((locality, false));
+    }
+    let gstin = if gstin.trim().is_empty() { "Unregistered" } else { gstin.trim() };
+    lines.push((format!("GSTIN: {}", gstin), false));
+    lines.push((format!("State: {}", state), false));
+    lines
+}
+
+
+pub(crate) fn template_context(doc: &InvoiceDocument) -> Result<Context, String> {
+    let mut context = Context::new();
+    context.insert("company", &doc.company);
+    context.insert("customer", &doc.customer);
+    context.insert("invoice", &doc.invoice);
+    context.insert("lines", &doc.lines);
+    context.insert("einvoice", &doc.einvoice);
+    context.insert("place_of_supply", &doc.place_of_supply);
+    let words = amount_in_words(doc.invoice.total_amount, Currency::Inr)?;
+    context.insert("amount_in_words", &words);
+    Ok(context)
+}
+
+fn template_lines(template: Option<&str>, context: &Context) -> Result<Vec<String>, String> {
+    match template.filter(|t| !t.trim().is_empty()) {
+        Some(template) => Ok(render_text(template, context)?
+            .lines()
+            .map(|line| line.trim_end().to_string())
+            .collect()),
+        None => Ok(Vec::new()),
+    }
+}
+
+fn heading(invoice: &Invoice) -> &'static str {
+    if invoice.status == InvoiceStatus::Cancelled {
+        "TAX INVOICE (CANCELLED)"
+    } else {
+        "TAX INVOICE"
+    }
+}
+
+fn tax_totals(invoice: &Invoice) -> Vec<(&'static str, f64)> {
+    let mut totals = vec![("Taxable Value", invoice.taxable_value)];
+    if invoice.igst_amount != 0.0 {
+        totals.push(("IGST", invoice.igst_amount));
+    }
+    if invoice.cgst_amount != 0.0 || invoice.sgst_amount != 0.0 {
+        totals.push(("CGST", invoice.cgst_amount));
+        totals.push(("SGST", invoice.sgst_amount));
+    }
+    if invoice.tcs_amount != 0.0 {
+        totals.push(("TCS", invoice.tcs_amount));
+    }
+    if invoice.round_off != 0.0 {
+        totals.push(("Round Off", invoice.round_off));
+    }
+    totals.push(("Invoice Total", invoice.total_amount));
+    totals
+}
+
+fn render_page(
+    doc: &InvoiceDocument,
+    template: &InvoiceTemplate,
+    context: &Context,
+    copy_label: Option<&str>,
+) -> Result<(Vec<u8>, usize), String> {
+    let invoice = &doc.invoice;
+    let (width, height, scale) = page_setup(template.layout);
+    let title = format!("Tax Invoice {}", invoice.invoice_number);
+    let mut pdf = PdfWriter::new(&title, width, height, scale, 12.0 * scale)?;
+    let content = pdf.content_width();
+    let left = pdf.margin;
+    let right = pdf.width - pdf.margin;
+
+    let logo_bottom = match template.logo_path.as_deref() {
+        Some(path) => {
+            let logo = load_logo(path, 16.0 * pdf.scale)?;
+            let bottom = pdf.y + pdf.line_height(14.0) - logo.height;
+            pdf.logo(logo, left, pdf.y + pdf.line_height(14.0));
+            bottom
+        }
+        None => pdf.y,
+    };
+    if let Some(label) = copy_label {
+        pdf.text_right(label, 8.0, right, true);
+    }
+    pdf.text_centered(heading(invoice), 14.0, true);
+    pdf.advance(pdf.line_height(14.0) * 1.2);
+    for line in template_lines(template.header_template.as_deref(), context)? {
+        pdf.text_centered(&line, 8.0, false);
+        pdf.advance(pdf.line_height(8.0));
+    }
+    pdf.y = pdf.y.min(logo_bottom - pdf.line_height(8.5));
+
+    // Seller on the left, invoice details in the middle, IRN QR code on the right
+    let header_top = pdf.y;
+    let qr_size = 32.0 * pdf.scale;
+    let show_qr = template.show_qr_code && doc.einvoice.is_some();
+    if let Some(einvoice) = doc.einvoice.as_ref().filter(|_| show_qr) {
+        pdf.qr_code(&einvoice.signed_qr_code, right - qr_size, header_top + 3.0, qr_size)?;
+    }
+
+    let seller = party_lines(
+        &doc.company.company_name,
+        doc.company.address.as_deref(),
+        doc.company.city.as_deref(),
+        doc.company.pincode.as_deref(),
+        &doc.company.gst_no,
+        &doc.company_state,
+    );
+    for (line, bold) in &seller {
+        pdf.text(line, if *bold { 11.0 } else { 8.5 }, left, *bold);
+        pdf.advance(pdf.line_height(if *bold { 11.0 } else { 8.5 }));
+    }
+    let seller_bottom = pdf.y;
+
+    pdf.y = header_top;
+    let meta_x = left + content * 0.48;
+    let meta = [
+        ("Invoice No", invoice.invoice_number.clone()),
+        ("Invoice Date", display_date(&invoice.invoice_date)),
+        ("Place of Supply", doc.place_of_supply.clone()),
+        (
+            "Reverse Charge",
+            if invoice.reverse_charge { "Yes" } else { "No" }.to_string(),
+        ),
+    ];
+    for (label, value) in &meta {
+        pdf.text(label, 8.5, meta_x, true);
+        pdf.text(&format!(": {}", value), 8.5, meta_x + 26.0 * pdf.scale, false);
+        pdf.advance(pdf.line_height(8.5));
+    }
+    if template.show_barcode {
+        let code = barcode::code128(&invoice.invoice_number)?;
+        let bar_height = 7.0 * pdf.scale;
+        let top = pdf.y + pdf.line_height(8.5) * 0.5;
+        pdf.barcode(&code, meta_x, top, 45.0 * pdf.scale, bar_height);
+        pdf.advance(bar_height + pdf.line_height(8.5) * 0.5);
+    }
+    pdf.y = pdf.y.min(seller_bottom);
+    if show_qr {
+        pdf.y = pdf.y.min(header_top - qr_size);
+    }
+
+    if let Some(einvoice) = &doc.einvoice {
+        pdf.advance(pdf.line_height(7.5) * 0.5);
+        pdf.text(&format!("IRN: {}", einvoice.irn), 7.5, left, false);
+        pdf.advance(pdf.line_height(7.5));
+        pdf.text(
+            &format!("Ack No: {}    Ack Date: {}", einvoice.ack_no, einvoice.ack_date),
+            7.5,
+            left,
+            false,
+        );
+        pdf.advance(pdf.line_height(7.5));
+    }
+
+    pdf.rule(pdf.y + pdf.line_height(8.5) * 0.5);
+    pdf.advance(pdf.line_height(8.5) * 0.5);
+    pdf.text("Bill To", 8.5, left, true);
+    pdf.advance(pdf.line_height(8.5));
+    let buyer = party_lines(
+        &doc.customer.report_customer,
+        doc.customer.address.as_deref(),
+        doc.customer.city.as_deref(),
+        doc.customer.pincode.as_deref(),
+        &doc.customer.gst_no,
+        &doc.customer_state,
+    );
+    for (line, bold) in &buyer {
+        pdf.text(line, if *bold { 10.0 } else { 8.5 }, left, *bold);
+        pdf.advance(pdf.line_height(if *bold { 10.0 } else { 8.5 }));
+    }
+    pdf.advance(pdf.line_height(8.0));
+
+    // Line items, repeating the header on every page
+    let description_chars = pdf.chars_fitting(ITEM_COLUMNS[1].width * content - 2.0, 8.0);
+    pdf.table_header(ITEM_COLUMNS, 8.0);
+    for (i, line) in doc.lines.iter().enumerate() {
+        let description = wrap(&line.description, description_chars);
+        if pdf.ensure_space(description.len() as f32 * pdf.line_height(8.0)) {
+            pdf.table_header(ITEM_COLUMNS, 8.0);
+        }
+        let tax = line.cgst_amount + line.sgst_amount + line.igst_amount;
+        let cells = vec![
+            vec![(i + 1).to_string()],
+            description,
+            vec![line.hsn_code.trim().to_string()],
+            vec![line.quantity.to_string()],
+            vec![format_amount(line.rate)],
+            vec![format_amount(line.total_discount())],
+            vec![format_amount(line.taxable_value)],
+            vec![line.gst_rate.to_string()],
+            vec![format_amount(tax)],
+            vec![format_amount(line.taxable_value + tax)],
+        ];
+        pdf.table_row(ITEM_COLUMNS, &cells, 8.0, false);
+    }
+    pdf.rule(pdf.y + pdf.line_height(8.0) * 0.75);
+    pdf.advance(pdf.line_height(8.0) * 0.5);
+
+    // Totals, right aligned under the table
+    let totals = tax_totals(invoice);
+    pdf.ensure_space(totals.len() as f32 * pdf.line_height(9.0) + pdf.line_height(8.5) * 3.0);
+    let label_x = left + content * 0.6;
+    for (label, amount) in &totals {
+        let bold = *label == "Invoice Total";
+        pdf.text(label, 9.0, label_x, bold);
+        pdf.text_right(
+            &format!("{} {}", CURRENCY_PREFIX, format_amount(*amount)),
+            9.0,
+            right - 1.0,
+            bold,
+        );
+        pdf.advance(pdf.line_height(9.0));
+    }
+    pdf.advance(pdf.line_height(8.5) * 0.5);
+
+    if template.show_amount_in_words {
+        let words_chars = pdf.chars_fitting(content, 8.5);
+        pdf.text("Amount in words:", 8.5, left, true);
+        pdf.advance(pdf.line_height(8.5));
+        for line in wrap(&amount_in_words(invoice.total_amount, Currency::Inr)?, words_chars) {
+            pdf.text(&line, 8.5, left, false);
+            pdf.advance(pdf.line_height(8.5));
+        }
+        pdf.advance(pdf.line_height(8.0));
+    }
+
+    if template.show_hsn_summary {
+        let mut hsn_summary: BTreeMap<(String, String), [f64; 4]> = BTreeMap::new();
+        for line in &doc.lines {
+            let key = (line.hsn_code.trim().to_string(), line.gst_rate.to_string());
+            let entry = hsn_summary.entry(key).or_insert([0.0; 4]);
+            entry[0] += line.taxable_value;
+            entry[1] += line.cgst_amount;
+            entry[2] += line.sgst_amount;
+            entry[3] += line.igst_amount;
+        }
+        pdf.ensure_space(pdf.line_height(8.0) * 3.0);
+        pdf.table_header(HSN_COLUMNS, 8.0);
+        for ((hsn, rate), amounts) in &hsn_summary {
+            if pdf.ensure_space(pdf.line_height(8.0)) {
+                pdf.table_header(HSN_COLUMNS, 8.0);
+            }
+            let mut cells = vec![vec![hsn.clone()], vec![rate.clone()]];
+            cells.extend(amounts.iter().map(|a| vec![format_amount(*a)]));
+            pdf.table_row(HSN_COLUMNS, &cells, 8.0, false);
+        }
+        pdf.rule(pdf.y + pdf.line_height(8.0) * 0.75);
+        pdf.advance(pdf.line_height(8.0) * 1.5);
+    }
+
+    if template.show_signature {
+        pdf.ensure_space(28.0 * pdf.scale);
+        pdf.text(
+            "Certified that the particulars given above are true and correct.",
+            7.5,
+            left,
+            false,
+        );
+        pdf.text_right(
+            &format!("For {}", doc.company.company_name),
+            9.0,
+            right,
+            true,
+        );
+        pdf.advance(18.0 * pdf.scale);
+        pdf.text_right("Authorised Signatory", 8.5, right, false);
+        pdf.advance(pdf.line_height(8.5) * 1.5);
+    }
+
+    for line in template_lines(template.footer_template.as_deref(), context)? {
+        pdf.ensure_space(pdf.line_height(7.5));
+        pdf.text_centered(&line, 7.5, false);
+        pdf.advance(pdf.line_height(7.5));
+    }
+
+    pdf.finish()
+}
+
+// One laid-out row of a thermal receipt
+enum ReceiptRow {
+    Text(String, f32, bool, Align),
+    Pair(String, String, f32, bool),
+    Rule,
+    Gap(f32),
+    Qr(String, f32),
+    Logo(Logo),
+}
+
+impl ReceiptRow {
+    fn height(&self, scale: f32) -> f32 {
+        match self {
+            ReceiptRow::Text(_, size, _, _) | ReceiptRow::Pair(_, _, size, _) => {
+                line_height(*size, scale)
+            }
+            ReceiptRow::Rule => 2.0,
+            ReceiptRow::Gap(mm) => *mm,
+            ReceiptRow::Qr(_, size) => size + 2.0,
+            ReceiptRow::Logo(logo) => logo.height + 2.0,
+        }
+    }
+}
+
+fn render_thermal(
+    doc: &InvoiceDocument,
+    template: &InvoiceTemplate,
+    context: &Context,
+    copy_label: Option<&str>,
+) -> Result<(Vec<u8>, usize), String> {
+    let invoice = &doc.invoice;
+    let (width, _, scale) = page_setup(PageLayout::Thermal80mm);
+    let content = width - 2.0 * THERMAL_MARGIN;
+    let wrap_at = |size: f32| chars_fitting(content, size, scale);
+    let centered = |text: &str, size: f32, bold: bool| -> Vec<ReceiptRow> {
+        wrap(text, wrap_at(size))
+            .into_iter()
+            .map(|line| ReceiptRow::Text(line, size, bold, Align::Center))
+            .collect()
+    };
+
+    let mut rows = Vec::new();
+    if let Some(label) = copy_label {
+        rows.extend(centered(label, 7.5, true));
+    }
+    if let Some(path) = template.logo_path.as_deref() {
+        rows.push(ReceiptRow::Logo(load_logo(path, 14.0)?));
+    }
+    rows.extend(centered(&doc.company.company_name, 10.0, true));
+    if let Some(address) = doc.company.address.as_deref() {
+        rows.extend(centered(address, 7.5, false));
+    }
+    let locality = [doc.company.city.as_deref(), doc.company.pincode.as_deref()]
+        .iter()
+        .flatten()
+        .map(|s| s.trim())
+        .filter(|s| !s.is_empty())
+        .collect::<Vec<_>>()
+        .join(" - ");
+    if !locality.is_empty() {
+        rows.extend(centered(&locality, 7.5, false));
+    }
+    rows.extend(centered(&format!("GSTIN: {}", doc.company.gst_no.trim()), 7.5, false));
+    for line in template_lines(template.header_template.as_deref(), context)? {
+        rows.extend(centered(&line, 7.5, false));
+    }
+    rows.push(ReceiptRow::Gap(2.0));
+    rows.push(ReceiptRow::Text(heading(invoice).to_string(), 9.0, true, Align::Center));
+
+    let pair = |label: &str, value: String| ReceiptRow::Pair(label.to_string(), value, 7.5, false);
+    rows.push(pair("Invoice No", invoice.invoice_number.clone()));
+    rows.push(pair("Date", display_date(&invoice.invoice_date)));
+    for line in wrap(
+        &format!("Customer: {}", doc.customer.report_customer),
+        wrap_at(7.5),
+    ) {
+        rows.push(ReceiptRow::Text(line, 7.5, false, Align::Left));
+    }
+    if !doc.customer.gst_no.trim().is_empty() {
+        rows.push(pair("GSTIN", doc.customer.gst_no.trim().to_string()));
+    }
+    rows.push(pair("Place of Supply", doc.place_of_supply.clone()));
+
+    rows.push(ReceiptRow::Rule);
+    rows.push(ReceiptRow::Pair("Item".to_string(), "Amount".to_string(), 7.5, true));
+    rows.push(ReceiptRow::Rule);
+    for line in &doc.lines {
+        for text in wrap(&line.description, wrap_at(7.5)) {
+            rows.push(ReceiptRow::Text(text, 7.5, false, Align::Left));
+        }
+        let tax = line.cgst_amount + line.sgst_amount + line.igst_amount;
+        rows.push(pair(
+            &format!(
+                "  {} x {} @ {}%",
+                line.quantity,
+                format_amount(line.rate),
+                line.gst_rate
+            ),
+            format_amount(line.taxable_value + tax),
+        ));
+    }
+    rows.push(ReceiptRow::Rule);
+    for (label, amount) in tax_totals(invoice) {
+        let bold = label == "Invoice Total";
+        let size = if bold { 9.0 } else { 7.5 };
+        rows.push(ReceiptRow::Pair(
+            label.to_string(),
+            format!("{} {}", CURRENCY_PREFIX, format_amount(amount)),
+            size,
+            bold,
+        ));
+    }
+    rows.push(ReceiptRow::Rule);
+
+    if template.show_amount_in_words {
+        for line in wrap(&amount_in_words(invoice.total_amount, Currency::Inr)?, wrap_at(7.0)) {
+            rows.push(ReceiptRow::Text(line, 7.0, false, Align::Left));
+        }
+    }
+    if let Some(einvoice) = doc.einvoice.as_ref().filter(|_| template.show_qr_code) {
+        rows.push(ReceiptRow::Gap(2.0));
+        rows.push(ReceiptRow::Qr(einvoice.signed_qr_code.clone(), 40.0));
+        for line in wrap(&format!("IRN: {}", einvoice.irn), wrap_at(6.0)) {
+            rows.push(ReceiptRow::Text(line, 6.0, false, Align::Center));
+        }
+    }
+    if template.show_signature {
+        rows.push(ReceiptRow::Gap(10.0));
+        rows.push(ReceiptRow::Text(
+            "Authorised Signatory".to_string(),
+            7.5,
+            false,
+            Align::Right,
+        ));
+    }
+    for line in template_lines(template.footer_template.as_deref(), context)? {
+        rows.push(ReceiptRow::Gap(1.0));
+        rows.extend(centered(&line, 7.5, false));
+    }
+
+    let height = 2.0 * THERMAL_MARGIN + rows.iter().map(|r| r.height(scale)).sum::<f32>();
+    let title = format!("Tax Invoice {}", invoice.invoice_number);
+    let mut pdf = PdfWriter::new(&title, width, height, scale, THERMAL_MARGIN)?;
+    let right = width - THERMAL_MARGIN;
+    for row in rows {
+        let advance = row.height(scale);
+        match row {
+            ReceiptRow::Text(text, size, bold, align) => match align {
+                Align::Left => pdf.text(&text, size, THERMAL_MARGIN, bold),
+                Align::Center => pdf.text_centered(&text, size, bold),
+                Align::Right => pdf.text_right(&text, size, right, bold),
+            },
+            ReceiptRow::Pair(label, value, size, bold) => {
+                pdf.text(&label, size, THERMAL_MARGIN, bold);
+                pdf.text_right(&value, size, right, bold);
+            }
+            ReceiptRow::Rule => pdf.rule(pdf.y + 1.0),
+            ReceiptRow::Gap(_) => {}
+            ReceiptRow::Qr(data, size) => {
+                pdf.qr_code(&data, (width - size) / 2.0, pdf.y + 2.0, size)?;
+            }
+            ReceiptRow::Logo(logo) => {
+                let x = (width - logo.width) / 2.0;
+                let top = pdf.y + 2.0;
+                pdf.logo(logo, x, top);
+            }
+        }
+        pdf.advance(advance);
+    }
+
+    pdf.finish()
+}
+
+pub fn render_to_output(
+    doc: &InvoiceDocument,
+    template: &InvoiceTemplate,
+    path: Option<String>,
+) -> Result<RenderedPdf, String> {
+    let (bytes, page_count) = render_bytes(doc, template, None)?;
+    write_output(bytes, page_count, path)
+}
+
+// `copy_label` marks a printed copy, e.g. "Duplicate for Transporter"
+pub fn render_bytes(
+    doc: &InvoiceDocument,
+    template: &InvoiceTemplate,
+    copy_label: Option<&str>,
+) -> Result<(Vec<u8>, usize), String> {
+    let context = template_context(doc)?;
+    match template.layout {
+        PageLayout::Thermal80mm => render_thermal(doc, template, &context, copy_label),
+        PageLayout::A4 | PageLayout::A5 => render_page(doc, template, &context, copy_label),
+    }
+}
+
+// Writes to `path` when given, otherwise returns the bytes for an in-app preview
+pub(crate) fn write_output(
+    bytes: Vec<u8>,
+    page_count: usize,
+    path: Option<String>,
+) -> Result<RenderedPdf, String> {
+    match path {
+        Some(path) => {
+            std::fs::write(&path, &bytes).map_err(|e| format!("Failed to write PDF: {}", e))?;
+            Ok(RenderedPdf {
+                path: Some(path),
+                pdf_base64: None,
+                page_count,
+            })
+        }
+        None => Ok(RenderedPdf {
+            path: None,
+            pdf_base64: Some(STANDARD.encode(bytes)),
+            page_count,
+        }),
+    }
+}
+
+// Uses the default template when none is given
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn render_invoice_pdf(
+    pool: State<'_, DbPool>,
+    invoice_id: i64,
+    company_id: i64,
+    template_id: Option<i64>,
+    path: Option<String>,
+) -> Result<RenderedPdf, AppError> {
+    let (document, template) = {
+        let conn = db::get_conn(&pool)?;
+        (
+            load_document(&conn, invoice_id, company_id)?,
+            invoice_templates::resolve_template(&conn, template_id)?,
+        )
+    };
+    Ok(render_to_output(&document, &template, path)?)
+}
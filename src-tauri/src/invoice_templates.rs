@@ -0,0 +1,392 @@
+use rusqlite::{params, Connection, OptionalExtension, Row};
+use serde::{Deserialize, Serialize};
+use tauri::State;
+use tera::{Context, Tera};
+
+use crate::db::{self, DbPool};
+use crate::error::AppError;
+use crate::invoice_pdf::{self, RenderedPdf};
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+pub enum PageLayout {
+    #[serde(rename = "a4")]
+    A4,
+    #[serde(rename = "a5")]
+    A5,
+    #[serde(rename = "thermal_80mm")]
+    Thermal80mm,
+}
+
+impl PageLayout {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PageLayout::A4 => "a4",
+            PageLayout::A5 => "a5",
+            PageLayout::Thermal80mm => "thermal_80mm",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "a4" => Some(PageLayout::A4),
+            "a5" => Some(PageLayout::A5),
+            "thermal_80mm" => Some(PageLayout::Thermal80mm),
+            _ => None,
+        }
+    }
+}
+
+// Invoice template data model
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct InvoiceTemplate {
+    pub id: Option<i64>,
+    pub name: String,
+    pub layout: PageLayout,
+    pub logo_path: Option<String>,
+    // Tera templates rendered above the invoice heading and below the signature block
+    pub header_template: Option<String>,
+    pub footer_template: Option<String>,
+    pub show_qr_code: bool,
+    pub show_hsn_summary: bool,
+    pub show_amount_in_words: bool,
+    pub show_signature: bool,
+    // Invoice number as a Code 128 barcode beside the invoice details
+    pub show_barcode: bool,
+    pub is_default: bool,
+    pub created_at: Option<String>,
+    pub updated_at: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CreateInvoiceTemplate {
+    pub name: String,
+    pub layout: PageLayout,
+    pub logo_path: Option<String>,
+    pub header_template: Option<String>,
+    pub footer_template: Option<String>,
+    pub show_qr_code: bool,
+    pub show_hsn_summary: bool,
+    pub show_amount_in_words: bool,
+    pub show_signature: bool,
+    #[serde(default)]
+    pub show_barcode: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct UpdateInvoiceTemplate {
+    pub name: Option<String>,
+    pub layout: Option<PageLayout>,
+    // An empty string clears the stored value
+    pub logo_path: Option<String>,
+    pub header_template: Option<String>,
+    pub footer_template: Option<String>,
+    pub show_qr_code: Option<bool>,
+    pub show_hsn_summary: Option<bool>,
+    pub show_amount_in_words: Option<bool>,
+    pub show_signature: Option<bool>,
+    pub show_barcode: Option<bool>,
+}
+
+const SELECT_TEMPLATE: &str = "
+    SELECT id, name, layout, logo_path, header_template, footer_template, show_qr_code,
+           show_hsn_summary, show_amount_in_words, show_signature, show_barcode, is_default,
+           created_at, updated_at
+    FROM invoice_templates";
+
+fn template_from_row(row: &Row) -> rusqlite::Result<InvoiceTemplate> {
+    let layout: String = row.get("layout")?;
+    Ok(InvoiceTemplate {
+        id: row.get("id")?,
+        name: row.get("name")?,
+        layout: PageLayout::parse(&layout).unwrap_or(PageLayout::A4),
+        logo_path: row.get("logo_path")?,
+        header_template: row.get("header_template")?,
+        footer_template: row.get("footer_template")?,
+        show_qr_code: row.get("show_qr_code")?,
+        show_hsn_summary: row.get("show_hsn_summary")?,
+        show_amount_in_words: row.get("show_amount_in_words")?,
+        show_signature: row.get("show_signature")?,
+        show_barcode: row.get("show_barcode")?,
+        is_default: row.get("is_default")?,
+        created_at: row.get("created_at")?,
+        updated_at: row.get("updated_at")?,
+    })
+}
+
+pub fn get_template_by_id(conn: &Connection, id: i64) -> Result<Option<InvoiceTemplate>, AppError> {
+    conn.query_row(&format!("{} WHERE id = ?1", SELECT_TEMPLATE), params![id], template_from_row)
+        .optional()
+        .map_err(AppError::from)
+}
+
+// Falls back to the first template if none is marked as default
+pub fn get_default_template(conn: &Connection) -> Result<InvoiceTemplate, AppError> {
+    conn.query_row(
+        &format!("{} ORDER BY is_default DESC, id LIMIT 1", SELECT_TEMPLATE),
+        [],
+        template_from_row,
+    )
+    .optional()?
+    .ok_or_else(|| AppError::invalid("No invoice templates are configured"))
+}
+
+pub fn resolve_template(
+    conn: &Connection,
+    template_id: Option<i64>,
+) -> Result<InvoiceTemplate, AppError> {
+    match template_id {
+        Some(id) => get_template_by_id(conn, id)?
+            .ok_or_else(|| AppError::not_found("Invoice template not found")),
+        None => get_default_template(conn),
+    }
+}
+
+// Tera errors keep the useful detail (line, unknown variable) in their source chain
+pub(crate) fn describe_tera_error(error: tera::Error) -> String {
+    let mut message = error.to_string();
+    let mut source = std::error::Error::source(&error);
+    while let Some(cause) = source {
+        message.push_str(": ");
+        message.push_str(&cause.to_string());
+        source = cause.source();
+    }
+    message
+}
+
+pub fn render_text(template: &str, context: &Context) -> Result<String, String> {
+    Tera::one_off(template, context, false)
+        .map_err(|e| format!("Invoice template error: {}", describe_tera_error(e)))
+}
+
+fn check_template_syntax(field: &str, label: &str, template: Option<&str>) -> Result<(), AppError> {
+    if let Some(template) = template.filter(|t| !t.trim().is_empty()) {
+        if template.len() > 2000 {
+            return Err(AppError::validation(
+                field,
+                format!("{} must be 2000 characters or less", label),
+            ));
+        }
+        Tera::default().add_raw_template(label, template).map_err(|e| {
+            AppError::validation(
+                field,
+                format!("{} is not a valid template: {}", label, describe_tera_error(e)),
+            )
+        })?;
+    }
+    Ok(())
+}
+
+fn check_logo_path(logo_path: Option<&str>) -> Result<(), AppError> {
+    if let Some(path) = logo_path.map(str::trim).filter(|p| !p.is_empty()) {
+        let lower = path.to_lowercase();
+        if !(lower.ends_with(".png") || lower.ends_with(".jpg") || lower.ends_with(".jpeg")) {
+            return Err(AppError::validation("logo_path", "Logo must be a PNG or JPEG file"));
+        }
+        if !std::path::Path::new(path).is_file() {
+            return Err(AppError::validation(
+                "logo_path",
+                format!("Logo file {} does not exist", path),
+            ));
+        }
+    }
+    Ok(())
+}
+
+fn validate_name(name: &str) -> Result<(), AppError> {
+    if name.trim().is_empty() {
+        return Err(AppError::validation("name", "Template name is required"));
+    }
+    if name.len() > 100 {
+        return Err(AppError::validation("name", "Template name must be 100 characters or less"));
+    }
+    Ok(())
+}
+
+fn validate_create(template: &CreateInvoiceTemplate) -> Result<(), AppError> {
+    validate_name(&template.name)?;
+    check_logo_path(template.logo_path.as_deref())?;
+    check_template_syntax(
+        "header_template",
+        "Header template",
+        template.header_template.as_deref(),
+    )?;
+    check_template_syntax("footer_template", "Footer template", template.footer_template.as_deref())
+}
+
+fn validate_update(template: &UpdateInvoiceTemplate) -> Result<(), AppError> {
+    if let Some(name) = &template.name {
+        validate_name(name)?;
+    }
+    check_logo_path(template.logo_path.as_deref())?;
+    check_template_syntax(
+        "header_template",
+        "Header template",
+        template.header_template.as_deref(),
+    )?;
+    check_template_syntax("footer_template", "Footer template", template.footer_template.as_deref())
+}
+
+fn map_write_error(e: rusqlite::Error) -> AppError {
+    let message = e.to_string();
+    if message.contains("UNIQUE constraint failed") {
+        return AppError::conflict("name", "An invoice template with this name already exists");
+    }
+    AppError::from(e)
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn list_invoice_templates(
+    pool: State<'_, DbPool>,
+) -> Result<Vec<InvoiceTemplate>, AppError> {
+    let conn = db::get_conn(&pool)?;
+    let mut stmt = conn.prepare(&format!("{} ORDER BY is_default DESC, name", SELECT_TEMPLATE))?;
+    let templates = stmt.query_map([], template_from_row)?.collect::<Result<Vec<_>, _>>()?;
+    Ok(templates)
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn create_invoice_template(
+    pool: State<'_, DbPool>,
+    template: CreateInvoiceTemplate,
+) -> Result<InvoiceTemplate, AppError> {
+    validate_create(&template)?;
+
+    let conn = db::get_conn(&pool)?;
+    conn.execute(
+        "INSERT INTO invoice_templates (name, layout, logo_path, header_template, footer_template,
+                                        show_qr_code, show_hsn_summary, show_amount_in_words,
+                                        show_signature, show_barcode)
+         VALUES (?1, ?2, NULLIF(?3, ''), NULLIF(?4, ''), NULLIF(?5, ''), ?6, ?7, ?8, ?9, ?10)",
+        params![
+            template.name.trim(),
+            template.layout.as_str(),
+            template.logo_path.as_deref().map(str::trim),
+            template.header_template,
+            template.footer_template,
+            template.show_qr_code,
+            template.show_hsn_summary,
+            template.show_amount_in_words,
+            template.show_signature,
+            template.show_barcode
+        ],
+    )
+    .map_err(map_write_error)?;
+
+    get_template_by_id(&conn, conn.last_insert_rowid())?
+        .ok_or_else(|| AppError::not_found("Invoice template not found after creation"))
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn update_invoice_template(
+    pool: State<'_, DbPool>,
+    id: i64,
+    template: UpdateInvoiceTemplate,
+) -> Result<InvoiceTemplate, AppError> {
+    validate_update(&template)?;
+
+    let conn = db::get_conn(&pool)?;
+    let changed = conn
+        .execute(
+            "UPDATE invoice_templates SET
+                name = COALESCE(?1, name),
+                layout = COALESCE(?2, layout),
+                logo_path = CASE WHEN ?3 IS NULL THEN logo_path ELSE NULLIF(?3, '') END,
+                header_template =
+                    CASE WHEN ?4 IS NULL THEN header_template ELSE NULLIF(?4, '') END,
+                footer_template =
+                    CASE WHEN ?5 IS NULL THEN footer_template ELSE NULLIF(?5, '') END,
+                show_qr_code = COALESCE(?6, show_qr_code),
+                show_hsn_summary = COALESCE(?7, show_hsn_summary),
+                show_amount_in_words = COALESCE(?8, show_amount_in_words),
+                show_signature = COALESCE(?9, show_signature),
+                show_barcode = COALESCE(?10, show_barcode),
+                updated_at = CURRENT_TIMESTAMP
+             WHERE id = ?11",
+            params![
+                template.name.as_deref().map(str::trim),
+                template.layout.map(|l| l.as_str()),
+                template.logo_path.as_deref().map(str::trim),
+                template.header_template,
+                template.footer_template,
+                template.show_qr_code,
+                template.show_hsn_summary,
+                template.show_amount_in_words,
+                template.show_signature,
+                template.show_barcode,
+                id
+            ],
+        )
+        .map_err(map_write_error)?;
+
+    if changed == 0 {
+        return Err(AppError::not_found("Invoice template not found"));
+    }
+
+    get_template_by_id(&conn, id)?
+        .ok_or_else(|| AppError::not_found("Invoice template not found after update"))
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn delete_invoice_template(pool: State<'_, DbPool>, id: i64) -> Result<(), AppError> {
+    let conn = db::get_conn(&pool)?;
+    let template = get_template_by_id(&conn, id)?
+        .ok_or_else(|| AppError::not_found("Invoice template not found"))?;
+    if template.is_default {
+        return Err(AppError::invalid("Choose another default template before deleting this one"));
+    }
+
+    conn.execute("DELETE FROM invoice_templates WHERE id = ?1", params![id])?;
+    Ok(())
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn set_default_invoice_template(
+    pool: State<'_, DbPool>,
+    id: i64,
+) -> Result<InvoiceTemplate, AppError> {
+    let mut conn = db::get_conn(&pool)?;
+    let tx = conn.transaction()?;
+    if get_template_by_id(&tx, id)?.is_none() {
+        return Err(AppError::not_found("Invoice template not found"));
+    }
+
+    // Clear first; the partial unique index allows only one default row
+    tx.execute(
+        "UPDATE invoice_templates SET is_default = 0, updated_at = CURRENT_TIMESTAMP
+         WHERE is_default = 1",
+        [],
+    )?;
+    tx.execute(
+        "UPDATE invoice_templates SET is_default = 1, updated_at = CURRENT_TIMESTAMP
+         WHERE id = ?1",
+        params![id],
+    )?;
+
+    let template = get_template_by_id(&tx, id)?
+        .ok_or_else(|| AppError::internal("Invoice template not found after update"))?;
+    tx.commit()?;
+    Ok(template)
+}
+
+// Renders an invoice with the given template without writing a file
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn preview_invoice_template(
+    pool: State<'_, DbPool>,
+    template_id: i64,
+    invoice_id: i64,
+    company_id: i64,
+) -> Result<RenderedPdf, AppError> {
+    let (template, document) = {
+        let conn = db::get_conn(&pool)?;
+        let template = get_template_by_id(&conn, template_id)?
+            .ok_or_else(|| AppError::not_found("Invoice template not found"))?;
+        (template, invoice_pdf::load_document(&conn, invoice_id, company_id)?)
+    };
+    Ok(invoice_pdf::render_to_output(&document, &template, None)?)
+}
@@ -0,0 +1,1612 @@
+use chrono::NaiveDate;
+use rusqlite::types::Value;
+use rusqlite::{params, Connection, OptionalExtension, Row};
+use serde::{Deserialize, Serialize};
+use tauri::State;
+
+use crate::audit::{self, AuditAction};
+use crate::batches;
+use crate::credit_notes;
+use crate::currencies;
+use crate::customers;
+use crate::db::{self, DbPool};
+use crate::einvoice;
+use crate::error::AppError;
+use crate::exports::{self, ExportDetails, ExportMode};
+use crate::financial_years;
+use crate::hsn;
+use crate::items;
+use crate::listing::{self, ListPage, ListQuery, SqlFilter};
+use crate::numbering::{self, DocumentType};
+use crate::place_of_supply::{self, SupplyKind};
+use crate::rounding::{self, RoundingMode};
+use crate::sales_orders;
+use crate::stock;
+use crate::tax::{self, InvoiceDiscount, TaxLineInput, AMOUNT_TOLERANCE};
+use crate::tcs;
+use crate::units;
+use crate::validation_rules::{self, RuleEntity};
+use crate::webhooks::{self, WebhookEvent};
+
+pub use crate::tax::round2;
+
+// Invoice data model
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum InvoiceStatus {
+    Draft,
+    Issued,
+    Cancelled,
+}
+
+impl InvoiceStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            InvoiceStatus::Draft => "draft",
+            InvoiceStatus::Issued => "issued",
+            InvoiceStatus::Cancelled => "cancelled",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "draft" => Some(InvoiceStatus::Draft),
+            "issued" => Some(InvoiceStatus::Issued),
+            "cancelled" => Some(InvoiceStatus::Cancelled),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Invoice {
+    pub id: Option<i64>,
+    pub company_id: i64,
+    pub invoice_number: String,
+    pub invoice_date: String,
+    pub customer_id: i64,
+    pub place_of_supply: String,
+    #[serde(default)]
+    pub supply_kind: SupplyKind,
+    // Present exactly when supply_kind is Export
+    #[serde(default)]
+    pub export: Option<ExportDetails>,
+    // SEZ supplies only; without it IGST is paid
+    #[serde(default)]
+    pub sez_mode: Option<ExportMode>,
+    // Invoice-level discount, shared across the lines before tax; see tax::InvoiceDiscount
+    #[serde(default)]
+    pub discount_percent: f64,
+    #[serde(default)]
+    pub discount_amount: f64,
+    pub taxable_value: f64,
+    pub cgst_amount: f64,
+    pub sgst_amount: f64,
+    pub igst_amount: f64,
+    // Compensation cess, the sum of the lines' cess
+    #[serde(default)]
+    pub cess_amount: f64,
+    // Under reverse charge the recipient pays the tax: the supplier's tax amounts above are zero
+    // and the tax the supply would have carried is kept in the rcm_* amounts
+    #[serde(default)]
+    pub reverse_charge: bool,
+    #[serde(default)]
+    pub rcm_cgst_amount: f64,
+    #[serde(default)]
+    pub rcm_sgst_amount: f64,
+    #[serde(default)]
+    pub rcm_igst_amount: f64,
+    #[serde(default)]
+    pub rcm_cess_amount: f64,
+    // TCS under section 206C(1H), worked out by the tcs module and included in total_amount
+    #[serde(default)]
+    pub tcs_base: f64,
+    #[serde(default)]
+    pub tcs_amount: f64,
+    // Rounding difference included in total_amount; see rounding::RoundingMode
+    pub round_off: f64,
+    pub total_amount: f64,
+    // Billing currency and its rate in rupees per unit; the amounts above stay in rupees and the
+    // foreign_* amounts are their equivalents in the billing currency. See currencies.
+    #[serde(default = "currencies::base_currency")]
+    pub currency: String,
+    #[serde(default = "currencies::unit_rate")]
+    pub exchange_rate: f64,
+    #[serde(default)]
+    pub foreign_taxable_value: f64,
+    #[serde(default)]
+    pub foreign_total_amount: f64,
+    // Sum of receipt allocations; maintained by the receipts module, never written from here
+    #[serde(default)]
+    pub amount_received: f64,
+    pub status: InvoiceStatus,
+    pub notes: Option<String>,
+    pub created_at: Option<String>,
+    pub updated_at: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CreateInvoice {
+    pub company_id: i64,
+    // Left empty to take the next number from the invoice sequence
+    #[serde(default)]
+    pub invoice_number: String,
+    pub invoice_date: String,
+    pub customer_id: i64,
+    // Left empty to use the customer's state
+    #[serde(default)]
+    pub place_of_supply: String,
+    #[serde(default)]
+    pub supply_kind: SupplyKind,
+    #[serde(default)]
+    pub export: Option<ExportDetails>,
+    #[serde(default)]
+    pub sez_mode: Option<ExportMode>,
+    #[serde(default)]
+    pub discount_percent: f64,
+    #[serde(default)]
+    pub discount_amount: f64,
+    #[serde(default)]
+    pub reverse_charge: bool,
+    pub taxable_value: f64,
+    // Tax amounts are recomputed by the backend; only their total is taken from here
+    pub cgst_amount: f64,
+    pub sgst_amount: f64,
+    pub igst_amount: f64,
+    #[serde(default)]
+    pub cess_amount: f64,
+    pub total_amount: f64,
+    // Rupees when not given; a foreign currency without a rate uses the rate on the invoice date
+    #[serde(default)]
+    pub currency: Option<String>,
+    #[serde(default)]
+    pub exchange_rate: Option<f64>,
+    pub status: Option<InvoiceStatus>,
+    pub notes: Option<String>,
+    #[serde(default)]
+    pub lines: Vec<InvoiceLineInput>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct UpdateInvoice {
+    pub invoice_number: Option<String>,
+    pub invoice_date: Option<String>,
+    pub customer_id: Option<i64>,
+    pub place_of_supply: Option<String>,
+    pub supply_kind: Option<SupplyKind>,
+    // Replaces the stored export details; cleared when the supply kind changes from export
+    pub export: Option<ExportDetails>,
+    // Cleared, like export, when the supply kind changes from SEZ
+    pub sez_mode: Option<ExportMode>,
+    pub discount_percent: Option<f64>,
+    pub discount_amount: Option<f64>,
+    pub reverse_charge: Option<bool>,
+    pub taxable_value: Option<f64>,
+    pub cgst_amount: Option<f64>,
+    pub sgst_amount: Option<f64>,
+    pub igst_amount: Option<f64>,
+    #[serde(default)]
+    pub cess_amount: Option<f64>,
+    pub total_amount: Option<f64>,
+    // A new currency without a rate takes the rate on the invoice date
+    #[serde(default)]
+    pub currency: Option<String>,
+    #[serde(default)]
+    pub exchange_rate: Option<f64>,
+    pub status: Option<InvoiceStatus>,
+    pub notes: Option<String>,
+    // When present, replaces every existing line of the invoice
+    pub lines: Option<Vec<InvoiceLineInput>>,
+}
+
+// Invoice line data model
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct InvoiceLine {
+    pub id: Option<i64>,
+    pub invoice_id: i64,
+    pub line_no: i64,
+    pub description: String,
+    pub hsn_code: String,
+    pub quantity: f64,
+    pub rate: f64,
+    pub discount_percent: f64,
+    pub discount: f64,
+    // This line's share of the invoice-level discount
+    pub invoice_discount: f64,
+    pub taxable_value: f64,
+    pub gst_rate: f64,
+    pub cgst_amount: f64,
+    pub sgst_amount: f64,
+    pub igst_amount: f64,
+    // Compensation cess as a percentage of the taxable value
+    pub cess_rate: f64,
+    pub cess_amount: f64,
+    pub item_id: Option<i64>,
+    pub unit_id: Option<i64>,
+    // The sales order line this line fulfils
+    pub sales_order_line_id: Option<i64>,
+    pub batch_id: Option<i64>,
+    // Read through from the batch master
+    pub batch_number: Option<String>,
+    pub serial_numbers: Vec<String>,
+    // GST code of the line's unit, read through from the units master
+    pub uqc: Option<String>,
+}
+
+impl InvoiceLine {
+    // Line and invoice discounts together, i.e. quantity x rate less the taxable value
+    pub fn total_discount(&self) -> f64 {
+        round2(round2(self.quantity * self.rate) - self.taxable_value)
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct InvoiceLineInput {
+    pub description: String,
+    pub hsn_code: String,
+    pub quantity: f64,
+    pub rate: f64,
+    // Percentage discount is applied before the flat discount
+    #[serde(default)]
+    pub discount_percent: f64,
+    #[serde(default)]
+    pub discount: f64,
+    // Worked out by the backend from the invoice discount
+    #[serde(default)]
+    pub invoice_discount: f64,
+    pub taxable_value: f64,
+    pub gst_rate: f64,
+    pub cgst_amount: f64,
+    pub sgst_amount: f64,
+    pub igst_amount: f64,
+    #[serde(default)]
+    pub cess_rate: f64,
+    // Worked out by the backend from the cess rate
+    #[serde(default)]
+    pub cess_amount: f64,
+    #[serde(default)]
+    pub item_id: Option<i64>,
+    #[serde(default)]
+    pub unit_id: Option<i64>,
+    #[serde(default)]
+    pub sales_order_line_id: Option<i64>,
+    // Required on issued invoices for batch-tracked items
+    #[serde(default)]
+    pub batch_id: Option<i64>,
+    // One per unit sold, required on issued invoices for serial-tracked items
+    #[serde(default)]
+    pub serial_numbers: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct InvoiceWithLines {
+    #[serde(flatten)]
+    pub invoice: Invoice,
+    pub lines: Vec<InvoiceLine>,
+}
+
+// Returned by create/update so non-blocking issues (e.g. HSN rate mismatches) reach the user
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SavedInvoice {
+    #[serde(flatten)]
+    pub invoice: Invoice,
+    pub warnings: Vec<String>,
+}
+
+const SELECT_INVOICE: &str = "
+    SELECT id, company_id, invoice_number, invoice_date, customer_id, place_of_supply, supply_kind,
+           discount_percent, discount_amount, taxable_value, cgst_amount, sgst_amount, igst_amount,
+           cess_amount, reverse_charge, rcm_cgst_amount, rcm_sgst_amount, rcm_igst_amount,
+           rcm_cess_amount, tcs_base,
+           tcs_amount, round_off, total_amount, amount_received, status, notes, created_at,
+           updated_at, export_mode, export_currency, export_exchange_rate, shipping_bill_number,
+           shipping_bill_date, port_code, sez_mode, currency_code, exchange_rate,
+           foreign_taxable_value, foreign_total_amount
+    FROM invoices";
+
+const SELECT_INVOICE_LINE: &str = "
+    SELECT id, invoice_id, line_no, description, hsn_code, quantity, rate, discount_percent,
+           discount, invoice_discount, taxable_value, gst_rate, cgst_amount, sgst_amount,
+           igst_amount, cess_rate, cess_amount, item_id, unit_id, sales_order_line_id, batch_id,
+           serial_numbers,
+           (SELECT uqc FROM units u WHERE u.id = invoice_lines.unit_id) AS uqc,
+           (SELECT batch_number FROM batches b WHERE b.id = invoice_lines.batch_id)
+               AS batch_number
+    FROM invoice_lines";
+
+pub const INVOICE_DATE_FORMAT: &str = "%Y-%m-%d";
+
+fn invoice_from_row(row: &Row) -> rusqlite::Result<Invoice> {
+    let status: String = row.get("status")?;
+    let supply_kind: String = row.get("supply_kind")?;
+    let export_mode: Option<String> = row.get("export_mode")?;
+    let sez_mode: Option<String> = row.get("sez_mode")?;
+    let export = match export_mode.as_deref().and_then(ExportMode::parse) {
+        Some(mode) => Some(ExportDetails {
+            mode,
+            currency: row.get::<_, Option<String>>("export_currency")?.unwrap_or_default(),
+            exchange_rate: row.get::<_, Option<f64>>("export_exchange_rate")?.unwrap_or(1.0),
+            shipping_bill_number: row.get("shipping_bill_number")?,
+            shipping_bill_date: row.get("shipping_bill_date")?,
+            port_code: row.get("port_code")?,
+        }),
+        None => None,
+    };
+    Ok(Invoice {
+        id: row.get("id")?,
+        company_id: row.get("company_id")?,
+        invoice_number: row.get("invoice_number")?,
+        invoice_date: row.get("invoice_date")?,
+        customer_id: row.get("customer_id")?,
+        place_of_supply: row.get("place_of_supply")?,
+        supply_kind: SupplyKind::parse(&supply_kind).unwrap_or_default(),
+        export,
+        sez_mode: sez_mode.as_deref().and_then(ExportMode::parse),
+        discount_percent: row.get("discount_percent")?,
+        discount_amount: row.get("discount_amount")?,
+        taxable_value: row.get("taxable_value")?,
+        cgst_amount: row.get("cgst_amount")?,
+        sgst_amount: row.get("sgst_amount")?,
+        igst_amount: row.get("igst_amount")?,
+        cess_amount: row.get("cess_amount")?,
+        reverse_charge: row.get("reverse_charge")?,
+        rcm_cgst_amount: row.get("rcm_cgst_amount")?,
+        rcm_sgst_amount: row.get("rcm_sgst_amount")?,
+        rcm_igst_amount: row.get("rcm_igst_amount")?,
+        rcm_cess_amount: row.get("rcm_cess_amount")?,
+        tcs_base: row.get("tcs_base")?,
+        tcs_amount: row.get("tcs_amount")?,
+        round_off: row.get("round_off")?,
+        total_amount: row.get("total_amount")?,
+        currency: row.get("currency_code")?,
+        exchange_rate: row.get("exchange_rate")?,
+        foreign_taxable_value: row.get("foreign_taxable_value")?,
+        foreign_total_amount: row.get("foreign_total_amount")?,
+        amount_received: row.get("amount_received")?,
+        status: InvoiceStatus::parse(&status).unwrap_or(InvoiceStatus::Draft),
+        notes: row.get("notes")?,
+        created_at: row.get("created_at")?,
+        updated_at: row.get("updated_at")?,
+    })
+}
+
+fn invoice_line_from_row(row: &Row) -> rusqlite::Result<InvoiceLine> {
+    let serial_numbers: Option<String> = row.get("serial_numbers")?;
+    Ok(InvoiceLine {
+        id: row.get("id")?,
+        invoice_id: row.get("invoice_id")?,
+        line_no: row.get("line_no")?,
+        description: row.get("description")?,
+        hsn_code: row.get("hsn_code")?,
+        quantity: row.get("quantity")?,
+        rate: row.get("rate")?,
+        discount_percent: row.get("discount_percent")?,
+        discount: row.get("discount")?,
+        invoice_discount: row.get("invoice_discount")?,
+        taxable_value: row.get("taxable_value")?,
+        gst_rate: row.get("gst_rate")?,
+        cgst_amount: row.get("cgst_amount")?,
+        sgst_amount: row.get("sgst_amount")?,
+        igst_amount: row.get("igst_amount")?,
+        cess_rate: row.get("cess_rate")?,
+        cess_amount: row.get("cess_amount")?,
+        item_id: row.get("item_id")?,
+        unit_id: row.get("unit_id")?,
+        sales_order_line_id: row.get("sales_order_line_id")?,
+        batch_id: row.get("batch_id")?,
+        batch_number: row.get("batch_number")?,
+        serial_numbers: serial_numbers
+            .and_then(|serials| serde_json::from_str(&serials).ok())
+            .unwrap_or_default(),
+        uqc: row.get("uqc")?,
+    })
+}
+
+// Validates a complete invoice; updates are merged onto the stored row before calling this
+pub fn validate_invoice(conn: &Connection, invoice: &Invoice) -> Result<(), AppError> {
+    if invoice.company_id <= 0 {
+        return Err(AppError::validation("company_id", "Company is required"));
+    }
+
+    if invoice.invoice_number.trim().is_empty() {
+        return Err(AppError::validation(
+            "invoice_number",
+            "Invoice number is required",
+        ));
+    }
+    if invoice.invoice_number.len() > 50 {
+        return Err(AppError::validation(
+            "invoice_number",
+            "Invoice number must be 50 characters or less",
+        ));
+    }
+
+    if NaiveDate::parse_from_str(invoice.invoice_date.trim(), INVOICE_DATE_FORMAT).is_err() {
+        return Err(AppError::validation(
+            "invoice_date",
+            "Invoice date must be a valid date in YYYY-MM-DD format",
+        ));
+    }
+    financial_years::ensure_period_open(conn, invoice.company_id, &invoice.invoice_date)?;
+
+    if invoice.customer_id <= 0 {
+        return Err(AppError::validation("customer_id", "Customer is required"));
+    }
+    let customer_exists: bool = conn.query_row(
+        "SELECT EXISTS(SELECT 1 FROM customers
+                       WHERE id = ?1 AND company_id = ?2 AND deleted_at IS NULL)",
+        params![invoice.customer_id, invoice.company_id],
+        |row| row.get(0),
+    )?;
+    if !customer_exists {
+        return Err(AppError::validation(
+            "customer_id",
+            "Customer does not exist for this company",
+        ));
+    }
+
+    let place_of_supply = invoice.place_of_supply.trim();
+    if place_of_supply.len() != 2 || !place_of_supply.chars().all(|c| c.is_ascii_digit()) {
+        return Err(AppError::validation(
+            "place_of_supply",
+            "Place of supply must be a 2-digit state code",
+        ));
+    }
+
+    let amounts = [
+        ("taxable_value", "Taxable value", invoice.taxable_value),
+        ("cgst_amount", "CGST amount", invoice.cgst_amount),
+        ("sgst_amount", "SGST amount", invoice.sgst_amount),
+        ("igst_amount", "IGST amount", invoice.igst_amount),
+        ("cess_amount", "Cess amount", invoice.cess_amount),
+        (
+            "rcm_cgst_amount",
+            "Reverse charge CGST",
+            invoice.rcm_cgst_amount,
+        ),
+        (
+            "rcm_sgst_amount",
+            "Reverse charge SGST",
+            invoice.rcm_sgst_amount,
+        ),
+        (
+            "rcm_igst_amount",
+            "Reverse charge IGST",
+            invoice.rcm_igst_amount,
+        ),
+        (
+            "rcm_cess_amount",
+            "Reverse charge cess",
+            invoice.rcm_cess_amount,
+        ),
+        ("tcs_amount", "TCS amount", invoice.tcs_amount),
+        ("total_amount", "Total amount", invoice.total_amount),
+    ];
+    for (field, label, amount) in amounts {
+        if !amount.is_finite() || amount < 0.0 {
+            return Err(AppError::validation(
+                field,
+                format!("{} must be a non-negative number", label),
+            ));
+        }
+    }
+
+    if invoice.igst_amount > 0.0 && (invoice.cgst_amount > 0.0 || invoice.sgst_amount > 0.0) {
+        return Err(AppError::validation(
+            "igst_amount",
+            "An invoice cannot charge both IGST and CGST/SGST",
+        ));
+    }
+
+    exports::validate_export(invoice)?;
+    if invoice.reverse_charge {
+        if invoice.supply_kind == SupplyKind::Export {
+            return Err(AppError::validation(
+                "reverse_charge",
+                "Exports cannot be supplied under reverse charge",
+            ));
+        }
+        let registered: bool = conn.query_row(
+            "SELECT TRIM(gst_no) != '' FROM customers WHERE id = ?1",
+            params![invoice.customer_id],
+            |row| row.get(0),
+        )?;
+        if !registered {
+            return Err(AppError::validation(
+                "reverse_charge",
+                "Reverse charge applies only to customers with a GSTIN",
+            ));
+        }
+        if invoice.cgst_amount + invoice.sgst_amount + invoice.igst_amount + invoice.cess_amount
+            > 0.0
+        {
+            return Err(AppError::validation(
+                "reverse_charge",
+                "Reverse charge invoices cannot charge GST to the customer",
+            ));
+        }
+    } else if invoice.rcm_cgst_amount
+        + invoice.rcm_sgst_amount
+        + invoice.rcm_igst_amount
+        + invoice.rcm_cess_amount
+        > 0.0
+    {
+        return Err(AppError::validation(
+            "reverse_charge",
+            "Reverse charge tax can only be recorded on reverse charge invoices",
+        ));
+    }
+
+    if !invoice.round_off.is_finite() || invoice.round_off.abs() >= 1.0 {
+        return Err(AppError::validation(
+            "round_off",
+            "Round-off must be less than one rupee",
+        ));
+    }
+    let expected_total = invoice.taxable_value
+        + invoice.cgst_amount
+        + invoice.sgst_amount
+        + invoice.igst_amount
+        + invoice.cess_amount
+        + invoice.tcs_amount
+        + invoice.round_off;
+    if (expected_total - invoice.total_amount).abs() > AMOUNT_TOLERANCE {
+        return Err(AppError::validation(
+            "total_amount",
+            "Total amount must equal taxable value plus taxes, cess, TCS and round-off",
+        ));
+    }
+
+    if let Some(notes) = &invoice.notes {
+        if notes.len() > 1000 {
+            return Err(AppError::validation(
+                "notes",
+                "Notes must be 1000 characters or less",
+            ));
+        }
+    }
+
+    Ok(())
+}
+
+fn validate_line(index: usize, line: &InvoiceLineInput) -> Result<(), String> {
+    let line_no = index + 1;
+
+    if line.description.trim().is_empty() {
+        return Err(format!("Line {}: description is required", line_no));
+    }
+    if line.description.len() > 500 {
+        return Err(format!(
+            "Line {}: description must be 500 characters or less",
+            line_no
+        ));
+    }
+
+    let hsn_code = line.hsn_code.trim();
+    if !(4..=8).contains(&hsn_code.len()) || !hsn_code.chars().all(|c| c.is_ascii_digit()) {
+        return Err(format!("Line {}: HSN/SAC code must be 4 to 8 digits", line_no));
+    }
+
+    let amounts = [
+        ("quantity", line.quantity),
+        ("rate", line.rate),
+        ("discount", line.discount),
+        ("taxable value", line.taxable_value),
+        ("GST rate", line.gst_rate),
+        ("CGST amount", line.cgst_amount),
+        ("SGST amount", line.sgst_amount),
+        ("IGST amount", line.igst_amount),
+        ("cess rate", line.cess_rate),
+        ("cess amount", line.cess_amount),
+    ];
+    for (label, amount) in amounts {
+        if !amount.is_finite() || amount < 0.0 {
+            return Err(format!("Line {}: {} must be a non-negative number", line_no, label));
+        }
+    }
+
+    if line.quantity <= 0.0 {
+        return Err(format!("Line {}: quantity must be greater than zero", line_no));
+    }
+    if line.gst_rate > 100.0 {
+        return Err(format!("Line {}: GST rate cannot exceed 100%", line_no));
+    }
+    if line.cess_rate > 100.0 {
+        return Err(format!("Line {}: cess rate cannot exceed 100%", line_no));
+    }
+
+    let expected_taxable = round2(
+        round2(line.quantity * line.rate) - line.total_line_discount() - line.invoice_discount,
+    );
+    if (expected_taxable - line.taxable_value).abs() > AMOUNT_TOLERANCE {
+        return Err(format!(
+            "Line {}: taxable value must equal quantity x rate less discounts",
+            line_no
+        ));
+    }
+
+    if (line.cgst_amount - line.sgst_amount).abs() > AMOUNT_TOLERANCE {
+        return Err(format!("Line {}: CGST and SGST amounts must be equal", line_no));
+    }
+    if line.igst_amount > 0.0 && (line.cgst_amount > 0.0 || line.sgst_amount > 0.0) {
+        return Err(format!(
+            "Line {}: a line cannot charge both IGST and CGST/SGST",
+            line_no
+        ));
+    }
+
+    Ok(())
+}
+
+// Header totals must match the sum of the lines so reports never disagree with the line detail
+fn validate_lines(invoice: &Invoice, lines: &[InvoiceLineInput]) -> Result<(), AppError> {
+    for (index, line) in lines.iter().enumerate() {
+        validate_line(index, line).map_err(|e| AppError::validation("lines", e))?;
+    }
+
+    if lines.is_empty() {
+        return Ok(());
+    }
+
+    let sums = [
+        (
+            "taxable_value",
+            "Taxable value",
+            invoice.taxable_value,
+            lines.iter().map(|l| l.taxable_value).sum::<f64>(),
+        ),
+        (
+            "cgst_amount",
+            "CGST amount",
+            invoice.cgst_amount,
+            lines.iter().map(|l| l.cgst_amount).sum::<f64>(),
+        ),
+        (
+            "sgst_amount",
+            "SGST amount",
+            invoice.sgst_amount,
+            lines.iter().map(|l| l.sgst_amount).sum::<f64>(),
+        ),
+        (
+            "igst_amount",
+            "IGST amount",
+            invoice.igst_amount,
+            lines.iter().map(|l| l.igst_amount).sum::<f64>(),
+        ),
+        (
+            "cess_amount",
+            "Cess amount",
+            invoice.cess_amount,
+            lines.iter().map(|l| l.cess_amount).sum::<f64>(),
+        ),
+    ];
+    for (field, label, header, total) in sums {
+        if (header - round2(total)).abs() > AMOUNT_TOLERANCE {
+            return Err(AppError::validation(
+                field,
+                format!("{} must equal the sum of the invoice lines", label),
+            ));
+        }
+    }
+
+    Ok(())
+}
+
+fn taxes_differ(line: &InvoiceLineInput, other: &InvoiceLineInput) -> bool {
+    (line.cgst_amount - other.cgst_amount).abs() > AMOUNT_TOLERANCE
+        || (line.sgst_amount - other.sgst_amount).abs() > AMOUNT_TOLERANCE
+        || (line.igst_amount - other.igst_amount).abs() > AMOUNT_TOLERANCE
+        || (line.cess_amount - other.cess_amount).abs() > AMOUNT_TOLERANCE
+}
+
+fn map_write_error(e: rusqlite::Error) -> AppError {
+    let message = e.to_string();
+    if message.contains("UNIQUE constraint failed") {
+        return AppError::conflict(
+            "invoice_number",
+            "An invoice with this number already exists for this company",
+        );
+    }
+    AppError::from(e)
+}
+
+pub fn get_invoice_by_id(
+    conn: &Connection,
+    id: i64,
+    company_id: i64,
+) -> Result<Option<Invoice>, AppError> {
+    conn.query_row(
+        &format!(
+            "{} WHERE id = ?1 AND company_id = ?2 AND deleted_at IS NULL",
+            SELECT_INVOICE
+        ),
+        params![id, company_id],
+        invoice_from_row,
+    )
+    .optional()
+    .map_err(AppError::from)
+}
+
+impl InvoiceLineInput {
+    // Percentage then flat discount, in rupees
+    pub fn total_line_discount(&self) -> f64 {
+        round2(round2(self.quantity * self.rate) * self.discount_percent / 100.0 + self.discount)
+    }
+}
+
+impl From<InvoiceLine> for InvoiceLineInput {
+    fn from(line: InvoiceLine) -> Self {
+        InvoiceLineInput {
+            description: line.description,
+            hsn_code: line.hsn_code,
+            quantity: line.quantity,
+            rate: line.rate,
+            discount_percent: line.discount_percent,
+            discount: line.discount,
+            invoice_discount: line.invoice_discount,
+            taxable_value: line.taxable_value,
+            gst_rate: line.gst_rate,
+            cgst_amount: line.cgst_amount,
+            sgst_amount: line.sgst_amount,
+            igst_amount: line.igst_amount,
+            cess_rate: line.cess_rate,
+            cess_amount: line.cess_amount,
+            item_id: line.item_id,
+            unit_id: line.unit_id,
+            sales_order_line_id: line.sales_order_line_id,
+            batch_id: line.batch_id,
+            serial_numbers: line.serial_numbers,
+        }
+    }
+}
+
+impl CreateInvoice {
+    fn into_parts(self) -> (Invoice, Vec<InvoiceLineInput>) {
+        let invoice = Invoice {
+            id: None,
+            company_id: self.company_id,
+            invoice_number: self.invoice_number.trim().to_string(),
+            invoice_date: self.invoice_date.trim().to_string(),
+            customer_id: self.customer_id,
+            place_of_supply: self.place_of_supply.trim().to_string(),
+            supply_kind: self.supply_kind,
+            export: self.export.map(|mut details| {
+                exports::normalize(&mut details);
+                details
+            }),
+            sez_mode: self.sez_mode,
+            discount_percent: self.discount_percent,
+            discount_amount: round2(self.discount_amount),
+            taxable_value: round2(self.taxable_value),
+            cgst_amount: round2(self.cgst_amount),
+            sgst_amount: round2(self.sgst_amount),
+            igst_amount: round2(self.igst_amount),
+            cess_amount: round2(self.cess_amount),
+            reverse_charge: self.reverse_charge,
+            rcm_cgst_amount: 0.0,
+            rcm_sgst_amount: 0.0,
+            rcm_igst_amount: 0.0,
+            rcm_cess_amount: 0.0,
+            tcs_base: 0.0,
+            tcs_amount: 0.0,
+            round_off: 0.0,
+            total_amount: round2(self.total_amount),
+            currency: self.currency.unwrap_or_default(),
+            // Zero until currencies::apply_currency settles it
+            exchange_rate: self.exchange_rate.unwrap_or(0.0),
+            foreign_taxable_value: 0.0,
+            foreign_total_amount: 0.0,
+            amount_received: 0.0,
+            status: self.status.unwrap_or(InvoiceStatus::Draft),
+            notes: self.notes,
+            created_at: None,
+            updated_at: None,
+        };
+        (invoice, self.lines)
+    }
+}
+
+impl UpdateInvoice {
+    fn apply_to(self, invoice: &mut Invoice) -> Option<Vec<InvoiceLineInput>> {
+        if let Some(invoice_number) = self.invoice_number {
+            invoice.invoice_number = invoice_number.trim().to_string();
+        }
+        if let Some(invoice_date) = self.invoice_date {
+            invoice.invoice_date = invoice_date.trim().to_string();
+        }
+        if let Some(customer_id) = self.customer_id {
+            invoice.customer_id = customer_id;
+        }
+        if let Some(place_of_supply) = self.place_of_supply {
+            invoice.place_of_supply = place_of_supply.trim().to_string();
+        }
+        if let Some(supply_kind) = self.supply_kind {
+            invoice.supply_kind = supply_kind;
+            if supply_kind != SupplyKind::Export {
+                invoice.export = None;
+            }
+            if supply_kind != SupplyKind::Sez {
+                invoice.sez_mode = None;
+            }
+        }
+        if let Some(sez_mode) = self.sez_mode {
+            invoice.sez_mode = Some(sez_mode);
+        }
+        if let Some(mut export) = self.export {
+            exports::normalize(&mut export);
+            invoice.export = Some(export);
+        }
+        if let Some(discount_percent) = self.discount_percent {
+            invoice.discount_percent = discount_percent;
+        }
+        if let Some(discount_amount) = self.discount_amount {
+            invoice.discount_amount = round2(discount_amount);
+        }
+        if let Some(reverse_charge) = self.reverse_charge {
+            invoice.reverse_charge = reverse_charge;
+        }
+        if let Some(taxable_value) = self.taxable_value {
+            invoice.taxable_value = round2(taxable_value);
+        }
+        if let Some(cgst_amount) = self.cgst_amount {
+            invoice.cgst_amount = round2(cgst_amount);
+        }
+        if let Some(sgst_amount) = self.sgst_amount {
+            invoice.sgst_amount = round2(sgst_amount);
+        }
+        if let Some(igst_amount) = self.igst_amount {
+            invoice.igst_amount = round2(igst_amount);
+        }
+        if let Some(cess_amount) = self.cess_amount {
+            invoice.cess_amount = round2(cess_amount);
+        }
+        if let Some(total_amount) = self.total_amount {
+            invoice.total_amount = round2(total_amount);
+        }
+        if let Some(currency) = self.currency {
+            invoice.currency = currency;
+            invoice.exchange_rate = 0.0;
+        }
+        if let Some(exchange_rate) = self.exchange_rate {
+            invoice.exchange_rate = exchange_rate;
+        }
+        if let Some(status) = self.status {
+            invoice.status = status;
+        }
+        if let Some(notes) = self.notes {
+            invoice.notes = Some(notes);
+        }
+        self.lines
+    }
+}
+
+// Decides CGST/SGST or IGST from the company's state, the place of supply and the supply kind,
+// then recomputes every taxable value and tax amount with the tax module: line discounts first,
+// then the invoice discount shared across the lines. Without lines only the total of the
+// submitted header taxes is kept. An empty place of supply defaults to the customer's state.
+// Reverse charge invoices then move the tax into the rcm_* amounts; see shift_to_recipient.
+// Exports and SEZ supplies under LUT are zero-rated and charge no IGST at all. Supplies to SEZ
+// customers are always treated as SEZ supplies.
+pub fn apply_tax_split(
+    conn: &Connection,
+    invoice: &mut Invoice,
+    lines: &mut [InvoiceLineInput],
+) -> Result<(), AppError> {
+    if let Some(customer) =
+        customers::get_customer_by_id(conn, invoice.customer_id, invoice.company_id)?
+    {
+        invoice.supply_kind = place_of_supply::effective_kind(&customer, invoice.supply_kind);
+        if invoice.place_of_supply.is_empty() {
+            invoice.place_of_supply =
+                place_of_supply::default_place_of_supply(&customer, invoice.supply_kind);
+        }
+    }
+    let regime = place_of_supply::regime_for(
+        conn,
+        invoice.company_id,
+        &invoice.place_of_supply,
+        invoice.supply_kind,
+    )?;
+    let discount = InvoiceDiscount {
+        percent: invoice.discount_percent,
+        amount: invoice.discount_amount,
+    };
+
+    if lines.is_empty() {
+        if !discount.is_zero() {
+            return Err(AppError::validation(
+                "lines",
+                "Add line items to apply an invoice discount",
+            ));
+        }
+        // A saved reverse charge invoice carries its tax in the rcm_* amounts
+        let split = tax::split_tax(
+            invoice.cgst_amount
+                + invoice.sgst_amount
+                + invoice.igst_amount
+                + invoice.rcm_cgst_amount
+                + invoice.rcm_sgst_amount
+                + invoice.rcm_igst_amount,
+            regime,
+        );
+        invoice.cgst_amount = split.cgst_amount;
+        invoice.sgst_amount = split.sgst_amount;
+        invoice.igst_amount = split.igst_amount;
+        invoice.cess_amount = round2(invoice.cess_amount + invoice.rcm_cess_amount);
+        shift_to_recipient(invoice, lines);
+        if exports::under_lut(invoice) {
+            clear_tax(invoice, lines);
+        }
+        return Ok(());
+    }
+
+    let inputs: Vec<TaxLineInput> = lines
+        .iter()
+        .map(|line| TaxLineInput {
+            quantity: line.quantity,
+            rate: line.rate,
+            discount: line.discount,
+            discount_percent: line.discount_percent,
+            gst_rate: line.gst_rate,
+            cess_rate: line.cess_rate,
+        })
+        .collect();
+    let totals = tax::compute_totals(&inputs, discount, regime, RoundingMode::None)?;
+    for (line, computed) in lines.iter_mut().zip(&totals.lines) {
+        line.invoice_discount = computed.invoice_discount;
+        line.taxable_value = computed.taxable_value;
+        line.cgst_amount = computed.cgst_amount;
+        line.sgst_amount = computed.sgst_amount;
+        line.igst_amount = computed.igst_amount;
+        line.cess_amount = computed.cess_amount;
+    }
+    invoice.taxable_value = totals.taxable_value;
+    invoice.cgst_amount = totals.cgst_amount;
+    invoice.sgst_amount = totals.sgst_amount;
+    invoice.igst_amount = totals.igst_amount;
+    invoice.cess_amount = totals.cess_amount;
+    shift_to_recipient(invoice, lines);
+    if exports::under_lut(invoice) {
+        clear_tax(invoice, lines);
+    }
+    Ok(())
+}
+
+// Under reverse charge the computed tax is recorded as the recipient's liability and the
+// supplier charges none, on the header and on every line
+fn shift_to_recipient(invoice: &mut Invoice, lines: &mut [InvoiceLineInput]) {
+    if !invoice.reverse_charge {
+        invoice.rcm_cgst_amount = 0.0;
+        invoice.rcm_sgst_amount = 0.0;
+        invoice.rcm_igst_amount = 0.0;
+        invoice.rcm_cess_amount = 0.0;
+        return;
+    }
+    invoice.rcm_cgst_amount = invoice.cgst_amount;
+    invoice.rcm_sgst_amount = invoice.sgst_amount;
+    invoice.rcm_igst_amount = invoice.igst_amount;
+    invoice.rcm_cess_amount = invoice.cess_amount;
+    clear_tax(invoice, lines);
+}
+
+fn clear_tax(invoice: &mut Invoice, lines: &mut [InvoiceLineInput]) {
+    invoice.cgst_amount = 0.0;
+    invoice.sgst_amount = 0.0;
+    invoice.igst_amount = 0.0;
+    invoice.cess_amount = 0.0;
+    for line in lines {
+        line.cgst_amount = 0.0;
+        line.sgst_amount = 0.0;
+        line.igst_amount = 0.0;
+        line.cess_amount = 0.0;
+    }
+}
+
+// Rounds the invoice total per the configured mode and records the difference as round-off.
+// The submitted total may be either the exact sum or the already-rounded figure.
+pub fn apply_rounding(conn: &Connection, invoice: &mut Invoice) -> Result<(), AppError> {
+    let exact = round2(
+        invoice.taxable_value
+            + invoice.cgst_amount
+            + invoice.sgst_amount
+            + invoice.igst_amount
+            + invoice.cess_amount
+            + invoice.tcs_amount,
+    );
+    let rounded = rounding::load_mode(conn)?.apply(exact);
+    if (invoice.total_amount - exact).abs() > AMOUNT_TOLERANCE
+        && (invoice.total_amount - rounded).abs() > AMOUNT_TOLERANCE
+    {
+        return Err(AppError::validation(
+            "total_amount",
+            format!(
+                "Total amount must equal taxable value plus taxes, cess and TCS ({:.2}, or {:.2} \
+                 after rounding)",
+                exact, rounded
+            ),
+        ));
+    }
+    invoice.round_off = round2(rounded - exact);
+    invoice.total_amount = rounded;
+    Ok(())
+}
+
+pub fn insert_invoice(conn: &Connection, invoice: &Invoice) -> Result<i64, AppError> {
+    let export = invoice.export.as_ref();
+    // Cached so bulk imports reuse one prepared statement
+    let mut stmt = conn
+        .prepare_cached(
+            "INSERT INTO invoices (company_id, invoice_number, invoice_date, customer_id, place_of_supply,
+                                   taxable_value, cgst_amount, sgst_amount, igst_amount, round_off,
+                                   total_amount, status, notes, supply_kind, discount_percent,
+                                   discount_amount, tcs_base, tcs_amount, reverse_charge,
+                                   rcm_cgst_amount, rcm_sgst_amount, rcm_igst_amount, export_mode,
+                                   export_currency, export_exchange_rate, shipping_bill_number,
+                                   shipping_bill_date, port_code, sez_mode, currency_code,
+                                   exchange_rate, foreign_taxable_value, foreign_total_amount,
+                                   cess_amount, rcm_cess_amount)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17,
+                     ?18, ?19, ?20, ?21, ?22, ?23, ?24, ?25, ?26, ?27, ?28, ?29, ?30, ?31, ?32, ?33,
+                     ?34, ?35)",
+        )?;
+    stmt.execute(params![
+        invoice.company_id,
+        invoice.invoice_number,
+        invoice.invoice_date,
+        invoice.customer_id,
+        invoice.place_of_supply,
+        invoice.taxable_value,
+        invoice.cgst_amount,
+        invoice.sgst_amount,
+        invoice.igst_amount,
+        invoice.round_off,
+        invoice.total_amount,
+        invoice.status.as_str(),
+        invoice.notes,
+        invoice.supply_kind.as_str(),
+        invoice.discount_percent,
+        invoice.discount_amount,
+        invoice.tcs_base,
+        invoice.tcs_amount,
+        invoice.reverse_charge,
+        invoice.rcm_cgst_amount,
+        invoice.rcm_sgst_amount,
+        invoice.rcm_igst_amount,
+        export.map(|details| details.mode.as_str()),
+        export.map(|details| details.currency.as_str()),
+        export.map(|details| details.exchange_rate),
+        export.and_then(|details| details.shipping_bill_number.as_deref()),
+        export.and_then(|details| details.shipping_bill_date.as_deref()),
+        export.and_then(|details| details.port_code.as_deref()),
+        invoice.sez_mode.map(|mode| mode.as_str()),
+        invoice.currency,
+        invoice.exchange_rate,
+        invoice.foreign_taxable_value,
+        invoice.foreign_total_amount,
+        invoice.cess_amount,
+        invoice.rcm_cess_amount
+    ])
+    .map_err(map_write_error)?;
+    Ok(conn.last_insert_rowid())
+}
+
+pub fn write_invoice(conn: &Connection, id: i64, invoice: &Invoice) -> Result<(), AppError> {
+    let export = invoice.export.as_ref();
+    conn.execute(
+        "UPDATE invoices SET
+            invoice_number = ?1,
+            invoice_date = ?2,
+            customer_id = ?3,
+            place_of_supply = ?4,
+            taxable_value = ?5,
+            cgst_amount = ?6,
+            sgst_amount = ?7,
+            igst_amount = ?8,
+            round_off = ?9,
+            total_amount = ?10,
+            status = ?11,
+            notes = ?12,
+            supply_kind = ?13,
+            discount_percent = ?14,
+            discount_amount = ?15,
+            tcs_base = ?16,
+            tcs_amount = ?17,
+            reverse_charge = ?18,
+            rcm_cgst_amount = ?19,
+            rcm_sgst_amount = ?20,
+            rcm_igst_amount = ?21,
+            export_mode = ?22,
+            export_currency = ?23,
+            export_exchange_rate = ?24,
+            shipping_bill_number = ?25,
+            shipping_bill_date = ?26,
+            port_code = ?27,
+            sez_mode = ?28,
+            currency_code = ?29,
+            exchange_rate = ?30,
+            foreign_taxable_value = ?31,
+            foreign_total_amount = ?32,
+            cess_amount = ?33,
+            rcm_cess_amount = ?34,
+            updated_at = CURRENT_TIMESTAMP
+         WHERE id = ?35 AND company_id = ?36",
+        params![
+            invoice.invoice_number,
+            invoice.invoice_date,
+            invoice.customer_id,
+            invoice.place_of_supply,
+            invoice.taxable_value,
+            invoice.cgst_amount,
+            invoice.sgst_amount,
+            invoice.igst_amount,
+            invoice.round_off,
+            invoice.total_amount,
+            invoice.status.as_str(),
+            invoice.notes,
+            invoice.supply_kind.as_str(),
+            invoice.discount_percent,
+            invoice.discount_amount,
+            invoice.tcs_base,
+            invoice.tcs_amount,
+            invoice.reverse_charge,
+            invoice.rcm_cgst_amount,
+            invoice.rcm_sgst_amount,
+            invoice.rcm_igst_amount,
+            export.map(|details| details.mode.as_str()),
+            export.map(|details| details.currency.as_str()),
+            export.map(|details| details.exchange_rate),
+            export.and_then(|details| details.shipping_bill_number.as_deref()),
+            export.and_then(|details| details.shipping_bill_date.as_deref()),
+            export.and_then(|details| details.port_code.as_deref()),
+            invoice.sez_mode.map(|mode| mode.as_str()),
+            invoice.currency,
+            invoice.exchange_rate,
+            invoice.foreign_taxable_value,
+            invoice.foreign_total_amount,
+            invoice.cess_amount,
+            invoice.rcm_cess_amount,
+            id,
+            invoice.company_id
+        ],
+    )
+    .map_err(map_write_error)?;
+    Ok(())
+}
+
+// Invoices dated within [from, to], oldest first; dates are YYYY-MM-DD strings
+pub fn get_invoices_in_range(
+    conn: &Connection,
+    company_id: i64,
+    from: &str,
+    to: &str,
+) -> Result<Vec<Invoice>, AppError> {
+    let mut stmt = conn.prepare(&format!(
+        "{} WHERE company_id = ?1 AND deleted_at IS NULL AND invoice_date BETWEEN ?2 AND ?3
+             ORDER BY invoice_date, invoice_number",
+        SELECT_INVOICE
+    ))?;
+    let invoices = stmt
+        .query_map(params![company_id, from, to], invoice_from_row)?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(invoices)
+}
+
+pub fn get_invoice_lines(conn: &Connection, invoice_id: i64) -> Result<Vec<InvoiceLine>, AppError> {
+    let mut stmt = conn.prepare(&format!(
+        "{} WHERE invoice_id = ?1 ORDER BY line_no",
+        SELECT_INVOICE_LINE
+    ))?;
+    let lines = stmt
+        .query_map(params![invoice_id], invoice_line_from_row)?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(lines)
+}
+
+// Replaces all lines of an invoice; callers run this inside the same transaction as the header write
+pub fn replace_invoice_lines(
+    conn: &Connection,
+    invoice_id: i64,
+    lines: &[InvoiceLineInput],
+) -> Result<(), AppError> {
+    conn.execute(
+        "DELETE FROM invoice_lines WHERE invoice_id = ?1",
+        params![invoice_id],
+    )?;
+
+    let mut stmt = conn
+        .prepare_cached(
+            "INSERT INTO invoice_lines (invoice_id, line_no, description, hsn_code, quantity, rate, discount,
+                                        taxable_value, gst_rate, cgst_amount, sgst_amount, igst_amount,
+                                        discount_percent, invoice_discount, item_id,
+                                        unit_id, sales_order_line_id, batch_id, serial_numbers,
+                                        cess_rate, cess_amount)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16,
+                     ?17, ?18, ?19, ?20, ?21)",
+        )?;
+    for (index, line) in lines.iter().enumerate() {
+        let serial_numbers = if line.serial_numbers.is_empty() {
+            None
+        } else {
+            let serials: Vec<&str> = line.serial_numbers.iter().map(|s| s.trim()).collect();
+            Some(serde_json::to_string(&serials).map_err(|e| e.to_string())?)
+        };
+        stmt.execute(params![
+            invoice_id,
+            index as i64 + 1,
+            line.description.trim(),
+            line.hsn_code.trim(),
+            line.quantity,
+            line.rate,
+            round2(line.discount),
+            round2(line.taxable_value),
+            line.gst_rate,
+            round2(line.cgst_amount),
+            round2(line.sgst_amount),
+            round2(line.igst_amount),
+            line.discount_percent,
+            round2(line.invoice_discount),
+            line.item_id,
+            line.unit_id,
+            line.sales_order_line_id,
+            line.batch_id,
+            serial_numbers,
+            line.cess_rate,
+            round2(line.cess_amount)
+        ])?;
+    }
+    Ok(())
+}
+
+pub fn get_invoice_with_lines_by_id(
+    conn: &Connection,
+    id: i64,
+    company_id: i64,
+) -> Result<Option<InvoiceWithLines>, String> {
+    match get_invoice_by_id(conn, id, company_id)? {
+        Some(invoice) => {
+            let lines = get_invoice_lines(conn, id)?;
+            Ok(Some(InvoiceWithLines { invoice, lines }))
+        }
+        None => Ok(None),
+    }
+}
+
+// Runs the company's invoice validation rules, which also see the customer's GSTIN, category
+// and registration type; returns the warnings of broken warning rules
+fn enforce_rules(conn: &Connection, invoice: &Invoice) -> Result<Vec<String>, AppError> {
+    let mut record = serde_json::to_value(invoice).map_err(|e| e.to_string())?;
+    if let Some(customer) =
+        customers::get_customer_by_id(conn, invoice.customer_id, invoice.company_id)?
+    {
+        let customer = customers::rule_record(&customer)?;
+        record["customer_gstin"] = customer["gst_no"].clone();
+        record["customer_category"] = customer["category"].clone();
+        record["customer_registration_type"] = customer["registration_type"].clone();
+    }
+    validation_rules::enforce(conn, invoice.company_id, RuleEntity::Invoice, &record)
+}
+
+// Creates an invoice inside the caller's transaction. With `derive_total` the total is worked
+// out from the lines, TCS and rounding instead of being checked against the one given.
+fn create_in(
+    conn: &Connection,
+    invoice: CreateInvoice,
+    derive_total: bool,
+) -> Result<SavedInvoice, AppError> {
+    let (mut invoice, mut lines) = invoice.into_parts();
+    if invoice.invoice_number.is_empty() {
+        invoice.invoice_number = numbering::allocate_number(
+            conn,
+            invoice.company_id,
+            DocumentType::Invoice,
+            &invoice.invoice_date,
+        )?;
+    }
+    apply_tax_split(conn, &mut invoice, &mut lines)?;
+    tcs::apply_tcs(conn, &mut invoice)?;
+    if derive_total {
+        invoice.total_amount = round2(
+            invoice.taxable_value
+                + invoice.cgst_amount
+                + invoice.sgst_amount
+                + invoice.igst_amount
+                + invoice.cess_amount
+                + invoice.tcs_amount,
+        );
+    }
+    apply_rounding(conn, &mut invoice)?;
+    currencies::apply_currency(conn, &mut invoice)?;
+    validate_invoice(conn, &invoice)?;
+    validate_lines(&invoice, &lines)?;
+    units::validate_line_units(conn, &lines)?;
+    items::validate_line_items(conn, invoice.company_id, &lines)?;
+    sales_orders::validate_fulfilment(conn, &invoice, &lines)?;
+    let tracking_warnings = batches::check_line_tracking(conn, &invoice, &lines)?;
+    let mut warnings = enforce_rules(conn, &invoice)?;
+    warnings.extend(hsn::rate_warnings(conn, &lines)?);
+    warnings.extend(stock::check_invoice_stock(conn, &invoice, &lines)?);
+    warnings.extend(tracking_warnings);
+
+    let id = insert_invoice(conn, &invoice)?;
+    replace_invoice_lines(conn, id, &lines)?;
+    let created = get_invoice_with_lines_by_id(conn, id, invoice.company_id)?
+        .ok_or_else(|| AppError::internal("Invoice not found after creation"))?;
+    audit::record(
+        conn,
+        invoice.company_id,
+        "invoice",
+        id,
+        AuditAction::Create,
+        None,
+        Some(&created),
+    )?;
+    webhooks::enqueue(conn, invoice.company_id, WebhookEvent::Created, &created)?;
+
+    Ok(SavedInvoice {
+        invoice: created.invoice,
+        warnings,
+    })
+}
+
+// For documents converted into invoices (e.g. quotations), whose totals predate TCS and rounding
+pub fn create_invoice_from_lines(
+    conn: &Connection,
+    invoice: CreateInvoice,
+) -> Result<SavedInvoice, AppError> {
+    create_in(conn, invoice, true)
+}
+
+// Creates an invoice with the totals as entered, on a connection the caller commits
+pub fn create_invoice_in(
+    conn: &Connection,
+    invoice: CreateInvoice,
+) -> Result<SavedInvoice, AppError> {
+    create_in(conn, invoice, false)
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn create_invoice(
+    pool: State<'_, DbPool>,
+    invoice: CreateInvoice,
+) -> Result<SavedInvoice, AppError> {
+    let mut conn = db::get_conn(&pool)?;
+    let tx = conn.transaction()?;
+    let saved = create_invoice_in(&tx, invoice)?;
+    tx.commit()?;
+    Ok(saved)
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn update_invoice(
+    pool: State<'_, DbPool>,
+    id: i64,
+    company_id: i64,
+    invoice: UpdateInvoice,
+) -> Result<SavedInvoice, AppError> {
+    let mut conn = db::get_conn(&pool)?;
+    let tx = conn.transaction()?;
+    let before = get_invoice_with_lines_by_id(&tx, id, company_id)?
+        .ok_or_else(|| AppError::not_found("Invoice not found"))?;
+    let mut existing = before.invoice.clone();
+
+    if existing.status == InvoiceStatus::Cancelled {
+        return Err(AppError::invalid("Cancelled invoices cannot be modified"));
+    }
+    // The IRP holds the registered copy; edits here would no longer match the IRN
+    if einvoice::get_einvoice_by_invoice_id(&tx, id)?.is_some() {
+        return Err(AppError::invalid(
+            "Invoices with a generated IRN cannot be modified",
+        ));
+    }
+    if credit_notes::has_active_notes(&tx, id)? {
+        return Err(
+            "Invoices with credit or debit notes cannot be modified; cancel the notes first".into(),
+        );
+    }
+    // Moving an invoice out of a locked month is as much a change as editing it there
+    financial_years::ensure_period_open(&tx, company_id, &existing.invoice_date)?;
+
+    let new_lines = invoice.apply_to(&mut existing);
+    let lines_submitted = new_lines.is_some();
+    let stored: Vec<InvoiceLineInput> =
+        before.lines.iter().cloned().map(InvoiceLineInput::from).collect();
+    let mut lines = new_lines.unwrap_or_else(|| stored.clone());
+    apply_tax_split(&tx, &mut existing, &mut lines)?;
+    tcs::apply_tcs(&tx, &mut existing)?;
+    apply_rounding(&tx, &mut existing)?;
+    currencies::apply_currency(&tx, &mut existing)?;
+    validate_invoice(&tx, &existing)?;
+    // Receipts already knocked off against the invoice must stay covered by it
+    if existing.amount_received > 0.0 {
+        if existing.status != InvoiceStatus::Issued {
+            return Err(
+                "Remove the receipt allocations before changing this invoice's status".into(),
+            );
+        }
+        if existing.total_amount + AMOUNT_TOLERANCE < existing.amount_received {
+            return Err(AppError::validation(
+                "total_amount",
+                format!(
+                    "Total amount cannot be less than the {:.2} already received",
+                    existing.amount_received
+                ),
+            ));
+        }
+    }
+
+    // Header-only edits must still agree with the stored lines, whose taxes are rewritten when a
+    // new place of supply or supply kind changes the split
+    validate_lines(&existing, &lines)?;
+    units::validate_line_units(&tx, &lines)?;
+    items::validate_line_items(&tx, existing.company_id, &lines)?;
+    sales_orders::validate_fulfilment(&tx, &existing, &lines)?;
+    let tracking_warnings = batches::check_line_tracking(&tx, &existing, &lines)?;
+    let mut warnings = enforce_rules(&tx, &existing)?;
+    warnings.extend(stock::check_invoice_stock(&tx, &existing, &lines)?);
+    warnings.extend(tracking_warnings);
+    write_invoice(&tx, id, &existing)?;
+    warnings.extend(if lines_submitted {
+        replace_invoice_lines(&tx, id, &lines)?;
+        hsn::rate_warnings(&tx, &lines)?
+    } else {
+        if lines.iter().zip(&stored).any(|(line, old)| taxes_differ(line, old)) {
+            replace_invoice_lines(&tx, id, &lines)?;
+        }
+        Vec::new()
+    });
+
+    let updated = get_invoice_with_lines_by_id(&tx, id, company_id)?
+        .ok_or_else(|| AppError::internal("Invoice not found after update"))?;
+    audit::record(
+        &tx,
+        company_id,
+        "invoice",
+        id,
+        AuditAction::Update,
+        Some(&before),
+        Some(&updated),
+    )?;
+    if updated.invoice.status == InvoiceStatus::Cancelled {
+        webhooks::enqueue(&tx, company_id, WebhookEvent::Cancelled, &updated)?;
+    }
+    tx.commit()?;
+
+    Ok(SavedInvoice {
+        invoice: updated.invoice,
+        warnings,
+    })
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn get_invoice(
+    pool: State<'_, DbPool>,
+    id: i64,
+    company_id: i64,
+) -> Result<Option<Invoice>, AppError> {
+    let conn = db::get_conn(&pool)?;
+    get_invoice_by_id(&conn, id, company_id)
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn get_invoice_with_lines(
+    pool: State<'_, DbPool>,
+    id: i64,
+    company_id: i64,
+) -> Result<Option<InvoiceWithLines>, AppError> {
+    let conn = db::get_conn(&pool)?;
+    Ok(get_invoice_with_lines_by_id(&conn, id, company_id)?)
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn delete_invoice(
+    pool: State<'_, DbPool>,
+    id: i64,
+    company_id: i64,
+) -> Result<(), AppError> {
+    let conn = db::get_conn(&pool)?;
+    soft_delete_invoice(&conn, id, company_id)
+}
+
+pub fn soft_delete_invoice(conn: &Connection, id: i64, company_id: i64) -> Result<(), AppError> {
+    let existing = get_invoice_with_lines_by_id(conn, id, company_id)?
+        .ok_or_else(|| AppError::not_found("Invoice not found"))?;
+
+    // Issued invoices are part of the tax record and must be cancelled instead
+    if existing.invoice.status != InvoiceStatus::Draft {
+        return Err(AppError::invalid(
+            "Only draft invoices can be deleted; cancel issued invoices instead",
+        ));
+    }
+    financial_years::ensure_period_open(conn, company_id, &existing.invoice.invoice_date)?;
+
+    // Moves the draft to the recycle bin; its number stays reserved until it is purged
+    conn.execute(
+        "UPDATE invoices SET deleted_at = CURRENT_TIMESTAMP WHERE id = ?1 AND company_id = ?2",
+        params![id, company_id],
+    )?;
+    audit::record(
+        conn,
+        company_id,
+        "invoice",
+        id,
+        AuditAction::Delete,
+        Some(&existing),
+        None,
+    )
+}
+
+#[derive(Debug, Serialize, Deserialize, Default)]
+pub struct InvoiceFilter {
+    // Matches the invoice number
+    pub search: Option<String>,
+    pub customer_id: Option<i64>,
+    pub status: Option<InvoiceStatus>,
+    pub from_date: Option<String>,
+    pub to_date: Option<String>,
+}
+
+const INVOICE_SORT_COLUMNS: &[(&str, &str)] = &[
+    ("invoice_date", "invoice_date"),
+    ("invoice_number", "invoice_number"),
+    ("total_amount", "total_amount"),
+    ("status", "status"),
+    ("created_at", "created_at"),
+];
+
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn list_invoices(
+    pool: State<'_, DbPool>,
+    company_id: i64,
+    query: Option<ListQuery<InvoiceFilter>>,
+) -> Result<ListPage<Invoice>, AppError> {
+    let conn = db::get_conn(&pool)?;
+    let query = query.unwrap_or_default();
+    let order_by = listing::sort_column(query.sort_by.as_deref(), INVOICE_SORT_COLUMNS)?;
+
+    let mut filter = SqlFilter::default();
+    filter.push("company_id = ? AND deleted_at IS NULL", Value::Integer(company_id));
+    filter.search(&["invoice_number"], query.filter.search.as_deref());
+    if let Some(customer_id) = query.filter.customer_id {
+        filter.push("customer_id = ?", Value::Integer(customer_id));
+    }
+    if let Some(status) = query.filter.status {
+        filter.push("status = ?", Value::Text(status.as_str().to_string()));
+    }
+    for (date, clause, field, label) in [
+        (
+            &query.filter.from_date,
+            "invoice_date >= ?",
+            "from_date",
+            "From",
+        ),
+        (&query.filter.to_date, "invoice_date <= ?", "to_date", "To"),
+    ] {
+        if let Some(date) = date.as_deref().map(str::trim).filter(|d| !d.is_empty()) {
+            let date = NaiveDate::parse_from_str(date, INVOICE_DATE_FORMAT).map_err(|_| {
+                AppError::validation(
+                    field,
+                    format!("{} date must be in YYYY-MM-DD format", label),
+                )
+            })?;
+            filter.push(
+                clause,
+                Value::Text(date.format(INVOICE_DATE_FORMAT).to_string()),
+            );
+        }
+    }
+    listing::fetch_page(
+        &conn,
+        SELECT_INVOICE,
+        &filter,
+        order_by,
+        "id",
+        &query,
+        invoice_from_row,
+    )
+}
@@ -0,0 +1,313 @@
+use rusqlite::{params, Connection, OptionalExtension, Row};
+use serde::{Deserialize, Serialize};
+use tauri::State;
+
+use crate::audit::{self, AuditAction};
+use crate::db::{self, DbPool};
+use crate::error::AppError;
+use crate::invoices::InvoiceLineInput;
+use crate::units;
+
+// Whether each sale of the item names the batch or the serial numbers it came from
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum ItemTracking {
+    #[default]
+    None,
+    Batch,
+    Serial,
+}
+
+impl ItemTracking {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ItemTracking::None => "none",
+            ItemTracking::Batch => "batch",
+            ItemTracking::Serial => "serial",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "none" => Some(ItemTracking::None),
+            "batch" => Some(ItemTracking::Batch),
+            "serial" => Some(ItemTracking::Serial),
+            _ => None,
+        }
+    }
+}
+
+// Item data model; `rate` is the default selling rate used when no price list applies
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Item {
+    pub id: i64,
+    pub company_id: i64,
+    pub code: String,
+    pub name: String,
+    pub hsn_code: String,
+    pub unit_id: Option<i64>,
+    pub gst_rate: f64,
+    pub rate: f64,
+    pub tracking: ItemTracking,
+    pub active: bool,
+    pub created_at: Option<String>,
+    pub updated_at: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SaveItem {
+    pub code: String,
+    pub name: String,
+    pub hsn_code: String,
+    #[serde(default)]
+    pub unit_id: Option<i64>,
+    pub gst_rate: f64,
+    #[serde(default)]
+    pub rate: f64,
+    #[serde(default)]
+    pub tracking: ItemTracking,
+    #[serde(default = "default_active")]
+    pub active: bool,
+}
+
+fn default_active() -> bool {
+    true
+}
+
+const SELECT_ITEM: &str = "
+    SELECT id, company_id, code, name, hsn_code, unit_id, gst_rate, rate, tracking, active,
+           created_at, updated_at
+    FROM items";
+
+fn item_from_row(row: &Row) -> rusqlite::Result<Item> {
+    let tracking: String = row.get("tracking")?;
+    Ok(Item {
+        id: row.get("id")?,
+        company_id: row.get("company_id")?,
+        code: row.get("code")?,
+        name: row.get("name")?,
+        hsn_code: row.get("hsn_code")?,
+        unit_id: row.get("unit_id")?,
+        gst_rate: row.get("gst_rate")?,
+        rate: row.get("rate")?,
+        tracking: ItemTracking::parse(&tracking).unwrap_or_default(),
+        active: row.get("active")?,
+        created_at: row.get("created_at")?,
+        updated_at: row.get("updated_at")?,
+    })
+}
+
+pub fn get_item_by_id(
+    conn: &Connection,
+    id: i64,
+    company_id: i64,
+) -> Result<Option<Item>, AppError> {
+    conn.query_row(
+        &format!("{} WHERE id = ?1 AND company_id = ?2", SELECT_ITEM),
+        params![id, company_id],
+        item_from_row,
+    )
+    .optional()
+    .map_err(AppError::from)
+}
+
+// Lines that name an item must name one of the invoice's company
+pub fn validate_line_items(
+    conn: &Connection,
+    company_id: i64,
+    lines: &[InvoiceLineInput],
+) -> Result<(), AppError> {
+    for (index, line) in lines.iter().enumerate() {
+        if let Some(item_id) = line.item_id {
+            if get_item_by_id(conn, item_id, company_id)?.is_none() {
+                return Err(AppError::validation(
+                    "lines",
+                    format!("Line {}: item not found", index + 1),
+                ));
+            }
+        }
+    }
+    Ok(())
+}
+
+fn validate(conn: &Connection, item: SaveItem) -> Result<SaveItem, AppError> {
+    let code = item.code.trim().to_ascii_uppercase();
+    if code.is_empty() || code.len() > 50 {
+        return Err(AppError::validation("code", "Item code must be 1 to 50 characters"));
+    }
+    let name = item.name.trim().to_string();
+    if name.is_empty() || name.len() > 200 {
+        return Err(AppError::validation("name", "Item name must be 1 to 200 characters"));
+    }
+    let hsn_code = item.hsn_code.trim().to_string();
+    if !(4..=8).contains(&hsn_code.len()) || !hsn_code.chars().all(|c| c.is_ascii_digit()) {
+        return Err(AppError::validation("hsn_code", "HSN/SAC code must be 4 to 8 digits"));
+    }
+    if !item.gst_rate.is_finite() || !(0.0..=100.0).contains(&item.gst_rate) {
+        return Err(AppError::validation("gst_rate", "GST rate must be between 0 and 100"));
+    }
+    if !item.rate.is_finite() || item.rate < 0.0 {
+        return Err(AppError::validation("rate", "Rate must be a non-negative number"));
+    }
+    if let Some(unit_id) = item.unit_id {
+        if units::get_unit_by_id(conn, unit_id)?.is_none() {
+            return Err(AppError::not_found("Unit of measure not found"));
+        }
+    }
+    Ok(SaveItem { code, name, hsn_code, ..item })
+}
+
+fn map_write_error(e: rusqlite::Error) -> AppError {
+    let message = e.to_string();
+    if message.contains("UNIQUE constraint failed: items") {
+        return AppError::conflict("code", "An item with this code already exists");
+    }
+    AppError::from(e)
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn list_items(
+    pool: State<'_, DbPool>,
+    company_id: i64,
+    include_inactive: Option<bool>,
+) -> Result<Vec<Item>, AppError> {
+    let conn = db::get_conn(&pool)?;
+    let mut stmt = conn.prepare(&format!(
+        "{} WHERE company_id = ?1 AND (?2 = 1 OR active = 1) ORDER BY name",
+        SELECT_ITEM
+    ))?;
+    let items = stmt
+        .query_map(params![company_id, include_inactive.unwrap_or(false)], item_from_row)?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(items)
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn get_item(
+    pool: State<'_, DbPool>,
+    id: i64,
+    company_id: i64,
+) -> Result<Item, AppError> {
+    let conn = db::get_conn(&pool)?;
+    get_item_by_id(&conn, id, company_id)?.ok_or_else(|| AppError::not_found("Item not found"))
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn create_item(
+    pool: State<'_, DbPool>,
+    company_id: i64,
+    item: SaveItem,
+) -> Result<Item, AppError> {
+    let conn = db::get_conn(&pool)?;
+    let item = validate(&conn, item)?;
+    conn.execute(
+        "INSERT INTO items (company_id, code, name, hsn_code, unit_id, gst_rate, rate, tracking,
+                            active)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+        params![
+            company_id,
+            item.code,
+            item.name,
+            item.hsn_code,
+            item.unit_id,
+            item.gst_rate,
+            item.rate,
+            item.tracking.as_str(),
+            item.active,
+        ],
+    )
+    .map_err(map_write_error)?;
+
+    let created = get_item_by_id(&conn, conn.last_insert_rowid(), company_id)?
+        .ok_or_else(|| AppError::internal("Item not found after creation"))?;
+    audit::record(
+        &conn,
+        company_id,
+        "item",
+        created.id,
+        AuditAction::Create,
+        None,
+        Some(&created),
+    )?;
+    Ok(created)
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn update_item(
+    pool: State<'_, DbPool>,
+    id: i64,
+    company_id: i64,
+    item: SaveItem,
+) -> Result<Item, AppError> {
+    let conn = db::get_conn(&pool)?;
+    let existing = get_item_by_id(&conn, id, company_id)?
+        .ok_or_else(|| AppError::not_found("Item not found"))?;
+    let item = validate(&conn, item)?;
+    conn.execute(
+        "UPDATE items SET code = ?1, name = ?2, hsn_code = ?3, unit_id = ?4, gst_rate = ?5,
+                          rate = ?6, tracking = ?7, active = ?8, updated_at = CURRENT_TIMESTAMP
+         WHERE id = ?9 AND company_id = ?10",
+        params![
+            item.code,
+            item.name,
+            item.hsn_code,
+            item.unit_id,
+            item.gst_rate,
+            item.rate,
+            item.tracking.as_str(),
+            item.active,
+            id,
+            company_id,
+        ],
+    )
+    .map_err(map_write_error)?;
+
+    let updated = get_item_by_id(&conn, id, company_id)?
+        .ok_or_else(|| AppError::internal("Item not found after update"))?;
+    audit::record(
+        &conn,
+        company_id,
+        "item",
+        id,
+        AuditAction::Update,
+        Some(&existing),
+        Some(&updated),
+    )?;
+    Ok(updated)
+}
+
+// Items already invoiced or priced are kept for history; deactivate them instead
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn delete_item(
+    pool: State<'_, DbPool>,
+    id: i64,
+    company_id: i64,
+) -> Result<(), AppError> {
+    let conn = db::get_conn(&pool)?;
+    let existing = get_item_by_id(&conn, id, company_id)?
+        .ok_or_else(|| AppError::not_found("Item not found"))?;
+    let in_use: bool = conn.query_row(
+        "SELECT EXISTS(SELECT 1 FROM invoice_lines WHERE item_id = ?1)
+                 OR EXISTS(SELECT 1 FROM price_list_items WHERE item_id = ?1)
+                 OR EXISTS(SELECT 1 FROM quotation_lines WHERE item_id = ?1)
+                 OR EXISTS(SELECT 1 FROM sales_order_lines WHERE item_id = ?1)
+                 OR EXISTS(SELECT 1 FROM delivery_challan_lines WHERE item_id = ?1)
+                 OR EXISTS(SELECT 1 FROM stock_adjustments WHERE item_id = ?1)
+                 OR EXISTS(SELECT 1 FROM batches WHERE item_id = ?1)",
+        params![id],
+        |row| row.get(0),
+    )?;
+    if in_use {
+        return Err(AppError::conflict(
+            "id",
+            "This item is used on documents, price lists or stock records; deactivate it instead",
+        ));
+    }
+    conn.execute("DELETE FROM items WHERE id = ?1 AND company_id = ?2", params![id, company_id])?;
+    audit::record(&conn, company_id, "item", id, AuditAction::Delete, Some(&existing), None)
+}
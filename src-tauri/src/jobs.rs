@@ -0,0 +1,600 @@
+use std::panic::{self, AssertUnwindSafe};
+use std::path::Path;
+use std::time::Duration;
+
+use chrono::Local;
+use rusqlite::{params, Connection, OptionalExtension, Row};
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Emitter, Manager, State};
+
+use crate::cancellation::{CancelToken, Cancellations};
+use crate::db::{self, DbPool};
+use crate::error::AppError;
+use crate::gstr1;
+use crate::import_jobs::{
+    self, CsvImportRequest, ImportFinished, ImportStatus, SalesImportRequest,
+};
+use crate::invoice_pdf;
+use crate::invoice_templates;
+
+pub const EVENT_JOB_UPDATED: &str = "jobs://updated";
+
+const TIMESTAMP_FORMAT: &str = "%Y-%m-%d %H:%M:%S";
+// How long the worker waits before looking for new work when the queue is empty
+const POLL_INTERVAL: Duration = Duration::from_secs(2);
+const DEFAULT_JOB_LIMIT: usize = 100;
+const MAX_JOB_LIMIT: usize = 1000;
+
+// What a job does, stored as JSON so queued work survives a restart
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum JobRequest {
+    CsvImport(CsvImportRequest),
+    SalesImport(SalesImportRequest),
+    // One PDF per invoice, named after the invoice number
+    InvoicePdfs {
+        invoice_ids: Vec<i64>,
+        template_id: Option<i64>,
+        folder: String,
+    },
+    Gstr1Export {
+        period: String,
+        path: String,
+    },
+}
+
+impl JobRequest {
+    pub fn kind(&self) -> &'static str {
+        match self {
+            JobRequest::CsvImport(_) => "csv_import",
+            JobRequest::SalesImport(_) => "sales_import",
+            JobRequest::InvoicePdfs { .. } => "invoice_pdfs",
+            JobRequest::Gstr1Export { .. } => "gstr1_export",
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum JobStatus {
+    Queued,
+    Running,
+    Completed,
+    Failed,
+    Cancelled,
+}
+
+impl JobStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            JobStatus::Queued => "queued",
+            JobStatus::Running => "running",
+            JobStatus::Completed => "completed",
+            JobStatus::Failed => "failed",
+            JobStatus::Cancelled => "cancelled",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "queued" => Some(JobStatus::Queued),
+            "running" => Some(JobStatus::Running),
+            "completed" => Some(JobStatus::Completed),
+            "failed" => Some(JobStatus::Failed),
+            "cancelled" => Some(JobStatus::Cancelled),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Job {
+    pub id: i64,
+    pub company_id: i64,
+    pub kind: String,
+    // None when the stored request no longer parses, e.g. after a downgrade
+    pub request: Option<JobRequest>,
+    pub status: JobStatus,
+    // Percent done
+    pub progress: f64,
+    pub message: Option<String>,
+    // The finished job's report; its shape depends on the kind
+    pub result: Option<serde_json::Value>,
+    pub error: Option<String>,
+    pub created_at: Option<String>,
+    pub started_at: Option<String>,
+    pub finished_at: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct InvoicePdfsResult {
+    pub folder: String,
+    pub files: Vec<String>,
+    // Invoices that could not be rendered; the rest are still written
+    pub errors: Vec<String>,
+}
+
+const SELECT_JOB: &str = "
+    SELECT id, company_id, kind, request, status, progress, message, result, error, created_at,
+           started_at, finished_at
+    FROM jobs";
+
+fn job_from_row(row: &Row) -> rusqlite::Result<Job> {
+    let request: String = row.get("request")?;
+    let status: String = row.get("status")?;
+    let result: Option<String> = row.get("result")?;
+    Ok(Job {
+        id: row.get("id")?,
+        company_id: row.get("company_id")?,
+        kind: row.get("kind")?,
+        request: serde_json::from_str(&request).ok(),
+        status: JobStatus::parse(&status).unwrap_or(JobStatus::Failed),
+        progress: row.get("progress")?,
+        message: row.get("message")?,
+        result: result.and_then(|result| serde_json::from_str(&result).ok()),
+        error: row.get("error")?,
+        created_at: row.get("created_at")?,
+        started_at: row.get("started_at")?,
+        finished_at: row.get("finished_at")?,
+    })
+}
+
+// Id a running job is registered under for cancellation; imports also report progress with it
+fn operation_id(job_id: i64) -> String {
+    format!("job-{}", job_id)
+}
+
+fn now() -> String {
+    Local::now().format(TIMESTAMP_FORMAT).to_string()
+}
+
+pub fn get_job_by_id(conn: &Connection, id: i64) -> Result<Option<Job>, String> {
+    conn.query_row(
+        &format!("{} WHERE id = ?1", SELECT_JOB),
+        params![id],
+        job_from_row,
+    )
+    .optional()
+    .map_err(|e| e.to_string())
+}
+
+pub fn enqueue(conn: &Connection, company_id: i64, request: &JobRequest) -> Result<i64, String> {
+    let json = serde_json::to_string(request).map_err(|e| e.to_string())?;
+    conn.execute(
+        "INSERT INTO jobs (company_id, kind, request) VALUES (?1, ?2, ?3)",
+        params![company_id, request.kind(), json],
+    )
+    .map_err(|e| e.to_string())?;
+    Ok(conn.last_insert_rowid())
+}
+
+fn validate(request: &JobRequest) -> Result<(), AppError> {
+    match request {
+        JobRequest::CsvImport(CsvImportRequest { path, .. })
+        | JobRequest::SalesImport(SalesImportRequest { path, .. }) => {
+            if !Path::new(path).is_file() {
+                return Err(AppError::validation(
+                    "path",
+                    format!("{} was not found", path),
+                ));
+            }
+        }
+        JobRequest::InvoicePdfs {
+            invoice_ids,
+            folder,
+            ..
+        } => {
+            if invoice_ids.is_empty() {
+                return Err(AppError::validation(
+                    "invoice_ids",
+                    "Select at least one invoice",
+                ));
+            }
+            if !Path::new(folder).is_dir() {
+                return Err(AppError::validation(
+                    "folder",
+                    format!("{} is not a folder", folder),
+                ));
+            }
+        }
+        JobRequest::Gstr1Export { period, path } => {
+            gstr1::parse_period(period)?;
+            if path.trim().is_empty() {
+                return Err(AppError::validation("path", "An output file is required"));
+            }
+        }
+    }
+    Ok(())
+}
+
+// Jobs left running when the app closed cannot be resumed safely, since an import may have
+// committed part of its rows, so they are marked failed rather than run again
+fn fail_interrupted(conn: &Connection) -> Result<usize, String> {
+    conn.execute(
+        "UPDATE jobs SET status = 'failed', error = 'Interrupted when the app closed',
+             finished_at = ?1
+         WHERE status = 'running'",
+        params![now()],
+    )
+    .map_err(|e| e.to_string())
+}
+
+// Marks the oldest queued job as running and returns it
+fn claim_next(conn: &Connection) -> Result<Option<Job>, String> {
+    let id: Option<i64> = conn
+        .query_row(
+            "SELECT id FROM jobs WHERE status = 'queued' ORDER BY id LIMIT 1",
+            [],
+            |row| row.get(0),
+        )
+        .optional()
+        .map_err(|e| e.to_string())?;
+    let Some(id) = id else {
+        return Ok(None);
+    };
+    let claimed = conn
+        .execute(
+            "UPDATE jobs SET status = 'running', started_at = ?1 WHERE id = ?2 AND status = 'queued'",
+            params![now(), id],
+        )
+        .map_err(|e| e.to_string())?;
+    if claimed == 0 {
+        return Ok(None);
+    }
+    get_job_by_id(conn, id)
+}
+
+// What a running job needs to report back
+#[derive(Clone)]
+struct JobContext {
+    app: AppHandle,
+    pool: DbPool,
+    job_id: i64,
+    cancel: CancelToken,
+}
+
+impl JobContext {
+    // Reports through `conn`, which may be inside an import's open transaction
+    fn save_progress(&self, conn: &Connection, percent: f64, message: String) {
+        let saved = conn
+            .execute(
+                "UPDATE jobs SET progress = ?1, message = ?2 WHERE id = ?3",
+                params![percent, message, self.job_id],
+            )
+            .map_err(|e| e.to_string())
+            .and_then(|_| get_job_by_id(conn, self.job_id));
+        match saved {
+            Ok(Some(job)) => {
+                let _ = self.app.emit(EVENT_JOB_UPDATED, job);
+            }
+            Ok(None) => {}
+            Err(e) => tracing::warn!(job = self.job_id, "job progress not saved: {}", e),
+        }
+    }
+
+    fn progress(&self, percent: f64, message: String) {
+        match db::get_conn(&self.pool) {
+            Ok(conn) => self.save_progress(&conn, percent, message),
+            Err(e) => tracing::warn!(job = self.job_id, "job progress not saved: {}", e),
+        }
+    }
+
+    fn finish(&self, status: JobStatus, result: Option<String>, error: Option<String>) {
+        let saved = db::get_conn(&self.pool)
+            .map_err(String::from)
+            .and_then(|conn| {
+                conn.execute(
+                    "UPDATE jobs SET status = ?1, result = ?2, error = ?3, finished_at = ?4,
+                     progress = CASE WHEN ?1 = 'completed' THEN 100 ELSE progress END
+                 WHERE id = ?5",
+                    params![status.as_str(), result, error, now(), self.job_id],
+                )
+                .map_err(|e| e.to_string())?;
+                get_job_by_id(&conn, self.job_id)
+            });
+        match saved {
+            Ok(Some(job)) => {
+                let _ = self.app.emit(EVENT_JOB_UPDATED, job);
+            }
+            Ok(None) => {}
+            Err(e) => tracing::warn!(job = self.job_id, "job result not saved: {}", e),
+        }
+    }
+
+    fn import_hook(&self) -> import_jobs::ProgressHook {
+        let context = self.clone();
+        Box::new(move |conn, progress| {
+            context.save_progress(
+                conn,
+                progress.percent,
+                format!(
+                    "{} rows processed, {} with errors",
+                    progress.rows_processed, progress.error_rows
+                ),
+            )
+        })
+    }
+}
+
+fn to_json<T: Serialize>(value: &T) -> Result<String, String> {
+    serde_json::to_string(value).map_err(|e| e.to_string())
+}
+
+fn import_outcome(finished: ImportFinished) -> (JobStatus, Option<String>, Option<String>) {
+    let status = match finished.status {
+        ImportStatus::Completed | ImportStatus::Running => JobStatus::Completed,
+        ImportStatus::Cancelled => JobStatus::Cancelled,
+        ImportStatus::Failed => JobStatus::Failed,
+    };
+    let result = match (&finished.csv, &finished.sales) {
+        (Some(csv), _) => to_json(csv).ok(),
+        (None, Some(sales)) => to_json(sales).ok(),
+        (None, None) => None,
+    };
+    (status, result, finished.error)
+}
+
+fn file_safe(value: &str) -> String {
+    value
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '-' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
+
+fn render_invoice_pdfs(
+    context: &JobContext,
+    company_id: i64,
+    invoice_ids: &[i64],
+    template_id: Option<i64>,
+    folder: &str,
+) -> Result<InvoicePdfsResult, String> {
+    let template = {
+        let conn = db::get_conn(&context.pool)?;
+        invoice_templates::resolve_template(&conn, template_id)?
+    };
+    let mut files = Vec::new();
+    let mut errors = Vec::new();
+    for (index, invoice_id) in invoice_ids.iter().enumerate() {
+        // Files already written are kept and listed when the job is cancelled
+        if context.cancel.is_cancelled() {
+            break;
+        }
+        let rendered = db::get_conn(&context.pool)
+            .map_err(String::from)
+            .and_then(|conn| {
+                invoice_pdf::load_document(&conn, *invoice_id, company_id).map_err(String::from)
+            })
+            .and_then(|document| {
+                let (bytes, _) = invoice_pdf::render_bytes(&document, &template, None)?;
+                let file_name = format!(
+                    "Invoice-{}.pdf",
+                    file_safe(&document.invoice().invoice_number)
+                );
+                let path = Path::new(folder).join(file_name);
+                std::fs::write(&path, bytes).map_err(|e| format!("Failed to write PDF: {}", e))?;
+                Ok(path.to_string_lossy().to_string())
+            });
+        match rendered {
+            Ok(path) => files.push(path),
+            Err(e) => errors.push(format!("Invoice {}: {}", invoice_id, e)),
+        }
+        context.progress(
+            (index + 1) as f64 * 100.0 / invoice_ids.len() as f64,
+            format!("{} of {} invoices rendered", index + 1, invoice_ids.len()),
+        );
+    }
+    Ok(InvoicePdfsResult {
+        folder: folder.to_string(),
+        files,
+        errors,
+    })
+}
+
+fn execute(context: &JobContext, job: &Job) -> (JobStatus, Option<String>, Option<String>) {
+    let Some(request) = &job.request else {
+        return (
+            JobStatus::Failed,
+            None,
+            Some(format!("Unknown job request of kind {}", job.kind)),
+        );
+    };
+    let label = operation_id(job.id);
+    let outcome = match request {
+        JobRequest::CsvImport(request) => {
+            return import_outcome(import_jobs::run_csv_job(
+                &context.app,
+                &context.pool,
+                label,
+                job.company_id,
+                request,
+                context.import_hook(),
+            ))
+        }
+        JobRequest::SalesImport(request) => {
+            return import_outcome(import_jobs::run_sales_job(
+                &context.app,
+                &context.pool,
+                label,
+                job.company_id,
+                request,
+                context.import_hook(),
+            ))
+        }
+        JobRequest::InvoicePdfs {
+            invoice_ids,
+            template_id,
+            folder,
+        } => render_invoice_pdfs(context, job.company_id, invoice_ids, *template_id, folder)
+            .and_then(|result| to_json(&result)),
+        JobRequest::Gstr1Export { period, path } => db::get_conn(&context.pool)
+            .map_err(String::from)
+            .and_then(|conn| {
+                let _watch = context.cancel.watch(&conn);
+                gstr1::export_return(&conn, job.company_id, period, Some(path.clone()))
+                    .map_err(String::from)
+            })
+            .and_then(|result| to_json(&result)),
+    };
+    match outcome {
+        Ok(result) if context.cancel.is_cancelled() => (JobStatus::Cancelled, Some(result), None),
+        Ok(result) => (JobStatus::Completed, Some(result), None),
+        Err(_) if context.cancel.is_cancelled() => (JobStatus::Cancelled, None, None),
+        Err(e) => (JobStatus::Failed, None, Some(e)),
+    }
+}
+
+fn run(context: &JobContext, job: &Job) {
+    let _ = context.app.emit(EVENT_JOB_UPDATED, job.clone());
+    // A panicking job is recorded as failed instead of stopping the worker
+    let (status, result, error) = panic::catch_unwind(AssertUnwindSafe(|| execute(context, job)))
+        .unwrap_or_else(|_| {
+            (
+                JobStatus::Failed,
+                None,
+                Some("The job stopped unexpectedly".to_string()),
+            )
+        });
+    tracing::info!(job = job.id, kind = %job.kind, status = status.as_str(), "job finished");
+    context.finish(status, result, error);
+}
+
+// Background thread started at launch that runs queued jobs one at a time, oldest first
+pub fn start_worker(app: AppHandle) {
+    std::thread::spawn(move || {
+        let pool = app.state::<DbPool>().inner().clone();
+        let mut recovered = false;
+        loop {
+            let next = db::get_conn(&pool).map_err(String::from).and_then(|conn| {
+                if !recovered {
+                    let interrupted = fail_interrupted(&conn)?;
+                    if interrupted > 0 {
+                        tracing::warn!(count = interrupted, "marked interrupted jobs as failed");
+                    }
+                    recovered = true;
+                }
+                claim_next(&conn)
+            });
+            match next {
+                Ok(Some(job)) => {
+                    let cancellations = app.state::<Cancellations>();
+                    let context = JobContext {
+                        app: app.clone(),
+                        pool: pool.clone(),
+                        job_id: job.id,
+                        cancel: cancellations.register(&operation_id(job.id)),
+                    };
+                    run(&context, &job);
+                    cancellations.unregister(&operation_id(job.id));
+                }
+                Ok(None) => std::thread::sleep(POLL_INTERVAL),
+                Err(e) => {
+                    tracing::warn!("job queue check failed: {}", e);
+                    std::thread::sleep(POLL_INTERVAL);
+                }
+            }
+        }
+    });
+}
+
+// Adds a job to the queue; its progress arrives as jobs://updated events
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn enqueue_job(
+    app: AppHandle,
+    pool: State<'_, DbPool>,
+    company_id: i64,
+    request: JobRequest,
+) -> Result<Job, AppError> {
+    validate(&request)?;
+    let conn = db::get_conn(&pool)?;
+    let id = enqueue(&conn, company_id, &request)?;
+    let job = get_job_by_id(&conn, id)?.ok_or_else(|| AppError::not_found("Job not found"))?;
+    let _ = app.emit(EVENT_JOB_UPDATED, job.clone());
+    Ok(job)
+}
+
+// Newest first, including finished jobs
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn list_jobs(
+    pool: State<'_, DbPool>,
+    company_id: i64,
+    status: Option<JobStatus>,
+    limit: Option<usize>,
+) -> Result<Vec<Job>, AppError> {
+    let limit = limit.unwrap_or(DEFAULT_JOB_LIMIT).clamp(1, MAX_JOB_LIMIT);
+    let conn = db::get_conn(&pool)?;
+    let mut stmt = conn.prepare(&format!(
+        "{} WHERE company_id = ?1 AND (?2 IS NULL OR status = ?2) ORDER BY id DESC LIMIT ?3",
+        SELECT_JOB
+    ))?;
+    let jobs = stmt
+        .query_map(
+            params![company_id, status.map(|s| s.as_str()), limit as i64],
+            job_from_row,
+        )?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(jobs)
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn get_job(pool: State<'_, DbPool>, id: i64) -> Result<Job, AppError> {
+    let conn = db::get_conn(&pool)?;
+    get_job_by_id(&conn, id)?.ok_or_else(|| AppError::not_found("Job not found"))
+}
+
+// Deletes finished jobs from the history; queued and running ones are kept
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn clear_job_history(
+    pool: State<'_, DbPool>,
+    company_id: i64,
+) -> Result<usize, AppError> {
+    let conn = db::get_conn(&pool)?;
+    let deleted = conn.execute(
+        "DELETE FROM jobs WHERE company_id = ?1 AND status IN ('completed', 'failed', 'cancelled')",
+        params![company_id],
+    )?;
+    Ok(deleted)
+}
+
+// A queued job is dropped from the queue straight away; a running one stops at its next
+// checkpoint and is then recorded as cancelled
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn cancel_job(
+    app: AppHandle,
+    pool: State<'_, DbPool>,
+    cancellations: State<'_, Cancellations>,
+    id: i64,
+) -> Result<Job, AppError> {
+    let conn = db::get_conn(&pool)?;
+    let job = get_job_by_id(&conn, id)?.ok_or_else(|| AppError::not_found("Job not found"))?;
+    match job.status {
+        JobStatus::Queued => {
+            conn.execute(
+                "UPDATE jobs SET status = 'cancelled', finished_at = ?1
+                 WHERE id = ?2 AND status = 'queued'",
+                params![now(), id],
+            )?;
+        }
+        JobStatus::Running => {
+            cancellations.cancel(&operation_id(id));
+        }
+        _ => {
+            return Err(AppError::invalid(
+                "Only queued or running jobs can be cancelled",
+            ))
+        }
+    }
+    let job = get_job_by_id(&conn, id)?.ok_or_else(|| AppError::not_found("Job not found"))?;
+    let _ = app.emit(EVENT_JOB_UPDATED, job.clone());
+    Ok(job)
+}
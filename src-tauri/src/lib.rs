@@ -1,269 +1,478 @@
-use serde::{Deserialize, Serialize};
+use tauri::ipc::Invoke;
+use tauri::{Manager, Wry};
 
-// Company data model
-#[derive(Debug, Serialize, Deserialize, Clone)]
-pub struct Company {
-    pub id: Option<i64>,
-    pub company_name: String,
-    pub gst_no: String,
-    pub state_code: String,
-    pub created_at: Option<String>,
-    pub updated_at: Option<String>,
-}
-
-#[derive(Debug, Serialize, Deserialize)]
-pub struct CreateCompany {
-    pub company_name: String,
-    pub gst_no: String,
-    pub state_code: String,
-}
-
-#[derive(Debug, Serialize, Deserialize)]
-pub struct UpdateCompany {
-    pub company_name: Option<String>,
-    pub gst_no: Option<String>,
-    pub state_code: Option<String>,
-}
-
-// Category data model
-#[derive(Debug, Serialize, Deserialize, Clone)]
-pub struct Category {
-    pub id: Option<i64>,
-    pub name: String,
-    pub created_at: Option<String>,
-    pub updated_at: Option<String>,
-}
-
-#[derive(Debug, Serialize, Deserialize)]
-pub struct CreateCategory {
-    pub name: String,
-}
-
-#[derive(Debug, Serialize, Deserialize)]
-pub struct UpdateCategory {
-    pub name: Option<String>,
-}
-
-// Customer data model
-#[derive(Debug, Serialize, Deserialize, Clone)]
-pub struct Customer {
-    pub id: Option<i64>,
-    pub report_customer: String,
-    pub tally_customer: String,
-    pub gst_no: String,
-    pub state_code: String,
-    pub category_id: i64,
-    pub category: Option<Category>,
-    pub created_at: Option<String>,
-    pub updated_at: Option<String>,
-}
-
-#[derive(Debug, Serialize, Deserialize)]
-pub struct CreateCustomer {
-    pub report_customer: String,
-    pub tally_customer: String,
-    pub gst_no: Option<String>,
-    pub state_code: Option<String>,
-    pub category_id: i64,
-}
-
-#[derive(Debug, Serialize, Deserialize)]
-pub struct UpdateCustomer {
-    pub report_customer: Option<String>,
-    pub tally_customer: Option<String>,
-    pub gst_no: Option<String>,
-    pub state_code: Option<String>,
-    pub category_id: Option<i64>,
-}
+mod accounting_import;
+mod amendments;
+mod amount_words;
+mod anonymize;
+mod api_server;
+mod attachments;
+mod audit;
+mod auth;
+mod backup;
+mod backup_archive;
+mod backup_cloud;
+mod backup_schedule;
+mod barcode;
+mod batches;
+mod bulk;
+mod cancellation;
+mod categories;
+mod change_events;
+mod cli;
+mod companies;
+mod credit_notes;
+mod csv_import;
+mod currencies;
+mod custom_reports;
+mod customer_duplicates;
+mod customer_statements;
+mod customers;
+mod dashboard;
+mod database_health;
+mod db;
+mod delivery_challans;
+mod demo_data;
+mod diagnostics;
+mod einvoice;
+mod email;
+mod encryption;
+mod error;
+mod eway_bills;
+mod exports;
+mod financial_years;
+mod full_archive;
+mod gstin;
+mod gstin_lookup;
+mod gstr1;
+mod gstr2b;
+mod hsn;
+mod import_jobs;
+mod import_sessions;
+mod invoice_pdf;
+mod invoice_templates;
+mod invoices;
+mod items;
+mod jobs;
+mod listing;
+mod logging;
+mod maintenance;
+mod migrations;
+mod numbering;
+mod pan;
+mod paste_import;
+mod payment_reminders;
+mod permissions;
+mod place_of_supply;
+mod price_lists;
+mod printing;
+mod purchases;
+mod quotations;
+mod receipts;
+mod recycle_bin;
+mod report_export;
+mod report_schedules;
+mod reports;
+mod rounding;
+mod sales_import;
+mod sales_orders;
+mod settings;
+mod states;
+mod stock;
+mod stock_valuation;
+mod sync;
+mod tally;
+mod tally_ledgers;
+mod tax;
+mod tcs;
+#[cfg(feature = "test-harness")]
+pub mod test_harness;
+mod transactions;
+mod units;
+mod validation_rules;
+mod webhooks;
 
 // Learn more about Tauri commands at https://tauri.app/develop/calling-rust/
 #[tauri::command]
+#[tracing::instrument(skip_all)]
 fn greet(name: &str) -> String {
     format!("Hello, {}! You've been greeted from Rust!", name)
 }
 
-#[tauri::command]
-async fn initialize_database() -> Result<(), String> {
-    // This will be handled by the frontend using the SQL plugin
-    Ok(())
-}
-
-#[tauri::command]
-async fn create_company(company: CreateCompany) -> Result<Company, String> {
-    // Validate input
-    if company.company_name.trim().is_empty() {
-        return Err("Company name is required".to_string());
-    }
-    if company.company_name.len() > 255 {
-        return Err("Company name must be 255 characters or less".to_string());
-    }
-    if company.gst_no.trim().is_empty() {
-        return Err("GST number is required".to_string());
-    }
-    if !is_valid_gst_format(&company.gst_no) {
-        return Err("GST number must be 15 characters and follow GST format".to_string());
-    }
-    if company.state_code.trim().is_empty() {
-        return Err("State code is required".to_string());
-    }
-
-    // Return the validated data - the actual database operation will be handled by the frontend
-    Ok(Company {
-        id: None,
-        company_name: company.company_name.trim().to_string(),
-        gst_no: company.gst_no.trim().to_string(),
-        state_code: company.state_code.trim().to_string(),
-        created_at: Some(chrono::Utc::now().to_rfc3339()),
-        updated_at: Some(chrono::Utc::now().to_rfc3339()),
-    })
-}
-
-#[tauri::command]
-async fn validate_company_update(company: UpdateCompany) -> Result<UpdateCompany, String> {
-    // Validate input
-    if let Some(name) = &company.company_name {
-        if name.trim().is_empty() {
-            return Err("Company name cannot be empty".to_string());
-        }
-        if name.len() > 255 {
-            return Err("Company name must be 255 characters or less".to_string());
-        }
-    }
-
-    if let Some(gst_no) = &company.gst_no {
-        if gst_no.trim().is_empty() {
-            return Err("GST number cannot be empty".to_string());
-        }
-        if !is_valid_gst_format(gst_no) {
-            return Err("GST number must be 15 characters and follow GST format".to_string());
-        }
-    }
-
-    if let Some(state_code) = &company.state_code {
-        if state_code.trim().is_empty() {
-            return Err("State code cannot be empty".to_string());
-        }
-    }
-
-    Ok(company)
+// Every command the frontend can invoke
+fn commands() -> impl Fn(Invoke<Wry>) -> bool + Send + Sync + 'static {
+    tauri::generate_handler![
+        greet,
+        migrations::initialize_database,
+        migrations::get_schema_status,
+        migrations::migrate_schema,
+        companies::create_company,
+        companies::validate_company_update,
+        companies::update_company,
+        companies::get_company,
+        companies::list_companies,
+        categories::validate_category_create,
+        categories::validate_category_update,
+        categories::create_category,
+        categories::update_category,
+        categories::list_categories,
+        categories::delete_category,
+        customers::validate_customer_create,
+        customers::validate_customer_update,
+        customers::create_customer,
+        customers::update_customer,
+        customers::get_customer,
+        customers::list_customers,
+        customers::delete_customer,
+        invoices::create_invoice,
+        invoices::update_invoice,
+        invoices::get_invoice,
+        invoices::get_invoice_with_lines,
+        invoices::delete_invoice,
+        invoices::list_invoices,
+        hsn::lookup_hsn,
+        gstin::validate_gstin,
+        states::derive_state_from_gstin,
+        gstin_lookup::get_gstin_api_config,
+        gstin_lookup::save_gstin_api_config,
+        gstin_lookup::verify_gstin_online,
+        tally::get_tally_export_config,
+        tally::save_tally_export_config,
+        tally::export_tally_vouchers,
+        tally_ledgers::import_tally_ledgers,
+        sales_import::import_sales_excel,
+        report_export::export_report_xlsx,
+        csv_import::save_import_profile,
+        csv_import::list_import_profiles,
+        csv_import::delete_import_profile,
+        csv_import::preview_csv_import,
+        csv_import::import_csv,
+        import_sessions::save_customer_mapping,
+        import_sessions::get_customer_mapping,
+        import_sessions::list_customer_mappings,
+        import_sessions::delete_customer_mapping,
+        import_sessions::create_import_session,
+        import_sessions::add_import_customers,
+        import_sessions::set_import_session_status,
+        import_sessions::resolve_import_customer,
+        import_sessions::list_import_customers,
+        import_sessions::import_report_rows,
+        import_sessions::list_import_reports,
+        accounting_import::import_accounting_export,
+        import_jobs::start_csv_import,
+        import_jobs::start_sales_import,
+        import_jobs::cancel_import,
+        jobs::enqueue_job,
+        jobs::list_jobs,
+        jobs::get_job,
+        jobs::clear_job_history,
+        jobs::cancel_job,
+        cancellation::cancel_operation,
+        transactions::run_transaction,
+        gstr1::generate_gstr1_json,
+        einvoice::get_einvoice_config,
+        einvoice::save_einvoice_config,
+        einvoice::build_einvoice_payload,
+        einvoice::get_einvoice,
+        einvoice::generate_irn,
+        einvoice::generate_irn_bulk,
+        einvoice::get_invoice_qr,
+        eway_bills::save_eway_bill_details,
+        eway_bills::get_eway_bill,
+        eway_bills::generate_eway_bill,
+        eway_bills::export_eway_bill_json,
+        eway_bills::record_eway_bill,
+        eway_bills::list_expiring_eway_bills,
+        invoice_pdf::render_invoice_pdf,
+        invoice_templates::list_invoice_templates,
+        invoice_templates::create_invoice_template,
+        invoice_templates::update_invoice_template,
+        invoice_templates::delete_invoice_template,
+        invoice_templates::set_default_invoice_template,
+        invoice_templates::preview_invoice_template,
+        amount_words::amount_to_words,
+        rounding::get_invoice_rounding,
+        rounding::set_invoice_rounding,
+        financial_years::list_financial_years,
+        financial_years::create_financial_year,
+        financial_years::close_financial_year,
+        financial_years::reopen_financial_year,
+        financial_years::list_period_locks,
+        financial_years::lock_period,
+        financial_years::unlock_period,
+        numbering::list_number_sequences,
+        numbering::save_number_sequence,
+        numbering::preview_next_number,
+        credit_notes::create_credit_debit_note,
+        credit_notes::get_credit_debit_note,
+        credit_notes::list_credit_debit_notes,
+        credit_notes::set_credit_debit_note_status,
+        credit_notes::delete_credit_debit_note,
+        receipts::record_receipt,
+        receipts::get_receipt,
+        receipts::list_receipts,
+        receipts::list_outstanding_invoices,
+        receipts::auto_allocate_receipt,
+        receipts::allocate_receipt,
+        receipts::remove_receipt_allocation,
+        receipts::delete_receipt,
+        customer_statements::customer_statement,
+        customer_statements::export_customer_statement_pdf,
+        customer_statements::export_customer_statement_xlsx,
+        reports::sales_register,
+        reports::monthly_category_summary,
+        reports::sales_by_customer,
+        reports::export_sales_by_customer_xlsx,
+        reports::state_supply_split,
+        dashboard::get_dashboard_data,
+        dashboard::get_dashboard_cache_seconds,
+        dashboard::set_dashboard_cache_seconds,
+        recycle_bin::list_deleted,
+        recycle_bin::restore,
+        recycle_bin::get_recycle_bin_retention,
+        recycle_bin::set_recycle_bin_retention,
+        audit::get_audit_trail,
+        companies::switch_active_company,
+        companies::get_active_company,
+        companies::clear_active_company,
+        auth::login,
+        auth::logout,
+        auth::get_session,
+        auth::create_user,
+        auth::list_users,
+        auth::update_user,
+        auth::reset_user_password,
+        auth::change_password,
+        backup::backup_database,
+        backup_schedule::get_backup_schedule,
+        backup_schedule::set_backup_schedule,
+        maintenance::run_maintenance,
+        database_health::check_database_health,
+        database_health::repair_database,
+        database_health::export_problem_rows,
+        demo_data::seed_demo_data,
+        backup::inspect_backup,
+        backup::restore_backup,
+        full_archive::export_full_archive,
+        full_archive::import_full_archive,
+        backup_cloud::get_cloud_backup_settings,
+        backup_cloud::set_cloud_backup_settings,
+        backup_cloud::upload_cloud_backup,
+        backup_cloud::list_cloud_backups,
+        backup_cloud::restore_cloud_backup,
+        encryption::get_encryption_status,
+        encryption::enable_database_encryption,
+        encryption::change_database_key,
+        customer_duplicates::find_duplicate_customers,
+        customer_duplicates::check_customer_duplicates,
+        customer_duplicates::merge_customers,
+        customers::bulk_import_customers,
+        bulk::bulk_assign_category,
+        bulk::bulk_delete_customers,
+        bulk::bulk_delete_invoices,
+        states::list_states,
+        states::get_state,
+        pan::get_pan_details,
+        pan::check_pan_consistency,
+        place_of_supply::get_tax_split,
+        tax::compute_invoice_totals,
+        tcs::get_tcs_settings,
+        tcs::set_tcs_settings,
+        tcs::get_tcs_report,
+        exports::get_export_register,
+        gstr1::hsn_summary,
+        gstr1::document_summary,
+        purchases::create_purchase,
+        purchases::update_purchase,
+        purchases::list_purchases,
+        purchases::delete_purchase,
+        gstr2b::import_gstr2b,
+        gstr2b::list_gstr2b_entries,
+        gstr2b::reconcile_gstr2b,
+        einvoice::cancel_einvoice,
+        amendments::mark_gstr1_amendment,
+        amendments::list_gstr1_amendments,
+        amendments::delete_gstr1_amendment,
+        validation_rules::list_validation_rules,
+        validation_rules::create_validation_rule,
+        validation_rules::update_validation_rule,
+        validation_rules::delete_validation_rule,
+        logging::get_log_level,
+        logging::set_log_level,
+        logging::get_recent_logs,
+        diagnostics::get_crash_reporting,
+        diagnostics::set_crash_reporting,
+        diagnostics::export_diagnostics,
+        diagnostics::clear_crash_reports,
+        diagnostics::get_pool_metrics,
+        anonymize::export_anonymized_db,
+        settings::get_settings,
+        settings::update_settings,
+        settings::get_display_settings,
+        settings::update_display_settings,
+        email::get_smtp_settings,
+        email::set_smtp_settings,
+        email::send_invoice_email,
+        email::send_statement_email,
+        email::list_email_log,
+        printing::list_printers,
+        printing::print_document,
+        attachments::add_attachment,
+        attachments::list_attachments,
+        attachments::open_attachment,
+        attachments::delete_attachment,
+        custom_reports::get_report_fields,
+        custom_reports::list_custom_reports,
+        custom_reports::save_custom_report,
+        custom_reports::delete_custom_report,
+        custom_reports::run_custom_report,
+        custom_reports::export_custom_report,
+        report_schedules::list_report_schedules,
+        report_schedules::create_report_schedule,
+        report_schedules::update_report_schedule,
+        report_schedules::delete_report_schedule,
+        report_schedules::run_report_schedule_now,
+        report_schedules::list_report_schedule_runs,
+        payment_reminders::list_due_invoices,
+        payment_reminders::list_payment_reminders,
+        payment_reminders::mark_payment_reminders_read,
+        payment_reminders::check_payment_reminders,
+        payment_reminders::list_reminder_snoozes,
+        payment_reminders::snooze_customer_reminders,
+        payment_reminders::unsnooze_customer_reminders,
+        payment_reminders::get_reminder_settings,
+        payment_reminders::set_reminder_settings,
+        currencies::list_currencies,
+        currencies::save_currency,
+        currencies::list_exchange_rates,
+        currencies::get_exchange_rate,
+        currencies::set_exchange_rate,
+        currencies::clear_exchange_rate_override,
+        currencies::get_exchange_rate_source,
+        currencies::set_exchange_rate_source,
+        currencies::fetch_exchange_rates,
+        units::list_uqc_codes,
+        units::list_units,
+        units::create_unit,
+        units::update_unit,
+        units::delete_unit,
+        items::list_items,
+        items::get_item,
+        items::create_item,
+        items::update_item,
+        items::delete_item,
+        price_lists::list_price_lists,
+        price_lists::create_price_list,
+        price_lists::update_price_list,
+        price_lists::delete_price_list,
+        price_lists::list_price_list_rates,
+        price_lists::set_price_list_rate,
+        price_lists::delete_price_list_rate,
+        price_lists::list_price_list_assignments,
+        price_lists::assign_price_list,
+        price_lists::unassign_price_list,
+        price_lists::resolve_price,
+        quotations::list_quotations,
+        quotations::get_quotation,
+        quotations::create_quotation,
+        quotations::update_quotation,
+        quotations::set_quotation_status,
+        quotations::delete_quotation,
+        quotations::convert_quotation_to_invoice,
+        quotations::export_quotation_pdf,
+        sales_orders::list_sales_orders,
+        sales_orders::get_sales_order,
+        sales_orders::create_sales_order,
+        sales_orders::update_sales_order,
+        sales_orders::set_sales_order_status,
+        sales_orders::delete_sales_order,
+        sales_orders::create_invoice_from_order,
+        sales_orders::open_orders_report,
+        delivery_challans::list_delivery_challans,
+        delivery_challans::get_delivery_challan,
+        delivery_challans::list_invoice_challans,
+        delivery_challans::create_delivery_challan,
+        delivery_challans::update_delivery_challan,
+        delivery_challans::set_delivery_challan_status,
+        delivery_challans::delete_delivery_challan,
+        delivery_challans::convert_challan_to_invoice,
+        delivery_challans::link_challan_to_invoice,
+        stock::stock_ledger,
+        stock::list_stock_levels,
+        stock::list_stock_adjustments,
+        stock::get_stock_settings,
+        stock::create_stock_adjustment,
+        stock::delete_stock_adjustment,
+        stock::set_stock_settings,
+        stock_valuation::closing_stock_report,
+        batches::list_batches,
+        batches::trace_batch,
+        batches::trace_serial,
+        batches::list_expiring_batches,
+        batches::create_batch,
+        batches::update_batch,
+        batches::delete_batch,
+        barcode::generate_barcode,
+        paste_import::parse_pasted_table,
+        api_server::get_api_server_status,
+        api_server::set_api_server_settings,
+        api_server::regenerate_api_token,
+        webhooks::list_webhooks,
+        webhooks::create_webhook,
+        webhooks::update_webhook,
+        webhooks::delete_webhook,
+        webhooks::list_webhook_deliveries,
+        webhooks::retry_webhook_delivery,
+        sync::get_sync_status,
+        sync::export_sync_changes,
+        sync::import_sync_changes,
+        sync::list_sync_conflicts,
+        sync::resolve_sync_conflict
+    ]
 }
 
-// Helper function to validate GST format
-fn is_valid_gst_format(gst_no: &str) -> bool {
-    let trimmed = gst_no.trim();
-    // Basic GST validation: 15 characters, first 2 digits for state code, next 10 for PAN, last 3 for entity type
-    trimmed.len() == 15 && trimmed.chars().all(|c| c.is_ascii_alphanumeric())
-}
-
-// Category validation commands
-#[tauri::command]
-async fn validate_category_create(category: CreateCategory) -> Result<CreateCategory, String> {
-    if category.name.trim().is_empty() {
-        return Err("Category name is required".to_string());
-    }
-    if category.name.len() > 100 {
-        return Err("Category name must be 100 characters or less".to_string());
-    }
-    Ok(category)
-}
-
-#[tauri::command]
-async fn validate_category_update(category: UpdateCategory) -> Result<UpdateCategory, String> {
-    if let Some(name) = &category.name {
-        if name.trim().is_empty() {
-            return Err("Category name cannot be empty".to_string());
-        }
-        if name.len() > 100 {
-            return Err("Category name must be 100 characters or less".to_string());
-        }
-    }
-    Ok(category)
-}
-
-// Customer validation commands
-#[tauri::command]
-async fn validate_customer_create(customer: CreateCustomer) -> Result<CreateCustomer, String> {
-    if customer.report_customer.trim().is_empty() {
-        return Err("Report customer name is required".to_string());
-    }
-    if customer.report_customer.len() > 255 {
-        return Err("Report customer name must be 255 characters or less".to_string());
-    }
-    
-    if customer.tally_customer.trim().is_empty() {
-        return Err("Tally customer name is required".to_string());
-    }
-    if customer.tally_customer.len() > 255 {
-        return Err("Tally customer name must be 255 characters or less".to_string());
-    }
-    
-    if let Some(gst_no) = &customer.gst_no {
-        if !gst_no.trim().is_empty() && !is_valid_gst_format(gst_no) {
-            return Err("GST number must be 15 characters and follow GST format".to_string());
-        }
-    }
-    
-    // State code is optional - no validation needed
-    
-    if customer.category_id <= 0 {
-        return Err("Category is required".to_string());
-    }
-    
-    Ok(customer)
-}
-
-#[tauri::command]
-async fn validate_customer_update(customer: UpdateCustomer) -> Result<UpdateCustomer, String> {
-    if let Some(report_customer) = &customer.report_customer {
-        if report_customer.trim().is_empty() {
-            return Err("Report customer name cannot be empty".to_string());
-        }
-        if report_customer.len() > 255 {
-            return Err("Report customer name must be 255 characters or less".to_string());
-        }
-    }
-    
-    if let Some(tally_customer) = &customer.tally_customer {
-        if tally_customer.trim().is_empty() {
-            return Err("Tally customer name cannot be empty".to_string());
-        }
-        if tally_customer.len() > 255 {
-            return Err("Tally customer name must be 255 characters or less".to_string());
-        }
-    }
-    
-    if let Some(gst_no) = &customer.gst_no {
-        if !gst_no.trim().is_empty() && !is_valid_gst_format(gst_no) {
-            return Err("GST number must be 15 characters and follow GST format".to_string());
-        }
-    }
-    
-    // State code is optional - no validation needed
-    
-    if let Some(category_id) = customer.category_id {
-        if category_id <= 0 {
-            return Err("Category is required".to_string());
-        }
-    }
-    
-    Ok(customer)
+// Headless exports for scripts, e.g. `sales-report export gstr1 --period 2024-05 --out gstr1.json`.
+// None when the arguments do not ask for one and the app should start instead.
+pub fn run_cli(args: &[String]) -> Option<i32> {
+    cli::run(args)
 }
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
+    // Every command passes the session and role check before it runs
+    let handler = permissions::guard(commands());
+
     tauri::Builder::default()
         .plugin(tauri_plugin_opener::init())
-        .plugin(tauri_plugin_sql::Builder::default().build())
-        .invoke_handler(tauri::generate_handler![
-            greet,
-            initialize_database,
-            create_company,
-            validate_company_update,
-            validate_category_create,
-            validate_category_update,
-            validate_customer_create,
-            validate_customer_update
-        ])
+        .plugin(tauri_plugin_notification::init())
+        .setup(|app| {
+            let logger = logging::init(app.handle())?;
+            diagnostics::install_panic_hook(logger.dir(), app.package_info().version.to_string());
+            let db_path = db::database_path(app.handle())?;
+            encryption::prepare(&db_path)?;
+            let pool = db::init_pool(&db_path)?;
+            let conn = db::get_conn(&pool)?;
+            logging::apply_level(&logger, logging::load_level(&conn)?)?;
+            app.manage(logger);
+            app.manage(pool);
+            app.manage(dashboard::DashboardCache::default());
+            app.manage(cancellation::Cancellations::default());
+            app.manage(companies::ActiveCompany::default());
+            app.manage(auth::Session::default());
+            app.manage(api_server::ApiServer::default());
+            change_events::start_dispatcher(app.handle().clone());
+            backup_schedule::start_scheduler(app.handle().clone());
+            report_schedules::start_scheduler(app.handle().clone());
+            payment_reminders::start_scheduler(app.handle().clone());
+            api_server::start_if_enabled(app.handle().clone());
+            webhooks::start_dispatcher(app.handle().clone());
+            jobs::start_worker(app.handle().clone());
+            Ok(())
+        })
+        .invoke_handler(move |invoke| {
+            diagnostics::note_command(invoke.message.command());
+            handler(invoke)
+        })
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
-}
\ No newline at end of file
+}
@@ -0,0 +1,133 @@
+use rusqlite::types::Value;
+use rusqlite::{params_from_iter, Connection, Row};
+use serde::{Deserialize, Serialize};
+
+use crate::error::AppError;
+
+const DEFAULT_PAGE_SIZE: u32 = 50;
+const MAX_PAGE_SIZE: u32 = 500;
+
+// Paging and sorting shared by the list commands; `filter` is specific to each list
+#[derive(Debug, Serialize, Deserialize, Default)]
+#[serde(bound(deserialize = "F: Deserialize<'de> + Default"))]
+pub struct ListQuery<F> {
+    // 1-based
+    pub page: Option<u32>,
+    pub page_size: Option<u32>,
+    pub sort_by: Option<String>,
+    #[serde(default)]
+    pub descending: bool,
+    #[serde(default)]
+    pub filter: F,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ListPage<T> {
+    pub rows: Vec<T>,
+    pub total_count: i64,
+}
+
+// WHERE clauses built up alongside their positional parameters
+#[derive(Default)]
+pub struct SqlFilter {
+    clauses: Vec<String>,
+    values: Vec<Value>,
+}
+
+impl SqlFilter {
+    pub fn push(&mut self, clause: &str, value: Value) {
+        self.clauses.push(clause.to_string());
+        self.values.push(value);
+    }
+
+    // Case-insensitive substring match against any of the given columns
+    pub fn search(&mut self, columns: &[&str], term: Option<&str>) {
+        let Some(term) = term.map(str::trim).filter(|t| !t.is_empty()) else {
+            return;
+        };
+        let pattern = format!("%{}%", term.replace('%', "\\%").replace('_', "\\_"));
+        let clause = columns
+            .iter()
+            .map(|column| format!("{} LIKE ? ESCAPE '\\'", column))
+            .collect::<Vec<_>>()
+            .join(" OR ");
+        self.clauses.push(format!("({})", clause));
+        for _ in columns {
+            self.values.push(Value::Text(pattern.clone()));
+        }
+    }
+
+    fn sql(&self) -> String {
+        if self.clauses.is_empty() {
+            String::new()
+        } else {
+            format!(" WHERE {}", self.clauses.join(" AND "))
+        }
+    }
+}
+
+// Maps a requested sort key onto one of the allowed column expressions, so user input never
+// reaches the ORDER BY clause directly
+pub fn sort_column(
+    sort_by: Option<&str>,
+    allowed: &[(&str, &'static str)],
+) -> Result<&'static str, AppError> {
+    let default = allowed.first().map(|(_, column)| *column).unwrap_or("rowid");
+    match sort_by.map(str::trim).filter(|s| !s.is_empty()) {
+        None => Ok(default),
+        Some(key) => allowed
+            .iter()
+            .find(|(name, _)| *name == key)
+            .map(|(_, column)| *column)
+            .ok_or_else(|| {
+                let names: Vec<&str> = allowed.iter().map(|(name, _)| *name).collect();
+                AppError::validation(
+                    "sort_by",
+                    format!("Cannot sort by {}; expected one of {}", key, names.join(", ")),
+                )
+            }),
+    }
+}
+
+// Runs `select` (a SELECT ... FROM without a WHERE clause) for one page, plus a count of
+// every matching row. `tie_break` keeps page boundaries stable when sort values repeat.
+pub fn fetch_page<T, F>(
+    conn: &Connection,
+    select: &str,
+    filter: &SqlFilter,
+    order_by: &str,
+    tie_break: &str,
+    query: &ListQuery<F>,
+    map: impl FnMut(&Row) -> rusqlite::Result<T>,
+) -> Result<ListPage<T>, AppError> {
+    let page_size = query
+        .page_size
+        .unwrap_or(DEFAULT_PAGE_SIZE)
+        .clamp(1, MAX_PAGE_SIZE);
+    let page = query.page.unwrap_or(1).max(1);
+    let direction = if query.descending { "DESC" } else { "ASC" };
+    let where_sql = filter.sql();
+
+    let total_count = conn.query_row(
+        &format!("SELECT COUNT(*) FROM ({}{})", select, where_sql),
+        params_from_iter(filter.values.iter()),
+        |row| row.get(0),
+    )?;
+
+    let sql = format!(
+        "{}{} ORDER BY {} {}, {} {} LIMIT {} OFFSET {}",
+        select,
+        where_sql,
+        order_by,
+        direction,
+        tie_break,
+        direction,
+        page_size,
+        (page as i64 - 1) * page_size as i64
+    );
+    let mut stmt = conn.prepare(&sql)?;
+    let rows = stmt
+        .query_map(params_from_iter(filter.values.iter()), map)?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(ListPage { rows, total_count })
+}
@@ -0,0 +1,248 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use rusqlite::Connection;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tauri::{AppHandle, Manager, State};
+use tracing_appender::non_blocking::WorkerGuard;
+use tracing_appender::rolling::{RollingFileAppender, Rotation};
+use tracing_subscriber::filter::LevelFilter;
+use tracing_subscriber::fmt::format::FmtSpan;
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::{fmt, reload, Registry};
+
+use crate::db::{self, get_setting, set_setting, DbPool};
+use crate::error::AppError;
+
+const SETTING_LOG_LEVEL: &str = "log_level";
+const LOG_FILE_PREFIX: &str = "sales-report";
+const LOG_FILE_SUFFIX: &str = "log";
+// One file per day; older files are deleted as new ones are opened
+const MAX_LOG_FILES: usize = 14;
+const DEFAULT_RECENT_LOGS: usize = 200;
+const MAX_RECENT_LOGS: usize = 2000;
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum LogLevel {
+    Error,
+    Warn,
+    #[default]
+    Info,
+    Debug,
+    Trace,
+}
+
+impl LogLevel {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            LogLevel::Error => "error",
+            LogLevel::Warn => "warn",
+            LogLevel::Info => "info",
+            LogLevel::Debug => "debug",
+            LogLevel::Trace => "trace",
+        }
+    }
+
+    // Case-insensitive, as the JSON log lines write levels in upper case
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "error" => Some(LogLevel::Error),
+            "warn" => Some(LogLevel::Warn),
+            "info" => Some(LogLevel::Info),
+            "debug" => Some(LogLevel::Debug),
+            "trace" => Some(LogLevel::Trace),
+            _ => None,
+        }
+    }
+
+    fn filter(&self) -> LevelFilter {
+        match self {
+            LogLevel::Error => LevelFilter::ERROR,
+            LogLevel::Warn => LevelFilter::WARN,
+            LogLevel::Info => LevelFilter::INFO,
+            LogLevel::Debug => LevelFilter::DEBUG,
+            LogLevel::Trace => LevelFilter::TRACE,
+        }
+    }
+}
+
+// Kept in app state: the worker guard flushes the log file when the app exits
+pub struct Logger {
+    dir: PathBuf,
+    level: reload::Handle<LevelFilter, Registry>,
+    _guard: WorkerGuard,
+}
+
+// One line of a log file, as shown in the log viewer
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct LogEntry {
+    pub timestamp: String,
+    pub level: LogLevel,
+    pub target: String,
+    pub message: String,
+    // Command the line was written from
+    pub command: Option<String>,
+    // Remaining structured fields, e.g. time.busy on the line closing a command
+    pub fields: Value,
+}
+
+// Writes JSON lines to a daily file in the app log dir. Every command runs in a span named after
+// it; the span's close line records how long the command took, and failures are logged as
+// warnings with the error. Starts at the default level until the saved one is applied.
+pub fn init(app: &AppHandle) -> Result<Logger, String> {
+    let dir = app
+        .path()
+        .app_log_dir()
+        .map_err(|e| format!("Failed to resolve app log directory: {}", e))?;
+    fs::create_dir_all(&dir).map_err(|e| format!("Failed to create log directory: {}", e))?;
+    let appender = RollingFileAppender::builder()
+        .rotation(Rotation::DAILY)
+        .filename_prefix(LOG_FILE_PREFIX)
+        .filename_suffix(LOG_FILE_SUFFIX)
+        .max_log_files(MAX_LOG_FILES)
+        .build(&dir)
+        .map_err(|e| format!("Failed to open log file: {}", e))?;
+    let (writer, guard) = tracing_appender::non_blocking(appender);
+    let (level, handle) = reload::Layer::new(LogLevel::default().filter());
+    tracing_subscriber::registry()
+        .with(level)
+        .with(
+            fmt::layer()
+                .json()
+                .with_writer(writer)
+                .with_span_events(FmtSpan::CLOSE)
+                .with_current_span(true)
+                .with_span_list(false),
+        )
+        .try_init()
+        .map_err(|e| format!("Failed to start logging: {}", e))?;
+    Ok(Logger {
+        dir,
+        level: handle,
+        _guard: guard,
+    })
+}
+
+impl Logger {
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    // Newest first; the date in the file name sorts chronologically
+    pub fn log_files(&self) -> Result<Vec<PathBuf>, String> {
+        let mut files = fs::read_dir(&self.dir)
+            .map_err(|e| format!("Failed to read log directory: {}", e))?
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.path())
+            .filter(|path| {
+                path.file_name()
+                    .and_then(|name| name.to_str())
+                    .is_some_and(|name| {
+                        name.starts_with(LOG_FILE_PREFIX) && name.ends_with(LOG_FILE_SUFFIX)
+                    })
+            })
+            .collect::<Vec<_>>();
+        files.sort();
+        files.reverse();
+        Ok(files)
+    }
+}
+
+pub fn load_level(conn: &Connection) -> Result<LogLevel, String> {
+    Ok(get_setting(conn, SETTING_LOG_LEVEL)?
+        .and_then(|value| LogLevel::parse(&value))
+        .unwrap_or_default())
+}
+
+pub fn apply_level(logger: &Logger, level: LogLevel) -> Result<(), String> {
+    logger
+        .level
+        .modify(|filter| *filter = level.filter())
+        .map_err(|e| format!("Failed to change the log level: {}", e))
+}
+
+fn entry_from_line(line: &str) -> Option<LogEntry> {
+    let mut record: Value = serde_json::from_str(line).ok()?;
+    let level = LogLevel::parse(record.get("level")?.as_str()?)?;
+    let text = |record: &Value, key: &str| {
+        record.get(key).and_then(Value::as_str).unwrap_or("").to_string()
+    };
+    let timestamp = text(&record, "timestamp");
+    let target = text(&record, "target");
+    let command = record
+        .pointer("/span/name")
+        .and_then(Value::as_str)
+        .map(str::to_string);
+    let mut fields = record.get_mut("fields").map(Value::take).unwrap_or_default();
+    let message = fields
+        .as_object_mut()
+        .and_then(|fields| fields.remove("message"))
+        .and_then(|message| message.as_str().map(str::to_string))
+        .unwrap_or_default();
+    Some(LogEntry {
+        timestamp,
+        level,
+        target,
+        message,
+        command,
+        fields,
+    })
+}
+
+// Newest entries first, read back from the newest files until `limit` is reached
+fn recent_entries(
+    logger: &Logger,
+    limit: usize,
+    min_level: LogLevel,
+) -> Result<Vec<LogEntry>, String> {
+    let mut entries = Vec::new();
+    for path in logger.log_files()? {
+        let content = fs::read_to_string(&path)
+            .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+        for line in content.lines().rev() {
+            if let Some(entry) = entry_from_line(line).filter(|entry| entry.level <= min_level) {
+                entries.push(entry);
+                if entries.len() == limit {
+                    return Ok(entries);
+                }
+            }
+        }
+    }
+    Ok(entries)
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn get_log_level(pool: State<'_, DbPool>) -> Result<LogLevel, AppError> {
+    let conn = db::get_conn(&pool)?;
+    Ok(load_level(&conn)?)
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn set_log_level(
+    pool: State<'_, DbPool>,
+    logger: State<'_, Logger>,
+    level: LogLevel,
+) -> Result<LogLevel, AppError> {
+    let conn = db::get_conn(&pool)?;
+    set_setting(&conn, SETTING_LOG_LEVEL, level.as_str())?;
+    apply_level(&logger, level)?;
+    tracing::info!(level = level.as_str(), "log level changed");
+    Ok(level)
+}
+
+// `level` is the least severe level to include; all levels are shown when it is not given
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn get_recent_logs(
+    logger: State<'_, Logger>,
+    limit: Option<usize>,
+    level: Option<LogLevel>,
+) -> Result<Vec<LogEntry>, AppError> {
+    let limit = limit.unwrap_or(DEFAULT_RECENT_LOGS).clamp(1, MAX_RECENT_LOGS);
+    Ok(recent_entries(&logger, limit, level.unwrap_or(LogLevel::Trace))?)
+}
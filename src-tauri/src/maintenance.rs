@@ -0,0 +1,93 @@
+use std::time::Instant;
+
+use chrono::Local;
+use rusqlite::Connection;
+use serde::{Deserialize, Serialize};
+use tauri::State;
+
+use crate::db::{self, set_setting, DbPool};
+use crate::error::AppError;
+
+// When maintenance last completed, whether run by hand or by the scheduler
+pub const SETTING_LAST_RUN: &str = "maintenance_last_run_at";
+
+const TIMESTAMP_FORMAT: &str = "%Y-%m-%d %H:%M:%S";
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct MaintenanceReport {
+    pub journal_mode: String,
+    // True when this run moved the database out of rollback journal mode
+    pub switched_to_wal: bool,
+    pub size_before_bytes: i64,
+    pub size_after_bytes: i64,
+    pub reclaimed_bytes: i64,
+    // WAL pages copied back into the database file by the final checkpoint
+    pub checkpointed_pages: i64,
+    pub duration_ms: u64,
+    pub ran_at: String,
+}
+
+fn database_size(conn: &Connection) -> Result<i64, String> {
+    conn.query_row(
+        "SELECT page_count * page_size FROM pragma_page_count(), pragma_page_size()",
+        [],
+        |row| row.get(0),
+    )
+    .map_err(|e| e.to_string())
+}
+
+fn ensure_wal(conn: &Connection) -> Result<(String, bool), String> {
+    let mode: String = conn
+        .query_row("PRAGMA journal_mode", [], |row| row.get(0))
+        .map_err(|e| e.to_string())?;
+    if mode.eq_ignore_ascii_case("wal") {
+        return Ok((mode.to_lowercase(), false));
+    }
+    // The pragma answers with the mode actually in effect, which stays the old one when
+    // another connection holds a lock
+    let mode: String = conn
+        .query_row("PRAGMA journal_mode = WAL", [], |row| row.get(0))
+        .map_err(|e| format!("Failed to switch to WAL mode: {}", e))?;
+    let switched = mode.eq_ignore_ascii_case("wal");
+    Ok((mode.to_lowercase(), switched))
+}
+
+// Rebuilds the database file without free pages, refreshes the query planner's statistics
+// and folds the WAL back into the file. Must run outside a transaction; other connections
+// wait on the busy timeout while VACUUM holds the database.
+pub fn run(conn: &Connection) -> Result<MaintenanceReport, String> {
+    let started = Instant::now();
+    let (journal_mode, switched_to_wal) = ensure_wal(conn)?;
+    let size_before_bytes = database_size(conn)?;
+
+    conn.execute_batch("VACUUM;")
+        .map_err(|e| format!("VACUUM failed: {}", e))?;
+    conn.execute_batch("ANALYZE;")
+        .map_err(|e| format!("ANALYZE failed: {}", e))?;
+    let ran_at = Local::now().format(TIMESTAMP_FORMAT).to_string();
+    set_setting(conn, SETTING_LAST_RUN, &ran_at)?;
+    // Returns (busy, pages in the WAL, pages checkpointed); outside WAL mode all are -1
+    let checkpointed_pages: i64 = conn
+        .query_row("PRAGMA wal_checkpoint(TRUNCATE)", [], |row| row.get(2))
+        .map_err(|e| format!("WAL checkpoint failed: {}", e))?;
+
+    let size_after_bytes = database_size(conn)?;
+    Ok(MaintenanceReport {
+        journal_mode,
+        switched_to_wal,
+        size_before_bytes,
+        size_after_bytes,
+        reclaimed_bytes: (size_before_bytes - size_after_bytes).max(0),
+        checkpointed_pages: checkpointed_pages.max(0),
+        duration_ms: started.elapsed().as_millis() as u64,
+        ran_at,
+    })
+}
+
+// Compacts and optimises the database now; the backup scheduler can also run this monthly
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn run_maintenance(pool: State<'_, DbPool>) -> Result<MaintenanceReport, AppError> {
+    let conn = db::get_conn(&pool)?;
+    Ok(run(&conn)?)
+}
@@ -0,0 +1,2080 @@
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+use tauri::State;
+
+use crate::customers::normalize_customer_name;
+use crate::db::{self, DbPool};
+use crate::error::AppError;
+use crate::hsn;
+use crate::recycle_bin;
+use crate::states;
+use crate::sync;
+
+// A migration step is either plain SQL or a Rust function for data fix-ups
+pub enum Step {
+    Sql(&'static str),
+    Rust(fn(&Connection) -> Result<(), String>),
+}
+
+pub struct Migration {
+    pub version: i64,
+    pub name: &'static str,
+    pub up: Step,
+    pub down: Step,
+}
+
+// Ordered list of migrations. Append new entries with the next version number; never edit applied ones.
+pub const MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: 1,
+        name: "core_tables",
+        up: Step::Sql(
+            "
+            CREATE TABLE IF NOT EXISTS companies (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                company_name TEXT NOT NULL,
+                gst_no TEXT NOT NULL UNIQUE,
+                state_code TEXT NOT NULL,
+                created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+                updated_at DATETIME DEFAULT CURRENT_TIMESTAMP
+            );
+
+            CREATE TABLE IF NOT EXISTS categories (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                name TEXT NOT NULL,
+                company_id INTEGER NOT NULL,
+                created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+                updated_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+                FOREIGN KEY (company_id) REFERENCES companies (id),
+                UNIQUE(name, company_id)
+            );
+
+            CREATE TABLE IF NOT EXISTS customers (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                report_customer TEXT NOT NULL,
+                tally_customer TEXT NOT NULL,
+                gst_no TEXT NOT NULL,
+                state_code TEXT NOT NULL,
+                category_id INTEGER NOT NULL,
+                company_id INTEGER NOT NULL,
+                normalized_name TEXT NOT NULL,
+                created_from_import_id TEXT,
+                created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+                updated_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+                FOREIGN KEY (category_id) REFERENCES categories (id),
+                FOREIGN KEY (company_id) REFERENCES companies (id),
+                UNIQUE(tally_customer, company_id),
+                UNIQUE(normalized_name, gst_no, company_id)
+            );
+            ",
+        ),
+        down: Step::Sql(
+            "
+            DROP TABLE IF EXISTS customers;
+            DROP TABLE IF EXISTS categories;
+            DROP TABLE IF EXISTS companies;
+            ",
+        ),
+    },
+    Migration {
+        version: 2,
+        name: "import_tables",
+        up: Step::Sql(
+            "
+            CREATE TABLE IF NOT EXISTS import_sessions (
+                id TEXT PRIMARY KEY,
+                company_id INTEGER NOT NULL,
+                file_name TEXT NOT NULL,
+                status TEXT NOT NULL DEFAULT 'pending',
+                created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+                updated_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+                FOREIGN KEY (company_id) REFERENCES companies (id)
+            );
+
+            CREATE TABLE IF NOT EXISTS import_customer_mappings (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                import_session_id TEXT NOT NULL,
+                report_customer_id TEXT NOT NULL,
+                report_customer_name TEXT NOT NULL,
+                mapped_customer_id INTEGER,
+                created_customer_id INTEGER,
+                status TEXT NOT NULL DEFAULT 'unverified',
+                created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+                updated_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+                FOREIGN KEY (import_session_id) REFERENCES import_sessions (id),
+                FOREIGN KEY (mapped_customer_id) REFERENCES customers (id),
+                FOREIGN KEY (created_customer_id) REFERENCES customers (id),
+                UNIQUE(import_session_id, report_customer_id)
+            );
+
+            CREATE TABLE IF NOT EXISTS persistent_customer_mappings (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                company_id INTEGER NOT NULL,
+                report_customer_name TEXT NOT NULL,
+                normalized_report_customer_name TEXT NOT NULL,
+                mapped_customer_id INTEGER NOT NULL,
+                mapping_type TEXT NOT NULL DEFAULT 'user_mapped',
+                created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+                updated_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+                FOREIGN KEY (company_id) REFERENCES companies (id),
+                FOREIGN KEY (mapped_customer_id) REFERENCES customers (id),
+                UNIQUE(company_id, normalized_report_customer_name)
+            );
+
+            CREATE TABLE IF NOT EXISTS app_settings (
+                key TEXT PRIMARY KEY,
+                value TEXT NOT NULL,
+                updated_at DATETIME DEFAULT CURRENT_TIMESTAMP
+            );
+            ",
+        ),
+        down: Step::Sql(
+            "
+            DROP TABLE IF EXISTS persistent_customer_mappings;
+            DROP TABLE IF EXISTS import_customer_mappings;
+            DROP TABLE IF EXISTS import_sessions;
+            DROP TABLE IF EXISTS app_settings;
+            ",
+        ),
+    },
+    Migration {
+        version: 3,
+        name: "backfill_customer_normalized_name",
+        up: Step::Rust(backfill_normalized_names),
+        // Columns are part of the core schema, nothing to undo
+        down: Step::Sql(""),
+    },
+    Migration {
+        version: 4,
+        name: "invoices",
+        up: Step::Sql(
+            "
+            CREATE TABLE IF NOT EXISTS invoices (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                company_id INTEGER NOT NULL,
+                invoice_number TEXT NOT NULL,
+                invoice_date TEXT NOT NULL,
+                customer_id INTEGER NOT NULL,
+                place_of_supply TEXT NOT NULL,
+                taxable_value REAL NOT NULL DEFAULT 0,
+                cgst_amount REAL NOT NULL DEFAULT 0,
+                sgst_amount REAL NOT NULL DEFAULT 0,
+                igst_amount REAL NOT NULL DEFAULT 0,
+                total_amount REAL NOT NULL DEFAULT 0,
+                status TEXT NOT NULL DEFAULT 'draft',
+                notes TEXT,
+                created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+                updated_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+                FOREIGN KEY (company_id) REFERENCES companies (id),
+                FOREIGN KEY (customer_id) REFERENCES customers (id),
+                UNIQUE(invoice_number, company_id)
+            );
+
+            CREATE INDEX IF NOT EXISTS idx_invoices_company_date ON invoices (company_id, invoice_date);
+            CREATE INDEX IF NOT EXISTS idx_invoices_customer ON invoices (customer_id);
+            ",
+        ),
+        down: Step::Sql("DROP TABLE IF EXISTS invoices;"),
+    },
+    Migration {
+        version: 5,
+        name: "invoice_lines",
+        up: Step::Sql(
+            "
+            CREATE TABLE IF NOT EXISTS invoice_lines (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                invoice_id INTEGER NOT NULL,
+                line_no INTEGER NOT NULL,
+                description TEXT NOT NULL,
+                hsn_code TEXT NOT NULL,
+                quantity REAL NOT NULL,
+                rate REAL NOT NULL,
+                discount REAL NOT NULL DEFAULT 0,
+                taxable_value REAL NOT NULL,
+                gst_rate REAL NOT NULL DEFAULT 0,
+                cgst_amount REAL NOT NULL DEFAULT 0,
+                sgst_amount REAL NOT NULL DEFAULT 0,
+                igst_amount REAL NOT NULL DEFAULT 0,
+                FOREIGN KEY (invoice_id) REFERENCES invoices (id) ON DELETE CASCADE,
+                UNIQUE(invoice_id, line_no)
+            );
+            ",
+        ),
+        down: Step::Sql("DROP TABLE IF EXISTS invoice_lines;"),
+    },
+    Migration {
+        version: 6,
+        name: "hsn_master",
+        up: Step::Rust(create_hsn_master),
+        down: Step::Sql("DROP TABLE IF EXISTS hsn_codes;"),
+    },
+    Migration {
+        version: 7,
+        name: "state_master",
+        up: Step::Rust(create_state_master),
+        down: Step::Sql("DROP TABLE IF EXISTS states;"),
+    },
+    Migration {
+        version: 8,
+        name: "gstin_verification_cache",
+        up: Step::Sql(
+            "
+            CREATE TABLE IF NOT EXISTS gstin_verifications (
+                gstin TEXT PRIMARY KEY,
+                legal_name TEXT,
+                trade_name TEXT,
+                registration_status TEXT,
+                address TEXT,
+                raw_response TEXT,
+                fetched_at DATETIME DEFAULT CURRENT_TIMESTAMP
+            );
+            ",
+        ),
+        down: Step::Sql("DROP TABLE IF EXISTS gstin_verifications;"),
+    },
+    Migration {
+        version: 9,
+        name: "import_mapping_profiles",
+        up: Step::Sql(
+            "
+            CREATE TABLE IF NOT EXISTS import_mapping_profiles (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                name TEXT NOT NULL,
+                target TEXT NOT NULL,
+                column_map TEXT NOT NULL,
+                date_format TEXT,
+                decimal_separator TEXT NOT NULL DEFAULT '.',
+                delimiter TEXT NOT NULL DEFAULT ',',
+                created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+                updated_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+                UNIQUE(name, target)
+            );
+            ",
+        ),
+        down: Step::Sql("DROP TABLE IF EXISTS import_mapping_profiles;"),
+    },
+    Migration {
+        version: 10,
+        name: "party_addresses",
+        up: Step::Sql(
+            "
+            ALTER TABLE companies ADD COLUMN address TEXT;
+            ALTER TABLE companies ADD COLUMN city TEXT;
+            ALTER TABLE companies ADD COLUMN pincode TEXT;
+            ALTER TABLE customers ADD COLUMN address TEXT;
+            ALTER TABLE customers ADD COLUMN city TEXT;
+            ALTER TABLE customers ADD COLUMN pincode TEXT;
+            ",
+        ),
+        down: Step::Sql(
+            "
+            ALTER TABLE customers DROP COLUMN pincode;
+            ALTER TABLE customers DROP COLUMN city;
+            ALTER TABLE customers DROP COLUMN address;
+            ALTER TABLE companies DROP COLUMN pincode;
+            ALTER TABLE companies DROP COLUMN city;
+            ALTER TABLE companies DROP COLUMN address;
+            ",
+        ),
+    },
+    Migration {
+        version: 11,
+        name: "einvoices",
+        up: Step::Sql(
+            "
+            CREATE TABLE IF NOT EXISTS einvoices (
+                invoice_id INTEGER PRIMARY KEY,
+                environment TEXT NOT NULL,
+                irn TEXT NOT NULL UNIQUE,
+                ack_no TEXT NOT NULL,
+                ack_date TEXT NOT NULL,
+                signed_invoice TEXT,
+                signed_qr_code TEXT NOT NULL,
+                status TEXT NOT NULL DEFAULT 'active',
+                request_payload TEXT NOT NULL,
+                response_payload TEXT NOT NULL,
+                created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+                updated_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+                FOREIGN KEY (invoice_id) REFERENCES invoices (id) ON DELETE CASCADE
+            );
+            ",
+        ),
+        down: Step::Sql("DROP TABLE IF EXISTS einvoices;"),
+    },
+    Migration {
+        version: 12,
+        name: "eway_bills",
+        up: Step::Sql(
+            "
+            CREATE TABLE IF NOT EXISTS eway_bills (
+                invoice_id INTEGER PRIMARY KEY,
+                transport_mode TEXT NOT NULL DEFAULT 'road',
+                transporter_id TEXT,
+                transporter_name TEXT,
+                vehicle_number TEXT,
+                vehicle_type TEXT NOT NULL DEFAULT 'regular',
+                transport_doc_no TEXT,
+                transport_doc_date TEXT,
+                distance_km INTEGER NOT NULL DEFAULT 0,
+                ewb_number TEXT UNIQUE,
+                ewb_date TEXT,
+                valid_until TEXT,
+                status TEXT NOT NULL DEFAULT 'pending',
+                created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+                updated_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+                FOREIGN KEY (invoice_id) REFERENCES invoices (id) ON DELETE CASCADE
+            );
+            CREATE INDEX IF NOT EXISTS idx_eway_bills_valid_until ON eway_bills (valid_until);
+            ",
+        ),
+        down: Step::Sql("DROP TABLE IF EXISTS eway_bills;"),
+    },
+    Migration {
+        version: 13,
+        name: "invoice_templates",
+        up: Step::Sql(
+            "
+            CREATE TABLE IF NOT EXISTS invoice_templates (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                name TEXT NOT NULL UNIQUE,
+                layout TEXT NOT NULL,
+                logo_path TEXT,
+                header_template TEXT,
+                footer_template TEXT,
+                show_qr_code INTEGER NOT NULL DEFAULT 1,
+                show_hsn_summary INTEGER NOT NULL DEFAULT 1,
+                show_amount_in_words INTEGER NOT NULL DEFAULT 1,
+                show_signature INTEGER NOT NULL DEFAULT 1,
+                is_default INTEGER NOT NULL DEFAULT 0,
+                created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+                updated_at DATETIME DEFAULT CURRENT_TIMESTAMP
+            );
+            CREATE UNIQUE INDEX IF NOT EXISTS idx_invoice_templates_default
+                ON invoice_templates (is_default) WHERE is_default = 1;
+
+            INSERT OR IGNORE INTO invoice_templates
+                (name, layout, footer_template, show_hsn_summary, show_signature, is_default)
+            VALUES
+                ('Standard A4', 'a4', 'This is a computer generated invoice.', 1, 1, 1),
+                ('Compact A5', 'a5', 'This is a computer generated invoice.', 0, 1, 0),
+                ('Thermal 80mm', 'thermal_80mm', 'Thank you for your business!', 0, 0, 0);
+            ",
+        ),
+        down: Step::Sql("DROP TABLE IF EXISTS invoice_templates;"),
+    },
+    Migration {
+        version: 14,
+        name: "invoice_round_off",
+        up: Step::Sql("ALTER TABLE invoices ADD COLUMN round_off REAL NOT NULL DEFAULT 0;"),
+        down: Step::Sql("ALTER TABLE invoices DROP COLUMN round_off;"),
+    },
+    Migration {
+        version: 15,
+        name: "financial_years",
+        up: Step::Sql(
+            "
+            CREATE TABLE IF NOT EXISTS financial_years (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                company_id INTEGER NOT NULL,
+                label TEXT NOT NULL,
+                start_date TEXT NOT NULL,
+                end_date TEXT NOT NULL,
+                is_closed INTEGER NOT NULL DEFAULT 0,
+                closed_at DATETIME,
+                created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+                updated_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+                FOREIGN KEY (company_id) REFERENCES companies (id) ON DELETE CASCADE,
+                UNIQUE(company_id, start_date)
+            );
+            CREATE TABLE IF NOT EXISTS period_locks (
+                company_id INTEGER NOT NULL,
+                period TEXT NOT NULL,
+                reason TEXT,
+                locked_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+                PRIMARY KEY (company_id, period),
+                FOREIGN KEY (company_id) REFERENCES companies (id) ON DELETE CASCADE
+            );
+            ",
+        ),
+        down: Step::Sql(
+            "
+            DROP TABLE IF EXISTS period_locks;
+            DROP TABLE IF EXISTS financial_years;
+            ",
+        ),
+    },
+    Migration {
+        version: 16,
+        name: "number_sequences",
+        up: Step::Sql(
+            "
+            CREATE TABLE IF NOT EXISTS number_sequences (
+                company_id INTEGER NOT NULL,
+                document_type TEXT NOT NULL,
+                prefix TEXT NOT NULL,
+                padding INTEGER NOT NULL DEFAULT 4,
+                reset_each_year INTEGER NOT NULL DEFAULT 1,
+                created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+                updated_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+                PRIMARY KEY (company_id, document_type),
+                FOREIGN KEY (company_id) REFERENCES companies (id) ON DELETE CASCADE
+            );
+            CREATE TABLE IF NOT EXISTS number_counters (
+                company_id INTEGER NOT NULL,
+                document_type TEXT NOT NULL,
+                period_key TEXT NOT NULL,
+                last_number INTEGER NOT NULL,
+                PRIMARY KEY (company_id, document_type, period_key),
+                FOREIGN KEY (company_id) REFERENCES companies (id) ON DELETE CASCADE
+            );
+            ",
+        ),
+        down: Step::Sql(
+            "
+            DROP TABLE IF EXISTS number_counters;
+            DROP TABLE IF EXISTS number_sequences;
+            ",
+        ),
+    },
+    Migration {
+        version: 17,
+        name: "credit_debit_notes",
+        up: Step::Sql(
+            "
+            CREATE TABLE IF NOT EXISTS credit_debit_notes (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                company_id INTEGER NOT NULL,
+                invoice_id INTEGER NOT NULL,
+                note_type TEXT NOT NULL,
+                note_number TEXT NOT NULL,
+                note_date TEXT NOT NULL,
+                reason TEXT NOT NULL,
+                taxable_value REAL NOT NULL DEFAULT 0,
+                cgst_amount REAL NOT NULL DEFAULT 0,
+                sgst_amount REAL NOT NULL DEFAULT 0,
+                igst_amount REAL NOT NULL DEFAULT 0,
+                total_amount REAL NOT NULL DEFAULT 0,
+                status TEXT NOT NULL DEFAULT 'draft',
+                notes TEXT,
+                created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+                updated_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+                FOREIGN KEY (company_id) REFERENCES companies (id),
+                FOREIGN KEY (invoice_id) REFERENCES invoices (id),
+                UNIQUE(note_number, company_id)
+            );
+            CREATE INDEX IF NOT EXISTS idx_credit_debit_notes_company_date
+                ON credit_debit_notes (company_id, note_date);
+            CREATE INDEX IF NOT EXISTS idx_credit_debit_notes_invoice
+                ON credit_debit_notes (invoice_id);
+
+            CREATE TABLE IF NOT EXISTS credit_debit_note_lines (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                note_id INTEGER NOT NULL,
+                line_no INTEGER NOT NULL,
+                invoice_line_id INTEGER,
+                description TEXT NOT NULL,
+                hsn_code TEXT NOT NULL,
+                quantity REAL NOT NULL DEFAULT 0,
+                taxable_value REAL NOT NULL,
+                gst_rate REAL NOT NULL DEFAULT 0,
+                cgst_amount REAL NOT NULL DEFAULT 0,
+                sgst_amount REAL NOT NULL DEFAULT 0,
+                igst_amount REAL NOT NULL DEFAULT 0,
+                FOREIGN KEY (note_id) REFERENCES credit_debit_notes (id) ON DELETE CASCADE,
+                FOREIGN KEY (invoice_line_id) REFERENCES invoice_lines (id) ON DELETE SET NULL,
+                UNIQUE(note_id, line_no)
+            );
+            ",
+        ),
+        down: Step::Sql(
+            "
+            DROP TABLE IF EXISTS credit_debit_note_lines;
+            DROP TABLE IF EXISTS credit_debit_notes;
+            ",
+        ),
+    },
+    Migration {
+        version: 18,
+        name: "receipts",
+        up: Step::Sql(
+            "
+            CREATE TABLE IF NOT EXISTS receipts (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                company_id INTEGER NOT NULL,
+                customer_id INTEGER NOT NULL,
+                receipt_date TEXT NOT NULL,
+                mode TEXT NOT NULL,
+                reference TEXT,
+                amount REAL NOT NULL,
+                notes TEXT,
+                created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+                updated_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+                FOREIGN KEY (company_id) REFERENCES companies (id),
+                FOREIGN KEY (customer_id) REFERENCES customers (id)
+            );
+            CREATE INDEX IF NOT EXISTS idx_receipts_customer ON receipts (customer_id, receipt_date);
+
+            CREATE TABLE IF NOT EXISTS receipt_allocations (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                receipt_id INTEGER NOT NULL,
+                invoice_id INTEGER NOT NULL,
+                amount REAL NOT NULL,
+                created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+                FOREIGN KEY (receipt_id) REFERENCES receipts (id) ON DELETE CASCADE,
+                FOREIGN KEY (invoice_id) REFERENCES invoices (id),
+                UNIQUE(receipt_id, invoice_id)
+            );
+            CREATE INDEX IF NOT EXISTS idx_receipt_allocations_invoice
+                ON receipt_allocations (invoice_id);
+
+            ALTER TABLE invoices ADD COLUMN amount_received REAL NOT NULL DEFAULT 0;
+            ",
+        ),
+        down: Step::Sql(
+            "
+            ALTER TABLE invoices DROP COLUMN amount_received;
+            DROP TABLE IF EXISTS receipt_allocations;
+            DROP TABLE IF EXISTS receipts;
+            ",
+        ),
+    },
+    Migration {
+        version: 19,
+        name: "invoice_cess",
+        // Compensation cess is reported separately from GST; existing invoices carry none
+        up: Step::Sql("ALTER TABLE invoices ADD COLUMN cess_amount REAL NOT NULL DEFAULT 0;"),
+        down: Step::Sql("ALTER TABLE invoices DROP COLUMN cess_amount;"),
+    },
+    Migration {
+        version: 20,
+        name: "soft_delete",
+        up: Step::Sql(
+            "
+            ALTER TABLE categories ADD COLUMN deleted_at DATETIME;
+            ALTER TABLE customers ADD COLUMN deleted_at DATETIME;
+            ALTER TABLE invoices ADD COLUMN deleted_at DATETIME;
+            ",
+        ),
+        down: Step::Sql(
+            "
+            DELETE FROM invoices WHERE deleted_at IS NOT NULL;
+            DELETE FROM customers WHERE deleted_at IS NOT NULL;
+            DELETE FROM categories WHERE deleted_at IS NOT NULL;
+            ALTER TABLE invoices DROP COLUMN deleted_at;
+            ALTER TABLE customers DROP COLUMN deleted_at;
+            ALTER TABLE categories DROP COLUMN deleted_at;
+            ",
+        ),
+    },
+    Migration {
+        version: 21,
+        name: "audit_log",
+        up: Step::Sql(
+            "
+            CREATE TABLE IF NOT EXISTS audit_log (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                company_id INTEGER NOT NULL,
+                entity TEXT NOT NULL,
+                entity_id INTEGER NOT NULL,
+                action TEXT NOT NULL,
+                changes TEXT NOT NULL DEFAULT '[]',
+                user_name TEXT,
+                created_at DATETIME DEFAULT CURRENT_TIMESTAMP
+            );
+            CREATE INDEX IF NOT EXISTS idx_audit_log_entity
+                ON audit_log (company_id, entity, entity_id);
+
+            -- The log is append-only
+            CREATE TRIGGER IF NOT EXISTS audit_log_no_update BEFORE UPDATE ON audit_log
+            BEGIN
+                SELECT RAISE(ABORT, 'Audit log entries cannot be changed');
+            END;
+            CREATE TRIGGER IF NOT EXISTS audit_log_no_delete BEFORE DELETE ON audit_log
+            BEGIN
+                SELECT RAISE(ABORT, 'Audit log entries cannot be deleted');
+            END;
+            ",
+        ),
+        down: Step::Sql("DROP TABLE IF EXISTS audit_log;"),
+    },
+    Migration {
+        version: 22,
+        name: "users",
+        up: Step::Sql(
+            "
+            CREATE TABLE IF NOT EXISTS users (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                username TEXT NOT NULL UNIQUE COLLATE NOCASE,
+                password_hash TEXT NOT NULL,
+                role TEXT NOT NULL DEFAULT 'viewer',
+                is_active INTEGER NOT NULL DEFAULT 1,
+                last_login_at DATETIME,
+                created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+                updated_at DATETIME DEFAULT CURRENT_TIMESTAMP
+            );
+            ",
+        ),
+        down: Step::Sql("DROP TABLE IF EXISTS users;"),
+    },
+    Migration {
+        version: 23,
+        name: "read_only_states",
+        up: Step::Sql(
+            "
+            -- The state master is reference data seeded by the app
+            CREATE TRIGGER IF NOT EXISTS states_no_insert BEFORE INSERT ON states
+            BEGIN
+                SELECT RAISE(ABORT, 'The state master is read-only');
+            END;
+            CREATE TRIGGER IF NOT EXISTS states_no_update BEFORE UPDATE ON states
+            BEGIN
+                SELECT RAISE(ABORT, 'The state master is read-only');
+            END;
+            CREATE TRIGGER IF NOT EXISTS states_no_delete BEFORE DELETE ON states
+            BEGIN
+                SELECT RAISE(ABORT, 'The state master is read-only');
+            END;
+            ",
+        ),
+        down: Step::Sql(
+            "
+            DROP TRIGGER IF EXISTS states_no_insert;
+            DROP TRIGGER IF EXISTS states_no_update;
+            DROP TRIGGER IF EXISTS states_no_delete;
+            ",
+        ),
+    },
+    Migration {
+        version: 24,
+        name: "invoice_supply_kind",
+        up: Step::Sql(
+            "
+            ALTER TABLE invoices ADD COLUMN supply_kind TEXT NOT NULL DEFAULT 'regular';
+            ",
+        ),
+        down: Step::Sql("ALTER TABLE invoices DROP COLUMN supply_kind;"),
+    },
+    Migration {
+        version: 25,
+        name: "invoice_discounts",
+        up: Step::Sql(
+            "
+            ALTER TABLE invoices ADD COLUMN discount_percent REAL NOT NULL DEFAULT 0;
+            ALTER TABLE invoices ADD COLUMN discount_amount REAL NOT NULL DEFAULT 0;
+            ALTER TABLE invoice_lines ADD COLUMN discount_percent REAL NOT NULL DEFAULT 0;
+            ALTER TABLE invoice_lines ADD COLUMN invoice_discount REAL NOT NULL DEFAULT 0;
+            ",
+        ),
+        down: Step::Sql(
+            "
+            ALTER TABLE invoice_lines DROP COLUMN invoice_discount;
+            ALTER TABLE invoice_lines DROP COLUMN discount_percent;
+            ALTER TABLE invoices DROP COLUMN discount_amount;
+            ALTER TABLE invoices DROP COLUMN discount_percent;
+            ",
+        ),
+    },
+    Migration {
+        version: 26,
+        name: "tcs",
+        up: Step::Sql(
+            "
+            ALTER TABLE invoices ADD COLUMN tcs_base REAL NOT NULL DEFAULT 0;
+            ALTER TABLE invoices ADD COLUMN tcs_amount REAL NOT NULL DEFAULT 0;
+            CREATE TABLE IF NOT EXISTS tcs_settings (
+                company_id INTEGER PRIMARY KEY,
+                enabled INTEGER NOT NULL DEFAULT 0,
+                rate REAL NOT NULL DEFAULT 0.1,
+                rate_without_pan REAL NOT NULL DEFAULT 1,
+                threshold REAL NOT NULL DEFAULT 5000000,
+                updated_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+                FOREIGN KEY (company_id) REFERENCES companies (id) ON DELETE CASCADE
+            );
+            ",
+        ),
+        down: Step::Sql(
+            "
+            DROP TABLE IF EXISTS tcs_settings;
+            ALTER TABLE invoices DROP COLUMN tcs_amount;
+            ALTER TABLE invoices DROP COLUMN tcs_base;
+            ",
+        ),
+    },
+    Migration {
+        version: 27,
+        name: "reverse_charge",
+        up: Step::Sql(
+            "
+            ALTER TABLE invoices ADD COLUMN reverse_charge INTEGER NOT NULL DEFAULT 0;
+            ALTER TABLE invoices ADD COLUMN rcm_cgst_amount REAL NOT NULL DEFAULT 0;
+            ALTER TABLE invoices ADD COLUMN rcm_sgst_amount REAL NOT NULL DEFAULT 0;
+            ALTER TABLE invoices ADD COLUMN rcm_igst_amount REAL NOT NULL DEFAULT 0;
+            ",
+        ),
+        down: Step::Sql(
+            "
+            ALTER TABLE invoices DROP COLUMN rcm_igst_amount;
+            ALTER TABLE invoices DROP COLUMN rcm_sgst_amount;
+            ALTER TABLE invoices DROP COLUMN rcm_cgst_amount;
+            ALTER TABLE invoices DROP COLUMN reverse_charge;
+            ",
+        ),
+    },
+    Migration {
+        version: 28,
+        name: "export_invoices",
+        up: Step::Sql(
+            "
+            ALTER TABLE invoices ADD COLUMN export_mode TEXT;
+            ALTER TABLE invoices ADD COLUMN export_currency TEXT;
+            ALTER TABLE invoices ADD COLUMN export_exchange_rate REAL;
+            ALTER TABLE invoices ADD COLUMN shipping_bill_number TEXT;
+            ALTER TABLE invoices ADD COLUMN shipping_bill_date TEXT;
+            ALTER TABLE invoices ADD COLUMN port_code TEXT;
+            UPDATE invoices
+            SET export_mode = CASE WHEN igst_amount > 0 THEN 'with_payment' ELSE 'lut' END,
+                export_currency = 'INR',
+                export_exchange_rate = 1
+            WHERE supply_kind = 'export';
+            ",
+        ),
+        down: Step::Sql(
+            "
+            ALTER TABLE invoices DROP COLUMN port_code;
+            ALTER TABLE invoices DROP COLUMN shipping_bill_date;
+            ALTER TABLE invoices DROP COLUMN shipping_bill_number;
+            ALTER TABLE invoices DROP COLUMN export_exchange_rate;
+            ALTER TABLE invoices DROP COLUMN export_currency;
+            ALTER TABLE invoices DROP COLUMN export_mode;
+            ",
+        ),
+    },
+    Migration {
+        version: 29,
+        name: "customer_registration_type",
+        up: Step::Sql(
+            "
+            ALTER TABLE customers ADD COLUMN registration_type TEXT NOT NULL DEFAULT 'regular';
+            UPDATE customers SET registration_type = 'unregistered' WHERE TRIM(gst_no) = '';
+            ALTER TABLE invoices ADD COLUMN sez_mode TEXT;
+            ",
+        ),
+        down: Step::Sql(
+            "
+            ALTER TABLE invoices DROP COLUMN sez_mode;
+            ALTER TABLE customers DROP COLUMN registration_type;
+            ",
+        ),
+    },
+    Migration {
+        version: 30,
+        name: "purchases_gstr2b",
+        up: Step::Sql(
+            "
+            CREATE TABLE IF NOT EXISTS purchases (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                company_id INTEGER NOT NULL,
+                supplier_gstin TEXT NOT NULL,
+                supplier_name TEXT NOT NULL,
+                invoice_number TEXT NOT NULL,
+                invoice_date TEXT NOT NULL,
+                place_of_supply TEXT NOT NULL,
+                reverse_charge INTEGER NOT NULL DEFAULT 0,
+                taxable_value REAL NOT NULL DEFAULT 0,
+                igst_amount REAL NOT NULL DEFAULT 0,
+                cgst_amount REAL NOT NULL DEFAULT 0,
+                sgst_amount REAL NOT NULL DEFAULT 0,
+                cess_amount REAL NOT NULL DEFAULT 0,
+                total_amount REAL NOT NULL DEFAULT 0,
+                notes TEXT,
+                created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+                updated_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+                FOREIGN KEY (company_id) REFERENCES companies (id)
+            );
+            CREATE INDEX IF NOT EXISTS idx_purchases_company_date
+                ON purchases (company_id, invoice_date);
+
+            CREATE TABLE IF NOT EXISTS gstr2b_entries (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                company_id INTEGER NOT NULL,
+                period TEXT NOT NULL,
+                supplier_gstin TEXT NOT NULL,
+                supplier_name TEXT NOT NULL DEFAULT '',
+                invoice_number TEXT NOT NULL,
+                invoice_date TEXT NOT NULL,
+                invoice_type TEXT NOT NULL DEFAULT 'R',
+                place_of_supply TEXT NOT NULL DEFAULT '',
+                reverse_charge INTEGER NOT NULL DEFAULT 0,
+                taxable_value REAL NOT NULL DEFAULT 0,
+                igst_amount REAL NOT NULL DEFAULT 0,
+                cgst_amount REAL NOT NULL DEFAULT 0,
+                sgst_amount REAL NOT NULL DEFAULT 0,
+                cess_amount REAL NOT NULL DEFAULT 0,
+                total_amount REAL NOT NULL DEFAULT 0,
+                itc_available INTEGER NOT NULL DEFAULT 1,
+                imported_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+                FOREIGN KEY (company_id) REFERENCES companies (id)
+            );
+            CREATE INDEX IF NOT EXISTS idx_gstr2b_entries_period
+                ON gstr2b_entries (company_id, period);
+            ",
+        ),
+        down: Step::Sql(
+            "
+            DROP TABLE IF EXISTS gstr2b_entries;
+            DROP TABLE IF EXISTS purchases;
+            ",
+        ),
+    },
+    Migration {
+        version: 31,
+        name: "einvoice_cancellation",
+        up: Step::Sql(
+            "
+            ALTER TABLE einvoices ADD COLUMN cancel_reason TEXT;
+            ALTER TABLE einvoices ADD COLUMN cancel_remarks TEXT;
+            ALTER TABLE einvoices ADD COLUMN cancelled_at TEXT;
+            ",
+        ),
+        down: Step::Sql(
+            "
+            ALTER TABLE einvoices DROP COLUMN cancelled_at;
+            ALTER TABLE einvoices DROP COLUMN cancel_remarks;
+            ALTER TABLE einvoices DROP COLUMN cancel_reason;
+            ",
+        ),
+    },
+    Migration {
+        version: 32,
+        name: "gstr1_amendments",
+        up: Step::Sql(
+            "
+            CREATE TABLE IF NOT EXISTS gstr1_amendments (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                company_id INTEGER NOT NULL,
+                document_type TEXT NOT NULL,
+                document_id INTEGER NOT NULL,
+                original_number TEXT NOT NULL,
+                original_date TEXT NOT NULL,
+                original_period TEXT NOT NULL,
+                return_period TEXT NOT NULL,
+                reason TEXT,
+                created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+                FOREIGN KEY (company_id) REFERENCES companies (id),
+                UNIQUE(company_id, document_type, document_id, return_period)
+            );
+            CREATE INDEX IF NOT EXISTS idx_gstr1_amendments_period
+                ON gstr1_amendments (company_id, return_period);
+            ",
+        ),
+        down: Step::Sql("DROP TABLE IF EXISTS gstr1_amendments;"),
+    },
+    Migration {
+        version: 33,
+        name: "validation_rules",
+        up: Step::Sql(
+            "
+            CREATE TABLE IF NOT EXISTS validation_rules (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                company_id INTEGER NOT NULL,
+                entity TEXT NOT NULL,
+                field TEXT NOT NULL,
+                rule_type TEXT NOT NULL,
+                parameter TEXT,
+                condition_field TEXT,
+                condition_value TEXT,
+                severity TEXT NOT NULL DEFAULT 'error',
+                message TEXT,
+                enabled BOOLEAN NOT NULL DEFAULT 1,
+                created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+                updated_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+                FOREIGN KEY (company_id) REFERENCES companies (id)
+            );
+            CREATE INDEX IF NOT EXISTS idx_validation_rules_entity
+                ON validation_rules (company_id, entity);
+            ",
+        ),
+        down: Step::Sql("DROP TABLE IF EXISTS validation_rules;"),
+    },
+    Migration {
+        version: 34,
+        name: "company_settings",
+        up: Step::Sql(
+            "
+            CREATE TABLE IF NOT EXISTS company_settings (
+                company_id INTEGER NOT NULL,
+                key TEXT NOT NULL,
+                value TEXT NOT NULL,
+                updated_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+                PRIMARY KEY (company_id, key),
+                FOREIGN KEY (company_id) REFERENCES companies (id) ON DELETE CASCADE
+            );
+            ",
+        ),
+        down: Step::Sql("DROP TABLE IF EXISTS company_settings;"),
+    },
+    Migration {
+        version: 35,
+        name: "email_log",
+        up: Step::Sql(
+            "
+            CREATE TABLE IF NOT EXISTS email_log (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                company_id INTEGER NOT NULL,
+                kind TEXT NOT NULL,
+                invoice_id INTEGER,
+                customer_id INTEGER,
+                recipients TEXT NOT NULL,
+                subject TEXT NOT NULL,
+                attachment_name TEXT,
+                status TEXT NOT NULL,
+                server_response TEXT,
+                error TEXT,
+                sent_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+                FOREIGN KEY (company_id) REFERENCES companies (id)
+            );
+            CREATE INDEX IF NOT EXISTS idx_email_log_company
+                ON email_log (company_id, sent_at);
+            CREATE INDEX IF NOT EXISTS idx_email_log_invoice
+                ON email_log (invoice_id);
+            ",
+        ),
+        down: Step::Sql("DROP TABLE IF EXISTS email_log;"),
+    },
+    Migration {
+        version: 36,
+        name: "attachments",
+        up: Step::Sql(
+            "
+            CREATE TABLE IF NOT EXISTS attachments (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                company_id INTEGER NOT NULL,
+                entity_type TEXT NOT NULL,
+                entity_id INTEGER NOT NULL,
+                file_name TEXT NOT NULL,
+                content_type TEXT NOT NULL,
+                size_bytes INTEGER NOT NULL,
+                sha256 TEXT NOT NULL,
+                created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+                FOREIGN KEY (company_id) REFERENCES companies (id)
+            );
+            CREATE INDEX IF NOT EXISTS idx_attachments_entity
+                ON attachments (company_id, entity_type, entity_id);
+            CREATE INDEX IF NOT EXISTS idx_attachments_sha256
+                ON attachments (sha256);
+            ",
+        ),
+        down: Step::Sql("DROP TABLE IF EXISTS attachments;"),
+    },
+    Migration {
+        version: 37,
+        name: "custom_reports",
+        up: Step::Sql(
+            "
+            CREATE TABLE IF NOT EXISTS custom_reports (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                company_id INTEGER NOT NULL,
+                name TEXT NOT NULL,
+                description TEXT,
+                definition TEXT NOT NULL,
+                created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+                updated_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+                FOREIGN KEY (company_id) REFERENCES companies (id),
+                UNIQUE(company_id, name)
+            );
+            ",
+        ),
+        down: Step::Sql("DROP TABLE IF EXISTS custom_reports;"),
+    },
+    Migration {
+        version: 38,
+        name: "report_schedules",
+        up: Step::Sql(
+            "
+            CREATE TABLE IF NOT EXISTS report_schedules (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                company_id INTEGER NOT NULL,
+                name TEXT NOT NULL,
+                report_kind TEXT NOT NULL,
+                custom_report_id INTEGER,
+                period TEXT,
+                format TEXT NOT NULL DEFAULT 'xlsx',
+                cron TEXT NOT NULL,
+                output_folder TEXT,
+                email_to TEXT,
+                enabled INTEGER NOT NULL DEFAULT 1,
+                last_run_at DATETIME,
+                next_run_at DATETIME,
+                created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+                updated_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+                FOREIGN KEY (company_id) REFERENCES companies (id),
+                FOREIGN KEY (custom_report_id) REFERENCES custom_reports (id) ON DELETE CASCADE,
+                UNIQUE(company_id, name)
+            );
+            CREATE TABLE IF NOT EXISTS report_schedule_runs (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                schedule_id INTEGER NOT NULL,
+                started_at DATETIME NOT NULL,
+                finished_at DATETIME NOT NULL,
+                status TEXT NOT NULL CHECK(status IN ('succeeded', 'failed')),
+                output_path TEXT,
+                emailed_to TEXT,
+                error TEXT,
+                FOREIGN KEY (schedule_id) REFERENCES report_schedules (id) ON DELETE CASCADE
+            );
+            CREATE INDEX IF NOT EXISTS idx_report_schedule_runs_schedule
+                ON report_schedule_runs (schedule_id, started_at);
+            ",
+        ),
+        down: Step::Sql(
+            "
+            DROP TABLE IF EXISTS report_schedule_runs;
+            DROP TABLE IF EXISTS report_schedules;
+            ",
+        ),
+    },
+    Migration {
+        version: 39,
+        name: "payment_reminders",
+        up: Step::Sql(
+            "
+            CREATE TABLE IF NOT EXISTS payment_reminders (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                company_id INTEGER NOT NULL,
+                invoice_id INTEGER NOT NULL,
+                customer_id INTEGER NOT NULL,
+                kind TEXT NOT NULL CHECK(kind IN ('due_soon', 'overdue')),
+                due_date TEXT NOT NULL,
+                outstanding REAL NOT NULL,
+                title TEXT NOT NULL,
+                message TEXT NOT NULL,
+                read_at DATETIME,
+                created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+                FOREIGN KEY (company_id) REFERENCES companies (id),
+                FOREIGN KEY (invoice_id) REFERENCES invoices (id) ON DELETE CASCADE,
+                FOREIGN KEY (customer_id) REFERENCES customers (id),
+                UNIQUE(invoice_id, kind)
+            );
+            CREATE INDEX IF NOT EXISTS idx_payment_reminders_company
+                ON payment_reminders (company_id, created_at);
+            CREATE TABLE IF NOT EXISTS reminder_snoozes (
+                company_id INTEGER NOT NULL,
+                customer_id INTEGER NOT NULL,
+                snoozed_until TEXT NOT NULL,
+                created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+                PRIMARY KEY (company_id, customer_id),
+                FOREIGN KEY (company_id) REFERENCES companies (id),
+                FOREIGN KEY (customer_id) REFERENCES customers (id) ON DELETE CASCADE
+            );
+            ",
+        ),
+        down: Step::Sql(
+            "
+            DROP TABLE IF EXISTS reminder_snoozes;
+            DROP TABLE IF EXISTS payment_reminders;
+            ",
+        ),
+    },
+    Migration {
+        version: 40,
+        name: "currencies",
+        up: Step::Sql(
+            "
+            CREATE TABLE IF NOT EXISTS currencies (
+                code TEXT PRIMARY KEY,
+                name TEXT NOT NULL,
+                symbol TEXT,
+                decimal_places INTEGER NOT NULL DEFAULT 2,
+                active INTEGER NOT NULL DEFAULT 1
+            );
+            INSERT OR IGNORE INTO currencies (code, name, symbol, decimal_places) VALUES
+                ('INR', 'Indian Rupee', '₹', 2),
+                ('USD', 'US Dollar', '$', 2),
+                ('EUR', 'Euro', '€', 2),
+                ('GBP', 'Pound Sterling', '£', 2),
+                ('JPY', 'Japanese Yen', '¥', 0),
+                ('AED', 'UAE Dirham', NULL, 2),
+                ('SGD', 'Singapore Dollar', 'S$', 2),
+                ('AUD', 'Australian Dollar', 'A$', 2),
+                ('CAD', 'Canadian Dollar', 'C$', 2),
+                ('CHF', 'Swiss Franc', NULL, 2),
+                ('CNY', 'Chinese Yuan', NULL, 2);
+            INSERT OR IGNORE INTO currencies (code, name)
+                SELECT DISTINCT export_currency, export_currency FROM invoices
+                WHERE export_currency IS NOT NULL AND export_currency != '';
+
+            CREATE TABLE IF NOT EXISTS exchange_rates (
+                currency_code TEXT NOT NULL,
+                rate_date TEXT NOT NULL,
+                source TEXT NOT NULL CHECK(source IN ('rbi', 'manual')),
+                rate REAL NOT NULL,
+                updated_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+                PRIMARY KEY (currency_code, rate_date, source),
+                FOREIGN KEY (currency_code) REFERENCES currencies (code)
+            );
+
+            ALTER TABLE invoices ADD COLUMN currency_code TEXT NOT NULL DEFAULT 'INR';
+            ALTER TABLE invoices ADD COLUMN exchange_rate REAL NOT NULL DEFAULT 1;
+            ALTER TABLE invoices ADD COLUMN foreign_taxable_value REAL NOT NULL DEFAULT 0;
+            ALTER TABLE invoices ADD COLUMN foreign_total_amount REAL NOT NULL DEFAULT 0;
+            UPDATE invoices
+            SET currency_code = export_currency, exchange_rate = export_exchange_rate
+            WHERE export_currency IS NOT NULL AND export_currency != ''
+              AND export_exchange_rate > 0;
+            UPDATE invoices
+            SET foreign_taxable_value = ROUND(taxable_value / exchange_rate, 2),
+                foreign_total_amount = ROUND(total_amount / exchange_rate, 2);
+            ",
+        ),
+        down: Step::Sql(
+            "
+            ALTER TABLE invoices DROP COLUMN foreign_total_amount;
+            ALTER TABLE invoices DROP COLUMN foreign_taxable_value;
+            ALTER TABLE invoices DROP COLUMN exchange_rate;
+            ALTER TABLE invoices DROP COLUMN currency_code;
+            DROP TABLE IF EXISTS exchange_rates;
+            DROP TABLE IF EXISTS currencies;
+            ",
+        ),
+    },
+    Migration {
+        version: 41,
+        name: "units",
+        up: Step::Sql(
+            "
+            CREATE TABLE IF NOT EXISTS units (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                code TEXT NOT NULL UNIQUE,
+                name TEXT NOT NULL,
+                uqc TEXT NOT NULL,
+                decimal_places INTEGER NOT NULL DEFAULT 0,
+                base_unit_id INTEGER,
+                conversion_factor REAL NOT NULL DEFAULT 1,
+                active INTEGER NOT NULL DEFAULT 1,
+                created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+                updated_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+                FOREIGN KEY (base_unit_id) REFERENCES units (id)
+            );
+            INSERT OR IGNORE INTO units (code, name, uqc, decimal_places) VALUES
+                ('NOS', 'Numbers', 'NOS', 0),
+                ('PCS', 'Pieces', 'PCS', 0),
+                ('KGS', 'Kilograms', 'KGS', 3),
+                ('LTR', 'Litres', 'LTR', 3),
+                ('MTR', 'Meters', 'MTR', 2),
+                ('SET', 'Sets', 'SET', 0),
+                ('PAC', 'Packs', 'PAC', 0),
+                ('OTH', 'Others', 'OTH', 2);
+            INSERT OR IGNORE INTO units
+                (code, name, uqc, decimal_places, base_unit_id, conversion_factor)
+                SELECT 'GMS', 'Grammes', 'GMS', 0, id, 0.001 FROM units WHERE code = 'KGS'
+                UNION ALL
+                SELECT 'TON', 'Tonnes', 'TON', 3, id, 1000 FROM units WHERE code = 'KGS'
+                UNION ALL
+                SELECT 'MLT', 'Millilitre', 'MLT', 0, id, 0.001 FROM units WHERE code = 'LTR'
+                UNION ALL
+                SELECT 'BOX', 'Box of 12', 'BOX', 0, id, 12 FROM units WHERE code = 'NOS'
+                UNION ALL
+                SELECT 'DOZ', 'Dozens', 'DOZ', 0, id, 12 FROM units WHERE code = 'NOS';
+
+            ALTER TABLE invoice_lines ADD COLUMN unit_id INTEGER;
+            ",
+        ),
+        down: Step::Sql(
+            "
+            ALTER TABLE invoice_lines DROP COLUMN unit_id;
+            DROP TABLE IF EXISTS units;
+            ",
+        ),
+    },
+    Migration {
+        version: 42,
+        name: "items_and_price_lists",
+        up: Step::Sql(
+            "
+            CREATE TABLE IF NOT EXISTS items (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                company_id INTEGER NOT NULL,
+                code TEXT NOT NULL,
+                name TEXT NOT NULL,
+                hsn_code TEXT NOT NULL,
+                unit_id INTEGER,
+                gst_rate REAL NOT NULL DEFAULT 0,
+                rate REAL NOT NULL DEFAULT 0,
+                active INTEGER NOT NULL DEFAULT 1,
+                created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+                updated_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+                UNIQUE (company_id, code),
+                FOREIGN KEY (company_id) REFERENCES companies (id),
+                FOREIGN KEY (unit_id) REFERENCES units (id)
+            );
+            ALTER TABLE invoice_lines ADD COLUMN item_id INTEGER;
+
+            CREATE TABLE IF NOT EXISTS price_lists (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                company_id INTEGER NOT NULL,
+                name TEXT NOT NULL,
+                active INTEGER NOT NULL DEFAULT 1,
+                created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+                updated_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+                UNIQUE (company_id, name),
+                FOREIGN KEY (company_id) REFERENCES companies (id)
+            );
+            CREATE TABLE IF NOT EXISTS price_list_items (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                price_list_id INTEGER NOT NULL,
+                item_id INTEGER NOT NULL,
+                rate REAL NOT NULL,
+                effective_from TEXT NOT NULL,
+                effective_to TEXT,
+                UNIQUE (price_list_id, item_id, effective_from),
+                FOREIGN KEY (price_list_id) REFERENCES price_lists (id) ON DELETE CASCADE,
+                FOREIGN KEY (item_id) REFERENCES items (id)
+            );
+            CREATE TABLE IF NOT EXISTS price_list_assignments (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                price_list_id INTEGER NOT NULL,
+                customer_id INTEGER,
+                category_id INTEGER,
+                CHECK ((customer_id IS NULL) != (category_id IS NULL)),
+                UNIQUE (price_list_id, customer_id, category_id),
+                FOREIGN KEY (price_list_id) REFERENCES price_lists (id) ON DELETE CASCADE,
+                FOREIGN KEY (customer_id) REFERENCES customers (id) ON DELETE CASCADE,
+                FOREIGN KEY (category_id) REFERENCES categories (id) ON DELETE CASCADE
+            );
+            CREATE INDEX IF NOT EXISTS idx_price_list_items_item
+                ON price_list_items (item_id, effective_from);
+            ",
+        ),
+        down: Step::Sql(
+            "
+            DROP TABLE IF EXISTS price_list_assignments;
+            DROP TABLE IF EXISTS price_list_items;
+            DROP TABLE IF EXISTS price_lists;
+            ALTER TABLE invoice_lines DROP COLUMN item_id;
+            DROP TABLE IF EXISTS items;
+            ",
+        ),
+    },
+    Migration {
+        version: 43,
+        name: "quotations",
+        up: Step::Sql(
+            "
+            CREATE TABLE IF NOT EXISTS quotations (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                company_id INTEGER NOT NULL,
+                quotation_number TEXT NOT NULL,
+                quotation_date TEXT NOT NULL,
+                valid_until TEXT NOT NULL,
+                customer_id INTEGER NOT NULL,
+                place_of_supply TEXT NOT NULL,
+                supply_kind TEXT NOT NULL DEFAULT 'regular',
+                discount_percent REAL NOT NULL DEFAULT 0,
+                discount_amount REAL NOT NULL DEFAULT 0,
+                taxable_value REAL NOT NULL DEFAULT 0,
+                cgst_amount REAL NOT NULL DEFAULT 0,
+                sgst_amount REAL NOT NULL DEFAULT 0,
+                igst_amount REAL NOT NULL DEFAULT 0,
+                round_off REAL NOT NULL DEFAULT 0,
+                total_amount REAL NOT NULL DEFAULT 0,
+                status TEXT NOT NULL DEFAULT 'draft'
+                    CHECK(status IN ('draft', 'sent', 'accepted', 'expired')),
+                notes TEXT,
+                invoice_id INTEGER,
+                created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+                updated_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+                UNIQUE (company_id, quotation_number),
+                FOREIGN KEY (company_id) REFERENCES companies (id),
+                FOREIGN KEY (customer_id) REFERENCES customers (id),
+                FOREIGN KEY (invoice_id) REFERENCES invoices (id)
+            );
+            CREATE TABLE IF NOT EXISTS quotation_lines (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                quotation_id INTEGER NOT NULL,
+                line_no INTEGER NOT NULL,
+                item_id INTEGER,
+                unit_id INTEGER,
+                description TEXT NOT NULL,
+                hsn_code TEXT NOT NULL,
+                quantity REAL NOT NULL,
+                rate REAL NOT NULL,
+                discount_percent REAL NOT NULL DEFAULT 0,
+                discount REAL NOT NULL DEFAULT 0,
+                invoice_discount REAL NOT NULL DEFAULT 0,
+                taxable_value REAL NOT NULL,
+                gst_rate REAL NOT NULL,
+                cgst_amount REAL NOT NULL DEFAULT 0,
+                sgst_amount REAL NOT NULL DEFAULT 0,
+                igst_amount REAL NOT NULL DEFAULT 0,
+                FOREIGN KEY (quotation_id) REFERENCES quotations (id) ON DELETE CASCADE,
+                FOREIGN KEY (item_id) REFERENCES items (id),
+                FOREIGN KEY (unit_id) REFERENCES units (id)
+            );
+            CREATE INDEX IF NOT EXISTS idx_quotations_company_date
+                ON quotations (company_id, quotation_date);
+            ",
+        ),
+        down: Step::Sql(
+            "
+            DROP TABLE IF EXISTS quotation_lines;
+            DROP TABLE IF EXISTS quotations;
+            DELETE FROM number_sequences WHERE document_type = 'quotation';
+            DELETE FROM number_counters WHERE document_type = 'quotation';
+            ",
+        ),
+    },
+    Migration {
+        version: 44,
+        name: "sales_orders",
+        up: Step::Sql(
+            "
+            CREATE TABLE IF NOT EXISTS sales_orders (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                company_id INTEGER NOT NULL,
+                order_number TEXT NOT NULL,
+                order_date TEXT NOT NULL,
+                expected_date TEXT,
+                customer_id INTEGER NOT NULL,
+                customer_reference TEXT,
+                status TEXT NOT NULL DEFAULT 'open'
+                    CHECK(status IN ('open', 'closed', 'cancelled')),
+                notes TEXT,
+                created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+                updated_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+                UNIQUE (company_id, order_number),
+                FOREIGN KEY (company_id) REFERENCES companies (id),
+                FOREIGN KEY (customer_id) REFERENCES customers (id)
+            );
+            CREATE TABLE IF NOT EXISTS sales_order_lines (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                order_id INTEGER NOT NULL,
+                line_no INTEGER NOT NULL,
+                item_id INTEGER NOT NULL,
+                description TEXT NOT NULL,
+                hsn_code TEXT NOT NULL,
+                unit_id INTEGER,
+                quantity REAL NOT NULL,
+                rate REAL NOT NULL,
+                discount_percent REAL NOT NULL DEFAULT 0,
+                gst_rate REAL NOT NULL,
+                FOREIGN KEY (order_id) REFERENCES sales_orders (id) ON DELETE CASCADE,
+                FOREIGN KEY (item_id) REFERENCES items (id),
+                FOREIGN KEY (unit_id) REFERENCES units (id)
+            );
+            CREATE INDEX IF NOT EXISTS idx_sales_orders_company_status
+                ON sales_orders (company_id, status);
+            ALTER TABLE invoice_lines ADD COLUMN sales_order_line_id INTEGER;
+            CREATE INDEX IF NOT EXISTS idx_invoice_lines_sales_order_line
+                ON invoice_lines (sales_order_line_id);
+            ",
+        ),
+        down: Step::Sql(
+            "
+            DROP INDEX IF EXISTS idx_invoice_lines_sales_order_line;
+            ALTER TABLE invoice_lines DROP COLUMN sales_order_line_id;
+            DROP TABLE IF EXISTS sales_order_lines;
+            DROP TABLE IF EXISTS sales_orders;
+            DELETE FROM number_sequences WHERE document_type = 'sales_order';
+            DELETE FROM number_counters WHERE document_type = 'sales_order';
+            ",
+        ),
+    },
+    Migration {
+        version: 45,
+        name: "delivery_challans",
+        up: Step::Sql(
+            "
+            CREATE TABLE IF NOT EXISTS delivery_challans (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                company_id INTEGER NOT NULL,
+                challan_number TEXT NOT NULL,
+                challan_date TEXT NOT NULL,
+                customer_id INTEGER NOT NULL,
+                purpose TEXT NOT NULL
+                    CHECK(purpose IN ('job_work', 'supply_on_approval', 'liquid_gas', 'other')),
+                place_of_supply TEXT NOT NULL,
+                vehicle_number TEXT,
+                total_value REAL NOT NULL DEFAULT 0,
+                status TEXT NOT NULL DEFAULT 'draft'
+                    CHECK(status IN ('draft', 'issued', 'invoiced', 'cancelled')),
+                notes TEXT,
+                invoice_id INTEGER,
+                created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+                updated_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+                UNIQUE (company_id, challan_number),
+                FOREIGN KEY (company_id) REFERENCES companies (id),
+                FOREIGN KEY (customer_id) REFERENCES customers (id),
+                FOREIGN KEY (invoice_id) REFERENCES invoices (id)
+            );
+            CREATE TABLE IF NOT EXISTS delivery_challan_lines (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                challan_id INTEGER NOT NULL,
+                line_no INTEGER NOT NULL,
+                item_id INTEGER,
+                unit_id INTEGER,
+                description TEXT NOT NULL,
+                hsn_code TEXT NOT NULL,
+                quantity REAL NOT NULL,
+                rate REAL NOT NULL,
+                value REAL NOT NULL,
+                gst_rate REAL NOT NULL,
+                FOREIGN KEY (challan_id) REFERENCES delivery_challans (id) ON DELETE CASCADE,
+                FOREIGN KEY (item_id) REFERENCES items (id),
+                FOREIGN KEY (unit_id) REFERENCES units (id)
+            );
+            CREATE INDEX IF NOT EXISTS idx_delivery_challans_company_date
+                ON delivery_challans (company_id, challan_date);
+            CREATE INDEX IF NOT EXISTS idx_delivery_challans_invoice
+                ON delivery_challans (invoice_id);
+            ",
+        ),
+        down: Step::Sql(
+            "
+            DROP TABLE IF EXISTS delivery_challan_lines;
+            DROP TABLE IF EXISTS delivery_challans;
+            DELETE FROM number_sequences WHERE document_type = 'delivery_challan';
+            DELETE FROM number_counters WHERE document_type = 'delivery_challan';
+            ",
+        ),
+    },
+    Migration {
+        version: 46,
+        name: "stock_adjustments",
+        up: Step::Sql(
+            "
+            CREATE TABLE IF NOT EXISTS stock_adjustments (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                company_id INTEGER NOT NULL,
+                item_id INTEGER NOT NULL,
+                adjustment_date TEXT NOT NULL,
+                quantity REAL NOT NULL,
+                reason TEXT NOT NULL,
+                created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+                FOREIGN KEY (company_id) REFERENCES companies (id),
+                FOREIGN KEY (item_id) REFERENCES items (id)
+            );
+            CREATE INDEX IF NOT EXISTS idx_stock_adjustments_item
+                ON stock_adjustments (company_id, item_id, adjustment_date);
+            CREATE INDEX IF NOT EXISTS idx_invoice_lines_item ON invoice_lines (item_id);
+            ",
+        ),
+        down: Step::Sql(
+            "
+            DROP INDEX IF EXISTS idx_invoice_lines_item;
+            DROP TABLE IF EXISTS stock_adjustments;
+            DELETE FROM company_settings WHERE key = 'block_negative_stock';
+            ",
+        ),
+    },
+    Migration {
+        version: 47,
+        name: "stock_valuation",
+        up: Step::Sql(
+            "
+            ALTER TABLE stock_adjustments ADD COLUMN unit_cost REAL;
+            ",
+        ),
+        down: Step::Sql(
+            "
+            ALTER TABLE stock_adjustments DROP COLUMN unit_cost;
+            DELETE FROM company_settings WHERE key = 'stock_valuation_method';
+            ",
+        ),
+    },
+    Migration {
+        version: 48,
+        name: "batch_serial_tracking",
+        up: Step::Sql(
+            "
+            ALTER TABLE items ADD COLUMN tracking TEXT NOT NULL DEFAULT 'none'
+                CHECK(tracking IN ('none', 'batch', 'serial'));
+            CREATE TABLE IF NOT EXISTS batches (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                company_id INTEGER NOT NULL,
+                item_id INTEGER NOT NULL,
+                batch_number TEXT NOT NULL,
+                manufacturing_date TEXT,
+                expiry_date TEXT,
+                created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+                updated_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+                UNIQUE (item_id, batch_number),
+                FOREIGN KEY (company_id) REFERENCES companies (id),
+                FOREIGN KEY (item_id) REFERENCES items (id)
+            );
+            CREATE INDEX IF NOT EXISTS idx_batches_company_expiry
+                ON batches (company_id, expiry_date);
+            ALTER TABLE invoice_lines ADD COLUMN batch_id INTEGER;
+            ALTER TABLE invoice_lines ADD COLUMN serial_numbers TEXT;
+            CREATE INDEX IF NOT EXISTS idx_invoice_lines_batch ON invoice_lines (batch_id);
+            ",
+        ),
+        down: Step::Sql(
+            "
+            DROP INDEX IF EXISTS idx_invoice_lines_batch;
+            ALTER TABLE invoice_lines DROP COLUMN serial_numbers;
+            ALTER TABLE invoice_lines DROP COLUMN batch_id;
+            DROP TABLE IF EXISTS batches;
+            ALTER TABLE items DROP COLUMN tracking;
+            ",
+        ),
+    },
+    Migration {
+        version: 49,
+        name: "invoice_template_barcode",
+        up: Step::Sql(
+            "
+            ALTER TABLE invoice_templates ADD COLUMN show_barcode INTEGER NOT NULL DEFAULT 0;
+            ",
+        ),
+        down: Step::Sql(
+            "
+            ALTER TABLE invoice_templates DROP COLUMN show_barcode;
+            ",
+        ),
+    },
+    Migration {
+        version: 50,
+        name: "webhooks",
+        up: Step::Sql(
+            "
+            CREATE TABLE IF NOT EXISTS webhooks (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                company_id INTEGER NOT NULL,
+                name TEXT NOT NULL,
+                url TEXT NOT NULL,
+                secret TEXT NOT NULL,
+                events TEXT NOT NULL DEFAULT '[]',
+                enabled INTEGER NOT NULL DEFAULT 1,
+                created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+                updated_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+                UNIQUE (company_id, name),
+                FOREIGN KEY (company_id) REFERENCES companies (id)
+            );
+            CREATE TABLE IF NOT EXISTS webhook_deliveries (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                webhook_id INTEGER NOT NULL,
+                event TEXT NOT NULL,
+                payload TEXT NOT NULL,
+                status TEXT NOT NULL DEFAULT 'pending'
+                    CHECK(status IN ('pending', 'delivered', 'failed')),
+                attempts INTEGER NOT NULL DEFAULT 0,
+                next_attempt_at DATETIME,
+                last_attempt_at DATETIME,
+                response_status INTEGER,
+                error TEXT,
+                delivered_at DATETIME,
+                created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+                FOREIGN KEY (webhook_id) REFERENCES webhooks (id) ON DELETE CASCADE
+            );
+            CREATE INDEX IF NOT EXISTS idx_webhook_deliveries_due
+                ON webhook_deliveries (status, next_attempt_at);
+            CREATE INDEX IF NOT EXISTS idx_webhook_deliveries_webhook
+                ON webhook_deliveries (webhook_id);
+            ",
+        ),
+        down: Step::Sql(
+            "
+            DROP INDEX IF EXISTS idx_webhook_deliveries_webhook;
+            DROP INDEX IF EXISTS idx_webhook_deliveries_due;
+            DROP TABLE IF EXISTS webhook_deliveries;
+            DROP TABLE IF EXISTS webhooks;
+            ",
+        ),
+    },
+    Migration {
+        version: 51,
+        name: "sync_change_log",
+        up: Step::Sql(
+            "
+            CREATE TABLE IF NOT EXISTS sync_state (
+                id INTEGER PRIMARY KEY CHECK (id = 1),
+                device_id TEXT NOT NULL,
+                clock INTEGER NOT NULL DEFAULT 0,
+                applying INTEGER NOT NULL DEFAULT 0
+            );
+            INSERT OR IGNORE INTO sync_state (id, device_id)
+                VALUES (1, lower(hex(randomblob(16))));
+            CREATE TABLE IF NOT EXISTS sync_changes (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                table_name TEXT NOT NULL,
+                row_id INTEGER NOT NULL,
+                operation TEXT NOT NULL CHECK(operation IN ('upsert', 'delete')),
+                row_data TEXT,
+                lamport INTEGER NOT NULL,
+                device_id TEXT NOT NULL,
+                created_at DATETIME DEFAULT CURRENT_TIMESTAMP
+            );
+            CREATE INDEX IF NOT EXISTS idx_sync_changes_row
+                ON sync_changes (table_name, row_id, device_id, lamport);
+            CREATE INDEX IF NOT EXISTS idx_sync_changes_device
+                ON sync_changes (device_id, lamport);
+            CREATE TABLE IF NOT EXISTS sync_peers (
+                device_id TEXT PRIMARY KEY,
+                last_lamport INTEGER NOT NULL DEFAULT 0,
+                local_clock INTEGER NOT NULL DEFAULT 0,
+                last_imported_at DATETIME
+            );
+            CREATE TABLE IF NOT EXISTS sync_conflicts (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                table_name TEXT NOT NULL,
+                row_id INTEGER NOT NULL,
+                remote_device_id TEXT NOT NULL,
+                remote_lamport INTEGER NOT NULL,
+                remote_operation TEXT NOT NULL,
+                remote_data TEXT,
+                local_data TEXT,
+                reason TEXT NOT NULL,
+                status TEXT NOT NULL DEFAULT 'open' CHECK(status IN ('open', 'resolved')),
+                resolution TEXT,
+                created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+                resolved_at DATETIME
+            );
+            ",
+        ),
+        down: Step::Sql(
+            "
+            DROP TABLE IF EXISTS sync_conflicts;
+            DROP TABLE IF EXISTS sync_peers;
+            DROP INDEX IF EXISTS idx_sync_changes_device;
+            DROP INDEX IF EXISTS idx_sync_changes_row;
+            DROP TABLE IF EXISTS sync_changes;
+            DROP TABLE IF EXISTS sync_state;
+            ",
+        ),
+    },
+    Migration {
+        version: 52,
+        name: "job_queue",
+        up: Step::Sql(
+            "
+            CREATE TABLE IF NOT EXISTS jobs (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                company_id INTEGER NOT NULL,
+                kind TEXT NOT NULL,
+                request TEXT NOT NULL,
+                status TEXT NOT NULL DEFAULT 'queued'
+                    CHECK(status IN ('queued', 'running', 'completed', 'failed', 'cancelled')),
+                progress REAL NOT NULL DEFAULT 0,
+                message TEXT,
+                result TEXT,
+                error TEXT,
+                created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+                started_at DATETIME,
+                finished_at DATETIME,
+                FOREIGN KEY (company_id) REFERENCES companies (id) ON DELETE CASCADE
+            );
+            CREATE INDEX IF NOT EXISTS idx_jobs_status ON jobs (status, id);
+            CREATE INDEX IF NOT EXISTS idx_jobs_company ON jobs (company_id, id);
+            ",
+        ),
+        down: Step::Sql(
+            "
+            DROP INDEX IF EXISTS idx_jobs_company;
+            DROP INDEX IF EXISTS idx_jobs_status;
+            DROP TABLE IF EXISTS jobs;
+            ",
+        ),
+    },
+    Migration {
+        version: 53,
+        name: "import_reports",
+        up: Step::Sql(
+            "
+            CREATE TABLE IF NOT EXISTS import_reports (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                company_id INTEGER NOT NULL,
+                invoice_no TEXT NOT NULL,
+                cust_cde TEXT NOT NULL,
+                cust_name TEXT NOT NULL,
+                IO_DATE TEXT,
+                Invno TEXT,
+                prod_cde TEXT,
+                prod_cust_no TEXT,
+                prod_name_ko TEXT,
+                tariff_code TEXT,
+                io_qty REAL,
+                rate_pre_unit REAL,
+                Amortisation_cost REAL,
+                supp_mat_cost REAL,
+                ASSESSABLE_VALUE REAL,
+                supplier_mat_value REAL,
+                Amort_Value REAL,
+                ED_Value REAL,
+                ADDL_DUTY REAL,
+                EDU_CESS REAL,
+                SH_EDT_CESS REAL,
+                Total REAL,
+                VAT_CST REAL,
+                invoice_Total REAL,
+                Grand_total REAL,
+                total_basic_value REAL,
+                total_ed_value REAL,
+                Total_VAT REAL,
+                Total_Inv_Value REAL,
+                ST_VAT REAL,
+                CGST_RATE REAL,
+                CGST_AMT REAL,
+                SGST_RATE REAL,
+                SGST_AMT REAL,
+                IGST_RATE REAL,
+                IGST_AMT REAL,
+                TCS_amt REAL,
+                CGST_TOTAL REAL,
+                SGST_TOTAL REAL,
+                IGST_TOTAL REAL,
+                Total_Amorization REAL,
+                Total_TCS REAL,
+                tally_customer_id INTEGER,
+                category_id INTEGER,
+                created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+                FOREIGN KEY (company_id) REFERENCES companies (id),
+                FOREIGN KEY (tally_customer_id) REFERENCES customers (id),
+                FOREIGN KEY (category_id) REFERENCES categories (id)
+            );
+            CREATE INDEX IF NOT EXISTS idx_import_reports_company ON import_reports (company_id);
+            ",
+        ),
+        down: Step::Sql(
+            "
+            DROP INDEX IF EXISTS idx_import_reports_company;
+            DROP TABLE IF EXISTS import_reports;
+            ",
+        ),
+    },
+    Migration {
+        version: 54,
+        name: "active_company_setting",
+        // The webview used to keep its own selected_company_id; the backend's setting replaces it
+        up: Step::Sql(
+            "
+            INSERT OR IGNORE INTO app_settings (key, value)
+                SELECT 'active_company_id', value FROM app_settings WHERE key = 'selected_company_id';
+            DELETE FROM app_settings WHERE key = 'selected_company_id';
+            ",
+        ),
+        // The old key is no longer read, nothing to restore
+        down: Step::Sql(""),
+    },
+    Migration {
+        version: 55,
+        name: "sync_row_uuids",
+        up: Step::Rust(sync::add_row_uuids),
+        down: Step::Rust(sync::remove_row_uuids),
+    },
+    Migration {
+        version: 56,
+        name: "invoice_line_cess",
+        // The header cess becomes the sum of the lines; under reverse charge it moves to the
+        // recipient like the GST
+        up: Step::Sql(
+            "
+            ALTER TABLE invoice_lines ADD COLUMN cess_rate REAL NOT NULL DEFAULT 0;
+            ALTER TABLE invoice_lines ADD COLUMN cess_amount REAL NOT NULL DEFAULT 0;
+            ALTER TABLE invoices ADD COLUMN rcm_cess_amount REAL NOT NULL DEFAULT 0;
+            ",
+        ),
+        down: Step::Sql(
+            "
+            ALTER TABLE invoices DROP COLUMN rcm_cess_amount;
+            ALTER TABLE invoice_lines DROP COLUMN cess_amount;
+            ALTER TABLE invoice_lines DROP COLUMN cess_rate;
+            ",
+        ),
+    },
+];
+
+fn create_state_master(conn: &Connection) -> Result<(), String> {
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS states (
+            code TEXT PRIMARY KEY,
+            name TEXT NOT NULL,
+            is_union_territory INTEGER NOT NULL DEFAULT 0
+        )",
+    )
+    .map_err(|e| e.to_string())?;
+    states::seed_states(conn)
+}
+
+fn create_hsn_master(conn: &Connection) -> Result<(), String> {
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS hsn_codes (
+            code TEXT PRIMARY KEY,
+            description TEXT NOT NULL,
+            gst_rate REAL,
+            is_service INTEGER NOT NULL DEFAULT 0
+        )",
+    )
+    .map_err(|e| e.to_string())?;
+    hsn::seed_hsn_codes(conn)
+}
+
+// Databases created by older frontend builds may lack these columns
+fn backfill_normalized_names(conn: &Connection) -> Result<(), String> {
+    for (column, definition) in [
+        ("normalized_name", "TEXT"),
+        ("created_from_import_id", "TEXT"),
+    ] {
+        if !column_exists(conn, "customers", column)? {
+            conn.execute_batch(&format!(
+                "ALTER TABLE customers ADD COLUMN {} {}",
+                column, definition
+            ))
+            .map_err(|e| e.to_string())?;
+        }
+    }
+
+    let mut stmt = conn
+        .prepare(
+            "SELECT id, report_customer FROM customers
+             WHERE normalized_name IS NULL OR normalized_name = ''",
+        )
+        .map_err(|e| e.to_string())?;
+    let rows = stmt
+        .query_map([], |row| Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?)))
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+
+    for (id, report_customer) in rows {
+        conn.execute(
+            "UPDATE customers SET normalized_name = ?1 WHERE id = ?2",
+            params![normalize_customer_name(&report_customer), id],
+        )
+        .map_err(|e| e.to_string())?;
+    }
+
+    Ok(())
+}
+
+pub fn column_exists(conn: &Connection, table: &str, column: &str) -> Result<bool, String> {
+    let count: i64 = conn
+        .query_row(
+            "SELECT COUNT(*) FROM pragma_table_info(?1) WHERE name = ?2",
+            params![table, column],
+            |row| row.get(0),
+        )
+        .map_err(|e| e.to_string())?;
+    Ok(count > 0)
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct AppliedMigration {
+    pub version: i64,
+    pub name: String,
+    pub applied_at: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PendingMigration {
+    pub version: i64,
+    pub name: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SchemaStatus {
+    pub current_version: i64,
+    pub latest_version: i64,
+    pub applied: Vec<AppliedMigration>,
+    pub pending: Vec<PendingMigration>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Direction {
+    Up,
+    Down,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct MigrationStepReport {
+    pub version: i64,
+    pub name: String,
+    pub direction: Direction,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct MigrationReport {
+    pub from_version: i64,
+    pub to_version: i64,
+    pub dry_run: bool,
+    pub steps: Vec<MigrationStepReport>,
+}
+
+pub fn latest_version() -> i64 {
+    MIGRATIONS.last().map(|m| m.version).unwrap_or(0)
+}
+
+fn ensure_version_table(conn: &Connection) -> Result<(), String> {
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS schema_version (
+            version INTEGER PRIMARY KEY,
+            name TEXT NOT NULL,
+            applied_at DATETIME DEFAULT CURRENT_TIMESTAMP
+        )",
+    )
+    .map_err(|e| e.to_string())
+}
+
+pub fn current_version(conn: &Connection) -> Result<i64, String> {
+    ensure_version_table(conn)?;
+    conn.query_row(
+        "SELECT COALESCE(MAX(version), 0) FROM schema_version",
+        [],
+        |row| row.get(0),
+    )
+    .map_err(|e| e.to_string())
+}
+
+fn applied_migrations(conn: &Connection) -> Result<Vec<AppliedMigration>, String> {
+    ensure_version_table(conn)?;
+    let mut stmt = conn
+        .prepare("SELECT version, name, applied_at FROM schema_version ORDER BY version")
+        .map_err(|e| e.to_string())?;
+    let applied = stmt
+        .query_map([], |row| {
+            Ok(AppliedMigration {
+                version: row.get(0)?,
+                name: row.get(1)?,
+                applied_at: row.get(2)?,
+            })
+        })
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+    Ok(applied)
+}
+
+fn run_step(conn: &Connection, step: &Step) -> Result<(), String> {
+    match step {
+        Step::Sql(sql) => conn.execute_batch(sql).map_err(|e| e.to_string()),
+        Step::Rust(f) => f(conn),
+    }
+}
+
+// Moves the schema to `target` inside one transaction. A dry run executes every step and then rolls back,
+// so broken SQL is still reported without touching the database.
+pub fn migrate_to(
+    conn: &mut Connection,
+    target: i64,
+    dry_run: bool,
+) -> Result<MigrationReport, AppError> {
+    if target < 0 || target > latest_version() {
+        return Err(AppError::validation(
+            "target_version",
+            format!("Target version must be between 0 and {}", latest_version()),
+        ));
+    }
+
+    let from_version = current_version(conn)?;
+    let tx = conn.transaction()?;
+    // Rebuilt below from the schema the steps leave behind
+    sync::drop_triggers(&tx)?;
+    let mut steps = Vec::new();
+
+    if target >= from_version {
+        for migration in MIGRATIONS
+            .iter()
+            .filter(|m| m.version > from_version && m.version <= target)
+        {
+            run_step(&tx, &migration.up).map_err(|e| {
+                format!(
+                    "Migration {} ({}) failed: {}",
+                    migration.version, migration.name, e
+                )
+            })?;
+            tx.execute(
+                "INSERT INTO schema_version (version, name) VALUES (?1, ?2)",
+                params![migration.version, migration.name],
+            )?;
+            steps.push(MigrationStepReport {
+                version: migration.version,
+                name: migration.name.to_string(),
+                direction: Direction::Up,
+            });
+        }
+    } else {
+        for migration in MIGRATIONS
+            .iter()
+            .rev()
+            .filter(|m| m.version <= from_version && m.version > target)
+        {
+            run_step(&tx, &migration.down).map_err(|e| {
+                format!("Rollback of {} ({}) failed: {}", migration.version, migration.name, e)
+            })?;
+            tx.execute(
+                "DELETE FROM schema_version WHERE version = ?1",
+                params![migration.version],
+            )?;
+            steps.push(MigrationStepReport {
+                version: migration.version,
+                name: migration.name.to_string(),
+                direction: Direction::Down,
+            });
+        }
+    }
+    sync::install_triggers(&tx)?;
+
+    if dry_run {
+        tx.rollback()?;
+    } else {
+        tx.commit()?;
+    }
+
+    Ok(MigrationReport {
+        from_version,
+        to_version: if dry_run { from_version } else { target },
+        dry_run,
+        steps,
+    })
+}
+
+pub fn run_pending(conn: &mut Connection) -> Result<MigrationReport, String> {
+    migrate_to(conn, latest_version(), false).map_err(String::from)
+}
+
+fn schema_status(conn: &Connection) -> Result<SchemaStatus, String> {
+    let current_version = current_version(conn)?;
+    let applied = applied_migrations(conn)?;
+    let pending = MIGRATIONS
+        .iter()
+        .filter(|m| m.version > current_version)
+        .map(|m| PendingMigration {
+            version: m.version,
+            name: m.name.to_string(),
+        })
+        .collect();
+
+    Ok(SchemaStatus {
+        current_version,
+        latest_version: latest_version(),
+        applied,
+        pending,
+    })
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn initialize_database(pool: State<'_, DbPool>) -> Result<SchemaStatus, AppError> {
+    let mut conn = db::get_conn(&pool)?;
+    run_pending(&mut conn)?;
+    recycle_bin::purge_expired(&conn)?;
+    Ok(schema_status(&conn)?)
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn get_schema_status(pool: State<'_, DbPool>) -> Result<SchemaStatus, AppError> {
+    let conn = db::get_conn(&pool)?;
+    Ok(schema_status(&conn)?)
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn migrate_schema(
+    pool: State<'_, DbPool>,
+    target_version: i64,
+    dry_run: bool,
+) -> Result<MigrationReport, AppError> {
+    let mut conn = db::get_conn(&pool)?;
+    migrate_to(&mut conn, target_version, dry_run)
+}
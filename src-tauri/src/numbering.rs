@@ -0,0 +1,395 @@
+use chrono::{Local, NaiveDate};
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use tauri::State;
+
+use crate::companies;
+use crate::db::{self, DbPool};
+use crate::error::AppError;
+use crate::financial_years::{fy_label, fy_start_year};
+use crate::invoices::INVOICE_DATE_FORMAT;
+
+// Replaced with the financial year label, e.g. "2024-25"
+const FY_TOKEN: &str = "{FY}";
+// CGST rule 46 limits document numbers to 16 characters
+const MAX_NUMBER_LENGTH: usize = 16;
+// Counter key for sequences that never reset
+const CONTINUOUS_PERIOD: &str = "all";
+// Gives up rather than spinning if a long run of numbers was entered by hand
+const MAX_ALLOCATION_ATTEMPTS: usize = 1000;
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum DocumentType {
+    Invoice,
+    CreditNote,
+    DebitNote,
+    Quotation,
+    SalesOrder,
+    DeliveryChallan,
+}
+
+impl DocumentType {
+    pub const ALL: [DocumentType; 6] = [
+        DocumentType::Invoice,
+        DocumentType::CreditNote,
+        DocumentType::DebitNote,
+        DocumentType::Quotation,
+        DocumentType::SalesOrder,
+        DocumentType::DeliveryChallan,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DocumentType::Invoice => "invoice",
+            DocumentType::CreditNote => "credit_note",
+            DocumentType::DebitNote => "debit_note",
+            DocumentType::Quotation => "quotation",
+            DocumentType::SalesOrder => "sales_order",
+            DocumentType::DeliveryChallan => "delivery_challan",
+        }
+    }
+
+    fn default_prefix(&self) -> &'static str {
+        match self {
+            DocumentType::Invoice => "INV/{FY}/",
+            DocumentType::CreditNote => "CN/{FY}/",
+            DocumentType::DebitNote => "DN/{FY}/",
+            DocumentType::Quotation => "QT/{FY}/",
+            DocumentType::SalesOrder => "SO/{FY}/",
+            DocumentType::DeliveryChallan => "DC/{FY}/",
+        }
+    }
+}
+
+// Number sequence data model
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct NumberSequence {
+    pub company_id: i64,
+    pub document_type: DocumentType,
+    pub prefix: String,
+    pub padding: u32,
+    pub reset_each_year: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SaveNumberSequence {
+    pub prefix: String,
+    pub padding: u32,
+    pub reset_each_year: bool,
+    // Continues the current year's series from this number, e.g. when moving from another system
+    pub start_number: Option<i64>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct NumberSequenceStatus {
+    #[serde(flatten)]
+    pub sequence: NumberSequence,
+    pub next_number: String,
+}
+
+impl NumberSequence {
+    fn default_for(company_id: i64, document_type: DocumentType) -> Self {
+        NumberSequence {
+            company_id,
+            document_type,
+            prefix: document_type.default_prefix().to_string(),
+            padding: 4,
+            reset_each_year: true,
+        }
+    }
+
+    fn period_key(&self, date: NaiveDate) -> String {
+        if self.reset_each_year {
+            fy_label(fy_start_year(date))
+        } else {
+            CONTINUOUS_PERIOD.to_string()
+        }
+    }
+
+    pub fn format(&self, date: NaiveDate, number: i64) -> String {
+        let prefix = self
+            .prefix
+            .replace(FY_TOKEN, &fy_label(fy_start_year(date)));
+        format!(
+            "{}{:0width$}",
+            prefix,
+            number,
+            width = self.padding as usize
+        )
+    }
+}
+
+pub fn load_sequence(
+    conn: &Connection,
+    company_id: i64,
+    document_type: DocumentType,
+) -> Result<NumberSequence, AppError> {
+    let stored = conn
+        .query_row(
+            "SELECT prefix, padding, reset_each_year FROM number_sequences
+             WHERE company_id = ?1 AND document_type = ?2",
+            params![company_id, document_type.as_str()],
+            |row| {
+                Ok(NumberSequence {
+                    company_id,
+                    document_type,
+                    prefix: row.get(0)?,
+                    padding: row.get(1)?,
+                    reset_each_year: row.get(2)?,
+                })
+            },
+        )
+        .optional()?;
+    Ok(stored.unwrap_or_else(|| NumberSequence::default_for(company_id, document_type)))
+}
+
+fn last_number(
+    conn: &Connection,
+    sequence: &NumberSequence,
+    date: NaiveDate,
+) -> Result<i64, AppError> {
+    conn.query_row(
+        "SELECT last_number FROM number_counters
+         WHERE company_id = ?1 AND document_type = ?2 AND period_key = ?3",
+        params![
+            sequence.company_id,
+            sequence.document_type.as_str(),
+            sequence.period_key(date)
+        ],
+        |row| row.get(0),
+    )
+    .optional()
+    .map(|n| n.unwrap_or(0))
+    .map_err(AppError::from)
+}
+
+fn number_taken(
+    conn: &Connection,
+    company_id: i64,
+    document_type: DocumentType,
+    number: &str,
+) -> Result<bool, AppError> {
+    let sql = match document_type {
+        DocumentType::Invoice => {
+            "SELECT EXISTS(SELECT 1 FROM invoices WHERE company_id = ?1 AND invoice_number = ?2)"
+        }
+        // Both note types share one number space per company
+        DocumentType::CreditNote | DocumentType::DebitNote => {
+            "SELECT EXISTS(SELECT 1 FROM credit_debit_notes WHERE company_id = ?1 AND note_number = ?2)"
+        }
+        DocumentType::Quotation => {
+            "SELECT EXISTS(SELECT 1 FROM quotations WHERE company_id = ?1 AND quotation_number = ?2)"
+        }
+        DocumentType::SalesOrder => {
+            "SELECT EXISTS(SELECT 1 FROM sales_orders WHERE company_id = ?1 AND order_number = ?2)"
+        }
+        DocumentType::DeliveryChallan => {
+            "SELECT EXISTS(SELECT 1 FROM delivery_challans WHERE company_id = ?1 AND challan_number = ?2)"
+        }
+    };
+    conn.query_row(sql, params![company_id, number], |row| row.get(0))
+        .map_err(AppError::from)
+}
+
+// Must run inside the transaction that inserts the document: the counter upsert takes
+// SQLite's write lock, so concurrent creates serialise and can never share a number
+pub fn allocate_number(
+    conn: &Connection,
+    company_id: i64,
+    document_type: DocumentType,
+    date: &str,
+) -> Result<String, AppError> {
+    let date = NaiveDate::parse_from_str(date.trim(), INVOICE_DATE_FORMAT)
+        .map_err(|_| AppError::invalid("A valid document date is required to allocate a number"))?;
+    let sequence = load_sequence(conn, company_id, document_type)?;
+    let period_key = sequence.period_key(date);
+
+    for _ in 0..MAX_ALLOCATION_ATTEMPTS {
+        let number: i64 = conn.query_row(
+            "INSERT INTO number_counters (company_id, document_type, period_key, last_number)
+                 VALUES (?1, ?2, ?3, 1)
+                 ON CONFLICT(company_id, document_type, period_key)
+                 DO UPDATE SET last_number = last_number + 1
+                 RETURNING last_number",
+            params![company_id, document_type.as_str(), period_key],
+            |row| row.get(0),
+        )?;
+        let formatted = sequence.format(date, number);
+        // Skip numbers already used by documents entered or imported by hand
+        if !number_taken(conn, company_id, document_type, &formatted)? {
+            return Ok(formatted);
+        }
+    }
+    Err(AppError::invalid(format!(
+        "Could not find a free {} number after {} attempts; check the sequence settings",
+        document_type.as_str().replace('_', " "),
+        MAX_ALLOCATION_ATTEMPTS
+    )))
+}
+
+fn validate_sequence(sequence: &NumberSequence) -> Result<(), AppError> {
+    if !(1..=8).contains(&sequence.padding) {
+        return Err(AppError::validation(
+            "padding",
+            "Padding must be between 1 and 8 digits",
+        ));
+    }
+    let literal = sequence.prefix.replace(FY_TOKEN, "");
+    if literal.contains(['{', '}']) {
+        return Err(AppError::validation(
+            "prefix",
+            format!("The only supported placeholder is {}", FY_TOKEN),
+        ));
+    }
+    if let Some(c) = literal
+        .chars()
+        .find(|c| !(c.is_ascii_alphanumeric() || *c == '/' || *c == '-'))
+    {
+        return Err(AppError::validation(
+            "prefix",
+            format!(
+                "Prefix may only contain letters, digits, '/' and '-' (found '{}')",
+                c
+            ),
+        ));
+    }
+
+    let widest = 10_i64.pow(sequence.padding) - 1;
+    let sample = sequence.format(Local::now().date_naive(), widest);
+    if sample.len() > MAX_NUMBER_LENGTH {
+        return Err(AppError::validation(
+            "prefix",
+            format!(
+                "Numbers such as {} exceed the GST limit of {} characters",
+                sample, MAX_NUMBER_LENGTH
+            ),
+        ));
+    }
+    Ok(())
+}
+
+fn sequence_status(
+    conn: &Connection,
+    sequence: NumberSequence,
+    date: NaiveDate,
+) -> Result<NumberSequenceStatus, String> {
+    let next = last_number(conn, &sequence, date)? + 1;
+    Ok(NumberSequenceStatus {
+        next_number: sequence.format(date, next),
+        sequence,
+    })
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn list_number_sequences(
+    pool: State<'_, DbPool>,
+    company_id: i64,
+) -> Result<Vec<NumberSequenceStatus>, AppError> {
+    let conn = db::get_conn(&pool)?;
+    let today = Local::now().date_naive();
+    let statuses = DocumentType::ALL
+        .iter()
+        .map(|document_type| {
+            let sequence = load_sequence(&conn, company_id, *document_type)?;
+            sequence_status(&conn, sequence, today)
+        })
+        .collect::<Result<Vec<_>, String>>()?;
+    Ok(statuses)
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn save_number_sequence(
+    pool: State<'_, DbPool>,
+    company_id: i64,
+    document_type: DocumentType,
+    sequence: SaveNumberSequence,
+) -> Result<NumberSequenceStatus, AppError> {
+    let updated = NumberSequence {
+        company_id,
+        document_type,
+        prefix: sequence.prefix.trim().to_string(),
+        padding: sequence.padding,
+        reset_each_year: sequence.reset_each_year,
+    };
+    validate_sequence(&updated)?;
+
+    let mut conn = db::get_conn(&pool)?;
+    let tx = conn.transaction()?;
+    companies::get_company_by_id(&tx, company_id)?
+        .ok_or_else(|| AppError::not_found("Company not found"))?;
+    tx.execute(
+        "INSERT INTO number_sequences (company_id, document_type, prefix, padding, reset_each_year)
+         VALUES (?1, ?2, ?3, ?4, ?5)
+         ON CONFLICT(company_id, document_type) DO UPDATE SET
+            prefix = excluded.prefix,
+            padding = excluded.padding,
+            reset_each_year = excluded.reset_each_year,
+            updated_at = CURRENT_TIMESTAMP",
+        params![
+            company_id,
+            document_type.as_str(),
+            updated.prefix,
+            updated.padding,
+            updated.reset_each_year
+        ],
+    )?;
+
+    let today = Local::now().date_naive();
+    if let Some(start_number) = sequence.start_number {
+        if start_number < 1 {
+            return Err(AppError::validation(
+                "start_number",
+                "Start number must be at least 1",
+            ));
+        }
+        let issued = last_number(&tx, &updated, today)?;
+        if start_number <= issued {
+            return Err(AppError::validation(
+                "start_number",
+                format!(
+                    "Numbers up to {} have already been issued in this series",
+                    updated.format(today, issued)
+                ),
+            ));
+        }
+        tx.execute(
+            "INSERT INTO number_counters (company_id, document_type, period_key, last_number)
+             VALUES (?1, ?2, ?3, ?4)
+             ON CONFLICT(company_id, document_type, period_key)
+             DO UPDATE SET last_number = excluded.last_number",
+            params![
+                company_id,
+                document_type.as_str(),
+                updated.period_key(today),
+                start_number - 1
+            ],
+        )?;
+    }
+
+    let status = sequence_status(&tx, updated, today)?;
+    tx.commit()?;
+    Ok(status)
+}
+
+// Shows the number the next document would get without consuming it
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn preview_next_number(
+    pool: State<'_, DbPool>,
+    company_id: i64,
+    document_type: DocumentType,
+    date: Option<String>,
+) -> Result<String, AppError> {
+    let date = match date.as_deref().map(str::trim).filter(|d| !d.is_empty()) {
+        Some(date) => NaiveDate::parse_from_str(date, INVOICE_DATE_FORMAT).map_err(|_| {
+            AppError::validation("date", "Date must be a valid date in YYYY-MM-DD format")
+        })?,
+        None => Local::now().date_naive(),
+    };
+    let conn = db::get_conn(&pool)?;
+    let sequence = load_sequence(&conn, company_id, document_type)?;
+    Ok(sequence_status(&conn, sequence, date)?.next_number)
+}
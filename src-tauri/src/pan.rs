@@ -0,0 +1,191 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+use tauri::State;
+
+use crate::companies::{self, Company};
+use crate::customers::{self, normalize_customer_name};
+use crate::db::{self, DbPool};
+use crate::error::AppError;
+use crate::gstin;
+
+// The 4th character of a PAN identifies the kind of holder
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum PanHolder {
+    Individual,
+    Company,
+    Huf,
+    Firm,
+    AssociationOfPersons,
+    Trust,
+    BodyOfIndividuals,
+    LocalAuthority,
+    ArtificialJuridicalPerson,
+    Government,
+}
+
+impl PanHolder {
+    pub fn from_code(code: char) -> Option<Self> {
+        match code {
+            'P' => Some(PanHolder::Individual),
+            'C' => Some(PanHolder::Company),
+            'H' => Some(PanHolder::Huf),
+            'F' => Some(PanHolder::Firm),
+            'A' => Some(PanHolder::AssociationOfPersons),
+            'T' => Some(PanHolder::Trust),
+            'B' => Some(PanHolder::BodyOfIndividuals),
+            'L' => Some(PanHolder::LocalAuthority),
+            'J' => Some(PanHolder::ArtificialJuridicalPerson),
+            'G' => Some(PanHolder::Government),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PanDetails {
+    pub gstin: String,
+    pub pan: Option<String>,
+    pub valid: bool,
+    pub holder: Option<PanHolder>,
+    pub message: Option<String>,
+}
+
+// GSTINs of one party that carry the same PAN
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PanUsage {
+    pub pan: String,
+    pub ids: Vec<i64>,
+    pub gstins: Vec<String>,
+}
+
+// Registrations that look like one party but carry different PANs
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PanMismatch {
+    pub entity: String,
+    pub name: String,
+    pub pans: Vec<PanUsage>,
+    pub message: String,
+}
+
+// Checks the PAN pattern and its holder-type character
+pub fn validate_pan(pan: &str) -> Result<PanHolder, String> {
+    let pan = pan.trim().to_ascii_uppercase();
+    if !gstin::is_valid_pan(&pan) {
+        return Err("PAN must be 5 letters, 4 digits and 1 letter".to_string());
+    }
+    let code = pan.as_bytes()[3] as char;
+    PanHolder::from_code(code)
+        .ok_or_else(|| format!("'{}' is not a valid PAN holder type (4th character)", code))
+}
+
+// PAN embedded in characters 3 to 12 of a GSTIN
+pub fn extract_pan(value: &str) -> Result<String, String> {
+    let parsed = gstin::parse_gstin(value).map_err(|e| e.message)?;
+    validate_pan(&parsed.pan)?;
+    Ok(parsed.pan)
+}
+
+// Groups (id, gstin) pairs by PAN and reports a mismatch when more than one PAN is used.
+// Blank or invalid GSTINs are left to GSTIN validation and skipped here.
+fn find_mismatch(entity: &str, name: &str, registrations: &[(i64, &str)]) -> Option<PanMismatch> {
+    let mut by_pan: BTreeMap<String, PanUsage> = BTreeMap::new();
+    for &(id, gst_no) in registrations {
+        let Ok(pan) = extract_pan(gst_no) else {
+            continue;
+        };
+        let usage = by_pan.entry(pan.clone()).or_insert_with(|| PanUsage {
+            pan,
+            ids: Vec::new(),
+            gstins: Vec::new(),
+        });
+        usage.ids.push(id);
+        usage.gstins.push(gst_no.trim().to_ascii_uppercase());
+    }
+    if by_pan.len() < 2 {
+        return None;
+    }
+    let pans: Vec<PanUsage> = by_pan.into_values().collect();
+    Some(PanMismatch {
+        entity: entity.to_string(),
+        name: name.to_string(),
+        message: format!(
+            "GSTINs of {} carry {} different PANs: {}",
+            name,
+            pans.len(),
+            pans.iter().map(|usage| usage.pan.as_str()).collect::<Vec<_>>().join(", ")
+        ),
+        pans,
+    })
+}
+
+fn company_mismatch(company: &Company, companies: &[Company]) -> Option<PanMismatch> {
+    let name = company.company_name.trim().to_lowercase();
+    let registrations: Vec<(i64, &str)> = companies
+        .iter()
+        .filter(|other| other.company_name.trim().to_lowercase() == name)
+        .filter_map(|other| Some((other.id?, other.gst_no.as_str())))
+        .collect();
+    find_mismatch("company", &company.company_name, &registrations)
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn get_pan_details(gstin: String) -> Result<PanDetails, AppError> {
+    let normalized = gstin.trim().to_ascii_uppercase();
+    let parsed = gstin::parse_gstin(&normalized);
+    let pan = parsed.as_ref().ok().map(|details| details.pan.clone());
+    let checked = parsed
+        .map_err(|e| e.message)
+        .and_then(|details| validate_pan(&details.pan));
+    Ok(PanDetails {
+        gstin: normalized,
+        valid: checked.is_ok(),
+        holder: checked.as_ref().ok().copied(),
+        message: checked.err(),
+        pan,
+    })
+}
+
+// Customers (grouped by normalized name) and companies (grouped by name) registered in
+// several states should share one PAN; anything else is flagged for review
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn check_pan_consistency(
+    pool: State<'_, DbPool>,
+    company_id: i64,
+) -> Result<Vec<PanMismatch>, AppError> {
+    let conn = db::get_conn(&pool)?;
+    let company = companies::get_company_by_id(&conn, company_id)?
+        .ok_or_else(|| AppError::not_found("Company not found"))?;
+    let all_companies = companies::get_all_companies(&conn)?;
+
+    let mut mismatches = Vec::new();
+    mismatches.extend(company_mismatch(&company, &all_companies));
+
+    let mut groups: BTreeMap<String, (String, Vec<(i64, String)>)> = BTreeMap::new();
+    for customer in customers::get_customers_by_company(&conn, company_id)? {
+        let Some(id) = customer.id else {
+            continue;
+        };
+        let key = customer
+            .normalized_name
+            .clone()
+            .filter(|name| !name.is_empty())
+            .unwrap_or_else(|| normalize_customer_name(&customer.report_customer));
+        groups
+            .entry(key)
+            .or_insert_with(|| (customer.report_customer.clone(), Vec::new()))
+            .1
+            .push((id, customer.gst_no));
+    }
+    for (name, registrations) in groups.values() {
+        let registrations: Vec<(i64, &str)> = registrations
+            .iter()
+            .map(|(id, gst_no)| (*id, gst_no.as_str()))
+            .collect();
+        mismatches.extend(find_mismatch("customer", name, &registrations));
+    }
+    Ok(mismatches)
+}
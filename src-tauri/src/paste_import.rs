@@ -0,0 +1,479 @@
+use chrono::NaiveDate;
+use csv::{ReaderBuilder, Trim};
+use serde::{Deserialize, Serialize};
+
+use crate::error::AppError;
+use crate::gstin;
+use crate::invoices::{round2, INVOICE_DATE_FORMAT};
+use crate::sales_import::DATE_FORMATS;
+use crate::states;
+
+const MAX_PASTED_ROWS: usize = 2000;
+
+// Header spellings recognised per draft field, compared case-insensitively
+const CUSTOMER_HEADERS: &[(&str, &[&str])] = &[
+    (
+        "report_customer",
+        &["name", "customer", "customer name", "report customer", "party", "party name"],
+    ),
+    ("tally_customer", &["tally name", "tally customer", "ledger", "ledger name"]),
+    ("gst_no", &["gstin", "gst no", "gst", "gst number", "gstin/uin"]),
+    ("state_code", &["state", "state code"]),
+    ("address", &["address"]),
+    ("city", &["city", "town"]),
+    ("pincode", &["pincode", "pin", "pin code"]),
+];
+const LINE_HEADERS: &[(&str, &[&str])] = &[
+    ("description", &["description", "item", "item name", "particulars", "product"]),
+    ("hsn_code", &["hsn", "hsn code", "hsn/sac", "sac"]),
+    ("quantity", &["qty", "quantity"]),
+    ("rate", &["rate", "price", "unit price"]),
+    ("discount_percent", &["disc %", "discount %", "discount percent"]),
+    ("taxable_value", &["taxable value", "taxable", "amount", "value"]),
+    ("gst_rate", &["gst %", "gst rate", "tax rate", "tax %"]),
+];
+// Fields filled from headerless columns, in column order, by the kind of value they hold
+const CUSTOMER_TEXT_FIELDS: &[&str] = &["report_customer", "tally_customer", "address", "city"];
+const LINE_AMOUNT_FIELDS: &[&str] = &["quantity", "rate", "taxable_value", "gst_rate"];
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum PasteTarget {
+    Customers,
+    InvoiceLines,
+}
+
+impl PasteTarget {
+    fn headers(&self) -> &'static [(&'static str, &'static [&'static str])] {
+        match self {
+            PasteTarget::Customers => CUSTOMER_HEADERS,
+            PasteTarget::InvoiceLines => LINE_HEADERS,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ColumnKind {
+    Empty,
+    Text,
+    Amount,
+    Date,
+    Gstin,
+}
+
+// A pasted cell read as the kind its column was inferred to hold
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(tag = "kind", content = "value", rename_all = "snake_case")]
+pub enum PastedValue {
+    Empty,
+    Text(String),
+    Amount(f64),
+    // YYYY-MM-DD
+    Date(String),
+    Gstin(String),
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PastedColumn {
+    pub index: usize,
+    pub header: Option<String>,
+    pub kind: ColumnKind,
+    // Draft field the column was mapped to, if any
+    pub field: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Default)]
+pub struct CustomerDraft {
+    pub report_customer: String,
+    pub tally_customer: String,
+    pub gst_no: Option<String>,
+    pub state_code: Option<String>,
+    pub address: Option<String>,
+    pub city: Option<String>,
+    pub pincode: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Default)]
+pub struct InvoiceLineDraft {
+    pub description: String,
+    pub hsn_code: String,
+    pub quantity: f64,
+    pub rate: f64,
+    pub discount_percent: f64,
+    pub taxable_value: f64,
+    pub gst_rate: f64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PastedRow {
+    // 1-based line in the pasted text
+    pub row_number: usize,
+    pub cells: Vec<PastedValue>,
+    pub customer: Option<CustomerDraft>,
+    pub invoice_line: Option<InvoiceLineDraft>,
+    pub errors: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PastedTable {
+    pub target: PasteTarget,
+    pub delimiter: String,
+    pub has_header: bool,
+    pub columns: Vec<PastedColumn>,
+    pub rows: Vec<PastedRow>,
+}
+
+fn parse_amount(raw: &str) -> Option<f64> {
+    let cleaned: String = raw
+        .chars()
+        .filter(|c| !c.is_whitespace() && !matches!(c, ',' | '₹' | '%'))
+        .collect();
+    // Accounting formats show negatives in brackets
+    let (negative, cleaned) = match cleaned.strip_prefix('(').and_then(|c| c.strip_suffix(')')) {
+        Some(inner) => (true, inner.to_string()),
+        None => (false, cleaned),
+    };
+    if cleaned.is_empty() || !cleaned.chars().any(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    let value = cleaned.parse::<f64>().ok()?;
+    Some(if negative { -value } else { value })
+}
+
+fn parse_date(raw: &str) -> Option<NaiveDate> {
+    DATE_FORMATS
+        .iter()
+        .find_map(|format| NaiveDate::parse_from_str(raw, format).ok())
+}
+
+fn matches_kind(raw: &str, kind: ColumnKind) -> bool {
+    match kind {
+        ColumnKind::Empty => raw.is_empty(),
+        ColumnKind::Text => true,
+        ColumnKind::Amount => parse_amount(raw).is_some(),
+        ColumnKind::Date => parse_date(raw).is_some(),
+        ColumnKind::Gstin => gstin::check_gstin(raw).is_ok(),
+    }
+}
+
+// The most specific kind every non-blank cell fits; a GSTIN column tolerates invalid entries
+// as long as most cells are GSTINs, so a typo is reported on its row rather than turning the
+// whole column into text
+fn infer_kind(cells: &[&str]) -> ColumnKind {
+    let filled: Vec<&str> = cells.iter().copied().filter(|c| !c.is_empty()).collect();
+    if filled.is_empty() {
+        return ColumnKind::Empty;
+    }
+    let gstins = filled.iter().filter(|c| matches_kind(c, ColumnKind::Gstin)).count();
+    if gstins * 2 > filled.len() {
+        return ColumnKind::Gstin;
+    }
+    [ColumnKind::Date, ColumnKind::Amount]
+        .into_iter()
+        .find(|kind| filled.iter().all(|c| matches_kind(c, *kind)))
+        .unwrap_or(ColumnKind::Text)
+}
+
+fn typed_value(raw: &str, kind: ColumnKind) -> PastedValue {
+    if raw.is_empty() {
+        return PastedValue::Empty;
+    }
+    match kind {
+        ColumnKind::Amount => parse_amount(raw).map(PastedValue::Amount),
+        ColumnKind::Date => parse_date(raw)
+            .map(|date| PastedValue::Date(date.format(INVOICE_DATE_FORMAT).to_string())),
+        ColumnKind::Gstin => Some(PastedValue::Gstin(raw.to_uppercase())),
+        ColumnKind::Empty | ColumnKind::Text => None,
+    }
+    .unwrap_or_else(|| PastedValue::Text(raw.to_string()))
+}
+
+fn header_field(target: PasteTarget, header: &str) -> Option<&'static str> {
+    let header = header.trim().to_lowercase();
+    target
+        .headers()
+        .iter()
+        .find(|(_, names)| names.contains(&header.as_str()))
+        .map(|(field, _)| *field)
+}
+
+// HSN codes are 4, 6 or 8 digits, which would otherwise read as amounts
+fn is_hsn_column(cells: &[&str]) -> bool {
+    let filled: Vec<&str> = cells.iter().copied().filter(|c| !c.is_empty()).collect();
+    !filled.is_empty()
+        && filled
+            .iter()
+            .all(|c| matches!(c.len(), 4 | 6 | 8) && c.chars().all(|ch| ch.is_ascii_digit()))
+}
+
+fn is_pincode_column(cells: &[&str]) -> bool {
+    let filled: Vec<&str> = cells.iter().copied().filter(|c| !c.is_empty()).collect();
+    !filled.is_empty()
+        && filled
+            .iter()
+            .all(|c| c.len() == 6 && c.chars().all(|ch| ch.is_ascii_digit()))
+}
+
+// Picks a field for each headerless column from the kind of values it holds
+fn guess_fields(target: PasteTarget, columns: &mut [PastedColumn], data: &[Vec<String>]) {
+    let cells_of = |index: usize| -> Vec<&str> {
+        data.iter()
+            .map(|row| row.get(index).map(String::as_str).unwrap_or(""))
+            .collect()
+    };
+    let mut taken: Vec<&str> = columns.iter().filter_map(|c| c.field.as_deref()).collect();
+    let mut guesses = Vec::new();
+    for column in columns.iter().filter(|c| c.field.is_none()) {
+        let cells = cells_of(column.index);
+        let candidates: &[&str] = match (target, column.kind) {
+            (PasteTarget::Customers, ColumnKind::Gstin) => &["gst_no"],
+            (PasteTarget::Customers, ColumnKind::Text) => CUSTOMER_TEXT_FIELDS,
+            (PasteTarget::Customers, ColumnKind::Amount) if is_pincode_column(&cells) => {
+                &["pincode"]
+            }
+            (PasteTarget::InvoiceLines, ColumnKind::Text) => &["description"],
+            (PasteTarget::InvoiceLines, ColumnKind::Amount) if is_hsn_column(&cells) => {
+                &["hsn_code"]
+            }
+            (PasteTarget::InvoiceLines, ColumnKind::Amount) => LINE_AMOUNT_FIELDS,
+            _ => &[],
+        };
+        let field = candidates.iter().copied().find(|field| !taken.contains(field));
+        if let Some(field) = field {
+            taken.push(field);
+        }
+        guesses.push((column.index, field));
+    }
+    for (index, field) in guesses {
+        columns[index].field = field.map(str::to_string);
+    }
+}
+
+fn field_value<'a>(row: &'a [String], columns: &[PastedColumn], field: &str) -> &'a str {
+    columns
+        .iter()
+        .find(|c| c.field.as_deref() == Some(field))
+        .and_then(|c| row.get(c.index))
+        .map(String::as_str)
+        .unwrap_or("")
+}
+
+fn customer_draft(row: &[String], columns: &[PastedColumn]) -> (CustomerDraft, Vec<String>) {
+    let value = |field: &str| field_value(row, columns, field).to_string();
+    let optional = |field: &str| Some(value(field)).filter(|v| !v.is_empty());
+    let mut errors = Vec::new();
+
+    let report_customer = value("report_customer");
+    if report_customer.is_empty() {
+        errors.push("Customer name is required".to_string());
+    }
+    let gst_no = optional("gst_no").map(|g| g.to_uppercase());
+    if let Some(gst_no) = &gst_no {
+        if let Err(e) = gstin::check_gstin(gst_no) {
+            errors.push(format!("GSTIN {}: {}", gst_no, e));
+        }
+    }
+    let mut state_code = optional("state_code");
+    if errors.is_empty() {
+        match states::resolve_state_code(
+            gst_no.as_deref().unwrap_or(""),
+            state_code.as_deref().unwrap_or(""),
+        ) {
+            Ok(code) => state_code = Some(code),
+            // A missing state is filled in on the form; a conflicting one is worth flagging
+            Err(e) if state_code.is_some() => errors.push(e.to_string()),
+            Err(_) => {}
+        }
+    }
+    let tally_customer = optional("tally_customer").unwrap_or_else(|| report_customer.clone());
+
+    let draft = CustomerDraft {
+        report_customer,
+        tally_customer,
+        gst_no,
+        state_code,
+        address: optional("address"),
+        city: optional("city"),
+        pincode: optional("pincode"),
+    };
+    (draft, errors)
+}
+
+fn invoice_line_draft(
+    row: &[String],
+    columns: &[PastedColumn],
+) -> (InvoiceLineDraft, Vec<String>) {
+    let mut errors = Vec::new();
+    let mut amount = |field: &str, label: &str| -> Option<f64> {
+        let raw = field_value(row, columns, field);
+        if raw.is_empty() {
+            return None;
+        }
+        let parsed = parse_amount(raw);
+        if parsed.is_none() {
+            errors.push(format!("{} '{}' is not a number", label, raw));
+        }
+        parsed
+    };
+    let quantity = amount("quantity", "Quantity");
+    let rate = amount("rate", "Rate");
+    let discount_percent = amount("discount_percent", "Discount").unwrap_or(0.0);
+    let taxable_value = amount("taxable_value", "Taxable value");
+    let gst_rate = amount("gst_rate", "GST rate").unwrap_or(0.0);
+
+    let description = field_value(row, columns, "description").to_string();
+    if description.is_empty() {
+        errors.push("Description is required".to_string());
+    }
+    // Whichever of quantity, rate and taxable value is missing is worked out from the others
+    let (quantity, rate, taxable_value) = match (quantity, rate, taxable_value) {
+        (Some(quantity), Some(rate), taxable) => (
+            quantity,
+            rate,
+            taxable.unwrap_or_else(|| round2(quantity * rate * (1.0 - discount_percent / 100.0))),
+        ),
+        (Some(quantity), None, Some(taxable)) if quantity != 0.0 => {
+            (quantity, round2(taxable / quantity), taxable)
+        }
+        (None, rate, Some(taxable)) => (1.0, rate.unwrap_or(taxable), taxable),
+        (quantity, rate, taxable) => {
+            errors.push("Enter a quantity and rate, or a taxable value".to_string());
+            (quantity.unwrap_or(0.0), rate.unwrap_or(0.0), taxable.unwrap_or(0.0))
+        }
+    };
+    if quantity <= 0.0 {
+        errors.push("Quantity must be greater than zero".to_string());
+    }
+
+    let draft = InvoiceLineDraft {
+        description,
+        hsn_code: field_value(row, columns, "hsn_code").to_string(),
+        quantity,
+        rate,
+        discount_percent,
+        taxable_value,
+        gst_rate,
+    };
+    (draft, errors)
+}
+
+// Non-blank rows with the line each one started on
+type PastedRows = Vec<(usize, Vec<String>)>;
+
+fn split_rows(text: &str) -> Result<(char, PastedRows), String> {
+    // Excel copies cells tab-separated; anything else is treated as CSV
+    let delimiter = if text.contains('\t') { '\t' } else { ',' };
+    let mut reader = ReaderBuilder::new()
+        .delimiter(delimiter as u8)
+        .has_headers(false)
+        .flexible(true)
+        .trim(Trim::All)
+        .from_reader(text.as_bytes());
+    let mut rows = Vec::new();
+    for record in reader.records() {
+        let record = record.map_err(|e| format!("Could not read the pasted text: {}", e))?;
+        if record.iter().all(|cell| cell.is_empty()) {
+            continue;
+        }
+        let row_number = record.position().map_or(rows.len() + 1, |p| p.line() as usize);
+        rows.push((row_number, record.iter().map(str::to_string).collect()));
+    }
+    Ok((delimiter, rows))
+}
+
+// Reads text copied from a spreadsheet into draft customers or invoice lines for the bulk entry
+// grids. Column meanings come from a header row when one is recognised, otherwise from what the
+// cells hold. Nothing is saved; rows with errors are returned so they can be fixed in the grid.
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn parse_pasted_table(
+    text: String,
+    target: PasteTarget,
+) -> Result<PastedTable, AppError> {
+    let (delimiter, mut rows) =
+        split_rows(&text).map_err(|message| AppError::validation("text", message))?;
+    if rows.is_empty() {
+        return Err(AppError::validation("text", "Nothing was pasted"));
+    }
+    if rows.len() > MAX_PASTED_ROWS {
+        return Err(AppError::validation(
+            "text",
+            format!("Paste at most {} rows at a time", MAX_PASTED_ROWS),
+        ));
+    }
+    let width = rows.iter().map(|(_, row)| row.len()).max().unwrap_or(0);
+    for (_, row) in rows.iter_mut() {
+        row.resize(width, String::new());
+    }
+
+    let has_header = rows[0].1.iter().any(|cell| header_field(target, cell).is_some());
+    let header = if has_header { Some(rows.remove(0).1) } else { None };
+    let data: Vec<Vec<String>> = rows.iter().map(|(_, row)| row.clone()).collect();
+
+    let mut columns: Vec<PastedColumn> = (0..width)
+        .map(|index| {
+            let cells: Vec<&str> = data.iter().map(|row| row[index].as_str()).collect();
+            let header = header.as_ref().map(|header| header[index].clone());
+            let field = header
+                .as_deref()
+                .and_then(|header| header_field(target, header))
+                .map(str::to_string);
+            PastedColumn {
+                index,
+                header,
+                kind: infer_kind(&cells),
+                field,
+            }
+        })
+        .collect();
+    // Two headers naming the same field: the first one wins
+    for index in 0..columns.len() {
+        let duplicate = columns[..index]
+            .iter()
+            .any(|earlier| earlier.field.is_some() && earlier.field == columns[index].field);
+        if duplicate {
+            columns[index].field = None;
+        }
+    }
+    guess_fields(target, &mut columns, &data);
+
+    let rows = rows
+        .into_iter()
+        .map(|(row_number, row)| {
+            let cells = row
+                .iter()
+                .zip(&columns)
+                .map(|(raw, column)| typed_value(raw, column.kind))
+                .collect();
+            let mut pasted = PastedRow {
+                row_number,
+                cells,
+                customer: None,
+                invoice_line: None,
+                errors: Vec::new(),
+            };
+            match target {
+                PasteTarget::Customers => {
+                    let (draft, errors) = customer_draft(&row, &columns);
+                    pasted.customer = Some(draft);
+                    pasted.errors = errors;
+                }
+                PasteTarget::InvoiceLines => {
+                    let (draft, errors) = invoice_line_draft(&row, &columns);
+                    pasted.invoice_line = Some(draft);
+                    pasted.errors = errors;
+                }
+            }
+            pasted
+        })
+        .collect();
+
+    Ok(PastedTable {
+        target,
+        delimiter: delimiter.to_string(),
+        has_header,
+        columns,
+        rows,
+    })
+}
@@ -0,0 +1,573 @@
+use std::collections::HashMap;
+use std::time::Duration;
+
+use chrono::{Local, NaiveDate};
+use rusqlite::{params, Connection, Row};
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Emitter, Manager, State};
+use tauri_plugin_notification::NotificationExt;
+use tera::{Context, Tera};
+
+use crate::customers;
+use crate::db::{self, DbPool};
+use crate::error::AppError;
+use crate::invoice_templates::describe_tera_error;
+use crate::invoices::INVOICE_DATE_FORMAT;
+use crate::receipts;
+use crate::settings;
+
+const SETTING_DAYS_BEFORE: &str = "reminder_days_before";
+const SETTING_DUE_SOON_TITLE: &str = "reminder_due_soon_title";
+const SETTING_DUE_SOON_BODY: &str = "reminder_due_soon_body";
+const SETTING_OVERDUE_TITLE: &str = "reminder_overdue_title";
+const SETTING_OVERDUE_BODY: &str = "reminder_overdue_body";
+
+const DEFAULT_DAYS_BEFORE: u32 = 3;
+const MAX_DAYS_BEFORE: u32 = 60;
+const MAX_SNOOZE_DAYS: i64 = 365;
+const DEFAULT_FEED_LIMIT: u32 = 100;
+const MAX_FEED_LIMIT: u32 = 1000;
+
+// How often the reminder task looks for invoices that have become due
+const CHECK_INTERVAL: Duration = Duration::from_secs(30 * 60);
+
+pub const EVENT_PAYMENT_REMINDERS: &str = "payment-reminders";
+
+const DUE_SOON_TITLE: &str = "Invoice {{ invoice_number }} is due on {{ due_date }}";
+const DUE_SOON_BODY: &str = "{{ customer_name }} has Rs. {{ outstanding }} outstanding on \
+invoice {{ invoice_number }}, due in {{ days }} day(s).";
+const OVERDUE_TITLE: &str = "Invoice {{ invoice_number }} is overdue";
+const OVERDUE_BODY: &str = "{{ customer_name }} has Rs. {{ outstanding }} outstanding on \
+invoice {{ invoice_number }}, {{ days }} day(s) past the due date of {{ due_date }}.";
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ReminderKind {
+    DueSoon,
+    Overdue,
+}
+
+impl ReminderKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ReminderKind::DueSoon => "due_soon",
+            ReminderKind::Overdue => "overdue",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "due_soon" => Some(ReminderKind::DueSoon),
+            "overdue" => Some(ReminderKind::Overdue),
+            _ => None,
+        }
+    }
+}
+
+// An unpaid invoice inside the reminder window. The due date is the invoice date plus the
+// company's payment terms; `days_until_due` is negative once it has passed.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct DueInvoice {
+    pub invoice_id: i64,
+    pub invoice_number: String,
+    pub invoice_date: String,
+    pub due_date: String,
+    pub days_until_due: i64,
+    pub customer_id: i64,
+    pub customer_name: String,
+    pub outstanding: f64,
+    pub snoozed_until: Option<String>,
+}
+
+// In-app reminder feed entry; each invoice gets at most one due-soon and one overdue reminder
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PaymentReminder {
+    pub id: i64,
+    pub company_id: i64,
+    pub invoice_id: i64,
+    pub customer_id: i64,
+    pub kind: ReminderKind,
+    pub due_date: String,
+    pub outstanding: f64,
+    pub title: String,
+    pub message: String,
+    pub read_at: Option<String>,
+    pub created_at: Option<String>,
+}
+
+// Tera templates with invoice_number, invoice_date, due_date, days, customer_name and
+// outstanding. Parts left out use the built-in wording.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct ReminderTemplate {
+    #[serde(default)]
+    pub title: Option<String>,
+    #[serde(default)]
+    pub body: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ReminderSettings {
+    // Invoices due within this many days get a due-soon reminder
+    pub days_before: u32,
+    pub due_soon: ReminderTemplate,
+    pub overdue: ReminderTemplate,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ReminderSnooze {
+    pub company_id: i64,
+    pub customer_id: i64,
+    pub customer_name: String,
+    pub snoozed_until: String,
+    pub created_at: Option<String>,
+}
+
+const SELECT_REMINDER: &str = "
+    SELECT id, company_id, invoice_id, customer_id, kind, due_date, outstanding, title, message,
+           read_at, created_at
+    FROM payment_reminders";
+
+fn reminder_from_row(row: &Row) -> rusqlite::Result<PaymentReminder> {
+    let kind: String = row.get("kind")?;
+    Ok(PaymentReminder {
+        id: row.get("id")?,
+        company_id: row.get("company_id")?,
+        invoice_id: row.get("invoice_id")?,
+        customer_id: row.get("customer_id")?,
+        kind: ReminderKind::parse(&kind).unwrap_or(ReminderKind::Overdue),
+        due_date: row.get("due_date")?,
+        outstanding: row.get("outstanding")?,
+        title: row.get("title")?,
+        message: row.get("message")?,
+        read_at: row.get("read_at")?,
+        created_at: row.get("created_at")?,
+    })
+}
+
+pub fn load_settings(conn: &Connection) -> Result<ReminderSettings, String> {
+    Ok(ReminderSettings {
+        days_before: settings::get(conn, SETTING_DAYS_BEFORE)?.unwrap_or(DEFAULT_DAYS_BEFORE),
+        due_soon: ReminderTemplate {
+            title: settings::get(conn, SETTING_DUE_SOON_TITLE)?,
+            body: settings::get(conn, SETTING_DUE_SOON_BODY)?,
+        },
+        overdue: ReminderTemplate {
+            title: settings::get(conn, SETTING_OVERDUE_TITLE)?,
+            body: settings::get(conn, SETTING_OVERDUE_BODY)?,
+        },
+    })
+}
+
+fn render(
+    label: &str,
+    template: Option<&str>,
+    default: &str,
+    context: &Context,
+) -> Result<String, String> {
+    let template = template.filter(|template| !template.trim().is_empty()).unwrap_or(default);
+    Tera::one_off(template, context, false)
+        .map(|text| text.trim().to_string())
+        .map_err(|e| format!("Reminder {} template error: {}", label, describe_tera_error(e)))
+}
+
+fn reminder_context(invoice: &DueInvoice) -> Context {
+    let mut context = Context::new();
+    context.insert("invoice_number", &invoice.invoice_number);
+    context.insert("invoice_date", &invoice.invoice_date);
+    context.insert("due_date", &invoice.due_date);
+    context.insert("days", &invoice.days_until_due.abs());
+    context.insert("customer_name", &invoice.customer_name);
+    context.insert("outstanding", &format!("{:.2}", invoice.outstanding));
+    context
+}
+
+// Returns the title and message for an invoice's reminder
+fn compose(
+    settings: &ReminderSettings,
+    kind: ReminderKind,
+    invoice: &DueInvoice,
+) -> Result<(String, String), String> {
+    let context = reminder_context(invoice);
+    let (template, title, body) = match kind {
+        ReminderKind::DueSoon => (&settings.due_soon, DUE_SOON_TITLE, DUE_SOON_BODY),
+        ReminderKind::Overdue => (&settings.overdue, OVERDUE_TITLE, OVERDUE_BODY),
+    };
+    Ok((
+        render("title", template.title.as_deref(), title, &context)?,
+        render("body", template.body.as_deref(), body, &context)?,
+    ))
+}
+
+fn snoozes(conn: &Connection, company_id: i64) -> Result<HashMap<i64, String>, AppError> {
+    let mut stmt = conn
+        .prepare("SELECT customer_id, snoozed_until FROM reminder_snoozes WHERE company_id = ?1")?;
+    let snoozes = stmt
+        .query_map(params![company_id], |row| Ok((row.get(0)?, row.get(1)?)))?
+        .collect::<Result<HashMap<_, _>, _>>()?;
+    Ok(snoozes)
+}
+
+fn customer_names(conn: &Connection, company_id: i64) -> Result<HashMap<i64, String>, AppError> {
+    let mut stmt =
+        conn.prepare("SELECT id, report_customer FROM customers WHERE company_id = ?1")?;
+    let names = stmt
+        .query_map(params![company_id], |row| Ok((row.get(0)?, row.get(1)?)))?
+        .collect::<Result<HashMap<_, _>, _>>()?;
+    Ok(names)
+}
+
+// Unpaid invoices due within `days_before` days of `today` or already overdue, most overdue
+// first. Snoozed customers are included with their snooze date so the UI can show them.
+pub fn due_invoices(
+    conn: &Connection,
+    company_id: i64,
+    today: NaiveDate,
+    days_before: u32,
+) -> Result<Vec<DueInvoice>, String> {
+    let terms = chrono::Duration::days(settings::payment_terms_days(conn, company_id)? as i64);
+    let names = customer_names(conn, company_id)?;
+    let snoozes = snoozes(conn, company_id)?;
+    let today_text = today.format(INVOICE_DATE_FORMAT).to_string();
+    let mut due = Vec::new();
+    for balance in receipts::get_company_outstanding_invoices(conn, company_id)? {
+        let parsed = NaiveDate::parse_from_str(&balance.invoice_date, INVOICE_DATE_FORMAT);
+        let Ok(invoice_date) = parsed else {
+            continue;
+        };
+        let due_date = invoice_date + terms;
+        let days_until_due = (due_date - today).num_days();
+        if days_until_due > days_before as i64 {
+            continue;
+        }
+        due.push(DueInvoice {
+            invoice_id: balance.invoice_id,
+            invoice_number: balance.invoice_number,
+            invoice_date: balance.invoice_date,
+            due_date: due_date.format(INVOICE_DATE_FORMAT).to_string(),
+            days_until_due,
+            customer_id: balance.customer_id,
+            customer_name: names.get(&balance.customer_id).cloned().unwrap_or_default(),
+            outstanding: balance.outstanding,
+            snoozed_until: snoozes
+                .get(&balance.customer_id)
+                .filter(|until| until.as_str() >= today_text.as_str())
+                .cloned(),
+        });
+    }
+    due.sort_by_key(|invoice| invoice.days_until_due);
+    Ok(due)
+}
+
+// Adds feed entries for invoices that have newly become due soon or overdue and returns them.
+// Invoices of snoozed customers are skipped until the snooze ends.
+pub fn generate_reminders(
+    conn: &Connection,
+    company_id: i64,
+    today: NaiveDate,
+) -> Result<Vec<PaymentReminder>, String> {
+    let settings = load_settings(conn)?;
+    let mut created = Vec::new();
+    for invoice in due_invoices(conn, company_id, today, settings.days_before)? {
+        if invoice.snoozed_until.is_some() {
+            continue;
+        }
+        let kind = if invoice.days_until_due < 0 {
+            ReminderKind::Overdue
+        } else {
+            ReminderKind::DueSoon
+        };
+        let (title, message) = compose(&settings, kind, &invoice)?;
+        let inserted = conn
+            .execute(
+                "INSERT OR IGNORE INTO payment_reminders (company_id, invoice_id, customer_id, kind,
+                                                          due_date, outstanding, title, message)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+                params![
+                    company_id,
+                    invoice.invoice_id,
+                    invoice.customer_id,
+                    kind.as_str(),
+                    invoice.due_date,
+                    invoice.outstanding,
+                    title,
+                    message,
+                ],
+            )
+            .map_err(|e| e.to_string())?;
+        if inserted == 0 {
+            continue;
+        }
+        created.push(
+            conn.query_row(
+                &format!("{} WHERE id = ?1", SELECT_REMINDER),
+                params![conn.last_insert_rowid()],
+                reminder_from_row,
+            )
+            .map_err(|e| e.to_string())?,
+        );
+    }
+    Ok(created)
+}
+
+// One desktop notification per check, so a backlog of due invoices does not flood the desktop
+fn notify(app: &AppHandle, reminders: &[PaymentReminder]) {
+    let (title, body) = match reminders {
+        [] => return,
+        [reminder] => (reminder.title.clone(), reminder.message.clone()),
+        _ => {
+            let overdue = reminders
+                .iter()
+                .filter(|reminder| reminder.kind == ReminderKind::Overdue)
+                .count();
+            (
+                format!("{} payment reminders", reminders.len()),
+                format!("{} overdue, {} due soon", overdue, reminders.len() - overdue),
+            )
+        }
+    };
+    if let Err(e) = app.notification().builder().title(title).body(body).show() {
+        tracing::warn!(error = %e, "failed to show reminder notification");
+    }
+}
+
+fn check_all(conn: &Connection) -> Result<Vec<PaymentReminder>, String> {
+    let mut stmt = conn.prepare("SELECT id FROM companies").map_err(|e| e.to_string())?;
+    let company_ids = stmt
+        .query_map([], |row| row.get::<_, i64>(0))
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+    let today = Local::now().date_naive();
+    let mut created = Vec::new();
+    for company_id in company_ids {
+        created.extend(generate_reminders(conn, company_id, today)?);
+    }
+    Ok(created)
+}
+
+// Background thread started at launch; new reminders go to the feed, a desktop notification
+// and a payment-reminders event
+pub fn start_scheduler(app: AppHandle) {
+    std::thread::spawn(move || loop {
+        let pool = app.state::<DbPool>();
+        match db::get_conn(&pool).map_err(String::from).and_then(|conn| check_all(&conn)) {
+            Ok(created) if !created.is_empty() => {
+                notify(&app, &created);
+                let _ = app.emit(EVENT_PAYMENT_REMINDERS, created);
+            }
+            Ok(_) => {}
+            Err(e) => tracing::warn!(error = %e, "payment reminder check failed"),
+        }
+        std::thread::sleep(CHECK_INTERVAL);
+    });
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn list_due_invoices(
+    pool: State<'_, DbPool>,
+    company_id: i64,
+) -> Result<Vec<DueInvoice>, AppError> {
+    let conn = db::get_conn(&pool)?;
+    let days_before = load_settings(&conn)?.days_before;
+    Ok(due_invoices(&conn, company_id, Local::now().date_naive(), days_before)?)
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn list_payment_reminders(
+    pool: State<'_, DbPool>,
+    company_id: i64,
+    unread_only: Option<bool>,
+    limit: Option<u32>,
+) -> Result<Vec<PaymentReminder>, AppError> {
+    let limit = limit.unwrap_or(DEFAULT_FEED_LIMIT).clamp(1, MAX_FEED_LIMIT);
+    let conn = db::get_conn(&pool)?;
+    let mut stmt = conn.prepare(&format!(
+        "{} WHERE company_id = ?1 AND (?2 = 0 OR read_at IS NULL)
+             ORDER BY created_at DESC, id DESC LIMIT ?3",
+        SELECT_REMINDER
+    ))?;
+    let reminders = stmt
+        .query_map(params![company_id, unread_only.unwrap_or(false), limit], reminder_from_row)?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(reminders)
+}
+
+// Marks the given reminders read, or every unread reminder of the company when `ids` is left
+// out. Returns how many were marked.
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn mark_payment_reminders_read(
+    pool: State<'_, DbPool>,
+    company_id: i64,
+    ids: Option<Vec<i64>>,
+) -> Result<usize, AppError> {
+    let mut conn = db::get_conn(&pool)?;
+    let tx = conn.transaction()?;
+    let marked = match ids {
+        Some(ids) => {
+            let mut marked = 0;
+            for id in ids {
+                marked += tx.execute(
+                    "UPDATE payment_reminders SET read_at = CURRENT_TIMESTAMP
+                         WHERE id = ?1 AND company_id = ?2 AND read_at IS NULL",
+                    params![id, company_id],
+                )?;
+            }
+            marked
+        }
+        None => tx.execute(
+            "UPDATE payment_reminders SET read_at = CURRENT_TIMESTAMP
+                 WHERE company_id = ?1 AND read_at IS NULL",
+            params![company_id],
+        )?,
+    };
+    tx.commit()?;
+    Ok(marked)
+}
+
+// Runs the reminder check for one company straight away and returns the new reminders
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn check_payment_reminders(
+    pool: State<'_, DbPool>,
+    app: AppHandle,
+    company_id: i64,
+) -> Result<Vec<PaymentReminder>, AppError> {
+    let conn = db::get_conn(&pool)?;
+    let created = generate_reminders(&conn, company_id, Local::now().date_naive())?;
+    if !created.is_empty() {
+        notify(&app, &created);
+        let _ = app.emit(EVENT_PAYMENT_REMINDERS, created.clone());
+    }
+    Ok(created)
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn list_reminder_snoozes(
+    pool: State<'_, DbPool>,
+    company_id: i64,
+) -> Result<Vec<ReminderSnooze>, AppError> {
+    let conn = db::get_conn(&pool)?;
+    let mut stmt = conn.prepare(
+        "SELECT s.company_id, s.customer_id, c.report_customer, s.snoozed_until, s.created_at
+             FROM reminder_snoozes s
+             JOIN customers c ON c.id = s.customer_id
+             WHERE s.company_id = ?1
+             ORDER BY s.snoozed_until",
+    )?;
+    let snoozes = stmt
+        .query_map(params![company_id], |row| {
+            Ok(ReminderSnooze {
+                company_id: row.get(0)?,
+                customer_id: row.get(1)?,
+                customer_name: row.get(2)?,
+                snoozed_until: row.get(3)?,
+                created_at: row.get(4)?,
+            })
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(snoozes)
+}
+
+// No reminders are raised for the customer's invoices up to and including `until`
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn snooze_customer_reminders(
+    pool: State<'_, DbPool>,
+    company_id: i64,
+    customer_id: i64,
+    until: String,
+) -> Result<(), AppError> {
+    let until = NaiveDate::parse_from_str(until.trim(), INVOICE_DATE_FORMAT)
+        .map_err(|_| AppError::validation("until", "Enter the date as YYYY-MM-DD"))?;
+    let today = Local::now().date_naive();
+    if until < today {
+        return Err(AppError::validation("until", "Choose a date from today onwards"));
+    }
+    if (until - today).num_days() > MAX_SNOOZE_DAYS {
+        return Err(AppError::validation(
+            "until",
+            format!("Reminders can be snoozed for at most {} days", MAX_SNOOZE_DAYS),
+        ));
+    }
+    let conn = db::get_conn(&pool)?;
+    if customers::get_customer_by_id(&conn, customer_id, company_id)?.is_none() {
+        return Err(AppError::not_found("Customer not found"));
+    }
+    conn.execute(
+        "INSERT INTO reminder_snoozes (company_id, customer_id, snoozed_until) VALUES (?1, ?2, ?3)
+         ON CONFLICT(company_id, customer_id)
+         DO UPDATE SET snoozed_until = ?3, created_at = CURRENT_TIMESTAMP",
+        params![company_id, customer_id, until.format(INVOICE_DATE_FORMAT).to_string()],
+    )?;
+    Ok(())
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn unsnooze_customer_reminders(
+    pool: State<'_, DbPool>,
+    company_id: i64,
+    customer_id: i64,
+) -> Result<(), AppError> {
+    let conn = db::get_conn(&pool)?;
+    conn.execute(
+        "DELETE FROM reminder_snoozes WHERE company_id = ?1 AND customer_id = ?2",
+        params![company_id, customer_id],
+    )?;
+    Ok(())
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn get_reminder_settings(pool: State<'_, DbPool>) -> Result<ReminderSettings, AppError> {
+    let conn = db::get_conn(&pool)?;
+    Ok(load_settings(&conn)?)
+}
+
+// Templates are rendered against a sample invoice first so mistakes show up here rather than
+// in the background task
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn set_reminder_settings(
+    pool: State<'_, DbPool>,
+    settings: ReminderSettings,
+) -> Result<ReminderSettings, AppError> {
+    if settings.days_before > MAX_DAYS_BEFORE {
+        return Err(AppError::validation(
+            "days_before",
+            format!("Remind at most {} days before the due date", MAX_DAYS_BEFORE),
+        ));
+    }
+    let sample = DueInvoice {
+        invoice_id: 0,
+        invoice_number: "INV-001".to_string(),
+        invoice_date: "2024-04-01".to_string(),
+        due_date: "2024-05-01".to_string(),
+        days_until_due: 3,
+        customer_id: 0,
+        customer_name: "Sample Customer".to_string(),
+        outstanding: 1000.0,
+        snoozed_until: None,
+    };
+    compose(&settings, ReminderKind::DueSoon, &sample)
+        .map_err(|e| AppError::validation("due_soon", e))?;
+    compose(&settings, ReminderKind::Overdue, &sample)
+        .map_err(|e| AppError::validation("overdue", e))?;
+
+    let conn = db::get_conn(&pool)?;
+    settings::put(&conn, SETTING_DAYS_BEFORE, &settings.days_before)?;
+    let templates = [
+        (SETTING_DUE_SOON_TITLE, &settings.due_soon.title),
+        (SETTING_DUE_SOON_BODY, &settings.due_soon.body),
+        (SETTING_OVERDUE_TITLE, &settings.overdue.title),
+        (SETTING_OVERDUE_BODY, &settings.overdue.body),
+    ];
+    for (key, value) in templates {
+        settings::put(&conn, key, &value.clone().unwrap_or_default())?;
+    }
+    Ok(load_settings(&conn)?)
+}
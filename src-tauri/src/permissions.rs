@@ -0,0 +1,492 @@
+use serde_json::Value;
+use tauri::ipc::{Invoke, InvokeBody, InvokeMessage};
+use tauri::{Manager, Runtime};
+
+use crate::audit;
+use crate::auth::{self, Role, Session};
+use crate::companies::ActiveCompany;
+use crate::db::{self, DbPool};
+use crate::error::AppError;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Permission {
+    // View data and export reports
+    Read,
+    // Create, change and delete business records
+    Write,
+    // Company setup, credentials, numbering and year-end
+    Configure,
+    ManageUsers,
+}
+
+impl Role {
+    pub fn allows(&self, permission: Permission) -> bool {
+        match self {
+            Role::Admin => true,
+            Role::Accountant => matches!(permission, Permission::Read | Permission::Write),
+            Role::Viewer => permission == Permission::Read,
+        }
+    }
+}
+
+// Callable without signing in
+const PUBLIC_COMMANDS: &[&str] = &[
+    "greet",
+    "login",
+    "logout",
+    "get_session",
+    "initialize_database",
+    "get_schema_status",
+    "get_display_settings",
+];
+
+// Given a company other than the active one by design: choosing which company to work in
+const ANY_COMPANY_COMMANDS: &[&str] = &["switch_active_company"];
+
+// Permission needed for each command; a command missing here is refused for everyone
+const COMMAND_PERMISSIONS: &[(&str, Permission)] = &[
+    ("migrate_schema", Permission::Configure),
+    ("create_company", Permission::Configure),
+    ("validate_company_update", Permission::Read),
+    ("update_company", Permission::Configure),
+    ("get_company", Permission::Read),
+    ("list_companies", Permission::Read),
+    ("validate_category_create", Permission::Read),
+    ("validate_category_update", Permission::Read),
+    ("create_category", Permission::Write),
+    ("update_category", Permission::Write),
+    ("list_categories", Permission::Read),
+    ("delete_category", Permission::Write),
+    ("validate_customer_create", Permission::Read),
+    ("validate_customer_update", Permission::Read),
+    ("create_customer", Permission::Write),
+    ("update_customer", Permission::Write),
+    ("get_customer", Permission::Read),
+    ("list_customers", Permission::Read),
+    ("delete_customer", Permission::Write),
+    ("create_invoice", Permission::Write),
+    ("update_invoice", Permission::Write),
+    ("get_invoice", Permission::Read),
+    ("get_invoice_with_lines", Permission::Read),
+    ("delete_invoice", Permission::Write),
+    ("list_invoices", Permission::Read),
+    ("lookup_hsn", Permission::Read),
+    ("validate_gstin", Permission::Read),
+    ("derive_state_from_gstin", Permission::Read),
+    ("get_gstin_api_config", Permission::Configure),
+    ("save_gstin_api_config", Permission::Configure),
+    ("verify_gstin_online", Permission::Write),
+    ("get_tally_export_config", Permission::Write),
+    ("save_tally_export_config", Permission::Configure),
+    ("export_tally_vouchers", Permission::Read),
+    ("import_tally_ledgers", Permission::Write),
+    ("import_sales_excel", Permission::Write),
+    ("export_report_xlsx", Permission::Read),
+    ("save_import_profile", Permission::Write),
+    ("list_import_profiles", Permission::Read),
+    ("delete_import_profile", Permission::Write),
+    ("preview_csv_import", Permission::Read),
+    ("import_csv", Permission::Write),
+    ("save_customer_mapping", Permission::Write),
+    ("get_customer_mapping", Permission::Read),
+    ("list_customer_mappings", Permission::Read),
+    ("delete_customer_mapping", Permission::Write),
+    ("create_import_session", Permission::Write),
+    ("add_import_customers", Permission::Write),
+    ("set_import_session_status", Permission::Write),
+    ("resolve_import_customer", Permission::Write),
+    ("list_import_customers", Permission::Read),
+    ("import_report_rows", Permission::Write),
+    ("list_import_reports", Permission::Read),
+    ("import_accounting_export", Permission::Write),
+    ("start_csv_import", Permission::Write),
+    ("start_sales_import", Permission::Write),
+    ("cancel_import", Permission::Write),
+    ("enqueue_job", Permission::Write),
+    ("list_jobs", Permission::Read),
+    ("get_job", Permission::Read),
+    ("clear_job_history", Permission::Write),
+    ("cancel_job", Permission::Write),
+    ("cancel_operation", Permission::Read),
+    ("run_transaction", Permission::Write),
+    ("generate_gstr1_json", Permission::Read),
+    ("get_einvoice_config", Permission::Configure),
+    ("save_einvoice_config", Permission::Configure),
+    ("build_einvoice_payload", Permission::Read),
+    ("get_einvoice", Permission::Read),
+    ("generate_irn", Permission::Write),
+    ("generate_irn_bulk", Permission::Write),
+    ("get_invoice_qr", Permission::Read),
+    ("save_eway_bill_details", Permission::Write),
+    ("get_eway_bill", Permission::Read),
+    ("generate_eway_bill", Permission::Write),
+    ("export_eway_bill_json", Permission::Read),
+    ("record_eway_bill", Permission::Write),
+    ("list_expiring_eway_bills", Permission::Read),
+    ("render_invoice_pdf", Permission::Read),
+    ("list_invoice_templates", Permission::Read),
+    ("create_invoice_template", Permission::Write),
+    ("update_invoice_template", Permission::Write),
+    ("delete_invoice_template", Permission::Write),
+    ("set_default_invoice_template", Permission::Write),
+    ("preview_invoice_template", Permission::Read),
+    ("amount_to_words", Permission::Read),
+    ("get_invoice_rounding", Permission::Read),
+    ("set_invoice_rounding", Permission::Configure),
+    ("list_financial_years", Permission::Read),
+    ("create_financial_year", Permission::Write),
+    ("close_financial_year", Permission::Configure),
+    ("reopen_financial_year", Permission::Configure),
+    ("list_period_locks", Permission::Read),
+    ("lock_period", Permission::Configure),
+    ("unlock_period", Permission::Configure),
+    ("list_number_sequences", Permission::Read),
+    ("save_number_sequence", Permission::Configure),
+    ("preview_next_number", Permission::Read),
+    ("create_credit_debit_note", Permission::Write),
+    ("get_credit_debit_note", Permission::Read),
+    ("list_credit_debit_notes", Permission::Read),
+    ("set_credit_debit_note_status", Permission::Write),
+    ("delete_credit_debit_note", Permission::Write),
+    ("record_receipt", Permission::Write),
+    ("get_receipt", Permission::Read),
+    ("list_receipts", Permission::Read),
+    ("list_outstanding_invoices", Permission::Read),
+    ("auto_allocate_receipt", Permission::Write),
+    ("allocate_receipt", Permission::Write),
+    ("remove_receipt_allocation", Permission::Write),
+    ("delete_receipt", Permission::Write),
+    ("customer_statement", Permission::Read),
+    ("export_customer_statement_pdf", Permission::Read),
+    ("export_customer_statement_xlsx", Permission::Read),
+    ("sales_register", Permission::Read),
+    ("monthly_category_summary", Permission::Read),
+    ("sales_by_customer", Permission::Read),
+    ("export_sales_by_customer_xlsx", Permission::Read),
+    ("state_supply_split", Permission::Read),
+    ("get_dashboard_data", Permission::Read),
+    ("get_dashboard_cache_seconds", Permission::Read),
+    ("set_dashboard_cache_seconds", Permission::Configure),
+    ("list_deleted", Permission::Read),
+    ("restore", Permission::Write),
+    ("get_recycle_bin_retention", Permission::Read),
+    ("set_recycle_bin_retention", Permission::Configure),
+    ("get_audit_trail", Permission::Read),
+    ("switch_active_company", Permission::Read),
+    ("get_active_company", Permission::Read),
+    ("clear_active_company", Permission::Read),
+    ("create_user", Permission::ManageUsers),
+    ("list_users", Permission::ManageUsers),
+    ("update_user", Permission::ManageUsers),
+    ("reset_user_password", Permission::ManageUsers),
+    ("change_password", Permission::Read),
+    ("backup_database", Permission::Configure),
+    ("get_backup_schedule", Permission::Configure),
+    ("set_backup_schedule", Permission::Configure),
+    ("run_maintenance", Permission::Configure),
+    ("check_database_health", Permission::Configure),
+    ("repair_database", Permission::Configure),
+    ("export_problem_rows", Permission::Configure),
+    ("seed_demo_data", Permission::Configure),
+    ("inspect_backup", Permission::Configure),
+    ("restore_backup", Permission::Configure),
+    ("export_full_archive", Permission::Configure),
+    ("import_full_archive", Permission::Configure),
+    ("get_cloud_backup_settings", Permission::Configure),
+    ("set_cloud_backup_settings", Permission::Configure),
+    ("upload_cloud_backup", Permission::Configure),
+    ("list_cloud_backups", Permission::Configure),
+    ("restore_cloud_backup", Permission::Configure),
+    ("get_encryption_status", Permission::Configure),
+    ("enable_database_encryption", Permission::Configure),
+    ("change_database_key", Permission::Configure),
+    ("find_duplicate_customers", Permission::Read),
+    ("check_customer_duplicates", Permission::Read),
+    ("merge_customers", Permission::Write),
+    ("bulk_import_customers", Permission::Write),
+    ("bulk_assign_category", Permission::Write),
+    ("bulk_delete_customers", Permission::Write),
+    ("bulk_delete_invoices", Permission::Write),
+    ("list_states", Permission::Read),
+    ("get_state", Permission::Read),
+    ("get_pan_details", Permission::Read),
+    ("check_pan_consistency", Permission::Read),
+    ("get_tax_split", Permission::Read),
+    ("compute_invoice_totals", Permission::Read),
+    ("get_tcs_settings", Permission::Read),
+    ("set_tcs_settings", Permission::Configure),
+    ("get_tcs_report", Permission::Read),
+    ("get_export_register", Permission::Read),
+    ("hsn_summary", Permission::Read),
+    ("document_summary", Permission::Read),
+    ("create_purchase", Permission::Write),
+    ("update_purchase", Permission::Write),
+    ("list_purchases", Permission::Read),
+    ("delete_purchase", Permission::Write),
+    ("import_gstr2b", Permission::Write),
+    ("list_gstr2b_entries", Permission::Read),
+    ("reconcile_gstr2b", Permission::Read),
+    ("cancel_einvoice", Permission::Write),
+    ("mark_gstr1_amendment", Permission::Write),
+    ("list_gstr1_amendments", Permission::Read),
+    ("delete_gstr1_amendment", Permission::Write),
+    ("list_validation_rules", Permission::Read),
+    ("create_validation_rule", Permission::Configure),
+    ("update_validation_rule", Permission::Configure),
+    ("delete_validation_rule", Permission::Configure),
+    ("get_log_level", Permission::Read),
+    ("set_log_level", Permission::Configure),
+    ("get_recent_logs", Permission::Configure),
+    ("get_crash_reporting", Permission::Read),
+    ("set_crash_reporting", Permission::Configure),
+    ("export_diagnostics", Permission::Configure),
+    ("clear_crash_reports", Permission::Configure),
+    ("get_pool_metrics", Permission::Configure),
+    ("export_anonymized_db", Permission::Configure),
+    ("get_settings", Permission::Read),
+    ("update_settings", Permission::Configure),
+    ("update_display_settings", Permission::Read),
+    ("get_smtp_settings", Permission::Configure),
+    ("set_smtp_settings", Permission::Configure),
+    ("send_invoice_email", Permission::Write),
+    ("send_statement_email", Permission::Write),
+    ("list_email_log", Permission::Read),
+    ("list_printers", Permission::Read),
+    ("print_document", Permission::Read),
+    ("add_attachment", Permission::Write),
+    ("list_attachments", Permission::Read),
+    ("open_attachment", Permission::Read),
+    ("delete_attachment", Permission::Write),
+    ("get_report_fields", Permission::Read),
+    ("list_custom_reports", Permission::Read),
+    ("save_custom_report", Permission::Write),
+    ("delete_custom_report", Permission::Write),
+    ("run_custom_report", Permission::Read),
+    ("export_custom_report", Permission::Read),
+    ("list_report_schedules", Permission::Read),
+    ("create_report_schedule", Permission::Configure),
+    ("update_report_schedule", Permission::Configure),
+    ("delete_report_schedule", Permission::Configure),
+    ("run_report_schedule_now", Permission::Write),
+    ("list_report_schedule_runs", Permission::Read),
+    ("list_due_invoices", Permission::Read),
+    ("list_payment_reminders", Permission::Read),
+    ("mark_payment_reminders_read", Permission::Write),
+    ("check_payment_reminders", Permission::Write),
+    ("list_reminder_snoozes", Permission::Read),
+    ("snooze_customer_reminders", Permission::Write),
+    ("unsnooze_customer_reminders", Permission::Write),
+    ("get_reminder_settings", Permission::Configure),
+    ("set_reminder_settings", Permission::Configure),
+    ("list_currencies", Permission::Read),
+    ("save_currency", Permission::Configure),
+    ("list_exchange_rates", Permission::Read),
+    ("get_exchange_rate", Permission::Read),
+    ("set_exchange_rate", Permission::Write),
+    ("clear_exchange_rate_override", Permission::Write),
+    ("get_exchange_rate_source", Permission::Configure),
+    ("set_exchange_rate_source", Permission::Configure),
+    ("fetch_exchange_rates", Permission::Write),
+    ("list_uqc_codes", Permission::Read),
+    ("list_units", Permission::Read),
+    ("create_unit", Permission::Configure),
+    ("update_unit", Permission::Configure),
+    ("delete_unit", Permission::Configure),
+    ("list_items", Permission::Read),
+    ("get_item", Permission::Read),
+    ("create_item", Permission::Write),
+    ("update_item", Permission::Write),
+    ("delete_item", Permission::Write),
+    ("list_price_lists", Permission::Read),
+    ("create_price_list", Permission::Write),
+    ("update_price_list", Permission::Write),
+    ("delete_price_list", Permission::Write),
+    ("list_price_list_rates", Permission::Read),
+    ("set_price_list_rate", Permission::Write),
+    ("delete_price_list_rate", Permission::Write),
+    ("list_price_list_assignments", Permission::Read),
+    ("assign_price_list", Permission::Write),
+    ("unassign_price_list", Permission::Write),
+    ("resolve_price", Permission::Read),
+    ("list_quotations", Permission::Read),
+    ("get_quotation", Permission::Read),
+    ("create_quotation", Permission::Write),
+    ("update_quotation", Permission::Write),
+    ("set_quotation_status", Permission::Write),
+    ("delete_quotation", Permission::Write),
+    ("convert_quotation_to_invoice", Permission::Write),
+    ("export_quotation_pdf", Permission::Read),
+    ("list_sales_orders", Permission::Read),
+    ("get_sales_order", Permission::Read),
+    ("create_sales_order", Permission::Write),
+    ("update_sales_order", Permission::Write),
+    ("set_sales_order_status", Permission::Write),
+    ("delete_sales_order", Permission::Write),
+    ("create_invoice_from_order", Permission::Write),
+    ("open_orders_report", Permission::Read),
+    ("list_delivery_challans", Permission::Read),
+    ("get_delivery_challan", Permission::Read),
+    ("list_invoice_challans", Permission::Read),
+    ("create_delivery_challan", Permission::Write),
+    ("update_delivery_challan", Permission::Write),
+    ("set_delivery_challan_status", Permission::Write),
+    ("delete_delivery_challan", Permission::Write),
+    ("convert_challan_to_invoice", Permission::Write),
+    ("link_challan_to_invoice", Permission::Write),
+    ("stock_ledger", Permission::Read),
+    ("list_stock_levels", Permission::Read),
+    ("list_stock_adjustments", Permission::Read),
+    ("get_stock_settings", Permission::Read),
+    ("create_stock_adjustment", Permission::Write),
+    ("delete_stock_adjustment", Permission::Write),
+    ("set_stock_settings", Permission::Configure),
+    ("closing_stock_report", Permission::Read),
+    ("list_batches", Permission::Read),
+    ("trace_batch", Permission::Read),
+    ("trace_serial", Permission::Read),
+    ("list_expiring_batches", Permission::Read),
+    ("create_batch", Permission::Write),
+    ("update_batch", Permission::Write),
+    ("delete_batch", Permission::Write),
+    ("generate_barcode", Permission::Read),
+    ("parse_pasted_table", Permission::Read),
+    ("get_api_server_status", Permission::Read),
+    ("set_api_server_settings", Permission::Configure),
+    ("regenerate_api_token", Permission::Configure),
+    ("list_webhooks", Permission::Configure),
+    ("create_webhook", Permission::Configure),
+    ("update_webhook", Permission::Configure),
+    ("delete_webhook", Permission::Configure),
+    ("list_webhook_deliveries", Permission::Read),
+    ("retry_webhook_delivery", Permission::Configure),
+    ("get_sync_status", Permission::Read),
+    ("export_sync_changes", Permission::Configure),
+    ("import_sync_changes", Permission::Configure),
+    ("list_sync_conflicts", Permission::Read),
+    ("resolve_sync_conflict", Permission::Configure),
+];
+
+fn required_permission(command: &str) -> Option<Permission> {
+    COMMAND_PERMISSIONS
+        .iter()
+        .find(|(name, _)| *name == command)
+        .map(|(_, permission)| *permission)
+}
+
+// Company the command was aimed at, so a refusal is logged where an admin will look for it
+fn target_company<R: Runtime>(message: &InvokeMessage<R>) -> i64 {
+    match message.payload() {
+        InvokeBody::Json(args) => args.get("companyId").and_then(|id| id.as_i64()).unwrap_or(0),
+        _ => 0,
+    }
+}
+
+// Company ids a command was given: the `companyId` argument and the `company_id` of records
+// passed to it, e.g. a new customer or the rows of a bulk import
+fn requested_companies(args: &Value) -> Vec<i64> {
+    let Some(args) = args.as_object() else {
+        return Vec::new();
+    };
+    let mut ids: Vec<i64> = args.get("companyId").and_then(Value::as_i64).into_iter().collect();
+    for value in args.values() {
+        let records: Vec<&Value> = match value {
+            Value::Array(items) => items.iter().collect(),
+            other => vec![other],
+        };
+        ids.extend(records.into_iter().filter_map(|record| record.get("company_id")?.as_i64()));
+    }
+    ids
+}
+
+fn check_company(args: &Value, pool: &DbPool, active: &ActiveCompany) -> Result<(), AppError> {
+    let requested = requested_companies(args);
+    if requested.is_empty() {
+        return Ok(());
+    }
+    let conn = db::get_conn(pool)?;
+    match active.current(&conn)? {
+        Some(id) if requested.iter().all(|company_id| *company_id == id) => Ok(()),
+        Some(_) => Err(AppError::Forbidden {
+            message: "Switch to this company before working in it".to_string(),
+        }),
+        None => Err(AppError::Forbidden {
+            message: "Choose a company to work in first".to_string(),
+        }),
+    }
+}
+
+fn check<R: Runtime>(
+    message: &InvokeMessage<R>,
+    pool: &DbPool,
+    session: &Session,
+    active: &ActiveCompany,
+) -> Result<(), AppError> {
+    let command = message.command();
+    let Some(permission) = required_permission(command) else {
+        return Err(AppError::Forbidden {
+            message: format!("{} has no permission defined", command),
+        });
+    };
+    match session.current() {
+        Some(user) if !user.role.allows(permission) => {
+            return Err(AppError::Forbidden {
+                message: format!("Your {} role cannot perform {}", user.role.as_str(), command),
+            });
+        }
+        Some(_) => {}
+        None => {
+            let conn = db::get_conn(pool)?;
+            if auth::users_exist(&conn)? {
+                return Err(AppError::Unauthenticated {
+                    message: "Please sign in to continue".to_string(),
+                });
+            }
+        }
+    }
+    match message.payload() {
+        InvokeBody::Json(args) if !ANY_COMPANY_COMMANDS.contains(&command) => {
+            check_company(args, pool, active)
+        }
+        _ => Ok(()),
+    }
+}
+
+// Runs before every command. Refusals are written to the audit log; a failure to log does not
+// turn a refusal into an allowed call.
+pub fn authorize<R: Runtime>(message: &InvokeMessage<R>) -> Result<(), AppError> {
+    let command = message.command();
+    if PUBLIC_COMMANDS.contains(&command) {
+        return Ok(());
+    }
+    let webview = message.webview();
+    let (Some(pool), Some(session), Some(active)) = (
+        webview.try_state::<DbPool>(),
+        webview.try_state::<Session>(),
+        webview.try_state::<ActiveCompany>(),
+    ) else {
+        return Err(AppError::Internal {
+            message: "The application is still starting".to_string(),
+        });
+    };
+    check(message, &pool, &session, &active).inspect_err(|reason| {
+        if let Ok(conn) = db::get_conn(&pool) {
+            let _ = audit::record_denied(&conn, target_company(message), command, reason.message());
+        }
+    })
+}
+
+// Wraps the app's command handler so every call passes `authorize` first. Plugin commands
+// (`plugin:<name>|<command>`) are routed to their plugin before this handler and are limited by
+// the capability files instead, so no plugin that reads or writes app data may be registered.
+pub fn guard<R: Runtime>(
+    handler: impl Fn(Invoke<R>) -> bool + Send + Sync + 'static,
+) -> impl Fn(Invoke<R>) -> bool + Send + Sync + 'static {
+    move |invoke| {
+        if let Err(error) = authorize(&invoke.message) {
+            tracing::warn!(command = invoke.message.command(), "refused: {}", error);
+            invoke.resolver.reject(error);
+            return true;
+        }
+        handler(invoke)
+    }
+}
@@ -0,0 +1,130 @@
+use rusqlite::Connection;
+use serde::{Deserialize, Serialize};
+use tauri::State;
+
+use crate::companies;
+use crate::customers::{self, Customer, RegistrationType};
+use crate::db::{self, DbPool};
+use crate::error::AppError;
+use crate::gstin;
+use crate::gstr1::EXPORT_STATE_CODE;
+use crate::tax::{self, TaxRegime, TaxSplit};
+
+// Supplies to SEZ units and exports are inter-state whatever the place of supply. Deemed
+// exports (e.g. to export-oriented units) are taxed like regular supplies but reported apart.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum SupplyKind {
+    #[default]
+    Regular,
+    Sez,
+    Export,
+    DeemedExport,
+}
+
+impl SupplyKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SupplyKind::Regular => "regular",
+            SupplyKind::Sez => "sez",
+            SupplyKind::Export => "export",
+            SupplyKind::DeemedExport => "deemed_export",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "regular" => Some(SupplyKind::Regular),
+            "sez" => Some(SupplyKind::Sez),
+            "export" => Some(SupplyKind::Export),
+            "deemed_export" => Some(SupplyKind::DeemedExport),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SupplyDecision {
+    pub supplier_state: String,
+    pub place_of_supply: String,
+    pub supply_kind: SupplyKind,
+    pub regime: TaxRegime,
+    pub split: TaxSplit,
+}
+
+// Place of supply when none is entered: the state in the customer's GSTIN for registered
+// customers, otherwise the customer's recorded state
+pub fn default_place_of_supply(customer: &Customer, kind: SupplyKind) -> String {
+    if kind == SupplyKind::Export {
+        return EXPORT_STATE_CODE.to_string();
+    }
+    match gstin::parse_gstin(&customer.gst_no) {
+        Ok(parsed) => parsed.state_code,
+        Err(_) => customer.state_code.trim().to_string(),
+    }
+}
+
+// Supplies to an SEZ customer are always SEZ supplies, so they never carry CGST/SGST
+pub fn effective_kind(customer: &Customer, kind: SupplyKind) -> SupplyKind {
+    if customer.registration_type == RegistrationType::Sez && kind == SupplyKind::Regular {
+        SupplyKind::Sez
+    } else {
+        kind
+    }
+}
+
+pub fn decide(supplier_state: &str, place_of_supply: &str, kind: SupplyKind) -> TaxRegime {
+    let place_of_supply = place_of_supply.trim();
+    let zero_rated = matches!(kind, SupplyKind::Sez | SupplyKind::Export);
+    if zero_rated || place_of_supply == EXPORT_STATE_CODE {
+        TaxRegime::InterState
+    } else if place_of_supply == supplier_state.trim() {
+        TaxRegime::IntraState
+    } else {
+        TaxRegime::InterState
+    }
+}
+
+// Regime of a company's supply, read from the company's registered state
+pub fn regime_for(
+    conn: &Connection,
+    company_id: i64,
+    place_of_supply: &str,
+    kind: SupplyKind,
+) -> Result<TaxRegime, AppError> {
+    let company = companies::get_company_by_id(conn, company_id)?
+        .ok_or_else(|| AppError::not_found("Company not found"))?;
+    Ok(decide(&company.state_code, place_of_supply, kind))
+}
+
+// Lets the invoice form show the split the backend will apply
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn get_tax_split(
+    pool: State<'_, DbPool>,
+    company_id: i64,
+    customer_id: i64,
+    place_of_supply: Option<String>,
+    supply_kind: Option<SupplyKind>,
+    taxable_value: f64,
+    gst_rate: f64,
+) -> Result<SupplyDecision, AppError> {
+    let conn = db::get_conn(&pool)?;
+    let company = companies::get_company_by_id(&conn, company_id)?
+        .ok_or_else(|| AppError::not_found("Company not found"))?;
+    let customer = customers::get_customer_by_id(&conn, customer_id, company_id)?
+        .ok_or_else(|| AppError::not_found("Customer not found"))?;
+    let supply_kind = effective_kind(&customer, supply_kind.unwrap_or_default());
+    let place_of_supply = place_of_supply
+        .map(|code| code.trim().to_string())
+        .filter(|code| !code.is_empty())
+        .unwrap_or_else(|| default_place_of_supply(&customer, supply_kind));
+    let regime = decide(&company.state_code, &place_of_supply, supply_kind);
+    Ok(SupplyDecision {
+        supplier_state: company.state_code.trim().to_string(),
+        split: tax::tax_on(taxable_value, gst_rate, regime),
+        place_of_supply,
+        supply_kind,
+        regime,
+    })
+}
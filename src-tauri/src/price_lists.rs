@@ -0,0 +1,522 @@
+use chrono::NaiveDate;
+use rusqlite::{params, Connection, OptionalExtension, Row};
+use serde::{Deserialize, Serialize};
+use tauri::State;
+
+use crate::categories;
+use crate::customers;
+use crate::db::{self, DbPool};
+use crate::error::AppError;
+use crate::invoices::INVOICE_DATE_FORMAT;
+use crate::items;
+
+// Open-ended rates compare as running to this date
+const OPEN_END: &str = "9999-12-31";
+
+// Price list data model
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PriceList {
+    pub id: i64,
+    pub company_id: i64,
+    pub name: String,
+    pub active: bool,
+    pub created_at: Option<String>,
+    pub updated_at: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SavePriceList {
+    pub name: String,
+    #[serde(default = "default_active")]
+    pub active: bool,
+}
+
+// An item's rate on a list from `effective_from` until `effective_to` (inclusive, open if None)
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PriceListRate {
+    pub id: i64,
+    pub price_list_id: i64,
+    pub item_id: i64,
+    pub item_name: String,
+    pub rate: f64,
+    pub effective_from: String,
+    pub effective_to: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SavePriceListRate {
+    pub item_id: i64,
+    pub rate: f64,
+    pub effective_from: String,
+    #[serde(default)]
+    pub effective_to: Option<String>,
+}
+
+// A list applies to exactly one of a customer or a customer category
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PriceListAssignment {
+    pub id: i64,
+    pub price_list_id: i64,
+    pub customer_id: Option<i64>,
+    pub category_id: Option<i64>,
+    pub assignee_name: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum PriceSource {
+    Customer,
+    Category,
+    Item,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ResolvedPrice {
+    pub item_id: i64,
+    pub rate: f64,
+    pub source: PriceSource,
+    pub price_list_id: Option<i64>,
+    pub price_list_name: Option<String>,
+}
+
+fn default_active() -> bool {
+    true
+}
+
+const SELECT_PRICE_LIST: &str =
+    "SELECT id, company_id, name, active, created_at, updated_at FROM price_lists";
+
+const SELECT_RATE: &str = "
+    SELECT r.id, r.price_list_id, r.item_id, i.name AS item_name, r.rate, r.effective_from,
+           r.effective_to
+    FROM price_list_items r
+    JOIN items i ON i.id = r.item_id";
+
+const SELECT_ASSIGNMENT: &str = "
+    SELECT a.id, a.price_list_id, a.customer_id, a.category_id,
+           COALESCE(c.report_customer, cat.name, '') AS assignee_name
+    FROM price_list_assignments a
+    LEFT JOIN customers c ON c.id = a.customer_id
+    LEFT JOIN categories cat ON cat.id = a.category_id";
+
+fn price_list_from_row(row: &Row) -> rusqlite::Result<PriceList> {
+    Ok(PriceList {
+        id: row.get("id")?,
+        company_id: row.get("company_id")?,
+        name: row.get("name")?,
+        active: row.get("active")?,
+        created_at: row.get("created_at")?,
+        updated_at: row.get("updated_at")?,
+    })
+}
+
+fn rate_from_row(row: &Row) -> rusqlite::Result<PriceListRate> {
+    Ok(PriceListRate {
+        id: row.get("id")?,
+        price_list_id: row.get("price_list_id")?,
+        item_id: row.get("item_id")?,
+        item_name: row.get("item_name")?,
+        rate: row.get("rate")?,
+        effective_from: row.get("effective_from")?,
+        effective_to: row.get("effective_to")?,
+    })
+}
+
+fn assignment_from_row(row: &Row) -> rusqlite::Result<PriceListAssignment> {
+    Ok(PriceListAssignment {
+        id: row.get("id")?,
+        price_list_id: row.get("price_list_id")?,
+        customer_id: row.get("customer_id")?,
+        category_id: row.get("category_id")?,
+        assignee_name: row.get("assignee_name")?,
+    })
+}
+
+fn parse_date(field: &str, value: &str) -> Result<NaiveDate, AppError> {
+    NaiveDate::parse_from_str(value.trim(), INVOICE_DATE_FORMAT)
+        .map_err(|_| AppError::validation(field, "Enter the date as YYYY-MM-DD"))
+}
+
+fn map_write_error(e: rusqlite::Error) -> AppError {
+    let message = e.to_string();
+    if message.contains("UNIQUE constraint failed: price_lists") {
+        return AppError::conflict("name", "A price list with this name already exists");
+    }
+    if message.contains("UNIQUE constraint failed: price_list_assignments") {
+        return AppError::Conflict {
+            field: None,
+            message: "This price list is already assigned there".to_string(),
+        };
+    }
+    AppError::from(e)
+}
+
+pub fn get_price_list_by_id(
+    conn: &Connection,
+    id: i64,
+    company_id: i64,
+) -> Result<Option<PriceList>, AppError> {
+    conn.query_row(
+        &format!("{} WHERE id = ?1 AND company_id = ?2", SELECT_PRICE_LIST),
+        params![id, company_id],
+        price_list_from_row,
+    )
+    .optional()
+    .map_err(AppError::from)
+}
+
+fn require_price_list(conn: &Connection, id: i64, company_id: i64) -> Result<PriceList, AppError> {
+    get_price_list_by_id(conn, id, company_id)?
+        .ok_or_else(|| AppError::not_found("Price list not found"))
+}
+
+// Rate of an item on the active lists assigned to `column`, preferring the latest start date
+fn assigned_rate(
+    conn: &Connection,
+    column: &str,
+    assignee_id: i64,
+    item_id: i64,
+    date: &str,
+) -> Result<Option<(i64, String, f64)>, AppError> {
+    conn.query_row(
+        &format!(
+            "SELECT l.id, l.name, r.rate
+             FROM price_list_assignments a
+             JOIN price_lists l ON l.id = a.price_list_id AND l.active = 1
+             JOIN price_list_items r ON r.price_list_id = l.id
+             WHERE a.{column} = ?1 AND r.item_id = ?2 AND r.effective_from <= ?3
+               AND COALESCE(r.effective_to, '{OPEN_END}') >= ?3
+             ORDER BY r.effective_from DESC, l.id DESC
+             LIMIT 1"
+        ),
+        params![assignee_id, item_id, date],
+        |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+    )
+    .optional()
+    .map_err(AppError::from)
+}
+
+// Customer lists win over category lists, which win over the item's own rate
+pub fn price_for(
+    conn: &Connection,
+    company_id: i64,
+    item_id: i64,
+    customer_id: Option<i64>,
+    date: &str,
+) -> Result<ResolvedPrice, AppError> {
+    let item = items::get_item_by_id(conn, item_id, company_id)?
+        .ok_or_else(|| AppError::not_found("Item not found"))?;
+    let customer = match customer_id {
+        Some(id) => Some(
+            customers::get_customer_by_id(conn, id, company_id)?
+                .ok_or_else(|| AppError::not_found("Customer not found"))?,
+        ),
+        None => None,
+    };
+
+    if let Some(customer) = customer {
+        let tiers = [
+            ("customer_id", customer.id.unwrap_or_default(), PriceSource::Customer),
+            ("category_id", customer.category_id, PriceSource::Category),
+        ];
+        for (column, assignee_id, source) in tiers {
+            if let Some((list_id, list_name, rate)) =
+                assigned_rate(conn, column, assignee_id, item_id, date)?
+            {
+                return Ok(ResolvedPrice {
+                    item_id,
+                    rate,
+                    source,
+                    price_list_id: Some(list_id),
+                    price_list_name: Some(list_name),
+                });
+            }
+        }
+    }
+
+    Ok(ResolvedPrice {
+        item_id,
+        rate: item.rate,
+        source: PriceSource::Item,
+        price_list_id: None,
+        price_list_name: None,
+    })
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn list_price_lists(
+    pool: State<'_, DbPool>,
+    company_id: i64,
+) -> Result<Vec<PriceList>, AppError> {
+    let conn = db::get_conn(&pool)?;
+    let mut stmt =
+        conn.prepare(&format!("{} WHERE company_id = ?1 ORDER BY name", SELECT_PRICE_LIST))?;
+    let lists =
+        stmt.query_map(params![company_id], price_list_from_row)?.collect::<Result<Vec<_>, _>>()?;
+    Ok(lists)
+}
+
+fn validate_name(name: &str) -> Result<String, AppError> {
+    let name = name.trim();
+    if name.is_empty() || name.len() > 100 {
+        return Err(AppError::validation("name", "Name must be 1 to 100 characters"));
+    }
+    Ok(name.to_string())
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn create_price_list(
+    pool: State<'_, DbPool>,
+    company_id: i64,
+    price_list: SavePriceList,
+) -> Result<PriceList, AppError> {
+    let name = validate_name(&price_list.name)?;
+    let conn = db::get_conn(&pool)?;
+    conn.execute(
+        "INSERT INTO price_lists (company_id, name, active) VALUES (?1, ?2, ?3)",
+        params![company_id, name, price_list.active],
+    )
+    .map_err(map_write_error)?;
+    require_price_list(&conn, conn.last_insert_rowid(), company_id)
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn update_price_list(
+    pool: State<'_, DbPool>,
+    id: i64,
+    company_id: i64,
+    price_list: SavePriceList,
+) -> Result<PriceList, AppError> {
+    let name = validate_name(&price_list.name)?;
+    let conn = db::get_conn(&pool)?;
+    let changed = conn
+        .execute(
+            "UPDATE price_lists SET name = ?1, active = ?2, updated_at = CURRENT_TIMESTAMP
+             WHERE id = ?3 AND company_id = ?4",
+            params![name, price_list.active, id, company_id],
+        )
+        .map_err(map_write_error)?;
+    if changed == 0 {
+        return Err(AppError::not_found("Price list not found"));
+    }
+    require_price_list(&conn, id, company_id)
+}
+
+// Removes the list with its rates and assignments
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn delete_price_list(
+    pool: State<'_, DbPool>,
+    id: i64,
+    company_id: i64,
+) -> Result<(), AppError> {
+    let conn = db::get_conn(&pool)?;
+    let deleted = conn.execute(
+        "DELETE FROM price_lists WHERE id = ?1 AND company_id = ?2",
+        params![id, company_id],
+    )?;
+    if deleted == 0 {
+        return Err(AppError::not_found("Price list not found"));
+    }
+    Ok(())
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn list_price_list_rates(
+    pool: State<'_, DbPool>,
+    price_list_id: i64,
+    company_id: i64,
+) -> Result<Vec<PriceListRate>, AppError> {
+    let conn = db::get_conn(&pool)?;
+    require_price_list(&conn, price_list_id, company_id)?;
+    let mut stmt = conn.prepare(&format!(
+        "{} WHERE r.price_list_id = ?1 ORDER BY i.name, r.effective_from",
+        SELECT_RATE
+    ))?;
+    let rates =
+        stmt.query_map(params![price_list_id], rate_from_row)?.collect::<Result<Vec<_>, _>>()?;
+    Ok(rates)
+}
+
+// Adds a rate, or replaces the one starting on the same date; periods may not overlap
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn set_price_list_rate(
+    pool: State<'_, DbPool>,
+    price_list_id: i64,
+    company_id: i64,
+    rate: SavePriceListRate,
+) -> Result<PriceListRate, AppError> {
+    if !rate.rate.is_finite() || rate.rate < 0.0 {
+        return Err(AppError::validation("rate", "Rate must be a non-negative number"));
+    }
+    let from = parse_date("effective_from", &rate.effective_from)?;
+    let to = match rate.effective_to.as_deref().map(str::trim).filter(|s| !s.is_empty()) {
+        Some(value) => Some(parse_date("effective_to", value)?),
+        None => None,
+    };
+    if to.is_some_and(|to| to < from) {
+        return Err(AppError::validation("effective_to", "End date is before the start date"));
+    }
+    let from = from.format(INVOICE_DATE_FORMAT).to_string();
+    let to = to.map(|to| to.format(INVOICE_DATE_FORMAT).to_string());
+
+    let conn = db::get_conn(&pool)?;
+    require_price_list(&conn, price_list_id, company_id)?;
+    if items::get_item_by_id(&conn, rate.item_id, company_id)?.is_none() {
+        return Err(AppError::not_found("Item not found"));
+    }
+    let overlapping: Option<String> = conn
+        .query_row(
+            &format!(
+                "SELECT effective_from FROM price_list_items
+                 WHERE price_list_id = ?1 AND item_id = ?2 AND effective_from != ?3
+                   AND effective_from <= COALESCE(?4, '{OPEN_END}')
+                   AND COALESCE(effective_to, '{OPEN_END}') >= ?3
+                 LIMIT 1"
+            ),
+            params![price_list_id, rate.item_id, from, to],
+            |row| row.get(0),
+        )
+        .optional()?;
+    if let Some(start) = overlapping {
+        return Err(AppError::validation(
+            "effective_from",
+            format!("This period overlaps the rate effective from {}", start),
+        ));
+    }
+
+    conn.execute(
+        "INSERT INTO price_list_items (price_list_id, item_id, rate, effective_from, effective_to)
+         VALUES (?1, ?2, ?3, ?4, ?5)
+         ON CONFLICT(price_list_id, item_id, effective_from)
+         DO UPDATE SET rate = excluded.rate, effective_to = excluded.effective_to",
+        params![price_list_id, rate.item_id, rate.rate, from, to],
+    )?;
+    Ok(conn.query_row(
+        &format!(
+            "{} WHERE r.price_list_id = ?1 AND r.item_id = ?2 AND r.effective_from = ?3",
+            SELECT_RATE
+        ),
+        params![price_list_id, rate.item_id, from],
+        rate_from_row,
+    )?)
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn delete_price_list_rate(
+    pool: State<'_, DbPool>,
+    id: i64,
+    company_id: i64,
+) -> Result<(), AppError> {
+    let conn = db::get_conn(&pool)?;
+    let deleted = conn.execute(
+        "DELETE FROM price_list_items WHERE id = ?1 AND price_list_id IN
+                 (SELECT id FROM price_lists WHERE company_id = ?2)",
+        params![id, company_id],
+    )?;
+    if deleted == 0 {
+        return Err(AppError::not_found("Price list rate not found"));
+    }
+    Ok(())
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn list_price_list_assignments(
+    pool: State<'_, DbPool>,
+    price_list_id: i64,
+    company_id: i64,
+) -> Result<Vec<PriceListAssignment>, AppError> {
+    let conn = db::get_conn(&pool)?;
+    require_price_list(&conn, price_list_id, company_id)?;
+    let mut stmt = conn.prepare(&format!(
+        "{} WHERE a.price_list_id = ?1 ORDER BY assignee_name",
+        SELECT_ASSIGNMENT
+    ))?;
+    let assignments = stmt
+        .query_map(params![price_list_id], assignment_from_row)?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(assignments)
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn assign_price_list(
+    pool: State<'_, DbPool>,
+    price_list_id: i64,
+    company_id: i64,
+    customer_id: Option<i64>,
+    category_id: Option<i64>,
+) -> Result<PriceListAssignment, AppError> {
+    let conn = db::get_conn(&pool)?;
+    require_price_list(&conn, price_list_id, company_id)?;
+    match (customer_id, category_id) {
+        (Some(id), None) => {
+            if customers::get_customer_by_id(&conn, id, company_id)?.is_none() {
+                return Err(AppError::not_found("Customer not found"));
+            }
+        }
+        (None, Some(id)) => {
+            if categories::get_category_by_id(&conn, id, company_id)?.is_none() {
+                return Err(AppError::not_found("Category not found"));
+            }
+        }
+        _ => {
+            return Err(AppError::validation(
+                "customer_id",
+                "Assign the price list to either a customer or a category",
+            ));
+        }
+    }
+    conn.execute(
+        "INSERT INTO price_list_assignments (price_list_id, customer_id, category_id)
+         VALUES (?1, ?2, ?3)",
+        params![price_list_id, customer_id, category_id],
+    )
+    .map_err(map_write_error)?;
+    Ok(conn.query_row(
+        &format!("{} WHERE a.id = ?1", SELECT_ASSIGNMENT),
+        params![conn.last_insert_rowid()],
+        assignment_from_row,
+    )?)
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn unassign_price_list(
+    pool: State<'_, DbPool>,
+    id: i64,
+    company_id: i64,
+) -> Result<(), AppError> {
+    let conn = db::get_conn(&pool)?;
+    let deleted = conn.execute(
+        "DELETE FROM price_list_assignments WHERE id = ?1 AND price_list_id IN
+                 (SELECT id FROM price_lists WHERE company_id = ?2)",
+        params![id, company_id],
+    )?;
+    if deleted == 0 {
+        return Err(AppError::not_found("Price list assignment not found"));
+    }
+    Ok(())
+}
+
+// Rate to prefill when an item is added to an invoice draft
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn resolve_price(
+    pool: State<'_, DbPool>,
+    company_id: i64,
+    item_id: i64,
+    customer_id: Option<i64>,
+    date: String,
+) -> Result<ResolvedPrice, AppError> {
+    let date = parse_date("date", &date)?.format(INVOICE_DATE_FORMAT).to_string();
+    let conn = db::get_conn(&pool)?;
+    price_for(&conn, company_id, item_id, customer_id, &date)
+}
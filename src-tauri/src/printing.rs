@@ -0,0 +1,248 @@
+use std::path::{Path, PathBuf};
+
+use chrono::Local;
+use serde::{Deserialize, Serialize};
+use tauri::State;
+use tokio::process::Command;
+
+use crate::customer_statements;
+use crate::db::{self, DbPool};
+use crate::error::AppError;
+use crate::invoice_pdf;
+use crate::invoice_templates;
+
+const MAX_COPIES: u32 = 10;
+// Labels required on the copies of a GST tax invoice for goods, in print order
+const COPY_LABELS: [&str; 3] = [
+    "Original for Recipient",
+    "Duplicate for Transporter",
+    "Triplicate for Supplier",
+];
+const EXTRA_COPY_LABEL: &str = "Extra Copy";
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Printer {
+    pub name: String,
+    pub is_default: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum PrintDocument {
+    Invoice {
+        invoice_id: i64,
+        company_id: i64,
+        // Default template when not given
+        #[serde(default)]
+        template_id: Option<i64>,
+    },
+    CustomerStatement {
+        company_id: i64,
+        customer_id: i64,
+        from: String,
+        to: String,
+    },
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PrintJob {
+    // None when the job went to the system default printer
+    pub printer: Option<String>,
+    pub copies: u32,
+    pub labels: Vec<String>,
+}
+
+// A rendered PDF and how many times to print it
+struct PrintFile {
+    bytes: Vec<u8>,
+    copies: u32,
+}
+
+fn copy_label(index: usize) -> &'static str {
+    COPY_LABELS.get(index).copied().unwrap_or(EXTRA_COPY_LABEL)
+}
+
+fn temp_path(index: usize) -> PathBuf {
+    let stamp = Local::now().format("%Y%m%d%H%M%S%f");
+    std::env::temp_dir().join(format!(
+        "sales_report_print_{}_{}_{}.pdf",
+        std::process::id(),
+        stamp,
+        index
+    ))
+}
+
+async fn run(command: &mut Command, program: &str) -> Result<String, String> {
+    let output = command
+        .output()
+        .await
+        .map_err(|e| format!("Failed to run {}: {}", program, e))?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(format!("{} failed: {}", program, stderr.trim()));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+// CUPS on Linux and macOS
+#[cfg(not(windows))]
+async fn system_printers() -> Result<Vec<Printer>, String> {
+    let names = run(Command::new("lpstat").arg("-e"), "lpstat").await?;
+    // Exits with an error when no default is set
+    let default = run(Command::new("lpstat").arg("-d"), "lpstat")
+        .await
+        .ok()
+        .and_then(|output| output.split_once(':').map(|(_, name)| name.trim().to_string()));
+    Ok(names
+        .lines()
+        .map(str::trim)
+        .filter(|name| !name.is_empty())
+        .map(|name| Printer {
+            name: name.to_string(),
+            is_default: default.as_deref() == Some(name),
+        })
+        .collect())
+}
+
+#[cfg(not(windows))]
+async fn send_to_printer(path: &Path, printer: Option<&str>, copies: u32) -> Result<(), String> {
+    let mut command = Command::new("lp");
+    if let Some(printer) = printer {
+        command.arg("-d").arg(printer);
+    }
+    command.arg("-n").arg(copies.to_string()).arg("--").arg(path);
+    run(&mut command, "lp").await.map(|_| ())
+}
+
+#[cfg(windows)]
+async fn system_printers() -> Result<Vec<Printer>, String> {
+    let script =
+        "Get-CimInstance Win32_Printer | ForEach-Object { \"$($_.Default)`t$($_.Name)\" }";
+    let output = run(
+        Command::new("powershell").args(["-NoProfile", "-Command", script]),
+        "PowerShell",
+    )
+    .await?;
+    Ok(output
+        .lines()
+        .filter_map(|line| line.trim().split_once('\t'))
+        .map(|(is_default, name)| Printer {
+            name: name.to_string(),
+            is_default: is_default.eq_ignore_ascii_case("true"),
+        })
+        .collect())
+}
+
+// Hands the file to the registered PDF viewer; the path and printer name go through the
+// environment so they are never parsed as script
+#[cfg(windows)]
+async fn send_to_printer(path: &Path, printer: Option<&str>, copies: u32) -> Result<(), String> {
+    let script = match printer {
+        Some(_) => {
+            "Start-Process -FilePath $env:SALES_REPORT_PRINT_FILE -Verb PrintTo \
+             -ArgumentList ('\"' + $env:SALES_REPORT_PRINTER + '\"') -Wait"
+        }
+        None => "Start-Process -FilePath $env:SALES_REPORT_PRINT_FILE -Verb Print -Wait",
+    };
+    for _ in 0..copies {
+        let mut command = Command::new("powershell");
+        command
+            .args(["-NoProfile", "-Command", script])
+            .env("SALES_REPORT_PRINT_FILE", path)
+            .env("SALES_REPORT_PRINTER", printer.unwrap_or(""));
+        run(&mut command, "PowerShell").await?;
+    }
+    Ok(())
+}
+
+// Invoices get one labelled file per copy; everything else is printed `copies` times
+fn render(
+    pool: &DbPool,
+    document: &PrintDocument,
+    copies: u32,
+    copy_labels: bool,
+) -> Result<(Vec<PrintFile>, Vec<String>), String> {
+    let conn = db::get_conn(pool)?;
+    match document {
+        PrintDocument::Invoice {
+            invoice_id,
+            company_id,
+            template_id,
+        } => {
+            let document = invoice_pdf::load_document(&conn, *invoice_id, *company_id)?;
+            let template = invoice_templates::resolve_template(&conn, *template_id)?;
+            if !copy_labels {
+                let (bytes, _) = invoice_pdf::render_bytes(&document, &template, None)?;
+                return Ok((vec![PrintFile { bytes, copies }], Vec::new()));
+            }
+            let labels = (0..copies as usize).map(copy_label).collect::<Vec<_>>();
+            let files = labels
+                .iter()
+                .map(|label| {
+                    let (bytes, _) = invoice_pdf::render_bytes(&document, &template, Some(*label))?;
+                    Ok(PrintFile { bytes, copies: 1 })
+                })
+                .collect::<Result<Vec<_>, String>>()?;
+            Ok((files, labels.iter().map(|label| label.to_string()).collect()))
+        }
+        PrintDocument::CustomerStatement {
+            company_id,
+            customer_id,
+            from,
+            to,
+        } => {
+            let statement =
+                customer_statements::build_statement(&conn, *company_id, *customer_id, from, to)?;
+            let (bytes, _) = customer_statements::render_statement_pdf(&conn, &statement)?;
+            Ok((vec![PrintFile { bytes, copies }], Vec::new()))
+        }
+    }
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn list_printers() -> Result<Vec<Printer>, AppError> {
+    Ok(system_printers().await?)
+}
+
+// Prints on `printer`, or the system default printer when it is not given. Invoice copies are
+// labelled Original/Duplicate/Triplicate unless `copy_labels` is false.
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn print_document(
+    pool: State<'_, DbPool>,
+    document: PrintDocument,
+    printer: Option<String>,
+    copies: Option<u32>,
+    copy_labels: Option<bool>,
+) -> Result<PrintJob, AppError> {
+    let copies = copies.unwrap_or(1);
+    if copies == 0 || copies > MAX_COPIES {
+        return Err(AppError::validation(
+            "copies",
+            format!("Print between 1 and {} copies", MAX_COPIES),
+        ));
+    }
+    let printer = printer.map(|name| name.trim().to_string()).filter(|name| !name.is_empty());
+    if let Some(name) = &printer {
+        if !system_printers().await?.iter().any(|known| &known.name == name) {
+            return Err(AppError::not_found(format!("Printer {} not found", name)));
+        }
+    }
+
+    let (files, labels) = render(&pool, &document, copies, copy_labels.unwrap_or(true))?;
+    for (index, file) in files.iter().enumerate() {
+        let path = temp_path(index);
+        std::fs::write(&path, &file.bytes)
+            .map_err(|e| format!("Failed to prepare the print file: {}", e))?;
+        let sent = send_to_printer(&path, printer.as_deref(), file.copies).await;
+        let _ = std::fs::remove_file(&path);
+        sent?;
+    }
+    tracing::info!(copies, printer = printer.as_deref().unwrap_or("default"), "document printed");
+    Ok(PrintJob {
+        printer,
+        copies,
+        labels,
+    })
+}
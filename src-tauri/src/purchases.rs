@@ -0,0 +1,329 @@
+use chrono::NaiveDate;
+use rusqlite::{params, Connection, OptionalExtension, Row};
+use serde::{Deserialize, Serialize};
+use tauri::State;
+
+use crate::db::{self, DbPool};
+use crate::error::AppError;
+use crate::financial_years;
+use crate::gstin;
+use crate::invoices::{round2, INVOICE_DATE_FORMAT};
+use crate::states;
+
+// Purchase data model: a supplier invoice as recorded in the books, kept just detailed enough
+// to be matched against GSTR-2B
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Purchase {
+    pub id: Option<i64>,
+    pub company_id: i64,
+    pub supplier_gstin: String,
+    pub supplier_name: String,
+    pub invoice_number: String,
+    pub invoice_date: String,
+    pub place_of_supply: String,
+    pub reverse_charge: bool,
+    pub taxable_value: f64,
+    pub igst_amount: f64,
+    pub cgst_amount: f64,
+    pub sgst_amount: f64,
+    pub cess_amount: f64,
+    pub total_amount: f64,
+    pub notes: Option<String>,
+    pub created_at: Option<String>,
+    pub updated_at: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SavePurchase {
+    pub supplier_gstin: String,
+    pub supplier_name: String,
+    pub invoice_number: String,
+    pub invoice_date: String,
+    pub place_of_supply: String,
+    #[serde(default)]
+    pub reverse_charge: bool,
+    pub taxable_value: f64,
+    #[serde(default)]
+    pub igst_amount: f64,
+    #[serde(default)]
+    pub cgst_amount: f64,
+    #[serde(default)]
+    pub sgst_amount: f64,
+    #[serde(default)]
+    pub cess_amount: f64,
+    // Derived from taxable value plus taxes when not given
+    pub total_amount: Option<f64>,
+    pub notes: Option<String>,
+}
+
+const SELECT_PURCHASE: &str = "
+    SELECT id, company_id, supplier_gstin, supplier_name, invoice_number, invoice_date,
+           place_of_supply, reverse_charge, taxable_value, igst_amount, cgst_amount, sgst_amount,
+           cess_amount, total_amount, notes, created_at, updated_at
+    FROM purchases";
+
+fn purchase_from_row(row: &Row) -> rusqlite::Result<Purchase> {
+    Ok(Purchase {
+        id: row.get("id")?,
+        company_id: row.get("company_id")?,
+        supplier_gstin: row.get("supplier_gstin")?,
+        supplier_name: row.get("supplier_name")?,
+        invoice_number: row.get("invoice_number")?,
+        invoice_date: row.get("invoice_date")?,
+        place_of_supply: row.get("place_of_supply")?,
+        reverse_charge: row.get("reverse_charge")?,
+        taxable_value: row.get("taxable_value")?,
+        igst_amount: row.get("igst_amount")?,
+        cgst_amount: row.get("cgst_amount")?,
+        sgst_amount: row.get("sgst_amount")?,
+        cess_amount: row.get("cess_amount")?,
+        total_amount: row.get("total_amount")?,
+        notes: row.get("notes")?,
+        created_at: row.get("created_at")?,
+        updated_at: row.get("updated_at")?,
+    })
+}
+
+pub fn get_purchase_by_id(
+    conn: &Connection,
+    id: i64,
+    company_id: i64,
+) -> Result<Option<Purchase>, AppError> {
+    conn.query_row(
+        &format!("{} WHERE id = ?1 AND company_id = ?2", SELECT_PURCHASE),
+        params![id, company_id],
+        purchase_from_row,
+    )
+    .optional()
+    .map_err(AppError::from)
+}
+
+// Purchases dated within the range, or all of them when no range is given
+pub fn get_purchases(
+    conn: &Connection,
+    company_id: i64,
+    from: Option<&str>,
+    to: Option<&str>,
+) -> Result<Vec<Purchase>, AppError> {
+    let mut stmt = conn.prepare(&format!(
+        "{} WHERE company_id = ?1
+               AND (?2 IS NULL OR invoice_date >= ?2) AND (?3 IS NULL OR invoice_date <= ?3)
+             ORDER BY invoice_date, supplier_gstin, invoice_number",
+        SELECT_PURCHASE
+    ))?;
+    let purchases = stmt
+        .query_map(params![company_id, from, to], purchase_from_row)?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(purchases)
+}
+
+fn normalize_purchase(purchase: &mut SavePurchase) {
+    purchase.supplier_gstin = purchase.supplier_gstin.trim().to_uppercase();
+    purchase.supplier_name = purchase.supplier_name.trim().to_string();
+    purchase.invoice_number = purchase.invoice_number.trim().to_string();
+    purchase.invoice_date = purchase.invoice_date.trim().to_string();
+    purchase.place_of_supply = purchase.place_of_supply.trim().to_string();
+}
+
+fn validate_purchase(purchase: &SavePurchase) -> Result<(), AppError> {
+    gstin::check_gstin(&purchase.supplier_gstin).map_err(|e| {
+        AppError::validation("supplier_gstin", format!("Supplier GSTIN is invalid: {}", e))
+    })?;
+    if purchase.supplier_name.is_empty() || purchase.supplier_name.len() > 200 {
+        return Err(AppError::validation(
+            "supplier_name",
+            "Supplier name is required and must be 200 characters or less",
+        ));
+    }
+    if purchase.invoice_number.is_empty() || purchase.invoice_number.len() > 16 {
+        return Err(AppError::validation(
+            "invoice_number",
+            "Invoice number is required and must be 16 characters or less",
+        ));
+    }
+    if NaiveDate::parse_from_str(&purchase.invoice_date, INVOICE_DATE_FORMAT).is_err() {
+        return Err(AppError::validation(
+            "invoice_date",
+            "Invoice date must be a valid date in YYYY-MM-DD format",
+        ));
+    }
+    if !states::is_known_state_code(&purchase.place_of_supply) {
+        return Err(AppError::validation(
+            "place_of_supply",
+            "Place of supply must be a valid state code",
+        ));
+    }
+    let amounts = [
+        ("taxable_value", purchase.taxable_value),
+        ("igst_amount", purchase.igst_amount),
+        ("cgst_amount", purchase.cgst_amount),
+        ("sgst_amount", purchase.sgst_amount),
+        ("cess_amount", purchase.cess_amount),
+    ];
+    for (field, amount) in amounts {
+        if !amount.is_finite() || amount < 0.0 {
+            return Err(AppError::validation(field, "Amounts cannot be negative"));
+        }
+    }
+    if purchase.igst_amount > 0.0 && (purchase.cgst_amount > 0.0 || purchase.sgst_amount > 0.0) {
+        return Err(AppError::validation(
+            "igst_amount",
+            "A purchase carries either IGST or CGST and SGST, not both",
+        ));
+    }
+    if let Some(total) = purchase.total_amount {
+        if !total.is_finite() || total < 0.0 {
+            return Err(AppError::validation("total_amount", "Total amount cannot be negative"));
+        }
+    }
+    if let Some(notes) = &purchase.notes {
+        if notes.len() > 1000 {
+            return Err(AppError::validation("notes", "Notes must be 1000 characters or less"));
+        }
+    }
+    Ok(())
+}
+
+fn total_of(purchase: &SavePurchase) -> f64 {
+    round2(purchase.total_amount.unwrap_or(
+        purchase.taxable_value
+            + purchase.igst_amount
+            + purchase.cgst_amount
+            + purchase.sgst_amount
+            + purchase.cess_amount,
+    ))
+}
+
+fn ensure_unique(
+    conn: &Connection,
+    company_id: i64,
+    purchase: &SavePurchase,
+    exclude_id: Option<i64>,
+) -> Result<(), AppError> {
+    let exists: bool = conn.query_row(
+        "SELECT EXISTS(SELECT 1 FROM purchases
+                       WHERE company_id = ?1 AND supplier_gstin = ?2
+                         AND UPPER(invoice_number) = UPPER(?3) AND (?4 IS NULL OR id != ?4))",
+        params![company_id, purchase.supplier_gstin, purchase.invoice_number, exclude_id],
+        |row| row.get(0),
+    )?;
+    if exists {
+        return Err(AppError::conflict(
+            "invoice_number",
+            format!(
+                "Invoice {} from {} is already recorded",
+                purchase.invoice_number, purchase.supplier_gstin
+            ),
+        ));
+    }
+    Ok(())
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn create_purchase(
+    pool: State<'_, DbPool>,
+    company_id: i64,
+    mut purchase: SavePurchase,
+) -> Result<Purchase, AppError> {
+    normalize_purchase(&mut purchase);
+    validate_purchase(&purchase)?;
+
+    let conn = db::get_conn(&pool)?;
+    financial_years::ensure_period_open(&conn, company_id, &purchase.invoice_date)?;
+    ensure_unique(&conn, company_id, &purchase, None)?;
+    conn.execute(
+        "INSERT INTO purchases (company_id, supplier_gstin, supplier_name, invoice_number, invoice_date, place_of_supply, reverse_charge, taxable_value, igst_amount, cgst_amount, sgst_amount, cess_amount, total_amount, notes)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14)",
+        params![
+            company_id,
+            purchase.supplier_gstin,
+            purchase.supplier_name,
+            purchase.invoice_number,
+            purchase.invoice_date,
+            purchase.place_of_supply,
+            purchase.reverse_charge,
+            round2(purchase.taxable_value),
+            round2(purchase.igst_amount),
+            round2(purchase.cgst_amount),
+            round2(purchase.sgst_amount),
+            round2(purchase.cess_amount),
+            total_of(&purchase),
+            purchase.notes
+        ],
+    )?;
+    get_purchase_by_id(&conn, conn.last_insert_rowid(), company_id)?
+        .ok_or_else(|| AppError::not_found("Purchase not found after creation"))
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn update_purchase(
+    pool: State<'_, DbPool>,
+    id: i64,
+    company_id: i64,
+    mut purchase: SavePurchase,
+) -> Result<Purchase, AppError> {
+    normalize_purchase(&mut purchase);
+    validate_purchase(&purchase)?;
+
+    let conn = db::get_conn(&pool)?;
+    let existing = get_purchase_by_id(&conn, id, company_id)?
+        .ok_or_else(|| AppError::not_found("Purchase not found"))?;
+    financial_years::ensure_period_open(&conn, company_id, &existing.invoice_date)?;
+    financial_years::ensure_period_open(&conn, company_id, &purchase.invoice_date)?;
+    ensure_unique(&conn, company_id, &purchase, Some(id))?;
+    conn.execute(
+        "UPDATE purchases SET supplier_gstin = ?1, supplier_name = ?2, invoice_number = ?3, invoice_date = ?4, place_of_supply = ?5, reverse_charge = ?6, taxable_value = ?7, igst_amount = ?8, cgst_amount = ?9, sgst_amount = ?10, cess_amount = ?11, total_amount = ?12, notes = ?13, updated_at = CURRENT_TIMESTAMP
+         WHERE id = ?14 AND company_id = ?15",
+        params![
+            purchase.supplier_gstin,
+            purchase.supplier_name,
+            purchase.invoice_number,
+            purchase.invoice_date,
+            purchase.place_of_supply,
+            purchase.reverse_charge,
+            round2(purchase.taxable_value),
+            round2(purchase.igst_amount),
+            round2(purchase.cgst_amount),
+            round2(purchase.sgst_amount),
+            round2(purchase.cess_amount),
+            total_of(&purchase),
+            purchase.notes,
+            id,
+            company_id
+        ],
+    )?;
+    get_purchase_by_id(&conn, id, company_id)?
+        .ok_or_else(|| AppError::not_found("Purchase not found after update"))
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn list_purchases(
+    pool: State<'_, DbPool>,
+    company_id: i64,
+    from: Option<String>,
+    to: Option<String>,
+) -> Result<Vec<Purchase>, AppError> {
+    let conn = db::get_conn(&pool)?;
+    get_purchases(&conn, company_id, from.as_deref(), to.as_deref())
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn delete_purchase(
+    pool: State<'_, DbPool>,
+    id: i64,
+    company_id: i64,
+) -> Result<(), AppError> {
+    let conn = db::get_conn(&pool)?;
+    let purchase = get_purchase_by_id(&conn, id, company_id)?
+        .ok_or_else(|| AppError::not_found("Purchase not found"))?;
+    financial_years::ensure_period_open(&conn, company_id, &purchase.invoice_date)?;
+    conn.execute(
+        "DELETE FROM purchases WHERE id = ?1 AND company_id = ?2",
+        params![id, company_id],
+    )?;
+    Ok(())
+}
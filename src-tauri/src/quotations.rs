@@ -0,0 +1,956 @@
+use chrono::{Local, NaiveDate};
+use rusqlite::{params, Connection, OptionalExtension, Row};
+use serde::{Deserialize, Serialize};
+use tauri::State;
+
+use crate::audit::{self, AuditAction};
+use crate::companies;
+use crate::customers;
+use crate::db::{self, DbPool};
+use crate::error::AppError;
+use crate::invoice_pdf::{
+    column, display_date, format_amount, party_lines, state_label, wrap, write_output, Align,
+    Column, PdfWriter, RenderedPdf,
+};
+use crate::invoices::{
+    self, round2, CreateInvoice, InvoiceLineInput, InvoiceStatus, SavedInvoice,
+    INVOICE_DATE_FORMAT,
+};
+use crate::items;
+use crate::numbering::{self, DocumentType};
+use crate::place_of_supply::{self, SupplyKind};
+use crate::rounding;
+use crate::tax::{self, InvoiceDiscount, TaxLineInput};
+use crate::units;
+
+// Quotations are valid for this long unless a date is given
+const DEFAULT_VALIDITY_DAYS: i64 = 30;
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum QuotationStatus {
+    Draft,
+    Sent,
+    Accepted,
+    Expired,
+}
+
+impl QuotationStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            QuotationStatus::Draft => "draft",
+            QuotationStatus::Sent => "sent",
+            QuotationStatus::Accepted => "accepted",
+            QuotationStatus::Expired => "expired",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "draft" => Some(QuotationStatus::Draft),
+            "sent" => Some(QuotationStatus::Sent),
+            "accepted" => Some(QuotationStatus::Accepted),
+            "expired" => Some(QuotationStatus::Expired),
+            _ => None,
+        }
+    }
+}
+
+// Quotation (proforma invoice) data model. `invoice_id` is set once it has been converted,
+// after which the quotation is locked.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Quotation {
+    pub id: i64,
+    pub company_id: i64,
+    pub quotation_number: String,
+    pub quotation_date: String,
+    pub valid_until: String,
+    pub customer_id: i64,
+    pub customer_name: String,
+    pub place_of_supply: String,
+    pub supply_kind: SupplyKind,
+    pub discount_percent: f64,
+    pub discount_amount: f64,
+    pub taxable_value: f64,
+    pub cgst_amount: f64,
+    pub sgst_amount: f64,
+    pub igst_amount: f64,
+    pub round_off: f64,
+    pub total_amount: f64,
+    pub status: QuotationStatus,
+    pub notes: Option<String>,
+    pub invoice_id: Option<i64>,
+    pub created_at: Option<String>,
+    pub updated_at: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct QuotationLine {
+    pub id: i64,
+    pub quotation_id: i64,
+    pub line_no: i64,
+    pub item_id: Option<i64>,
+    pub unit_id: Option<i64>,
+    pub description: String,
+    pub hsn_code: String,
+    pub quantity: f64,
+    pub rate: f64,
+    pub discount_percent: f64,
+    pub discount: f64,
+    pub invoice_discount: f64,
+    pub taxable_value: f64,
+    pub gst_rate: f64,
+    pub cgst_amount: f64,
+    pub sgst_amount: f64,
+    pub igst_amount: f64,
+}
+
+// Amounts are worked out by the backend from quantity, rate, discounts and GST rate
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct QuotationLineInput {
+    #[serde(default)]
+    pub item_id: Option<i64>,
+    #[serde(default)]
+    pub unit_id: Option<i64>,
+    pub description: String,
+    pub hsn_code: String,
+    pub quantity: f64,
+    pub rate: f64,
+    #[serde(default)]
+    pub discount_percent: f64,
+    #[serde(default)]
+    pub discount: f64,
+    pub gst_rate: f64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SaveQuotation {
+    // Left empty to take the next number from the quotation sequence
+    #[serde(default)]
+    pub quotation_number: String,
+    pub quotation_date: String,
+    #[serde(default)]
+    pub valid_until: Option<String>,
+    pub customer_id: i64,
+    // Left empty to use the customer's state
+    #[serde(default)]
+    pub place_of_supply: String,
+    #[serde(default)]
+    pub supply_kind: SupplyKind,
+    #[serde(default)]
+    pub discount_percent: f64,
+    #[serde(default)]
+    pub discount_amount: f64,
+    pub notes: Option<String>,
+    pub lines: Vec<QuotationLineInput>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct QuotationWithLines {
+    #[serde(flatten)]
+    pub quotation: Quotation,
+    pub lines: Vec<QuotationLine>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ConvertedQuotation {
+    pub quotation: Quotation,
+    pub invoice: SavedInvoice,
+}
+
+const SELECT_QUOTATION: &str = "
+    SELECT q.id, q.company_id, q.quotation_number, q.quotation_date, q.valid_until,
+           q.customer_id, COALESCE(c.report_customer, '') AS customer_name, q.place_of_supply,
+           q.supply_kind, q.discount_percent, q.discount_amount, q.taxable_value, q.cgst_amount,
+           q.sgst_amount, q.igst_amount, q.round_off, q.total_amount, q.status, q.notes,
+           q.invoice_id, q.created_at, q.updated_at
+    FROM quotations q
+    LEFT JOIN customers c ON c.id = q.customer_id";
+
+const SELECT_QUOTATION_LINE: &str = "
+    SELECT id, quotation_id, line_no, item_id, unit_id, description, hsn_code, quantity, rate,
+           discount_percent, discount, invoice_discount, taxable_value, gst_rate, cgst_amount,
+           sgst_amount, igst_amount
+    FROM quotation_lines";
+
+const QUOTATION_COLUMNS: &[Column] = &[
+    column("#", 0.04, Align::Left),
+    column("Description", 0.30, Align::Left),
+    column("HSN/SAC", 0.09, Align::Left),
+    column("Qty", 0.08, Align::Right),
+    column("Rate", 0.11, Align::Right),
+    column("Disc.", 0.08, Align::Right),
+    column("Taxable", 0.12, Align::Right),
+    column("GST %", 0.06, Align::Right),
+    column("Tax", 0.12, Align::Right),
+];
+
+fn quotation_from_row(row: &Row) -> rusqlite::Result<Quotation> {
+    let supply_kind: String = row.get("supply_kind")?;
+    let status: String = row.get("status")?;
+    Ok(Quotation {
+        id: row.get("id")?,
+        company_id: row.get("company_id")?,
+        quotation_number: row.get("quotation_number")?,
+        quotation_date: row.get("quotation_date")?,
+        valid_until: row.get("valid_until")?,
+        customer_id: row.get("customer_id")?,
+        customer_name: row.get("customer_name")?,
+        place_of_supply: row.get("place_of_supply")?,
+        supply_kind: SupplyKind::parse(&supply_kind).unwrap_or_default(),
+        discount_percent: row.get("discount_percent")?,
+        discount_amount: row.get("discount_amount")?,
+        taxable_value: row.get("taxable_value")?,
+        cgst_amount: row.get("cgst_amount")?,
+        sgst_amount: row.get("sgst_amount")?,
+        igst_amount: row.get("igst_amount")?,
+        round_off: row.get("round_off")?,
+        total_amount: row.get("total_amount")?,
+        status: QuotationStatus::parse(&status).unwrap_or(QuotationStatus::Draft),
+        notes: row.get("notes")?,
+        invoice_id: row.get("invoice_id")?,
+        created_at: row.get("created_at")?,
+        updated_at: row.get("updated_at")?,
+    })
+}
+
+fn quotation_line_from_row(row: &Row) -> rusqlite::Result<QuotationLine> {
+    Ok(QuotationLine {
+        id: row.get("id")?,
+        quotation_id: row.get("quotation_id")?,
+        line_no: row.get("line_no")?,
+        item_id: row.get("item_id")?,
+        unit_id: row.get("unit_id")?,
+        description: row.get("description")?,
+        hsn_code: row.get("hsn_code")?,
+        quantity: row.get("quantity")?,
+        rate: row.get("rate")?,
+        discount_percent: row.get("discount_percent")?,
+        discount: row.get("discount")?,
+        invoice_discount: row.get("invoice_discount")?,
+        taxable_value: row.get("taxable_value")?,
+        gst_rate: row.get("gst_rate")?,
+        cgst_amount: row.get("cgst_amount")?,
+        sgst_amount: row.get("sgst_amount")?,
+        igst_amount: row.get("igst_amount")?,
+    })
+}
+
+fn map_write_error(e: rusqlite::Error) -> AppError {
+    let message = e.to_string();
+    if message.contains("UNIQUE constraint failed: quotations") {
+        return AppError::conflict(
+            "quotation_number",
+            "A quotation with this number already exists",
+        );
+    }
+    AppError::from(e)
+}
+
+fn today() -> String {
+    Local::now().date_naive().format(INVOICE_DATE_FORMAT).to_string()
+}
+
+fn parse_date(field: &str, value: &str) -> Result<NaiveDate, AppError> {
+    NaiveDate::parse_from_str(value.trim(), INVOICE_DATE_FORMAT)
+        .map_err(|_| AppError::validation(field, "Enter the date as YYYY-MM-DD"))
+}
+
+// Draft and sent quotations lapse once their validity date has passed
+fn expire_quotations(conn: &Connection, company_id: i64) -> Result<(), String> {
+    conn.execute(
+        "UPDATE quotations SET status = 'expired', updated_at = CURRENT_TIMESTAMP
+         WHERE company_id = ?1 AND status IN ('draft', 'sent') AND valid_until < ?2",
+        params![company_id, today()],
+    )
+    .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+pub fn get_quotation_by_id(
+    conn: &Connection,
+    id: i64,
+    company_id: i64,
+) -> Result<Option<Quotation>, AppError> {
+    conn.query_row(
+        &format!("{} WHERE q.id = ?1 AND q.company_id = ?2", SELECT_QUOTATION),
+        params![id, company_id],
+        quotation_from_row,
+    )
+    .optional()
+    .map_err(AppError::from)
+}
+
+pub fn get_quotation_lines(
+    conn: &Connection,
+    quotation_id: i64,
+) -> Result<Vec<QuotationLine>, AppError> {
+    let mut stmt = conn
+        .prepare(&format!("{} WHERE quotation_id = ?1 ORDER BY line_no", SELECT_QUOTATION_LINE))?;
+    let lines = stmt
+        .query_map(params![quotation_id], quotation_line_from_row)?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(lines)
+}
+
+fn with_lines(conn: &Connection, id: i64, company_id: i64) -> Result<QuotationWithLines, AppError> {
+    let quotation = get_quotation_by_id(conn, id, company_id)?
+        .ok_or_else(|| AppError::not_found("Quotation not found"))?;
+    let lines = get_quotation_lines(conn, id)?;
+    Ok(QuotationWithLines { quotation, lines })
+}
+
+// A converted quotation is a record of what was invoiced and can no longer change
+fn require_unlocked(quotation: &Quotation) -> Result<(), AppError> {
+    if quotation.invoice_id.is_some() {
+        return Err(AppError::conflict(
+            "id",
+            "This quotation has been converted to an invoice and is locked",
+        ));
+    }
+    Ok(())
+}
+
+fn validate_line(index: usize, line: &QuotationLineInput) -> Result<(), AppError> {
+    let line_no = index + 1;
+    if line.description.trim().is_empty() || line.description.len() > 500 {
+        return Err(AppError::validation(
+            "lines",
+            format!("Line {}: description must be 1 to 500 characters", line_no),
+        ));
+    }
+    let hsn_code = line.hsn_code.trim();
+    if !(4..=8).contains(&hsn_code.len()) || !hsn_code.chars().all(|c| c.is_ascii_digit()) {
+        return Err(AppError::validation(
+            "lines",
+            format!("Line {}: HSN/SAC code must be 4 to 8 digits", line_no),
+        ));
+    }
+    if !line.quantity.is_finite() || line.quantity <= 0.0 {
+        return Err(AppError::validation(
+            "lines",
+            format!("Line {}: quantity must be greater than zero", line_no),
+        ));
+    }
+    if !line.gst_rate.is_finite() || !(0.0..=100.0).contains(&line.gst_rate) {
+        return Err(AppError::validation(
+            "lines",
+            format!("Line {}: GST rate must be between 0 and 100", line_no),
+        ));
+    }
+    Ok(())
+}
+
+// Invoice-shaped lines, which the unit/item checks and conversion both take
+fn invoice_lines(lines: &[QuotationLineInput]) -> Vec<InvoiceLineInput> {
+    lines
+        .iter()
+        .map(|line| InvoiceLineInput {
+            description: line.description.trim().to_string(),
+            hsn_code: line.hsn_code.trim().to_string(),
+            quantity: line.quantity,
+            rate: line.rate,
+            discount_percent: line.discount_percent,
+            discount: line.discount,
+            invoice_discount: 0.0,
+            taxable_value: 0.0,
+            gst_rate: line.gst_rate,
+            cgst_amount: 0.0,
+            sgst_amount: 0.0,
+            igst_amount: 0.0,
+            cess_rate: 0.0,
+            cess_amount: 0.0,
+            item_id: line.item_id,
+            unit_id: line.unit_id,
+            sales_order_line_id: None,
+            batch_id: None,
+            serial_numbers: Vec::new(),
+        })
+        .collect()
+}
+
+// Validates the quotation and works out its place of supply, validity and amounts
+fn prepare(
+    conn: &Connection,
+    company_id: i64,
+    quotation: &SaveQuotation,
+) -> Result<(Quotation, Vec<QuotationLine>), AppError> {
+    let date = parse_date("quotation_date", &quotation.quotation_date)?;
+    let valid_until = match quotation.valid_until.as_deref().filter(|v| !v.trim().is_empty()) {
+        Some(value) => parse_date("valid_until", value)?,
+        None => date + chrono::Duration::days(DEFAULT_VALIDITY_DAYS),
+    };
+    if valid_until < date {
+        return Err(AppError::validation(
+            "valid_until",
+            "Validity date cannot be before the quotation date",
+        ));
+    }
+    if quotation.lines.is_empty() {
+        return Err(AppError::validation("lines", "Add at least one line to the quotation"));
+    }
+    if let Some(notes) = &quotation.notes {
+        if notes.len() > 1000 {
+            return Err(AppError::validation("notes", "Notes must be 1000 characters or less"));
+        }
+    }
+    for (index, line) in quotation.lines.iter().enumerate() {
+        validate_line(index, line)?;
+    }
+    let as_invoice_lines = invoice_lines(&quotation.lines);
+    units::validate_line_units(conn, &as_invoice_lines)?;
+    items::validate_line_items(conn, company_id, &as_invoice_lines)?;
+
+    let customer = customers::get_customer_by_id(conn, quotation.customer_id, company_id)?
+        .ok_or_else(|| AppError::not_found("Customer not found"))?;
+    let supply_kind = place_of_supply::effective_kind(&customer, quotation.supply_kind);
+    let place = match quotation.place_of_supply.trim() {
+        "" => place_of_supply::default_place_of_supply(&customer, supply_kind),
+        place => place.to_string(),
+    };
+    let regime = place_of_supply::regime_for(conn, company_id, &place, supply_kind)?;
+    let inputs: Vec<TaxLineInput> = quotation
+        .lines
+        .iter()
+        .map(|line| TaxLineInput {
+            quantity: line.quantity,
+            rate: line.rate,
+            discount: line.discount,
+            discount_percent: line.discount_percent,
+            gst_rate: line.gst_rate,
+            cess_rate: 0.0,
+        })
+        .collect();
+    let discount = InvoiceDiscount {
+        percent: quotation.discount_percent,
+        amount: quotation.discount_amount,
+    };
+    let totals = tax::compute_totals(&inputs, discount, regime, rounding::load_mode(conn)?)?;
+
+    let lines = quotation
+        .lines
+        .iter()
+        .zip(&totals.lines)
+        .enumerate()
+        .map(|(index, (line, computed))| QuotationLine {
+            id: 0,
+            quotation_id: 0,
+            line_no: index as i64 + 1,
+            item_id: line.item_id,
+            unit_id: line.unit_id,
+            description: line.description.trim().to_string(),
+            hsn_code: line.hsn_code.trim().to_string(),
+            quantity: line.quantity,
+            rate: line.rate,
+            discount_percent: line.discount_percent,
+            discount: round2(line.discount),
+            invoice_discount: computed.invoice_discount,
+            taxable_value: computed.taxable_value,
+            gst_rate: line.gst_rate,
+            cgst_amount: computed.cgst_amount,
+            sgst_amount: computed.sgst_amount,
+            igst_amount: computed.igst_amount,
+        })
+        .collect();
+    let prepared = Quotation {
+        id: 0,
+        company_id,
+        quotation_number: quotation.quotation_number.trim().to_string(),
+        quotation_date: date.format(INVOICE_DATE_FORMAT).to_string(),
+        valid_until: valid_until.format(INVOICE_DATE_FORMAT).to_string(),
+        customer_id: customer.id.unwrap_or_default(),
+        customer_name: customer.report_customer,
+        place_of_supply: place,
+        supply_kind,
+        discount_percent: quotation.discount_percent,
+        discount_amount: round2(quotation.discount_amount),
+        taxable_value: totals.taxable_value,
+        cgst_amount: totals.cgst_amount,
+        sgst_amount: totals.sgst_amount,
+        igst_amount: totals.igst_amount,
+        round_off: totals.round_off,
+        total_amount: totals.total_amount,
+        status: QuotationStatus::Draft,
+        notes: quotation.notes.clone(),
+        invoice_id: None,
+        created_at: None,
+        updated_at: None,
+    };
+    Ok((prepared, lines))
+}
+
+fn replace_lines(
+    conn: &Connection,
+    quotation_id: i64,
+    lines: &[QuotationLine],
+) -> Result<(), AppError> {
+    conn.execute("DELETE FROM quotation_lines WHERE quotation_id = ?1", params![quotation_id])?;
+    let mut stmt = conn.prepare(
+        "INSERT INTO quotation_lines (quotation_id, line_no, item_id, unit_id, description,
+                                          hsn_code, quantity, rate, discount_percent, discount,
+                                          invoice_discount, taxable_value, gst_rate, cgst_amount,
+                                          sgst_amount, igst_amount)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16)",
+    )?;
+    for line in lines {
+        stmt.execute(params![
+            quotation_id,
+            line.line_no,
+            line.item_id,
+            line.unit_id,
+            line.description,
+            line.hsn_code,
+            line.quantity,
+            line.rate,
+            line.discount_percent,
+            line.discount,
+            line.invoice_discount,
+            line.taxable_value,
+            line.gst_rate,
+            line.cgst_amount,
+            line.sgst_amount,
+            line.igst_amount
+        ])?;
+    }
+    Ok(())
+}
+
+pub(crate) fn render_quotation_pdf(
+    conn: &Connection,
+    document: &QuotationWithLines,
+) -> Result<(Vec<u8>, usize), AppError> {
+    let quotation = &document.quotation;
+    let company = companies::get_company_by_id(conn, quotation.company_id)?
+        .ok_or_else(|| AppError::not_found("Company not found"))?;
+    let customer =
+        customers::get_customer_by_id(conn, quotation.customer_id, quotation.company_id)?
+            .ok_or_else(|| AppError::not_found("Customer not found"))?;
+
+    let title = format!("Quotation {}", quotation.quotation_number);
+    let mut pdf = PdfWriter::new(&title, 210.0, 297.0, 1.0, 12.0)?;
+    let left = pdf.margin;
+    let right = pdf.width - pdf.margin;
+    let description_chars = pdf.chars_fitting(0.30 * pdf.content_width() - 2.0, 8.0);
+
+    pdf.text_centered("QUOTATION", 14.0, true);
+    pdf.advance(pdf.line_height(14.0) * 1.2);
+
+    let seller = party_lines(
+        &company.company_name,
+        company.address.as_deref(),
+        company.city.as_deref(),
+        company.pincode.as_deref(),
+        &company.gst_no,
+        &state_label(conn, &company.state_code)?,
+    );
+    let buyer = party_lines(
+        &customer.report_customer,
+        customer.address.as_deref(),
+        customer.city.as_deref(),
+        customer.pincode.as_deref(),
+        &customer.gst_no,
+        &state_label(conn, &customer.state_code)?,
+    );
+    let top = pdf.y;
+    for (line, bold) in &seller {
+        pdf.text(line, if *bold { 11.0 } else { 8.5 }, left, *bold);
+        pdf.advance(pdf.line_height(if *bold { 11.0 } else { 8.5 }));
+    }
+    let seller_bottom = pdf.y;
+    pdf.y = top;
+    let buyer_x = left + pdf.content_width() * 0.55;
+    for (line, bold) in &buyer {
+        pdf.text(line, if *bold { 11.0 } else { 8.5 }, buyer_x, *bold);
+        pdf.advance(pdf.line_height(if *bold { 11.0 } else { 8.5 }));
+    }
+    pdf.y = pdf.y.min(seller_bottom);
+
+    pdf.advance(pdf.line_height(8.5) * 0.5);
+    let details = [
+        format!("Quotation No: {}", quotation.quotation_number),
+        format!("Date: {}", display_date(&quotation.quotation_date)),
+        format!("Valid Until: {}", display_date(&quotation.valid_until)),
+        format!("Place of Supply: {}", state_label(conn, &quotation.place_of_supply)?),
+    ];
+    for detail in &details {
+        pdf.text(detail, 9.0, left, false);
+        pdf.advance(pdf.line_height(9.0));
+    }
+    pdf.advance(pdf.line_height(9.0) * 0.5);
+
+    pdf.table_header(QUOTATION_COLUMNS, 8.0);
+    for line in &document.lines {
+        let description = wrap(&line.description, description_chars);
+        let needed = description.len().max(1) as f32 * pdf.line_height(8.0);
+        if pdf.ensure_space(needed) {
+            pdf.table_header(QUOTATION_COLUMNS, 8.0);
+        }
+        let discount = round2(line.quantity * line.rate) - line.taxable_value;
+        pdf.table_row(
+            QUOTATION_COLUMNS,
+            &[
+                vec![line.line_no.to_string()],
+                description,
+                vec![line.hsn_code.clone()],
+                vec![line.quantity.to_string()],
+                vec![format_amount(line.rate)],
+                vec![format_amount(discount)],
+                vec![format_amount(line.taxable_value)],
+                vec![format!("{}", line.gst_rate)],
+                vec![format_amount(line.cgst_amount + line.sgst_amount + line.igst_amount)],
+            ],
+            8.0,
+            false,
+        );
+    }
+
+    let mut totals = vec![("Taxable Value", quotation.taxable_value)];
+    if quotation.igst_amount != 0.0 {
+        totals.push(("IGST", quotation.igst_amount));
+    }
+    if quotation.cgst_amount != 0.0 || quotation.sgst_amount != 0.0 {
+        totals.push(("CGST", quotation.cgst_amount));
+        totals.push(("SGST", quotation.sgst_amount));
+    }
+    if quotation.round_off != 0.0 {
+        totals.push(("Round Off", quotation.round_off));
+    }
+    totals.push(("Quotation Total", quotation.total_amount));
+    pdf.ensure_space(pdf.line_height(9.0) * (totals.len() as f32 + 1.0));
+    pdf.rule(pdf.y + pdf.line_height(8.0) * 0.75);
+    pdf.advance(pdf.line_height(9.0) * 0.5);
+    for (label, amount) in totals {
+        let bold = label == "Quotation Total";
+        pdf.text_right(&format!("{}: {}", label, format_amount(amount)), 9.0, right, bold);
+        pdf.advance(pdf.line_height(9.0));
+    }
+
+    if let Some(notes) = quotation.notes.as_deref().filter(|n| !n.trim().is_empty()) {
+        pdf.advance(pdf.line_height(9.0));
+        let note_chars = pdf.chars_fitting(pdf.content_width(), 8.5);
+        for line in wrap(notes, note_chars) {
+            pdf.ensure_space(pdf.line_height(8.5));
+            pdf.text(&line, 8.5, left, false);
+            pdf.advance(pdf.line_height(8.5));
+        }
+    }
+    pdf.advance(pdf.line_height(9.0));
+    pdf.text(
+        "This is a quotation and not a tax invoice. Prices are valid until the date above.",
+        8.0,
+        left,
+        false,
+    );
+    Ok(pdf.finish()?)
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn list_quotations(
+    pool: State<'_, DbPool>,
+    company_id: i64,
+    status: Option<QuotationStatus>,
+) -> Result<Vec<Quotation>, AppError> {
+    let conn = db::get_conn(&pool)?;
+    expire_quotations(&conn, company_id)?;
+    let mut stmt = conn.prepare(&format!(
+        "{} WHERE q.company_id = ?1 AND (?2 IS NULL OR q.status = ?2)
+             ORDER BY q.quotation_date DESC, q.id DESC",
+        SELECT_QUOTATION
+    ))?;
+    let quotations = stmt
+        .query_map(params![company_id, status.map(|s| s.as_str())], quotation_from_row)?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(quotations)
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn get_quotation(
+    pool: State<'_, DbPool>,
+    id: i64,
+    company_id: i64,
+) -> Result<QuotationWithLines, AppError> {
+    let conn = db::get_conn(&pool)?;
+    expire_quotations(&conn, company_id)?;
+    with_lines(&conn, id, company_id)
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn create_quotation(
+    pool: State<'_, DbPool>,
+    company_id: i64,
+    quotation: SaveQuotation,
+) -> Result<QuotationWithLines, AppError> {
+    let mut conn = db::get_conn(&pool)?;
+    let tx = conn.transaction()?;
+    let (mut prepared, lines) = prepare(&tx, company_id, &quotation)?;
+    if prepared.quotation_number.is_empty() {
+        prepared.quotation_number = numbering::allocate_number(
+            &tx,
+            company_id,
+            DocumentType::Quotation,
+            &prepared.quotation_date,
+        )?;
+    }
+    tx.execute(
+        "INSERT INTO quotations (company_id, quotation_number, quotation_date, valid_until,
+                                 customer_id, place_of_supply, supply_kind, discount_percent,
+                                 discount_amount, taxable_value, cgst_amount, sgst_amount,
+                                 igst_amount, round_off, total_amount, status, notes)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17)",
+        params![
+            company_id,
+            prepared.quotation_number,
+            prepared.quotation_date,
+            prepared.valid_until,
+            prepared.customer_id,
+            prepared.place_of_supply,
+            prepared.supply_kind.as_str(),
+            prepared.discount_percent,
+            prepared.discount_amount,
+            prepared.taxable_value,
+            prepared.cgst_amount,
+            prepared.sgst_amount,
+            prepared.igst_amount,
+            prepared.round_off,
+            prepared.total_amount,
+            prepared.status.as_str(),
+            prepared.notes,
+        ],
+    )
+    .map_err(map_write_error)?;
+    let id = tx.last_insert_rowid();
+    replace_lines(&tx, id, &lines)?;
+    let created = with_lines(&tx, id, company_id)?;
+    audit::record(&tx, company_id, "quotation", id, AuditAction::Create, None, Some(&created))?;
+    tx.commit()?;
+    Ok(created)
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn update_quotation(
+    pool: State<'_, DbPool>,
+    id: i64,
+    company_id: i64,
+    quotation: SaveQuotation,
+) -> Result<QuotationWithLines, AppError> {
+    let mut conn = db::get_conn(&pool)?;
+    let tx = conn.transaction()?;
+    let existing = with_lines(&tx, id, company_id)?;
+    require_unlocked(&existing.quotation)?;
+    let (mut prepared, lines) = prepare(&tx, company_id, &quotation)?;
+    if prepared.quotation_number.is_empty() {
+        prepared.quotation_number = existing.quotation.quotation_number.clone();
+    }
+    // A new validity date revives an expired quotation as a draft
+    let status = match existing.quotation.status {
+        QuotationStatus::Expired if prepared.valid_until >= today() => QuotationStatus::Draft,
+        status => status,
+    };
+    tx.execute(
+        "UPDATE quotations SET quotation_number = ?1, quotation_date = ?2, valid_until = ?3,
+                customer_id = ?4, place_of_supply = ?5, supply_kind = ?6, discount_percent = ?7,
+                discount_amount = ?8, taxable_value = ?9, cgst_amount = ?10, sgst_amount = ?11,
+                igst_amount = ?12, round_off = ?13, total_amount = ?14, status = ?15, notes = ?16,
+                updated_at = CURRENT_TIMESTAMP
+         WHERE id = ?17 AND company_id = ?18",
+        params![
+            prepared.quotation_number,
+            prepared.quotation_date,
+            prepared.valid_until,
+            prepared.customer_id,
+            prepared.place_of_supply,
+            prepared.supply_kind.as_str(),
+            prepared.discount_percent,
+            prepared.discount_amount,
+            prepared.taxable_value,
+            prepared.cgst_amount,
+            prepared.sgst_amount,
+            prepared.igst_amount,
+            prepared.round_off,
+            prepared.total_amount,
+            status.as_str(),
+            prepared.notes,
+            id,
+            company_id,
+        ],
+    )
+    .map_err(map_write_error)?;
+    replace_lines(&tx, id, &lines)?;
+    let updated = with_lines(&tx, id, company_id)?;
+    audit::record(
+        &tx,
+        company_id,
+        "quotation",
+        id,
+        AuditAction::Update,
+        Some(&existing),
+        Some(&updated),
+    )?;
+    tx.commit()?;
+    Ok(updated)
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn set_quotation_status(
+    pool: State<'_, DbPool>,
+    id: i64,
+    company_id: i64,
+    status: QuotationStatus,
+) -> Result<Quotation, AppError> {
+    let conn = db::get_conn(&pool)?;
+    let existing = get_quotation_by_id(&conn, id, company_id)?
+        .ok_or_else(|| AppError::not_found("Quotation not found"))?;
+    require_unlocked(&existing)?;
+    if status != QuotationStatus::Expired && existing.valid_until < today() {
+        return Err(AppError::validation(
+            "status",
+            "The quotation's validity has passed; extend it before changing its status",
+        ));
+    }
+    conn.execute(
+        "UPDATE quotations SET status = ?1, updated_at = CURRENT_TIMESTAMP
+         WHERE id = ?2 AND company_id = ?3",
+        params![status.as_str(), id, company_id],
+    )?;
+    let updated = get_quotation_by_id(&conn, id, company_id)?
+        .ok_or_else(|| AppError::internal("Quotation not found after update"))?;
+    audit::record(
+        &conn,
+        company_id,
+        "quotation",
+        id,
+        AuditAction::Update,
+        Some(&existing),
+        Some(&updated),
+    )?;
+    Ok(updated)
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn delete_quotation(
+    pool: State<'_, DbPool>,
+    id: i64,
+    company_id: i64,
+) -> Result<(), AppError> {
+    let conn = db::get_conn(&pool)?;
+    let existing = with_lines(&conn, id, company_id)?;
+    require_unlocked(&existing.quotation)?;
+    conn.execute(
+        "DELETE FROM quotations WHERE id = ?1 AND company_id = ?2",
+        params![id, company_id],
+    )?;
+    audit::record(&conn, company_id, "quotation", id, AuditAction::Delete, Some(&existing), None)
+}
+
+// Copies the quotation into a draft invoice dated `invoice_date` (today when not given) and
+// locks the quotation against further changes
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn convert_quotation_to_invoice(
+    pool: State<'_, DbPool>,
+    id: i64,
+    company_id: i64,
+    invoice_date: Option<String>,
+) -> Result<ConvertedQuotation, AppError> {
+    let invoice_date = match invoice_date.as_deref().filter(|d| !d.trim().is_empty()) {
+        Some(date) => parse_date("invoice_date", date)?.format(INVOICE_DATE_FORMAT).to_string(),
+        None => today(),
+    };
+    let mut conn = db::get_conn(&pool)?;
+    expire_quotations(&conn, company_id)?;
+    let tx = conn.transaction()?;
+    let existing = with_lines(&tx, id, company_id)?;
+    require_unlocked(&existing.quotation)?;
+    if existing.quotation.status == QuotationStatus::Expired {
+        return Err(AppError::validation(
+            "status",
+            "This quotation has expired; extend its validity before converting it",
+        ));
+    }
+
+    let quotation = &existing.quotation;
+    let lines = existing
+        .lines
+        .iter()
+        .map(|line| QuotationLineInput {
+            item_id: line.item_id,
+            unit_id: line.unit_id,
+            description: line.description.clone(),
+            hsn_code: line.hsn_code.clone(),
+            quantity: line.quantity,
+            rate: line.rate,
+            discount_percent: line.discount_percent,
+            discount: line.discount,
+            gst_rate: line.gst_rate,
+        })
+        .collect::<Vec<_>>();
+    let invoice = invoices::create_invoice_from_lines(
+        &tx,
+        CreateInvoice {
+            company_id,
+            invoice_number: String::new(),
+            invoice_date,
+            customer_id: quotation.customer_id,
+            place_of_supply: quotation.place_of_supply.clone(),
+            supply_kind: quotation.supply_kind,
+            export: None,
+            sez_mode: None,
+            discount_percent: quotation.discount_percent,
+            discount_amount: quotation.discount_amount,
+            reverse_charge: false,
+            taxable_value: quotation.taxable_value,
+            cgst_amount: quotation.cgst_amount,
+            sgst_amount: quotation.sgst_amount,
+            igst_amount: quotation.igst_amount,
+            cess_amount: 0.0,
+            total_amount: quotation.total_amount,
+            currency: None,
+            exchange_rate: None,
+            status: Some(InvoiceStatus::Draft),
+            notes: Some(format!("Against quotation {}", quotation.quotation_number)),
+            lines: invoice_lines(&lines),
+        },
+    )?;
+
+    tx.execute(
+        "UPDATE quotations SET status = 'accepted', invoice_id = ?1,
+                               updated_at = CURRENT_TIMESTAMP
+         WHERE id = ?2 AND company_id = ?3",
+        params![invoice.invoice.id, id, company_id],
+    )?;
+    let converted = get_quotation_by_id(&tx, id, company_id)?
+        .ok_or_else(|| AppError::internal("Quotation not found after conversion"))?;
+    audit::record(
+        &tx,
+        company_id,
+        "quotation",
+        id,
+        AuditAction::Update,
+        Some(quotation),
+        Some(&converted),
+    )?;
+    tx.commit()?;
+    Ok(ConvertedQuotation { quotation: converted, invoice })
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn export_quotation_pdf(
+    pool: State<'_, DbPool>,
+    id: i64,
+    company_id: i64,
+    path: Option<String>,
+) -> Result<RenderedPdf, AppError> {
+    let (bytes, page_count) = {
+        let conn = db::get_conn(&pool)?;
+        let document = with_lines(&conn, id, company_id)?;
+        render_quotation_pdf(&conn, &document)?
+    };
+    Ok(write_output(bytes, page_count, path)?)
+}
@@ -0,0 +1,625 @@
+use std::collections::HashSet;
+
+use chrono::NaiveDate;
+use rusqlite::{params, Connection, OptionalExtension, Row};
+use serde::{Deserialize, Serialize};
+use tauri::State;
+
+use crate::customers;
+use crate::db::{self, DbPool};
+use crate::error::AppError;
+use crate::financial_years;
+use crate::invoices::{round2, INVOICE_DATE_FORMAT};
+use crate::tax::AMOUNT_TOLERANCE;
+use crate::webhooks::{self, WebhookEvent};
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum PaymentMode {
+    Cash,
+    Cheque,
+    BankTransfer,
+    Upi,
+    Card,
+    Other,
+}
+
+impl PaymentMode {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PaymentMode::Cash => "cash",
+            PaymentMode::Cheque => "cheque",
+            PaymentMode::BankTransfer => "bank_transfer",
+            PaymentMode::Upi => "upi",
+            PaymentMode::Card => "card",
+            PaymentMode::Other => "other",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "cash" => Some(PaymentMode::Cash),
+            "cheque" => Some(PaymentMode::Cheque),
+            "bank_transfer" => Some(PaymentMode::BankTransfer),
+            "upi" => Some(PaymentMode::Upi),
+            "card" => Some(PaymentMode::Card),
+            "other" => Some(PaymentMode::Other),
+            _ => None,
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            PaymentMode::Cash => "Cash",
+            PaymentMode::Cheque => "Cheque",
+            PaymentMode::BankTransfer => "Bank Transfer",
+            PaymentMode::Upi => "UPI",
+            PaymentMode::Card => "Card",
+            PaymentMode::Other => "Other",
+        }
+    }
+}
+
+// Receipt data model
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Receipt {
+    pub id: Option<i64>,
+    pub company_id: i64,
+    pub customer_id: i64,
+    pub receipt_date: String,
+    pub mode: PaymentMode,
+    pub reference: Option<String>,
+    pub amount: f64,
+    pub allocated_amount: f64,
+    pub unallocated_amount: f64,
+    pub notes: Option<String>,
+    pub created_at: Option<String>,
+    pub updated_at: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ReceiptAllocation {
+    pub id: Option<i64>,
+    pub receipt_id: i64,
+    pub invoice_id: i64,
+    pub invoice_number: String,
+    pub invoice_date: String,
+    pub amount: f64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ReceiptWithAllocations {
+    #[serde(flatten)]
+    pub receipt: Receipt,
+    pub allocations: Vec<ReceiptAllocation>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CreateReceipt {
+    pub company_id: i64,
+    pub customer_id: i64,
+    pub receipt_date: String,
+    pub mode: PaymentMode,
+    pub reference: Option<String>,
+    pub amount: f64,
+    pub notes: Option<String>,
+    // Knocks the receipt off against the oldest outstanding invoices straight away
+    #[serde(default)]
+    pub auto_allocate: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AllocationInput {
+    pub invoice_id: i64,
+    pub amount: f64,
+}
+
+// What is still owed on an invoice after credit/debit notes and receipts
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct InvoiceBalance {
+    pub invoice_id: i64,
+    pub invoice_number: String,
+    pub invoice_date: String,
+    pub customer_id: i64,
+    pub total_amount: f64,
+    pub note_adjustment: f64,
+    pub amount_received: f64,
+    pub outstanding: f64,
+}
+
+const SELECT_RECEIPT: &str = "
+    SELECT r.id, r.company_id, r.customer_id, r.receipt_date, r.mode, r.reference, r.amount,
+           (SELECT COALESCE(SUM(a.amount), 0) FROM receipt_allocations a
+            WHERE a.receipt_id = r.id) AS allocated_amount,
+           r.notes, r.created_at, r.updated_at
+    FROM receipts r";
+
+// Issued invoices only; credit notes reduce and debit notes increase what the customer owes
+const SELECT_BALANCE: &str = "
+    SELECT i.id, i.invoice_number, i.invoice_date, i.customer_id, i.total_amount,
+           (SELECT COALESCE(SUM(CASE WHEN n.note_type = 'credit' THEN -n.total_amount
+                                     ELSE n.total_amount END), 0)
+            FROM credit_debit_notes n
+            WHERE n.invoice_id = i.id AND n.status = 'issued') AS note_adjustment,
+           i.amount_received
+    FROM invoices i
+    WHERE i.status = 'issued'";
+
+fn receipt_from_row(row: &Row) -> rusqlite::Result<Receipt> {
+    let mode: String = row.get("mode")?;
+    let amount: f64 = row.get("amount")?;
+    let allocated_amount: f64 = row.get("allocated_amount")?;
+    Ok(Receipt {
+        id: row.get("id")?,
+        company_id: row.get("company_id")?,
+        customer_id: row.get("customer_id")?,
+        receipt_date: row.get("receipt_date")?,
+        mode: PaymentMode::parse(&mode).unwrap_or(PaymentMode::Other),
+        reference: row.get("reference")?,
+        amount,
+        allocated_amount: round2(allocated_amount),
+        unallocated_amount: round2(amount - allocated_amount),
+        notes: row.get("notes")?,
+        created_at: row.get("created_at")?,
+        updated_at: row.get("updated_at")?,
+    })
+}
+
+fn balance_from_row(row: &Row) -> rusqlite::Result<InvoiceBalance> {
+    let total_amount: f64 = row.get("total_amount")?;
+    let note_adjustment: f64 = row.get("note_adjustment")?;
+    let amount_received: f64 = row.get("amount_received")?;
+    Ok(InvoiceBalance {
+        invoice_id: row.get("id")?,
+        invoice_number: row.get("invoice_number")?,
+        invoice_date: row.get("invoice_date")?,
+        customer_id: row.get("customer_id")?,
+        total_amount,
+        note_adjustment: round2(note_adjustment),
+        amount_received: round2(amount_received),
+        outstanding: round2(total_amount + note_adjustment - amount_received),
+    })
+}
+
+pub fn get_receipt_by_id(
+    conn: &Connection,
+    id: i64,
+    company_id: i64,
+) -> Result<Option<Receipt>, AppError> {
+    conn.query_row(
+        &format!("{} WHERE r.id = ?1 AND r.company_id = ?2", SELECT_RECEIPT),
+        params![id, company_id],
+        receipt_from_row,
+    )
+    .optional()
+    .map_err(AppError::from)
+}
+
+fn get_allocations(conn: &Connection, receipt_id: i64) -> Result<Vec<ReceiptAllocation>, AppError> {
+    let mut stmt = conn.prepare(
+        "SELECT a.id, a.receipt_id, a.invoice_id, i.invoice_number, i.invoice_date, a.amount
+             FROM receipt_allocations a
+             JOIN invoices i ON i.id = a.invoice_id
+             WHERE a.receipt_id = ?1
+             ORDER BY i.invoice_date, i.id",
+    )?;
+    let allocations = stmt
+        .query_map(params![receipt_id], |row| {
+            Ok(ReceiptAllocation {
+                id: row.get(0)?,
+                receipt_id: row.get(1)?,
+                invoice_id: row.get(2)?,
+                invoice_number: row.get(3)?,
+                invoice_date: row.get(4)?,
+                amount: row.get(5)?,
+            })
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(allocations)
+}
+
+fn receipt_with_allocations(
+    conn: &Connection,
+    id: i64,
+    company_id: i64,
+) -> Result<Option<ReceiptWithAllocations>, String> {
+    let Some(receipt) = get_receipt_by_id(conn, id, company_id)? else {
+        return Ok(None);
+    };
+    let allocations = get_allocations(conn, id)?;
+    Ok(Some(ReceiptWithAllocations {
+        receipt,
+        allocations,
+    }))
+}
+
+// Oldest first, which is the order FIFO allocation settles them in
+pub fn get_outstanding_invoices(
+    conn: &Connection,
+    company_id: i64,
+    customer_id: i64,
+) -> Result<Vec<InvoiceBalance>, AppError> {
+    let mut stmt = conn.prepare(&format!(
+        "{} AND i.company_id = ?1 AND i.customer_id = ?2 ORDER BY i.invoice_date, i.id",
+        SELECT_BALANCE
+    ))?;
+    let balances = stmt
+        .query_map(params![company_id, customer_id], balance_from_row)?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(balances
+        .into_iter()
+        .filter(|b| b.outstanding > AMOUNT_TOLERANCE)
+        .collect())
+}
+
+// Every customer's unpaid invoices, oldest first
+pub fn get_company_outstanding_invoices(
+    conn: &Connection,
+    company_id: i64,
+) -> Result<Vec<InvoiceBalance>, AppError> {
+    let mut stmt = conn.prepare(&format!(
+        "{} AND i.company_id = ?1 ORDER BY i.invoice_date, i.id",
+        SELECT_BALANCE
+    ))?;
+    let balances = stmt
+        .query_map(params![company_id], balance_from_row)?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(balances
+        .into_iter()
+        .filter(|b| b.outstanding > AMOUNT_TOLERANCE)
+        .collect())
+}
+
+fn get_invoice_balance(
+    conn: &Connection,
+    invoice_id: i64,
+) -> Result<Option<InvoiceBalance>, AppError> {
+    conn.query_row(
+        &format!("{} AND i.id = ?1", SELECT_BALANCE),
+        params![invoice_id],
+        balance_from_row,
+    )
+    .optional()
+    .map_err(AppError::from)
+}
+
+// Recomputes the invoice's received total from its allocations, queuing the paid webhook when
+// this settles the invoice
+fn refresh_amount_received(conn: &Connection, invoice_id: i64) -> Result<(), AppError> {
+    let before = get_invoice_balance(conn, invoice_id)?;
+    conn.execute(
+        "UPDATE invoices SET amount_received = (
+             SELECT ROUND(COALESCE(SUM(amount), 0), 2) FROM receipt_allocations WHERE invoice_id = ?1
+         )
+         WHERE id = ?1",
+        params![invoice_id],
+    )?;
+
+    let was_outstanding = before.is_some_and(|b| b.outstanding > AMOUNT_TOLERANCE);
+    let after = get_invoice_balance(conn, invoice_id)?;
+    if let Some(after) = after.filter(|b| was_outstanding && b.outstanding <= AMOUNT_TOLERANCE) {
+        let company_id: i64 = conn.query_row(
+            "SELECT company_id FROM invoices WHERE id = ?1",
+            params![invoice_id],
+            |row| row.get(0),
+        )?;
+        webhooks::enqueue(conn, company_id, WebhookEvent::Paid, &after)?;
+    }
+    Ok(())
+}
+
+fn add_allocation(
+    conn: &Connection,
+    receipt_id: i64,
+    invoice_id: i64,
+    amount: f64,
+) -> Result<(), AppError> {
+    conn.execute(
+        "INSERT INTO receipt_allocations (receipt_id, invoice_id, amount) VALUES (?1, ?2, ?3)
+         ON CONFLICT(receipt_id, invoice_id) DO UPDATE SET amount = ROUND(amount + excluded.amount, 2)",
+        params![receipt_id, invoice_id, round2(amount)],
+    )?;
+    refresh_amount_received(conn, invoice_id)
+}
+
+// Settles the customer's oldest invoices first until the receipt is used up
+fn allocate_fifo(conn: &Connection, receipt: &Receipt) -> Result<(), String> {
+    let receipt_id = receipt.id.unwrap_or_default();
+    let mut remaining = receipt.unallocated_amount;
+    for balance in get_outstanding_invoices(conn, receipt.company_id, receipt.customer_id)? {
+        if remaining <= AMOUNT_TOLERANCE {
+            break;
+        }
+        let amount = round2(remaining.min(balance.outstanding));
+        add_allocation(conn, receipt_id, balance.invoice_id, amount)?;
+        remaining = round2(remaining - amount);
+    }
+    Ok(())
+}
+
+fn validate_receipt(receipt: &CreateReceipt) -> Result<(), AppError> {
+    if NaiveDate::parse_from_str(receipt.receipt_date.trim(), INVOICE_DATE_FORMAT).is_err() {
+        return Err(AppError::validation(
+            "receipt_date",
+            "Receipt date must be a valid date in YYYY-MM-DD format",
+        ));
+    }
+    if !receipt.amount.is_finite() || receipt.amount <= 0.0 {
+        return Err(AppError::validation(
+            "amount",
+            "Receipt amount must be greater than zero",
+        ));
+    }
+    if let Some(reference) = &receipt.reference {
+        if reference.len() > 100 {
+            return Err(AppError::validation(
+                "reference",
+                "Reference must be 100 characters or less",
+            ));
+        }
+    }
+    if receipt.mode == PaymentMode::Cheque
+        && receipt.reference.as_deref().map(str::trim).unwrap_or("").is_empty()
+    {
+        return Err(AppError::validation(
+            "reference",
+            "Cheque receipts need the cheque number as the reference",
+        ));
+    }
+    if let Some(notes) = &receipt.notes {
+        if notes.len() > 1000 {
+            return Err(AppError::validation(
+                "notes",
+                "Notes must be 1000 characters or less",
+            ));
+        }
+    }
+    Ok(())
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn record_receipt(
+    pool: State<'_, DbPool>,
+    receipt: CreateReceipt,
+) -> Result<ReceiptWithAllocations, AppError> {
+    let mut conn = db::get_conn(&pool)?;
+    let tx = conn.transaction()?;
+    let created = record_receipt_in(&tx, &receipt)?;
+    tx.commit()?;
+    Ok(created)
+}
+
+// Records a receipt on a connection the caller commits, as `record_receipt` does in its own
+// transaction
+pub fn record_receipt_in(
+    conn: &Connection,
+    receipt: &CreateReceipt,
+) -> Result<ReceiptWithAllocations, AppError> {
+    validate_receipt(receipt)?;
+    customers::get_customer_by_id(conn, receipt.customer_id, receipt.company_id)?.ok_or_else(
+        || AppError::validation("customer_id", "Customer does not exist for this company"),
+    )?;
+    financial_years::ensure_period_open(conn, receipt.company_id, &receipt.receipt_date)?;
+
+    conn.execute(
+        "INSERT INTO receipts (company_id, customer_id, receipt_date, mode, reference, amount, notes)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+        params![
+            receipt.company_id,
+            receipt.customer_id,
+            receipt.receipt_date.trim(),
+            receipt.mode.as_str(),
+            receipt
+                .reference
+                .as_deref()
+                .map(str::trim)
+                .filter(|r| !r.is_empty()),
+            round2(receipt.amount),
+            receipt.notes
+        ],
+    )?;
+    let id = conn.last_insert_rowid();
+
+    if receipt.auto_allocate {
+        let created = get_receipt_by_id(conn, id, receipt.company_id)?
+            .ok_or_else(|| AppError::internal("Receipt not found after creation"))?;
+        allocate_fifo(conn, &created)?;
+    }
+
+    receipt_with_allocations(conn, id, receipt.company_id)?
+        .ok_or_else(|| AppError::internal("Receipt not found after creation"))
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn get_receipt(
+    pool: State<'_, DbPool>,
+    id: i64,
+    company_id: i64,
+) -> Result<Option<ReceiptWithAllocations>, AppError> {
+    let conn = db::get_conn(&pool)?;
+    Ok(receipt_with_allocations(&conn, id, company_id)?)
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn list_receipts(
+    pool: State<'_, DbPool>,
+    company_id: i64,
+    customer_id: Option<i64>,
+) -> Result<Vec<Receipt>, AppError> {
+    let conn = db::get_conn(&pool)?;
+    let mut stmt = conn.prepare(&format!(
+        "{} WHERE r.company_id = ?1 AND (?2 IS NULL OR r.customer_id = ?2)
+             ORDER BY r.receipt_date DESC, r.id DESC",
+        SELECT_RECEIPT
+    ))?;
+    let receipts = stmt
+        .query_map(params![company_id, customer_id], receipt_from_row)?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(receipts)
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn list_outstanding_invoices(
+    pool: State<'_, DbPool>,
+    company_id: i64,
+    customer_id: i64,
+) -> Result<Vec<InvoiceBalance>, AppError> {
+    let conn = db::get_conn(&pool)?;
+    get_outstanding_invoices(&conn, company_id, customer_id)
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn auto_allocate_receipt(
+    pool: State<'_, DbPool>,
+    id: i64,
+    company_id: i64,
+) -> Result<ReceiptWithAllocations, AppError> {
+    let mut conn = db::get_conn(&pool)?;
+    let tx = conn.transaction()?;
+    let receipt = get_receipt_by_id(&tx, id, company_id)?
+        .ok_or_else(|| AppError::not_found("Receipt not found"))?;
+    if receipt.unallocated_amount <= AMOUNT_TOLERANCE {
+        return Err(AppError::invalid("This receipt is already fully allocated"));
+    }
+    allocate_fifo(&tx, &receipt)?;
+
+    let updated = receipt_with_allocations(&tx, id, company_id)?
+        .ok_or_else(|| AppError::internal("Receipt not found after allocation"))?;
+    tx.commit()?;
+    Ok(updated)
+}
+
+// Adds to any amount already allocated from this receipt to the same invoice
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn allocate_receipt(
+    pool: State<'_, DbPool>,
+    id: i64,
+    company_id: i64,
+    allocations: Vec<AllocationInput>,
+) -> Result<ReceiptWithAllocations, AppError> {
+    if allocations.is_empty() {
+        return Err(AppError::validation(
+            "allocations",
+            "At least one allocation is required",
+        ));
+    }
+    let mut seen = HashSet::new();
+    for allocation in &allocations {
+        if !allocation.amount.is_finite() || allocation.amount <= 0.0 {
+            return Err(AppError::validation(
+                "allocations",
+                "Allocation amounts must be greater than zero",
+            ));
+        }
+        if !seen.insert(allocation.invoice_id) {
+            return Err(AppError::validation(
+                "allocations",
+                "Each invoice may appear only once per allocation",
+            ));
+        }
+    }
+
+    let mut conn = db::get_conn(&pool)?;
+    let tx = conn.transaction()?;
+    let receipt = get_receipt_by_id(&tx, id, company_id)?
+        .ok_or_else(|| AppError::not_found("Receipt not found"))?;
+    let requested: f64 = allocations.iter().map(|a| a.amount).sum();
+    if requested > receipt.unallocated_amount + AMOUNT_TOLERANCE {
+        return Err(AppError::validation(
+            "allocations",
+            format!(
+                "Only {:.2} of this receipt is left to allocate",
+                receipt.unallocated_amount
+            ),
+        ));
+    }
+
+    let outstanding = get_outstanding_invoices(&tx, company_id, receipt.customer_id)?;
+    for allocation in &allocations {
+        let balance = outstanding
+            .iter()
+            .find(|b| b.invoice_id == allocation.invoice_id)
+            .ok_or_else(|| {
+                AppError::validation(
+                    "allocations",
+                    "Receipts can only be allocated to this customer's issued invoices with a balance",
+                )
+            })?;
+        if allocation.amount > balance.outstanding + AMOUNT_TOLERANCE {
+            return Err(AppError::validation(
+                "allocations",
+                format!(
+                    "Invoice {} only has {:.2} outstanding",
+                    balance.invoice_number, balance.outstanding
+                ),
+            ));
+        }
+        add_allocation(&tx, id, allocation.invoice_id, allocation.amount)?;
+    }
+
+    let updated = receipt_with_allocations(&tx, id, company_id)?
+        .ok_or_else(|| AppError::internal("Receipt not found after allocation"))?;
+    tx.commit()?;
+    Ok(updated)
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn remove_receipt_allocation(
+    pool: State<'_, DbPool>,
+    id: i64,
+    company_id: i64,
+    invoice_id: i64,
+) -> Result<ReceiptWithAllocations, AppError> {
+    let mut conn = db::get_conn(&pool)?;
+    let tx = conn.transaction()?;
+    get_receipt_by_id(&tx, id, company_id)?
+        .ok_or_else(|| AppError::not_found("Receipt not found"))?;
+
+    let removed = tx.execute(
+        "DELETE FROM receipt_allocations WHERE receipt_id = ?1 AND invoice_id = ?2",
+        params![id, invoice_id],
+    )?;
+    if removed == 0 {
+        return Err(AppError::not_found(
+            "This receipt is not allocated to that invoice",
+        ));
+    }
+    refresh_amount_received(&tx, invoice_id)?;
+
+    let updated = receipt_with_allocations(&tx, id, company_id)?
+        .ok_or_else(|| AppError::internal("Receipt not found after update"))?;
+    tx.commit()?;
+    Ok(updated)
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn delete_receipt(
+    pool: State<'_, DbPool>,
+    id: i64,
+    company_id: i64,
+) -> Result<(), AppError> {
+    let mut conn = db::get_conn(&pool)?;
+    let tx = conn.transaction()?;
+    let receipt = get_receipt_by_id(&tx, id, company_id)?
+        .ok_or_else(|| AppError::not_found("Receipt not found"))?;
+    financial_years::ensure_period_open(&tx, company_id, &receipt.receipt_date)?;
+
+    let allocations = get_allocations(&tx, id)?;
+    tx.execute(
+        "DELETE FROM receipts WHERE id = ?1 AND company_id = ?2",
+        params![id, company_id],
+    )?;
+    for allocation in allocations {
+        refresh_amount_received(&tx, allocation.invoice_id)?;
+    }
+    tx.commit()?;
+    Ok(())
+}
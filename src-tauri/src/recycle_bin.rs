@@ -0,0 +1,206 @@
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use tauri::State;
+
+use crate::audit::{self, AuditAction};
+use crate::db::{self, get_setting, set_setting, DbPool};
+use crate::error::AppError;
+
+const SETTING_RETENTION_DAYS: &str = "recycle_bin_retention_days";
+const DEFAULT_RETENTION_DAYS: u32 = 30;
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum DeletedEntity {
+    Customer,
+    Category,
+    Invoice,
+}
+
+impl DeletedEntity {
+    fn table(&self) -> &'static str {
+        match self {
+            DeletedEntity::Customer => "customers",
+            DeletedEntity::Category => "categories",
+            DeletedEntity::Invoice => "invoices",
+        }
+    }
+
+    // Column shown to the user when listing the bin
+    fn name_column(&self) -> &'static str {
+        match self {
+            DeletedEntity::Customer => "report_customer",
+            DeletedEntity::Category => "name",
+            DeletedEntity::Invoice => "invoice_number",
+        }
+    }
+
+    fn label(&self) -> &'static str {
+        match self {
+            DeletedEntity::Customer => "Customer",
+            DeletedEntity::Category => "Category",
+            DeletedEntity::Invoice => "Invoice",
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DeletedRecord {
+    pub id: i64,
+    pub name: String,
+    pub deleted_at: String,
+}
+
+pub fn load_retention_days(conn: &Connection) -> Result<u32, String> {
+    Ok(get_setting(conn, SETTING_RETENTION_DAYS)?
+        .and_then(|value| value.parse().ok())
+        .unwrap_or(DEFAULT_RETENTION_DAYS))
+}
+
+// Permanently removes records deleted more than the retention period ago. Children go first
+// so a customer is only purged once its invoices are, and a category once its customers are.
+pub fn purge_expired(conn: &Connection) -> Result<usize, String> {
+    let cutoff = format!("-{} days", load_retention_days(conn)?);
+    let statements = [
+        "DELETE FROM invoices
+         WHERE deleted_at IS NOT NULL AND deleted_at <= datetime('now', ?1)",
+        "DELETE FROM customers
+         WHERE deleted_at IS NOT NULL AND deleted_at <= datetime('now', ?1)
+           AND NOT EXISTS (SELECT 1 FROM invoices i WHERE i.customer_id = customers.id)
+           AND NOT EXISTS (SELECT 1 FROM receipts r WHERE r.customer_id = customers.id)",
+        "DELETE FROM categories
+         WHERE deleted_at IS NOT NULL AND deleted_at <= datetime('now', ?1)
+           AND NOT EXISTS (SELECT 1 FROM customers c WHERE c.category_id = categories.id)",
+    ];
+    let mut purged = 0;
+    for sql in statements {
+        purged += conn
+            .execute(sql, params![cutoff])
+            .map_err(|e| e.to_string())?;
+    }
+    Ok(purged)
+}
+
+// A restored record must not point at a parent that is still in the bin
+fn check_parent_active(
+    conn: &Connection,
+    entity: DeletedEntity,
+    id: i64,
+    company_id: i64,
+) -> Result<(), AppError> {
+    let (sql, parent) = match entity {
+        DeletedEntity::Customer => (
+            "SELECT cat.deleted_at IS NOT NULL FROM customers c
+             JOIN categories cat ON cat.id = c.category_id
+             WHERE c.id = ?1 AND c.company_id = ?2",
+            "category",
+        ),
+        DeletedEntity::Invoice => (
+            "SELECT c.deleted_at IS NOT NULL FROM invoices i
+             JOIN customers c ON c.id = i.customer_id
+             WHERE i.id = ?1 AND i.company_id = ?2",
+            "customer",
+        ),
+        DeletedEntity::Category => return Ok(()),
+    };
+    let parent_deleted: Option<bool> = conn
+        .query_row(sql, params![id, company_id], |row| row.get(0))
+        .optional()?;
+    if parent_deleted == Some(true) {
+        return Err(AppError::invalid(format!(
+            "Restore the {}'s {} first",
+            entity.label().to_lowercase(),
+            parent
+        )));
+    }
+    Ok(())
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn list_deleted(
+    pool: State<'_, DbPool>,
+    company_id: i64,
+    entity: DeletedEntity,
+) -> Result<Vec<DeletedRecord>, AppError> {
+    let conn = db::get_conn(&pool)?;
+    purge_expired(&conn)?;
+    let mut stmt = conn.prepare(&format!(
+        "SELECT id, {}, deleted_at FROM {}
+             WHERE company_id = ?1 AND deleted_at IS NOT NULL
+             ORDER BY deleted_at DESC",
+        entity.name_column(),
+        entity.table()
+    ))?;
+    let records = stmt
+        .query_map(params![company_id], |row| {
+            Ok(DeletedRecord {
+                id: row.get(0)?,
+                name: row.get(1)?,
+                deleted_at: row.get(2)?,
+            })
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(records)
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn restore(
+    pool: State<'_, DbPool>,
+    company_id: i64,
+    entity: DeletedEntity,
+    id: i64,
+) -> Result<(), AppError> {
+    let conn = db::get_conn(&pool)?;
+    check_parent_active(&conn, entity, id, company_id)?;
+    let changed = conn.execute(
+        &format!(
+            "UPDATE {} SET deleted_at = NULL, updated_at = CURRENT_TIMESTAMP
+                 WHERE id = ?1 AND company_id = ?2 AND deleted_at IS NOT NULL",
+            entity.table()
+        ),
+        params![id, company_id],
+    )?;
+    if changed == 0 {
+        return Err(AppError::not_found(format!(
+            "{} is not in the recycle bin",
+            entity.label()
+        )));
+    }
+    audit::record::<()>(
+        &conn,
+        company_id,
+        &entity.label().to_lowercase(),
+        id,
+        AuditAction::Restore,
+        None,
+        None,
+    )
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn get_recycle_bin_retention(pool: State<'_, DbPool>) -> Result<u32, AppError> {
+    let conn = db::get_conn(&pool)?;
+    Ok(load_retention_days(&conn)?)
+}
+
+// Records already older than the new period are purged straight away
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn set_recycle_bin_retention(
+    pool: State<'_, DbPool>,
+    days: u32,
+) -> Result<u32, AppError> {
+    if days == 0 {
+        return Err(AppError::validation(
+            "days",
+            "Retention must be at least one day",
+        ));
+    }
+    let conn = db::get_conn(&pool)?;
+    set_setting(&conn, SETTING_RETENTION_DAYS, &days.to_string())?;
+    purge_expired(&conn)?;
+    Ok(load_retention_days(&conn)?)
+}
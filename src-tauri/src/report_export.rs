@@ -0,0 +1,400 @@
+use std::collections::BTreeMap;
+
+use chrono::{Datelike, NaiveDate};
+use rusqlite::types::Type;
+use rusqlite::{params, Connection};
+use rust_xlsxwriter::{ExcelDateTime, Format, FormatAlign, FormatBorder, Workbook, Worksheet};
+use serde::{Deserialize, Serialize};
+use tauri::State;
+
+use crate::cancellation::Cancellations;
+use crate::db::{self, DbPool};
+use crate::error::AppError;
+use crate::invoices::{InvoiceStatus, INVOICE_DATE_FORMAT};
+
+const CURRENCY_FORMAT: &str = "₹#,##0.00";
+const DATE_FORMAT: &str = "dd-mm-yyyy";
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ReportType {
+    SalesRegister,
+    CustomerWise,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ReportFilters {
+    pub company_id: i64,
+    pub from_date: String,
+    pub to_date: String,
+    pub customer_id: Option<i64>,
+    // Defaults to issued invoices only
+    pub status: Option<InvoiceStatus>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ReportExportResult {
+    pub path: String,
+    pub sheets: Vec<String>,
+    pub row_count: usize,
+}
+
+struct ReportRow {
+    invoice_number: String,
+    invoice_date: NaiveDate,
+    customer_id: i64,
+    customer_name: String,
+    gst_no: String,
+    place_of_supply: String,
+    taxable_value: f64,
+    cgst_amount: f64,
+    sgst_amount: f64,
+    igst_amount: f64,
+    round_off: f64,
+    total_amount: f64,
+}
+
+pub(crate) struct Formats {
+    pub(crate) header: Format,
+    pub(crate) text: Format,
+    pub(crate) date: Format,
+    pub(crate) currency: Format,
+    pub(crate) total_label: Format,
+    pub(crate) total_currency: Format,
+}
+
+impl Formats {
+    pub(crate) fn new() -> Self {
+        let header = Format::new()
+            .set_bold()
+            .set_background_color("#D9E1F2")
+            .set_border_bottom(FormatBorder::Thin)
+            .set_align(FormatAlign::Center);
+        let total = Format::new().set_bold().set_border_top(FormatBorder::Double);
+        Formats {
+            header,
+            text: Format::new(),
+            date: Format::new().set_num_format(DATE_FORMAT),
+            currency: Format::new().set_num_format(CURRENCY_FORMAT),
+            total_label: total.clone(),
+            total_currency: total.set_num_format(CURRENCY_FORMAT),
+        }
+    }
+}
+
+// Running sums of the six amount columns
+#[derive(Default, Clone, Copy)]
+struct Amounts {
+    taxable_value: f64,
+    cgst_amount: f64,
+    sgst_amount: f64,
+    igst_amount: f64,
+    round_off: f64,
+    total_amount: f64,
+}
+
+impl Amounts {
+    fn add(&mut self, row: &ReportRow) {
+        self.taxable_value += row.taxable_value;
+        self.cgst_amount += row.cgst_amount;
+        self.sgst_amount += row.sgst_amount;
+        self.igst_amount += row.igst_amount;
+        self.round_off += row.round_off;
+        self.total_amount += row.total_amount;
+    }
+
+    fn values(&self) -> [f64; 6] {
+        [
+            self.taxable_value,
+            self.cgst_amount,
+            self.sgst_amount,
+            self.igst_amount,
+            self.round_off,
+            self.total_amount,
+        ]
+    }
+}
+
+fn load_rows(conn: &Connection, filters: &ReportFilters) -> Result<Vec<ReportRow>, AppError> {
+    let from = NaiveDate::parse_from_str(filters.from_date.trim(), INVOICE_DATE_FORMAT)
+        .map_err(|_| AppError::validation("from_date", "From date must be in YYYY-MM-DD format"))?;
+    let to = NaiveDate::parse_from_str(filters.to_date.trim(), INVOICE_DATE_FORMAT)
+        .map_err(|_| AppError::validation("to_date", "To date must be in YYYY-MM-DD format"))?;
+    if from > to {
+        return Err(AppError::validation(
+            "to_date",
+            "From date must be on or before the to date",
+        ));
+    }
+    let status = filters.status.unwrap_or(InvoiceStatus::Issued);
+
+    let mut stmt = conn.prepare(
+        "SELECT i.invoice_number, i.invoice_date, i.customer_id, c.report_customer, c.gst_no,
+                    i.place_of_supply, i.taxable_value, i.cgst_amount, i.sgst_amount,
+                    i.igst_amount, i.round_off, i.total_amount
+             FROM invoices i
+             JOIN customers c ON c.id = i.customer_id
+             WHERE i.company_id = ?1 AND i.status = ?2
+               AND i.invoice_date BETWEEN ?3 AND ?4
+               AND (?5 IS NULL OR i.customer_id = ?5)
+             ORDER BY i.invoice_date, i.invoice_number",
+    )?;
+    let rows = stmt
+        .query_map(
+            params![
+                filters.company_id,
+                status.as_str(),
+                from.format(INVOICE_DATE_FORMAT).to_string(),
+                to.format(INVOICE_DATE_FORMAT).to_string(),
+                filters.customer_id
+            ],
+            |row| {
+                let date: String = row.get(1)?;
+                let invoice_date = NaiveDate::parse_from_str(&date, INVOICE_DATE_FORMAT)
+                    .map_err(|e| {
+                        rusqlite::Error::FromSqlConversionFailure(1, Type::Text, Box::new(e))
+                    })?;
+                Ok(ReportRow {
+                    invoice_number: row.get(0)?,
+                    invoice_date,
+                    customer_id: row.get(2)?,
+                    customer_name: row.get(3)?,
+                    gst_no: row.get(4)?,
+                    place_of_supply: row.get(5)?,
+                    taxable_value: row.get(6)?,
+                    cgst_amount: row.get(7)?,
+                    sgst_amount: row.get(8)?,
+                    igst_amount: row.get(9)?,
+                    round_off: row.get(10)?,
+                    total_amount: row.get(11)?,
+                })
+            },
+        )?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(rows)
+}
+
+// One sheet per calendar month, in date order
+fn group_by_month(rows: &[ReportRow]) -> BTreeMap<(i32, u32), Vec<&ReportRow>> {
+    let mut months: BTreeMap<(i32, u32), Vec<&ReportRow>> = BTreeMap::new();
+    for row in rows {
+        months
+            .entry((row.invoice_date.year(), row.invoice_date.month()))
+            .or_default()
+            .push(row);
+    }
+    months
+}
+
+pub(crate) fn xlsx_error(e: rust_xlsxwriter::XlsxError) -> String {
+    format!("Failed to write workbook: {}", e)
+}
+
+pub(crate) fn write_headers(
+    sheet: &mut Worksheet,
+    formats: &Formats,
+    headers: &[(&str, f64)],
+) -> Result<(), String> {
+    for (col, (title, width)) in headers.iter().enumerate() {
+        let col = col as u16;
+        sheet
+            .write_string_with_format(0, col, *title, &formats.header)
+            .map_err(xlsx_error)?;
+        sheet.set_column_width(col, *width).map_err(xlsx_error)?;
+    }
+    sheet.set_freeze_panes(1, 0).map_err(xlsx_error)?;
+    Ok(())
+}
+
+fn write_amounts(
+    sheet: &mut Worksheet,
+    row: u32,
+    first_col: u16,
+    values: [f64; 6],
+    format: &Format,
+) -> Result<(), String> {
+    for (offset, value) in values.iter().enumerate() {
+        sheet
+            .write_number_with_format(row, first_col + offset as u16, *value, format)
+            .map_err(xlsx_error)?;
+    }
+    Ok(())
+}
+
+fn write_sales_register(
+    sheet: &mut Worksheet,
+    formats: &Formats,
+    rows: &[&ReportRow],
+) -> Result<(), String> {
+    write_headers(
+        sheet,
+        formats,
+        &[
+            ("Invoice No", 16.0),
+            ("Date", 12.0),
+            ("Customer", 36.0),
+            ("GSTIN", 18.0),
+            ("Place of Supply", 14.0),
+            ("Taxable Value", 16.0),
+            ("CGST", 14.0),
+            ("SGST", 14.0),
+            ("IGST", 14.0),
+            ("Round Off", 12.0),
+            ("Total", 16.0),
+        ],
+    )?;
+
+    let mut totals = Amounts::default();
+    let mut line = 1u32;
+    for row in rows {
+        let date = ExcelDateTime::from_ymd(
+            row.invoice_date.year() as u16,
+            row.invoice_date.month() as u8,
+            row.invoice_date.day() as u8,
+        )
+        .map_err(xlsx_error)?;
+        sheet
+            .write_string_with_format(line, 0, &row.invoice_number, &formats.text)
+            .map_err(xlsx_error)?;
+        sheet
+            .write_datetime_with_format(line, 1, &date, &formats.date)
+            .map_err(xlsx_error)?;
+        sheet
+            .write_string_with_format(line, 2, &row.customer_name, &formats.text)
+            .map_err(xlsx_error)?;
+        sheet
+            .write_string_with_format(line, 3, &row.gst_no, &formats.text)
+            .map_err(xlsx_error)?;
+        sheet
+            .write_string_with_format(line, 4, &row.place_of_supply, &formats.text)
+            .map_err(xlsx_error)?;
+        let mut amounts = Amounts::default();
+        amounts.add(row);
+        write_amounts(sheet, line, 5, amounts.values(), &formats.currency)?;
+        totals.add(row);
+        line += 1;
+    }
+
+    sheet
+        .write_string_with_format(line, 0, "Total", &formats.total_label)
+        .map_err(xlsx_error)?;
+    write_amounts(sheet, line, 5, totals.values(), &formats.total_currency)
+}
+
+fn write_customer_wise(
+    sheet: &mut Worksheet,
+    formats: &Formats,
+    rows: &[&ReportRow],
+) -> Result<(), String> {
+    write_headers(
+        sheet,
+        formats,
+        &[
+            ("Customer", 36.0),
+            ("GSTIN", 18.0),
+            ("Invoices", 10.0),
+            ("Taxable Value", 16.0),
+            ("CGST", 14.0),
+            ("SGST", 14.0),
+            ("IGST", 14.0),
+            ("Round Off", 12.0),
+            ("Total", 16.0),
+        ],
+    )?;
+
+    // Keyed by name first so the sheet is alphabetical
+    let mut customers: BTreeMap<(String, i64), (&ReportRow, usize, Amounts)> = BTreeMap::new();
+    let mut totals = Amounts::default();
+    for row in rows {
+        let entry = customers
+            .entry((row.customer_name.to_lowercase(), row.customer_id))
+            .or_insert_with(|| (*row, 0, Amounts::default()));
+        entry.1 += 1;
+        entry.2.add(row);
+        totals.add(row);
+    }
+
+    let mut line = 1u32;
+    for (first, count, amounts) in customers.values() {
+        sheet
+            .write_string_with_format(line, 0, &first.customer_name, &formats.text)
+            .map_err(xlsx_error)?;
+        sheet
+            .write_string_with_format(line, 1, &first.gst_no, &formats.text)
+            .map_err(xlsx_error)?;
+        sheet
+            .write_number(line, 2, *count as f64)
+            .map_err(xlsx_error)?;
+        write_amounts(sheet, line, 3, amounts.values(), &formats.currency)?;
+        line += 1;
+    }
+
+    sheet
+        .write_string_with_format(line, 0, "Total", &formats.total_label)
+        .map_err(xlsx_error)?;
+    sheet
+        .write_number_with_format(line, 2, rows.len() as f64, &formats.total_label)
+        .map_err(xlsx_error)?;
+    write_amounts(sheet, line, 3, totals.values(), &formats.total_currency)
+}
+
+pub fn write_report(
+    conn: &Connection,
+    report_type: ReportType,
+    filters: &ReportFilters,
+    path: String,
+) -> Result<ReportExportResult, String> {
+    let rows = load_rows(conn, filters)?;
+
+    let formats = Formats::new();
+    let mut workbook = Workbook::new();
+    let mut sheets = Vec::new();
+
+    let months = group_by_month(&rows);
+    if months.is_empty() {
+        // Still produce a workbook with headers so the user sees the report shape
+        let sheet = workbook.add_worksheet();
+        sheet.set_name("No data").map_err(xlsx_error)?;
+        sheets.push("No data".to_string());
+        match report_type {
+            ReportType::SalesRegister => write_sales_register(sheet, &formats, &[])?,
+            ReportType::CustomerWise => write_customer_wise(sheet, &formats, &[])?,
+        }
+    }
+    for ((year, month), month_rows) in &months {
+        let name = NaiveDate::from_ymd_opt(*year, *month, 1)
+            .map(|d| d.format("%b %Y").to_string())
+            .unwrap_or_else(|| format!("{}-{:02}", year, month));
+        let sheet = workbook.add_worksheet();
+        sheet.set_name(&name).map_err(xlsx_error)?;
+        match report_type {
+            ReportType::SalesRegister => write_sales_register(sheet, &formats, month_rows)?,
+            ReportType::CustomerWise => write_customer_wise(sheet, &formats, month_rows)?,
+        }
+        sheets.push(name);
+    }
+
+    workbook.save(&path).map_err(xlsx_error)?;
+
+    Ok(ReportExportResult {
+        path,
+        sheets,
+        row_count: rows.len(),
+    })
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn export_report_xlsx(
+    pool: State<'_, DbPool>,
+    cancellations: State<'_, Cancellations>,
+    report_type: ReportType,
+    filters: ReportFilters,
+    path: String,
+    operation_id: Option<String>,
+) -> Result<ReportExportResult, AppError> {
+    let operation = cancellations.start(operation_id.as_deref());
+    let conn = db::get_conn(&pool)?;
+    let _watch = operation.token.watch(&conn);
+    write_report(&conn, report_type, &filters, path).map_err(|e| operation.token.map_err(e))
+}
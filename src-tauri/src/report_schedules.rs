@@ -0,0 +1,850 @@
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use chrono::{Datelike, Local, NaiveDate, NaiveDateTime, Timelike};
+use rusqlite::{params, Connection, OptionalExtension, Row};
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Emitter, Manager, State};
+
+use crate::custom_reports::{self, ExportFormat};
+use crate::db::{self, DbPool};
+use crate::email::{self, ReportAttachment};
+use crate::error::AppError;
+use crate::financial_years;
+use crate::invoices::INVOICE_DATE_FORMAT;
+use crate::report_export::{self, ReportFilters, ReportType};
+
+const TIMESTAMP_FORMAT: &str = "%Y-%m-%d %H:%M:%S";
+const MAX_NAME_LENGTH: usize = 100;
+// Run history kept per schedule; older runs are dropped as new ones are recorded
+const MAX_RUNS_KEPT: i64 = 100;
+// How far ahead to look for the next matching time before treating a schedule as never due
+const MAX_LOOKAHEAD_DAYS: i64 = 5 * 366;
+
+// The scheduler checks for due schedules once a minute, the resolution of a cron expression
+const CHECK_INTERVAL: Duration = Duration::from_secs(60);
+
+pub const EVENT_REPORT_SCHEDULE_COMPLETED: &str = "report-schedule-completed";
+pub const EVENT_REPORT_SCHEDULE_FAILED: &str = "report-schedule-failed";
+
+const XLSX_CONTENT_TYPE: &str =
+    "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet";
+const CSV_CONTENT_TYPE: &str = "text/csv";
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ScheduledReportKind {
+    SalesRegister,
+    CustomerWise,
+    Custom,
+}
+
+impl ScheduledReportKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ScheduledReportKind::SalesRegister => "sales_register",
+            ScheduledReportKind::CustomerWise => "customer_wise",
+            ScheduledReportKind::Custom => "custom",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "sales_register" => Some(ScheduledReportKind::SalesRegister),
+            "customer_wise" => Some(ScheduledReportKind::CustomerWise),
+            "custom" => Some(ScheduledReportKind::Custom),
+            _ => None,
+        }
+    }
+
+    fn builtin(&self) -> Option<ReportType> {
+        match self {
+            ScheduledReportKind::SalesRegister => Some(ReportType::SalesRegister),
+            ScheduledReportKind::CustomerWise => Some(ReportType::CustomerWise),
+            ScheduledReportKind::Custom => None,
+        }
+    }
+}
+
+// Date range covered by a built-in report, relative to the day it runs
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ReportPeriod {
+    PreviousDay,
+    PreviousWeek,
+    PreviousMonth,
+    MonthToDate,
+    FinancialYearToDate,
+}
+
+impl ReportPeriod {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ReportPeriod::PreviousDay => "previous_day",
+            ReportPeriod::PreviousWeek => "previous_week",
+            ReportPeriod::PreviousMonth => "previous_month",
+            ReportPeriod::MonthToDate => "month_to_date",
+            ReportPeriod::FinancialYearToDate => "financial_year_to_date",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "previous_day" => Some(ReportPeriod::PreviousDay),
+            "previous_week" => Some(ReportPeriod::PreviousWeek),
+            "previous_month" => Some(ReportPeriod::PreviousMonth),
+            "month_to_date" => Some(ReportPeriod::MonthToDate),
+            "financial_year_to_date" => Some(ReportPeriod::FinancialYearToDate),
+            _ => None,
+        }
+    }
+
+    // Weeks run Monday to Sunday
+    fn range(&self, today: NaiveDate) -> Result<(NaiveDate, NaiveDate), String> {
+        let month_start = today.with_day(1).unwrap_or(today);
+        Ok(match self {
+            ReportPeriod::PreviousDay => {
+                let day = today.pred_opt().unwrap_or(today);
+                (day, day)
+            }
+            ReportPeriod::PreviousWeek => {
+                let monday =
+                    today - chrono::Duration::days(today.weekday().num_days_from_monday() as i64);
+                (monday - chrono::Duration::days(7), monday - chrono::Duration::days(1))
+            }
+            ReportPeriod::PreviousMonth => {
+                let last = month_start.pred_opt().unwrap_or(month_start);
+                (last.with_day(1).unwrap_or(last), last)
+            }
+            ReportPeriod::MonthToDate => (month_start, today),
+            ReportPeriod::FinancialYearToDate => {
+                let (start, _) =
+                    financial_years::fy_bounds(financial_years::fy_start_year(today))?;
+                (start, today)
+            }
+        })
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum RunStatus {
+    Succeeded,
+    Failed,
+}
+
+impl RunStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RunStatus::Succeeded => "succeeded",
+            RunStatus::Failed => "failed",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "succeeded" => Some(RunStatus::Succeeded),
+            "failed" => Some(RunStatus::Failed),
+            _ => None,
+        }
+    }
+}
+
+// A standard five-field cron expression: minute, hour, day of month, month and day of week.
+// Each field takes `*`, numbers, ranges (`1-5`), steps (`*/15`, `1-10/2`) and comma lists.
+// Day of week runs 0-7 with both 0 and 7 meaning Sunday.
+#[derive(Debug, Clone, Copy)]
+pub struct CronSchedule {
+    minutes: u64,
+    hours: u64,
+    days: u64,
+    months: u64,
+    weekdays: u64,
+    // As in cron, when both day fields are restricted a day matching either one is due
+    any_day: bool,
+    any_weekday: bool,
+}
+
+fn parse_cron_field(field: &str, label: &str, min: u32, max: u32) -> Result<u64, String> {
+    let mut mask = 0u64;
+    for part in field.split(',') {
+        let (range, step) = match part.split_once('/') {
+            Some((range, step)) => {
+                let step: u32 = step
+                    .parse()
+                    .ok()
+                    .filter(|step| *step > 0)
+                    .ok_or_else(|| format!("Invalid step '{}' in the {} field", step, label))?;
+                (range, step)
+            }
+            None => (part, 1),
+        };
+        let number = |value: &str| -> Result<u32, String> {
+            value
+                .parse()
+                .ok()
+                .filter(|value| (min..=max).contains(value))
+                .ok_or_else(|| {
+                    format!(
+                        "The {} field takes values from {} to {}, not '{}'",
+                        label, min, max, value
+                    )
+                })
+        };
+        let (start, end) = if range == "*" {
+            (min, max)
+        } else if let Some((start, end)) = range.split_once('-') {
+            (number(start)?, number(end)?)
+        } else if part.contains('/') {
+            // `5/15` means every 15 starting at 5
+            (number(range)?, max)
+        } else {
+            let value = number(range)?;
+            (value, value)
+        };
+        if start > end {
+            return Err(format!("Invalid range '{}' in the {} field", range, label));
+        }
+        for value in (start..=end).step_by(step as usize) {
+            mask |= 1 << value;
+        }
+    }
+    Ok(mask)
+}
+
+impl CronSchedule {
+    pub fn parse(expression: &str) -> Result<Self, String> {
+        let fields: Vec<&str> = expression.split_whitespace().collect();
+        if fields.len() != 5 {
+            return Err(
+                "A schedule needs five fields: minute, hour, day of month, month and day of week"
+                    .to_string(),
+            );
+        }
+        let mut weekdays = parse_cron_field(fields[4], "day of week", 0, 7)?;
+        if weekdays & (1 << 7) != 0 {
+            weekdays |= 1;
+        }
+        Ok(CronSchedule {
+            minutes: parse_cron_field(fields[0], "minute", 0, 59)?,
+            hours: parse_cron_field(fields[1], "hour", 0, 23)?,
+            days: parse_cron_field(fields[2], "day of month", 1, 31)?,
+            months: parse_cron_field(fields[3], "month", 1, 12)?,
+            weekdays,
+            any_day: fields[2] == "*",
+            any_weekday: fields[4] == "*",
+        })
+    }
+
+    fn matches_day(&self, date: NaiveDate) -> bool {
+        if self.months & (1 << date.month()) == 0 {
+            return false;
+        }
+        let day = self.days & (1 << date.day()) != 0;
+        let weekday = self.weekdays & (1 << date.weekday().num_days_from_sunday()) != 0;
+        match (self.any_day, self.any_weekday) {
+            (true, true) => true,
+            (true, false) => weekday,
+            (false, true) => day,
+            (false, false) => day || weekday,
+        }
+    }
+
+    // The first matching minute strictly after `after`, or None when nothing matches within
+    // the lookahead (for example `0 0 31 2 *`)
+    pub fn next_after(&self, after: NaiveDateTime) -> Option<NaiveDateTime> {
+        let start = after.with_second(0)?.with_nanosecond(0)? + chrono::Duration::minutes(1);
+        let mut date = start.date();
+        for _ in 0..MAX_LOOKAHEAD_DAYS {
+            if self.matches_day(date) {
+                let first_hour = if date == start.date() { start.hour() } else { 0 };
+                for hour in (first_hour..24).filter(|hour| self.hours & (1 << hour) != 0) {
+                    let first_minute = if date == start.date() && hour == start.hour() {
+                        start.minute()
+                    } else {
+                        0
+                    };
+                    if let Some(minute) =
+                        (first_minute..60).find(|minute| self.minutes & (1 << minute) != 0)
+                    {
+                        return date.and_hms_opt(hour, minute, 0);
+                    }
+                }
+            }
+            date = date.succ_opt()?;
+        }
+        None
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ReportSchedule {
+    pub id: i64,
+    pub company_id: i64,
+    pub name: String,
+    pub report_kind: ScheduledReportKind,
+    pub custom_report_id: Option<i64>,
+    pub period: Option<ReportPeriod>,
+    pub format: ExportFormat,
+    pub cron: String,
+    pub output_folder: Option<String>,
+    pub email_to: Option<String>,
+    pub enabled: bool,
+    pub last_run_at: Option<String>,
+    pub next_run_at: Option<String>,
+    pub created_at: Option<String>,
+    pub updated_at: Option<String>,
+}
+
+// Built-in reports take a period and are always xlsx; custom reports use their saved filters
+// and either format. A schedule writes to `output_folder`, emails `email_to`, or both.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ReportScheduleInput {
+    pub name: String,
+    pub report_kind: ScheduledReportKind,
+    pub custom_report_id: Option<i64>,
+    pub period: Option<ReportPeriod>,
+    pub format: Option<ExportFormat>,
+    pub cron: String,
+    pub output_folder: Option<String>,
+    pub email_to: Option<String>,
+    pub enabled: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ReportScheduleRun {
+    pub id: i64,
+    pub schedule_id: i64,
+    pub started_at: String,
+    pub finished_at: String,
+    pub status: RunStatus,
+    pub output_path: Option<String>,
+    pub emailed_to: Option<String>,
+    pub error: Option<String>,
+}
+
+// Payload of the completed and failed events
+#[derive(Debug, Serialize, Clone)]
+pub struct ReportScheduleEvent {
+    pub company_id: i64,
+    pub name: String,
+    pub run: ReportScheduleRun,
+}
+
+const SELECT_SCHEDULE: &str = "
+    SELECT id, company_id, name, report_kind, custom_report_id, period, format, cron,
+           output_folder, email_to, enabled, last_run_at, next_run_at, created_at, updated_at
+    FROM report_schedules";
+
+const SELECT_RUN: &str = "
+    SELECT id, schedule_id, started_at, finished_at, status, output_path, emailed_to, error
+    FROM report_schedule_runs";
+
+fn schedule_from_row(row: &Row) -> rusqlite::Result<ReportSchedule> {
+    let report_kind: String = row.get("report_kind")?;
+    let period: Option<String> = row.get("period")?;
+    let format: String = row.get("format")?;
+    Ok(ReportSchedule {
+        id: row.get("id")?,
+        company_id: row.get("company_id")?,
+        name: row.get("name")?,
+        report_kind: ScheduledReportKind::parse(&report_kind)
+            .unwrap_or(ScheduledReportKind::SalesRegister),
+        custom_report_id: row.get("custom_report_id")?,
+        period: period.as_deref().and_then(ReportPeriod::parse),
+        format: ExportFormat::parse(&format).unwrap_or(ExportFormat::Xlsx),
+        cron: row.get("cron")?,
+        output_folder: row.get("output_folder")?,
+        email_to: row.get("email_to")?,
+        enabled: row.get("enabled")?,
+        last_run_at: row.get("last_run_at")?,
+        next_run_at: row.get("next_run_at")?,
+        created_at: row.get("created_at")?,
+        updated_at: row.get("updated_at")?,
+    })
+}
+
+fn run_from_row(row: &Row) -> rusqlite::Result<ReportScheduleRun> {
+    let status: String = row.get("status")?;
+    Ok(ReportScheduleRun {
+        id: row.get("id")?,
+        schedule_id: row.get("schedule_id")?,
+        started_at: row.get("started_at")?,
+        finished_at: row.get("finished_at")?,
+        status: RunStatus::parse(&status).unwrap_or(RunStatus::Failed),
+        output_path: row.get("output_path")?,
+        emailed_to: row.get("emailed_to")?,
+        error: row.get("error")?,
+    })
+}
+
+pub fn get_schedule_by_id(
+    conn: &Connection,
+    id: i64,
+    company_id: i64,
+) -> Result<Option<ReportSchedule>, AppError> {
+    conn.query_row(
+        &format!("{} WHERE id = ?1 AND company_id = ?2", SELECT_SCHEDULE),
+        params![id, company_id],
+        schedule_from_row,
+    )
+    .optional()
+    .map_err(AppError::from)
+}
+
+fn due_schedules(conn: &Connection, now: NaiveDateTime) -> Result<Vec<ReportSchedule>, String> {
+    let mut stmt = conn
+        .prepare(&format!(
+            "{} WHERE enabled = 1 AND next_run_at IS NOT NULL AND next_run_at <= ?1
+             ORDER BY next_run_at, id",
+            SELECT_SCHEDULE
+        ))
+        .map_err(|e| e.to_string())?;
+    let schedules = stmt
+        .query_map(params![now.format(TIMESTAMP_FORMAT).to_string()], schedule_from_row)
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+    Ok(schedules)
+}
+
+fn next_run(cron: &str, after: NaiveDateTime) -> Option<String> {
+    CronSchedule::parse(cron)
+        .ok()?
+        .next_after(after)
+        .map(|at| at.format(TIMESTAMP_FORMAT).to_string())
+}
+
+fn trimmed(value: Option<&str>) -> Option<String> {
+    value
+        .map(str::trim)
+        .filter(|value| !value.is_empty())
+        .map(str::to_string)
+}
+
+// Returns the input with optional text trimmed and the format settled
+fn validate(
+    conn: &Connection,
+    company_id: i64,
+    input: ReportScheduleInput,
+) -> Result<ReportScheduleInput, AppError> {
+    let name = input.name.trim().to_string();
+    if name.is_empty() {
+        return Err(AppError::validation("name", "Name is required"));
+    }
+    if name.chars().count() > MAX_NAME_LENGTH {
+        return Err(AppError::validation(
+            "name",
+            format!("Name can be at most {} characters", MAX_NAME_LENGTH),
+        ));
+    }
+    let cron = input.cron.split_whitespace().collect::<Vec<_>>().join(" ");
+    CronSchedule::parse(&cron).map_err(|e| AppError::validation("cron", e))?;
+    if next_run(&cron, Local::now().naive_local()).is_none() {
+        return Err(AppError::validation("cron", "This schedule never comes due"));
+    }
+
+    let (custom_report_id, period, format) = match input.report_kind {
+        ScheduledReportKind::Custom => {
+            let id = input
+                .custom_report_id
+                .ok_or_else(|| AppError::validation("custom_report_id", "Choose a saved report"))?;
+            if custom_reports::get_custom_report_by_id(conn, id, company_id)?.is_none() {
+                return Err(AppError::not_found("Report not found"));
+            }
+            (Some(id), None, input.format.unwrap_or(ExportFormat::Xlsx))
+        }
+        _ => {
+            let period = input
+                .period
+                .ok_or_else(|| AppError::validation("period", "Choose the period to report on"))?;
+            if input.format == Some(ExportFormat::Csv) {
+                return Err(AppError::validation(
+                    "format",
+                    "Built-in reports can only be exported to Excel",
+                ));
+            }
+            (None, Some(period), ExportFormat::Xlsx)
+        }
+    };
+
+    let output_folder = trimmed(input.output_folder.as_deref());
+    if let Some(folder) = &output_folder {
+        if !Path::new(folder).is_dir() {
+            return Err(AppError::validation(
+                "output_folder",
+                format!("Folder {} does not exist", folder),
+            ));
+        }
+    }
+    let email_to = trimmed(input.email_to.as_deref());
+    if let Some(to) = &email_to {
+        email::parse_recipients(to)?;
+    }
+    if output_folder.is_none() && email_to.is_none() {
+        return Err(AppError::validation(
+            "output_folder",
+            "Choose a folder to save the report to or an address to email it to",
+        ));
+    }
+
+    Ok(ReportScheduleInput {
+        name,
+        report_kind: input.report_kind,
+        custom_report_id,
+        period,
+        format: Some(format),
+        cron,
+        output_folder,
+        email_to,
+        enabled: input.enabled,
+    })
+}
+
+fn file_name(schedule: &ReportSchedule, at: NaiveDateTime) -> String {
+    let name: String = schedule
+        .name
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '-' { c } else { '_' })
+        .collect();
+    format!("{}_{}.{}", name, at.format("%Y%m%d_%H%M"), schedule.format.as_str())
+}
+
+// Writes the report into the output folder, or a temporary file when it is only emailed
+fn generate(
+    conn: &Connection,
+    schedule: &ReportSchedule,
+    at: NaiveDateTime,
+) -> Result<PathBuf, String> {
+    let dir = match &schedule.output_folder {
+        Some(folder) => PathBuf::from(folder),
+        None => std::env::temp_dir(),
+    };
+    let path = dir.join(file_name(schedule, at));
+    let target = path.to_string_lossy().to_string();
+    match schedule.report_kind.builtin() {
+        Some(report_type) => {
+            let period = schedule
+                .period
+                .ok_or_else(|| "The schedule has no report period".to_string())?;
+            let (from, to) = period.range(at.date())?;
+            let filters = ReportFilters {
+                company_id: schedule.company_id,
+                from_date: from.format(INVOICE_DATE_FORMAT).to_string(),
+                to_date: to.format(INVOICE_DATE_FORMAT).to_string(),
+                customer_id: None,
+                status: None,
+            };
+            report_export::write_report(conn, report_type, &filters, target)?;
+        }
+        None => {
+            let id = schedule
+                .custom_report_id
+                .ok_or_else(|| "The schedule has no saved report".to_string())?;
+            custom_reports::export_saved_report(
+                conn,
+                id,
+                schedule.company_id,
+                schedule.format,
+                target,
+            )?;
+        }
+    }
+    Ok(path)
+}
+
+// Returns where the report was saved, if anywhere
+async fn produce(
+    pool: &DbPool,
+    schedule: &ReportSchedule,
+    at: NaiveDateTime,
+) -> Result<Option<String>, String> {
+    let path = {
+        let conn = db::get_conn(pool)?;
+        generate(&conn, schedule, at)?
+    };
+    let saved = schedule
+        .output_folder
+        .as_ref()
+        .map(|_| path.to_string_lossy().to_string());
+    let Some(to) = &schedule.email_to else {
+        return Ok(saved);
+    };
+
+    let bytes = std::fs::read(&path);
+    if saved.is_none() {
+        let _ = std::fs::remove_file(&path);
+    }
+    let attachment = ReportAttachment {
+        name: file_name(schedule, at),
+        content_type: match schedule.format {
+            ExportFormat::Csv => CSV_CONTENT_TYPE,
+            ExportFormat::Xlsx => XLSX_CONTENT_TYPE,
+        },
+        bytes: bytes.map_err(|e| format!("Failed to read the generated report: {}", e))?,
+    };
+    let generated = at.format(TIMESTAMP_FORMAT);
+    email::send_report_email(
+        pool,
+        schedule.company_id,
+        to,
+        format!("{} ({})", schedule.name, at.format(INVOICE_DATE_FORMAT)),
+        format!("Attached is the scheduled report {}, generated on {}.", schedule.name, generated),
+        attachment,
+    )
+    .await
+    .map_err(|e| format!("The report was generated but could not be emailed: {}", e))?;
+    Ok(saved)
+}
+
+// Records the outcome and moves the schedule on to its next due time. A failed run is not
+// retried; the schedule waits for its next slot.
+fn record_run(
+    conn: &Connection,
+    schedule: &ReportSchedule,
+    started: NaiveDateTime,
+    outcome: &Result<Option<String>, String>,
+) -> Result<ReportScheduleRun, String> {
+    let finished = Local::now().naive_local();
+    let (status, output_path, emailed_to, error) = match outcome {
+        Ok(path) => (RunStatus::Succeeded, path.clone(), schedule.email_to.clone(), None),
+        Err(e) => (RunStatus::Failed, None, None, Some(e.clone())),
+    };
+    conn.execute(
+        "INSERT INTO report_schedule_runs (schedule_id, started_at, finished_at, status,
+                                           output_path, emailed_to, error)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+        params![
+            schedule.id,
+            started.format(TIMESTAMP_FORMAT).to_string(),
+            finished.format(TIMESTAMP_FORMAT).to_string(),
+            status.as_str(),
+            output_path,
+            emailed_to,
+            error,
+        ],
+    )
+    .map_err(|e| e.to_string())?;
+    let run_id = conn.last_insert_rowid();
+    conn.execute(
+        "DELETE FROM report_schedule_runs
+         WHERE schedule_id = ?1
+           AND id NOT IN (SELECT id FROM report_schedule_runs WHERE schedule_id = ?1
+                          ORDER BY started_at DESC, id DESC LIMIT ?2)",
+        params![schedule.id, MAX_RUNS_KEPT],
+    )
+    .map_err(|e| e.to_string())?;
+    conn.execute(
+        "UPDATE report_schedules SET last_run_at = ?1, next_run_at = ?2 WHERE id = ?3",
+        params![
+            started.format(TIMESTAMP_FORMAT).to_string(),
+            next_run(&schedule.cron, finished),
+            schedule.id,
+        ],
+    )
+    .map_err(|e| e.to_string())?;
+    conn.query_row(&format!("{} WHERE id = ?1", SELECT_RUN), params![run_id], run_from_row)
+        .map_err(|e| e.to_string())
+}
+
+async fn execute(pool: &DbPool, schedule: &ReportSchedule) -> Result<ReportScheduleRun, String> {
+    let started = Local::now().naive_local();
+    let outcome = produce(pool, schedule, started).await;
+    let conn = db::get_conn(pool)?;
+    let run = record_run(&conn, schedule, started, &outcome)?;
+    match &outcome {
+        Ok(_) => tracing::info!(schedule_id = schedule.id, "scheduled report generated"),
+        Err(e) => tracing::warn!(schedule_id = schedule.id, error = %e, "scheduled report failed"),
+    }
+    Ok(run)
+}
+
+fn emit_run(app: &AppHandle, schedule: &ReportSchedule, run: ReportScheduleRun) {
+    let event = match run.status {
+        RunStatus::Succeeded => EVENT_REPORT_SCHEDULE_COMPLETED,
+        RunStatus::Failed => EVENT_REPORT_SCHEDULE_FAILED,
+    };
+    let payload = ReportScheduleEvent {
+        company_id: schedule.company_id,
+        name: schedule.name.clone(),
+        run,
+    };
+    let _ = app.emit(event, payload);
+}
+
+// Background thread started at launch. Schedules whose time passed while the app was closed
+// run once on the first check and then continue from their next slot.
+pub fn start_scheduler(app: AppHandle) {
+    std::thread::spawn(move || loop {
+        let pool = app.state::<DbPool>().inner().clone();
+        let due = db::get_conn(&pool)
+            .map_err(String::from)
+            .and_then(|conn| due_schedules(&conn, Local::now().naive_local()))
+            .unwrap_or_default();
+        for schedule in due {
+            if let Ok(run) = tauri::async_runtime::block_on(execute(&pool, &schedule)) {
+                emit_run(&app, &schedule, run);
+            }
+        }
+        std::thread::sleep(CHECK_INTERVAL);
+    });
+}
+
+fn map_write_error(e: rusqlite::Error) -> AppError {
+    let message = e.to_string();
+    if message.contains("UNIQUE constraint failed: report_schedules") {
+        return AppError::conflict("name", "A schedule with this name already exists");
+    }
+    AppError::from(e)
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn list_report_schedules(
+    pool: State<'_, DbPool>,
+    company_id: i64,
+) -> Result<Vec<ReportSchedule>, AppError> {
+    let conn = db::get_conn(&pool)?;
+    let mut stmt =
+        conn.prepare(&format!("{} WHERE company_id = ?1 ORDER BY name", SELECT_SCHEDULE))?;
+    let schedules =
+        stmt.query_map(params![company_id], schedule_from_row)?.collect::<Result<Vec<_>, _>>()?;
+    Ok(schedules)
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn create_report_schedule(
+    pool: State<'_, DbPool>,
+    company_id: i64,
+    schedule: ReportScheduleInput,
+) -> Result<ReportSchedule, AppError> {
+    let conn = db::get_conn(&pool)?;
+    let schedule = validate(&conn, company_id, schedule)?;
+    let next_run_at = schedule
+        .enabled
+        .then(|| next_run(&schedule.cron, Local::now().naive_local()))
+        .flatten();
+    conn.execute(
+        "INSERT INTO report_schedules (company_id, name, report_kind, custom_report_id, period,
+                                       format, cron, output_folder, email_to, enabled,
+                                       next_run_at)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)",
+        params![
+            company_id,
+            schedule.name,
+            schedule.report_kind.as_str(),
+            schedule.custom_report_id,
+            schedule.period.map(|period| period.as_str()),
+            schedule.format.unwrap_or(ExportFormat::Xlsx).as_str(),
+            schedule.cron,
+            schedule.output_folder,
+            schedule.email_to,
+            schedule.enabled,
+            next_run_at,
+        ],
+    )
+    .map_err(map_write_error)?;
+    get_schedule_by_id(&conn, conn.last_insert_rowid(), company_id)?
+        .ok_or_else(|| AppError::not_found("Schedule not found after creation"))
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn update_report_schedule(
+    pool: State<'_, DbPool>,
+    id: i64,
+    company_id: i64,
+    schedule: ReportScheduleInput,
+) -> Result<ReportSchedule, AppError> {
+    let conn = db::get_conn(&pool)?;
+    if get_schedule_by_id(&conn, id, company_id)?.is_none() {
+        return Err(AppError::not_found("Schedule not found"));
+    }
+    let schedule = validate(&conn, company_id, schedule)?;
+    let next_run_at = schedule
+        .enabled
+        .then(|| next_run(&schedule.cron, Local::now().naive_local()))
+        .flatten();
+    conn.execute(
+        "UPDATE report_schedules
+         SET name = ?1, report_kind = ?2, custom_report_id = ?3, period = ?4, format = ?5,
+             cron = ?6, output_folder = ?7, email_to = ?8, enabled = ?9, next_run_at = ?10,
+             updated_at = CURRENT_TIMESTAMP
+         WHERE id = ?11 AND company_id = ?12",
+        params![
+            schedule.name,
+            schedule.report_kind.as_str(),
+            schedule.custom_report_id,
+            schedule.period.map(|period| period.as_str()),
+            schedule.format.unwrap_or(ExportFormat::Xlsx).as_str(),
+            schedule.cron,
+            schedule.output_folder,
+            schedule.email_to,
+            schedule.enabled,
+            next_run_at,
+            id,
+            company_id,
+        ],
+    )
+    .map_err(map_write_error)?;
+    get_schedule_by_id(&conn, id, company_id)?
+        .ok_or_else(|| AppError::not_found("Schedule not found"))
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn delete_report_schedule(
+    pool: State<'_, DbPool>,
+    id: i64,
+    company_id: i64,
+) -> Result<(), AppError> {
+    let conn = db::get_conn(&pool)?;
+    let deleted = conn.execute(
+        "DELETE FROM report_schedules WHERE id = ?1 AND company_id = ?2",
+        params![id, company_id],
+    )?;
+    if deleted == 0 {
+        return Err(AppError::not_found("Schedule not found"));
+    }
+    Ok(())
+}
+
+// Runs a schedule straight away, whether or not it is enabled. The run is recorded and the
+// usual event is emitted, so the history looks the same as for a scheduled run.
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn run_report_schedule_now(
+    pool: State<'_, DbPool>,
+    app: AppHandle,
+    id: i64,
+    company_id: i64,
+) -> Result<ReportScheduleRun, AppError> {
+    let schedule = {
+        let conn = db::get_conn(&pool)?;
+        get_schedule_by_id(&conn, id, company_id)?
+            .ok_or_else(|| AppError::not_found("Schedule not found"))?
+    };
+    let run = execute(&pool, &schedule).await?;
+    emit_run(&app, &schedule, run.clone());
+    Ok(run)
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn list_report_schedule_runs(
+    pool: State<'_, DbPool>,
+    id: i64,
+    company_id: i64,
+) -> Result<Vec<ReportScheduleRun>, AppError> {
+    let conn = db::get_conn(&pool)?;
+    if get_schedule_by_id(&conn, id, company_id)?.is_none() {
+        return Err(AppError::not_found("Schedule not found"));
+    }
+    let mut stmt = conn.prepare(&format!(
+        "{} WHERE schedule_id = ?1 ORDER BY started_at DESC, id DESC",
+        SELECT_RUN
+    ))?;
+    let runs = stmt.query_map(params![id], run_from_row)?.collect::<Result<Vec<_>, _>>()?;
+    Ok(runs)
+}
@@ -0,0 +1,770 @@
+use chrono::{Datelike, Months, NaiveDate};
+use rusqlite::types::Value;
+use rusqlite::{params, params_from_iter, Connection};
+use rust_xlsxwriter::Workbook;
+use serde::{Deserialize, Serialize};
+use tauri::State;
+
+use crate::companies;
+use crate::db::{self, DbPool};
+use crate::error::AppError;
+use crate::gstr1::{self, B2CL_THRESHOLD, EXPORT_STATE_CODE};
+use crate::invoices::{round2, InvoiceStatus, INVOICE_DATE_FORMAT};
+use crate::report_export::{write_headers, xlsx_error, Formats, ReportExportResult};
+
+const DEFAULT_PAGE_SIZE: u32 = 100;
+const MAX_PAGE_SIZE: u32 = 1000;
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum SalesRegisterSort {
+    #[default]
+    InvoiceDate,
+    InvoiceNumber,
+    CustomerName,
+    TaxableValue,
+    TotalAmount,
+}
+
+impl SalesRegisterSort {
+    // Only these fixed expressions ever reach the ORDER BY clause
+    fn column(&self) -> &'static str {
+        match self {
+            SalesRegisterSort::InvoiceDate => "i.invoice_date",
+            SalesRegisterSort::InvoiceNumber => "i.invoice_number",
+            SalesRegisterSort::CustomerName => "c.report_customer COLLATE NOCASE",
+            SalesRegisterSort::TaxableValue => "i.taxable_value",
+            SalesRegisterSort::TotalAmount => "i.total_amount",
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Default)]
+pub struct SalesRegisterFilters {
+    pub customer_id: Option<i64>,
+    pub category_id: Option<i64>,
+    // Matched against the invoice's place of supply
+    pub state_code: Option<String>,
+    // Defaults to issued invoices only
+    pub status: Option<InvoiceStatus>,
+    // Some(true) for reverse charge invoices only, Some(false) to leave them out
+    pub reverse_charge: Option<bool>,
+    #[serde(default)]
+    pub sort_by: SalesRegisterSort,
+    #[serde(default)]
+    pub descending: bool,
+    // 1-based
+    pub page: Option<u32>,
+    pub page_size: Option<u32>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SalesRegisterRow {
+    pub invoice_id: i64,
+    pub invoice_number: String,
+    pub invoice_date: String,
+    pub customer_id: i64,
+    pub customer_name: String,
+    pub gst_no: String,
+    pub category_name: Option<String>,
+    pub place_of_supply: String,
+    pub taxable_value: f64,
+    pub cgst_amount: f64,
+    pub sgst_amount: f64,
+    pub igst_amount: f64,
+    pub cess_amount: f64,
+    pub round_off: f64,
+    pub total_amount: f64,
+    pub reverse_charge: bool,
+    // Tax payable by the recipient; the supplier's tax amounts above are zero
+    pub reverse_charge_tax: f64,
+}
+
+// Grand totals over every matching invoice, not just the current page
+#[derive(Debug, Serialize, Deserialize, Default)]
+pub struct SalesRegisterTotals {
+    pub invoice_count: i64,
+    pub taxable_value: f64,
+    pub cgst_amount: f64,
+    pub sgst_amount: f64,
+    pub igst_amount: f64,
+    pub cess_amount: f64,
+    pub round_off: f64,
+    pub total_amount: f64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SalesRegister {
+    pub rows: Vec<SalesRegisterRow>,
+    pub totals: SalesRegisterTotals,
+    // Matching reverse charge invoices, with their tax amounts taken from the tax the
+    // recipient pays; they are also part of `totals`, at zero tax
+    pub reverse_charge: SalesRegisterTotals,
+    pub page: u32,
+    pub page_size: u32,
+    pub total_pages: u32,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SummaryCategory {
+    pub id: Option<i64>,
+    pub name: String,
+}
+
+// Matrices are indexed [month][category], following the `months` and `categories` order
+#[derive(Debug, Serialize, Deserialize)]
+pub struct MonthlyCategorySummary {
+    // "YYYY-MM", every month in the range including empty ones
+    pub months: Vec<String>,
+    pub categories: Vec<SummaryCategory>,
+    pub taxable_value: Vec<Vec<f64>>,
+    pub tax_amount: Vec<Vec<f64>>,
+    pub month_totals: Vec<f64>,
+    pub category_totals: Vec<f64>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CustomerSales {
+    pub customer_id: i64,
+    pub customer_name: String,
+    pub gst_no: String,
+    pub category_name: Option<String>,
+    pub invoice_count: i64,
+    pub taxable_value: f64,
+    pub total_amount: f64,
+    pub average_invoice_value: f64,
+    // Same dates one year earlier
+    pub previous_total_amount: f64,
+    // Percentage change; None when there were no sales in the previous period
+    pub growth_percent: Option<f64>,
+}
+
+// Registration type buckets following GSTR-1 tables 4 (B2B), 5 (B2C large), 7 (B2C small) and 6A
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "snake_case")]
+pub enum SupplyType {
+    B2b,
+    B2cLarge,
+    B2cSmall,
+    Export,
+}
+
+impl SupplyType {
+    fn parse(value: &str) -> Option<Self> {
+        match value {
+            "b2b" => Some(SupplyType::B2b),
+            "b2c_large" => Some(SupplyType::B2cLarge),
+            "b2c_small" => Some(SupplyType::B2cSmall),
+            "export" => Some(SupplyType::Export),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct StateSupplyRow {
+    pub state_code: String,
+    pub state_name: Option<String>,
+    pub supply_type: SupplyType,
+    pub inter_state: bool,
+    pub invoice_count: i64,
+    pub taxable_value: f64,
+    pub cgst_amount: f64,
+    pub sgst_amount: f64,
+    pub igst_amount: f64,
+    pub cess_amount: f64,
+    pub total_amount: f64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SupplyTypeTotal {
+    pub supply_type: SupplyType,
+    pub invoice_count: i64,
+    pub taxable_value: f64,
+    pub total_tax: f64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct StateSupplySplit {
+    pub period: String,
+    pub rows: Vec<StateSupplyRow>,
+    pub totals: Vec<SupplyTypeTotal>,
+}
+
+fn parse_dates(from: &str, to: &str) -> Result<(NaiveDate, NaiveDate), AppError> {
+    let from = NaiveDate::parse_from_str(from.trim(), INVOICE_DATE_FORMAT)
+        .map_err(|_| AppError::validation("from", "From date must be in YYYY-MM-DD format"))?;
+    let to = NaiveDate::parse_from_str(to.trim(), INVOICE_DATE_FORMAT)
+        .map_err(|_| AppError::validation("to", "To date must be in YYYY-MM-DD format"))?;
+    if from > to {
+        return Err(AppError::validation(
+            "to",
+            "From date must be on or before the to date",
+        ));
+    }
+    Ok((from, to))
+}
+
+fn parse_range(from: &str, to: &str) -> Result<(String, String), String> {
+    let (from, to) = parse_dates(from, to)?;
+    Ok((
+        from.format(INVOICE_DATE_FORMAT).to_string(),
+        to.format(INVOICE_DATE_FORMAT).to_string(),
+    ))
+}
+
+// Shared FROM/WHERE for the page and totals queries, with its positional parameters
+fn register_filter(
+    company_id: i64,
+    from: String,
+    to: String,
+    filters: &SalesRegisterFilters,
+) -> (String, Vec<Value>) {
+    let status = filters.status.unwrap_or(InvoiceStatus::Issued);
+    let mut sql = "FROM invoices i
+         JOIN customers c ON c.id = i.customer_id
+         LEFT JOIN categories cat ON cat.id = c.category_id
+         WHERE i.company_id = ? AND i.deleted_at IS NULL AND i.status = ?
+           AND i.invoice_date BETWEEN ? AND ?"
+        .to_string();
+    let mut values = vec![
+        Value::Integer(company_id),
+        Value::Text(status.as_str().to_string()),
+        Value::Text(from),
+        Value::Text(to),
+    ];
+    if let Some(customer_id) = filters.customer_id {
+        sql.push_str(" AND i.customer_id = ?");
+        values.push(Value::Integer(customer_id));
+    }
+    if let Some(category_id) = filters.category_id {
+        sql.push_str(" AND c.category_id = ?");
+        values.push(Value::Integer(category_id));
+    }
+    if let Some(state_code) = filters.state_code.as_deref().map(str::trim) {
+        if !state_code.is_empty() {
+            sql.push_str(" AND i.place_of_supply = ?");
+            values.push(Value::Text(state_code.to_string()));
+        }
+    }
+    if let Some(reverse_charge) = filters.reverse_charge {
+        sql.push_str(" AND i.reverse_charge = ?");
+        values.push(Value::Integer(reverse_charge as i64));
+    }
+    (sql, values)
+}
+
+// Totals over the filtered invoices. For the reverse charge section only those invoices are
+// summed, and their tax columns carry the tax payable by the recipient.
+fn register_totals(
+    conn: &Connection,
+    filter_sql: &str,
+    values: &[Value],
+    reverse_charge: bool,
+) -> Result<SalesRegisterTotals, AppError> {
+    let ((cgst, sgst, igst), condition) = if reverse_charge {
+        (
+            ("i.rcm_cgst_amount", "i.rcm_sgst_amount", "i.rcm_igst_amount"),
+            " AND i.reverse_charge = 1",
+        )
+    } else {
+        (("i.cgst_amount", "i.sgst_amount", "i.igst_amount"), "")
+    };
+    conn.query_row(
+        &format!(
+            "SELECT COUNT(*), COALESCE(SUM(i.taxable_value), 0),
+                    COALESCE(SUM({}), 0), COALESCE(SUM({}), 0),
+                    COALESCE(SUM({}), 0), COALESCE(SUM(i.cess_amount), 0),
+                    COALESCE(SUM(i.round_off), 0), COALESCE(SUM(i.total_amount), 0)
+             {}{}",
+            cgst, sgst, igst, filter_sql, condition
+        ),
+        params_from_iter(values.iter()),
+        |row| {
+            Ok(SalesRegisterTotals {
+                invoice_count: row.get(0)?,
+                taxable_value: round2(row.get(1)?),
+                cgst_amount: round2(row.get(2)?),
+                sgst_amount: round2(row.get(3)?),
+                igst_amount: round2(row.get(4)?),
+                cess_amount: round2(row.get(5)?),
+                round_off: round2(row.get(6)?),
+                total_amount: round2(row.get(7)?),
+            })
+        },
+    )
+    .map_err(AppError::from)
+}
+
+pub fn load_sales_register(
+    conn: &Connection,
+    company_id: i64,
+    from: &str,
+    to: &str,
+    filters: &SalesRegisterFilters,
+) -> Result<SalesRegister, AppError> {
+    let (from, to) = parse_range(from, to)?;
+    let page_size = filters.page_size.unwrap_or(DEFAULT_PAGE_SIZE).clamp(1, MAX_PAGE_SIZE);
+    let page = filters.page.unwrap_or(1).max(1);
+    let (filter_sql, values) = register_filter(company_id, from, to, filters);
+
+    let totals = register_totals(conn, &filter_sql, &values, false)?;
+    let reverse_charge = register_totals(conn, &filter_sql, &values, true)?;
+
+    let direction = if filters.descending { "DESC" } else { "ASC" };
+    // The id tie-break keeps page boundaries stable between requests
+    let sql = format!(
+        "SELECT i.id, i.invoice_number, i.invoice_date, i.customer_id, c.report_customer,
+                c.gst_no, cat.name, i.place_of_supply, i.taxable_value, i.cgst_amount,
+                i.sgst_amount, i.igst_amount, i.cess_amount, i.round_off, i.total_amount,
+                i.reverse_charge, i.rcm_cgst_amount + i.rcm_sgst_amount + i.rcm_igst_amount
+         {} ORDER BY {} {}, i.id {} LIMIT {} OFFSET {}",
+        filter_sql,
+        filters.sort_by.column(),
+        direction,
+        direction,
+        page_size,
+        (page as i64 - 1) * page_size as i64
+    );
+    let mut stmt = conn.prepare(&sql)?;
+    let rows = stmt
+        .query_map(params_from_iter(values.iter()), |row| {
+            Ok(SalesRegisterRow {
+                invoice_id: row.get(0)?,
+                invoice_number: row.get(1)?,
+                invoice_date: row.get(2)?,
+                customer_id: row.get(3)?,
+                customer_name: row.get(4)?,
+                gst_no: row.get(5)?,
+                category_name: row.get(6)?,
+                place_of_supply: row.get(7)?,
+                taxable_value: row.get(8)?,
+                cgst_amount: row.get(9)?,
+                sgst_amount: row.get(10)?,
+                igst_amount: row.get(11)?,
+                cess_amount: row.get(12)?,
+                round_off: row.get(13)?,
+                total_amount: row.get(14)?,
+                reverse_charge: row.get(15)?,
+                reverse_charge_tax: round2(row.get(16)?),
+            })
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let total_pages = (totals.invoice_count as u32).div_ceil(page_size);
+    Ok(SalesRegister {
+        rows,
+        totals,
+        reverse_charge,
+        page,
+        page_size,
+        total_pages,
+    })
+}
+
+fn months_between(from: &str, to: &str) -> Result<Vec<String>, String> {
+    let (from, to) = (
+        NaiveDate::parse_from_str(from, INVOICE_DATE_FORMAT).map_err(|e| e.to_string())?,
+        NaiveDate::parse_from_str(to, INVOICE_DATE_FORMAT).map_err(|e| e.to_string())?,
+    );
+    let (mut year, mut month) = (from.year(), from.month());
+    let mut months = Vec::new();
+    while (year, month) <= (to.year(), to.month()) {
+        months.push(format!("{:04}-{:02}", year, month));
+        if month == 12 {
+            year += 1;
+            month = 1;
+        } else {
+            month += 1;
+        }
+    }
+    Ok(months)
+}
+
+// Customers without a category are grouped under "Uncategorised"
+pub fn load_monthly_category_summary(
+    conn: &Connection,
+    company_id: i64,
+    from: &str,
+    to: &str,
+    status: Option<InvoiceStatus>,
+) -> Result<MonthlyCategorySummary, AppError> {
+    let (from, to) = parse_range(from, to)?;
+    let months = months_between(&from, &to)?;
+    let status = status.unwrap_or(InvoiceStatus::Issued);
+
+    let mut stmt = conn.prepare(
+        "SELECT substr(i.invoice_date, 1, 7) AS month, cat.id, cat.name,
+                    SUM(i.taxable_value),
+                    SUM(i.cgst_amount + i.sgst_amount + i.igst_amount + i.cess_amount)
+             FROM invoices i
+             JOIN customers c ON c.id = i.customer_id
+             LEFT JOIN categories cat ON cat.id = c.category_id
+             WHERE i.company_id = ?1 AND i.status = ?2 AND i.invoice_date BETWEEN ?3 AND ?4
+             GROUP BY month, cat.id
+             ORDER BY cat.name COLLATE NOCASE, month",
+    )?;
+    let cells = stmt
+        .query_map(params![company_id, status.as_str(), from, to], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, Option<i64>>(1)?,
+                row.get::<_, Option<String>>(2)?,
+                row.get::<_, f64>(3)?,
+                row.get::<_, f64>(4)?,
+            ))
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let mut categories: Vec<SummaryCategory> = Vec::new();
+    for (_, id, name, _, _) in &cells {
+        if !categories.iter().any(|c| c.id == *id) {
+            categories.push(SummaryCategory {
+                id: *id,
+                name: name.clone().unwrap_or_else(|| "Uncategorised".to_string()),
+            });
+        }
+    }
+
+    let mut taxable_value = vec![vec![0.0; categories.len()]; months.len()];
+    let mut tax_amount = vec![vec![0.0; categories.len()]; months.len()];
+    for (month, id, _, taxable, tax) in &cells {
+        let row = months.iter().position(|m| m == month);
+        let col = categories.iter().position(|c| c.id == *id);
+        if let (Some(row), Some(col)) = (row, col) {
+            taxable_value[row][col] = round2(*taxable);
+            tax_amount[row][col] = round2(*tax);
+        }
+    }
+    let month_totals = taxable_value
+        .iter()
+        .map(|row| round2(row.iter().sum()))
+        .collect();
+    let category_totals = (0..categories.len())
+        .map(|col| round2(taxable_value.iter().map(|row| row[col]).sum()))
+        .collect();
+
+    Ok(MonthlyCategorySummary {
+        months,
+        categories,
+        taxable_value,
+        tax_amount,
+        month_totals,
+        category_totals,
+    })
+}
+
+// Customers with no issued invoice in the period are left out
+pub fn load_sales_by_customer(
+    conn: &Connection,
+    company_id: i64,
+    from: &str,
+    to: &str,
+    category_id: Option<i64>,
+) -> Result<Vec<CustomerSales>, AppError> {
+    let (from, to) = parse_dates(from, to)?;
+    // 29 February maps to 28 February in a non-leap previous year
+    let previous = |date: NaiveDate| {
+        date.checked_sub_months(Months::new(12))
+            .unwrap_or(date)
+            .format(INVOICE_DATE_FORMAT)
+            .to_string()
+    };
+    let (previous_from, previous_to) = (previous(from), previous(to));
+    let (from, to) = (
+        from.format(INVOICE_DATE_FORMAT).to_string(),
+        to.format(INVOICE_DATE_FORMAT).to_string(),
+    );
+
+    let mut stmt = conn.prepare(
+        "SELECT c.id, c.report_customer, c.gst_no, cat.name,
+                    SUM(CASE WHEN i.invoice_date BETWEEN ?2 AND ?3 THEN 1 ELSE 0 END) AS count,
+                    SUM(CASE WHEN i.invoice_date BETWEEN ?2 AND ?3
+                             THEN i.taxable_value ELSE 0 END),
+                    SUM(CASE WHEN i.invoice_date BETWEEN ?2 AND ?3
+                             THEN i.total_amount ELSE 0 END) AS total,
+                    SUM(CASE WHEN i.invoice_date BETWEEN ?4 AND ?5
+                             THEN i.total_amount ELSE 0 END)
+             FROM invoices i
+             JOIN customers c ON c.id = i.customer_id
+             LEFT JOIN categories cat ON cat.id = c.category_id
+             WHERE i.company_id = ?1 AND i.status = 'issued'
+               AND (i.invoice_date BETWEEN ?2 AND ?3 OR i.invoice_date BETWEEN ?4 AND ?5)
+               AND (?6 IS NULL OR c.category_id = ?6)
+             GROUP BY c.id
+             HAVING count > 0
+             ORDER BY total DESC, c.report_customer COLLATE NOCASE",
+    )?;
+    let rows = stmt
+        .query_map(
+            params![company_id, from, to, previous_from, previous_to, category_id],
+            |row| {
+                let invoice_count: i64 = row.get(4)?;
+                let total_amount = round2(row.get(6)?);
+                let previous_total_amount = round2(row.get(7)?);
+                let growth_percent = if previous_total_amount > 0.0 {
+                    Some(round2(
+                        (total_amount - previous_total_amount) / previous_total_amount * 100.0,
+                    ))
+                } else {
+                    None
+                };
+                Ok(CustomerSales {
+                    customer_id: row.get(0)?,
+                    customer_name: row.get(1)?,
+                    gst_no: row.get(2)?,
+                    category_name: row.get(3)?,
+                    invoice_count,
+                    taxable_value: round2(row.get(5)?),
+                    total_amount,
+                    average_invoice_value: round2(total_amount / invoice_count as f64),
+                    previous_total_amount,
+                    growth_percent,
+                })
+            },
+        )?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(rows)
+}
+
+// Classifies issued invoices the same way the GSTR-1 export does, so the figures can be
+// compared against the portal's table-wise summary
+pub fn load_state_supply_split(
+    conn: &Connection,
+    company_id: i64,
+    period: &str,
+) -> Result<StateSupplySplit, AppError> {
+    let (from, to) = gstr1::parse_period(period)?;
+    let company = companies::get_company_by_id(conn, company_id)?
+        .ok_or_else(|| AppError::not_found("Company not found"))?;
+
+    let mut stmt = conn.prepare(
+        "SELECT pos, s.name, supply_type, pos != ?2 AS inter_state, COUNT(*),
+                    SUM(taxable_value), SUM(cgst_amount), SUM(sgst_amount), SUM(igst_amount),
+                    SUM(cess_amount), SUM(total_amount)
+             FROM (
+                 SELECT TRIM(i.place_of_supply) AS pos, i.taxable_value, i.cgst_amount,
+                        i.sgst_amount, i.igst_amount, i.cess_amount, i.total_amount,
+                        CASE
+                            WHEN TRIM(i.place_of_supply) = ?5 THEN 'export'
+                            WHEN TRIM(c.gst_no) != '' THEN 'b2b'
+                            WHEN TRIM(i.place_of_supply) != ?2 AND i.total_amount > ?6
+                                THEN 'b2c_large'
+                            ELSE 'b2c_small'
+                        END AS supply_type
+                 FROM invoices i
+                 JOIN customers c ON c.id = i.customer_id
+                 WHERE i.company_id = ?1 AND i.status = 'issued'
+                   AND i.invoice_date BETWEEN ?3 AND ?4
+             )
+             LEFT JOIN states s ON s.code = pos
+             GROUP BY pos, supply_type
+             ORDER BY pos, supply_type",
+    )?;
+    let rows = stmt
+        .query_map(
+            params![
+                company_id,
+                company.state_code.trim(),
+                from.format(INVOICE_DATE_FORMAT).to_string(),
+                to.format(INVOICE_DATE_FORMAT).to_string(),
+                EXPORT_STATE_CODE,
+                B2CL_THRESHOLD
+            ],
+            |row| {
+                let supply_type: String = row.get(2)?;
+                Ok(StateSupplyRow {
+                    state_code: row.get(0)?,
+                    state_name: row.get(1)?,
+                    supply_type: SupplyType::parse(&supply_type).unwrap_or(SupplyType::B2cSmall),
+                    inter_state: row.get(3)?,
+                    invoice_count: row.get(4)?,
+                    taxable_value: round2(row.get(5)?),
+                    cgst_amount: round2(row.get(6)?),
+                    sgst_amount: round2(row.get(7)?),
+                    igst_amount: round2(row.get(8)?),
+                    cess_amount: round2(row.get(9)?),
+                    total_amount: round2(row.get(10)?),
+                })
+            },
+        )?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let mut totals: Vec<SupplyTypeTotal> = Vec::new();
+    for row in &rows {
+        let tax = row.cgst_amount + row.sgst_amount + row.igst_amount + row.cess_amount;
+        match totals.iter_mut().find(|t| t.supply_type == row.supply_type) {
+            Some(total) => {
+                total.invoice_count += row.invoice_count;
+                total.taxable_value = round2(total.taxable_value + row.taxable_value);
+                total.total_tax = round2(total.total_tax + tax);
+            }
+            None => totals.push(SupplyTypeTotal {
+                supply_type: row.supply_type,
+                invoice_count: row.invoice_count,
+                taxable_value: row.taxable_value,
+                total_tax: round2(tax),
+            }),
+        }
+    }
+    totals.sort_by_key(|t| t.supply_type);
+
+    Ok(StateSupplySplit {
+        period: period.trim().to_string(),
+        rows,
+        totals,
+    })
+}
+
+fn write_sales_by_customer(rows: &[CustomerSales], path: &str) -> Result<(), String> {
+    let formats = Formats::new();
+    let mut workbook = Workbook::new();
+    let sheet = workbook.add_worksheet();
+    sheet.set_name("Sales by Customer").map_err(xlsx_error)?;
+    write_headers(
+        sheet,
+        &formats,
+        &[
+            ("Customer", 32.0),
+            ("GSTIN", 18.0),
+            ("Category", 18.0),
+            ("Invoices", 10.0),
+            ("Taxable Value", 16.0),
+            ("Total", 16.0),
+            ("Average Invoice", 16.0),
+            ("Previous Year", 16.0),
+            ("Growth %", 10.0),
+        ],
+    )?;
+
+    let mut line = 1u32;
+    for row in rows {
+        sheet
+            .write_string_with_format(line, 0, &row.customer_name, &formats.text)
+            .map_err(xlsx_error)?;
+        sheet
+            .write_string_with_format(line, 1, &row.gst_no, &formats.text)
+            .map_err(xlsx_error)?;
+        sheet
+            .write_string_with_format(
+                line,
+                2,
+                row.category_name.as_deref().unwrap_or(""),
+                &formats.text,
+            )
+            .map_err(xlsx_error)?;
+        sheet
+            .write_number(line, 3, row.invoice_count as f64)
+            .map_err(xlsx_error)?;
+        for (col, value) in [
+            (4u16, row.taxable_value),
+            (5, row.total_amount),
+            (6, row.average_invoice_value),
+            (7, row.previous_total_amount),
+        ] {
+            sheet
+                .write_number_with_format(line, col, value, &formats.currency)
+                .map_err(xlsx_error)?;
+        }
+        if let Some(growth) = row.growth_percent {
+            sheet.write_number(line, 8, growth).map_err(xlsx_error)?;
+        }
+        line += 1;
+    }
+
+    sheet
+        .write_string_with_format(line, 0, "Total", &formats.total_label)
+        .map_err(xlsx_error)?;
+    sheet
+        .write_number_with_format(
+            line,
+            3,
+            rows.iter().map(|r| r.invoice_count).sum::<i64>() as f64,
+            &formats.total_label,
+        )
+        .map_err(xlsx_error)?;
+    for (col, value) in [
+        (4u16, rows.iter().map(|r| r.taxable_value).sum::<f64>()),
+        (5, rows.iter().map(|r| r.total_amount).sum()),
+        (7, rows.iter().map(|r| r.previous_total_amount).sum()),
+    ] {
+        sheet
+            .write_number_with_format(line, col, round2(value), &formats.total_currency)
+            .map_err(xlsx_error)?;
+    }
+
+    workbook.save(path).map_err(xlsx_error)?;
+    Ok(())
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn sales_register(
+    pool: State<'_, DbPool>,
+    company_id: i64,
+    from: String,
+    to: String,
+    filters: Option<SalesRegisterFilters>,
+) -> Result<SalesRegister, AppError> {
+    let conn = db::get_conn(&pool)?;
+    load_sales_register(&conn, company_id, &from, &to, &filters.unwrap_or_default())
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn monthly_category_summary(
+    pool: State<'_, DbPool>,
+    company_id: i64,
+    from: String,
+    to: String,
+    status: Option<InvoiceStatus>,
+) -> Result<MonthlyCategorySummary, AppError> {
+    let conn = db::get_conn(&pool)?;
+    load_monthly_category_summary(&conn, company_id, &from, &to, status)
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn sales_by_customer(
+    pool: State<'_, DbPool>,
+    company_id: i64,
+    from: String,
+    to: String,
+    category_id: Option<i64>,
+) -> Result<Vec<CustomerSales>, AppError> {
+    let conn = db::get_conn(&pool)?;
+    load_sales_by_customer(&conn, company_id, &from, &to, category_id)
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn export_sales_by_customer_xlsx(
+    pool: State<'_, DbPool>,
+    company_id: i64,
+    from: String,
+    to: String,
+    category_id: Option<i64>,
+    path: String,
+) -> Result<ReportExportResult, AppError> {
+    let rows = {
+        let conn = db::get_conn(&pool)?;
+        load_sales_by_customer(&conn, company_id, &from, &to, category_id)?
+    };
+    write_sales_by_customer(&rows, &path)?;
+    Ok(ReportExportResult {
+        path,
+        sheets: vec!["Sales by Customer".to_string()],
+        row_count: rows.len(),
+    })
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn state_supply_split(
+    pool: State<'_, DbPool>,
+    company_id: i64,
+    period: String,
+) -> Result<StateSupplySplit, AppError> {
+    let conn = db::get_conn(&pool)?;
+    load_state_supply_split(&conn, company_id, &period)
+}
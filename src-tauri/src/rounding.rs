@@ -0,0 +1,81 @@
+use rusqlite::Connection;
+use serde::{Deserialize, Serialize};
+use tauri::State;
+
+use crate::db::{self, get_setting, set_setting, DbPool};
+use crate::error::AppError;
+
+const SETTING_ROUNDING: &str = "invoice_rounding";
+
+// How invoice totals are rounded to whole rupees; the difference is kept as round-off
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum RoundingMode {
+    None,
+    Nearest,
+    Up,
+    Down,
+}
+
+impl RoundingMode {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RoundingMode::None => "none",
+            RoundingMode::Nearest => "nearest",
+            RoundingMode::Up => "up",
+            RoundingMode::Down => "down",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "none" => Some(RoundingMode::None),
+            "nearest" => Some(RoundingMode::Nearest),
+            "up" => Some(RoundingMode::Up),
+            "down" => Some(RoundingMode::Down),
+            _ => None,
+        }
+    }
+
+    // Works in whole paise so values like 100.00 never round up through float noise
+    pub fn apply(&self, amount: f64) -> f64 {
+        let paise = (amount * 100.0).round() as i64;
+        let (rupees, remainder) = (paise.div_euclid(100), paise.rem_euclid(100));
+        let rounded = match self {
+            RoundingMode::None => return paise as f64 / 100.0,
+            RoundingMode::Nearest if remainder >= 50 => rupees + 1,
+            RoundingMode::Up if remainder > 0 => rupees + 1,
+            RoundingMode::Nearest | RoundingMode::Up | RoundingMode::Down => rupees,
+        };
+        rounded as f64
+    }
+}
+
+pub fn load_mode(conn: &Connection) -> Result<RoundingMode, String> {
+    Ok(get_setting(conn, SETTING_ROUNDING)?
+        .and_then(|value| RoundingMode::parse(&value))
+        .unwrap_or(RoundingMode::None))
+}
+
+pub fn save_mode(conn: &Connection, mode: RoundingMode) -> Result<(), String> {
+    Ok(set_setting(conn, SETTING_ROUNDING, mode.as_str())?)
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn get_invoice_rounding(pool: State<'_, DbPool>) -> Result<RoundingMode, AppError> {
+    let conn = db::get_conn(&pool)?;
+    Ok(load_mode(&conn)?)
+}
+
+// Applies to invoices saved from now on; existing totals are not recalculated
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn set_invoice_rounding(
+    pool: State<'_, DbPool>,
+    mode: RoundingMode,
+) -> Result<RoundingMode, AppError> {
+    let conn = db::get_conn(&pool)?;
+    save_mode(&conn, mode)?;
+    Ok(load_mode(&conn)?)
+}
@@ -0,0 +1,568 @@
+use std::collections::{HashMap, HashSet};
+
+use calamine::{open_workbook_auto, Data, Range, Reader};
+use chrono::{Duration, NaiveDate};
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+use tauri::State;
+
+use crate::currencies;
+use crate::customers::{self, normalize_customer_name, Customer};
+use crate::db::{self, DbPool};
+use crate::error::AppError;
+use crate::gstin;
+use crate::invoices::{self, Invoice, InvoiceStatus, INVOICE_DATE_FORMAT};
+use crate::place_of_supply::SupplyKind;
+use crate::states;
+
+// Date layouts seen in exported sales registers, tried in order
+pub const DATE_FORMATS: &[&str] = &[
+    INVOICE_DATE_FORMAT,
+    "%d-%m-%Y",
+    "%d/%m/%Y",
+    "%d.%m.%Y",
+    "%d-%b-%Y",
+    "%d-%b-%y",
+    "%d/%m/%y",
+];
+
+// Maps spreadsheet columns (by header text, case-insensitive) to invoice fields
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SalesColumnMapping {
+    pub sheet: Option<String>,
+    // 1-based row holding the column headers; data starts on the next row
+    pub header_row: Option<usize>,
+    pub invoice_number: String,
+    pub invoice_date: String,
+    // Matched against tally_customer, then report_customer
+    pub customer: String,
+    pub customer_gstin: Option<String>,
+    pub place_of_supply: Option<String>,
+    pub taxable_value: String,
+    pub cgst_amount: Option<String>,
+    pub sgst_amount: Option<String>,
+    pub igst_amount: Option<String>,
+    // Derived from taxable value plus taxes when not mapped
+    pub total_amount: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum SalesRowStatus {
+    Valid,
+    Imported,
+    Error,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SalesRowResult {
+    // Spreadsheet row number as shown in Excel
+    pub row_number: usize,
+    pub invoice_number: Option<String>,
+    pub status: SalesRowStatus,
+    pub errors: Vec<String>,
+    pub invoice_id: Option<i64>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SalesImportReport {
+    pub sheet: String,
+    pub total_rows: usize,
+    pub valid_rows: usize,
+    pub error_rows: usize,
+    pub imported_rows: usize,
+    pub dry_run: bool,
+    pub rows: Vec<SalesRowResult>,
+}
+
+pub(crate) struct ColumnIndexes {
+    invoice_number: usize,
+    invoice_date: usize,
+    customer: usize,
+    customer_gstin: Option<usize>,
+    place_of_supply: Option<usize>,
+    taxable_value: usize,
+    cgst_amount: Option<usize>,
+    sgst_amount: Option<usize>,
+    igst_amount: Option<usize>,
+    total_amount: Option<usize>,
+}
+
+fn resolve_columns(
+    headers: &[String],
+    mapping: &SalesColumnMapping,
+) -> Result<ColumnIndexes, AppError> {
+    let find = |column: &str| -> Result<usize, AppError> {
+        let wanted = column.trim().to_lowercase();
+        headers
+            .iter()
+            .position(|header| header.trim().to_lowercase() == wanted)
+            .ok_or_else(|| {
+                AppError::validation(
+                    "mapping",
+                    format!("Column '{}' was not found in the header row", column.trim()),
+                )
+            })
+    };
+    let find_optional = |column: &Option<String>| -> Result<Option<usize>, AppError> {
+        match column.as_deref().map(str::trim) {
+            Some(column) if !column.is_empty() => find(column).map(Some),
+            _ => Ok(None),
+        }
+    };
+
+    Ok(ColumnIndexes {
+        invoice_number: find(&mapping.invoice_number)?,
+        invoice_date: find(&mapping.invoice_date)?,
+        customer: find(&mapping.customer)?,
+        customer_gstin: find_optional(&mapping.customer_gstin)?,
+        place_of_supply: find_optional(&mapping.place_of_supply)?,
+        taxable_value: find(&mapping.taxable_value)?,
+        cgst_amount: find_optional(&mapping.cgst_amount)?,
+        sgst_amount: find_optional(&mapping.sgst_amount)?,
+        igst_amount: find_optional(&mapping.igst_amount)?,
+        total_amount: find_optional(&mapping.total_amount)?,
+    })
+}
+
+pub(crate) fn cell_text(cell: &Data) -> String {
+    match cell {
+        Data::Empty => String::new(),
+        Data::String(value) => value.trim().to_string(),
+        // Invoice numbers typed as numbers come back as floats
+        Data::Float(value) if value.fract() == 0.0 => format!("{}", *value as i64),
+        other => other.to_string().trim().to_string(),
+    }
+}
+
+pub(crate) fn cell_date(cell: &Data) -> Result<NaiveDate, String> {
+    let excel_serial = |serial: f64| {
+        // Excel serial dates count days from 1899-12-30
+        NaiveDate::from_ymd_opt(1899, 12, 30)
+            .and_then(|epoch| epoch.checked_add_signed(Duration::days(serial.trunc() as i64)))
+            .ok_or_else(|| format!("Invalid date serial {}", serial))
+    };
+
+    match cell {
+        Data::DateTime(value) => excel_serial(value.as_f64()),
+        Data::Float(value) => excel_serial(*value),
+        Data::Int(value) => excel_serial(*value as f64),
+        Data::DateTimeIso(value) => {
+            NaiveDate::parse_from_str(value.get(..10).unwrap_or(value), INVOICE_DATE_FORMAT)
+                .map_err(|_| format!("Invalid date '{}'", value))
+        }
+        Data::Empty => Err("Invoice date is required".to_string()),
+        other => {
+            let text = cell_text(other);
+            DATE_FORMATS
+                .iter()
+                .find_map(|format| NaiveDate::parse_from_str(&text, format).ok())
+                .ok_or_else(|| format!("Invalid date '{}'", text))
+        }
+    }
+}
+
+pub(crate) fn cell_amount(cell: &Data, label: &str) -> Result<f64, String> {
+    match cell {
+        Data::Empty => Ok(0.0),
+        Data::Float(value) => Ok(*value),
+        Data::Int(value) => Ok(*value as f64),
+        other => {
+            let text = cell_text(other).replace([',', '₹'], "");
+            let text = text.trim();
+            if text.is_empty() {
+                return Ok(0.0);
+            }
+            text.parse::<f64>()
+                .map_err(|_| format!("{} '{}' is not a number", label, text))
+        }
+    }
+}
+
+// Customer lookup keyed the same way the UI matches names
+pub struct CustomerIndex<'a> {
+    by_tally_name: HashMap<String, &'a Customer>,
+    by_normalized_name: HashMap<String, &'a Customer>,
+    by_gstin: HashMap<String, &'a Customer>,
+}
+
+impl<'a> CustomerIndex<'a> {
+    pub fn new(customers: &'a [Customer]) -> Self {
+        let mut index = CustomerIndex {
+            by_tally_name: HashMap::new(),
+            by_normalized_name: HashMap::new(),
+            by_gstin: HashMap::new(),
+        };
+        for customer in customers {
+            index
+                .by_tally_name
+                .insert(customer.tally_customer.trim().to_lowercase(), customer);
+            index
+                .by_normalized_name
+                .entry(normalize_customer_name(&customer.report_customer))
+                .or_insert(customer);
+            if !customer.gst_no.trim().is_empty() {
+                index
+                    .by_gstin
+                    .entry(customer.gst_no.trim().to_uppercase())
+                    .or_insert(customer);
+            }
+        }
+        index
+    }
+
+    pub fn find(&self, name: &str, gst_no: Option<&str>) -> Option<&'a Customer> {
+        if let Some(customer) = gst_no.and_then(|g| self.by_gstin.get(g)) {
+            return Some(*customer);
+        }
+        self.by_tally_name
+            .get(&name.trim().to_lowercase())
+            .or_else(|| self.by_normalized_name.get(&normalize_customer_name(name)))
+            .copied()
+    }
+}
+
+pub fn place_of_supply(conn: &Connection, value: &str) -> Result<Option<String>, String> {
+    let value = value.trim();
+    if value.is_empty() {
+        return Ok(None);
+    }
+    // Accept "33", "3", "33-Tamil Nadu" or "Tamil Nadu"
+    let code_part = value.split(['-', ' ']).next().unwrap_or("");
+    if !code_part.is_empty() && code_part.chars().all(|c| c.is_ascii_digit()) {
+        return Ok(Some(format!("{:0>2}", code_part)));
+    }
+    Ok(states::get_state_by_name(conn, value)?.map(|state| state.code))
+}
+
+pub fn invoice_exists(
+    conn: &Connection,
+    company_id: i64,
+    invoice_number: &str,
+) -> Result<bool, AppError> {
+    conn.query_row(
+        "SELECT EXISTS(SELECT 1 FROM invoices WHERE company_id = ?1 AND invoice_number = ?2)",
+        params![company_id, invoice_number],
+        |row| row.get(0),
+    )
+    .map_err(AppError::from)
+}
+
+// Builds an invoice from one spreadsheet row, collecting every problem rather than stopping at the first
+fn parse_row(
+    conn: &Connection,
+    company_id: i64,
+    row: &[Data],
+    columns: &ColumnIndexes,
+    customers: &CustomerIndex,
+) -> Result<Result<Invoice, Vec<String>>, String> {
+    let empty = Data::Empty;
+    let cell = |index: usize| row.get(index).unwrap_or(&empty);
+    let optional_amount = |index: Option<usize>, label: &str| match index {
+        Some(index) => cell_amount(cell(index), label),
+        None => Ok(0.0),
+    };
+    let mut errors = Vec::new();
+
+    let invoice_number = cell_text(cell(columns.invoice_number));
+    if invoice_number.is_empty() {
+        errors.push("Invoice number is required".to_string());
+    }
+
+    let invoice_date = cell_date(cell(columns.invoice_date))
+        .map_err(|e| errors.push(e))
+        .ok();
+
+    let gst_no = columns
+        .customer_gstin
+        .map(|index| cell_text(cell(index)).to_uppercase())
+        .filter(|gst_no| !gst_no.is_empty());
+    if let Some(gst_no) = &gst_no {
+        if let Err(e) = gstin::check_gstin(gst_no) {
+            errors.push(e);
+        }
+    }
+
+    let customer_name = cell_text(cell(columns.customer));
+    let customer = if customer_name.is_empty() && gst_no.is_none() {
+        errors.push("Customer is required".to_string());
+        None
+    } else {
+        let found = customers.find(&customer_name, gst_no.as_deref());
+        if found.is_none() {
+            errors.push(format!("Customer '{}' is not set up for this company", customer_name));
+        }
+        found
+    };
+
+    let mut amount = |index: Option<usize>, label: &str| {
+        optional_amount(index, label)
+            .map_err(|e| errors.push(e))
+            .unwrap_or(0.0)
+    };
+    let taxable_value = amount(Some(columns.taxable_value), "Taxable value");
+    let cgst_amount = amount(columns.cgst_amount, "CGST amount");
+    let sgst_amount = amount(columns.sgst_amount, "SGST amount");
+    let igst_amount = amount(columns.igst_amount, "IGST amount");
+    let total_amount = match columns.total_amount {
+        Some(index) => amount(Some(index), "Total amount"),
+        None => invoices::round2(taxable_value + cgst_amount + sgst_amount + igst_amount),
+    };
+
+    let mut supply = match columns.place_of_supply {
+        Some(index) => place_of_supply(conn, &cell_text(cell(index)))?,
+        None => None,
+    };
+    if supply.is_none() {
+        supply = customer
+            .map(|c| c.state_code.trim().to_string())
+            .filter(|code| !code.is_empty())
+            .or_else(|| gst_no.as_deref().and_then(|g| g.get(..2)).map(str::to_string));
+    }
+
+    if !invoice_number.is_empty() && invoice_exists(conn, company_id, &invoice_number)? {
+        errors.push(format!("Invoice {} already exists", invoice_number));
+    }
+
+    let (Some(invoice_date), Some(customer)) = (invoice_date, customer) else {
+        return Ok(Err(errors));
+    };
+
+    let mut invoice = Invoice {
+        id: None,
+        company_id,
+        invoice_number,
+        invoice_date: invoice_date.format(INVOICE_DATE_FORMAT).to_string(),
+        customer_id: customer.id.unwrap_or_default(),
+        place_of_supply: supply.unwrap_or_default(),
+        supply_kind: SupplyKind::Regular,
+        export: None,
+        sez_mode: None,
+        discount_percent: 0.0,
+        discount_amount: 0.0,
+        taxable_value,
+        cgst_amount,
+        sgst_amount,
+        igst_amount,
+        cess_amount: 0.0,
+        reverse_charge: false,
+        rcm_cgst_amount: 0.0,
+        rcm_sgst_amount: 0.0,
+        rcm_igst_amount: 0.0,
+        rcm_cess_amount: 0.0,
+        tcs_base: 0.0,
+        tcs_amount: 0.0,
+        round_off: 0.0,
+        total_amount,
+        currency: currencies::base_currency(),
+        exchange_rate: currencies::unit_rate(),
+        foreign_taxable_value: 0.0,
+        foreign_total_amount: 0.0,
+        amount_received: 0.0,
+        status: InvoiceStatus::Issued,
+        notes: None,
+        created_at: None,
+        updated_at: None,
+    };
+    if errors.is_empty() {
+        if let Err(e) = invoices::apply_tax_split(conn, &mut invoice, &mut [])
+            .and_then(|_| invoices::apply_rounding(conn, &mut invoice))
+            .and_then(|_| currencies::apply_currency(conn, &mut invoice))
+            .and_then(|_| invoices::validate_invoice(conn, &invoice))
+        {
+            errors.push(e.to_string());
+        }
+    }
+
+    if errors.is_empty() {
+        Ok(Ok(invoice))
+    } else {
+        Ok(Err(errors))
+    }
+}
+
+// Finds the header row and the mapped columns; returns the 1-based header row number
+pub(crate) fn sheet_columns(
+    range: &Range<Data>,
+    mapping: &SalesColumnMapping,
+) -> Result<(usize, ColumnIndexes), AppError> {
+    let header_row = mapping.header_row.unwrap_or(1).max(1);
+    let headers: Vec<String> = data_rows(range, 0)
+        .find(|(number, _)| *number == header_row)
+        .map(|(_, row)| row.iter().map(cell_text).collect())
+        .ok_or_else(|| {
+            AppError::validation(
+                "mapping",
+                format!("Header row {} is empty or outside the sheet", header_row),
+            )
+        })?;
+    Ok((header_row, resolve_columns(&headers, mapping)?))
+}
+
+// Rows below `header_row`, numbered as in Excel
+pub(crate) fn data_rows(
+    range: &Range<Data>,
+    header_row: usize,
+) -> impl Iterator<Item = (usize, &[Data])> + '_ {
+    // Range rows are relative to the first used cell, which is not always A1
+    let first_row = range.start().map(|(row, _)| row as usize + 1).unwrap_or(1);
+    range
+        .rows()
+        .enumerate()
+        .map(move |(i, row)| (first_row + i, row))
+        .filter(move |(number, _)| *number > header_row)
+}
+
+// Checks one data row. Empty rows give None; a valid row comes back with its invoice for the
+// caller to insert.
+pub(crate) fn check_row(
+    conn: &Connection,
+    company_id: i64,
+    row_number: usize,
+    row: &[Data],
+    columns: &ColumnIndexes,
+    customer_index: &CustomerIndex,
+    seen_numbers: &mut HashSet<String>,
+) -> Result<Option<(SalesRowResult, Option<Invoice>)>, String> {
+    if row.iter().all(|cell| matches!(cell, Data::Empty)) {
+        return Ok(None);
+    }
+    let checked = match parse_row(conn, company_id, row, columns, customer_index)? {
+        Ok(invoice) if !seen_numbers.insert(invoice.invoice_number.clone()) => (
+            SalesRowResult {
+                row_number,
+                errors: vec![format!(
+                    "Invoice {} appears more than once in the file",
+                    invoice.invoice_number
+                )],
+                invoice_number: Some(invoice.invoice_number),
+                status: SalesRowStatus::Error,
+                invoice_id: None,
+            },
+            None,
+        ),
+        Ok(invoice) => (
+            SalesRowResult {
+                row_number,
+                invoice_number: Some(invoice.invoice_number.clone()),
+                status: SalesRowStatus::Valid,
+                errors: Vec::new(),
+                invoice_id: None,
+            },
+            Some(invoice),
+        ),
+        Err(errors) => (
+            SalesRowResult {
+                row_number,
+                invoice_number: Some(cell_text(
+                    row.get(columns.invoice_number).unwrap_or(&Data::Empty),
+                ))
+                .filter(|n| !n.is_empty()),
+                status: SalesRowStatus::Error,
+                errors,
+                invoice_id: None,
+            },
+            None,
+        ),
+    };
+    Ok(Some(checked))
+}
+
+pub(crate) fn load_sheet(
+    path: &str,
+    sheet: Option<&str>,
+) -> Result<(String, Range<Data>), AppError> {
+    let mut workbook =
+        open_workbook_auto(path).map_err(|e| format!("Failed to open workbook: {}", e))?;
+    let sheet_name = match sheet.map(str::trim).filter(|s| !s.is_empty()) {
+        Some(name) => name.to_string(),
+        None => workbook
+            .sheet_names()
+            .first()
+            .cloned()
+            .ok_or_else(|| AppError::validation("path", "Workbook has no sheets"))?,
+    };
+    let range = workbook
+        .worksheet_range(&sheet_name)
+        .map_err(|e| format!("Failed to read sheet '{}': {}", sheet_name, e))?;
+    Ok((sheet_name, range))
+}
+
+// Checks every row of `range` and, unless `dry_run`, inserts the valid invoices in one
+// transaction. Shared by the spreadsheet import and the importers for other accounting tools.
+pub(crate) fn import_rows(
+    conn: &mut Connection,
+    company_id: i64,
+    sheet: String,
+    range: &Range<Data>,
+    mapping: &SalesColumnMapping,
+    dry_run: bool,
+) -> Result<SalesImportReport, String> {
+    let (header_row, columns) = sheet_columns(range, mapping)?;
+    let rows = data_rows(range, header_row);
+
+    let company_customers = customers::get_customers_by_company(conn, company_id)?;
+    let customer_index = CustomerIndex::new(&company_customers);
+
+    let mut results = Vec::new();
+    let mut valid = Vec::new();
+    let mut seen_numbers = HashSet::new();
+    for (row_number, row) in rows {
+        let checked = check_row(
+            conn,
+            company_id,
+            row_number,
+            row,
+            &columns,
+            &customer_index,
+            &mut seen_numbers,
+        )?;
+        let Some((result, invoice)) = checked else {
+            continue;
+        };
+        if let Some(invoice) = invoice {
+            valid.push((results.len(), invoice));
+        }
+        results.push(result);
+    }
+
+    let valid_rows = valid.len();
+    if !dry_run && !valid.is_empty() {
+        let tx = conn.transaction().map_err(|e| e.to_string())?;
+        for (index, invoice) in &valid {
+            let id = invoices::insert_invoice(&tx, invoice).map_err(|e| {
+                format!("Row {}: {}", results[*index].row_number, e)
+            })?;
+            results[*index].status = SalesRowStatus::Imported;
+            results[*index].invoice_id = Some(id);
+        }
+        tx.commit().map_err(|e| e.to_string())?;
+    }
+
+    Ok(SalesImportReport {
+        sheet,
+        total_rows: results.len(),
+        valid_rows,
+        error_rows: results.len() - valid_rows,
+        imported_rows: if dry_run { 0 } else { valid_rows },
+        dry_run,
+        rows: results,
+    })
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn import_sales_excel(
+    pool: State<'_, DbPool>,
+    company_id: i64,
+    path: String,
+    mapping: SalesColumnMapping,
+    dry_run: Option<bool>,
+) -> Result<SalesImportReport, AppError> {
+    let dry_run = dry_run.unwrap_or(false);
+    let (sheet, range) = load_sheet(&path, mapping.sheet.as_deref())?;
+    let mut conn = db::get_conn(&pool)?;
+    Ok(import_rows(
+        &mut conn, company_id, sheet, &range, &mapping, dry_run,
+    )?)
+}
@@ -0,0 +1,904 @@
+use std::collections::HashMap;
+
+use chrono::{Local, NaiveDate};
+use rusqlite::{params, Connection, OptionalExtension, Row};
+use serde::{Deserialize, Serialize};
+use tauri::State;
+
+use crate::audit::{self, AuditAction};
+use crate::customers;
+use crate::db::{self, DbPool};
+use crate::error::AppError;
+use crate::invoices::{
+    self, round2, CreateInvoice, Invoice, InvoiceLineInput, InvoiceStatus, SavedInvoice,
+    INVOICE_DATE_FORMAT,
+};
+use crate::items;
+use crate::numbering::{self, DocumentType};
+use crate::place_of_supply::SupplyKind;
+use crate::price_lists;
+use crate::tax::AMOUNT_TOLERANCE;
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum OrderStatus {
+    Open,
+    // Short-closed: whatever is still pending will not be supplied
+    Closed,
+    Cancelled,
+}
+
+impl OrderStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            OrderStatus::Open => "open",
+            OrderStatus::Closed => "closed",
+            OrderStatus::Cancelled => "cancelled",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "open" => Some(OrderStatus::Open),
+            "closed" => Some(OrderStatus::Closed),
+            "cancelled" => Some(OrderStatus::Cancelled),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Fulfilment {
+    Pending,
+    Partial,
+    Fulfilled,
+}
+
+// Sales order data model; `order_value` is the pre-tax value of the lines
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SalesOrder {
+    pub id: i64,
+    pub company_id: i64,
+    pub order_number: String,
+    pub order_date: String,
+    pub expected_date: Option<String>,
+    pub customer_id: i64,
+    pub customer_name: String,
+    pub customer_reference: Option<String>,
+    pub status: OrderStatus,
+    pub fulfilment: Fulfilment,
+    pub order_value: f64,
+    pub notes: Option<String>,
+    pub created_at: Option<String>,
+    pub updated_at: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SalesOrderLine {
+    pub id: i64,
+    pub order_id: i64,
+    pub line_no: i64,
+    pub item_id: i64,
+    pub description: String,
+    pub hsn_code: String,
+    pub unit_id: Option<i64>,
+    pub quantity: f64,
+    pub rate: f64,
+    pub discount_percent: f64,
+    pub gst_rate: f64,
+    // Quantity on invoices against this line that are not cancelled, drafts included
+    pub fulfilled_quantity: f64,
+    pub pending_quantity: f64,
+}
+
+impl SalesOrderLine {
+    // Pre-tax value of `quantity` units at this line's rate and discount
+    fn value_of(&self, quantity: f64) -> f64 {
+        round2(quantity * self.rate * (100.0 - self.discount_percent) / 100.0)
+    }
+}
+
+// Lines with an id update that line; the others are added. A rate left out is taken from the
+// customer's price lists.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SalesOrderLineInput {
+    #[serde(default)]
+    pub id: Option<i64>,
+    pub item_id: i64,
+    #[serde(default)]
+    pub description: Option<String>,
+    pub quantity: f64,
+    #[serde(default)]
+    pub rate: Option<f64>,
+    #[serde(default)]
+    pub discount_percent: f64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SaveSalesOrder {
+    // Left empty to take the next number from the sales order sequence
+    #[serde(default)]
+    pub order_number: String,
+    pub order_date: String,
+    #[serde(default)]
+    pub expected_date: Option<String>,
+    pub customer_id: i64,
+    // The customer's purchase order number
+    #[serde(default)]
+    pub customer_reference: Option<String>,
+    pub notes: Option<String>,
+    pub lines: Vec<SalesOrderLineInput>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SalesOrderWithLines {
+    #[serde(flatten)]
+    pub order: SalesOrder,
+    pub lines: Vec<SalesOrderLine>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct OpenOrderRow {
+    pub order_id: i64,
+    pub order_number: String,
+    pub order_date: String,
+    pub expected_date: Option<String>,
+    pub customer_id: i64,
+    pub customer_name: String,
+    pub ordered_quantity: f64,
+    pub fulfilled_quantity: f64,
+    pub pending_quantity: f64,
+    pub pending_value: f64,
+    pub overdue: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct OpenOrdersReport {
+    pub rows: Vec<OpenOrderRow>,
+    pub total_pending_value: f64,
+}
+
+const SELECT_ORDER: &str = "
+    SELECT o.id, o.company_id, o.order_number, o.order_date, o.expected_date, o.customer_id,
+           COALESCE(c.report_customer, '') AS customer_name, o.customer_reference, o.status,
+           o.notes, o.created_at, o.updated_at
+    FROM sales_orders o
+    LEFT JOIN customers c ON c.id = o.customer_id";
+
+// Invoice quantities of `fulfilled` exclude the invoice given as ?2, so an invoice being edited
+// does not count against itself
+const FULFILLED_QUANTITY: &str = "
+    SELECT COALESCE(SUM(il.quantity), 0)
+    FROM invoice_lines il
+    JOIN invoices i ON i.id = il.invoice_id
+    WHERE il.sales_order_line_id = l.id AND i.deleted_at IS NULL AND i.status != 'cancelled'
+      AND i.id IS NOT ?2";
+
+const ORDER_LINE_COLUMNS: &str = "
+    l.id, l.order_id, l.line_no, l.item_id, l.description, l.hsn_code, l.unit_id, l.quantity,
+    l.rate, l.discount_percent, l.gst_rate";
+
+fn order_from_row(row: &Row) -> rusqlite::Result<SalesOrder> {
+    let status: String = row.get("status")?;
+    Ok(SalesOrder {
+        id: row.get("id")?,
+        company_id: row.get("company_id")?,
+        order_number: row.get("order_number")?,
+        order_date: row.get("order_date")?,
+        expected_date: row.get("expected_date")?,
+        customer_id: row.get("customer_id")?,
+        customer_name: row.get("customer_name")?,
+        customer_reference: row.get("customer_reference")?,
+        status: OrderStatus::parse(&status).unwrap_or(OrderStatus::Open),
+        // Both settled from the lines
+        fulfilment: Fulfilment::Pending,
+        order_value: 0.0,
+        notes: row.get("notes")?,
+        created_at: row.get("created_at")?,
+        updated_at: row.get("updated_at")?,
+    })
+}
+
+fn order_line_from_row(row: &Row) -> rusqlite::Result<SalesOrderLine> {
+    let quantity: f64 = row.get("quantity")?;
+    let fulfilled_quantity: f64 = row.get("fulfilled_quantity")?;
+    Ok(SalesOrderLine {
+        id: row.get("id")?,
+        order_id: row.get("order_id")?,
+        line_no: row.get("line_no")?,
+        item_id: row.get("item_id")?,
+        description: row.get("description")?,
+        hsn_code: row.get("hsn_code")?,
+        unit_id: row.get("unit_id")?,
+        quantity,
+        rate: row.get("rate")?,
+        discount_percent: row.get("discount_percent")?,
+        gst_rate: row.get("gst_rate")?,
+        fulfilled_quantity,
+        pending_quantity: (quantity - fulfilled_quantity).max(0.0),
+    })
+}
+
+fn map_write_error(e: rusqlite::Error) -> AppError {
+    let message = e.to_string();
+    if message.contains("UNIQUE constraint failed: sales_orders") {
+        return AppError::conflict("order_number", "A sales order with this number already exists");
+    }
+    AppError::from(e)
+}
+
+fn today() -> String {
+    Local::now().date_naive().format(INVOICE_DATE_FORMAT).to_string()
+}
+
+fn parse_date(field: &str, value: &str) -> Result<NaiveDate, AppError> {
+    NaiveDate::parse_from_str(value.trim(), INVOICE_DATE_FORMAT)
+        .map_err(|_| AppError::validation(field, "Enter the date as YYYY-MM-DD"))
+}
+
+fn order_lines(
+    conn: &Connection,
+    order_id: i64,
+    exclude_invoice: Option<i64>,
+) -> Result<Vec<SalesOrderLine>, AppError> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {}, ({}) AS fulfilled_quantity
+             FROM sales_order_lines l WHERE l.order_id = ?1 ORDER BY l.line_no",
+        ORDER_LINE_COLUMNS, FULFILLED_QUANTITY
+    ))?;
+    let lines = stmt
+        .query_map(params![order_id, exclude_invoice], order_line_from_row)?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(lines)
+}
+
+fn settle(mut order: SalesOrder, lines: &[SalesOrderLine]) -> SalesOrder {
+    let ordered: f64 = lines.iter().map(|line| line.quantity).sum();
+    let pending: f64 = lines.iter().map(|line| line.pending_quantity).sum();
+    order.order_value = round2(lines.iter().map(|line| line.value_of(line.quantity)).sum());
+    order.fulfilment = if pending <= AMOUNT_TOLERANCE {
+        Fulfilment::Fulfilled
+    } else if pending < ordered - AMOUNT_TOLERANCE {
+        Fulfilment::Partial
+    } else {
+        Fulfilment::Pending
+    };
+    order
+}
+
+pub fn get_order_with_lines(
+    conn: &Connection,
+    id: i64,
+    company_id: i64,
+) -> Result<Option<SalesOrderWithLines>, AppError> {
+    let order = conn
+        .query_row(
+            &format!("{} WHERE o.id = ?1 AND o.company_id = ?2", SELECT_ORDER),
+            params![id, company_id],
+            order_from_row,
+        )
+        .optional()?;
+    match order {
+        Some(order) => {
+            let lines = order_lines(conn, id, None)?;
+            Ok(Some(SalesOrderWithLines { order: settle(order, &lines), lines }))
+        }
+        None => Ok(None),
+    }
+}
+
+fn require_order(
+    conn: &Connection,
+    id: i64,
+    company_id: i64,
+) -> Result<SalesOrderWithLines, AppError> {
+    get_order_with_lines(conn, id, company_id)?
+        .ok_or_else(|| AppError::not_found("Sales order not found"))
+}
+
+// Invoice lines against an order must be for the order's customer and item, on an open order,
+// and within the quantity still pending
+pub fn validate_fulfilment(
+    conn: &Connection,
+    invoice: &Invoice,
+    lines: &[InvoiceLineInput],
+) -> Result<(), AppError> {
+    let mut claimed: HashMap<i64, f64> = HashMap::new();
+    for (index, line) in lines.iter().enumerate() {
+        let Some(order_line_id) = line.sales_order_line_id else {
+            continue;
+        };
+        let line_no = index + 1;
+        let order_line = conn
+            .query_row(
+                &format!(
+                    "SELECT {}, o.company_id, o.customer_id, o.status, o.order_number,
+                            ({}) AS fulfilled_quantity
+                     FROM sales_order_lines l
+                     JOIN sales_orders o ON o.id = l.order_id
+                     WHERE l.id = ?1",
+                    ORDER_LINE_COLUMNS, FULFILLED_QUANTITY
+                ),
+                params![order_line_id, invoice.id],
+                |row| {
+                    let status: String = row.get("status")?;
+                    Ok((
+                        order_line_from_row(row)?,
+                        row.get::<_, i64>("company_id")?,
+                        row.get::<_, i64>("customer_id")?,
+                        OrderStatus::parse(&status),
+                        row.get::<_, String>("order_number")?,
+                    ))
+                },
+            )
+            .optional()?;
+        let Some((order_line, company_id, customer_id, status, order_number)) = order_line else {
+            return Err(AppError::validation(
+                "lines",
+                format!("Line {}: sales order line not found", line_no),
+            ));
+        };
+        if company_id != invoice.company_id || customer_id != invoice.customer_id {
+            return Err(AppError::validation(
+                "lines",
+                format!("Line {}: sales order {} is for another customer", line_no, order_number),
+            ));
+        }
+        if status != Some(OrderStatus::Open) {
+            return Err(AppError::validation(
+                "lines",
+                format!("Line {}: sales order {} is not open", line_no, order_number),
+            ));
+        }
+        if line.item_id.is_some_and(|item_id| item_id != order_line.item_id) {
+            return Err(AppError::validation(
+                "lines",
+                format!("Line {}: item differs from the one ordered on {}", line_no, order_number),
+            ));
+        }
+        let total = claimed.entry(order_line_id).or_default();
+        *total += line.quantity;
+        if *total > order_line.pending_quantity + AMOUNT_TOLERANCE {
+            return Err(AppError::validation(
+                "lines",
+                format!(
+                    "Line {}: only {} is pending on sales order {}",
+                    line_no, order_line.pending_quantity, order_number
+                ),
+            ));
+        }
+    }
+    Ok(())
+}
+
+struct PreparedLine {
+    id: Option<i64>,
+    item: items::Item,
+    description: String,
+    quantity: f64,
+    rate: f64,
+    discount_percent: f64,
+}
+
+fn prepare_lines(
+    conn: &Connection,
+    company_id: i64,
+    order: &SaveSalesOrder,
+    order_date: &str,
+) -> Result<Vec<PreparedLine>, AppError> {
+    if order.lines.is_empty() {
+        return Err(AppError::validation("lines", "Add at least one line to the order"));
+    }
+    let mut prepared = Vec::with_capacity(order.lines.len());
+    for (index, line) in order.lines.iter().enumerate() {
+        let line_no = index + 1;
+        if !line.quantity.is_finite() || line.quantity <= 0.0 {
+            return Err(AppError::validation(
+                "lines",
+                format!("Line {}: quantity must be greater than zero", line_no),
+            ));
+        }
+        if !line.discount_percent.is_finite() || !(0.0..=100.0).contains(&line.discount_percent) {
+            return Err(AppError::validation(
+                "lines",
+                format!("Line {}: discount must be between 0 and 100%", line_no),
+            ));
+        }
+        let item = items::get_item_by_id(conn, line.item_id, company_id)?
+            .ok_or_else(|| AppError::not_found(format!("Line {}: item not found", line_no)))?;
+        let rate = match line.rate {
+            Some(rate) if !rate.is_finite() || rate < 0.0 => {
+                return Err(AppError::validation(
+                    "lines",
+                    format!("Line {}: rate must be a non-negative number", line_no),
+                ));
+            }
+            Some(rate) => rate,
+            None => {
+                price_lists::price_for(
+                    conn,
+                    company_id,
+                    item.id,
+                    Some(order.customer_id),
+                    order_date,
+                )?
+                .rate
+            }
+        };
+        let description = line
+            .description
+            .as_deref()
+            .map(str::trim)
+            .filter(|d| !d.is_empty())
+            .unwrap_or(&item.name)
+            .to_string();
+        prepared.push(PreparedLine {
+            id: line.id,
+            item,
+            description,
+            quantity: line.quantity,
+            rate,
+            discount_percent: line.discount_percent,
+        });
+    }
+    Ok(prepared)
+}
+
+// Updates lines in place so invoices keep pointing at them; a line may only be removed or cut
+// below its invoiced quantity if nothing has been invoiced against it
+fn save_lines(
+    conn: &Connection,
+    order_id: i64,
+    existing: &[SalesOrderLine],
+    lines: &[PreparedLine],
+) -> Result<(), AppError> {
+    for old in existing {
+        let kept = lines.iter().find(|line| line.id == Some(old.id));
+        match kept {
+            None if old.fulfilled_quantity > 0.0 => {
+                return Err(AppError::validation(
+                    "lines",
+                    format!("Line {} has been invoiced and cannot be removed", old.line_no),
+                ));
+            }
+            None => {
+                conn.execute("DELETE FROM sales_order_lines WHERE id = ?1", params![old.id])?;
+            }
+            Some(line) => {
+                if old.fulfilled_quantity > 0.0 && line.item.id != old.item_id {
+                    return Err(AppError::validation(
+                        "lines",
+                        format!("Line {} has been invoiced; its item cannot change", old.line_no),
+                    ));
+                }
+                if line.quantity < old.fulfilled_quantity - AMOUNT_TOLERANCE {
+                    return Err(AppError::validation(
+                        "lines",
+                        format!(
+                            "Line {}: {} has already been invoiced",
+                            old.line_no, old.fulfilled_quantity
+                        ),
+                    ));
+                }
+            }
+        }
+    }
+
+    for (index, line) in lines.iter().enumerate() {
+        let line_no = index as i64 + 1;
+        match line.id {
+            Some(id) => {
+                if !existing.iter().any(|old| old.id == id) {
+                    return Err(AppError::not_found(format!("Order line {} not found", id)));
+                }
+                conn.execute(
+                    "UPDATE sales_order_lines SET line_no = ?1, item_id = ?2, description = ?3,
+                            hsn_code = ?4, unit_id = ?5, quantity = ?6, rate = ?7,
+                            discount_percent = ?8, gst_rate = ?9
+                     WHERE id = ?10",
+                    params![
+                        line_no,
+                        line.item.id,
+                        line.description,
+                        line.item.hsn_code,
+                        line.item.unit_id,
+                        line.quantity,
+                        line.rate,
+                        line.discount_percent,
+                        line.item.gst_rate,
+                        id,
+                    ],
+                )?;
+            }
+            None => {
+                conn.execute(
+                    "INSERT INTO sales_order_lines (order_id, line_no, item_id, description,
+                                                    hsn_code, unit_id, quantity, rate,
+                                                    discount_percent, gst_rate)
+                     VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+                    params![
+                        order_id,
+                        line_no,
+                        line.item.id,
+                        line.description,
+                        line.item.hsn_code,
+                        line.item.unit_id,
+                        line.quantity,
+                        line.rate,
+                        line.discount_percent,
+                        line.item.gst_rate,
+                    ],
+                )?;
+            }
+        }
+    }
+    Ok(())
+}
+
+fn validate_header(order: &SaveSalesOrder) -> Result<(String, Option<String>), AppError> {
+    let order_date = parse_date("order_date", &order.order_date)?;
+    let expected = match order.expected_date.as_deref().filter(|d| !d.trim().is_empty()) {
+        Some(value) => Some(parse_date("expected_date", value)?),
+        None => None,
+    };
+    if expected.is_some_and(|expected| expected < order_date) {
+        return Err(AppError::validation(
+            "expected_date",
+            "Expected date cannot be before the order date",
+        ));
+    }
+    if order.customer_reference.as_deref().is_some_and(|r| r.len() > 100) {
+        return Err(AppError::validation(
+            "customer_reference",
+            "Customer reference must be 100 characters or less",
+        ));
+    }
+    if order.notes.as_deref().is_some_and(|n| n.len() > 1000) {
+        return Err(AppError::validation("notes", "Notes must be 1000 characters or less"));
+    }
+    Ok((
+        order_date.format(INVOICE_DATE_FORMAT).to_string(),
+        expected.map(|d| d.format(INVOICE_DATE_FORMAT).to_string()),
+    ))
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn list_sales_orders(
+    pool: State<'_, DbPool>,
+    company_id: i64,
+    status: Option<OrderStatus>,
+    customer_id: Option<i64>,
+) -> Result<Vec<SalesOrder>, AppError> {
+    let conn = db::get_conn(&pool)?;
+    let mut stmt = conn.prepare(&format!(
+        "{} WHERE o.company_id = ?1 AND (?2 IS NULL OR o.status = ?2)
+               AND (?3 IS NULL OR o.customer_id = ?3)
+             ORDER BY o.order_date DESC, o.id DESC",
+        SELECT_ORDER
+    ))?;
+    let orders = stmt
+        .query_map(params![company_id, status.map(|s| s.as_str()), customer_id], order_from_row)?
+        .collect::<Result<Vec<_>, _>>()?;
+    let mut settled = Vec::with_capacity(orders.len());
+    for order in orders {
+        let lines = order_lines(&conn, order.id, None)?;
+        settled.push(settle(order, &lines));
+    }
+    Ok(settled)
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn get_sales_order(
+    pool: State<'_, DbPool>,
+    id: i64,
+    company_id: i64,
+) -> Result<SalesOrderWithLines, AppError> {
+    let conn = db::get_conn(&pool)?;
+    require_order(&conn, id, company_id)
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn create_sales_order(
+    pool: State<'_, DbPool>,
+    company_id: i64,
+    order: SaveSalesOrder,
+) -> Result<SalesOrderWithLines, AppError> {
+    let (order_date, expected_date) = validate_header(&order)?;
+    let mut conn = db::get_conn(&pool)?;
+    let tx = conn.transaction()?;
+    if customers::get_customer_by_id(&tx, order.customer_id, company_id)?.is_none() {
+        return Err(AppError::not_found("Customer not found"));
+    }
+    let lines = prepare_lines(&tx, company_id, &order, &order_date)?;
+    if lines.iter().any(|line| line.id.is_some()) {
+        return Err(AppError::validation("lines", "A new order cannot have existing lines"));
+    }
+    let order_number = match order.order_number.trim() {
+        "" => numbering::allocate_number(&tx, company_id, DocumentType::SalesOrder, &order_date)?,
+        number => number.to_string(),
+    };
+    tx.execute(
+        "INSERT INTO sales_orders (company_id, order_number, order_date, expected_date,
+                                   customer_id, customer_reference, status, notes)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, 'open', ?7)",
+        params![
+            company_id,
+            order_number,
+            order_date,
+            expected_date,
+            order.customer_id,
+            order.customer_reference.as_deref().map(str::trim),
+            order.notes,
+        ],
+    )
+    .map_err(map_write_error)?;
+    let id = tx.last_insert_rowid();
+    save_lines(&tx, id, &[], &lines)?;
+    let created = require_order(&tx, id, company_id)?;
+    audit::record(&tx, company_id, "sales_order", id, AuditAction::Create, None, Some(&created))?;
+    tx.commit()?;
+    Ok(created)
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn update_sales_order(
+    pool: State<'_, DbPool>,
+    id: i64,
+    company_id: i64,
+    order: SaveSalesOrder,
+) -> Result<SalesOrderWithLines, AppError> {
+    let (order_date, expected_date) = validate_header(&order)?;
+    let mut conn = db::get_conn(&pool)?;
+    let tx = conn.transaction()?;
+    let existing = require_order(&tx, id, company_id)?;
+    if existing.order.status != OrderStatus::Open {
+        return Err(AppError::validation("status", "Only open orders can be edited"));
+    }
+    let invoiced = existing.lines.iter().any(|line| line.fulfilled_quantity > 0.0);
+    if invoiced && order.customer_id != existing.order.customer_id {
+        return Err(AppError::validation(
+            "customer_id",
+            "The order has been invoiced; its customer cannot change",
+        ));
+    }
+    if customers::get_customer_by_id(&tx, order.customer_id, company_id)?.is_none() {
+        return Err(AppError::not_found("Customer not found"));
+    }
+    let lines = prepare_lines(&tx, company_id, &order, &order_date)?;
+    let order_number = match order.order_number.trim() {
+        "" => existing.order.order_number.clone(),
+        number => number.to_string(),
+    };
+    tx.execute(
+        "UPDATE sales_orders SET order_number = ?1, order_date = ?2, expected_date = ?3,
+                customer_id = ?4, customer_reference = ?5, notes = ?6,
+                updated_at = CURRENT_TIMESTAMP
+         WHERE id = ?7 AND company_id = ?8",
+        params![
+            order_number,
+            order_date,
+            expected_date,
+            order.customer_id,
+            order.customer_reference.as_deref().map(str::trim),
+            order.notes,
+            id,
+            company_id,
+        ],
+    )
+    .map_err(map_write_error)?;
+    save_lines(&tx, id, &existing.lines, &lines)?;
+    let updated = require_order(&tx, id, company_id)?;
+    audit::record(
+        &tx,
+        company_id,
+        "sales_order",
+        id,
+        AuditAction::Update,
+        Some(&existing),
+        Some(&updated),
+    )?;
+    tx.commit()?;
+    Ok(updated)
+}
+
+// Closing short-closes the pending quantity; cancelling needs an order with nothing invoiced
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn set_sales_order_status(
+    pool: State<'_, DbPool>,
+    id: i64,
+    company_id: i64,
+    status: OrderStatus,
+) -> Result<SalesOrder, AppError> {
+    let conn = db::get_conn(&pool)?;
+    let existing = require_order(&conn, id, company_id)?;
+    if status == OrderStatus::Cancelled
+        && existing.lines.iter().any(|line| line.fulfilled_quantity > 0.0)
+    {
+        return Err(AppError::validation(
+            "status",
+            "The order has been invoiced; close it instead of cancelling it",
+        ));
+    }
+    conn.execute(
+        "UPDATE sales_orders SET status = ?1, updated_at = CURRENT_TIMESTAMP
+         WHERE id = ?2 AND company_id = ?3",
+        params![status.as_str(), id, company_id],
+    )?;
+    let updated = require_order(&conn, id, company_id)?;
+    audit::record(
+        &conn,
+        company_id,
+        "sales_order",
+        id,
+        AuditAction::Update,
+        Some(&existing.order),
+        Some(&updated.order),
+    )?;
+    Ok(updated.order)
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn delete_sales_order(
+    pool: State<'_, DbPool>,
+    id: i64,
+    company_id: i64,
+) -> Result<(), AppError> {
+    let conn = db::get_conn(&pool)?;
+    let existing = require_order(&conn, id, company_id)?;
+    let referenced: bool = conn.query_row(
+        "SELECT EXISTS(SELECT 1 FROM invoice_lines il
+                           JOIN sales_order_lines l ON l.id = il.sales_order_line_id
+                           WHERE l.order_id = ?1)",
+        params![id],
+        |row| row.get(0),
+    )?;
+    if referenced {
+        return Err(AppError::conflict(
+            "id",
+            "Invoices have been raised against this order; close it instead",
+        ));
+    }
+    conn.execute(
+        "DELETE FROM sales_orders WHERE id = ?1 AND company_id = ?2",
+        params![id, company_id],
+    )?;
+    audit::record(&conn, company_id, "sales_order", id, AuditAction::Delete, Some(&existing), None)
+}
+
+// Raises a draft invoice for everything still pending on the order
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn create_invoice_from_order(
+    pool: State<'_, DbPool>,
+    id: i64,
+    company_id: i64,
+    invoice_date: Option<String>,
+) -> Result<SavedInvoice, AppError> {
+    let invoice_date = match invoice_date.as_deref().filter(|d| !d.trim().is_empty()) {
+        Some(date) => parse_date("invoice_date", date)?.format(INVOICE_DATE_FORMAT).to_string(),
+        None => today(),
+    };
+    let mut conn = db::get_conn(&pool)?;
+    let tx = conn.transaction()?;
+    let existing = require_order(&tx, id, company_id)?;
+    if existing.order.status != OrderStatus::Open {
+        return Err(AppError::validation("status", "Only open orders can be invoiced"));
+    }
+    let lines: Vec<InvoiceLineInput> = existing
+        .lines
+        .iter()
+        .filter(|line| line.pending_quantity > AMOUNT_TOLERANCE)
+        .map(|line| InvoiceLineInput {
+            description: line.description.clone(),
+            hsn_code: line.hsn_code.clone(),
+            quantity: line.pending_quantity,
+            rate: line.rate,
+            discount_percent: line.discount_percent,
+            discount: 0.0,
+            invoice_discount: 0.0,
+            taxable_value: 0.0,
+            gst_rate: line.gst_rate,
+            cgst_amount: 0.0,
+            sgst_amount: 0.0,
+            igst_amount: 0.0,
+            cess_rate: 0.0,
+            cess_amount: 0.0,
+            item_id: Some(line.item_id),
+            unit_id: line.unit_id,
+            sales_order_line_id: Some(line.id),
+            batch_id: None,
+            serial_numbers: Vec::new(),
+        })
+        .collect();
+    if lines.is_empty() {
+        return Err(AppError::validation("id", "Nothing is pending on this order"));
+    }
+
+    let order = &existing.order;
+    let notes = match order.customer_reference.as_deref().filter(|r| !r.trim().is_empty()) {
+        Some(reference) => format!("Sales order {} (PO {})", order.order_number, reference),
+        None => format!("Sales order {}", order.order_number),
+    };
+    let saved = invoices::create_invoice_from_lines(
+        &tx,
+        CreateInvoice {
+            company_id,
+            invoice_number: String::new(),
+            invoice_date,
+            customer_id: order.customer_id,
+            place_of_supply: String::new(),
+            supply_kind: SupplyKind::Regular,
+            export: None,
+            sez_mode: None,
+            discount_percent: 0.0,
+            discount_amount: 0.0,
+            reverse_charge: false,
+            taxable_value: 0.0,
+            cgst_amount: 0.0,
+            sgst_amount: 0.0,
+            igst_amount: 0.0,
+            cess_amount: 0.0,
+            total_amount: 0.0,
+            currency: None,
+            exchange_rate: None,
+            status: Some(InvoiceStatus::Draft),
+            notes: Some(notes),
+            lines,
+        },
+    )?;
+    tx.commit()?;
+    Ok(saved)
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn open_orders_report(
+    pool: State<'_, DbPool>,
+    company_id: i64,
+    customer_id: Option<i64>,
+) -> Result<OpenOrdersReport, AppError> {
+    let conn = db::get_conn(&pool)?;
+    let mut stmt = conn.prepare(&format!(
+        "{} WHERE o.company_id = ?1 AND o.status = 'open'
+               AND (?2 IS NULL OR o.customer_id = ?2)
+             ORDER BY o.order_date, o.id",
+        SELECT_ORDER
+    ))?;
+    let orders = stmt
+        .query_map(params![company_id, customer_id], order_from_row)?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let today = today();
+    let mut rows = Vec::new();
+    for order in orders {
+        let lines = order_lines(&conn, order.id, None)?;
+        let pending_quantity: f64 = lines.iter().map(|line| line.pending_quantity).sum();
+        if pending_quantity <= AMOUNT_TOLERANCE {
+            continue;
+        }
+        rows.push(OpenOrderRow {
+            order_id: order.id,
+            order_number: order.order_number,
+            order_date: order.order_date,
+            overdue: order.expected_date.as_deref().is_some_and(|date| date < today.as_str()),
+            expected_date: order.expected_date,
+            customer_id: order.customer_id,
+            customer_name: order.customer_name,
+            ordered_quantity: lines.iter().map(|line| line.quantity).sum(),
+            fulfilled_quantity: lines.iter().map(|line| line.fulfilled_quantity).sum(),
+            pending_quantity,
+            pending_value: round2(
+                lines.iter().map(|line| line.value_of(line.pending_quantity)).sum(),
+            ),
+        });
+    }
+    let total_pending_value = round2(rows.iter().map(|row| row.pending_value).sum());
+    Ok(OpenOrdersReport { rows, total_pending_value })
+}
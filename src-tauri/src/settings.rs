@@ -0,0 +1,491 @@
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use tauri::State;
+
+use crate::auth::Session;
+use crate::backup_schedule::{self, BackupSchedule, UpdateBackupSchedule};
+use crate::companies;
+use crate::db::{self, get_setting, set_setting, DbPool};
+use crate::error::AppError;
+use crate::place_of_supply::SupplyKind;
+use crate::rounding::{self, RoundingMode};
+use crate::states;
+
+const SETTING_SUPPLY_KIND: &str = "invoice_default_supply_kind";
+const SETTING_PAYMENT_TERMS: &str = "invoice_payment_terms_days";
+const SETTING_INVOICE_NOTES: &str = "invoice_default_notes";
+const SETTING_DATE_FORMAT: &str = "date_format";
+const SETTING_THEME: &str = "theme";
+
+const COMPANY_PLACE_OF_SUPPLY: &str = "default_place_of_supply";
+const COMPANY_PAYMENT_TERMS: &str = "payment_terms_days";
+const COMPANY_INVOICE_NOTES: &str = "invoice_notes";
+const COMPANY_SIGNATORY: &str = "authorised_signatory";
+
+const DEFAULT_PAYMENT_TERMS_DAYS: u32 = 30;
+const MAX_PAYMENT_TERMS_DAYS: u32 = 365;
+const MAX_NOTES_LENGTH: usize = 1000;
+const MAX_SIGNATORY_LENGTH: usize = 100;
+
+// A value stored as text in app_settings or company_settings
+pub trait SettingValue: Sized {
+    fn to_setting(&self) -> String;
+    fn from_setting(value: &str) -> Option<Self>;
+}
+
+impl SettingValue for bool {
+    fn to_setting(&self) -> String {
+        self.to_string()
+    }
+
+    fn from_setting(value: &str) -> Option<Self> {
+        value.parse().ok()
+    }
+}
+
+impl SettingValue for u32 {
+    fn to_setting(&self) -> String {
+        self.to_string()
+    }
+
+    fn from_setting(value: &str) -> Option<Self> {
+        value.parse().ok()
+    }
+}
+
+// Empty text reads back as no value
+impl SettingValue for String {
+    fn to_setting(&self) -> String {
+        self.clone()
+    }
+
+    fn from_setting(value: &str) -> Option<Self> {
+        Some(value.to_string()).filter(|value| !value.is_empty())
+    }
+}
+
+impl SettingValue for RoundingMode {
+    fn to_setting(&self) -> String {
+        self.as_str().to_string()
+    }
+
+    fn from_setting(value: &str) -> Option<Self> {
+        RoundingMode::parse(value)
+    }
+}
+
+impl SettingValue for SupplyKind {
+    fn to_setting(&self) -> String {
+        self.as_str().to_string()
+    }
+
+    fn from_setting(value: &str) -> Option<Self> {
+        SupplyKind::parse(value)
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DateFormat {
+    #[default]
+    #[serde(rename = "dd/mm/yyyy")]
+    DayMonthYear,
+    #[serde(rename = "dd-mm-yyyy")]
+    DayMonthYearDashed,
+    #[serde(rename = "yyyy-mm-dd")]
+    YearMonthDay,
+    #[serde(rename = "mm/dd/yyyy")]
+    MonthDayYear,
+}
+
+impl DateFormat {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DateFormat::DayMonthYear => "dd/mm/yyyy",
+            DateFormat::DayMonthYearDashed => "dd-mm-yyyy",
+            DateFormat::YearMonthDay => "yyyy-mm-dd",
+            DateFormat::MonthDayYear => "mm/dd/yyyy",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "dd/mm/yyyy" => Some(DateFormat::DayMonthYear),
+            "dd-mm-yyyy" => Some(DateFormat::DayMonthYearDashed),
+            "yyyy-mm-dd" => Some(DateFormat::YearMonthDay),
+            "mm/dd/yyyy" => Some(DateFormat::MonthDayYear),
+            _ => None,
+        }
+    }
+}
+
+impl SettingValue for DateFormat {
+    fn to_setting(&self) -> String {
+        self.as_str().to_string()
+    }
+
+    fn from_setting(value: &str) -> Option<Self> {
+        DateFormat::parse(value)
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum Theme {
+    Light,
+    Dark,
+    #[default]
+    System,
+}
+
+impl Theme {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Theme::Light => "light",
+            Theme::Dark => "dark",
+            Theme::System => "system",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "light" => Some(Theme::Light),
+            "dark" => Some(Theme::Dark),
+            "system" => Some(Theme::System),
+            _ => None,
+        }
+    }
+}
+
+impl SettingValue for Theme {
+    fn to_setting(&self) -> String {
+        self.as_str().to_string()
+    }
+
+    fn from_setting(value: &str) -> Option<Self> {
+        Theme::parse(value)
+    }
+}
+
+pub fn get<T: SettingValue>(conn: &Connection, key: &str) -> Result<Option<T>, String> {
+    Ok(get_setting(conn, key)?.and_then(|value| T::from_setting(&value)))
+}
+
+pub fn put<T: SettingValue>(conn: &Connection, key: &str, value: &T) -> Result<(), String> {
+    Ok(set_setting(conn, key, &value.to_setting())?)
+}
+
+pub fn get_for_company<T: SettingValue>(
+    conn: &Connection,
+    company_id: i64,
+    key: &str,
+) -> Result<Option<T>, AppError> {
+    let value: Option<String> = conn
+        .query_row(
+            "SELECT value FROM company_settings WHERE company_id = ?1 AND key = ?2",
+            params![company_id, key],
+            |row| row.get(0),
+        )
+        .optional()?;
+    Ok(value.and_then(|value| T::from_setting(&value)))
+}
+
+// Clearing a company value makes the company fall back to the application default
+pub fn put_for_company<T: SettingValue>(
+    conn: &Connection,
+    company_id: i64,
+    key: &str,
+    value: Option<&T>,
+) -> Result<(), AppError> {
+    match value {
+        Some(value) => conn.execute(
+            "INSERT INTO company_settings (company_id, key, value) VALUES (?1, ?2, ?3)
+             ON CONFLICT(company_id, key) DO UPDATE SET value = ?3, updated_at = CURRENT_TIMESTAMP",
+            params![company_id, key, value.to_setting()],
+        ),
+        None => conn.execute(
+            "DELETE FROM company_settings WHERE company_id = ?1 AND key = ?2",
+            params![company_id, key],
+        ),
+    }?;
+    Ok(())
+}
+
+// Defaults for new invoices; rounding is the same setting as rounding::get_invoice_rounding
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct InvoiceDefaults {
+    pub rounding: RoundingMode,
+    pub supply_kind: SupplyKind,
+    pub payment_terms_days: u32,
+    pub notes: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct DisplaySettings {
+    pub date_format: DateFormat,
+    pub theme: Theme,
+}
+
+// Per-company overrides; a missing value means the application default applies
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct CompanyPreferences {
+    pub company_id: i64,
+    pub place_of_supply: Option<String>,
+    pub payment_terms_days: Option<u32>,
+    pub invoice_notes: Option<String>,
+    pub authorised_signatory: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct AppSettings {
+    pub invoice: InvoiceDefaults,
+    pub backup: BackupSchedule,
+    pub display: DisplaySettings,
+    pub company: Option<CompanyPreferences>,
+}
+
+// Sections left out are not changed
+#[derive(Debug, Serialize, Deserialize, Default)]
+pub struct UpdateSettings {
+    #[serde(default)]
+    pub invoice: Option<InvoiceDefaults>,
+    #[serde(default)]
+    pub backup: Option<UpdateBackupSchedule>,
+    #[serde(default)]
+    pub display: Option<DisplaySettings>,
+    #[serde(default)]
+    pub company: Option<CompanyPreferences>,
+}
+
+pub fn load_invoice_defaults(conn: &Connection) -> Result<InvoiceDefaults, String> {
+    Ok(InvoiceDefaults {
+        rounding: rounding::load_mode(conn)?,
+        supply_kind: get(conn, SETTING_SUPPLY_KIND)?.unwrap_or_default(),
+        payment_terms_days: get(conn, SETTING_PAYMENT_TERMS)?
+            .unwrap_or(DEFAULT_PAYMENT_TERMS_DAYS),
+        notes: get(conn, SETTING_INVOICE_NOTES)?,
+    })
+}
+
+// Days a company's customers get to pay; the company preference wins over the app default
+pub fn payment_terms_days(conn: &Connection, company_id: i64) -> Result<u32, String> {
+    match get_for_company(conn, company_id, COMPANY_PAYMENT_TERMS)? {
+        Some(days) => Ok(days),
+        None => Ok(load_invoice_defaults(conn)?.payment_terms_days),
+    }
+}
+
+pub fn load_display(conn: &Connection) -> Result<DisplaySettings, String> {
+    Ok(DisplaySettings {
+        date_format: get(conn, SETTING_DATE_FORMAT)?.unwrap_or_default(),
+        theme: get(conn, SETTING_THEME)?.unwrap_or_default(),
+    })
+}
+
+// A user's own choice is kept under the app-wide key with the user id appended
+fn user_key(key: &str, user_id: i64) -> String {
+    format!("{}.user.{}", key, user_id)
+}
+
+// What `user_id` picked, falling back to the app-wide display settings
+pub fn load_user_display(
+    conn: &Connection,
+    user_id: Option<i64>,
+) -> Result<DisplaySettings, String> {
+    let defaults = load_display(conn)?;
+    let Some(user_id) = user_id else {
+        return Ok(defaults);
+    };
+    Ok(DisplaySettings {
+        date_format: get(conn, &user_key(SETTING_DATE_FORMAT, user_id))?
+            .unwrap_or(defaults.date_format),
+        theme: get(conn, &user_key(SETTING_THEME, user_id))?.unwrap_or(defaults.theme),
+    })
+}
+
+pub fn load_company_preferences(
+    conn: &Connection,
+    company_id: i64,
+) -> Result<CompanyPreferences, String> {
+    Ok(CompanyPreferences {
+        company_id,
+        place_of_supply: get_for_company(conn, company_id, COMPANY_PLACE_OF_SUPPLY)?,
+        payment_terms_days: get_for_company(conn, company_id, COMPANY_PAYMENT_TERMS)?,
+        invoice_notes: get_for_company(conn, company_id, COMPANY_INVOICE_NOTES)?,
+        authorised_signatory: get_for_company(conn, company_id, COMPANY_SIGNATORY)?,
+    })
+}
+
+pub fn load_settings(conn: &Connection, company_id: Option<i64>) -> Result<AppSettings, String> {
+    let company = match company_id {
+        Some(company_id) => Some(load_company_preferences(conn, company_id)?),
+        None => None,
+    };
+    Ok(AppSettings {
+        invoice: load_invoice_defaults(conn)?,
+        backup: backup_schedule::load_schedule(conn)?,
+        display: load_display(conn)?,
+        company,
+    })
+}
+
+fn trimmed(value: &Option<String>) -> Option<String> {
+    value
+        .as_deref()
+        .map(str::trim)
+        .filter(|value| !value.is_empty())
+        .map(str::to_string)
+}
+
+fn validate_payment_terms(field: &str, days: u32) -> Result<(), AppError> {
+    if days > MAX_PAYMENT_TERMS_DAYS {
+        return Err(AppError::validation(
+            field,
+            format!("Payment terms cannot be more than {} days", MAX_PAYMENT_TERMS_DAYS),
+        ));
+    }
+    Ok(())
+}
+
+fn validate_notes(field: &str, notes: &Option<String>) -> Result<(), AppError> {
+    if notes.as_ref().is_some_and(|notes| notes.chars().count() > MAX_NOTES_LENGTH) {
+        return Err(AppError::validation(
+            field,
+            format!("Invoice notes cannot be longer than {} characters", MAX_NOTES_LENGTH),
+        ));
+    }
+    Ok(())
+}
+
+fn validate_update(conn: &Connection, update: &UpdateSettings) -> Result<(), AppError> {
+    if let Some(invoice) = &update.invoice {
+        validate_payment_terms("invoice.payment_terms_days", invoice.payment_terms_days)?;
+        validate_notes("invoice.notes", &trimmed(&invoice.notes))?;
+    }
+    if let Some(company) = &update.company {
+        if companies::get_company_by_id(conn, company.company_id)?.is_none() {
+            return Err(AppError::not_found("Company not found"));
+        }
+        if let Some(code) = trimmed(&company.place_of_supply) {
+            if !states::is_known_state_code(&code) {
+                return Err(AppError::validation(
+                    "company.place_of_supply",
+                    format!("Unknown state code {}", code),
+                ));
+            }
+        }
+        if let Some(days) = company.payment_terms_days {
+            validate_payment_terms("company.payment_terms_days", days)?;
+        }
+        validate_notes("company.invoice_notes", &trimmed(&company.invoice_notes))?;
+        if trimmed(&company.authorised_signatory)
+            .is_some_and(|name| name.chars().count() > MAX_SIGNATORY_LENGTH)
+        {
+            return Err(AppError::validation(
+                "company.authorised_signatory",
+                format!(
+                    "Authorised signatory cannot be longer than {} characters",
+                    MAX_SIGNATORY_LENGTH
+                ),
+            ));
+        }
+    }
+    Ok(())
+}
+
+fn save_update(conn: &Connection, update: &UpdateSettings) -> Result<(), String> {
+    if let Some(invoice) = &update.invoice {
+        rounding::save_mode(conn, invoice.rounding)?;
+        put(conn, SETTING_SUPPLY_KIND, &invoice.supply_kind)?;
+        put(conn, SETTING_PAYMENT_TERMS, &invoice.payment_terms_days)?;
+        put(conn, SETTING_INVOICE_NOTES, &trimmed(&invoice.notes).unwrap_or_default())?;
+    }
+    if let Some(backup) = &update.backup {
+        backup_schedule::save_schedule(conn, backup)?;
+    }
+    if let Some(display) = &update.display {
+        put(conn, SETTING_DATE_FORMAT, &display.date_format)?;
+        put(conn, SETTING_THEME, &display.theme)?;
+    }
+    if let Some(company) = &update.company {
+        let id = company.company_id;
+        let place_of_supply = trimmed(&company.place_of_supply);
+        let notes = trimmed(&company.invoice_notes);
+        let signatory = trimmed(&company.authorised_signatory);
+        put_for_company(conn, id, COMPANY_PLACE_OF_SUPPLY, place_of_supply.as_ref())?;
+        put_for_company(conn, id, COMPANY_PAYMENT_TERMS, company.payment_terms_days.as_ref())?;
+        put_for_company(conn, id, COMPANY_INVOICE_NOTES, notes.as_ref())?;
+        put_for_company(conn, id, COMPANY_SIGNATORY, signatory.as_ref())?;
+    }
+    Ok(())
+}
+
+// Company preferences are returned for `company_id`, or the active company when it is not given
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn get_settings(
+    pool: State<'_, DbPool>,
+    company_id: Option<i64>,
+) -> Result<AppSettings, AppError> {
+    let conn = db::get_conn(&pool)?;
+    let company_id = match company_id {
+        Some(company_id) => Some(company_id),
+        None => companies::saved_active_company(&conn)?,
+    };
+    Ok(load_settings(&conn, company_id)?)
+}
+
+// All sections are saved together or not at all
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn update_settings(
+    pool: State<'_, DbPool>,
+    settings: UpdateSettings,
+) -> Result<AppSettings, AppError> {
+    let mut conn = db::get_conn(&pool)?;
+    validate_update(&conn, &settings)?;
+    let tx = conn.transaction()?;
+    save_update(&tx, &settings)?;
+    tx.commit()?;
+    let company_id = match &settings.company {
+        Some(company) => Some(company.company_id),
+        None => companies::saved_active_company(&conn)?,
+    };
+    Ok(load_settings(&conn, company_id)?)
+}
+
+// The signed-in user's display settings. Before anyone signs in these are the app-wide ones, so
+// the sign-in screen already uses the configured theme.
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn get_display_settings(
+    pool: State<'_, DbPool>,
+    session: State<'_, Session>,
+) -> Result<DisplaySettings, AppError> {
+    let conn = db::get_conn(&pool)?;
+    Ok(load_user_display(&conn, session.current().map(|user| user.id))?)
+}
+
+// Saved for the signed-in user only; the app-wide defaults are part of update_settings. Before
+// the first account exists there is nobody to save them for, so the defaults are changed.
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn update_display_settings(
+    pool: State<'_, DbPool>,
+    session: State<'_, Session>,
+    display: DisplaySettings,
+) -> Result<DisplaySettings, AppError> {
+    let mut conn = db::get_conn(&pool)?;
+    let user_id = session.current().map(|user| user.id);
+    let (date_format_key, theme_key) = match user_id {
+        Some(user_id) => (
+            user_key(SETTING_DATE_FORMAT, user_id),
+            user_key(SETTING_THEME, user_id),
+        ),
+        None => (SETTING_DATE_FORMAT.to_string(), SETTING_THEME.to_string()),
+    };
+    let tx = conn.transaction()?;
+    put(&tx, &date_format_key, &display.date_format)?;
+    put(&tx, &theme_key, &display.theme)?;
+    tx.commit()?;
+    Ok(load_user_display(&conn, user_id)?)
+}
@@ -0,0 +1,177 @@
+use rusqlite::{params, Connection, OptionalExtension, Row};
+use serde::{Deserialize, Serialize};
+use tauri::State;
+
+use crate::db::{self, DbPool};
+use crate::error::AppError;
+use crate::gstin;
+
+// State master data model
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct IndianState {
+    pub code: String,
+    pub name: String,
+    pub is_union_territory: bool,
+}
+
+// GST state codes. 25 and 26 were merged in 2020 but old registrations still carry 25.
+const STATE_SEED: &[(&str, &str, bool)] = &[
+    ("01", "Jammu and Kashmir", true),
+    ("02", "Himachal Pradesh", false),
+    ("03", "Punjab", false),
+    ("04", "Chandigarh", true),
+    ("05", "Uttarakhand", false),
+    ("06", "Haryana", false),
+    ("07", "Delhi", true),
+    ("08", "Rajasthan", false),
+    ("09", "Uttar Pradesh", false),
+    ("10", "Bihar", false),
+    ("11", "Sikkim", false),
+    ("12", "Arunachal Pradesh", false),
+    ("13", "Nagaland", false),
+    ("14", "Manipur", false),
+    ("15", "Mizoram", false),
+    ("16", "Tripura", false),
+    ("17", "Meghalaya", false),
+    ("18", "Assam", false),
+    ("19", "West Bengal", false),
+    ("20", "Jharkhand", false),
+    ("21", "Odisha", false),
+    ("22", "Chhattisgarh", false),
+    ("23", "Madhya Pradesh", false),
+    ("24", "Gujarat", false),
+    ("25", "Daman and Diu", true),
+    ("26", "Dadra and Nagar Haveli and Daman and Diu", true),
+    ("27", "Maharashtra", false),
+    ("28", "Andhra Pradesh (Old)", false),
+    ("29", "Karnataka", false),
+    ("30", "Goa", false),
+    ("31", "Lakshadweep", true),
+    ("32", "Kerala", false),
+    ("33", "Tamil Nadu", false),
+    ("34", "Puducherry", true),
+    ("35", "Andaman and Nicobar Islands", true),
+    ("36", "Telangana", false),
+    ("37", "Andhra Pradesh", false),
+    ("38", "Ladakh", true),
+    ("97", "Other Territory", true),
+    ("99", "Centre Jurisdiction", false),
+];
+
+fn state_from_row(row: &Row) -> rusqlite::Result<IndianState> {
+    Ok(IndianState {
+        code: row.get("code")?,
+        name: row.get("name")?,
+        is_union_territory: row.get("is_union_territory")?,
+    })
+}
+
+// Used by the migration that creates the master table
+pub fn seed_states(conn: &Connection) -> Result<(), String> {
+    let mut stmt = conn
+        .prepare("INSERT OR IGNORE INTO states (code, name, is_union_territory) VALUES (?1, ?2, ?3)")
+        .map_err(|e| e.to_string())?;
+    for (code, name, is_union_territory) in STATE_SEED {
+        stmt.execute(params![code, name, is_union_territory])
+            .map_err(|e| e.to_string())?;
+    }
+    Ok(())
+}
+
+pub fn get_state_by_code(conn: &Connection, code: &str) -> Result<Option<IndianState>, String> {
+    conn.query_row(
+        "SELECT code, name, is_union_territory FROM states WHERE code = ?1",
+        params![code.trim()],
+        state_from_row,
+    )
+    .optional()
+    .map_err(|e| e.to_string())
+}
+
+pub fn get_state_by_name(conn: &Connection, name: &str) -> Result<Option<IndianState>, String> {
+    conn.query_row(
+        "SELECT code, name, is_union_territory FROM states WHERE name = ?1 COLLATE NOCASE",
+        params![name.trim()],
+        state_from_row,
+    )
+    .optional()
+    .map_err(|e| e.to_string())
+}
+
+// The states table is read-only and seeded from STATE_SEED, so the seed is the master list
+pub fn is_known_state_code(code: &str) -> bool {
+    STATE_SEED.iter().any(|(known, _, _)| *known == code)
+}
+
+fn check_known(code: &str) -> Result<String, AppError> {
+    if !code.is_empty() && !is_known_state_code(code) {
+        return Err(AppError::validation(
+            "state_code",
+            format!("{} is not a valid GST state code", code),
+        ));
+    }
+    Ok(code.to_string())
+}
+
+// Returns the state code to store: auto-filled from the GSTIN when blank, rejected when it contradicts the GSTIN
+pub fn resolve_state_code(gst_no: &str, state_code: &str) -> Result<String, AppError> {
+    let gst_no = gst_no.trim();
+    let state_code = state_code.trim();
+
+    if gst_no.is_empty() {
+        return check_known(state_code);
+    }
+
+    let derived = &gst_no[..2.min(gst_no.len())];
+    if state_code.is_empty() {
+        return check_known(derived);
+    }
+    if state_code != derived {
+        return Err(AppError::validation(
+            "state_code",
+            format!(
+                "State code {} does not match the GSTIN state code {}",
+                state_code, derived
+            ),
+        ));
+    }
+    check_known(state_code)
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn derive_state_from_gstin(
+    pool: State<'_, DbPool>,
+    gstin: String,
+) -> Result<IndianState, AppError> {
+    let parsed = gstin::parse_gstin(&gstin).map_err(|e| AppError::validation("gstin", e.message))?;
+    let conn = db::get_conn(&pool)?;
+    get_state_by_code(&conn, &parsed.state_code)?.ok_or_else(|| {
+        AppError::not_found(format!(
+            "State code {} is not in the state master",
+            parsed.state_code
+        ))
+    })
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn list_states(pool: State<'_, DbPool>) -> Result<Vec<IndianState>, AppError> {
+    let conn = db::get_conn(&pool)?;
+    let mut stmt =
+        conn.prepare("SELECT code, name, is_union_territory FROM states ORDER BY code")?;
+    let states = stmt
+        .query_map([], state_from_row)?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(states)
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn get_state(
+    pool: State<'_, DbPool>,
+    code: String,
+) -> Result<Option<IndianState>, AppError> {
+    let conn = db::get_conn(&pool)?;
+    Ok(get_state_by_code(&conn, &code)?)
+}
@@ -0,0 +1,511 @@
+use std::collections::BTreeMap;
+
+use chrono::NaiveDate;
+use rusqlite::{params, Connection, OptionalExtension, Row};
+use serde::{Deserialize, Serialize};
+use tauri::State;
+
+use crate::audit::{self, AuditAction};
+use crate::db::{self, DbPool};
+use crate::error::AppError;
+use crate::financial_years;
+use crate::invoices::{round2, Invoice, InvoiceLineInput, InvoiceStatus, INVOICE_DATE_FORMAT};
+use crate::items::{self, Item};
+use crate::settings;
+use crate::stock_valuation::ValuationMethod;
+
+const SETTING_BLOCK_NEGATIVE_STOCK: &str = "block_negative_stock";
+const SETTING_VALUATION_METHOD: &str = "stock_valuation_method";
+const MAX_REASON_LENGTH: usize = 200;
+// Quantities closer to zero than this are treated as zero
+const QUANTITY_TOLERANCE: f64 = 0.0005;
+
+// Stock movements of one item, oldest first. Issued invoices take stock out and issued credit
+// notes against them bring it back; drafts and cancelled documents move nothing. ?3 leaves out
+// one invoice so it can be checked against the stock that would remain without it.
+const MOVEMENTS: &str = "
+    SELECT 'invoice' AS source, i.id AS document_id, i.invoice_number AS document_number,
+           i.invoice_date AS entry_date, 0.0 AS quantity_in, l.quantity AS quantity_out,
+           NULL AS unit_cost, NULL AS reason
+    FROM invoice_lines l
+    JOIN invoices i ON i.id = l.invoice_id
+    WHERE i.company_id = ?1 AND l.item_id = ?2 AND i.status = 'issued'
+      AND i.deleted_at IS NULL AND (?3 IS NULL OR i.id != ?3)
+    UNION ALL
+    SELECT 'credit_note', n.id, n.note_number, n.note_date, nl.quantity, 0.0, NULL, n.reason
+    FROM credit_debit_note_lines nl
+    JOIN credit_debit_notes n ON n.id = nl.note_id
+    JOIN invoice_lines l ON l.id = nl.invoice_line_id
+    WHERE n.company_id = ?1 AND l.item_id = ?2 AND n.note_type = 'credit'
+      AND n.status = 'issued'
+    UNION ALL
+    SELECT 'adjustment', a.id, NULL, a.adjustment_date, MAX(a.quantity, 0), MAX(-a.quantity, 0),
+           a.unit_cost, a.reason
+    FROM stock_adjustments a
+    WHERE a.company_id = ?1 AND a.item_id = ?2";
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum MovementSource {
+    Invoice,
+    CreditNote,
+    Adjustment,
+}
+
+impl MovementSource {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "invoice" => Some(MovementSource::Invoice),
+            "credit_note" => Some(MovementSource::CreditNote),
+            "adjustment" => Some(MovementSource::Adjustment),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct StockMovement {
+    pub source: MovementSource,
+    pub document_id: i64,
+    pub document_number: Option<String>,
+    pub entry_date: String,
+    pub quantity_in: f64,
+    pub quantity_out: f64,
+    // Only adjustments adding stock carry a cost of their own
+    pub unit_cost: Option<f64>,
+    pub reason: Option<String>,
+    // Stock on hand after this movement
+    pub balance: f64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct StockLedger {
+    pub item: Item,
+    pub from_date: Option<String>,
+    pub to_date: Option<String>,
+    pub opening_quantity: f64,
+    pub entries: Vec<StockMovement>,
+    pub total_in: f64,
+    pub total_out: f64,
+    pub closing_quantity: f64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct StockLevel {
+    pub item_id: i64,
+    pub code: String,
+    pub name: String,
+    pub unit_id: Option<i64>,
+    pub quantity: f64,
+}
+
+// A manual correction: positive quantities add stock (e.g. opening stock, goods received),
+// negative ones remove it (e.g. damage, shortage)
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct StockAdjustment {
+    pub id: i64,
+    pub company_id: i64,
+    pub item_id: i64,
+    pub adjustment_date: String,
+    pub quantity: f64,
+    pub unit_cost: Option<f64>,
+    pub reason: String,
+    pub created_at: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SaveStockAdjustment {
+    pub item_id: i64,
+    pub adjustment_date: String,
+    pub quantity: f64,
+    // Cost per unit of stock added, for valuation
+    #[serde(default)]
+    pub unit_cost: Option<f64>,
+    pub reason: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct StockSettings {
+    // Refuse to issue invoices that would take an item below zero instead of only warning
+    pub block_negative_stock: bool,
+    #[serde(default)]
+    pub valuation_method: ValuationMethod,
+}
+
+const SELECT_ADJUSTMENT: &str = "
+    SELECT id, company_id, item_id, adjustment_date, quantity, unit_cost, reason, created_at
+    FROM stock_adjustments";
+
+fn adjustment_from_row(row: &Row) -> rusqlite::Result<StockAdjustment> {
+    Ok(StockAdjustment {
+        id: row.get("id")?,
+        company_id: row.get("company_id")?,
+        item_id: row.get("item_id")?,
+        adjustment_date: row.get("adjustment_date")?,
+        quantity: row.get("quantity")?,
+        unit_cost: row.get("unit_cost")?,
+        reason: row.get("reason")?,
+        created_at: row.get("created_at")?,
+    })
+}
+
+fn movement_from_row(row: &Row) -> rusqlite::Result<StockMovement> {
+    let source: String = row.get("source")?;
+    Ok(StockMovement {
+        source: MovementSource::parse(&source).unwrap_or(MovementSource::Adjustment),
+        document_id: row.get("document_id")?,
+        document_number: row.get("document_number")?,
+        entry_date: row.get("entry_date")?,
+        quantity_in: row.get("quantity_in")?,
+        quantity_out: row.get("quantity_out")?,
+        unit_cost: row.get("unit_cost")?,
+        reason: row.get("reason")?,
+        balance: 0.0,
+    })
+}
+
+pub fn load_settings(conn: &Connection, company_id: i64) -> Result<StockSettings, String> {
+    Ok(StockSettings {
+        block_negative_stock: settings::get_for_company(
+            conn,
+            company_id,
+            SETTING_BLOCK_NEGATIVE_STOCK,
+        )?
+        .unwrap_or(false),
+        valuation_method: settings::get_for_company(conn, company_id, SETTING_VALUATION_METHOD)?
+            .unwrap_or_default(),
+    })
+}
+
+// Every movement of the item, oldest first, without running balances
+pub fn movements(
+    conn: &Connection,
+    company_id: i64,
+    item_id: i64,
+    exclude_invoice: Option<i64>,
+) -> Result<Vec<StockMovement>, AppError> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT * FROM ({}) ORDER BY entry_date, quantity_out > 0, document_id",
+        MOVEMENTS
+    ))?;
+    let movements = stmt
+        .query_map(params![company_id, item_id, exclude_invoice], movement_from_row)?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(movements)
+}
+
+pub fn on_hand(
+    conn: &Connection,
+    company_id: i64,
+    item_id: i64,
+    exclude_invoice: Option<i64>,
+) -> Result<f64, AppError> {
+    conn.query_row(
+        &format!(
+            "SELECT COALESCE(SUM(quantity_in - quantity_out), 0) FROM ({})",
+            MOVEMENTS
+        ),
+        params![company_id, item_id, exclude_invoice],
+        |row| row.get(0),
+    )
+    .map_err(AppError::from)
+}
+
+// Warnings for items an issued invoice would take below zero; an error instead when the
+// company blocks negative stock. Drafts move no stock and are not checked.
+pub fn check_invoice_stock(
+    conn: &Connection,
+    invoice: &Invoice,
+    lines: &[InvoiceLineInput],
+) -> Result<Vec<String>, AppError> {
+    if invoice.status != InvoiceStatus::Issued {
+        return Ok(Vec::new());
+    }
+    let mut needed: BTreeMap<i64, f64> = BTreeMap::new();
+    for line in lines {
+        if let Some(item_id) = line.item_id {
+            *needed.entry(item_id).or_default() += line.quantity;
+        }
+    }
+    let mut shortages = Vec::new();
+    for (item_id, quantity) in needed {
+        let available = on_hand(conn, invoice.company_id, item_id, invoice.id)?;
+        if available - quantity < -QUANTITY_TOLERANCE {
+            let name = items::get_item_by_id(conn, item_id, invoice.company_id)?
+                .map(|item| item.code)
+                .unwrap_or_else(|| item_id.to_string());
+            shortages.push(format!(
+                "Item {}: {} in stock but {} invoiced",
+                name,
+                round2(available),
+                round2(quantity)
+            ));
+        }
+    }
+    if !shortages.is_empty() && load_settings(conn, invoice.company_id)?.block_negative_stock {
+        return Err(AppError::validation(
+            "lines",
+            format!("Not enough stock. {}", shortages.join("; ")),
+        ));
+    }
+    Ok(shortages)
+}
+
+fn parse_optional_date(field: &str, value: &Option<String>) -> Result<Option<String>, AppError> {
+    match value.as_deref().map(str::trim).filter(|v| !v.is_empty()) {
+        Some(date) => NaiveDate::parse_from_str(date, INVOICE_DATE_FORMAT)
+            .map(|d| Some(d.format(INVOICE_DATE_FORMAT).to_string()))
+            .map_err(|_| AppError::validation(field, "Enter the date as YYYY-MM-DD")),
+        None => Ok(None),
+    }
+}
+
+fn get_adjustment_by_id(
+    conn: &Connection,
+    id: i64,
+    company_id: i64,
+) -> Result<Option<StockAdjustment>, AppError> {
+    conn.query_row(
+        &format!("{} WHERE id = ?1 AND company_id = ?2", SELECT_ADJUSTMENT),
+        params![id, company_id],
+        adjustment_from_row,
+    )
+    .optional()
+    .map_err(AppError::from)
+}
+
+// Movements in the range with running balances; without dates the whole history is listed
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn stock_ledger(
+    pool: State<'_, DbPool>,
+    company_id: i64,
+    item_id: i64,
+    from_date: Option<String>,
+    to_date: Option<String>,
+) -> Result<StockLedger, AppError> {
+    let from_date = parse_optional_date("from_date", &from_date)?;
+    let to_date = parse_optional_date("to_date", &to_date)?;
+    if let (Some(from), Some(to)) = (&from_date, &to_date) {
+        if from > to {
+            return Err(AppError::validation("to_date", "End date must not be before start date"));
+        }
+    }
+    let conn = db::get_conn(&pool)?;
+    let item = items::get_item_by_id(&conn, item_id, company_id)?
+        .ok_or_else(|| AppError::not_found("Item not found"))?;
+
+    let mut opening_quantity = 0.0;
+    let mut entries = Vec::new();
+    for movement in movements(&conn, company_id, item_id, None)? {
+        let date = movement.entry_date.as_str();
+        if from_date.as_deref().is_some_and(|from| date < from) {
+            opening_quantity += movement.quantity_in - movement.quantity_out;
+        } else if to_date.as_deref().is_none_or(|to| date <= to) {
+            entries.push(movement);
+        }
+    }
+    let mut balance = opening_quantity;
+    for entry in entries.iter_mut() {
+        balance += entry.quantity_in - entry.quantity_out;
+        entry.balance = balance;
+    }
+    let total_in: f64 = entries.iter().map(|e| e.quantity_in).sum();
+    let total_out: f64 = entries.iter().map(|e| e.quantity_out).sum();
+    Ok(StockLedger {
+        item,
+        from_date,
+        to_date,
+        opening_quantity,
+        entries,
+        total_in,
+        total_out,
+        closing_quantity: balance,
+    })
+}
+
+// Stock on hand of every active item
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn list_stock_levels(
+    pool: State<'_, DbPool>,
+    company_id: i64,
+) -> Result<Vec<StockLevel>, AppError> {
+    let conn = db::get_conn(&pool)?;
+    let mut stmt = conn.prepare(
+        "SELECT id, code, name, unit_id FROM items
+             WHERE company_id = ?1 AND active = 1 ORDER BY name",
+    )?;
+    let items = stmt
+        .query_map(params![company_id], |row| {
+            Ok(StockLevel {
+                item_id: row.get("id")?,
+                code: row.get("code")?,
+                name: row.get("name")?,
+                unit_id: row.get("unit_id")?,
+                quantity: 0.0,
+            })
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+    items
+        .into_iter()
+        .map(|level| {
+            let quantity = on_hand(&conn, company_id, level.item_id, None)?;
+            Ok(StockLevel { quantity, ..level })
+        })
+        .collect()
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn list_stock_adjustments(
+    pool: State<'_, DbPool>,
+    company_id: i64,
+    item_id: Option<i64>,
+) -> Result<Vec<StockAdjustment>, AppError> {
+    let conn = db::get_conn(&pool)?;
+    let mut stmt = conn.prepare(&format!(
+        "{} WHERE company_id = ?1 AND (?2 IS NULL OR item_id = ?2)
+             ORDER BY adjustment_date DESC, id DESC",
+        SELECT_ADJUSTMENT
+    ))?;
+    let adjustments = stmt
+        .query_map(params![company_id, item_id], adjustment_from_row)?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(adjustments)
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn create_stock_adjustment(
+    pool: State<'_, DbPool>,
+    company_id: i64,
+    adjustment: SaveStockAdjustment,
+) -> Result<StockAdjustment, AppError> {
+    let conn = db::get_conn(&pool)?;
+    create_stock_adjustment_in(&conn, company_id, &adjustment)
+}
+
+// Validates and records an adjustment on `conn`, so it can also run inside a caller's
+// transaction
+pub fn create_stock_adjustment_in(
+    conn: &Connection,
+    company_id: i64,
+    adjustment: &SaveStockAdjustment,
+) -> Result<StockAdjustment, AppError> {
+    let date = NaiveDate::parse_from_str(adjustment.adjustment_date.trim(), INVOICE_DATE_FORMAT)
+        .map_err(|_| AppError::validation("adjustment_date", "Enter the date as YYYY-MM-DD"))?
+        .format(INVOICE_DATE_FORMAT)
+        .to_string();
+    if !adjustment.quantity.is_finite() || adjustment.quantity.abs() < QUANTITY_TOLERANCE {
+        return Err(AppError::validation("quantity", "Quantity must be a non-zero number"));
+    }
+    if let Some(unit_cost) = adjustment.unit_cost {
+        if !unit_cost.is_finite() || unit_cost < 0.0 {
+            return Err(AppError::validation("unit_cost", "Cost must be a non-negative amount"));
+        }
+        if adjustment.quantity < 0.0 {
+            return Err(AppError::validation(
+                "unit_cost",
+                "Stock taken out is valued at its current cost; leave the cost empty",
+            ));
+        }
+    }
+    let reason = adjustment.reason.trim();
+    if reason.is_empty() || reason.chars().count() > MAX_REASON_LENGTH {
+        return Err(AppError::validation(
+            "reason",
+            format!("Reason must be 1 to {} characters", MAX_REASON_LENGTH),
+        ));
+    }
+    if items::get_item_by_id(conn, adjustment.item_id, company_id)?.is_none() {
+        return Err(AppError::not_found("Item not found"));
+    }
+    financial_years::ensure_period_open(conn, company_id, &date)?;
+    conn.execute(
+        "INSERT INTO stock_adjustments (company_id, item_id, adjustment_date, quantity,
+                                        unit_cost, reason)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+        params![
+            company_id,
+            adjustment.item_id,
+            date,
+            adjustment.quantity,
+            adjustment.unit_cost,
+            reason
+        ],
+    )?;
+    let created = get_adjustment_by_id(conn, conn.last_insert_rowid(), company_id)?
+        .ok_or_else(|| AppError::internal("Stock adjustment not found after creation"))?;
+    audit::record(
+        conn,
+        company_id,
+        "stock_adjustment",
+        created.id,
+        AuditAction::Create,
+        None,
+        Some(&created),
+    )?;
+    Ok(created)
+}
+
+// Adjustments are not edited; delete a wrong one and record it again
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn delete_stock_adjustment(
+    pool: State<'_, DbPool>,
+    id: i64,
+    company_id: i64,
+) -> Result<(), AppError> {
+    let conn = db::get_conn(&pool)?;
+    let existing = get_adjustment_by_id(&conn, id, company_id)?
+        .ok_or_else(|| AppError::not_found("Stock adjustment not found"))?;
+    financial_years::ensure_period_open(&conn, company_id, &existing.adjustment_date)?;
+    conn.execute(
+        "DELETE FROM stock_adjustments WHERE id = ?1 AND company_id = ?2",
+        params![id, company_id],
+    )?;
+    audit::record(
+        &conn,
+        company_id,
+        "stock_adjustment",
+        id,
+        AuditAction::Delete,
+        Some(&existing),
+        None,
+    )
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn get_stock_settings(
+    pool: State<'_, DbPool>,
+    company_id: i64,
+) -> Result<StockSettings, AppError> {
+    let conn = db::get_conn(&pool)?;
+    Ok(load_settings(&conn, company_id)?)
+}
+
+// Blocking applies to invoices issued from now on; a new valuation method revalues all stock
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn set_stock_settings(
+    pool: State<'_, DbPool>,
+    company_id: i64,
+    settings: StockSettings,
+) -> Result<StockSettings, AppError> {
+    let conn = db::get_conn(&pool)?;
+    settings::put_for_company(
+        &conn,
+        company_id,
+        SETTING_BLOCK_NEGATIVE_STOCK,
+        Some(&settings.block_negative_stock),
+    )?;
+    settings::put_for_company(
+        &conn,
+        company_id,
+        SETTING_VALUATION_METHOD,
+        Some(&settings.valuation_method),
+    )?;
+    Ok(load_settings(&conn, company_id)?)
+}
@@ -0,0 +1,263 @@
+use std::collections::VecDeque;
+
+use chrono::NaiveDate;
+use rusqlite::params;
+use serde::{Deserialize, Serialize};
+use tauri::State;
+
+use crate::db::{self, DbPool};
+use crate::error::AppError;
+use crate::invoices::{round2, INVOICE_DATE_FORMAT};
+use crate::settings::SettingValue;
+use crate::stock::{self, StockMovement};
+
+// Quantities closer to zero than this are treated as zero
+const QUANTITY_TOLERANCE: f64 = 0.0005;
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum ValuationMethod {
+    Fifo,
+    #[default]
+    WeightedAverage,
+}
+
+impl ValuationMethod {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ValuationMethod::Fifo => "fifo",
+            ValuationMethod::WeightedAverage => "weighted_average",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "fifo" => Some(ValuationMethod::Fifo),
+            "weighted_average" => Some(ValuationMethod::WeightedAverage),
+            _ => None,
+        }
+    }
+}
+
+impl SettingValue for ValuationMethod {
+    fn to_setting(&self) -> String {
+        self.as_str().to_string()
+    }
+
+    fn from_setting(value: &str) -> Option<Self> {
+        ValuationMethod::parse(value)
+    }
+}
+
+// Quantity and cost of stock after a run of movements
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default)]
+pub struct Valuation {
+    pub quantity: f64,
+    pub value: f64,
+}
+
+// Works through an item's movements in date order. Receipts without a cost of their own (sales
+// returns, adjustments entered without one) come in at the item's current cost. Issues beyond
+// the stock on hand leave a shortfall valued at the latest cost until later receipts cover it.
+struct Engine {
+    method: ValuationMethod,
+    // FIFO lots still on hand, oldest first, as (quantity, unit cost)
+    layers: VecDeque<(f64, f64)>,
+    shortfall: f64,
+    // Running totals for weighted average
+    quantity: f64,
+    value: f64,
+    last_cost: f64,
+}
+
+impl Engine {
+    fn new(method: ValuationMethod) -> Self {
+        Engine {
+            method,
+            layers: VecDeque::new(),
+            shortfall: 0.0,
+            quantity: 0.0,
+            value: 0.0,
+            last_cost: 0.0,
+        }
+    }
+
+    fn current_cost(&self) -> f64 {
+        match self.method {
+            ValuationMethod::WeightedAverage if self.quantity > QUANTITY_TOLERANCE => {
+                self.value / self.quantity
+            }
+            _ => self.last_cost,
+        }
+    }
+
+    fn receive(&mut self, quantity: f64, unit_cost: Option<f64>) {
+        let cost = unit_cost.unwrap_or_else(|| self.current_cost());
+        match self.method {
+            ValuationMethod::WeightedAverage => {
+                self.quantity += quantity;
+                self.value += quantity * cost;
+                if self.quantity.abs() < QUANTITY_TOLERANCE {
+                    self.value = 0.0;
+                }
+            }
+            ValuationMethod::Fifo => {
+                let covered = quantity.min(self.shortfall);
+                self.shortfall -= covered;
+                if quantity - covered > QUANTITY_TOLERANCE {
+                    self.layers.push_back((quantity - covered, cost));
+                }
+            }
+        }
+        self.last_cost = cost;
+    }
+
+    fn issue(&mut self, quantity: f64) {
+        match self.method {
+            ValuationMethod::WeightedAverage => {
+                let cost = self.current_cost();
+                self.quantity -= quantity;
+                self.value -= quantity * cost;
+                if self.quantity.abs() < QUANTITY_TOLERANCE {
+                    self.value = 0.0;
+                }
+            }
+            ValuationMethod::Fifo => {
+                let mut remaining = quantity;
+                while remaining > QUANTITY_TOLERANCE {
+                    let Some(front) = self.layers.front_mut() else {
+                        break;
+                    };
+                    let taken = remaining.min(front.0);
+                    front.0 -= taken;
+                    remaining -= taken;
+                    if front.0 <= QUANTITY_TOLERANCE {
+                        self.layers.pop_front();
+                    }
+                }
+                if remaining > QUANTITY_TOLERANCE {
+                    self.shortfall += remaining;
+                }
+            }
+        }
+    }
+
+    fn valuation(&self) -> Valuation {
+        match self.method {
+            ValuationMethod::WeightedAverage => Valuation {
+                quantity: self.quantity,
+                value: self.value,
+            },
+            ValuationMethod::Fifo => {
+                let on_hand: f64 = self.layers.iter().map(|(quantity, _)| quantity).sum();
+                let cost: f64 = self.layers.iter().map(|(quantity, cost)| quantity * cost).sum();
+                Valuation {
+                    quantity: on_hand - self.shortfall,
+                    value: cost - self.shortfall * self.last_cost,
+                }
+            }
+        }
+    }
+}
+
+// Value of the stock left after `movements`, which must be in date order
+pub fn value_movements(method: ValuationMethod, movements: &[StockMovement]) -> Valuation {
+    let mut engine = Engine::new(method);
+    for movement in movements {
+        if movement.quantity_in > 0.0 {
+            engine.receive(movement.quantity_in, movement.unit_cost);
+        }
+        if movement.quantity_out > 0.0 {
+            engine.issue(movement.quantity_out);
+        }
+    }
+    let valuation = engine.valuation();
+    Valuation {
+        quantity: valuation.quantity,
+        value: round2(valuation.value),
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ClosingStockRow {
+    pub item_id: i64,
+    pub code: String,
+    pub name: String,
+    pub hsn_code: String,
+    pub unit_id: Option<i64>,
+    pub quantity: f64,
+    // Value divided by quantity
+    pub unit_cost: f64,
+    pub value: f64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ClosingStockReport {
+    pub as_of: String,
+    pub method: ValuationMethod,
+    pub rows: Vec<ClosingStockRow>,
+    pub total_value: f64,
+}
+
+// Quantity and value of every item with stock (or a shortfall) at the end of `as_of`, valued by
+// the company's configured method; the closing stock figure for the year-end accounts
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn closing_stock_report(
+    pool: State<'_, DbPool>,
+    company_id: i64,
+    as_of: String,
+) -> Result<ClosingStockReport, AppError> {
+    let as_of = NaiveDate::parse_from_str(as_of.trim(), INVOICE_DATE_FORMAT)
+        .map_err(|_| AppError::validation("as_of", "Enter the date as YYYY-MM-DD"))?
+        .format(INVOICE_DATE_FORMAT)
+        .to_string();
+    let conn = db::get_conn(&pool)?;
+    let method = stock::load_settings(&conn, company_id)?.valuation_method;
+
+    let mut stmt = conn.prepare(
+        "SELECT id, code, name, hsn_code, unit_id FROM items
+             WHERE company_id = ?1 ORDER BY name",
+    )?;
+    let items = stmt
+        .query_map(params![company_id], |row| {
+            Ok(ClosingStockRow {
+                item_id: row.get("id")?,
+                code: row.get("code")?,
+                name: row.get("name")?,
+                hsn_code: row.get("hsn_code")?,
+                unit_id: row.get("unit_id")?,
+                quantity: 0.0,
+                unit_cost: 0.0,
+                value: 0.0,
+            })
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let mut rows = Vec::new();
+    for item in items {
+        let movements: Vec<StockMovement> =
+            stock::movements(&conn, company_id, item.item_id, None)?
+                .into_iter()
+                .filter(|movement| movement.entry_date <= as_of)
+                .collect();
+        let valuation = value_movements(method, &movements);
+        if valuation.quantity.abs() < QUANTITY_TOLERANCE {
+            continue;
+        }
+        let unit_cost = round2(valuation.value / valuation.quantity);
+        rows.push(ClosingStockRow {
+            quantity: valuation.quantity,
+            unit_cost,
+            value: valuation.value,
+            ..item
+        });
+    }
+    let total_value = round2(rows.iter().map(|row| row.value).sum());
+    Ok(ClosingStockReport {
+        as_of,
+        method,
+        rows,
+        total_value,
+    })
+}
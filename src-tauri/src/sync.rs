@@ -0,0 +1,963 @@
+use std::fs;
+
+use rusqlite::types::Value as SqlValue;
+use rusqlite::{params, params_from_iter, Connection, OptionalExtension, Row};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tauri::State;
+
+use crate::db::{self, DbPool};
+use crate::error::AppError;
+use crate::migrations;
+use crate::transactions;
+
+const CHANGE_SET_FORMAT: &str = "sales-report-sync";
+const CHANGE_SET_VERSION: u32 = 2;
+// Business data shared between devices; users, settings, logs and the like stay per device
+const SYNCED_TABLES: &[&str] = &[
+    "companies",
+    "categories",
+    "customers",
+    "units",
+    "items",
+    "batches",
+    "price_lists",
+    "price_list_items",
+    "price_list_assignments",
+    "financial_years",
+    "invoices",
+    "invoice_lines",
+    "credit_debit_notes",
+    "credit_debit_note_lines",
+    "receipts",
+    "receipt_allocations",
+    "quotations",
+    "quotation_lines",
+    "sales_orders",
+    "sales_order_lines",
+    "delivery_challans",
+    "delivery_challan_lines",
+    "stock_adjustments",
+    "purchases",
+];
+// Rows that existed before rows carried a uuid get `legacy-<device id>-<row id>`. Local ids of
+// different devices overlap, so the device id keeps unrelated rows from sharing a uuid; rows a
+// device received by id before therefore get a uuid of their own on each device.
+const LEGACY_UUID_PREFIX: &str = "legacy-";
+// json_object and json_set take at most 127 arguments, so wide rows are built in chunks
+const JSON_PAIRS_PER_CALL: usize = 50;
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ChangeOperation {
+    Upsert,
+    Delete,
+}
+
+impl ChangeOperation {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ChangeOperation::Upsert => "upsert",
+            ChangeOperation::Delete => "delete",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "upsert" => Some(ChangeOperation::Upsert),
+            "delete" => Some(ChangeOperation::Delete),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ConflictResolution {
+    KeepLocal,
+    TakeRemote,
+}
+
+impl ConflictResolution {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ConflictResolution::KeepLocal => "keep_local",
+            ConflictResolution::TakeRemote => "take_remote",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "keep_local" => Some(ConflictResolution::KeepLocal),
+            "take_remote" => Some(ConflictResolution::TakeRemote),
+            _ => None,
+        }
+    }
+}
+
+// One row-level change; `data` is the whole row after an upsert and absent for a delete. Rows
+// are identified by their sync_uuid, and references to synced rows in `data` by the uuid of
+// the row referred to, since local ids differ between devices.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SyncChange {
+    pub table_name: String,
+    pub row_uuid: String,
+    pub operation: ChangeOperation,
+    pub data: Option<Value>,
+    pub lamport: i64,
+    pub device_id: String,
+}
+
+// Contents of an exported change-set file
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ChangeSet {
+    pub format: String,
+    pub version: u32,
+    pub device_id: String,
+    // Sender's Lamport clock when the file was written
+    pub clock: i64,
+    pub exported_at: String,
+    pub changes: Vec<SyncChange>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SyncPeer {
+    pub device_id: String,
+    // Highest of the peer's Lamport timestamps applied here
+    pub last_lamport: i64,
+    pub last_imported_at: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SyncStatus {
+    pub device_id: String,
+    pub clock: i64,
+    pub local_changes: i64,
+    pub open_conflicts: i64,
+    pub peers: Vec<SyncPeer>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SyncExportResult {
+    pub path: String,
+    pub change_count: usize,
+    // Pass as `since` next time to export only what changed after this file
+    pub clock: i64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SyncImportReport {
+    pub device_id: String,
+    pub applied: usize,
+    // Already imported earlier, or identical to the local row
+    pub skipped: usize,
+    pub conflicts: usize,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SyncConflict {
+    pub id: i64,
+    pub table_name: String,
+    pub row_uuid: String,
+    pub remote_device_id: String,
+    pub remote_lamport: i64,
+    pub remote_operation: ChangeOperation,
+    pub remote_data: Option<Value>,
+    // None when the row no longer exists here
+    pub local_data: Option<Value>,
+    // Why the change was not applied: a concurrent local edit or the database error
+    pub reason: String,
+    pub resolved: bool,
+    pub resolution: Option<ConflictResolution>,
+    pub created_at: Option<String>,
+    pub resolved_at: Option<String>,
+}
+
+const CREATE_LOG_BY_UUID: &str = "
+    CREATE TABLE sync_changes (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        table_name TEXT NOT NULL,
+        row_uuid TEXT NOT NULL,
+        operation TEXT NOT NULL CHECK(operation IN ('upsert', 'delete')),
+        row_data TEXT,
+        lamport INTEGER NOT NULL,
+        device_id TEXT NOT NULL,
+        created_at DATETIME DEFAULT CURRENT_TIMESTAMP
+    );
+    CREATE INDEX idx_sync_changes_row ON sync_changes (table_name, row_uuid, device_id, lamport);
+    CREATE INDEX idx_sync_changes_device ON sync_changes (device_id, lamport);
+    CREATE TABLE sync_conflicts (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        table_name TEXT NOT NULL,
+        row_uuid TEXT NOT NULL,
+        remote_device_id TEXT NOT NULL,
+        remote_lamport INTEGER NOT NULL,
+        remote_operation TEXT NOT NULL,
+        remote_data TEXT,
+        local_data TEXT,
+        reason TEXT NOT NULL,
+        status TEXT NOT NULL DEFAULT 'open' CHECK(status IN ('open', 'resolved')),
+        resolution TEXT,
+        created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+        resolved_at DATETIME
+    );";
+
+const SELECT_CONFLICT: &str = "
+    SELECT id, table_name, row_uuid, remote_device_id, remote_lamport, remote_operation,
+           remote_data, local_data, reason, status, resolution, created_at, resolved_at
+    FROM sync_conflicts";
+
+fn json_column(row: &Row, column: &str) -> rusqlite::Result<Option<Value>> {
+    let text: Option<String> = row.get(column)?;
+    Ok(text.and_then(|text| serde_json::from_str(&text).ok()))
+}
+
+fn conflict_from_row(row: &Row) -> rusqlite::Result<SyncConflict> {
+    let operation: String = row.get("remote_operation")?;
+    let status: String = row.get("status")?;
+    let resolution: Option<String> = row.get("resolution")?;
+    Ok(SyncConflict {
+        id: row.get("id")?,
+        table_name: row.get("table_name")?,
+        row_uuid: row.get("row_uuid")?,
+        remote_device_id: row.get("remote_device_id")?,
+        remote_lamport: row.get("remote_lamport")?,
+        remote_operation: ChangeOperation::parse(&operation).unwrap_or(ChangeOperation::Upsert),
+        remote_data: json_column(row, "remote_data")?,
+        local_data: json_column(row, "local_data")?,
+        reason: row.get("reason")?,
+        resolved: status == "resolved",
+        resolution: resolution.as_deref().and_then(ConflictResolution::parse),
+        created_at: row.get("created_at")?,
+        resolved_at: row.get("resolved_at")?,
+    })
+}
+
+fn table_columns(conn: &Connection, table: &str) -> Result<Vec<String>, String> {
+    let mut stmt = conn
+        .prepare(&format!("PRAGMA table_info(\"{}\")", table))
+        .map_err(|e| e.to_string())?;
+    let columns = stmt
+        .query_map([], |row| row.get::<_, String>("name"))
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+    Ok(columns)
+}
+
+// A synced table's columns other than id, and which of them refer to rows of synced tables
+struct TableShape {
+    columns: Vec<String>,
+    // (column, table referred to)
+    references: Vec<(String, String)>,
+}
+
+impl TableShape {
+    fn parent(&self, column: &str) -> Option<&str> {
+        self.references
+            .iter()
+            .find(|(from, _)| from == column)
+            .map(|(_, table)| table.as_str())
+    }
+}
+
+fn table_shape(conn: &Connection, table: &str) -> Result<TableShape, String> {
+    let columns = table_columns(conn, table)?
+        .into_iter()
+        .filter(|column| column != "id")
+        .collect();
+    let mut stmt = conn
+        .prepare(&format!("PRAGMA foreign_key_list(\"{}\")", table))
+        .map_err(|e| e.to_string())?;
+    let references = stmt
+        .query_map([], |row| {
+            Ok((
+                row.get::<_, String>("from")?,
+                row.get::<_, String>("table")?,
+                row.get::<_, Option<String>>("to")?,
+            ))
+        })
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?
+        .into_iter()
+        .filter(|(_, parent, to)| is_synced(parent) && to.as_deref().unwrap_or("id") == "id")
+        .map(|(from, parent, _)| (from, parent))
+        .collect();
+    Ok(TableShape {
+        columns,
+        references,
+    })
+}
+
+// SQL expression building a JSON object of the row's columns, read through `prefix` (NEW. in a
+// trigger, a table alias in a plain SELECT). References carry the uuid of the row referred to.
+fn row_json_expr(shape: &TableShape, prefix: &str) -> String {
+    let value = |column: &String| match shape.parent(column) {
+        Some(parent) => format!(
+            "(SELECT referred.sync_uuid FROM \"{}\" AS referred WHERE referred.id = {}\"{}\")",
+            parent, prefix, column
+        ),
+        None => format!("{}\"{}\"", prefix, column),
+    };
+    let mut chunks = shape.columns.chunks(JSON_PAIRS_PER_CALL);
+    let pair = |column: &String| format!("'{}', {}", column, value(column));
+    let mut expr = format!(
+        "json_object({})",
+        chunks.next().unwrap_or(&[]).iter().map(pair).collect::<Vec<_>>().join(", ")
+    );
+    for chunk in chunks {
+        let paths = chunk
+            .iter()
+            .map(|column| format!("'$.\"{}\"', {}", column, value(column)))
+            .collect::<Vec<_>>()
+            .join(", ");
+        expr = format!("json_set({}, {})", expr, paths);
+    }
+    expr
+}
+
+fn is_synced(table: &str) -> bool {
+    SYNCED_TABLES.contains(&table)
+}
+
+fn table_exists(conn: &Connection, table: &str) -> Result<bool, String> {
+    conn.query_row(
+        "SELECT COUNT(*) FROM sqlite_master WHERE type = 'table' AND name = ?1",
+        params![table],
+        |row| row.get::<_, i64>(0),
+    )
+    .map(|count| count > 0)
+    .map_err(|e| e.to_string())
+}
+
+// Migrations drop the change triggers before altering tables and reinstall them afterwards, so
+// the triggers always list the current columns
+pub fn drop_triggers(conn: &Connection) -> Result<(), String> {
+    let mut stmt = conn
+        .prepare(
+            "SELECT name FROM sqlite_master
+             WHERE type = 'trigger' AND name LIKE 'sync\\_%' ESCAPE '\\'",
+        )
+        .map_err(|e| e.to_string())?;
+    let triggers = stmt
+        .query_map([], |row| row.get::<_, String>(0))
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+    for trigger in triggers {
+        conn.execute_batch(&format!("DROP TRIGGER IF EXISTS \"{}\";", trigger))
+            .map_err(|e| e.to_string())?;
+    }
+    Ok(())
+}
+
+pub fn install_triggers(conn: &Connection) -> Result<(), String> {
+    // Also missing after rolling back past the migration that keyed the log on row uuids
+    if !table_exists(conn, "sync_state")?
+        || !migrations::column_exists(conn, "sync_changes", "row_uuid")?
+    {
+        return Ok(());
+    }
+    for table in SYNCED_TABLES {
+        let shape = table_shape(conn, table)?;
+        if !shape.columns.iter().any(|column| column == "sync_uuid") {
+            continue;
+        }
+        let record = |operation: ChangeOperation, row: &str, data: &str, join: &str| {
+            format!(
+                "UPDATE sync_state SET clock = clock + 1 WHERE id = 1;
+                 INSERT INTO sync_changes (table_name, row_uuid, operation, row_data, lamport,
+                                           device_id)
+                 SELECT '{}', {}.sync_uuid, '{}', {}, state.clock, state.device_id
+                 FROM sync_state AS state{} WHERE state.id = 1;",
+                table,
+                row,
+                operation.as_str(),
+                data,
+                join
+            )
+        };
+        // New rows get their uuid here, then are logged as stored
+        let insert = format!(
+            "UPDATE \"{0}\" SET sync_uuid = lower(hex(randomblob(16)))
+             WHERE id = NEW.id AND sync_uuid IS NULL;
+             {1}",
+            table,
+            record(
+                ChangeOperation::Upsert,
+                "stored",
+                &row_json_expr(&shape, "stored."),
+                &format!(" JOIN \"{}\" AS stored ON stored.id = NEW.id", table)
+            )
+        );
+        let update = record(
+            ChangeOperation::Upsert,
+            "NEW",
+            &row_json_expr(&shape, "NEW."),
+            "",
+        );
+        let delete = record(ChangeOperation::Delete, "OLD", "NULL", "");
+        // Filling in the uuid of a new row is not a change of its own
+        for (event, condition, body) in [
+            ("insert", "", insert),
+            ("update", " AND OLD.sync_uuid IS NOT NULL", update),
+            ("delete", " AND OLD.sync_uuid IS NOT NULL", delete),
+        ] {
+            conn.execute_batch(&format!(
+                "CREATE TRIGGER IF NOT EXISTS sync_{0}_{1} AFTER {2} ON \"{0}\"
+                 WHEN (SELECT applying FROM sync_state WHERE id = 1) = 0{3}
+                 BEGIN {4} END;",
+                table,
+                event,
+                event.to_uppercase(),
+                condition,
+                body
+            ))
+            .map_err(|e| format!("Failed to install sync trigger on {}: {}", table, e))?;
+        }
+    }
+    Ok(())
+}
+
+// The row_data of a change logged by id, as it reads when logged by uuid
+fn legacy_row_json(data: &str, shape: &TableShape, prefix: &str) -> String {
+    let mut expr = format!(
+        "json_set(json_remove({0}, '$.id'), '$.sync_uuid', '{1}' || row_id)",
+        data, prefix
+    );
+    for (column, _) in &shape.references {
+        expr = format!(
+            "json_set({0}, '$.\"{1}\"', '{2}' || json_extract({3}, '$.\"{1}\"'))",
+            expr, column, prefix, data
+        );
+    }
+    expr
+}
+
+// Migration 55: local ids collide between devices that each insert rows, so every synced row
+// gets a uuid shared by all devices and the change log is keyed on it
+pub fn add_row_uuids(conn: &Connection) -> Result<(), String> {
+    // The device id is hex, so it is safe inside the SQL below
+    let (device_id, _) = local_state(conn)?;
+    let prefix = format!("{}{}-", LEGACY_UUID_PREFIX, device_id);
+    for table in SYNCED_TABLES {
+        if !migrations::column_exists(conn, table, "id")? {
+            continue;
+        }
+        conn.execute_batch(&format!(
+            "ALTER TABLE \"{0}\" ADD COLUMN sync_uuid TEXT;
+             UPDATE \"{0}\" SET sync_uuid = '{1}' || id;
+             CREATE UNIQUE INDEX IF NOT EXISTS idx_{0}_sync_uuid ON \"{0}\" (sync_uuid);",
+            table, prefix
+        ))
+        .map_err(|e| e.to_string())?;
+    }
+
+    conn.execute_batch(&format!(
+        "ALTER TABLE sync_changes RENAME TO sync_changes_by_id;
+         ALTER TABLE sync_conflicts RENAME TO sync_conflicts_by_id;
+         DROP INDEX IF EXISTS idx_sync_changes_row;
+         DROP INDEX IF EXISTS idx_sync_changes_device;
+         {}",
+        CREATE_LOG_BY_UUID
+    ))
+    .map_err(|e| e.to_string())?;
+    // Logged changes and open conflicts are kept, rewritten as they would have been logged
+    for table in SYNCED_TABLES {
+        let shape = table_shape(conn, table)?;
+        conn.execute_batch(&format!(
+            "INSERT INTO sync_changes (id, table_name, row_uuid, operation, row_data, lamport,
+                                       device_id, created_at)
+             SELECT id, table_name, '{0}' || row_id, operation, {1}, lamport, device_id,
+                    created_at
+             FROM sync_changes_by_id WHERE table_name = '{2}';
+             INSERT INTO sync_conflicts (id, table_name, row_uuid, remote_device_id,
+                                         remote_lamport, remote_operation, remote_data,
+                                         local_data, reason, status, resolution, created_at,
+                                         resolved_at)
+             SELECT id, table_name, '{0}' || row_id, remote_device_id, remote_lamport,
+                    remote_operation, {3}, {4}, reason, status, resolution, created_at,
+                    resolved_at
+             FROM sync_conflicts_by_id WHERE table_name = '{2}';",
+            prefix,
+            legacy_row_json("row_data", &shape, &prefix),
+            table,
+            legacy_row_json("remote_data", &shape, &prefix),
+            legacy_row_json("local_data", &shape, &prefix)
+        ))
+        .map_err(|e| e.to_string())?;
+    }
+    conn.execute_batch(
+        "DROP TABLE sync_changes_by_id;
+         DROP TABLE sync_conflicts_by_id;",
+    )
+    .map_err(|e| e.to_string())
+}
+
+// Entries keyed by uuid have no local id to go back to, so rolling back starts the log afresh
+pub fn remove_row_uuids(conn: &Connection) -> Result<(), String> {
+    for table in SYNCED_TABLES {
+        if !migrations::column_exists(conn, table, "sync_uuid")? {
+            continue;
+        }
+        conn.execute_batch(&format!(
+            "DROP INDEX IF EXISTS idx_{0}_sync_uuid;
+             ALTER TABLE \"{0}\" DROP COLUMN sync_uuid;",
+            table
+        ))
+        .map_err(|e| e.to_string())?;
+    }
+    conn.execute_batch(
+        "DROP TABLE IF EXISTS sync_conflicts;
+         DROP INDEX IF EXISTS idx_sync_changes_device;
+         DROP INDEX IF EXISTS idx_sync_changes_row;
+         DROP TABLE IF EXISTS sync_changes;
+         CREATE TABLE sync_changes (
+             id INTEGER PRIMARY KEY AUTOINCREMENT,
+             table_name TEXT NOT NULL,
+             row_id INTEGER NOT NULL,
+             operation TEXT NOT NULL CHECK(operation IN ('upsert', 'delete')),
+             row_data TEXT,
+             lamport INTEGER NOT NULL,
+             device_id TEXT NOT NULL,
+             created_at DATETIME DEFAULT CURRENT_TIMESTAMP
+         );
+         CREATE INDEX idx_sync_changes_row ON sync_changes (table_name, row_id, device_id, lamport);
+         CREATE INDEX idx_sync_changes_device ON sync_changes (device_id, lamport);
+         CREATE TABLE sync_conflicts (
+             id INTEGER PRIMARY KEY AUTOINCREMENT,
+             table_name TEXT NOT NULL,
+             row_id INTEGER NOT NULL,
+             remote_device_id TEXT NOT NULL,
+             remote_lamport INTEGER NOT NULL,
+             remote_operation TEXT NOT NULL,
+             remote_data TEXT,
+             local_data TEXT,
+             reason TEXT NOT NULL,
+             status TEXT NOT NULL DEFAULT 'open' CHECK(status IN ('open', 'resolved')),
+             resolution TEXT,
+             created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+             resolved_at DATETIME
+         );",
+    )
+    .map_err(|e| e.to_string())
+}
+
+fn local_state(conn: &Connection) -> Result<(String, i64), String> {
+    conn.query_row("SELECT device_id, clock FROM sync_state WHERE id = 1", [], |row| {
+        Ok((row.get(0)?, row.get(1)?))
+    })
+    .map_err(|e| e.to_string())
+}
+
+// Stops the triggers recording changes that came from another device
+fn set_applying(conn: &Connection, applying: bool) -> Result<(), String> {
+    conn.execute("UPDATE sync_state SET applying = ?1 WHERE id = 1", params![applying])
+        .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+fn current_row(conn: &Connection, table: &str, row_uuid: &str) -> Result<Option<Value>, String> {
+    let shape = table_shape(conn, table)?;
+    let text: Option<String> = conn
+        .query_row(
+            &format!(
+                "SELECT {} FROM \"{}\" AS stored WHERE stored.sync_uuid = ?1",
+                row_json_expr(&shape, "stored."),
+                table
+            ),
+            params![row_uuid],
+            |row| row.get(0),
+        )
+        .optional()
+        .map_err(|e| e.to_string())?;
+    Ok(text.and_then(|text| serde_json::from_str(&text).ok()))
+}
+
+fn sql_value(value: &Value) -> SqlValue {
+    match value {
+        Value::Null => SqlValue::Null,
+        Value::Bool(flag) => SqlValue::Integer(i64::from(*flag)),
+        Value::Number(number) => match number.as_i64() {
+            Some(integer) => SqlValue::Integer(integer),
+            None => SqlValue::Real(number.as_f64().unwrap_or_default()),
+        },
+        Value::String(text) => SqlValue::Text(text.clone()),
+        other => SqlValue::Text(other.to_string()),
+    }
+}
+
+// Writes the change to the table; columns the local schema lacks are ignored. The row keeps
+// whatever local id it has here, and references are turned back into local ids.
+fn apply_change(conn: &Connection, change: &SyncChange) -> Result<(), String> {
+    let table = change.table_name.as_str();
+    match (change.operation, &change.data) {
+        (ChangeOperation::Delete, _) => {
+            conn.execute(
+                &format!("DELETE FROM \"{}\" WHERE sync_uuid = ?1", table),
+                params![change.row_uuid],
+            )
+            .map_err(|e| e.to_string())?;
+        }
+        (ChangeOperation::Upsert, Some(Value::Object(data))) => {
+            let shape = table_shape(conn, table)?;
+            let columns: Vec<String> = shape
+                .columns
+                .iter()
+                .filter(|column| *column != "sync_uuid" && data.contains_key(*column))
+                .cloned()
+                .collect();
+            let mut values: Vec<SqlValue> =
+                columns.iter().map(|column| sql_value(&data[column])).collect();
+            values.push(SqlValue::Text(change.row_uuid.clone()));
+            let names: Vec<String> = columns
+                .iter()
+                .map(|column| format!("\"{}\", ", column))
+                .collect();
+            let placeholders: Vec<String> = columns
+                .iter()
+                .enumerate()
+                .map(|(n, column)| match shape.parent(column) {
+                    Some(parent) => format!(
+                        "(SELECT id FROM \"{}\" WHERE sync_uuid = ?{}), ",
+                        parent,
+                        n + 1
+                    ),
+                    None => format!("?{}, ", n + 1),
+                })
+                .collect();
+            let assignments = columns
+                .iter()
+                .map(|column| format!("\"{0}\" = excluded.\"{0}\"", column))
+                .collect::<Vec<_>>()
+                .join(", ");
+            let on_conflict = if columns.is_empty() {
+                "DO NOTHING".to_string()
+            } else {
+                format!("DO UPDATE SET {}", assignments)
+            };
+            conn.execute(
+                &format!(
+                    "INSERT INTO \"{}\" ({}sync_uuid) VALUES ({}?{}) ON CONFLICT(sync_uuid) {}",
+                    table,
+                    names.concat(),
+                    placeholders.concat(),
+                    columns.len() + 1,
+                    on_conflict
+                ),
+                params_from_iter(values),
+            )
+            .map_err(|e| e.to_string())?;
+        }
+        (ChangeOperation::Upsert, _) => {
+            return Err("Change has no row data".to_string());
+        }
+    }
+    Ok(())
+}
+
+fn record_conflict(
+    conn: &Connection,
+    change: &SyncChange,
+    local_data: Option<&Value>,
+    reason: &str,
+) -> Result<(), String> {
+    conn.execute(
+        "INSERT INTO sync_conflicts (table_name, row_uuid, remote_device_id, remote_lamport,
+                                     remote_operation, remote_data, local_data, reason)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+        params![
+            change.table_name,
+            change.row_uuid,
+            change.device_id,
+            change.lamport,
+            change.operation.as_str(),
+            change.data.as_ref().map(Value::to_string),
+            local_data.map(Value::to_string),
+            reason
+        ],
+    )
+    .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+// Appends a local change carrying the row as it is now, so the next export sends it out again
+fn rerecord_row(conn: &Connection, table: &str, row_uuid: &str) -> Result<(), String> {
+    let data = current_row(conn, table, row_uuid)?;
+    let operation = if data.is_some() {
+        ChangeOperation::Upsert
+    } else {
+        ChangeOperation::Delete
+    };
+    conn.execute("UPDATE sync_state SET clock = clock + 1 WHERE id = 1", [])
+        .map_err(|e| e.to_string())?;
+    conn.execute(
+        "INSERT INTO sync_changes (table_name, row_uuid, operation, row_data, lamport, device_id)
+         SELECT ?1, ?2, ?3, ?4, clock, device_id FROM sync_state WHERE id = 1",
+        params![table, row_uuid, operation.as_str(), data.as_ref().map(Value::to_string)],
+    )
+    .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+fn get_conflict_by_id(conn: &Connection, id: i64) -> Result<Option<SyncConflict>, String> {
+    conn.query_row(
+        &format!("{} WHERE id = ?1", SELECT_CONFLICT),
+        params![id],
+        conflict_from_row,
+    )
+    .optional()
+    .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn get_sync_status(pool: State<'_, DbPool>) -> Result<SyncStatus, AppError> {
+    let conn = db::get_conn(&pool)?;
+    let (device_id, clock) = local_state(&conn)?;
+    let local_changes: i64 = conn.query_row(
+        "SELECT COUNT(*) FROM sync_changes WHERE device_id = ?1",
+        params![device_id],
+        |row| row.get(0),
+    )?;
+    let open_conflicts: i64 = conn.query_row(
+        "SELECT COUNT(*) FROM sync_conflicts WHERE status = 'open'",
+        [],
+        |row| row.get(0),
+    )?;
+    let mut stmt = conn.prepare(
+        "SELECT device_id, last_lamport, last_imported_at FROM sync_peers
+             ORDER BY last_imported_at DESC",
+    )?;
+    let peers = stmt
+        .query_map([], |row| {
+            Ok(SyncPeer {
+                device_id: row.get("device_id")?,
+                last_lamport: row.get("last_lamport")?,
+                last_imported_at: row.get("last_imported_at")?,
+            })
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(SyncStatus {
+        device_id,
+        clock,
+        local_changes,
+        open_conflicts,
+        peers,
+    })
+}
+
+// Writes the changes made on this device after Lamport time `since` (everything when not
+// given) to `path`. Importing the same change twice is harmless, so overlapping files are fine.
+// Changes received from other devices are not passed on: with three or more devices, each one
+// has to import the files of every other device, not just those of a device in between.
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn export_sync_changes(
+    pool: State<'_, DbPool>,
+    path: String,
+    since: Option<i64>,
+) -> Result<SyncExportResult, AppError> {
+    let conn = db::get_conn(&pool)?;
+    let (device_id, clock) = local_state(&conn)?;
+    let mut stmt = conn.prepare(
+        "SELECT table_name, row_uuid, operation, row_data, lamport, device_id
+             FROM sync_changes WHERE device_id = ?1 AND lamport > ?2 ORDER BY lamport",
+    )?;
+    let changes = stmt
+        .query_map(params![device_id, since.unwrap_or(0)], |row| {
+            let operation: String = row.get("operation")?;
+            Ok(SyncChange {
+                table_name: row.get("table_name")?,
+                row_uuid: row.get("row_uuid")?,
+                operation: ChangeOperation::parse(&operation).unwrap_or(ChangeOperation::Upsert),
+                data: json_column(row, "row_data")?,
+                lamport: row.get("lamport")?,
+                device_id: row.get("device_id")?,
+            })
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let change_set = ChangeSet {
+        format: CHANGE_SET_FORMAT.to_string(),
+        version: CHANGE_SET_VERSION,
+        device_id,
+        clock,
+        exported_at: chrono::Local::now().format("%Y-%m-%d %H:%M:%S").to_string(),
+        changes,
+    };
+    let json = serde_json::to_string(&change_set).map_err(|e| e.to_string())?;
+    fs::write(&path, json).map_err(|e| format!("Failed to write {}: {}", path, e))?;
+    Ok(SyncExportResult {
+        path,
+        change_count: change_set.changes.len(),
+        clock,
+    })
+}
+
+// Applies another device's change-set file. A change is held back as a conflict when the same
+// row was also changed here since the last import from that device and the two versions
+// differ, or when the database rejects it (e.g. a duplicate invoice number); everything else is
+// applied in Lamport order. The local clock moves past the sender's, as Lamport clocks require.
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn import_sync_changes(
+    pool: State<'_, DbPool>,
+    path: String,
+) -> Result<SyncImportReport, AppError> {
+    let text = fs::read_to_string(&path).map_err(|e| format!("Failed to read {}: {}", path, e))?;
+    let mut change_set: ChangeSet = serde_json::from_str(&text)
+        .map_err(|_| AppError::validation("path", "This is not a sync change-set file"))?;
+    if change_set.format != CHANGE_SET_FORMAT || change_set.version != CHANGE_SET_VERSION {
+        return Err(AppError::validation(
+            "path",
+            "This change-set file was written by an unsupported version",
+        ));
+    }
+
+    let mut conn = db::get_conn(&pool)?;
+    let tx = conn.transaction()?;
+    let (device_id, _) = local_state(&tx)?;
+    if change_set.device_id == device_id {
+        return Err(AppError::validation("path", "This file was exported from this device"));
+    }
+    let (last_lamport, local_clock_at_import): (i64, i64) = tx
+        .query_row(
+            "SELECT last_lamport, local_clock FROM sync_peers WHERE device_id = ?1",
+            params![change_set.device_id],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+        .optional()?
+        .unwrap_or((0, 0));
+
+    change_set.changes.sort_by_key(|change| change.lamport);
+    let mut report = SyncImportReport {
+        device_id: change_set.device_id.clone(),
+        applied: 0,
+        skipped: 0,
+        conflicts: 0,
+    };
+    let mut highest = last_lamport;
+    set_applying(&tx, true)?;
+    for change in &change_set.changes {
+        if change.lamport <= last_lamport {
+            report.skipped += 1;
+            continue;
+        }
+        highest = highest.max(change.lamport);
+        if !is_synced(&change.table_name) || !table_exists(&tx, &change.table_name)? {
+            report.skipped += 1;
+            continue;
+        }
+
+        let local = current_row(&tx, &change.table_name, &change.row_uuid)?;
+        let same = match change.operation {
+            ChangeOperation::Delete => local.is_none(),
+            ChangeOperation::Upsert => local.is_some() && local == change.data,
+        };
+        if same {
+            report.skipped += 1;
+            continue;
+        }
+        let local_edit: Option<i64> = tx.query_row(
+            "SELECT MAX(lamport) FROM sync_changes
+                 WHERE table_name = ?1 AND row_uuid = ?2 AND device_id = ?3",
+            params![change.table_name, change.row_uuid, device_id],
+            |row| row.get(0),
+        )?;
+        if local_edit.is_some_and(|lamport| lamport > local_clock_at_import) {
+            record_conflict(&tx, change, local.as_ref(), "Changed on both devices")?;
+            report.conflicts += 1;
+            continue;
+        }
+        // A failing change is undone and recorded without affecting the rest of the import
+        match transactions::with_savepoint(&tx, "sync_change", || apply_change(&tx, change)) {
+            Ok(()) => report.applied += 1,
+            Err(e) => {
+                record_conflict(&tx, change, local.as_ref(), &e)?;
+                report.conflicts += 1;
+            }
+        }
+    }
+    set_applying(&tx, false)?;
+
+    tx.execute(
+        "UPDATE sync_state SET clock = MAX(clock, ?1) + 1 WHERE id = 1",
+        params![change_set.clock],
+    )?;
+    tx.execute(
+        "INSERT INTO sync_peers (device_id, last_lamport, local_clock, last_imported_at)
+         SELECT ?1, ?2, clock, CURRENT_TIMESTAMP FROM sync_state WHERE id = 1
+         ON CONFLICT(device_id) DO UPDATE SET
+            last_lamport = excluded.last_lamport, local_clock = excluded.local_clock,
+            last_imported_at = excluded.last_imported_at",
+        params![change_set.device_id, highest],
+    )?;
+    tx.commit()?;
+    Ok(report)
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn list_sync_conflicts(
+    pool: State<'_, DbPool>,
+    include_resolved: Option<bool>,
+) -> Result<Vec<SyncConflict>, AppError> {
+    let conn = db::get_conn(&pool)?;
+    let mut stmt = conn.prepare(&format!(
+        "{} WHERE ?1 OR status = 'open' ORDER BY id DESC",
+        SELECT_CONFLICT
+    ))?;
+    let conflicts = stmt
+        .query_map(
+            params![include_resolved.unwrap_or(false)],
+            conflict_from_row,
+        )?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(conflicts)
+}
+
+// Keeping the local row re-sends it on the next export; taking the remote row applies it here
+// as a local change, so other devices converge on it too
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn resolve_sync_conflict(
+    pool: State<'_, DbPool>,
+    id: i64,
+    resolution: ConflictResolution,
+) -> Result<SyncConflict, AppError> {
+    let mut conn = db::get_conn(&pool)?;
+    let tx = conn.transaction()?;
+    let conflict =
+        get_conflict_by_id(&tx, id)?.ok_or_else(|| AppError::not_found("Conflict not found"))?;
+    if conflict.resolved {
+        return Err(AppError::validation("id", "This conflict is already resolved"));
+    }
+    match resolution {
+        ConflictResolution::KeepLocal => {
+            rerecord_row(&tx, &conflict.table_name, &conflict.row_uuid)?
+        }
+        ConflictResolution::TakeRemote => {
+            let change = SyncChange {
+                table_name: conflict.table_name.clone(),
+                row_uuid: conflict.row_uuid.clone(),
+                operation: conflict.remote_operation,
+                data: conflict.remote_data.clone(),
+                lamport: conflict.remote_lamport,
+                device_id: conflict.remote_device_id.clone(),
+            };
+            apply_change(&tx, &change)?;
+        }
+    }
+    tx.execute(
+        "UPDATE sync_conflicts
+         SET status = 'resolved', resolution = ?1, resolved_at = CURRENT_TIMESTAMP
+         WHERE id = ?2",
+        params![resolution.as_str(), id],
+    )?;
+    let resolved = get_conflict_by_id(&tx, id)?
+        .ok_or_else(|| AppError::not_found("Conflict not found after resolving"))?;
+    tx.commit()?;
+    Ok(resolved)
+}
@@ -0,0 +1,404 @@
+use chrono::NaiveDate;
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+use tauri::State;
+
+use crate::amount_words::{amount_in_words, Currency};
+use crate::credit_notes::NoteType;
+use crate::db::{self, get_setting, set_setting, DbPool};
+use crate::error::AppError;
+use crate::invoices::INVOICE_DATE_FORMAT;
+
+const SETTING_COMPANY_NAME: &str = "tally_company_name";
+const SETTING_SALES_LEDGER: &str = "tally_sales_ledger";
+const SETTING_CGST_LEDGER: &str = "tally_cgst_ledger";
+const SETTING_SGST_LEDGER: &str = "tally_sgst_ledger";
+const SETTING_IGST_LEDGER: &str = "tally_igst_ledger";
+const SETTING_VOUCHER_TYPE: &str = "tally_voucher_type";
+const SETTING_ROUND_OFF_LEDGER: &str = "tally_round_off_ledger";
+const SETTING_CREDIT_NOTE_VOUCHER_TYPE: &str = "tally_credit_note_voucher_type";
+const SETTING_DEBIT_NOTE_VOUCHER_TYPE: &str = "tally_debit_note_voucher_type";
+
+// Ledger and company names must match the masters in the target Tally company exactly
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct TallyExportConfig {
+    pub company_name: String,
+    pub sales_ledger: String,
+    pub cgst_ledger: String,
+    pub sgst_ledger: String,
+    pub igst_ledger: String,
+    pub voucher_type: String,
+    #[serde(default)]
+    pub round_off_ledger: String,
+    #[serde(default)]
+    pub credit_note_voucher_type: String,
+    #[serde(default)]
+    pub debit_note_voucher_type: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DateRange {
+    pub from: String,
+    pub to: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TallyExportResult {
+    pub xml: String,
+    pub voucher_count: usize,
+    pub path: Option<String>,
+}
+
+struct VoucherRow {
+    voucher_type: String,
+    invoice_number: String,
+    invoice_date: String,
+    // The original invoice for credit and debit notes, otherwise the invoice itself
+    reference: String,
+    // Credit notes reverse a sale, so every ledger entry swaps sides
+    is_credit_note: bool,
+    party_ledger: String,
+    place_of_supply: Option<String>,
+    taxable_value: f64,
+    cgst_amount: f64,
+    sgst_amount: f64,
+    igst_amount: f64,
+    round_off: f64,
+    total_amount: f64,
+}
+
+pub fn load_config(conn: &Connection) -> Result<TallyExportConfig, String> {
+    let value = |key: &str, default: &str| -> Result<String, String> {
+        Ok(get_setting(conn, key)?
+            .filter(|v| !v.trim().is_empty())
+            .unwrap_or_else(|| default.to_string()))
+    };
+
+    Ok(TallyExportConfig {
+        company_name: value(SETTING_COMPANY_NAME, "")?,
+        sales_ledger: value(SETTING_SALES_LEDGER, "Sales")?,
+        cgst_ledger: value(SETTING_CGST_LEDGER, "Output CGST")?,
+        sgst_ledger: value(SETTING_SGST_LEDGER, "Output SGST")?,
+        igst_ledger: value(SETTING_IGST_LEDGER, "Output IGST")?,
+        voucher_type: value(SETTING_VOUCHER_TYPE, "Sales")?,
+        round_off_ledger: value(SETTING_ROUND_OFF_LEDGER, "Round Off")?,
+        credit_note_voucher_type: value(SETTING_CREDIT_NOTE_VOUCHER_TYPE, "Credit Note")?,
+        debit_note_voucher_type: value(SETTING_DEBIT_NOTE_VOUCHER_TYPE, "Debit Note")?,
+    })
+}
+
+pub fn escape_xml(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+pub fn parse_range(range: &DateRange) -> Result<(NaiveDate, NaiveDate), AppError> {
+    let from = NaiveDate::parse_from_str(range.from.trim(), INVOICE_DATE_FORMAT)
+        .map_err(|_| AppError::validation("from", "From date must be in YYYY-MM-DD format"))?;
+    let to = NaiveDate::parse_from_str(range.to.trim(), INVOICE_DATE_FORMAT)
+        .map_err(|_| AppError::validation("to", "To date must be in YYYY-MM-DD format"))?;
+    if from > to {
+        return Err(AppError::validation(
+            "to",
+            "From date must be on or before the to date",
+        ));
+    }
+    Ok((from, to))
+}
+
+// Tally expects amounts with debit entries negative and credit entries positive
+fn ledger_entry(xml: &mut String, ledger: &str, is_debit: bool, amount: f64) {
+    let signed = if is_debit { -amount } else { amount };
+    xml.push_str("        <ALLLEDGERENTRIES.LIST>\n");
+    xml.push_str(&format!(
+        "          <LEDGERNAME>{}</LEDGERNAME>\n",
+        escape_xml(ledger)
+    ));
+    xml.push_str(&format!(
+        "          <ISDEEMEDPOSITIVE>{}</ISDEEMEDPOSITIVE>\n",
+        if is_debit { "Yes" } else { "No" }
+    ));
+    xml.push_str(&format!("          <AMOUNT>{:.2}</AMOUNT>\n", signed));
+    xml.push_str("        </ALLLEDGERENTRIES.LIST>\n");
+}
+
+fn voucher_xml(
+    xml: &mut String,
+    config: &TallyExportConfig,
+    row: &VoucherRow,
+) -> Result<(), String> {
+    let date = NaiveDate::parse_from_str(&row.invoice_date, INVOICE_DATE_FORMAT)
+        .map_err(|_| format!("Voucher {} has an invalid date", row.invoice_number))?;
+    let voucher_type = escape_xml(&row.voucher_type);
+
+    xml.push_str("    <TALLYMESSAGE xmlns:UDF=\"TallyUDF\">\n");
+    xml.push_str(&format!(
+        "      <VOUCHER VCHTYPE=\"{}\" ACTION=\"Create\" OBJVIEW=\"Accounting Voucher View\">\n",
+        voucher_type
+    ));
+    xml.push_str(&format!("        <DATE>{}</DATE>\n", date.format("%Y%m%d")));
+    xml.push_str(&format!(
+        "        <VOUCHERTYPENAME>{}</VOUCHERTYPENAME>\n",
+        voucher_type
+    ));
+    xml.push_str(&format!(
+        "        <VOUCHERNUMBER>{}</VOUCHERNUMBER>\n",
+        escape_xml(&row.invoice_number)
+    ));
+    xml.push_str(&format!(
+        "        <REFERENCE>{}</REFERENCE>\n",
+        escape_xml(&row.reference)
+    ));
+    xml.push_str(&format!(
+        "        <PARTYLEDGERNAME>{}</PARTYLEDGERNAME>\n",
+        escape_xml(&row.party_ledger)
+    ));
+    if let Some(place_of_supply) = &row.place_of_supply {
+        xml.push_str(&format!(
+            "        <PLACEOFSUPPLY>{}</PLACEOFSUPPLY>\n",
+            escape_xml(place_of_supply)
+        ));
+    }
+    xml.push_str(&format!(
+        "        <NARRATION>{}</NARRATION>\n",
+        escape_xml(&amount_in_words(row.total_amount, Currency::Inr)?)
+    ));
+    xml.push_str("        <PERSISTEDVIEW>Accounting Voucher View</PERSISTEDVIEW>\n");
+
+    let reversed = row.is_credit_note;
+    ledger_entry(xml, &row.party_ledger, !reversed, row.total_amount);
+    ledger_entry(xml, &config.sales_ledger, reversed, row.taxable_value);
+    if row.cgst_amount > 0.0 {
+        ledger_entry(xml, &config.cgst_ledger, reversed, row.cgst_amount);
+    }
+    if row.sgst_amount > 0.0 {
+        ledger_entry(xml, &config.sgst_ledger, reversed, row.sgst_amount);
+    }
+    if row.igst_amount > 0.0 {
+        ledger_entry(xml, &config.igst_ledger, reversed, row.igst_amount);
+    }
+    // Rounding up is extra income (credit); rounding down is an expense (debit)
+    if row.round_off != 0.0 {
+        ledger_entry(
+            xml,
+            &config.round_off_ledger,
+            row.round_off < 0.0,
+            row.round_off.abs(),
+        );
+    }
+
+    xml.push_str("      </VOUCHER>\n");
+    xml.push_str("    </TALLYMESSAGE>\n");
+    Ok(())
+}
+
+// Wraps voucher TALLYMESSAGE blocks in the standard import envelope
+pub fn envelope(company_name: &str, messages: &str) -> String {
+    let mut xml = String::new();
+    xml.push_str("<ENVELOPE>\n");
+    xml.push_str("  <HEADER>\n    <TALLYREQUEST>Import Data</TALLYREQUEST>\n  </HEADER>\n");
+    xml.push_str("  <BODY>\n    <IMPORTDATA>\n");
+    xml.push_str("      <REQUESTDESC>\n        <REPORTNAME>Vouchers</REPORTNAME>\n");
+    xml.push_str(&format!(
+        "        <STATICVARIABLES>\n          <SVCURRENTCOMPANY>{}</SVCURRENTCOMPANY>\n        </STATICVARIABLES>\n",
+        escape_xml(company_name)
+    ));
+    xml.push_str("      </REQUESTDESC>\n");
+    xml.push_str("      <REQUESTDATA>\n");
+    xml.push_str(messages);
+    xml.push_str("      </REQUESTDATA>\n");
+    xml.push_str("    </IMPORTDATA>\n  </BODY>\n");
+    xml.push_str("</ENVELOPE>\n");
+    xml
+}
+
+fn load_vouchers(
+    conn: &Connection,
+    config: &TallyExportConfig,
+    company_id: i64,
+    from: NaiveDate,
+    to: NaiveDate,
+) -> Result<Vec<VoucherRow>, AppError> {
+    let from = from.format(INVOICE_DATE_FORMAT).to_string();
+    let to = to.format(INVOICE_DATE_FORMAT).to_string();
+
+    let mut stmt = conn.prepare(
+        "SELECT i.invoice_number, i.invoice_date, c.tally_customer, s.name AS state_name,
+                    i.taxable_value, i.cgst_amount, i.sgst_amount, i.igst_amount, i.round_off,
+                    i.total_amount
+             FROM invoices i
+             JOIN customers c ON c.id = i.customer_id
+             LEFT JOIN states s ON s.code = i.place_of_supply
+             WHERE i.company_id = ?1 AND i.status = 'issued'
+               AND i.invoice_date BETWEEN ?2 AND ?3
+             ORDER BY i.invoice_date, i.invoice_number",
+    )?;
+    let mut rows = stmt
+        .query_map(params![company_id, from, to], |row| {
+            let invoice_number: String = row.get(0)?;
+            Ok(VoucherRow {
+                voucher_type: config.voucher_type.clone(),
+                reference: invoice_number.clone(),
+                invoice_number,
+                invoice_date: row.get(1)?,
+                is_credit_note: false,
+                party_ledger: row.get(2)?,
+                place_of_supply: row.get(3)?,
+                taxable_value: row.get(4)?,
+                cgst_amount: row.get(5)?,
+                sgst_amount: row.get(6)?,
+                igst_amount: row.get(7)?,
+                round_off: row.get(8)?,
+                total_amount: row.get(9)?,
+            })
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let mut stmt = conn.prepare(
+        "SELECT n.note_number, n.note_date, i.invoice_number, n.note_type, c.tally_customer,
+                    s.name AS state_name, n.taxable_value, n.cgst_amount, n.sgst_amount,
+                    n.igst_amount, n.total_amount
+             FROM credit_debit_notes n
+             JOIN invoices i ON i.id = n.invoice_id
+             JOIN customers c ON c.id = i.customer_id
+             LEFT JOIN states s ON s.code = i.place_of_supply
+             WHERE n.company_id = ?1 AND n.status = 'issued'
+               AND n.note_date BETWEEN ?2 AND ?3",
+    )?;
+    let notes = stmt
+        .query_map(params![company_id, from, to], |row| {
+            let is_credit_note = row.get::<_, String>(3)? == NoteType::Credit.as_str();
+            Ok(VoucherRow {
+                voucher_type: if is_credit_note {
+                    config.credit_note_voucher_type.clone()
+                } else {
+                    config.debit_note_voucher_type.clone()
+                },
+                invoice_number: row.get(0)?,
+                invoice_date: row.get(1)?,
+                reference: row.get(2)?,
+                is_credit_note,
+                party_ledger: row.get(4)?,
+                place_of_supply: row.get(5)?,
+                taxable_value: row.get(6)?,
+                cgst_amount: row.get(7)?,
+                sgst_amount: row.get(8)?,
+                igst_amount: row.get(9)?,
+                round_off: 0.0,
+                total_amount: row.get(10)?,
+            })
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    rows.extend(notes);
+    rows.sort_by(|a, b| {
+        a.invoice_date
+            .cmp(&b.invoice_date)
+            .then_with(|| a.invoice_number.cmp(&b.invoice_number))
+    });
+    Ok(rows)
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn get_tally_export_config(
+    pool: State<'_, DbPool>,
+) -> Result<TallyExportConfig, AppError> {
+    let conn = db::get_conn(&pool)?;
+    Ok(load_config(&conn)?)
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn save_tally_export_config(
+    pool: State<'_, DbPool>,
+    config: TallyExportConfig,
+) -> Result<TallyExportConfig, AppError> {
+    let ledgers = [
+        ("sales_ledger", "Sales ledger", &config.sales_ledger),
+        ("cgst_ledger", "CGST ledger", &config.cgst_ledger),
+        ("sgst_ledger", "SGST ledger", &config.sgst_ledger),
+        ("igst_ledger", "IGST ledger", &config.igst_ledger),
+        ("voucher_type", "Voucher type", &config.voucher_type),
+    ];
+    for (field, label, name) in ledgers {
+        if name.trim().is_empty() {
+            return Err(AppError::validation(
+                field,
+                format!("{} is required", label),
+            ));
+        }
+    }
+
+    let conn = db::get_conn(&pool)?;
+    set_setting(&conn, SETTING_COMPANY_NAME, config.company_name.trim())?;
+    set_setting(&conn, SETTING_SALES_LEDGER, config.sales_ledger.trim())?;
+    set_setting(&conn, SETTING_CGST_LEDGER, config.cgst_ledger.trim())?;
+    set_setting(&conn, SETTING_SGST_LEDGER, config.sgst_ledger.trim())?;
+    set_setting(&conn, SETTING_IGST_LEDGER, config.igst_ledger.trim())?;
+    set_setting(&conn, SETTING_VOUCHER_TYPE, config.voucher_type.trim())?;
+    set_setting(&conn, SETTING_ROUND_OFF_LEDGER, config.round_off_ledger.trim())?;
+    set_setting(
+        &conn,
+        SETTING_CREDIT_NOTE_VOUCHER_TYPE,
+        config.credit_note_voucher_type.trim(),
+    )?;
+    set_setting(
+        &conn,
+        SETTING_DEBIT_NOTE_VOUCHER_TYPE,
+        config.debit_note_voucher_type.trim(),
+    )?;
+    Ok(load_config(&conn)?)
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn export_tally_vouchers(
+    pool: State<'_, DbPool>,
+    company_id: i64,
+    date_range: DateRange,
+    path: Option<String>,
+) -> Result<TallyExportResult, AppError> {
+    let (from, to) = parse_range(&date_range)?;
+
+    let conn = db::get_conn(&pool)?;
+    let mut config = load_config(&conn)?;
+    if config.company_name.is_empty() {
+        // Fall back to our own company name, which usually matches the Tally company
+        config.company_name = conn
+            .query_row(
+                "SELECT company_name FROM companies WHERE id = ?1",
+                params![company_id],
+                |row| row.get(0),
+            )
+            .map_err(|_| AppError::not_found("Company not found"))?;
+    }
+
+    let vouchers = load_vouchers(&conn, &config, company_id, from, to)?;
+    let mut messages = String::new();
+    for voucher in &vouchers {
+        voucher_xml(&mut messages, &config, voucher)?;
+    }
+    let xml = envelope(&config.company_name, &messages);
+
+    if let Some(path) = &path {
+        std::fs::write(path, &xml)
+            .map_err(|e| format!("Failed to write Tally export: {}", e))?;
+    }
+
+    Ok(TallyExportResult {
+        xml,
+        voucher_count: vouchers.len(),
+        path,
+    })
+}
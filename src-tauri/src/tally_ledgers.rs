@@ -0,0 +1,466 @@
+use std::collections::HashSet;
+
+use quick_xml::events::Event;
+use quick_xml::Reader;
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+use tauri::State;
+
+use crate::companies;
+use crate::customers::{self, normalize_customer_name, CreateCustomer, Customer};
+use crate::db::{self, DbPool};
+use crate::error::AppError;
+use crate::gstin;
+use crate::states;
+
+// Name similarity at or above this links a ledger to a customer without review
+const AUTO_MATCH_THRESHOLD: f64 = 0.92;
+// Candidates between this and the auto-match threshold are offered as suggestions
+const SUGGESTION_THRESHOLD: f64 = 0.75;
+const MAX_SUGGESTIONS: usize = 3;
+
+// Only customer ledgers are imported unless the caller asks for other groups
+const DEFAULT_LEDGER_GROUP: &str = "Sundry Debtors";
+
+// A ledger master as read from a Tally export
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct TallyLedger {
+    pub name: String,
+    pub parent: Option<String>,
+    pub gstin: Option<String>,
+    pub state_name: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum LedgerImportStatus {
+    // Already linked by tally_customer; GST details refreshed where missing
+    Updated,
+    Unchanged,
+    // Linked to an existing report customer by GSTIN or name similarity
+    Matched,
+    Created,
+    // Needs manual mapping
+    Unmatched,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct LedgerSuggestion {
+    pub customer_id: i64,
+    pub report_customer: String,
+    pub score: f64,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct LedgerImportResult {
+    pub ledger_name: String,
+    pub gstin: Option<String>,
+    pub status: LedgerImportStatus,
+    pub customer_id: Option<i64>,
+    pub score: Option<f64>,
+    pub suggestions: Vec<LedgerSuggestion>,
+    pub message: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct LedgerImportReport {
+    pub total_ledgers: usize,
+    pub created: usize,
+    pub updated: usize,
+    pub matched: usize,
+    pub unchanged: usize,
+    pub unmatched: usize,
+    pub results: Vec<LedgerImportResult>,
+}
+
+fn decode_utf16(bytes: &[u8], little_endian: bool) -> Result<String, String> {
+    let units = bytes.chunks_exact(2).map(|pair| {
+        if little_endian {
+            u16::from_le_bytes([pair[0], pair[1]])
+        } else {
+            u16::from_be_bytes([pair[0], pair[1]])
+        }
+    });
+    char::decode_utf16(units)
+        .collect::<Result<String, _>>()
+        .map_err(|_| "Tally export is not valid UTF-16".to_string())
+}
+
+// Tally writes Unicode exports as UTF-16 with a byte order mark
+fn decode_export(bytes: &[u8]) -> Result<String, String> {
+    match bytes {
+        [0xFF, 0xFE, rest @ ..] => decode_utf16(rest, true),
+        [0xFE, 0xFF, rest @ ..] => decode_utf16(rest, false),
+        [0xEF, 0xBB, 0xBF, rest @ ..] => String::from_utf8(rest.to_vec())
+            .map_err(|_| "Tally export is not valid UTF-8".to_string()),
+        _ => String::from_utf8(bytes.to_vec())
+            .map_err(|_| "Tally export is not valid UTF-8".to_string()),
+    }
+}
+
+fn non_empty(value: &str) -> Option<String> {
+    let value = value.trim();
+    if value.is_empty() {
+        None
+    } else {
+        Some(value.to_string())
+    }
+}
+
+// Reads LEDGER masters from a Tally XML export. The ledger name comes from the
+// NAME attribute, falling back to the first NAME.LIST entry for older exports.
+pub fn parse_ledgers(xml: &str) -> Result<Vec<TallyLedger>, String> {
+    let mut reader = Reader::from_str(xml);
+    reader.config_mut().trim_text(true);
+
+    let mut ledgers = Vec::new();
+    let mut current: Option<TallyLedger> = None;
+    let mut path: Vec<String> = Vec::new();
+
+    loop {
+        match reader.read_event() {
+            Ok(Event::Start(e)) => {
+                let tag = String::from_utf8_lossy(e.name().as_ref()).to_uppercase();
+                if tag == "LEDGER" {
+                    let name = e
+                        .try_get_attribute("NAME")
+                        .map_err(|e| format!("Invalid Tally XML: {}", e))?
+                        .map(|attr| attr.unescape_value().map(|v| v.into_owned()))
+                        .transpose()
+                        .map_err(|e| format!("Invalid Tally XML: {}", e))?
+                        .unwrap_or_default();
+                    current = Some(TallyLedger {
+                        name: name.trim().to_string(),
+                        ..TallyLedger::default()
+                    });
+                }
+                path.push(tag);
+            }
+            Ok(Event::End(e)) => {
+                let tag = String::from_utf8_lossy(e.name().as_ref()).to_uppercase();
+                path.pop();
+                if tag == "LEDGER" {
+                    if let Some(ledger) = current.take() {
+                        if !ledger.name.is_empty() {
+                            ledgers.push(ledger);
+                        }
+                    }
+                }
+            }
+            Ok(Event::Text(e)) => {
+                let Some(ledger) = current.as_mut() else {
+                    continue;
+                };
+                let text = e
+                    .unescape()
+                    .map_err(|e| format!("Invalid Tally XML: {}", e))?;
+                let tag = path.last().map(String::as_str).unwrap_or("");
+                let parent_tag = path
+                    .len()
+                    .checked_sub(2)
+                    .map(|i| path[i].as_str())
+                    .unwrap_or("");
+                match tag {
+                    "PARENT" => ledger.parent = non_empty(&text),
+                    "PARTYGSTIN" | "GSTIN" if ledger.gstin.is_none() => {
+                        ledger.gstin = non_empty(&text).map(|g| g.to_uppercase())
+                    }
+                    "LEDSTATENAME" | "STATENAME" if ledger.state_name.is_none() => {
+                        ledger.state_name = non_empty(&text)
+                    }
+                    "NAME" if parent_tag == "NAME.LIST" && ledger.name.is_empty() => {
+                        ledger.name = text.trim().to_string()
+                    }
+                    _ => {}
+                }
+            }
+            Ok(Event::Eof) => break,
+            Ok(_) => {}
+            Err(e) => {
+                return Err(format!(
+                    "Invalid Tally XML at position {}: {}",
+                    reader.buffer_position(),
+                    e
+                ))
+            }
+        }
+    }
+
+    Ok(ledgers)
+}
+
+fn similarity(a: &str, b: &str) -> f64 {
+    strsim::jaro_winkler(a, b)
+}
+
+// Ranks unlinked customers by normalized name similarity to the ledger
+fn rank_candidates(ledger_name: &str, candidates: &[&Customer]) -> Vec<LedgerSuggestion> {
+    let normalized = normalize_customer_name(ledger_name);
+    let mut ranked: Vec<LedgerSuggestion> = candidates
+        .iter()
+        .filter_map(|customer| {
+            let customer_name = customer
+                .normalized_name
+                .clone()
+                .unwrap_or_else(|| normalize_customer_name(&customer.report_customer));
+            let score = similarity(&normalized, &customer_name);
+            if score < SUGGESTION_THRESHOLD {
+                return None;
+            }
+            Some(LedgerSuggestion {
+                customer_id: customer.id?,
+                report_customer: customer.report_customer.clone(),
+                score: (score * 1000.0).round() / 1000.0,
+            })
+        })
+        .collect();
+    ranked.sort_by(|a, b| b.score.total_cmp(&a.score));
+    ranked
+}
+
+// State code for a ledger: from its GSTIN when valid, otherwise from its state name
+fn ledger_state_code(conn: &Connection, ledger: &TallyLedger) -> Result<String, String> {
+    if let Some(gst_no) = &ledger.gstin {
+        if let Ok(parsed) = gstin::parse_gstin(gst_no) {
+            return Ok(parsed.state_code);
+        }
+    }
+    match &ledger.state_name {
+        Some(name) => Ok(states::get_state_by_name(conn, name)?
+            .map(|state| state.code)
+            .unwrap_or_default()),
+        None => Ok(String::new()),
+    }
+}
+
+fn valid_gstin(ledger: &TallyLedger) -> Option<&str> {
+    ledger
+        .gstin
+        .as_deref()
+        .filter(|gst_no| gstin::check_gstin(gst_no).is_ok())
+}
+
+// Fills in GST details that the customer record is missing; never overwrites
+fn refresh_gst_details(
+    conn: &Connection,
+    customer: &Customer,
+    ledger: &TallyLedger,
+) -> Result<bool, AppError> {
+    let Some(gst_no) = valid_gstin(ledger) else {
+        return Ok(false);
+    };
+    if !customer.gst_no.trim().is_empty() {
+        return Ok(false);
+    }
+
+    let state_code = states::resolve_state_code(gst_no, "")?;
+    conn.execute(
+        "UPDATE customers SET gst_no = ?1, state_code = ?2, updated_at = CURRENT_TIMESTAMP
+         WHERE id = ?3 AND company_id = ?4",
+        params![gst_no, state_code, customer.id, customer.company_id],
+    )?;
+    Ok(true)
+}
+
+fn link_ledger(
+    conn: &Connection,
+    customer: &Customer,
+    ledger: &TallyLedger,
+) -> Result<(), AppError> {
+    conn.execute(
+        "UPDATE customers SET tally_customer = ?1, updated_at = CURRENT_TIMESTAMP
+         WHERE id = ?2 AND company_id = ?3",
+        params![ledger.name, customer.id, customer.company_id],
+    )
+    .map_err(|e| {
+        if e.to_string().contains("UNIQUE constraint failed") {
+            AppError::conflict(
+                "tally_customer",
+                format!(
+                    "Tally ledger {} is already linked to another customer",
+                    ledger.name
+                ),
+            )
+        } else {
+            AppError::from(e)
+        }
+    })?;
+    refresh_gst_details(conn, customer, ledger)?;
+    Ok(())
+}
+
+fn result(ledger: &TallyLedger, status: LedgerImportStatus) -> LedgerImportResult {
+    LedgerImportResult {
+        ledger_name: ledger.name.clone(),
+        gstin: ledger.gstin.clone(),
+        status,
+        customer_id: None,
+        score: None,
+        suggestions: Vec::new(),
+        message: None,
+    }
+}
+
+pub fn import_ledgers(
+    conn: &Connection,
+    company_id: i64,
+    ledgers: &[TallyLedger],
+    default_category_id: Option<i64>,
+) -> Result<Vec<LedgerImportResult>, AppError> {
+    let customers = customers::get_customers_by_company(conn, company_id)?;
+    let ledger_names: HashSet<&str> = ledgers.iter().map(|l| l.name.as_str()).collect();
+    let mut claimed: HashSet<i64> = HashSet::new();
+    let mut results = Vec::with_capacity(ledgers.len());
+
+    for ledger in ledgers {
+        // 1. Already linked by ledger name
+        if let Some(customer) = customers.iter().find(|c| c.tally_customer == ledger.name) {
+            let customer_id = customer.id.unwrap_or_default();
+            claimed.insert(customer_id);
+            let status = if refresh_gst_details(conn, customer, ledger)? {
+                LedgerImportStatus::Updated
+            } else {
+                LedgerImportStatus::Unchanged
+            };
+            results.push(LedgerImportResult {
+                customer_id: Some(customer_id),
+                ..result(ledger, status)
+            });
+            continue;
+        }
+
+        // Customers whose current tally_customer is another ledger in this file are taken
+        let candidates: Vec<&Customer> = customers
+            .iter()
+            .filter(|c| c.id.is_some_and(|id| !claimed.contains(&id)))
+            .filter(|c| !ledger_names.contains(c.tally_customer.as_str()))
+            .collect();
+
+        // 2. Same GSTIN is an unambiguous match
+        if let Some(gst_no) = valid_gstin(ledger) {
+            let same_gstin: Vec<&&Customer> = candidates
+                .iter()
+                .filter(|c| c.gst_no.trim().eq_ignore_ascii_case(gst_no))
+                .collect();
+            if let [customer] = same_gstin.as_slice() {
+                link_ledger(conn, customer, ledger)?;
+                let customer_id = customer.id.unwrap_or_default();
+                claimed.insert(customer_id);
+                results.push(LedgerImportResult {
+                    customer_id: Some(customer_id),
+                    score: Some(1.0),
+                    message: Some("Matched by GSTIN".to_string()),
+                    ..result(ledger, LedgerImportStatus::Matched)
+                });
+                continue;
+            }
+        }
+
+        // 3. Fuzzy name match, only when the best candidate is clearly ahead
+        let ranked = rank_candidates(&ledger.name, &candidates);
+        let runner_up = ranked.get(1).map(|s| s.score).unwrap_or(0.0);
+        let best = ranked
+            .first()
+            .filter(|b| b.score >= AUTO_MATCH_THRESHOLD && b.score > runner_up);
+        if let Some(best) = best {
+            let customer = candidates
+                .iter()
+                .find(|c| c.id == Some(best.customer_id))
+                .ok_or_else(|| AppError::internal("Matched customer disappeared"))?;
+            link_ledger(conn, customer, ledger)?;
+            claimed.insert(best.customer_id);
+            results.push(LedgerImportResult {
+                customer_id: Some(best.customer_id),
+                score: Some(best.score),
+                message: Some(format!("Matched to {}", best.report_customer)),
+                ..result(ledger, LedgerImportStatus::Matched)
+            });
+            continue;
+        }
+
+        // 4. Nothing close: create a customer when a category was given, otherwise
+        //    flag it. Near misses are always left for manual mapping.
+        match default_category_id {
+            Some(category_id) if ranked.is_empty() => {
+                let gst_no = valid_gstin(ledger).map(str::to_string);
+                let state_code = ledger_state_code(conn, ledger)?;
+                let customer = CreateCustomer {
+                    report_customer: ledger.name.clone(),
+                    tally_customer: ledger.name.clone(),
+                    gst_no,
+                    registration_type: None,
+                    state_code: Some(state_code),
+                    category_id,
+                    company_id,
+                    address: None,
+                    city: None,
+                    pincode: None,
+                };
+                let id = customers::insert_customer(conn, &customer, None)?;
+                claimed.insert(id);
+                results.push(LedgerImportResult {
+                    customer_id: Some(id),
+                    ..result(ledger, LedgerImportStatus::Created)
+                });
+            }
+            _ => {
+                let message = if ranked.is_empty() {
+                    "No matching customer found"
+                } else {
+                    "Similar customers found; confirm the mapping manually"
+                };
+                results.push(LedgerImportResult {
+                    message: Some(message.to_string()),
+                    suggestions: ranked.into_iter().take(MAX_SUGGESTIONS).collect(),
+                    ..result(ledger, LedgerImportStatus::Unmatched)
+                });
+            }
+        }
+    }
+
+    Ok(results)
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn import_tally_ledgers(
+    pool: State<'_, DbPool>,
+    company_id: i64,
+    path: String,
+    default_category_id: Option<i64>,
+    ledger_groups: Option<Vec<String>>,
+) -> Result<LedgerImportReport, AppError> {
+    let bytes = std::fs::read(&path).map_err(|e| format!("Failed to read Tally export: {}", e))?;
+    let xml = decode_export(&bytes).map_err(|e| AppError::validation("path", e))?;
+
+    let groups = ledger_groups
+        .filter(|groups| !groups.is_empty())
+        .unwrap_or_else(|| vec![DEFAULT_LEDGER_GROUP.to_string()]);
+    let ledgers: Vec<TallyLedger> = parse_ledgers(&xml)
+        .map_err(|e| AppError::validation("path", e))?
+        .into_iter()
+        .filter(|ledger| {
+            ledger.parent.as_deref().is_some_and(|parent| {
+                groups.iter().any(|group| group.trim().eq_ignore_ascii_case(parent))
+            })
+        })
+        .collect();
+
+    let mut conn = db::get_conn(&pool)?;
+    companies::get_company_by_id(&conn, company_id)?
+        .ok_or_else(|| AppError::not_found("Company not found"))?;
+    let tx = conn.transaction()?;
+    let results = import_ledgers(&tx, company_id, &ledgers, default_category_id)?;
+    tx.commit()?;
+
+    let count = |status: LedgerImportStatus| results.iter().filter(|r| r.status == status).count();
+    Ok(LedgerImportReport {
+        total_ledgers: ledgers.len(),
+        created: count(LedgerImportStatus::Created),
+        updated: count(LedgerImportStatus::Updated),
+        matched: count(LedgerImportStatus::Matched),
+        unchanged: count(LedgerImportStatus::Unchanged),
+        unmatched: count(LedgerImportStatus::Unmatched),
+        results,
+    })
+}
@@ -0,0 +1,782 @@
+use serde::{Deserialize, Serialize};
+use tauri::State;
+
+use crate::companies;
+use crate::customers;
+use crate::db::{self, DbPool};
+use crate::error::AppError;
+use crate::exports::ExportMode;
+use crate::place_of_supply::{self, SupplyKind};
+use crate::rounding::{self, RoundingMode};
+use crate::tcs;
+
+// Tax math shared by invoices, notes and the live preview. The functions are pure; callers
+// resolve the regime and rounding mode from the database first.
+
+// Amounts may differ by floating point noise; anything within half a paisa is treated as equal
+pub const AMOUNT_TOLERANCE: f64 = 0.005;
+
+pub fn round2(value: f64) -> f64 {
+    (value * 100.0).round() / 100.0
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum TaxRegime {
+    // CGST + SGST/UTGST
+    IntraState,
+    // IGST
+    InterState,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq)]
+pub struct TaxSplit {
+    pub cgst_amount: f64,
+    pub sgst_amount: f64,
+    pub igst_amount: f64,
+}
+
+impl TaxSplit {
+    pub fn total(&self) -> f64 {
+        round2(self.cgst_amount + self.sgst_amount + self.igst_amount)
+    }
+}
+
+// Splits a tax amount under the regime. CGST and SGST are each half the tax rounded to the
+// paisa, so the two are always equal even when the tax is an odd number of paise.
+pub fn split_tax(tax: f64, regime: TaxRegime) -> TaxSplit {
+    match regime {
+        TaxRegime::InterState => TaxSplit {
+            igst_amount: round2(tax),
+            ..TaxSplit::default()
+        },
+        TaxRegime::IntraState => {
+            let half = round2(tax / 2.0);
+            TaxSplit {
+                cgst_amount: half,
+                sgst_amount: half,
+                igst_amount: 0.0,
+            }
+        }
+    }
+}
+
+pub fn tax_on(taxable_value: f64, gst_rate: f64, regime: TaxRegime) -> TaxSplit {
+    split_tax(taxable_value * gst_rate / 100.0, regime)
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct TaxLineInput {
+    pub quantity: f64,
+    pub rate: f64,
+    // Flat discount in rupees, applied after any percentage discount
+    #[serde(default)]
+    pub discount: f64,
+    #[serde(default)]
+    pub discount_percent: f64,
+    pub gst_rate: f64,
+    // Compensation cess as a percentage of the taxable value
+    #[serde(default)]
+    pub cess_rate: f64,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct LineTotals {
+    pub gross_amount: f64,
+    // Line discount, then this line's share of the invoice discount
+    pub discount: f64,
+    pub invoice_discount: f64,
+    pub taxable_value: f64,
+    pub gst_rate: f64,
+    pub cgst_amount: f64,
+    pub sgst_amount: f64,
+    pub igst_amount: f64,
+    pub cess_amount: f64,
+    pub total_amount: f64,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct InvoiceTotals {
+    pub regime: TaxRegime,
+    pub lines: Vec<LineTotals>,
+    pub gross_amount: f64,
+    pub line_discount: f64,
+    pub invoice_discount: f64,
+    pub taxable_value: f64,
+    pub cgst_amount: f64,
+    pub sgst_amount: f64,
+    pub igst_amount: f64,
+    pub cess_amount: f64,
+    // GST and cess payable by the recipient under reverse charge, left out of the amounts above
+    pub reverse_charge_tax: f64,
+    pub tcs_base: f64,
+    pub tcs_amount: f64,
+    pub round_off: f64,
+    pub total_amount: f64,
+}
+
+impl InvoiceTotals {
+    // Moves the GST and cess to the recipient: the supplier charges none and the total drops by it
+    pub fn with_reverse_charge(self, rounding: RoundingMode) -> Self {
+        let tax = round2(self.cgst_amount + self.sgst_amount + self.igst_amount + self.cess_amount);
+        let mut totals = self.without_gst(rounding);
+        totals.reverse_charge_tax = tax;
+        totals
+    }
+
+    // Drops the GST and cess from the lines and the total, as for exports under LUT
+    pub fn without_gst(mut self, rounding: RoundingMode) -> Self {
+        let tax = self.cgst_amount + self.sgst_amount + self.igst_amount + self.cess_amount;
+        let exact = round2(self.total_amount - self.round_off - tax);
+        for line in &mut self.lines {
+            line.total_amount = round2(
+                line.total_amount
+                    - line.cgst_amount
+                    - line.sgst_amount
+                    - line.igst_amount
+                    - line.cess_amount,
+            );
+            line.cgst_amount = 0.0;
+            line.sgst_amount = 0.0;
+            line.igst_amount = 0.0;
+            line.cess_amount = 0.0;
+        }
+        self.cgst_amount = 0.0;
+        self.sgst_amount = 0.0;
+        self.igst_amount = 0.0;
+        self.cess_amount = 0.0;
+        self.total_amount = rounding.apply(exact);
+        self.round_off = round2(self.total_amount - exact);
+        self
+    }
+
+    // Adds TCS, which is charged on the invoice value after GST, and rounds the total again
+    pub fn with_tcs(mut self, tcs_base: f64, tcs_amount: f64, rounding: RoundingMode) -> Self {
+        let exact = round2(self.total_amount - self.round_off + tcs_amount);
+        self.tcs_base = tcs_base;
+        self.tcs_amount = tcs_amount;
+        self.total_amount = rounding.apply(exact);
+        self.round_off = round2(self.total_amount - exact);
+        self
+    }
+}
+
+// Gross amount and line discount, after validating the line. The percentage discount is taken
+// first and the flat discount after it.
+fn line_amounts(index: usize, line: &TaxLineInput) -> Result<(f64, f64), String> {
+    let line_no = index + 1;
+    let amounts = [
+        ("quantity", line.quantity),
+        ("rate", line.rate),
+        ("discount", line.discount),
+        ("discount percentage", line.discount_percent),
+        ("GST rate", line.gst_rate),
+        ("cess rate", line.cess_rate),
+    ];
+    for (label, amount) in amounts {
+        if !amount.is_finite() || amount < 0.0 {
+            return Err(format!("Line {}: {} must be a non-negative number", line_no, label));
+        }
+    }
+    if line.quantity <= 0.0 {
+        return Err(format!("Line {}: quantity must be greater than zero", line_no));
+    }
+    for (label, percent) in [
+        ("discount percentage", line.discount_percent),
+        ("GST rate", line.gst_rate),
+        ("cess rate", line.cess_rate),
+    ] {
+        if percent > 100.0 {
+            return Err(format!("Line {}: {} cannot exceed 100%", line_no, label));
+        }
+    }
+
+    let gross_amount = round2(line.quantity * line.rate);
+    let discount = round2(gross_amount * line.discount_percent / 100.0 + line.discount);
+    if discount > gross_amount + AMOUNT_TOLERANCE {
+        return Err(format!("Line {}: discount cannot exceed quantity x rate", line_no));
+    }
+    Ok((gross_amount, discount))
+}
+
+fn finish_line(
+    line: &TaxLineInput,
+    gross_amount: f64,
+    discount: f64,
+    invoice_discount: f64,
+    regime: TaxRegime,
+) -> LineTotals {
+    let taxable_value = round2(gross_amount - discount - invoice_discount);
+    let split = tax_on(taxable_value, line.gst_rate, regime);
+    let cess_amount = round2(taxable_value * line.cess_rate / 100.0);
+    LineTotals {
+        gross_amount,
+        discount,
+        invoice_discount,
+        taxable_value,
+        gst_rate: line.gst_rate,
+        cgst_amount: split.cgst_amount,
+        sgst_amount: split.sgst_amount,
+        igst_amount: split.igst_amount,
+        cess_amount,
+        total_amount: round2(taxable_value + split.total() + cess_amount),
+    }
+}
+
+// Discount on the whole invoice, applied after line discounts: percentage first, then the flat
+// amount
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq)]
+pub struct InvoiceDiscount {
+    #[serde(default)]
+    pub percent: f64,
+    #[serde(default)]
+    pub amount: f64,
+}
+
+impl InvoiceDiscount {
+    pub fn is_zero(&self) -> bool {
+        self.percent == 0.0 && self.amount == 0.0
+    }
+
+    // Rupee value of the discount on lines worth `net_total` after their own discounts
+    pub fn resolve(&self, net_total: f64) -> Result<f64, String> {
+        for (label, value) in [("percentage", self.percent), ("amount", self.amount)] {
+            if !value.is_finite() || value < 0.0 {
+                return Err(format!("Invoice discount {} must be a non-negative number", label));
+            }
+        }
+        if self.percent > 100.0 {
+            return Err("Invoice discount percentage cannot exceed 100%".to_string());
+        }
+        let discount = round2(net_total * self.percent / 100.0 + self.amount);
+        if discount > net_total + AMOUNT_TOLERANCE {
+            return Err("Invoice discount cannot exceed the value of the lines".to_string());
+        }
+        Ok(discount)
+    }
+}
+
+// Splits `amount` across lines in proportion to `bases`, in whole paise. Leftover paise go to
+// the largest remainders (earlier lines first on ties), so the shares always add up to `amount`.
+pub fn allocate(amount: f64, bases: &[f64]) -> Vec<f64> {
+    let total = (amount * 100.0).round() as i128;
+    let bases: Vec<i128> = bases.iter().map(|base| (base * 100.0).round() as i128).collect();
+    let base_total: i128 = bases.iter().sum();
+    if total <= 0 || base_total <= 0 {
+        return vec![0.0; bases.len()];
+    }
+
+    let mut shares = Vec::with_capacity(bases.len());
+    let mut remainders = Vec::with_capacity(bases.len());
+    for (index, base) in bases.iter().enumerate() {
+        shares.push(total * base / base_total);
+        remainders.push((total * base % base_total, index));
+    }
+    let leftover = total - shares.iter().sum::<i128>();
+    remainders.sort_by(|a, b| b.0.cmp(&a.0).then(a.1.cmp(&b.1)));
+    for &(_, index) in remainders.iter().take(leftover as usize) {
+        shares[index] += 1;
+    }
+    shares.into_iter().map(|paise| paise as f64 / 100.0).collect()
+}
+
+// Line discounts come first, then the invoice discount is shared across the lines by their
+// discounted value, so each line's taxable value already reflects it. Header totals are the sums
+// of the rounded line figures, so they always agree with the lines.
+pub fn compute_totals(
+    lines: &[TaxLineInput],
+    discount: InvoiceDiscount,
+    regime: TaxRegime,
+    rounding: RoundingMode,
+) -> Result<InvoiceTotals, AppError> {
+    let amounts = lines
+        .iter()
+        .enumerate()
+        .map(|(index, line)| line_amounts(index, line))
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| AppError::validation("lines", e))?;
+    let nets: Vec<f64> = amounts
+        .iter()
+        .map(|(gross, discount)| round2(gross - discount))
+        .collect();
+    let invoice_discount = discount
+        .resolve(round2(nets.iter().sum()))
+        .map_err(|e| AppError::validation("discount", e))?;
+    let shares = allocate(invoice_discount, &nets);
+    let lines: Vec<LineTotals> = lines
+        .iter()
+        .zip(amounts)
+        .zip(shares)
+        .map(|((line, (gross, discount)), share)| finish_line(line, gross, discount, share, regime))
+        .collect();
+
+    let sum = |amount: fn(&LineTotals) -> f64| round2(lines.iter().map(amount).sum());
+    let gross_amount = sum(|line| line.gross_amount);
+    let line_discount = sum(|line| line.discount);
+    let taxable_value = sum(|line| line.taxable_value);
+    let cgst_amount = sum(|line| line.cgst_amount);
+    let sgst_amount = sum(|line| line.sgst_amount);
+    let igst_amount = sum(|line| line.igst_amount);
+    let cess_amount = sum(|line| line.cess_amount);
+
+    let exact = round2(taxable_value + cgst_amount + sgst_amount + igst_amount + cess_amount);
+    let total_amount = rounding.apply(exact);
+    Ok(InvoiceTotals {
+        regime,
+        lines,
+        gross_amount,
+        line_discount,
+        invoice_discount,
+        taxable_value,
+        cgst_amount,
+        sgst_amount,
+        igst_amount,
+        cess_amount,
+        reverse_charge_tax: 0.0,
+        tcs_base: 0.0,
+        tcs_amount: 0.0,
+        round_off: round2(total_amount - exact),
+        total_amount,
+    })
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TaxContext {
+    pub company_id: i64,
+    pub customer_id: i64,
+    // Defaults to the customer's state
+    pub place_of_supply: Option<String>,
+    #[serde(default)]
+    pub supply_kind: SupplyKind,
+    #[serde(default)]
+    pub discount: InvoiceDiscount,
+    #[serde(default)]
+    pub reverse_charge: bool,
+    // For exports and SEZ supplies; under LUT no IGST is charged
+    pub export_mode: Option<ExportMode>,
+    // Needed for TCS; without a date no TCS is shown
+    pub invoice_date: Option<String>,
+    // Set when previewing an edit to a saved invoice
+    pub invoice_id: Option<i64>,
+}
+
+// Live preview for the invoice form, using the same math the backend applies when saving
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn compute_invoice_totals(
+    pool: State<'_, DbPool>,
+    lines: Vec<TaxLineInput>,
+    context: TaxContext,
+) -> Result<InvoiceTotals, AppError> {
+    let conn = db::get_conn(&pool)?;
+    let company = companies::get_company_by_id(&conn, context.company_id)?
+        .ok_or_else(|| AppError::not_found("Company not found"))?;
+    let customer = customers::get_customer_by_id(&conn, context.customer_id, context.company_id)?
+        .ok_or_else(|| AppError::not_found("Customer not found"))?;
+    let supply_kind = place_of_supply::effective_kind(&customer, context.supply_kind);
+    let place_of_supply = context
+        .place_of_supply
+        .map(|code| code.trim().to_string())
+        .filter(|code| !code.is_empty())
+        .unwrap_or_else(|| place_of_supply::default_place_of_supply(&customer, supply_kind));
+    let regime = place_of_supply::decide(&company.state_code, &place_of_supply, supply_kind);
+    let rounding = rounding::load_mode(&conn)?;
+    let mut totals = compute_totals(&lines, context.discount, regime, rounding)?;
+    if context.reverse_charge {
+        totals = totals.with_reverse_charge(rounding);
+    } else if matches!(supply_kind, SupplyKind::Export | SupplyKind::Sez)
+        && context.export_mode == Some(ExportMode::Lut)
+    {
+        totals = totals.without_gst(rounding);
+    }
+    let (tcs_base, tcs_amount) = match context.invoice_date.as_deref() {
+        Some(invoice_date) => tcs::compute_tcs(
+            &conn,
+            &tcs::Sale {
+                company_id: context.company_id,
+                customer_id: context.customer_id,
+                id: context.invoice_id,
+                invoice_date,
+                supply_kind,
+                value: totals.taxable_value
+                    + totals.cgst_amount
+                    + totals.sgst_amount
+                    + totals.igst_amount
+                    + totals.cess_amount,
+            },
+        )?,
+        None => (0.0, 0.0),
+    };
+    Ok(totals.with_tcs(tcs_base, tcs_amount, rounding))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NO_DISCOUNT: InvoiceDiscount = InvoiceDiscount {
+        percent: 0.0,
+        amount: 0.0,
+    };
+
+    fn totals(
+        lines: &[TaxLineInput],
+        regime: TaxRegime,
+        rounding: RoundingMode,
+    ) -> Result<InvoiceTotals, AppError> {
+        compute_totals(lines, NO_DISCOUNT, regime, rounding)
+    }
+
+    // One line on its own, without any invoice-level discount
+    fn compute_line(
+        index: usize,
+        line: &TaxLineInput,
+        regime: TaxRegime,
+    ) -> Result<LineTotals, String> {
+        let (gross_amount, discount) = line_amounts(index, line)?;
+        Ok(finish_line(line, gross_amount, discount, 0.0, regime))
+    }
+
+    fn line(quantity: f64, rate: f64, gst_rate: f64) -> TaxLineInput {
+        TaxLineInput {
+            quantity,
+            rate,
+            discount: 0.0,
+            discount_percent: 0.0,
+            gst_rate,
+            cess_rate: 0.0,
+        }
+    }
+
+    #[test]
+    fn round2_rounds_to_the_paisa() {
+        assert_eq!(round2(10.004), 10.0);
+        assert_eq!(round2(10.006), 10.01);
+        assert_eq!(round2(-1.234), -1.23);
+        assert_eq!(round2(0.0), 0.0);
+    }
+
+    #[test]
+    fn inter_state_tax_is_all_igst() {
+        let split = tax_on(1000.0, 18.0, TaxRegime::InterState);
+        assert_eq!(split.igst_amount, 180.0);
+        assert_eq!(split.cgst_amount, 0.0);
+        assert_eq!(split.sgst_amount, 0.0);
+    }
+
+    #[test]
+    fn intra_state_tax_is_halved() {
+        let split = tax_on(1000.0, 18.0, TaxRegime::IntraState);
+        assert_eq!(split.cgst_amount, 90.0);
+        assert_eq!(split.sgst_amount, 90.0);
+        assert_eq!(split.igst_amount, 0.0);
+    }
+
+    #[test]
+    fn halves_are_rounded_separately() {
+        let split = tax_on(1.0, 5.0, TaxRegime::IntraState);
+        assert_eq!(split.cgst_amount, 0.03);
+        assert_eq!(split.sgst_amount, 0.03);
+        assert_eq!(split.total(), 0.06);
+    }
+
+    #[test]
+    fn split_rounds_tax_before_splitting() {
+        let split = tax_on(33.34, 5.0, TaxRegime::InterState);
+        assert_eq!(split.igst_amount, 1.67);
+    }
+
+    #[test]
+    fn zero_rated_line_has_no_tax() {
+        let totals = compute_line(0, &line(1.0, 500.0, 0.0), TaxRegime::IntraState).unwrap();
+        assert_eq!(totals.taxable_value, 500.0);
+        assert_eq!(totals.cgst_amount + totals.sgst_amount + totals.igst_amount, 0.0);
+        assert_eq!(totals.total_amount, 500.0);
+    }
+
+    #[test]
+    fn flat_discount_reduces_taxable_value() {
+        let mut input = line(2.0, 100.0, 12.0);
+        input.discount = 20.0;
+        let totals = compute_line(0, &input, TaxRegime::InterState).unwrap();
+        assert_eq!(totals.gross_amount, 200.0);
+        assert_eq!(totals.discount, 20.0);
+        assert_eq!(totals.taxable_value, 180.0);
+        assert_eq!(totals.igst_amount, 21.6);
+        assert_eq!(totals.total_amount, 201.6);
+    }
+
+    #[test]
+    fn percentage_discount_is_applied_before_flat_discount() {
+        let mut input = line(1.0, 1000.0, 18.0);
+        input.discount_percent = 10.0;
+        input.discount = 50.0;
+        let totals = compute_line(0, &input, TaxRegime::IntraState).unwrap();
+        assert_eq!(totals.discount, 150.0);
+        assert_eq!(totals.taxable_value, 850.0);
+        assert_eq!(totals.cgst_amount, 76.5);
+        assert_eq!(totals.sgst_amount, 76.5);
+    }
+
+    #[test]
+    fn full_discount_is_allowed() {
+        let mut input = line(1.0, 100.0, 18.0);
+        input.discount_percent = 100.0;
+        let totals = compute_line(0, &input, TaxRegime::IntraState).unwrap();
+        assert_eq!(totals.taxable_value, 0.0);
+        assert_eq!(totals.total_amount, 0.0);
+    }
+
+    #[test]
+    fn discount_above_gross_is_rejected() {
+        let mut input = line(1.0, 100.0, 18.0);
+        input.discount = 100.01;
+        let error = compute_line(2, &input, TaxRegime::IntraState).unwrap_err();
+        assert_eq!(error, "Line 3: discount cannot exceed quantity x rate");
+    }
+
+    #[test]
+    fn cess_is_charged_on_taxable_value() {
+        let mut input = line(1.0, 1000.0, 28.0);
+        input.cess_rate = 12.0;
+        let totals = compute_line(0, &input, TaxRegime::InterState).unwrap();
+        assert_eq!(totals.igst_amount, 280.0);
+        assert_eq!(totals.cess_amount, 120.0);
+        assert_eq!(totals.total_amount, 1400.0);
+    }
+
+    #[test]
+    fn invalid_amounts_are_rejected() {
+        let cases = [
+            (line(0.0, 10.0, 18.0), "Line 1: quantity must be greater than zero"),
+            (line(-1.0, 10.0, 18.0), "Line 1: quantity must be a non-negative number"),
+            (line(1.0, f64::NAN, 18.0), "Line 1: rate must be a non-negative number"),
+            (line(1.0, 10.0, -5.0), "Line 1: GST rate must be a non-negative number"),
+            (line(1.0, 10.0, 101.0), "Line 1: GST rate cannot exceed 100%"),
+        ];
+        for (input, expected) in cases {
+            assert_eq!(compute_line(0, &input, TaxRegime::InterState).unwrap_err(), expected);
+        }
+
+        let mut input = line(1.0, 10.0, 18.0);
+        input.cess_rate = 150.0;
+        assert_eq!(
+            compute_line(0, &input, TaxRegime::InterState).unwrap_err(),
+            "Line 1: cess rate cannot exceed 100%"
+        );
+        input.cess_rate = 0.0;
+        input.discount_percent = f64::INFINITY;
+        assert_eq!(
+            compute_line(0, &input, TaxRegime::InterState).unwrap_err(),
+            "Line 1: discount percentage must be a non-negative number"
+        );
+    }
+
+    #[test]
+    fn totals_are_sums_of_rounded_lines() {
+        let lines = [line(1.0, 100.2, 5.0), line(1.0, 100.2, 5.0), line(1.0, 99.6, 5.0)];
+        let result = totals(&lines, TaxRegime::IntraState, RoundingMode::None).unwrap();
+        assert_eq!(result.taxable_value, 300.0);
+        assert_eq!(result.cgst_amount, 7.51);
+        assert_eq!(result.sgst_amount, 7.51);
+        assert_eq!(result.round_off, 0.0);
+        assert_eq!(result.total_amount, 315.02);
+        let line_tax: f64 = result
+            .lines
+            .iter()
+            .map(|line| line.cgst_amount + line.sgst_amount)
+            .sum();
+        assert_eq!(round2(line_tax), round2(result.cgst_amount + result.sgst_amount));
+    }
+
+    #[test]
+    fn totals_apply_rounding_mode() {
+        let lines = [line(1.0, 100.5, 0.0)];
+        let nearest = totals(&lines, TaxRegime::InterState, RoundingMode::Nearest).unwrap();
+        assert_eq!(nearest.total_amount, 101.0);
+        assert_eq!(nearest.round_off, 0.5);
+
+        let down = totals(&lines, TaxRegime::InterState, RoundingMode::Down).unwrap();
+        assert_eq!(down.total_amount, 100.0);
+        assert_eq!(down.round_off, -0.5);
+
+        let lines = [line(1.0, 100.01, 0.0)];
+        let up = totals(&lines, TaxRegime::InterState, RoundingMode::Up).unwrap();
+        assert_eq!(up.total_amount, 101.0);
+        assert_eq!(up.round_off, 0.99);
+    }
+
+    #[test]
+    fn totals_include_cess() {
+        let mut input = line(2.0, 500.0, 28.0);
+        input.cess_rate = 1.0;
+        let result = totals(&[input], TaxRegime::IntraState, RoundingMode::None).unwrap();
+        assert_eq!(result.cess_amount, 10.0);
+        assert_eq!(result.cgst_amount, 140.0);
+        assert_eq!(result.total_amount, 1290.0);
+    }
+
+    #[test]
+    fn empty_invoice_totals_are_zero() {
+        let result = totals(&[], TaxRegime::IntraState, RoundingMode::Nearest).unwrap();
+        assert!(result.lines.is_empty());
+        assert_eq!(result.total_amount, 0.0);
+        assert_eq!(result.round_off, 0.0);
+    }
+
+    #[test]
+    fn errors_name_the_failing_line() {
+        let lines = [line(1.0, 10.0, 18.0), line(0.0, 10.0, 18.0)];
+        let error = totals(&lines, TaxRegime::IntraState, RoundingMode::None).unwrap_err();
+        assert_eq!(
+            error,
+            AppError::validation("lines", "Line 2: quantity must be greater than zero")
+        );
+    }
+
+    #[test]
+    fn allocation_is_proportional_and_exact() {
+        assert_eq!(allocate(30.0, &[100.0, 200.0]), vec![10.0, 20.0]);
+        let shares = allocate(10.0, &[1.0, 1.0, 1.0]);
+        assert_eq!(shares, vec![3.34, 3.33, 3.33]);
+        assert_eq!(round2(shares.iter().sum()), 10.0);
+    }
+
+    #[test]
+    fn allocation_gives_leftover_paise_to_largest_remainders() {
+        // Exact shares are 0.6, 1.2 and 0.2 paise
+        assert_eq!(allocate(0.02, &[3.0, 6.0, 1.0]), vec![0.01, 0.01, 0.0]);
+    }
+
+    #[test]
+    fn allocation_of_nothing_or_onto_nothing_is_zero() {
+        assert_eq!(allocate(0.0, &[10.0, 20.0]), vec![0.0, 0.0]);
+        assert_eq!(allocate(5.0, &[0.0, 0.0]), vec![0.0, 0.0]);
+        assert!(allocate(5.0, &[]).is_empty());
+    }
+
+    #[test]
+    fn invoice_discount_is_shared_by_discounted_line_value() {
+        let mut first = line(1.0, 1000.0, 18.0);
+        first.discount_percent = 50.0;
+        let second = line(1.0, 1500.0, 5.0);
+        let discount = InvoiceDiscount {
+            percent: 10.0,
+            amount: 0.0,
+        };
+        let result =
+            compute_totals(&[first, second], discount, TaxRegime::InterState, RoundingMode::None)
+                .unwrap();
+        assert_eq!(result.gross_amount, 2500.0);
+        assert_eq!(result.line_discount, 500.0);
+        assert_eq!(result.invoice_discount, 200.0);
+        assert_eq!(result.lines[0].invoice_discount, 50.0);
+        assert_eq!(result.lines[1].invoice_discount, 150.0);
+        assert_eq!(result.lines[0].taxable_value, 450.0);
+        assert_eq!(result.lines[1].taxable_value, 1350.0);
+        assert_eq!(result.igst_amount, 148.5);
+        assert_eq!(result.total_amount, 1948.5);
+    }
+
+    #[test]
+    fn invoice_percentage_discount_comes_before_flat_amount() {
+        let discount = InvoiceDiscount {
+            percent: 10.0,
+            amount: 50.0,
+        };
+        assert_eq!(discount.resolve(1000.0), Ok(150.0));
+    }
+
+    #[test]
+    fn invoice_discount_cannot_exceed_line_value() {
+        let discount = InvoiceDiscount {
+            percent: 0.0,
+            amount: 100.01,
+        };
+        let error = compute_totals(
+            &[line(1.0, 100.0, 18.0)],
+            discount,
+            TaxRegime::IntraState,
+            RoundingMode::None,
+        )
+        .unwrap_err();
+        assert_eq!(
+            error,
+            AppError::validation(
+                "discount",
+                "Invoice discount cannot exceed the value of the lines"
+            )
+        );
+    }
+
+    #[test]
+    fn invalid_invoice_discounts_are_rejected() {
+        let negative = InvoiceDiscount {
+            percent: 0.0,
+            amount: -1.0,
+        };
+        assert_eq!(
+            negative.resolve(100.0).unwrap_err(),
+            "Invoice discount amount must be a non-negative number"
+        );
+        let too_large = InvoiceDiscount {
+            percent: 120.0,
+            amount: 0.0,
+        };
+        assert_eq!(
+            too_large.resolve(100.0).unwrap_err(),
+            "Invoice discount percentage cannot exceed 100%"
+        );
+    }
+
+    #[test]
+    fn tcs_is_added_before_rounding() {
+        let lines = [line(1.0, 1000.0, 18.0)];
+        let result = totals(&lines, TaxRegime::InterState, RoundingMode::Nearest)
+            .unwrap()
+            .with_tcs(1180.0, 1.18, RoundingMode::Nearest);
+        assert_eq!(result.tcs_amount, 1.18);
+        assert_eq!(result.total_amount, 1181.0);
+        assert_eq!(result.round_off, -0.18);
+    }
+
+    #[test]
+    fn reverse_charge_moves_tax_to_the_recipient() {
+        let lines = [line(1.0, 1000.0, 18.0)];
+        let result = totals(&lines, TaxRegime::IntraState, RoundingMode::None)
+            .unwrap()
+            .with_reverse_charge(RoundingMode::None);
+        assert_eq!(result.reverse_charge_tax, 180.0);
+        assert_eq!(result.cgst_amount + result.sgst_amount + result.igst_amount, 0.0);
+        assert_eq!(result.lines[0].cgst_amount, 0.0);
+        assert_eq!(result.lines[0].total_amount, 1000.0);
+        assert_eq!(result.total_amount, 1000.0);
+    }
+
+    #[test]
+    fn reverse_charge_moves_cess_to_the_recipient() {
+        let mut input = line(1.0, 1000.0, 28.0);
+        input.cess_rate = 12.0;
+        let result = totals(&[input], TaxRegime::InterState, RoundingMode::None)
+            .unwrap()
+            .with_reverse_charge(RoundingMode::None);
+        assert_eq!(result.reverse_charge_tax, 400.0);
+        assert_eq!(result.cess_amount, 0.0);
+        assert_eq!(result.lines[0].cess_amount, 0.0);
+        assert_eq!(result.lines[0].total_amount, 1000.0);
+        assert_eq!(result.total_amount, 1000.0);
+    }
+
+    #[test]
+    fn lut_exports_carry_no_cess() {
+        let mut input = line(1.0, 999.5, 28.0);
+        input.cess_rate = 12.0;
+        let result = totals(&[input], TaxRegime::InterState, RoundingMode::Nearest)
+            .unwrap()
+            .without_gst(RoundingMode::Nearest);
+        assert_eq!(result.igst_amount + result.cess_amount, 0.0);
+        assert_eq!(result.reverse_charge_tax, 0.0);
+        assert_eq!(result.total_amount, 1000.0);
+        assert_eq!(result.round_off, 0.5);
+    }
+}
@@ -0,0 +1,309 @@
+use chrono::{Months, NaiveDate};
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use tauri::State;
+
+use crate::customers;
+use crate::db::{self, DbPool};
+use crate::error::AppError;
+use crate::financial_years::{self, fy_bounds, fy_label};
+use crate::invoices::{round2, Invoice, InvoiceStatus, INVOICE_DATE_FORMAT};
+use crate::pan;
+use crate::place_of_supply::SupplyKind;
+
+// Section 206C(1H): TCS on sales to one buyer above the threshold in a financial year
+const DEFAULT_THRESHOLD: f64 = 5_000_000.0;
+const DEFAULT_RATE: f64 = 0.1;
+// Section 206CC: higher rate when the buyer has no PAN on record
+const DEFAULT_RATE_WITHOUT_PAN: f64 = 1.0;
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct TcsSettings {
+    pub enabled: bool,
+    // Percentages
+    pub rate: f64,
+    pub rate_without_pan: f64,
+    pub threshold: f64,
+}
+
+impl Default for TcsSettings {
+    fn default() -> Self {
+        TcsSettings {
+            enabled: false,
+            rate: DEFAULT_RATE,
+            rate_without_pan: DEFAULT_RATE_WITHOUT_PAN,
+            threshold: DEFAULT_THRESHOLD,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TcsReportRow {
+    pub customer_id: i64,
+    pub customer_name: String,
+    pub pan: Option<String>,
+    pub invoice_count: i64,
+    pub invoice_value: f64,
+    pub tcs_base: f64,
+    pub tcs_amount: f64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TcsReport {
+    pub period: String,
+    pub from_date: String,
+    pub to_date: String,
+    pub rows: Vec<TcsReportRow>,
+    pub total_base: f64,
+    pub total_tcs: f64,
+}
+
+pub fn load_settings(conn: &Connection, company_id: i64) -> Result<TcsSettings, AppError> {
+    let settings = conn
+        .query_row(
+            "SELECT enabled, rate, rate_without_pan, threshold FROM tcs_settings
+             WHERE company_id = ?1",
+            params![company_id],
+            |row| {
+                Ok(TcsSettings {
+                    enabled: row.get(0)?,
+                    rate: row.get(1)?,
+                    rate_without_pan: row.get(2)?,
+                    threshold: row.get(3)?,
+                })
+            },
+        )
+        .optional()?;
+    Ok(settings.unwrap_or_default())
+}
+
+fn validate_settings(settings: &TcsSettings) -> Result<(), AppError> {
+    for (field, label, rate) in [
+        ("rate", "TCS rate", settings.rate),
+        (
+            "rate_without_pan",
+            "TCS rate without PAN",
+            settings.rate_without_pan,
+        ),
+    ] {
+        if !rate.is_finite() || !(0.0..=100.0).contains(&rate) {
+            return Err(AppError::validation(
+                field,
+                format!("{} must be between 0 and 100%", label),
+            ));
+        }
+    }
+    if !settings.threshold.is_finite() || settings.threshold < 0.0 {
+        return Err(AppError::validation(
+            "threshold",
+            "TCS threshold must be a non-negative amount",
+        ));
+    }
+    Ok(())
+}
+
+// The sale whose TCS is being worked out; `id` is None for an invoice not yet saved
+pub struct Sale<'a> {
+    pub company_id: i64,
+    pub customer_id: i64,
+    pub id: Option<i64>,
+    pub invoice_date: &'a str,
+    pub supply_kind: SupplyKind,
+    // Taxable value plus GST and cess
+    pub value: f64,
+}
+
+// Value of the customer's other sales in the financial year that come before this one
+fn prior_sales(
+    conn: &Connection,
+    sale: &Sale,
+    fy_start: NaiveDate,
+    fy_end: NaiveDate,
+) -> Result<f64, AppError> {
+    conn.query_row(
+        "SELECT COALESCE(SUM(taxable_value + cgst_amount + sgst_amount + igst_amount
+                             + cess_amount), 0)
+         FROM invoices
+         WHERE company_id = ?1 AND customer_id = ?2 AND deleted_at IS NULL
+           AND status != 'cancelled' AND supply_kind != 'export'
+           AND invoice_date BETWEEN ?3 AND ?4
+           AND (invoice_date < ?5 OR (invoice_date = ?5 AND id < ?6))",
+        params![
+            sale.company_id,
+            sale.customer_id,
+            fy_start.format(INVOICE_DATE_FORMAT).to_string(),
+            fy_end.format(INVOICE_DATE_FORMAT).to_string(),
+            sale.invoice_date.trim(),
+            sale.id.unwrap_or(i64::MAX)
+        ],
+        |row| row.get(0),
+    )
+    .map_err(AppError::from)
+}
+
+// TCS base and amount on the part of this sale that takes the customer's sales for the
+// financial year past the threshold. Exports carry none.
+pub fn compute_tcs(conn: &Connection, sale: &Sale) -> Result<(f64, f64), String> {
+    let settings = load_settings(conn, sale.company_id)?;
+    if !settings.enabled || sale.supply_kind == SupplyKind::Export {
+        return Ok((0.0, 0.0));
+    }
+    let Ok(date) = NaiveDate::parse_from_str(sale.invoice_date.trim(), INVOICE_DATE_FORMAT) else {
+        return Ok((0.0, 0.0));
+    };
+    let (fy_start, fy_end) = fy_bounds(financial_years::fy_start_year(date))?;
+
+    let value = round2(sale.value);
+    let prior = prior_sales(conn, sale, fy_start, fy_end)?;
+    let base = round2((prior + value - settings.threshold).clamp(0.0, value));
+    if base <= 0.0 {
+        return Ok((0.0, 0.0));
+    }
+
+    let has_pan = customers::get_customer_by_id(conn, sale.customer_id, sale.company_id)?
+        .is_some_and(|customer| pan::extract_pan(&customer.gst_no).is_ok());
+    let rate = if has_pan {
+        settings.rate
+    } else {
+        settings.rate_without_pan
+    };
+    Ok((base, round2(base * rate / 100.0)))
+}
+
+// Sets the invoice's TCS before its total is checked. Cancelled invoices carry none, and later
+// invoices are not revisited when an earlier one changes.
+pub fn apply_tcs(conn: &Connection, invoice: &mut Invoice) -> Result<(), String> {
+    let (tcs_base, tcs_amount) = if invoice.status == InvoiceStatus::Cancelled {
+        (0.0, 0.0)
+    } else {
+        compute_tcs(
+            conn,
+            &Sale {
+                company_id: invoice.company_id,
+                customer_id: invoice.customer_id,
+                id: invoice.id,
+                invoice_date: &invoice.invoice_date,
+                supply_kind: invoice.supply_kind,
+                value: invoice.taxable_value
+                    + invoice.cgst_amount
+                    + invoice.sgst_amount
+                    + invoice.igst_amount
+                    + invoice.cess_amount,
+            },
+        )?
+    };
+    invoice.tcs_base = tcs_base;
+    invoice.tcs_amount = tcs_amount;
+    Ok(())
+}
+
+// Quarter 1 is April to June
+fn quarter_bounds(fy_start_year: i32, quarter: u32) -> Result<(NaiveDate, NaiveDate), AppError> {
+    if !(1..=4).contains(&quarter) {
+        return Err(AppError::validation(
+            "quarter",
+            "Quarter must be between 1 and 4",
+        ));
+    }
+    let (fy_start, _) = fy_bounds(fy_start_year)?;
+    let start = fy_start
+        .checked_add_months(Months::new((quarter - 1) * 3))
+        .ok_or_else(|| AppError::validation("quarter", "Invalid quarter"))?;
+    let end = start
+        .checked_add_months(Months::new(3))
+        .and_then(|next| next.pred_opt())
+        .ok_or_else(|| AppError::validation("quarter", "Invalid quarter"))?;
+    Ok((start, end))
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn get_tcs_settings(
+    pool: State<'_, DbPool>,
+    company_id: i64,
+) -> Result<TcsSettings, AppError> {
+    let conn = db::get_conn(&pool)?;
+    load_settings(&conn, company_id)
+}
+
+// Applies to invoices saved from now on; existing invoices keep the TCS they were saved with
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn set_tcs_settings(
+    pool: State<'_, DbPool>,
+    company_id: i64,
+    settings: TcsSettings,
+) -> Result<TcsSettings, AppError> {
+    validate_settings(&settings)?;
+    let conn = db::get_conn(&pool)?;
+    conn.execute(
+        "INSERT INTO tcs_settings (company_id, enabled, rate, rate_without_pan, threshold)
+         VALUES (?1, ?2, ?3, ?4, ?5)
+         ON CONFLICT(company_id) DO UPDATE SET
+            enabled = ?2, rate = ?3, rate_without_pan = ?4, threshold = ?5,
+            updated_at = CURRENT_TIMESTAMP",
+        params![
+            company_id,
+            settings.enabled,
+            settings.rate,
+            settings.rate_without_pan,
+            settings.threshold
+        ],
+    )?;
+    load_settings(&conn, company_id)
+}
+
+// TCS collected per customer in a quarter, for the quarterly TCS return
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn get_tcs_report(
+    pool: State<'_, DbPool>,
+    company_id: i64,
+    fy_start_year: i32,
+    quarter: u32,
+) -> Result<TcsReport, AppError> {
+    let (from, to) = quarter_bounds(fy_start_year, quarter)?;
+    let conn = db::get_conn(&pool)?;
+    let mut stmt = conn.prepare(
+        "SELECT c.id, c.report_customer, c.gst_no, COUNT(*),
+                    SUM(i.taxable_value + i.cgst_amount + i.sgst_amount + i.igst_amount
+                        + i.cess_amount),
+                    SUM(i.tcs_base), SUM(i.tcs_amount)
+             FROM invoices i
+             JOIN customers c ON c.id = i.customer_id
+             WHERE i.company_id = ?1 AND i.deleted_at IS NULL AND i.status != 'cancelled'
+               AND i.tcs_amount > 0 AND i.invoice_date BETWEEN ?2 AND ?3
+             GROUP BY c.id
+             ORDER BY c.report_customer",
+    )?;
+    let rows = stmt
+        .query_map(
+            params![
+                company_id,
+                from.format(INVOICE_DATE_FORMAT).to_string(),
+                to.format(INVOICE_DATE_FORMAT).to_string()
+            ],
+            |row| {
+                let gst_no: String = row.get(2)?;
+                Ok(TcsReportRow {
+                    customer_id: row.get(0)?,
+                    customer_name: row.get(1)?,
+                    pan: pan::extract_pan(&gst_no).ok(),
+                    invoice_count: row.get(3)?,
+                    invoice_value: round2(row.get(4)?),
+                    tcs_base: round2(row.get(5)?),
+                    tcs_amount: round2(row.get(6)?),
+                })
+            },
+        )?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    Ok(TcsReport {
+        period: format!("Q{} {}", quarter, fy_label(fy_start_year)),
+        from_date: from.format(INVOICE_DATE_FORMAT).to_string(),
+        to_date: to.format(INVOICE_DATE_FORMAT).to_string(),
+        total_base: round2(rows.iter().map(|row| row.tcs_base).sum()),
+        total_tcs: round2(rows.iter().map(|row| row.tcs_amount).sum()),
+        rows,
+    })
+}
@@ -0,0 +1,137 @@
+// The data layer on a private in-memory database, for integration tests. Built with the
+// `test-harness` feature:
+//
+//     let harness = TestHarness::new()?;
+//     let company = block_on(companies::create_company(harness.pool(), company))?;
+//
+// Commands called directly skip the session and permission checks of the invoke handler;
+// `invoke` goes through the same guard the app uses, for the commands in `ipc_commands`.
+// Commands that take an AppHandle need the real runtime and cannot be called here.
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use serde_json::Value;
+use tauri::ipc::{CallbackFn, Invoke, InvokeBody};
+use tauri::test::{
+    get_ipc_response, mock_builder, mock_context, noop_assets, MockRuntime, INVOKE_KEY,
+};
+use tauri::webview::InvokeRequest;
+use tauri::{App, Manager, State, WebviewWindow, WebviewWindowBuilder};
+
+use crate::db;
+use crate::permissions;
+
+pub use tauri::async_runtime::block_on;
+
+pub use crate::db::{DbConn, DbPool};
+pub use crate::error::AppError;
+
+// Modules tests reach commands and request types through
+pub mod categories {
+    pub use crate::categories::*;
+}
+pub mod companies {
+    pub use crate::companies::*;
+}
+pub mod customers {
+    pub use crate::customers::*;
+}
+pub mod invoices {
+    pub use crate::invoices::*;
+}
+pub mod items {
+    pub use crate::items::*;
+}
+pub mod receipts {
+    pub use crate::receipts::*;
+}
+pub mod reports {
+    pub use crate::reports::*;
+}
+pub mod stock {
+    pub use crate::stock::*;
+}
+pub mod sync {
+    pub use crate::sync::*;
+}
+pub mod transactions {
+    pub use crate::transactions::*;
+}
+
+// Each harness gets its own database, so tests can run in parallel
+static NEXT_DATABASE: AtomicUsize = AtomicUsize::new(0);
+
+pub struct TestHarness {
+    app: App<MockRuntime>,
+    webview: WebviewWindow<MockRuntime>,
+}
+
+// Reachable through `TestHarness::invoke`
+fn ipc_commands() -> impl Fn(Invoke<MockRuntime>) -> bool + Send + Sync + 'static {
+    tauri::generate_handler![
+        crate::auth::login,
+        crate::auth::logout,
+        crate::auth::create_user,
+        crate::companies::create_company,
+        crate::companies::list_companies,
+        crate::companies::switch_active_company,
+        crate::customers::create_customer,
+        crate::customers::get_customer,
+        crate::customers::list_customers
+    ]
+}
+
+impl TestHarness {
+    // Migrated, empty database with the state the commands expect already managed
+    pub fn new() -> Result<Self, String> {
+        let name = format!(
+            "test_harness_{}_{}",
+            std::process::id(),
+            NEXT_DATABASE.fetch_add(1, Ordering::Relaxed)
+        );
+        let pool = db::init_memory_pool(&name)?;
+        let app = mock_builder()
+            .invoke_handler(permissions::guard(ipc_commands()))
+            .build(mock_context(noop_assets()))
+            .map_err(|e| e.to_string())?;
+        app.manage(pool);
+        app.manage(crate::dashboard::DashboardCache::default());
+        app.manage(crate::cancellation::Cancellations::default());
+        app.manage(crate::companies::ActiveCompany::default());
+        app.manage(crate::auth::Session::default());
+        let webview = WebviewWindowBuilder::new(&app, "main", Default::default())
+            .build()
+            .map_err(|e| e.to_string())?;
+        Ok(TestHarness { app, webview })
+    }
+
+    // Calls a command as the frontend would, with camelCase argument names. Errors are the
+    // serialized rejection, e.g. `{ "code": "forbidden", "message": ... }`.
+    pub fn invoke(&self, command: &str, args: Value) -> Result<Value, Value> {
+        let request = InvokeRequest {
+            cmd: command.to_string(),
+            callback: CallbackFn(0),
+            error: CallbackFn(1),
+            url: "http://tauri.localhost".parse().map_err(|_| Value::Null)?,
+            body: InvokeBody::Json(args),
+            headers: Default::default(),
+            invoke_key: INVOKE_KEY.to_string(),
+        };
+        get_ipc_response(&self.webview, request)
+            .and_then(|body| body.deserialize().map_err(|e| Value::String(e.to_string())))
+    }
+
+    pub fn pool(&self) -> State<'_, DbPool> {
+        self.app.state::<DbPool>()
+    }
+
+    // Any other managed state a command takes, e.g. `Cancellations`
+    pub fn state<T: Send + Sync + 'static>(&self) -> State<'_, T> {
+        self.app.state::<T>()
+    }
+
+    // For setting up rows no command creates, or checking what a command wrote
+    pub fn conn(&self) -> Result<DbConn, AppError> {
+        db::get_conn(&self.pool())
+    }
+}
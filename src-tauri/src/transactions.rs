@@ -0,0 +1,234 @@
+use rusqlite::{Connection, Transaction};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tauri::State;
+
+use crate::customers::{self, CreateCustomer};
+use crate::db::{self, DbPool};
+use crate::error::AppError;
+use crate::invoices::{self, CreateInvoice};
+use crate::receipts::{self, CreateReceipt};
+use crate::stock::{self, SaveStockAdjustment};
+
+const MAX_STEPS: usize = 200;
+
+// Steps are applied one after another, so one savepoint name serves them all
+const STEP_SAVEPOINT: &str = "transaction_step";
+
+// Runs `work` in a transaction that commits only when it succeeds. Anything it did is rolled
+// back on error, so several helpers can be combined without leaving a half-created document.
+pub fn atomically<T, E: From<AppError>>(
+    conn: &mut Connection,
+    work: impl FnOnce(&Transaction<'_>) -> Result<T, E>,
+) -> Result<T, E> {
+    let tx = conn.transaction().map_err(AppError::from)?;
+    let value = work(&tx)?;
+    tx.commit().map_err(AppError::from)?;
+    Ok(value)
+}
+
+// Runs `work` under a savepoint of the open transaction on `conn`. A failure undoes only what
+// `work` did and is returned; the transaction stays usable.
+pub fn with_savepoint<T, E: From<AppError>>(
+    conn: &Connection,
+    name: &str,
+    work: impl FnOnce() -> Result<T, E>,
+) -> Result<T, E> {
+    conn.execute_batch(&format!("SAVEPOINT {}", name))
+        .map_err(AppError::from)?;
+    match work() {
+        Ok(value) => {
+            conn.execute_batch(&format!("RELEASE {}", name))
+                .map_err(AppError::from)?;
+            Ok(value)
+        }
+        Err(e) => {
+            conn.execute_batch(&format!("ROLLBACK TO {0}; RELEASE {0}", name))
+                .map_err(AppError::from)?;
+            Err(e)
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum StepAction {
+    CreateCustomer { customer: CreateCustomer },
+    CreateInvoice { invoice: CreateInvoice },
+    RecordReceipt { receipt: CreateReceipt },
+    StockAdjustment { adjustment: SaveStockAdjustment },
+}
+
+impl StepAction {
+    fn kind(&self) -> &'static str {
+        match self {
+            StepAction::CreateCustomer { .. } => "create_customer",
+            StepAction::CreateInvoice { .. } => "create_invoice",
+            StepAction::RecordReceipt { .. } => "record_receipt",
+            StepAction::StockAdjustment { .. } => "stock_adjustment",
+        }
+    }
+}
+
+// One step of `run_transaction`. Any value in it can be `{ "$ref": n }`, replaced by the id
+// created by step n (counted from 0), e.g. to invoice a customer created earlier in the batch.
+#[derive(Debug, Deserialize)]
+pub struct TransactionStep {
+    #[serde(flatten)]
+    pub action: StepAction,
+    // A failing optional step is undone and reported instead of failing the whole transaction
+    #[serde(default)]
+    pub optional: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct StepOutcome {
+    pub index: usize,
+    pub kind: String,
+    pub id: Option<i64>,
+    pub warnings: Vec<String>,
+    // The created record, as the single-record command returns it
+    pub record: Option<Value>,
+    // Set for an optional step that failed and was skipped
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TransactionResult {
+    pub steps: Vec<StepOutcome>,
+}
+
+fn resolve_refs(value: &mut Value, ids: &[Option<i64>]) -> Result<(), AppError> {
+    match value {
+        Value::Object(map) => {
+            if let (1, Some(reference)) = (map.len(), map.get("$ref")) {
+                let step = reference
+                    .as_u64()
+                    .map(|step| step as usize)
+                    .ok_or_else(|| {
+                        AppError::invalid("A $ref must be the number of an earlier step")
+                    })?;
+                let id = match ids.get(step) {
+                    Some(Some(id)) => *id,
+                    Some(None) => {
+                        return Err(AppError::invalid(format!(
+                            "Step {} was skipped, so its id cannot be used",
+                            step
+                        )))
+                    }
+                    None => {
+                        return Err(AppError::invalid(format!("Step {} has not run yet", step)))
+                    }
+                };
+                *value = Value::from(id);
+                return Ok(());
+            }
+            map.values_mut()
+                .try_for_each(|nested| resolve_refs(nested, ids))
+        }
+        Value::Array(values) => values
+            .iter_mut()
+            .try_for_each(|nested| resolve_refs(nested, ids)),
+        _ => Ok(()),
+    }
+}
+
+// Later steps refer to this id, so a saved record without one is a failure, not a 0
+fn saved_id(id: Option<i64>, record: &str) -> Result<i64, AppError> {
+    id.ok_or_else(|| AppError::internal(format!("The {} was saved without an id", record)))
+}
+
+fn to_record<T: Serialize>(record: &T) -> Result<Value, String> {
+    serde_json::to_value(record).map_err(|e| e.to_string())
+}
+
+// Every step is scoped to the company the transaction runs for, whatever the step says
+fn apply_step(
+    conn: &Connection,
+    company_id: i64,
+    action: StepAction,
+) -> Result<(i64, Vec<String>, Value), AppError> {
+    match action {
+        StepAction::CreateCustomer { mut customer } => {
+            customer.company_id = company_id;
+            let created = customers::create_customer_in(conn, customer, None)?;
+            Ok((saved_id(created.id, "customer")?, Vec::new(), to_record(&created)?))
+        }
+        StepAction::CreateInvoice { mut invoice } => {
+            invoice.company_id = company_id;
+            let saved = invoices::create_invoice_in(conn, invoice)?;
+            let record = to_record(&saved)?;
+            Ok((saved_id(saved.invoice.id, "invoice")?, saved.warnings, record))
+        }
+        StepAction::RecordReceipt { mut receipt } => {
+            receipt.company_id = company_id;
+            let created = receipts::record_receipt_in(conn, &receipt)?;
+            Ok((saved_id(created.receipt.id, "receipt")?, Vec::new(), to_record(&created)?))
+        }
+        StepAction::StockAdjustment { adjustment } => {
+            let created = stock::create_stock_adjustment_in(conn, company_id, &adjustment)?;
+            Ok((created.id, Vec::new(), to_record(&created)?))
+        }
+    }
+}
+
+// Applies `steps` in order inside one transaction, each under its own savepoint. Numbering,
+// stock, audit and webhook rows written by a step are part of it, so either the whole batch is
+// committed or, when a required step fails, nothing is.
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn run_transaction(
+    pool: State<'_, DbPool>,
+    company_id: i64,
+    steps: Vec<Value>,
+) -> Result<TransactionResult, AppError> {
+    if steps.is_empty() || steps.len() > MAX_STEPS {
+        return Err(AppError::validation(
+            "steps",
+            format!("A transaction takes 1 to {} steps", MAX_STEPS),
+        ));
+    }
+
+    let mut conn = db::get_conn(&pool)?;
+    atomically(&mut conn, |tx| {
+        let mut ids = Vec::with_capacity(steps.len());
+        let mut outcomes = Vec::with_capacity(steps.len());
+        for (index, mut raw) in steps.into_iter().enumerate() {
+            let context = format!("Step {}: ", index);
+            resolve_refs(&mut raw, &ids).map_err(|e| e.prefixed(&context))?;
+            let step: TransactionStep = serde_json::from_value(raw)
+                .map_err(|e| AppError::validation("steps", format!("{}{}", context, e)))?;
+            let kind = step.action.kind().to_string();
+
+            let applied = with_savepoint(tx, STEP_SAVEPOINT, || {
+                apply_step(tx, company_id, step.action)
+            });
+            match applied {
+                Ok((id, warnings, record)) => {
+                    ids.push(Some(id));
+                    outcomes.push(StepOutcome {
+                        index,
+                        kind,
+                        id: Some(id),
+                        warnings,
+                        record: Some(record),
+                        error: None,
+                    });
+                }
+                Err(e) if step.optional => {
+                    ids.push(None);
+                    outcomes.push(StepOutcome {
+                        index,
+                        kind,
+                        id: None,
+                        warnings: Vec::new(),
+                        record: None,
+                        error: Some(e.to_string()),
+                    });
+                }
+                Err(e) => return Err(e.prefixed(&context)),
+            }
+        }
+        Ok(TransactionResult { steps: outcomes })
+    })
+}
@@ -0,0 +1,363 @@
+use std::collections::HashMap;
+
+use rusqlite::{params, Connection, OptionalExtension, Row};
+use serde::{Deserialize, Serialize};
+use tauri::State;
+
+use crate::db::{self, DbPool};
+use crate::error::AppError;
+use crate::invoices::InvoiceLineInput;
+
+const MAX_DECIMAL_PLACES: u32 = 3;
+
+// Unit Quantity Codes accepted by the GST portal in the HSN summary and e-invoices
+pub const UQC_CODES: [(&str, &str); 45] = [
+    ("BAG", "Bags"),
+    ("BAL", "Bale"),
+    ("BDL", "Bundles"),
+    ("BKL", "Buckles"),
+    ("BOU", "Billion of units"),
+    ("BOX", "Box"),
+    ("BTL", "Bottles"),
+    ("BUN", "Bunches"),
+    ("CAN", "Cans"),
+    ("CBM", "Cubic meters"),
+    ("CCM", "Cubic centimeters"),
+    ("CMS", "Centimeters"),
+    ("CTN", "Cartons"),
+    ("DOZ", "Dozens"),
+    ("DRM", "Drums"),
+    ("GGK", "Great gross"),
+    ("GMS", "Grammes"),
+    ("GRS", "Gross"),
+    ("GYD", "Gross yards"),
+    ("KGS", "Kilograms"),
+    ("KLR", "Kilolitre"),
+    ("KME", "Kilometre"),
+    ("LTR", "Litres"),
+    ("MLT", "Millilitre"),
+    ("MTR", "Meters"),
+    ("MTS", "Metric ton"),
+    ("NOS", "Numbers"),
+    ("OTH", "Others"),
+    ("PAC", "Packs"),
+    ("PCS", "Pieces"),
+    ("PRS", "Pairs"),
+    ("QTL", "Quintal"),
+    ("ROL", "Rolls"),
+    ("SET", "Sets"),
+    ("SQF", "Square feet"),
+    ("SQM", "Square meters"),
+    ("SQY", "Square yards"),
+    ("TBS", "Tablets"),
+    ("TGM", "Ten gross"),
+    ("THD", "Thousands"),
+    ("TON", "Tonnes"),
+    ("TUB", "Tubes"),
+    ("UGS", "US gallons"),
+    ("UNT", "Units"),
+    ("YDS", "Yards"),
+];
+
+// Unit of measure data model. A unit with a base unit converts to it by `conversion_factor`,
+// e.g. BOX with base NOS and factor 12; the HSN summary reports quantities in the base unit.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Unit {
+    pub id: i64,
+    pub code: String,
+    pub name: String,
+    pub uqc: String,
+    pub decimal_places: u32,
+    pub base_unit_id: Option<i64>,
+    pub conversion_factor: f64,
+    pub active: bool,
+    pub created_at: Option<String>,
+    pub updated_at: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SaveUnit {
+    pub code: String,
+    pub name: String,
+    pub uqc: String,
+    pub decimal_places: u32,
+    #[serde(default)]
+    pub base_unit_id: Option<i64>,
+    #[serde(default)]
+    pub conversion_factor: Option<f64>,
+    #[serde(default = "default_active")]
+    pub active: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct UqcCode {
+    pub code: String,
+    pub description: String,
+}
+
+// The code and quantity a line is reported with in GST returns
+#[derive(Debug, Clone)]
+pub struct ReportingUnit {
+    pub uqc: String,
+    pub factor: f64,
+}
+
+fn default_active() -> bool {
+    true
+}
+
+const SELECT_UNIT: &str = "
+    SELECT id, code, name, uqc, decimal_places, base_unit_id, conversion_factor, active,
+           created_at, updated_at
+    FROM units";
+
+fn unit_from_row(row: &Row) -> rusqlite::Result<Unit> {
+    Ok(Unit {
+        id: row.get("id")?,
+        code: row.get("code")?,
+        name: row.get("name")?,
+        uqc: row.get("uqc")?,
+        decimal_places: row.get("decimal_places")?,
+        base_unit_id: row.get("base_unit_id")?,
+        conversion_factor: row.get("conversion_factor")?,
+        active: row.get("active")?,
+        created_at: row.get("created_at")?,
+        updated_at: row.get("updated_at")?,
+    })
+}
+
+pub fn is_valid_uqc(code: &str) -> bool {
+    UQC_CODES.iter().any(|(uqc, _)| *uqc == code)
+}
+
+pub fn get_unit_by_id(conn: &Connection, id: i64) -> Result<Option<Unit>, AppError> {
+    conn.query_row(&format!("{} WHERE id = ?1", SELECT_UNIT), params![id], unit_from_row)
+        .optional()
+        .map_err(AppError::from)
+}
+
+// Resolves each unit to the UQC and factor its quantities are reported with
+pub fn reporting_units(conn: &Connection) -> Result<HashMap<i64, ReportingUnit>, AppError> {
+    let mut stmt = conn.prepare(
+        "SELECT u.id, COALESCE(b.uqc, u.uqc),
+                    CASE WHEN b.id IS NULL THEN 1 ELSE u.conversion_factor END
+             FROM units u
+             LEFT JOIN units b ON b.id = u.base_unit_id",
+    )?;
+    let units = stmt
+        .query_map([], |row| {
+            Ok((row.get(0)?, ReportingUnit { uqc: row.get(1)?, factor: row.get(2)? }))
+        })?
+        .collect::<Result<HashMap<_, _>, _>>()?;
+    Ok(units)
+}
+
+// Each line's unit must be active and its quantity within the unit's precision
+pub fn validate_line_units(conn: &Connection, lines: &[InvoiceLineInput]) -> Result<(), AppError> {
+    for (index, line) in lines.iter().enumerate() {
+        let Some(unit_id) = line.unit_id else {
+            continue;
+        };
+        let unit = get_unit_by_id(conn, unit_id)?.filter(|unit| unit.active).ok_or_else(|| {
+            AppError::validation("lines", format!("Line {}: unit of measure not found", index + 1))
+        })?;
+        let scale = 10f64.powi(unit.decimal_places as i32);
+        if ((line.quantity * scale).round() - line.quantity * scale).abs() > 1e-6 {
+            return Err(AppError::validation(
+                "lines",
+                format!(
+                    "Line {}: quantities in {} can have at most {} decimal places",
+                    index + 1,
+                    unit.code,
+                    unit.decimal_places
+                ),
+            ));
+        }
+    }
+    Ok(())
+}
+
+fn map_write_error(e: rusqlite::Error) -> AppError {
+    let message = e.to_string();
+    if message.contains("UNIQUE constraint failed: units") {
+        return AppError::conflict("code", "A unit with this code already exists");
+    }
+    AppError::from(e)
+}
+
+// Returns the unit with its code upper-cased and the conversion settled
+fn validate(conn: &Connection, id: Option<i64>, unit: SaveUnit) -> Result<SaveUnit, AppError> {
+    let code = unit.code.trim().to_ascii_uppercase();
+    if code.is_empty() || code.len() > 10 {
+        return Err(AppError::validation("code", "Code must be 1 to 10 characters"));
+    }
+    let name = unit.name.trim().to_string();
+    if name.is_empty() || name.len() > 100 {
+        return Err(AppError::validation("name", "Name must be 1 to 100 characters"));
+    }
+    let uqc = unit.uqc.trim().to_ascii_uppercase();
+    if !is_valid_uqc(&uqc) {
+        return Err(AppError::validation("uqc", format!("{} is not a GST UQC code", uqc)));
+    }
+    if unit.decimal_places > MAX_DECIMAL_PLACES {
+        return Err(AppError::validation(
+            "decimal_places",
+            format!("Use at most {} decimal places", MAX_DECIMAL_PLACES),
+        ));
+    }
+
+    let conversion_factor = match unit.base_unit_id {
+        Some(base_id) => {
+            if Some(base_id) == id {
+                return Err(AppError::validation("base_unit_id", "A unit cannot be its own base"));
+            }
+            let base = get_unit_by_id(conn, base_id)?
+                .ok_or_else(|| AppError::not_found("Base unit not found"))?;
+            // One level only, so every unit converts straight to the unit it is reported in
+            if base.base_unit_id.is_some() {
+                return Err(AppError::validation(
+                    "base_unit_id",
+                    format!("{} converts to another unit and cannot be a base unit", base.code),
+                ));
+            }
+            let factor = unit.conversion_factor.unwrap_or(0.0);
+            if !factor.is_finite() || factor <= 0.0 {
+                return Err(AppError::validation(
+                    "conversion_factor",
+                    "Conversion factor must be greater than zero",
+                ));
+            }
+            factor
+        }
+        None => 1.0,
+    };
+    if let Some(id) = id {
+        let has_derived: bool = conn.query_row(
+            "SELECT EXISTS(SELECT 1 FROM units WHERE base_unit_id = ?1)",
+            params![id],
+            |row| row.get(0),
+        )?;
+        if has_derived && unit.base_unit_id.is_some() {
+            return Err(AppError::validation(
+                "base_unit_id",
+                "Other units convert to this one, so it cannot have a base unit",
+            ));
+        }
+    }
+
+    Ok(SaveUnit {
+        code,
+        name,
+        uqc,
+        decimal_places: unit.decimal_places,
+        base_unit_id: unit.base_unit_id,
+        conversion_factor: Some(conversion_factor),
+        active: unit.active,
+    })
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn list_uqc_codes() -> Result<Vec<UqcCode>, AppError> {
+    Ok(UQC_CODES
+        .iter()
+        .map(|(code, description)| UqcCode {
+            code: code.to_string(),
+            description: description.to_string(),
+        })
+        .collect())
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn list_units(
+    pool: State<'_, DbPool>,
+    include_inactive: Option<bool>,
+) -> Result<Vec<Unit>, AppError> {
+    let conn = db::get_conn(&pool)?;
+    let mut stmt =
+        conn.prepare(&format!("{} WHERE ?1 = 1 OR active = 1 ORDER BY code", SELECT_UNIT))?;
+    let units = stmt
+        .query_map(params![include_inactive.unwrap_or(false)], unit_from_row)?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(units)
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn create_unit(pool: State<'_, DbPool>, unit: SaveUnit) -> Result<Unit, AppError> {
+    let conn = db::get_conn(&pool)?;
+    let unit = validate(&conn, None, unit)?;
+    conn.execute(
+        "INSERT INTO units (code, name, uqc, decimal_places, base_unit_id, conversion_factor,
+                            active)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+        params![
+            unit.code,
+            unit.name,
+            unit.uqc,
+            unit.decimal_places,
+            unit.base_unit_id,
+            unit.conversion_factor,
+            unit.active,
+        ],
+    )
+    .map_err(map_write_error)?;
+    get_unit_by_id(&conn, conn.last_insert_rowid())?
+        .ok_or_else(|| AppError::not_found("Unit not found after creation"))
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn update_unit(
+    pool: State<'_, DbPool>,
+    id: i64,
+    unit: SaveUnit,
+) -> Result<Unit, AppError> {
+    let conn = db::get_conn(&pool)?;
+    if get_unit_by_id(&conn, id)?.is_none() {
+        return Err(AppError::not_found("Unit not found"));
+    }
+    let unit = validate(&conn, Some(id), unit)?;
+    conn.execute(
+        "UPDATE units SET code = ?1, name = ?2, uqc = ?3, decimal_places = ?4, base_unit_id = ?5,
+                          conversion_factor = ?6, active = ?7, updated_at = CURRENT_TIMESTAMP
+         WHERE id = ?8",
+        params![
+            unit.code,
+            unit.name,
+            unit.uqc,
+            unit.decimal_places,
+            unit.base_unit_id,
+            unit.conversion_factor,
+            unit.active,
+            id,
+        ],
+    )
+    .map_err(map_write_error)?;
+    get_unit_by_id(&conn, id)?.ok_or_else(|| AppError::not_found("Unit not found"))
+}
+
+// Units used on invoice lines or as a base unit are kept; deactivate them instead
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn delete_unit(pool: State<'_, DbPool>, id: i64) -> Result<(), AppError> {
+    let conn = db::get_conn(&pool)?;
+    let in_use: bool = conn.query_row(
+        "SELECT EXISTS(SELECT 1 FROM invoice_lines WHERE unit_id = ?1)
+                 OR EXISTS(SELECT 1 FROM units WHERE base_unit_id = ?1)",
+        params![id],
+        |row| row.get(0),
+    )?;
+    if in_use {
+        return Err(AppError::conflict(
+            "id",
+            "This unit is in use; deactivate it instead of deleting it",
+        ));
+    }
+    let deleted = conn.execute("DELETE FROM units WHERE id = ?1", params![id])?;
+    if deleted == 0 {
+        return Err(AppError::not_found("Unit not found"));
+    }
+    Ok(())
+}
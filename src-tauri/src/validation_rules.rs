@@ -0,0 +1,513 @@
+use rusqlite::{params, Connection, OptionalExtension, Row};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tauri::State;
+
+use crate::db::{self, DbPool};
+use crate::error::AppError;
+
+// Fields rules can test, named as in the record the entity builds for the engine
+const CUSTOMER_FIELDS: &[&str] = &[
+    "report_customer",
+    "tally_customer",
+    "gst_no",
+    "registration_type",
+    "state_code",
+    "category",
+    "address",
+    "city",
+    "pincode",
+];
+const INVOICE_FIELDS: &[&str] = &[
+    "invoice_number",
+    "invoice_date",
+    "place_of_supply",
+    "supply_kind",
+    "reverse_charge",
+    "taxable_value",
+    "discount_amount",
+    "total_amount",
+    "notes",
+    "customer_gstin",
+    "customer_category",
+    "customer_registration_type",
+];
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum RuleEntity {
+    Customer,
+    Invoice,
+}
+
+impl RuleEntity {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RuleEntity::Customer => "customer",
+            RuleEntity::Invoice => "invoice",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "customer" => Some(RuleEntity::Customer),
+            "invoice" => Some(RuleEntity::Invoice),
+            _ => None,
+        }
+    }
+
+    fn fields(&self) -> &'static [&'static str] {
+        match self {
+            RuleEntity::Customer => CUSTOMER_FIELDS,
+            RuleEntity::Invoice => INVOICE_FIELDS,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum RuleType {
+    Required,
+    MaxLength,
+    MinValue,
+    MaxValue,
+    // Parameter is a comma-separated list of allowed values
+    OneOf,
+}
+
+impl RuleType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RuleType::Required => "required",
+            RuleType::MaxLength => "max_length",
+            RuleType::MinValue => "min_value",
+            RuleType::MaxValue => "max_value",
+            RuleType::OneOf => "one_of",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "required" => Some(RuleType::Required),
+            "max_length" => Some(RuleType::MaxLength),
+            "min_value" => Some(RuleType::MinValue),
+            "max_value" => Some(RuleType::MaxValue),
+            "one_of" => Some(RuleType::OneOf),
+            _ => None,
+        }
+    }
+}
+
+// Errors block the save; warnings are returned with saved invoices and do not apply to
+// customers, whose saves have no way to report them
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+impl Severity {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "error" => Some(Severity::Error),
+            "warning" => Some(Severity::Warning),
+            _ => None,
+        }
+    }
+}
+
+// Validation rule data model. Statutory checks (GSTIN format, tax split, periods) stay in code;
+// rules add the company's own requirements on top of them.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ValidationRule {
+    pub id: Option<i64>,
+    pub company_id: i64,
+    pub entity: RuleEntity,
+    pub field: String,
+    pub rule_type: RuleType,
+    pub parameter: Option<String>,
+    // The rule only applies when this field equals one of the comma-separated values
+    pub condition_field: Option<String>,
+    pub condition_value: Option<String>,
+    pub severity: Severity,
+    // Shown instead of the generated message
+    pub message: Option<String>,
+    pub enabled: bool,
+    pub created_at: Option<String>,
+    pub updated_at: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SaveValidationRule {
+    pub entity: RuleEntity,
+    pub field: String,
+    pub rule_type: RuleType,
+    pub parameter: Option<String>,
+    pub condition_field: Option<String>,
+    pub condition_value: Option<String>,
+    pub severity: Severity,
+    pub message: Option<String>,
+    #[serde(default = "enabled_by_default")]
+    pub enabled: bool,
+}
+
+fn enabled_by_default() -> bool {
+    true
+}
+
+const SELECT_RULE: &str = "
+    SELECT id, company_id, entity, field, rule_type, parameter, condition_field, condition_value,
+           severity, message, enabled, created_at, updated_at
+    FROM validation_rules";
+
+fn rule_from_row(row: &Row) -> rusqlite::Result<ValidationRule> {
+    let entity: String = row.get("entity")?;
+    let rule_type: String = row.get("rule_type")?;
+    let severity: String = row.get("severity")?;
+    Ok(ValidationRule {
+        id: row.get("id")?,
+        company_id: row.get("company_id")?,
+        entity: RuleEntity::parse(&entity).unwrap_or(RuleEntity::Invoice),
+        field: row.get("field")?,
+        rule_type: RuleType::parse(&rule_type).unwrap_or(RuleType::Required),
+        parameter: row.get("parameter")?,
+        condition_field: row.get("condition_field")?,
+        condition_value: row.get("condition_value")?,
+        severity: Severity::parse(&severity).unwrap_or(Severity::Error),
+        message: row.get("message")?,
+        enabled: row.get("enabled")?,
+        created_at: row.get("created_at")?,
+        updated_at: row.get("updated_at")?,
+    })
+}
+
+pub fn get_rule_by_id(
+    conn: &Connection,
+    id: i64,
+    company_id: i64,
+) -> Result<Option<ValidationRule>, AppError> {
+    conn.query_row(
+        &format!("{} WHERE id = ?1 AND company_id = ?2", SELECT_RULE),
+        params![id, company_id],
+        rule_from_row,
+    )
+    .optional()
+    .map_err(AppError::from)
+}
+
+fn get_rules(
+    conn: &Connection,
+    company_id: i64,
+    entity: Option<RuleEntity>,
+) -> Result<Vec<ValidationRule>, AppError> {
+    let mut stmt = conn.prepare(&format!(
+        "{} WHERE company_id = ?1 AND (?2 IS NULL OR entity = ?2) ORDER BY entity, field, id",
+        SELECT_RULE
+    ))?;
+    let rules = stmt
+        .query_map(
+            params![company_id, entity.map(|entity| entity.as_str())],
+            rule_from_row,
+        )?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(rules)
+}
+
+// Renders a record field the way rules compare it; missing and null fields are blank
+fn field_text(record: &Value, field: &str) -> String {
+    match record.get(field) {
+        Some(Value::String(text)) => text.trim().to_string(),
+        Some(Value::Number(number)) => number.to_string(),
+        Some(Value::Bool(flag)) => flag.to_string(),
+        _ => String::new(),
+    }
+}
+
+fn list_contains(list: &str, value: &str) -> bool {
+    list.split(',')
+        .map(str::trim)
+        .any(|item| item.eq_ignore_ascii_case(value))
+}
+
+fn number(parameter: Option<&str>) -> Option<f64> {
+    parameter
+        .and_then(|p| p.trim().parse::<f64>().ok())
+        .filter(|n| n.is_finite())
+}
+
+fn applies(rule: &ValidationRule, record: &Value) -> bool {
+    match (&rule.condition_field, &rule.condition_value) {
+        (Some(field), Some(values)) => list_contains(values, &field_text(record, field)),
+        _ => true,
+    }
+}
+
+// The rule's message when the record breaks it
+fn violation(rule: &ValidationRule, record: &Value) -> Option<String> {
+    let value = field_text(record, &rule.field);
+    let parameter = rule.parameter.as_deref();
+    let broken = match rule.rule_type {
+        RuleType::Required => value.is_empty(),
+        // Blank values are left to a required rule
+        _ if value.is_empty() => false,
+        RuleType::MaxLength => number(parameter)
+            .is_some_and(|limit| value.chars().count() as f64 > limit),
+        RuleType::MinValue => match (value.parse::<f64>(), number(parameter)) {
+            (Ok(value), Some(limit)) => value < limit,
+            _ => false,
+        },
+        RuleType::MaxValue => match (value.parse::<f64>(), number(parameter)) {
+            (Ok(value), Some(limit)) => value > limit,
+            _ => false,
+        },
+        RuleType::OneOf => !list_contains(parameter.unwrap_or(""), &value),
+    };
+    if !broken {
+        return None;
+    }
+    let message = rule.message.as_deref().map(str::trim).filter(|m| !m.is_empty());
+    Some(match message {
+        Some(message) => message.to_string(),
+        None => {
+            let parameter = parameter.unwrap_or("").trim();
+            match rule.rule_type {
+                RuleType::Required => format!("{} is required", rule.field),
+                RuleType::MaxLength => {
+                    format!("{} must be {} characters or less", rule.field, parameter)
+                }
+                RuleType::MinValue => format!("{} must be at least {}", rule.field, parameter),
+                RuleType::MaxValue => format!("{} cannot exceed {}", rule.field, parameter),
+                RuleType::OneOf => format!("{} must be one of: {}", rule.field, parameter),
+            }
+        }
+    })
+}
+
+// Runs the company's enabled rules for the entity. Any broken error rule fails the save with
+// every error message; broken warning rules are returned.
+pub fn enforce(
+    conn: &Connection,
+    company_id: i64,
+    entity: RuleEntity,
+    record: &Value,
+) -> Result<Vec<String>, AppError> {
+    let mut errors = Vec::new();
+    let mut warnings = Vec::new();
+    for rule in get_rules(conn, company_id, Some(entity))? {
+        if !rule.enabled || !applies(&rule, record) {
+            continue;
+        }
+        if let Some(message) = violation(&rule, record) {
+            match rule.severity {
+                Severity::Error => errors.push((rule.field, message)),
+                Severity::Warning => warnings.push(message),
+            }
+        }
+    }
+    match errors.as_slice() {
+        [] => Ok(warnings),
+        [(field, message)] => Err(AppError::validation(field, message.as_str())),
+        // Broken rules on several fields are reported together, without a field
+        _ => Err(AppError::invalid(
+            errors
+                .iter()
+                .map(|(_, message)| message.as_str())
+                .collect::<Vec<_>>()
+                .join("; "),
+        )),
+    }
+}
+
+fn normalize_rule(rule: &mut SaveValidationRule) {
+    let text = |value: &Option<String>| {
+        value
+            .as_deref()
+            .map(str::trim)
+            .filter(|v| !v.is_empty())
+            .map(str::to_string)
+    };
+    rule.field = rule.field.trim().to_string();
+    rule.parameter = text(&rule.parameter);
+    rule.condition_field = text(&rule.condition_field);
+    rule.condition_value = text(&rule.condition_value);
+    rule.message = text(&rule.message);
+}
+
+fn validate_rule(rule: &SaveValidationRule) -> Result<(), AppError> {
+    let fields = rule.entity.fields();
+    if !fields.contains(&rule.field.as_str()) {
+        return Err(AppError::validation(
+            "field",
+            format!(
+                "Unknown {} field '{}'; use one of: {}",
+                rule.entity.as_str(),
+                rule.field,
+                fields.join(", ")
+            ),
+        ));
+    }
+    let parameter = rule.parameter.as_deref();
+    match rule.rule_type {
+        RuleType::Required => {}
+        RuleType::MaxLength => {
+            if parameter.and_then(|p| p.parse::<u32>().ok()).is_none_or(|n| n == 0) {
+                return Err(AppError::validation(
+                    "parameter",
+                    "Maximum length must be a whole number above zero",
+                ));
+            }
+        }
+        RuleType::MinValue | RuleType::MaxValue => {
+            if number(parameter).is_none() {
+                return Err(AppError::validation(
+                    "parameter",
+                    "The limit must be a number",
+                ));
+            }
+        }
+        RuleType::OneOf => {
+            if parameter
+                .unwrap_or("")
+                .split(',')
+                .all(|v| v.trim().is_empty())
+            {
+                return Err(AppError::validation(
+                    "parameter",
+                    "List the allowed values, separated by commas",
+                ));
+            }
+        }
+    }
+    match (&rule.condition_field, &rule.condition_value) {
+        (None, None) => {}
+        (Some(field), Some(_)) if fields.contains(&field.as_str()) => {}
+        (Some(field), Some(_)) => {
+            return Err(AppError::validation(
+                "condition_field",
+                format!("Unknown condition field '{}'", field),
+            ));
+        }
+        (Some(_), None) => {
+            return Err(AppError::validation(
+                "condition_value",
+                "A condition needs both a field and the values it matches",
+            ));
+        }
+        (None, Some(_)) => {
+            return Err(AppError::validation(
+                "condition_field",
+                "A condition needs both a field and the values it matches",
+            ));
+        }
+    }
+    if rule.message.as_ref().is_some_and(|m| m.len() > 200) {
+        return Err(AppError::validation(
+            "message",
+            "Message must be 200 characters or less",
+        ));
+    }
+    Ok(())
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn list_validation_rules(
+    pool: State<'_, DbPool>,
+    company_id: i64,
+    entity: Option<RuleEntity>,
+) -> Result<Vec<ValidationRule>, AppError> {
+    let conn = db::get_conn(&pool)?;
+    get_rules(&conn, company_id, entity)
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn create_validation_rule(
+    pool: State<'_, DbPool>,
+    company_id: i64,
+    mut rule: SaveValidationRule,
+) -> Result<ValidationRule, AppError> {
+    normalize_rule(&mut rule);
+    validate_rule(&rule)?;
+    let conn = db::get_conn(&pool)?;
+    conn.execute(
+        "INSERT INTO validation_rules (company_id, entity, field, rule_type, parameter, condition_field, condition_value, severity, message, enabled)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+        params![
+            company_id,
+            rule.entity.as_str(),
+            rule.field,
+            rule.rule_type.as_str(),
+            rule.parameter,
+            rule.condition_field,
+            rule.condition_value,
+            rule.severity.as_str(),
+            rule.message,
+            rule.enabled
+        ],
+    )?;
+    get_rule_by_id(&conn, conn.last_insert_rowid(), company_id)?
+        .ok_or_else(|| AppError::not_found("Rule not found after creation"))
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn update_validation_rule(
+    pool: State<'_, DbPool>,
+    id: i64,
+    company_id: i64,
+    mut rule: SaveValidationRule,
+) -> Result<ValidationRule, AppError> {
+    normalize_rule(&mut rule);
+    validate_rule(&rule)?;
+    let conn = db::get_conn(&pool)?;
+    let changed = conn
+        .execute(
+            "UPDATE validation_rules SET entity = ?1, field = ?2, rule_type = ?3, parameter = ?4, condition_field = ?5, condition_value = ?6, severity = ?7, message = ?8, enabled = ?9, updated_at = CURRENT_TIMESTAMP
+             WHERE id = ?10 AND company_id = ?11",
+            params![
+                rule.entity.as_str(),
+                rule.field,
+                rule.rule_type.as_str(),
+                rule.parameter,
+                rule.condition_field,
+                rule.condition_value,
+                rule.severity.as_str(),
+                rule.message,
+                rule.enabled,
+                id,
+                company_id
+            ],
+        )?;
+    if changed == 0 {
+        return Err(AppError::not_found("Rule not found"));
+    }
+    get_rule_by_id(&conn, id, company_id)?
+        .ok_or_else(|| AppError::not_found("Rule not found after update"))
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn delete_validation_rule(
+    pool: State<'_, DbPool>,
+    id: i64,
+    company_id: i64,
+) -> Result<(), AppError> {
+    let conn = db::get_conn(&pool)?;
+    let deleted = conn.execute(
+        "DELETE FROM validation_rules WHERE id = ?1 AND company_id = ?2",
+        params![id, company_id],
+    )?;
+    if deleted == 0 {
+        return Err(AppError::not_found("Rule not found"));
+    }
+    Ok(())
+}
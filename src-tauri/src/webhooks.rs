@@ -0,0 +1,606 @@
+use std::time::Duration;
+
+use argon2::password_hash::rand_core::{OsRng, RngCore};
+use chrono::{Local, NaiveDateTime};
+use hmac::{Hmac, Mac};
+use rusqlite::{params, Connection, OptionalExtension, Row};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use tauri::{AppHandle, Manager, State};
+
+use crate::db::{self, DbPool};
+use crate::error::AppError;
+
+const TIMESTAMP_FORMAT: &str = "%Y-%m-%d %H:%M:%S";
+const CHECK_INTERVAL: Duration = Duration::from_secs(15);
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(15);
+// Wait before each retry; a delivery is marked failed once these are used up
+const RETRY_DELAYS: &[i64] = &[60, 5 * 60, 30 * 60, 2 * 60 * 60, 12 * 60 * 60];
+const SECRET_BYTES: usize = 24;
+const MIN_SECRET_LENGTH: usize = 16;
+const DEFAULT_DELIVERY_LIMIT: usize = 100;
+const MAX_DELIVERY_LIMIT: usize = 1000;
+// Response bodies are kept only as far as needed to diagnose a failure
+const MAX_ERROR_LENGTH: usize = 500;
+
+type HmacSha256 = Hmac<Sha256>;
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+pub enum WebhookEvent {
+    #[serde(rename = "invoice.created")]
+    Created,
+    #[serde(rename = "invoice.paid")]
+    Paid,
+    #[serde(rename = "invoice.cancelled")]
+    Cancelled,
+}
+
+impl WebhookEvent {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            WebhookEvent::Created => "invoice.created",
+            WebhookEvent::Paid => "invoice.paid",
+            WebhookEvent::Cancelled => "invoice.cancelled",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "invoice.created" => Some(WebhookEvent::Created),
+            "invoice.paid" => Some(WebhookEvent::Paid),
+            "invoice.cancelled" => Some(WebhookEvent::Cancelled),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum DeliveryStatus {
+    Pending,
+    Delivered,
+    Failed,
+}
+
+impl DeliveryStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DeliveryStatus::Pending => "pending",
+            DeliveryStatus::Delivered => "delivered",
+            DeliveryStatus::Failed => "failed",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "pending" => Some(DeliveryStatus::Pending),
+            "delivered" => Some(DeliveryStatus::Delivered),
+            "failed" => Some(DeliveryStatus::Failed),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Webhook {
+    pub id: i64,
+    pub company_id: i64,
+    pub name: String,
+    pub url: String,
+    // Key for the X-Webhook-Signature header; the receiver needs it to verify payloads
+    pub secret: String,
+    pub events: Vec<WebhookEvent>,
+    pub enabled: bool,
+    pub created_at: Option<String>,
+    pub updated_at: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct WebhookInput {
+    pub name: String,
+    pub url: String,
+    // Generated when left out on creation; kept when left out on update
+    pub secret: Option<String>,
+    pub events: Vec<WebhookEvent>,
+    pub enabled: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct WebhookDelivery {
+    pub id: i64,
+    pub webhook_id: i64,
+    pub webhook_name: String,
+    pub event: WebhookEvent,
+    pub payload: String,
+    pub status: DeliveryStatus,
+    pub attempts: u32,
+    pub next_attempt_at: Option<String>,
+    pub last_attempt_at: Option<String>,
+    pub response_status: Option<u16>,
+    pub error: Option<String>,
+    pub delivered_at: Option<String>,
+    pub created_at: Option<String>,
+}
+
+// A delivery due to be sent, with what is needed to send it
+struct DueDelivery {
+    id: i64,
+    event: String,
+    payload: String,
+    attempts: u32,
+    url: String,
+    secret: String,
+}
+
+const SELECT_WEBHOOK: &str = "
+    SELECT id, company_id, name, url, secret, events, enabled, created_at, updated_at
+    FROM webhooks";
+
+const SELECT_DELIVERY: &str = "
+    SELECT d.id, d.webhook_id, w.name AS webhook_name, d.event, d.payload, d.status, d.attempts,
+           d.next_attempt_at, d.last_attempt_at, d.response_status, d.error, d.delivered_at,
+           d.created_at
+    FROM webhook_deliveries d
+    JOIN webhooks w ON w.id = d.webhook_id";
+
+fn webhook_from_row(row: &Row) -> rusqlite::Result<Webhook> {
+    let events: String = row.get("events")?;
+    Ok(Webhook {
+        id: row.get("id")?,
+        company_id: row.get("company_id")?,
+        name: row.get("name")?,
+        url: row.get("url")?,
+        secret: row.get("secret")?,
+        events: serde_json::from_str::<Vec<String>>(&events)
+            .unwrap_or_default()
+            .iter()
+            .filter_map(|event| WebhookEvent::parse(event))
+            .collect(),
+        enabled: row.get("enabled")?,
+        created_at: row.get("created_at")?,
+        updated_at: row.get("updated_at")?,
+    })
+}
+
+fn delivery_from_row(row: &Row) -> rusqlite::Result<WebhookDelivery> {
+    let event: String = row.get("event")?;
+    let status: String = row.get("status")?;
+    Ok(WebhookDelivery {
+        id: row.get("id")?,
+        webhook_id: row.get("webhook_id")?,
+        webhook_name: row.get("webhook_name")?,
+        event: WebhookEvent::parse(&event).unwrap_or(WebhookEvent::Created),
+        payload: row.get("payload")?,
+        status: DeliveryStatus::parse(&status).unwrap_or(DeliveryStatus::Pending),
+        attempts: row.get("attempts")?,
+        next_attempt_at: row.get("next_attempt_at")?,
+        last_attempt_at: row.get("last_attempt_at")?,
+        response_status: row.get("response_status")?,
+        error: row.get("error")?,
+        delivered_at: row.get("delivered_at")?,
+        created_at: row.get("created_at")?,
+    })
+}
+
+fn map_write_error(e: rusqlite::Error) -> AppError {
+    let message = e.to_string();
+    if message.contains("UNIQUE constraint failed: webhooks") {
+        return AppError::conflict("name", "A webhook with this name already exists");
+    }
+    AppError::from(e)
+}
+
+fn now() -> NaiveDateTime {
+    Local::now().naive_local()
+}
+
+fn events_json(events: &[WebhookEvent]) -> String {
+    serde_json::to_string(&events.iter().map(WebhookEvent::as_str).collect::<Vec<_>>())
+        .unwrap_or_else(|_| "[]".to_string())
+}
+
+fn generate_secret() -> String {
+    let mut bytes = [0u8; SECRET_BYTES];
+    OsRng.fill_bytes(&mut bytes);
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+pub fn get_webhook_by_id(
+    conn: &Connection,
+    id: i64,
+    company_id: i64,
+) -> Result<Option<Webhook>, String> {
+    conn.query_row(
+        &format!("{} WHERE id = ?1 AND company_id = ?2", SELECT_WEBHOOK),
+        params![id, company_id],
+        webhook_from_row,
+    )
+    .optional()
+    .map_err(|e| e.to_string())
+}
+
+fn validate(webhook: &mut WebhookInput) -> Result<(), AppError> {
+    webhook.name = webhook.name.trim().to_string();
+    if webhook.name.is_empty() || webhook.name.len() > 100 {
+        return Err(AppError::validation("name", "Name must be 1 to 100 characters"));
+    }
+    webhook.url = webhook.url.trim().to_string();
+    let url = reqwest::Url::parse(&webhook.url).map_err(|_| {
+        AppError::validation("url", "Enter a full URL, e.g. https://example.com/hook")
+    })?;
+    if !matches!(url.scheme(), "http" | "https") || url.host_str().is_none() {
+        return Err(AppError::validation("url", "Webhook URLs must use http or https"));
+    }
+    if webhook.events.is_empty() {
+        return Err(AppError::validation("events", "Choose at least one event"));
+    }
+    webhook.events.sort_by_key(WebhookEvent::as_str);
+    webhook.events.dedup();
+    if let Some(secret) = &webhook.secret {
+        if secret.trim().len() < MIN_SECRET_LENGTH {
+            return Err(AppError::validation(
+                "secret",
+                format!("Secret must be at least {} characters", MIN_SECRET_LENGTH),
+            ));
+        }
+    }
+    Ok(())
+}
+
+// Queues `event` for every enabled webhook of the company that subscribes to it. Called inside
+// the transaction that makes the change, so nothing is sent for changes that roll back.
+pub fn enqueue<T: Serialize>(
+    conn: &Connection,
+    company_id: i64,
+    event: WebhookEvent,
+    data: &T,
+) -> Result<(), String> {
+    let mut stmt = conn
+        .prepare(&format!("{} WHERE company_id = ?1 AND enabled = 1", SELECT_WEBHOOK))
+        .map_err(|e| e.to_string())?;
+    let webhooks = stmt
+        .query_map(params![company_id], webhook_from_row)
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+    let subscribed: Vec<&Webhook> = webhooks.iter().filter(|w| w.events.contains(&event)).collect();
+    if subscribed.is_empty() {
+        return Ok(());
+    }
+
+    let occurred_at = now().format(TIMESTAMP_FORMAT).to_string();
+    let payload = serde_json::to_string(&serde_json::json!({
+        "event": event.as_str(),
+        "company_id": company_id,
+        "occurred_at": occurred_at,
+        "data": data,
+    }))
+    .map_err(|e| e.to_string())?;
+    for webhook in subscribed {
+        conn.execute(
+            "INSERT INTO webhook_deliveries (webhook_id, event, payload, status, next_attempt_at)
+             VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![
+                webhook.id,
+                event.as_str(),
+                payload,
+                DeliveryStatus::Pending.as_str(),
+                occurred_at
+            ],
+        )
+        .map_err(|e| e.to_string())?;
+    }
+    Ok(())
+}
+
+// Hex HMAC-SHA256 of "<timestamp>.<body>"; binding the timestamp lets receivers reject replays
+fn signature(secret: &str, timestamp: i64, body: &str) -> Result<String, String> {
+    let mut mac = HmacSha256::new_from_slice(secret.as_bytes()).map_err(|e| e.to_string())?;
+    mac.update(timestamp.to_string().as_bytes());
+    mac.update(b".");
+    mac.update(body.as_bytes());
+    Ok(format!("{:x}", mac.finalize().into_bytes()))
+}
+
+fn due_deliveries(conn: &Connection, now: NaiveDateTime) -> Result<Vec<DueDelivery>, String> {
+    let mut stmt = conn
+        .prepare(
+            "SELECT d.id, d.event, d.payload, d.attempts, w.url, w.secret
+             FROM webhook_deliveries d
+             JOIN webhooks w ON w.id = d.webhook_id
+             WHERE d.status = 'pending' AND d.next_attempt_at <= ?1 AND w.enabled = 1
+             ORDER BY d.next_attempt_at, d.id",
+        )
+        .map_err(|e| e.to_string())?;
+    let due = stmt
+        .query_map(params![now.format(TIMESTAMP_FORMAT).to_string()], |row| {
+            Ok(DueDelivery {
+                id: row.get("id")?,
+                event: row.get("event")?,
+                payload: row.get("payload")?,
+                attempts: row.get("attempts")?,
+                url: row.get("url")?,
+                secret: row.get("secret")?,
+            })
+        })
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+    Ok(due)
+}
+
+// Posts the payload; a 2xx response counts as delivered
+async fn send(
+    client: &reqwest::Client,
+    delivery: &DueDelivery,
+) -> (Option<u16>, Result<(), String>) {
+    let timestamp = Local::now().timestamp();
+    let signature = match signature(&delivery.secret, timestamp, &delivery.payload) {
+        Ok(signature) => signature,
+        Err(e) => return (None, Err(e)),
+    };
+    let response = client
+        .post(&delivery.url)
+        .header(reqwest::header::CONTENT_TYPE, "application/json")
+        .header("X-Webhook-Event", &delivery.event)
+        .header("X-Webhook-Delivery", delivery.id.to_string())
+        .header("X-Webhook-Timestamp", timestamp.to_string())
+        .header("X-Webhook-Signature", format!("sha256={}", signature))
+        .body(delivery.payload.clone())
+        .send()
+        .await;
+    match response {
+        Ok(response) => {
+            let status = response.status();
+            if status.is_success() {
+                return (Some(status.as_u16()), Ok(()));
+            }
+            let body = response.text().await.unwrap_or_default();
+            let body: String = body.chars().take(MAX_ERROR_LENGTH).collect();
+            (Some(status.as_u16()), Err(format!("HTTP {}: {}", status, body.trim())))
+        }
+        Err(e) => (None, Err(format!("Request failed: {}", e))),
+    }
+}
+
+fn record_attempt(
+    conn: &Connection,
+    delivery: &DueDelivery,
+    response_status: Option<u16>,
+    result: Result<(), String>,
+) -> Result<(), String> {
+    let attempted_at = now();
+    let attempts = delivery.attempts + 1;
+    let (status, next_attempt_at, error, delivered_at) = match result {
+        Ok(()) => (
+            DeliveryStatus::Delivered,
+            None,
+            None,
+            Some(attempted_at.format(TIMESTAMP_FORMAT).to_string()),
+        ),
+        Err(error) => match RETRY_DELAYS.get(delivery.attempts as usize) {
+            Some(delay) => (
+                DeliveryStatus::Pending,
+                Some(
+                    (attempted_at + chrono::Duration::seconds(*delay))
+                        .format(TIMESTAMP_FORMAT)
+                        .to_string(),
+                ),
+                Some(error),
+                None,
+            ),
+            None => (DeliveryStatus::Failed, None, Some(error), None),
+        },
+    };
+    conn.execute(
+        "UPDATE webhook_deliveries
+         SET status = ?1, attempts = ?2, next_attempt_at = ?3, last_attempt_at = ?4,
+             response_status = ?5, error = ?6, delivered_at = ?7
+         WHERE id = ?8",
+        params![
+            status.as_str(),
+            attempts,
+            next_attempt_at,
+            attempted_at.format(TIMESTAMP_FORMAT).to_string(),
+            response_status,
+            error,
+            delivered_at,
+            delivery.id
+        ],
+    )
+    .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+async fn dispatch_due(pool: &DbPool, client: &reqwest::Client) -> Result<(), String> {
+    // No connection is held while a delivery is in flight
+    let due = {
+        let conn = db::get_conn(pool)?;
+        due_deliveries(&conn, now())?
+    };
+    for delivery in due {
+        let (response_status, result) = send(client, &delivery).await;
+        if let Err(e) = &result {
+            tracing::warn!(delivery = delivery.id, "webhook delivery failed: {}", e);
+        }
+        let conn = db::get_conn(pool)?;
+        record_attempt(&conn, &delivery, response_status, result)?;
+    }
+    Ok(())
+}
+
+// Background thread started at launch that sends queued deliveries and retries failed ones
+pub fn start_dispatcher(app: AppHandle) {
+    std::thread::spawn(move || {
+        let client = match reqwest::Client::builder().timeout(REQUEST_TIMEOUT).build() {
+            Ok(client) => client,
+            Err(e) => {
+                tracing::warn!("webhook dispatcher not started: {}", e);
+                return;
+            }
+        };
+        loop {
+            let pool = app.state::<DbPool>().inner().clone();
+            if let Err(e) = tauri::async_runtime::block_on(dispatch_due(&pool, &client)) {
+                tracing::warn!("webhook dispatch failed: {}", e);
+            }
+            std::thread::sleep(CHECK_INTERVAL);
+        }
+    });
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn list_webhooks(
+    pool: State<'_, DbPool>,
+    company_id: i64,
+) -> Result<Vec<Webhook>, AppError> {
+    let conn = db::get_conn(&pool)?;
+    let mut stmt =
+        conn.prepare(&format!("{} WHERE company_id = ?1 ORDER BY name", SELECT_WEBHOOK))?;
+    let webhooks =
+        stmt.query_map(params![company_id], webhook_from_row)?.collect::<Result<Vec<_>, _>>()?;
+    Ok(webhooks)
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn create_webhook(
+    pool: State<'_, DbPool>,
+    company_id: i64,
+    mut webhook: WebhookInput,
+) -> Result<Webhook, AppError> {
+    validate(&mut webhook)?;
+    let secret = webhook
+        .secret
+        .map(|secret| secret.trim().to_string())
+        .unwrap_or_else(generate_secret);
+    let conn = db::get_conn(&pool)?;
+    conn.execute(
+        "INSERT INTO webhooks (company_id, name, url, secret, events, enabled)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+        params![
+            company_id,
+            webhook.name,
+            webhook.url,
+            secret,
+            events_json(&webhook.events),
+            webhook.enabled
+        ],
+    )
+    .map_err(map_write_error)?;
+    get_webhook_by_id(&conn, conn.last_insert_rowid(), company_id)?
+        .ok_or_else(|| AppError::not_found("Webhook not found after creation"))
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn update_webhook(
+    pool: State<'_, DbPool>,
+    id: i64,
+    company_id: i64,
+    mut webhook: WebhookInput,
+) -> Result<Webhook, AppError> {
+    validate(&mut webhook)?;
+    let conn = db::get_conn(&pool)?;
+    let existing = get_webhook_by_id(&conn, id, company_id)?
+        .ok_or_else(|| AppError::not_found("Webhook not found"))?;
+    let secret = webhook
+        .secret
+        .map(|secret| secret.trim().to_string())
+        .unwrap_or(existing.secret);
+    conn.execute(
+        "UPDATE webhooks
+         SET name = ?1, url = ?2, secret = ?3, events = ?4, enabled = ?5,
+             updated_at = CURRENT_TIMESTAMP
+         WHERE id = ?6 AND company_id = ?7",
+        params![
+            webhook.name,
+            webhook.url,
+            secret,
+            events_json(&webhook.events),
+            webhook.enabled,
+            id,
+            company_id
+        ],
+    )
+    .map_err(map_write_error)?;
+    get_webhook_by_id(&conn, id, company_id)?
+        .ok_or_else(|| AppError::not_found("Webhook not found"))
+}
+
+// Its delivery log goes with it
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn delete_webhook(
+    pool: State<'_, DbPool>,
+    id: i64,
+    company_id: i64,
+) -> Result<(), AppError> {
+    let mut conn = db::get_conn(&pool)?;
+    let tx = conn.transaction()?;
+    if get_webhook_by_id(&tx, id, company_id)?.is_none() {
+        return Err(AppError::not_found("Webhook not found"));
+    }
+    tx.execute("DELETE FROM webhook_deliveries WHERE webhook_id = ?1", params![id])?;
+    tx.execute("DELETE FROM webhooks WHERE id = ?1 AND company_id = ?2", params![id, company_id])?;
+    tx.commit()?;
+    Ok(())
+}
+
+// Newest first, optionally for one webhook or in one status
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn list_webhook_deliveries(
+    pool: State<'_, DbPool>,
+    company_id: i64,
+    webhook_id: Option<i64>,
+    status: Option<DeliveryStatus>,
+    limit: Option<usize>,
+) -> Result<Vec<WebhookDelivery>, AppError> {
+    let limit = limit.unwrap_or(DEFAULT_DELIVERY_LIMIT).clamp(1, MAX_DELIVERY_LIMIT);
+    let conn = db::get_conn(&pool)?;
+    let mut stmt = conn.prepare(&format!(
+        "{} WHERE w.company_id = ?1 AND (?2 IS NULL OR d.webhook_id = ?2)
+               AND (?3 IS NULL OR d.status = ?3)
+             ORDER BY d.id DESC LIMIT ?4",
+        SELECT_DELIVERY
+    ))?;
+    let deliveries = stmt
+        .query_map(
+            params![company_id, webhook_id, status.map(|s| s.as_str()), limit as i64],
+            delivery_from_row,
+        )?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(deliveries)
+}
+
+// Queues a failed delivery to be sent again on the next check, with a fresh set of retries
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn retry_webhook_delivery(
+    pool: State<'_, DbPool>,
+    id: i64,
+    company_id: i64,
+) -> Result<WebhookDelivery, AppError> {
+    let conn = db::get_conn(&pool)?;
+    let load = |conn: &Connection| {
+        conn.query_row(
+            &format!("{} WHERE d.id = ?1 AND w.company_id = ?2", SELECT_DELIVERY),
+            params![id, company_id],
+            delivery_from_row,
+        )
+        .optional()
+        .map_err(AppError::from)
+    };
+    let delivery = load(&conn)?.ok_or_else(|| AppError::not_found("Delivery not found"))?;
+    if delivery.status != DeliveryStatus::Failed {
+        return Err(AppError::validation("id", "Only failed deliveries can be retried"));
+    }
+    conn.execute(
+        "UPDATE webhook_deliveries SET status = ?1, attempts = 0, next_attempt_at = ?2
+         WHERE id = ?3",
+        params![DeliveryStatus::Pending.as_str(), now().format(TIMESTAMP_FORMAT).to_string(), id],
+    )?;
+    load(&conn)?.ok_or_else(|| AppError::not_found("Delivery not found"))
+}
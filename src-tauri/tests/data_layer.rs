@@ -0,0 +1,330 @@
+#![cfg(feature = "test-harness")]
+
+use serde_json::json;
+use tauri_app_lib::test_harness::{
+    block_on, categories, companies, customers, invoices, sync, transactions, TestHarness,
+};
+
+fn company(harness: &TestHarness) -> (i64, i64) {
+    let company = block_on(companies::create_company(
+        harness.pool(),
+        companies::CreateCompany {
+            company_name: "Harness Traders".to_string(),
+            gst_no: "27AAPFU0939F1ZV".to_string(),
+            state_code: String::new(),
+            address: None,
+            city: None,
+            pincode: None,
+        },
+    ))
+    .expect("company is created");
+    let company_id = company.id.expect("company has an id");
+    let conn = harness.conn().expect("connection");
+    let category = categories::get_category_by_name(&conn, "Regular", company_id)
+        .expect("categories load")
+        .and_then(|category| category.id)
+        .expect("initial categories are seeded");
+    (company_id, category)
+}
+
+fn customer_count(harness: &TestHarness, company_id: i64) -> i64 {
+    let conn = harness.conn().expect("connection");
+    conn.query_row(
+        "SELECT COUNT(*) FROM customers WHERE company_id = ?1",
+        [company_id],
+        |row| row.get(0),
+    )
+    .expect("customers count")
+}
+
+fn customer_step(company_id: i64, category_id: i64) -> serde_json::Value {
+    json!({
+        "kind": "create_customer",
+        "customer": {
+            "report_customer": "Patel Agencies",
+            "tally_customer": "Patel Agencies - Pune",
+            "gst_no": null,
+            "state_code": "27",
+            "category_id": category_id,
+            "company_id": company_id
+        }
+    })
+}
+
+#[test]
+fn failed_step_rolls_back_the_whole_transaction() {
+    let harness = TestHarness::new().expect("harness starts");
+    let (company_id, category_id) = company(&harness);
+
+    let steps = vec![
+        customer_step(company_id, category_id),
+        json!({
+            "kind": "stock_adjustment",
+            "adjustment": {
+                "item_id": 999,
+                "adjustment_date": "2024-04-01",
+                "quantity": 5.0,
+                "reason": "Opening stock"
+            }
+        }),
+    ];
+    let result = block_on(transactions::run_transaction(
+        harness.pool(),
+        company_id,
+        steps,
+    ));
+    assert!(result.is_err());
+    assert_eq!(customer_count(&harness, company_id), 0);
+
+    let steps = vec![customer_step(company_id, category_id)];
+    let result = block_on(transactions::run_transaction(
+        harness.pool(),
+        company_id,
+        steps,
+    ))
+    .expect("transaction commits");
+    assert!(result.steps[0].id.is_some());
+    assert_eq!(customer_count(&harness, company_id), 1);
+}
+
+#[test]
+fn customer_round_trips_through_the_commands() {
+    let harness = TestHarness::new().expect("harness starts");
+    let (company_id, category_id) = company(&harness);
+
+    let customer =
+        serde_json::from_value(customer_step(company_id, category_id)["customer"].clone())
+            .expect("customer parses");
+    let created = block_on(customers::create_customer(harness.pool(), customer, None))
+        .expect("customer is created");
+    let id = created.id.expect("customer has an id");
+
+    let loaded = block_on(customers::get_customer(harness.pool(), id, company_id))
+        .expect("customer loads")
+        .expect("customer exists");
+    assert_eq!(loaded.report_customer, "Patel Agencies");
+    assert_eq!(loaded.tally_customer, "Patel Agencies - Pune");
+
+    let changes = serde_json::from_value(json!({
+        "report_customer": null,
+        "tally_customer": "Patel Agencies - Nashik",
+        "gst_no": null,
+        "registration_type": null,
+        "state_code": null,
+        "category_id": null,
+        "address": null,
+        "city": "Nashik",
+        "pincode": null
+    }))
+    .expect("changes parse");
+    let updated = block_on(customers::update_customer(
+        harness.pool(),
+        id,
+        company_id,
+        changes,
+    ))
+    .expect("customer is updated");
+    assert_eq!(updated.report_customer, "Patel Agencies");
+    assert_eq!(updated.tally_customer, "Patel Agencies - Nashik");
+    assert_eq!(updated.city.as_deref(), Some("Nashik"));
+
+    block_on(customers::delete_customer(harness.pool(), id, company_id))
+        .expect("customer is deleted");
+    let loaded =
+        block_on(customers::get_customer(harness.pool(), id, company_id)).expect("customer loads");
+    assert!(loaded.is_none());
+}
+
+#[test]
+fn viewers_cannot_change_company_setup() {
+    let harness = TestHarness::new().expect("harness starts");
+    let user = |username: &str, role: &str| json!({ "user": { "username": username, "password": "correct horse", "role": role } });
+    let sign_in = |username: &str| {
+        harness
+            .invoke(
+                "login",
+                json!({ "username": username, "password": "correct horse" }),
+            )
+            .expect("user signs in")
+    };
+    let new_company = json!({
+        "company": {
+            "company_name": "Harness Traders",
+            "gst_no": "27AAPFU0939F1ZV",
+            "state_code": ""
+        }
+    });
+
+    harness
+        .invoke("create_user", user("owner", "admin"))
+        .expect("first admin is created");
+    let refused = harness.invoke("create_company", new_company.clone());
+    assert_eq!(refused.unwrap_err()["code"], "unauthenticated");
+
+    sign_in("owner");
+    harness
+        .invoke("create_user", user("clerk", "viewer"))
+        .expect("viewer is created");
+    sign_in("clerk");
+    assert!(harness.invoke("list_companies", json!({})).is_ok());
+    let refused = harness.invoke("create_company", new_company.clone());
+    assert_eq!(refused.unwrap_err()["code"], "forbidden");
+
+    sign_in("owner");
+    assert!(harness.invoke("create_company", new_company).is_ok());
+}
+
+#[test]
+fn plugin_invokes_are_refused() {
+    let harness = TestHarness::new().expect("harness starts");
+    assert!(harness.invoke("list_companies", json!({})).is_ok());
+
+    let result = harness.invoke(
+        "plugin:sql|execute",
+        json!({
+            "db": "sqlite:sales_report.db",
+            "query": "UPDATE users SET role = 'admin'",
+            "values": []
+        }),
+    );
+    assert!(result.is_err());
+}
+
+#[test]
+fn commands_only_reach_the_active_company() {
+    let harness = TestHarness::new().expect("harness starts");
+    let (active_id, _) = company(&harness);
+    let other = harness
+        .invoke(
+            "create_company",
+            json!({
+                "company": {
+                    "company_name": "Other Traders",
+                    "gst_no": "29AAPFU0939F1ZR",
+                    "state_code": ""
+                }
+            }),
+        )
+        .expect("second company is created");
+    let other_id = other["id"].as_i64().expect("company has an id");
+
+    let listed = harness.invoke("list_customers", json!({ "companyId": active_id }));
+    assert_eq!(listed.unwrap_err()["code"], "forbidden");
+
+    harness
+        .invoke("switch_active_company", json!({ "companyId": active_id }))
+        .expect("company is activated");
+    assert!(harness
+        .invoke("list_customers", json!({ "companyId": active_id }))
+        .is_ok());
+    let listed = harness.invoke("list_customers", json!({ "companyId": other_id }));
+    assert_eq!(listed.unwrap_err()["code"], "forbidden");
+}
+
+fn insert_customer(harness: &TestHarness, name: &str) {
+    let conn = harness.conn().expect("connection");
+    conn.execute(
+        "INSERT INTO customers (report_customer, tally_customer, gst_no, state_code, category_id,
+                                company_id, normalized_name)
+         SELECT ?1, ?1, '', '27', id, company_id, lower(?1) FROM categories WHERE name = 'Regular'",
+        [name],
+    )
+    .expect("customer is inserted");
+}
+
+fn customer_names(harness: &TestHarness) -> Vec<String> {
+    let conn = harness.conn().expect("connection");
+    let mut stmt = conn
+        .prepare(
+            "SELECT c.tally_customer FROM customers c
+             JOIN categories g ON g.id = c.category_id AND g.company_id = c.company_id
+             ORDER BY c.tally_customer",
+        )
+        .expect("query prepares");
+    let names = stmt
+        .query_map([], |row| row.get(0))
+        .expect("customers load")
+        .collect::<Result<Vec<String>, _>>()
+        .expect("customers load");
+    names
+}
+
+fn send_changes(from: &TestHarness, to: &TestHarness, name: &str) -> sync::SyncImportReport {
+    let path = std::env::temp_dir()
+        .join(format!("{}_{}.json", name, std::process::id()))
+        .to_string_lossy()
+        .into_owned();
+    block_on(sync::export_sync_changes(from.pool(), path.clone(), None)).expect("changes export");
+    let report =
+        block_on(sync::import_sync_changes(to.pool(), path.clone())).expect("changes import");
+    let _ = std::fs::remove_file(&path);
+    report
+}
+
+#[test]
+fn rows_inserted_on_two_devices_both_survive_a_sync() {
+    let first = TestHarness::new().expect("harness starts");
+    let second = TestHarness::new().expect("harness starts");
+    company(&first);
+    send_changes(&first, &second, "sync_setup");
+
+    // Both get local id 1 on their own device
+    insert_customer(&first, "Patel Agencies");
+    insert_customer(&second, "Shah Traders");
+    let report = send_changes(&first, &second, "sync_first_to_second");
+    assert_eq!((report.applied, report.conflicts), (1, 0));
+    let report = send_changes(&second, &first, "sync_second_to_first");
+    assert_eq!((report.applied, report.conflicts), (1, 0));
+
+    let expected = vec!["Patel Agencies".to_string(), "Shah Traders".to_string()];
+    assert_eq!(customer_names(&first), expected);
+    assert_eq!(customer_names(&second), expected);
+}
+
+#[test]
+fn invoice_lines_carry_cess_into_the_total() {
+    let harness = TestHarness::new().expect("harness starts");
+    let (company_id, _) = company(&harness);
+    insert_customer(&harness, "Patel Agencies");
+    let customer_id: i64 = harness
+        .conn()
+        .expect("connection")
+        .query_row("SELECT id FROM customers", [], |row| row.get(0))
+        .expect("customer exists");
+
+    let invoice: invoices::CreateInvoice = serde_json::from_value(json!({
+        "company_id": company_id,
+        "invoice_date": "2024-04-01",
+        "customer_id": customer_id,
+        "taxable_value": 1000.0,
+        "cgst_amount": 140.0,
+        "sgst_amount": 140.0,
+        "igst_amount": 0.0,
+        "cess_amount": 120.0,
+        "total_amount": 1400.0,
+        "status": null,
+        "notes": null,
+        "lines": [{
+            "description": "Aerated water",
+            "hsn_code": "2202",
+            "quantity": 1.0,
+            "rate": 1000.0,
+            "taxable_value": 1000.0,
+            "gst_rate": 28.0,
+            "cgst_amount": 140.0,
+            "sgst_amount": 140.0,
+            "igst_amount": 0.0,
+            "cess_rate": 12.0
+        }]
+    }))
+    .expect("invoice parses");
+    let saved =
+        block_on(invoices::create_invoice(harness.pool(), invoice)).expect("invoice is created");
+    assert_eq!(saved.invoice.cess_amount, 120.0);
+    assert_eq!(saved.invoice.total_amount, 1400.0);
+
+    let conn = harness.conn().expect("connection");
+    let id = saved.invoice.id.expect("invoice has an id");
+    let lines = invoices::get_invoice_lines(&conn, id).expect("lines load");
+    assert_eq!((lines[0].cess_rate, lines[0].cess_amount), (12.0, 120.0));
+}
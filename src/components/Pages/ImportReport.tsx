@@ -140,6 +140,7 @@ export default function ImportReport() {
       // Create customer mappings in database
       await dbService.createImportCustomerMappings(
         sessionId,
+        selectedCompany.id!,
         analyzedCustomers.map(rc => ({
           reportCustomerId: rc.reportCustomerId,
           reportCustomerName: rc.name,
@@ -244,7 +245,7 @@ export default function ImportReport() {
       }
 
       // Update session status
-      await dbService.updateImportSessionStatus(importSessionId, 'importing')
+      await dbService.updateImportSessionStatus(importSessionId, selectedCompany.id!, 'importing')
 
       // Use the existing import functionality
       const importResult = await dbService.importReportData(
@@ -254,13 +255,13 @@ export default function ImportReport() {
       )
 
       if (importResult.success) {
-        await dbService.updateImportSessionStatus(importSessionId, 'completed')
+        await dbService.updateImportSessionStatus(importSessionId, selectedCompany.id!, 'completed')
         setResult({
           success: true,
           message: `Successfully imported ${importResult.importedRows} rows for ${selectedCompany.company_name}`,
         })
       } else {
-        await dbService.updateImportSessionStatus(importSessionId, 'failed')
+        await dbService.updateImportSessionStatus(importSessionId, selectedCompany.id!, 'failed')
         setResult({
           success: false,
           message: `Import completed with errors: ${importResult.errors.join(', ')}`,
@@ -268,7 +269,7 @@ export default function ImportReport() {
       }
     } catch (error) {
       if (importSessionId) {
-        await dbService.updateImportSessionStatus(importSessionId, 'failed')
+        await dbService.updateImportSessionStatus(importSessionId, selectedCompany.id!, 'failed')
       }
       setResult({
         success: false,
@@ -1,11 +1,18 @@
 import { createContext, useContext, useEffect, useState } from 'react';
+import { invoke } from '@tauri-apps/api/core';
+import { listen } from '@tauri-apps/api/event';
 
 type Theme = 'dark' | 'light' | 'system';
 
+// Returned by get_display_settings: the signed-in user's choice, or the app-wide one
+type DisplaySettings = {
+  date_format: string;
+  theme: Theme;
+};
+
 type ThemeProviderProps = {
   children: React.ReactNode;
   defaultTheme?: Theme;
-  storageKey?: string;
 };
 
 type ThemeProviderState = {
@@ -23,16 +30,31 @@ const ThemeProviderContext = createContext<ThemeProviderState>(initialState);
 export function ThemeProvider({
   children,
   defaultTheme = 'system',
-  storageKey = 'vite-ui-theme',
   ...props
 }: ThemeProviderProps) {
-  const [theme, setTheme] = useState<Theme>(() => {
-    try {
-      return (localStorage.getItem(storageKey) as Theme) || defaultTheme;
-    } catch {
-      return defaultTheme;
-    }
-  });
+  const [theme, setTheme] = useState<Theme>(defaultTheme);
+  const [display, setDisplay] = useState<DisplaySettings | null>(null);
+
+  useEffect(() => {
+    const load = () =>
+      invoke<DisplaySettings>('get_display_settings')
+        .then(settings => {
+          setDisplay(settings);
+          setTheme(settings.theme);
+        })
+        .catch(() => {
+          console.warn('Failed to load the saved theme');
+        });
+
+    load();
+    // Each user has their own theme, so it changes when someone signs in or out
+    const unlisten = listen('session://changed', () => {
+      load();
+    });
+    return () => {
+      unlisten.then(stop => stop());
+    };
+  }, []);
 
   useEffect(() => {
     const root = window.document.documentElement;
@@ -55,12 +77,15 @@ export function ThemeProvider({
   const value = {
     theme,
     setTheme: (newTheme: Theme) => {
-      try {
-        localStorage.setItem(storageKey, newTheme);
-      } catch {
-        console.warn('Failed to save theme to localStorage');
-      }
       setTheme(newTheme);
+      if (!display) return;
+      const updated = { ...display, theme: newTheme };
+      setDisplay(updated);
+      invoke<DisplaySettings>('update_display_settings', {
+        display: updated,
+      }).catch(() => {
+        console.warn('Failed to save theme');
+      });
     },
   };
 